@@ -0,0 +1,94 @@
+//! Pipeline runs over a state full of trades with the VWAP and SMA reading their whole windows against keeping
+//! running sums and reading only what came since the previous run. Run with `cargo bench --bench features`.
+
+use std::{hint::black_box, sync::Arc};
+
+use arkin::{
+    config::{
+        FeatureConfig, PeriodInputConfig, PipelineConfig, SMAFeatureConfig, VWAPFeatureConfig, WindowInputConfig,
+    },
+    features::FeatureEvent,
+    models::{Instrument, Venue},
+    pipeline::Pipeline,
+    state::StateManager,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use time::OffsetDateTime;
+
+const TRADES_PER_SECOND: i64 = 100;
+const SECONDS: i64 = 600;
+const RUNS: i64 = 60;
+
+fn pipeline_config(incremental: bool) -> PipelineConfig {
+    let window = |feature_id: &str| WindowInputConfig {
+        from: "base".into(),
+        feature_id: feature_id.into(),
+        window: 300,
+    };
+    PipelineConfig {
+        name: "bench".into(),
+        frequency: 1,
+        bar_close: None,
+        threads: None,
+        warm_up: Default::default(),
+        features: vec![
+            FeatureConfig::VWAP(VWAPFeatureConfig {
+                id: "vwap".into(),
+                input_price: window("trade_price"),
+                input_quantity: window("trade_quantity"),
+                output: "vwap".into(),
+                incremental,
+            }),
+            FeatureConfig::SMA(SMAFeatureConfig {
+                id: "sma".into(),
+                input: PeriodInputConfig {
+                    from: "base".into(),
+                    feature_id: "trade_price".into(),
+                    periods: 10_000,
+                },
+                output: "sma".into(),
+                incremental,
+            }),
+        ],
+    }
+}
+
+fn filled_state(instrument: &Instrument, start: OffsetDateTime) -> Arc<StateManager> {
+    let state = Arc::new(StateManager::default());
+    for i in 0..TRADES_PER_SECOND * (SECONDS + RUNS) {
+        let time = start + time::Duration::milliseconds(i * 1000 / TRADES_PER_SECOND);
+        let price = 100. + (i % 17) as f64;
+        state.add_feature(FeatureEvent::new("trade_price".into(), instrument.clone(), time, price));
+        state.add_feature(FeatureEvent::new("trade_quantity".into(), instrument.clone(), time, 1.));
+    }
+    state
+}
+
+/// Runs the pipeline once a second over the last minute of trades
+fn run(pipeline: Pipeline, instrument: &Instrument, start: OffsetDateTime) {
+    for s in SECONDS..SECONDS + RUNS {
+        black_box(pipeline.calculate(instrument.clone(), start + time::Duration::seconds(s)));
+    }
+}
+
+fn features(c: &mut Criterion) {
+    let start = OffsetDateTime::now_utc();
+    let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+    let state = filled_state(&instrument, start);
+
+    let mut group = c.benchmark_group("features");
+    group.sample_size(10);
+    for (name, incremental) in [("windowed", false), ("incremental", true)] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || Pipeline::from_config(state.clone(), &pipeline_config(incremental)).unwrap(),
+                |pipeline| run(pipeline, &instrument, start),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, features);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+//! Cost of passing instruments around on the hot path, the owned spec is what every event carried before
+//! instruments were interned. Run with `cargo bench --bench instrument`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use arkin::{
+    models::{Event, Instrument, InstrumentSpec, Tick, Venue},
+    state::StateManager,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use time::OffsetDateTime;
+
+/// Counts allocations so the pressure can be printed next to the timings
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const EVENTS: usize = 10_000;
+
+fn allocations_per_event(name: &str, f: impl Fn()) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..EVENTS {
+        f();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{}: {:.2} allocations per event", name, allocations as f64 / EVENTS as f64);
+}
+
+fn instrument(c: &mut Criterion) {
+    let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+    let spec = (*instrument).clone();
+
+    allocations_per_event("clone spec", || drop(black_box(spec.clone())));
+    allocations_per_event("clone instrument", || drop(black_box(instrument.clone())));
+
+    c.bench_function("clone spec", |b| b.iter(|| black_box(spec.clone())));
+    c.bench_function("clone instrument", |b| b.iter(|| black_box(instrument.clone())));
+
+    let specs: HashMap<InstrumentSpec, usize> = [(spec.clone(), 1)].into();
+    let instruments: HashMap<Instrument, usize> = [(instrument.clone(), 1)].into();
+    c.bench_function("lookup spec", |b| b.iter(|| black_box(specs.get(&spec))));
+    c.bench_function("lookup instrument", |b| b.iter(|| black_box(instruments.get(&instrument))));
+
+    let start = OffsetDateTime::now_utc();
+    c.bench_function("insert ticks", |b| {
+        b.iter_batched(
+            StateManager::default,
+            |state| {
+                for i in 0..1000 {
+                    state.add_event(Event::Tick(Tick::new(
+                        start + time::Duration::milliseconds(i),
+                        instrument.clone(),
+                        i as u64,
+                        100.0.into(),
+                        1.0.into(),
+                        101.0.into(),
+                        1.0.into(),
+                    )));
+                }
+                state
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, instrument);
+criterion_main!(benches);
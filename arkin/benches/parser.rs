@@ -0,0 +1,99 @@
+//! Cost of parsing Binance frames on the hot path. Run with `cargo bench --bench parser` and again with
+//! `--features simd-json` to compare serde_json with simd-json.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use arkin::{
+    config::{BinanceMarket, InstrumentConfig},
+    ingestors::BinanceParser,
+    models::{InstrumentRegistry, InstrumentType, Venue},
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rust_decimal::Decimal;
+
+/// Counts allocations so the pressure can be printed next to the timings
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const FRAMES: usize = 10_000;
+
+const AGG_TRADE: &str = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1698796800043,"a":3863267,"s":"BTCUSDT","p":"34512.10","q":"0.012","f":15146241,"l":15146244,"T":1698796799890,"m":false}}"#;
+const BOOK_TICKER: &str = r#"{"stream":"btcusdt@bookTicker","data":{"e":"bookTicker","u":2487455691211,"s":"BTCUSDT","b":"21840.40","B":"21.292","a":"21840.50","A":"11.169","T":1676026461537,"E":1676026461542}}"#;
+const RAW_BOOK_TICKER: &str = r#"{"e":"bookTicker","u":2487455691211,"s":"BTCUSDT","b":"21840.40","B":"21.292","a":"21840.50","A":"11.169","T":1676026461537,"E":1676026461542}"#;
+
+fn parser() -> BinanceParser {
+    let config = InstrumentConfig {
+        venue: Venue::Binance,
+        symbol: "BTCUSDT".into(),
+        instrument_type: InstrumentType::Perpetual,
+        base: "btc".into(),
+        quote: "usdt".into(),
+        maturity: None,
+        strike: None,
+        option_type: None,
+        tick_size: Decimal::new(1, 1),
+        lot_size: Decimal::new(1, 3),
+        min_notional: Decimal::ZERO,
+        contract_multiplier: Decimal::ONE,
+        settlement: "usdt".into(),
+    };
+    BinanceParser::new(Arc::new(InstrumentRegistry::from_config(&[config])))
+}
+
+/// Frames arrive as owned strings from the websocket, so copying them is left out of the count
+fn allocations_per_frame(parser: &BinanceParser, name: &str, frame: &str) {
+    let frames = vec![frame.to_owned(); FRAMES];
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for frame in frames {
+        drop(black_box(parser.parse(BinanceMarket::Swaps, frame).unwrap()));
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{}: {:.2} allocations per frame", name, allocations as f64 / FRAMES as f64);
+}
+
+fn parse(c: &mut Criterion) {
+    let parser = parser();
+    let frames = [
+        ("parse agg trade", AGG_TRADE),
+        ("parse book ticker", BOOK_TICKER),
+        ("parse raw book ticker", RAW_BOOK_TICKER),
+    ];
+
+    for (name, frame) in frames {
+        allocations_per_frame(&parser, name, frame);
+    }
+    for (name, frame) in frames {
+        c.bench_function(name, |b| {
+            b.iter_batched(
+                || frame.to_owned(),
+                |frame| parser.parse(BinanceMarket::Swaps, frame).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
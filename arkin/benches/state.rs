@@ -0,0 +1,77 @@
+//! Contention of concurrent writers on the state. Every instrument and event type is its own series behind the
+//! shard lock of its key, so writers of different instruments should scale with the threads while writers of one
+//! instrument take turns. Run with `cargo bench --bench state`.
+
+use std::{hint::black_box, sync::Arc, thread};
+
+use arkin::{
+    models::{Event, Instrument, Tick, Venue},
+    state::StateManager,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use time::OffsetDateTime;
+
+const THREADS: usize = 4;
+const TICKS: usize = 10_000;
+
+fn write(state: &StateManager, instrument: &Instrument, start: OffsetDateTime) {
+    for i in 0..TICKS {
+        state.add_event(Event::Tick(Tick::new(
+            start + time::Duration::milliseconds(i as i64),
+            instrument.clone(),
+            i as u64,
+            100.0.into(),
+            1.0.into(),
+            101.0.into(),
+            1.0.into(),
+        )));
+    }
+}
+
+fn concurrent_writes(state: Arc<StateManager>, instruments: &[Instrument], start: OffsetDateTime) {
+    thread::scope(|s| {
+        for instrument in instruments {
+            let state = state.clone();
+            s.spawn(move || write(&state, instrument, start));
+        }
+    });
+    black_box(state);
+}
+
+fn state(c: &mut Criterion) {
+    let start = OffsetDateTime::now_utc();
+    let distinct = ["BTC", "ETH", "SOL", "BNB", "XRP", "ADA", "DOGE", "AVAX"]
+        .into_iter()
+        .take(THREADS)
+        .map(|base| Instrument::perpetual(Venue::Binance, base.into(), "USDT".into()))
+        .collect::<Vec<_>>();
+    let same = vec![distinct[0].clone(); THREADS];
+
+    let mut group = c.benchmark_group("state");
+    group.sample_size(10);
+    group.bench_function("one writer", |b| {
+        b.iter_batched(
+            StateManager::default,
+            |state| write(&state, &distinct[0], start),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("writers of distinct instruments", |b| {
+        b.iter_batched(
+            || Arc::new(StateManager::default()),
+            |state| concurrent_writes(state, &distinct, start),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("writers of one instrument", |b| {
+        b.iter_batched(
+            || Arc::new(StateManager::default()),
+            |state| concurrent_writes(state, &same, start),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, state);
+criterion_main!(benches);
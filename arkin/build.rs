@@ -0,0 +1,63 @@
+use tonic_build::manual::{Builder, Method, Service};
+
+// The messages live in src/grpc/proto.rs and mirror proto/control.proto, only the service is generated here
+// so the build doesn't need protoc.
+fn main() {
+    let method = |name: &str, route: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::proto::{}", input))
+            .output_type(format!("crate::grpc::proto::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+            .build()
+    };
+
+    let control = Service::builder()
+        .name("Control")
+        .package("arkin.control")
+        .method(method(
+            "list_strategies",
+            "ListStrategies",
+            "ListStrategiesRequest",
+            "ListStrategiesResponse",
+        ))
+        .method(method("start_strategy", "StartStrategy", "StrategyRequest", "StrategyStatus"))
+        .method(method("stop_strategy", "StopStrategy", "StrategyRequest", "StrategyStatus"))
+        .method(method(
+            "reload_config",
+            "ReloadConfig",
+            "ReloadConfigRequest",
+            "ReloadConfigResponse",
+        ))
+        .method(method("get_positions", "GetPositions", "PositionsRequest", "PositionsResponse"))
+        .method(method(
+            "cancel_all_orders",
+            "CancelAllOrders",
+            "CancelAllOrdersRequest",
+            "CancelAllOrdersResponse",
+        ))
+        .method(method("health", "Health", "HealthRequest", "HealthResponse"))
+        .method(method(
+            "set_log_filter",
+            "SetLogFilter",
+            "LogFilterRequest",
+            "LogFilterResponse",
+        ))
+        .method(method(
+            "set_trading_enabled",
+            "SetTradingEnabled",
+            "TradingFlagRequest",
+            "TradingFlagsResponse",
+        ))
+        .method(method(
+            "list_trading_flags",
+            "ListTradingFlags",
+            "ListTradingFlagsRequest",
+            "TradingFlagsResponse",
+        ))
+        .build();
+
+    Builder::new().compile(&[control]);
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS backtest_equity;
+DROP TABLE IF EXISTS backtest_runs;
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS backtest_runs (
+    run_id BIGSERIAL PRIMARY KEY,
+    created_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    config_hash TEXT NOT NULL,
+    git_revision TEXT, -- Nullable when not run from a checkout
+    config TEXT NOT NULL, -- Full config as json
+    start_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    end_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    capital NUMERIC(21, 9) NOT NULL,
+    fills BIGINT NOT NULL,
+    commission NUMERIC(21, 9) NOT NULL,
+    exposure NUMERIC(21, 9) NOT NULL,
+    gross_pnl NUMERIC(21, 9) NOT NULL,
+    net_pnl NUMERIC(21, 9) NOT NULL,
+    max_drawdown NUMERIC(21, 9) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_backtest_runs_config_hash ON backtest_runs (config_hash);
+
+
+CREATE TABLE IF NOT EXISTS backtest_equity (
+    run_id BIGINT NOT NULL REFERENCES backtest_runs ON DELETE CASCADE,
+    event_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    equity NUMERIC(21, 9) NOT NULL,
+    PRIMARY KEY (run_id, event_time)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE orders DROP COLUMN IF EXISTS filled_time;
+ALTER TABLE orders DROP COLUMN IF EXISTS acked_time;
+ALTER TABLE orders DROP COLUMN IF EXISTS created_time;
+ALTER TABLE orders DROP COLUMN IF EXISTS venue_order_id;
+ALTER TABLE orders DROP COLUMN IF EXISTS client_order_id;
@@ -0,0 +1,7 @@
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS client_order_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS venue_order_id TEXT; -- Nullable until acknowledged by the venue
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS created_time TIMESTAMP(3) WITH TIME ZONE;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS acked_time TIMESTAMP(3) WITH TIME ZONE;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS filled_time TIMESTAMP(3) WITH TIME ZONE;
+UPDATE orders SET created_time = event_time WHERE created_time IS NULL;
+ALTER TABLE orders ALTER COLUMN created_time SET NOT NULL;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS funding_rates (
+    event_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    instrument_id INTEGER NOT NULL REFERENCES instruments,
+    mark_price NUMERIC(21, 9) NOT NULL,
+    index_price NUMERIC(21, 9) NOT NULL,
+    funding_rate NUMERIC(21, 9) NOT NULL,
+    next_funding_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (source, instrument_id, event_time)
+);
+-- Convert the table to a hypertable
+SELECT create_hypertable('funding_rates', 'event_time');
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS candles (
+    open_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    instrument_id INTEGER NOT NULL REFERENCES instruments,
+    interval_ms BIGINT NOT NULL,
+    event_time TIMESTAMP(3) WITH TIME ZONE NOT NULL,
+    open NUMERIC(21, 9) NOT NULL,
+    high NUMERIC(21, 9) NOT NULL,
+    low NUMERIC(21, 9) NOT NULL,
+    close NUMERIC(21, 9) NOT NULL,
+    volume NUMERIC(21, 9) NOT NULL,
+    quote_volume NUMERIC(21, 9) NOT NULL,
+    trades BIGINT NOT NULL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (source, instrument_id, interval_ms, open_time)
+);
+-- Convert the table to a hypertable
+SELECT create_hypertable('candles', 'open_time');
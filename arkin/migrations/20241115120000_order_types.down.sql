@@ -0,0 +1,2 @@
+ALTER TABLE orders DROP COLUMN IF EXISTS time_in_force;
+ALTER TABLE orders DROP COLUMN IF EXISTS trigger_price;
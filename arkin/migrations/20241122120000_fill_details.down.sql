@@ -0,0 +1,3 @@
+ALTER TABLE fills DROP COLUMN IF EXISTS trade_id;
+ALTER TABLE fills DROP COLUMN IF EXISTS liquidity;
+ALTER TABLE fills DROP COLUMN IF EXISTS commission_asset;
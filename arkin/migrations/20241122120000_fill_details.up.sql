@@ -0,0 +1,3 @@
+ALTER TABLE fills ADD COLUMN IF NOT EXISTS commission_asset TEXT;
+ALTER TABLE fills ADD COLUMN IF NOT EXISTS liquidity TEXT NOT NULL DEFAULT 'taker';
+ALTER TABLE fills ADD COLUMN IF NOT EXISTS trade_id BIGINT; -- Simulated fills have no venue trade id
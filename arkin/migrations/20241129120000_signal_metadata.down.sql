@@ -0,0 +1,3 @@
+ALTER TABLE signals DROP COLUMN IF EXISTS expires_at;
+ALTER TABLE signals DROP COLUMN IF EXISTS horizon;
+ALTER TABLE signals DROP COLUMN IF EXISTS confidence;
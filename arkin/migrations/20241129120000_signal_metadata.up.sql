@@ -0,0 +1,3 @@
+ALTER TABLE signals ADD COLUMN IF NOT EXISTS confidence NUMERIC;
+ALTER TABLE signals ADD COLUMN IF NOT EXISTS horizon BIGINT; -- Seconds
+ALTER TABLE signals ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP(3) WITH TIME ZONE;
@@ -0,0 +1,299 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+use rust_decimal::Decimal;
+use strum::Display;
+use tokio::{select, time::interval};
+use tracing::{error, info, warn};
+
+mod notifiers;
+
+pub use notifiers::*;
+
+use crate::{
+    bus::EventBus,
+    config::AlertingConfig,
+    metrics::METRICS,
+    models::{Fill, Instrument, RiskEvent, Tick, Trade},
+    portfolio::PnlTracker,
+    strategies::StrategyId,
+};
+
+const FEED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub severity: Severity,
+    /// Alerts with the same key share their cooldown
+    pub key: String,
+    pub title: String,
+    pub message: String,
+}
+
+impl Alert {
+    fn new(severity: Severity, key: String, title: impl Into<String>, message: String) -> Self {
+        Alert {
+            severity,
+            key,
+            title: title.into(),
+            message,
+        }
+    }
+}
+
+/// Turns events into alerts, keeps the pnl and the feed activity the rules need
+pub struct AlertRules {
+    config: AlertingConfig,
+    pnl: PnlTracker,
+    peaks: HashMap<StrategyId, Decimal>,
+    feeds: HashMap<Instrument, Feed>,
+}
+
+struct Feed {
+    last_seen: Instant,
+    down: bool,
+}
+
+impl AlertRules {
+    pub fn from_config(config: &AlertingConfig) -> Self {
+        AlertRules {
+            config: config.to_owned(),
+            pnl: PnlTracker::default(),
+            peaks: HashMap::new(),
+            feeds: HashMap::new(),
+        }
+    }
+
+    pub fn on_fill(&mut self, fill: &Fill) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let notional = (fill.price * fill.quantity).value().abs();
+        if let Some(limit) = self.config.fill_notional_above.filter(|limit| notional > *limit) {
+            alerts.push(Alert::new(
+                Severity::Info,
+                format!("fill.{}", fill.order_id),
+                "Large fill",
+                format!(
+                    "{} filled {} {} at {}, notional {} above {}",
+                    fill.strategy_id, fill.quantity, fill.instrument, fill.price, notional, limit
+                ),
+            ));
+        }
+
+        self.pnl.update(fill);
+        let realized = self.pnl.realized(&fill.strategy_id);
+        let peak = self.peaks.entry(fill.strategy_id.clone()).or_insert(Decimal::ZERO);
+        *peak = (*peak).max(realized);
+        let drawdown = *peak - realized;
+        if let Some(limit) = self.config.drawdown_above.filter(|limit| drawdown > *limit) {
+            alerts.push(Alert::new(
+                Severity::Critical,
+                format!("drawdown.{}", fill.strategy_id),
+                "Drawdown",
+                format!(
+                    "{} is {} below its peak realized pnl of {}, limit {}",
+                    fill.strategy_id, drawdown, peak, limit
+                ),
+            ));
+        }
+        alerts
+    }
+
+    pub fn on_risk(&self, event: &RiskEvent) -> Option<Alert> {
+        self.config.risk_events.then(|| {
+            Alert::new(
+                Severity::Warning,
+                format!("risk.{}.{}.{}", event.strategy_id, event.instrument, event.reason),
+                "Risk limit",
+                format!("{} on {}: {}", event.strategy_id, event.instrument, event.reason),
+            )
+        })
+    }
+
+    /// Market data arrived, tells when a feed that was down is back
+    pub fn on_market_data(&mut self, instrument: &Instrument, now: Instant) -> Option<Alert> {
+        let feed = self.feeds.entry(instrument.clone()).or_insert(Feed {
+            last_seen: now,
+            down: false,
+        });
+        let outage = now - feed.last_seen;
+        feed.last_seen = now;
+        if !std::mem::take(&mut feed.down) {
+            return None;
+        }
+        Some(Alert::new(
+            Severity::Info,
+            format!("feed.{}.up", instrument),
+            "Feed recovered",
+            format!("Market data for {} is back after {}s", instrument, outage.as_secs()),
+        ))
+    }
+
+    /// Feeds that went quiet since the last check, every outage is reported once
+    pub fn check_feeds(&mut self, now: Instant) -> Vec<Alert> {
+        let Some(limit) = self.config.feed_outage_after.map(Duration::from_secs) else {
+            return Vec::new();
+        };
+        self.feeds
+            .iter_mut()
+            .filter(|(_, feed)| !feed.down && now - feed.last_seen > limit)
+            .map(|(instrument, feed)| {
+                feed.down = true;
+                Alert::new(
+                    Severity::Critical,
+                    format!("feed.{}.down", instrument),
+                    "Feed outage",
+                    format!("No market data for {} in the last {}s", instrument, limit.as_secs()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Mutes repeats of an alert until its cooldown has passed
+pub struct Cooldown {
+    period: Duration,
+    sent: HashMap<String, Instant>,
+}
+
+impl Cooldown {
+    pub fn new(period: Duration) -> Self {
+        Cooldown {
+            period,
+            sent: HashMap::new(),
+        }
+    }
+
+    pub fn ready(&mut self, alert: &Alert, now: Instant) -> bool {
+        match self.sent.get(&alert.key) {
+            Some(sent) if now - *sent < self.period => false,
+            _ => {
+                self.sent.insert(alert.key.clone(), now);
+                true
+            }
+        }
+    }
+}
+
+/// Watches the bus and sends the alerts to every configured notifier
+pub struct AlertManager {
+    bus: Arc<EventBus>,
+    rules: AlertRules,
+    cooldown: Cooldown,
+    notifiers: Arc<Vec<NotifierType>>,
+}
+
+impl AlertManager {
+    pub fn from_config(bus: Arc<EventBus>, config: &AlertingConfig) -> Self {
+        AlertManager {
+            bus,
+            rules: AlertRules::from_config(config),
+            cooldown: Cooldown::new(Duration::from_secs(config.cooldown)),
+            notifiers: Arc::new(NotifierFactory::from_config(&config.notifiers)),
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!("Starting alert manager with {} notifiers...", self.notifiers.len());
+        let mut fills = self.bus.subscribe::<Fill>();
+        let mut risk = self.bus.subscribe::<RiskEvent>();
+        let mut ticks = self.bus.subscribe::<Tick>();
+        let mut trades = self.bus.subscribe::<Trade>();
+        let mut feed_check = interval(FEED_CHECK_INTERVAL);
+        loop {
+            let alerts = select! {
+                Some(fill) = fills.recv() => self.rules.on_fill(&fill),
+                Some(event) = risk.recv() => self.rules.on_risk(&event).into_iter().collect(),
+                Some(tick) = ticks.recv() => self.rules.on_market_data(&tick.instrument, Instant::now()).into_iter().collect(),
+                Some(trade) = trades.recv() => {
+                    self.rules.on_market_data(&trade.instrument, Instant::now()).into_iter().collect()
+                }
+                _ = feed_check.tick() => self.rules.check_feeds(Instant::now()),
+            };
+            for alert in alerts {
+                self.send(alert);
+            }
+        }
+    }
+
+    fn send(&mut self, alert: Alert) {
+        if !self.cooldown.ready(&alert, Instant::now()) {
+            return;
+        }
+        warn!(severity = %alert.severity, key = %alert.key, "Alert {}: {}", alert.title, alert.message);
+        METRICS.alerts.with_label_values(&[&alert.severity.to_string()]).inc();
+
+        // A slow mail server must not hold up the next events
+        let notifiers = self.notifiers.clone();
+        tokio::spawn(async move {
+            let results = join_all(notifiers.iter().map(|n| n.notify(&alert))).await;
+            for (notifier, result) in notifiers.iter().zip(results) {
+                if let Err(e) = result {
+                    error!("Failed to send alert through {}: {}", notifier, e);
+                    METRICS.alert_errors.with_label_values(&[&notifier.to_string()]).inc();
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_alert_rules() {
+        let mut rules = AlertRules::from_config(&config::load().alerting);
+        let instrument = test_utils::test_perp_instrument();
+        let fill = |order_id, price: f64, quantity: f64| {
+            Fill::new(
+                datetime!(2024-01-01 00:00:00).assume_utc(),
+                instrument.clone(),
+                order_id,
+                "test".into(),
+                price.into(),
+                quantity.into(),
+                0.0.into(),
+            )
+        };
+
+        assert!(rules.on_fill(&fill(1, 100., 1.)).is_empty());
+        let alerts = rules.on_fill(&fill(2, 100., 100.));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].title, "Large fill");
+
+        // Closing 101 at 90 loses 1010, more than the 500 drawdown limit
+        let alerts = rules.on_fill(&fill(3, 90., -101.));
+        assert_eq!(
+            alerts.iter().map(|a| a.title.as_str()).collect::<Vec<_>>(),
+            vec!["Large fill", "Drawdown"]
+        );
+
+        let start = Instant::now();
+        assert!(rules.on_market_data(&instrument, start).is_none());
+        assert!(rules.check_feeds(start + Duration::from_secs(30)).is_empty());
+        let outage = start + Duration::from_secs(61);
+        assert_eq!(rules.check_feeds(outage)[0].key, format!("feed.{}.down", instrument));
+        assert!(rules.check_feeds(outage + Duration::from_secs(10)).is_empty());
+        let recovered = rules.on_market_data(&instrument, outage + Duration::from_secs(20)).unwrap();
+        assert_eq!(recovered.severity, Severity::Info);
+
+        let mut cooldown = Cooldown::new(Duration::from_secs(300));
+        assert!(cooldown.ready(&recovered, start));
+        assert!(!cooldown.ready(&recovered, start + Duration::from_secs(299)));
+        assert!(cooldown.ready(&recovered, start + Duration::from_secs(300)));
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+use serde_json::json;
+use tracing::error;
+
+use super::Alert;
+use crate::{
+    config::{EmailNotifierConfig, NotifierConfig, SlackNotifierConfig, TelegramNotifierConfig},
+    credentials::{resolve, CredentialsError, Secret},
+};
+
+#[async_trait]
+pub trait Notifier {
+    async fn notify(&self, alert: &Alert) -> Result<()>;
+}
+
+pub enum NotifierType {
+    Telegram(TelegramNotifier),
+    Slack(SlackNotifier),
+    // The smtp transport is a lot larger than the http clients
+    Email(Box<EmailNotifier>),
+}
+
+#[async_trait]
+impl Notifier for NotifierType {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        match self {
+            NotifierType::Telegram(n) => n.notify(alert).await,
+            NotifierType::Slack(n) => n.notify(alert).await,
+            NotifierType::Email(n) => n.notify(alert).await,
+        }
+    }
+}
+
+impl fmt::Display for NotifierType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifierType::Telegram(_) => write!(f, "telegram"),
+            NotifierType::Slack(_) => write!(f, "slack"),
+            NotifierType::Email(_) => write!(f, "email"),
+        }
+    }
+}
+
+pub struct NotifierFactory {}
+
+impl NotifierFactory {
+    /// Notifiers whose secrets can't be read are left out so the others still work
+    pub fn from_config(config: &[NotifierConfig]) -> Vec<NotifierType> {
+        config
+            .iter()
+            .filter_map(|config| {
+                let notifier = match config {
+                    NotifierConfig::Telegram(c) => TelegramNotifier::from_config(c)
+                        .map(NotifierType::Telegram)
+                        .map_err(anyhow::Error::from),
+                    NotifierConfig::Slack(c) => SlackNotifier::from_config(c)
+                        .map(NotifierType::Slack)
+                        .map_err(anyhow::Error::from),
+                    NotifierConfig::Email(c) => EmailNotifier::from_config(c).map(|n| NotifierType::Email(Box::new(n))),
+                };
+                notifier.inspect_err(|e| error!("Alert notifier disabled: {}", e)).ok()
+            })
+            .collect()
+    }
+}
+
+fn text(alert: &Alert) -> String {
+    format!("[{}] {}\n{}", alert.severity, alert.title, alert.message)
+}
+
+/// Sends alerts as a message from a bot to a chat
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    bot_token: Secret,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn from_config(config: &TelegramNotifierConfig) -> Result<Self, CredentialsError> {
+        Ok(TelegramNotifier {
+            client: reqwest::Client::new(),
+            bot_token: resolve(&config.bot_token)?,
+            chat_id: config.chat_id.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token.expose());
+        self.client
+            .post(url)
+            .json(&json!({ "chat_id": self.chat_id, "text": text(alert) }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            // The url holds the token
+            .map_err(|e| anyhow!(e.without_url()))?;
+        Ok(())
+    }
+}
+
+/// Posts alerts to a slack incoming webhook
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: Secret,
+}
+
+impl SlackNotifier {
+    pub fn from_config(config: &SlackNotifierConfig) -> Result<Self, CredentialsError> {
+        Ok(SlackNotifier {
+            client: reqwest::Client::new(),
+            webhook_url: resolve(&config.webhook_url)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        self.client
+            .post(self.webhook_url.expose())
+            .json(&json!({ "text": text(alert) }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| anyhow!(e.without_url()))?;
+        Ok(())
+    }
+}
+
+/// Mails alerts over smtp with starttls
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn from_config(config: &EmailNotifierConfig) -> Result<Self> {
+        let credentials = Credentials::new(
+            resolve(&config.username)?.expose().to_owned(),
+            resolve(&config.password)?.expose().to_owned(),
+        );
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+            .port(config.smtp_port)
+            .credentials(credentials)
+            .build();
+        Ok(EmailNotifier {
+            transport,
+            from: config.from.parse()?,
+            to: config.to.iter().map(|to| to.parse()).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[{}] {}", alert.severity, alert.title));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        self.transport.send(message.body(alert.message.clone())?).await?;
+        Ok(())
+    }
+}
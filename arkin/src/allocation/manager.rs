@@ -4,6 +4,7 @@ use crate::{
     models::{Allocation, Signal},
 };
 use rayon::prelude::*;
+use time::OffsetDateTime;
 
 pub struct AllocationManager {
     allocations: Vec<Box<dyn AllocationModule>>,
@@ -16,10 +17,18 @@ impl AllocationManager {
         }
     }
 
-    pub fn calculate(&self, signals: &[Signal]) -> Vec<Allocation> {
+    /// Allocations as of the timestamp, signals that expired by then are flat
+    pub fn calculate(&self, signals: &[Signal], timestamp: &OffsetDateTime) -> Vec<Allocation> {
+        let signals = signals
+            .iter()
+            .map(|s| Signal {
+                signal: s.weight_at(timestamp),
+                ..s.clone()
+            })
+            .collect::<Vec<_>>();
         self.allocations
             .par_iter()
-            .map(|a| a.calculate(signals))
+            .map(|a| a.calculate(&signals))
             .flat_map(|a| a)
             .collect::<Vec<_>>()
     }
@@ -0,0 +1,303 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::{
+    constants::TIMESTAMP_FORMAT,
+    health::HealthReport,
+    models::{Fill, Order, Position},
+    server::Server,
+};
+
+/// Read-only view of a running server for dashboards and quick checks with curl
+pub fn router(server: Arc<Server>) -> Router {
+    Router::new()
+        .route("/positions", get(positions))
+        .route("/orders/open", get(open_orders))
+        .route("/fills", get(fills))
+        .route("/features", get(features))
+        .route("/state/stats", get(state_stats))
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+        .with_state(server)
+}
+
+/// Serve the api until the task is dropped
+pub async fn serve(address: &str, server: Arc<Server>) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind api to {}: {}", address, e);
+            return;
+        }
+    };
+    info!("Serving api on http://{}", address);
+    if let Err(e) = axum::serve(listener, router(server)).await {
+        error!("Api failed: {}", e);
+    }
+}
+
+// Decimals and timestamps are sent as strings so they keep their precision
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionResponse {
+    pub strategy_id: String,
+    pub instrument: String,
+    pub start_time: String,
+    pub quantity: String,
+    pub avg_price: String,
+    pub commission: String,
+    pub realized_pnl: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrderResponse {
+    pub order_id: u64,
+    pub client_order_id: String,
+    pub venue_order_id: Option<String>,
+    pub event_time: String,
+    pub strategy_id: String,
+    pub instrument: String,
+    pub order_type: String,
+    pub quantity: String,
+    pub quantity_filled: String,
+    pub avg_fill_price: Option<String>,
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FillResponse {
+    pub order_id: u64,
+    pub event_time: String,
+    pub strategy_id: String,
+    pub instrument: String,
+    pub price: String,
+    pub quantity: String,
+    pub commission: String,
+}
+
+impl From<&Position> for PositionResponse {
+    fn from(position: &Position) -> Self {
+        PositionResponse {
+            strategy_id: position.strategy_id.to_string(),
+            instrument: position.instrument.to_string(),
+            start_time: format_time(&position.start_time),
+            quantity: position.quantity.to_string(),
+            avg_price: position.avg_price.to_string(),
+            commission: position.commission.to_string(),
+            realized_pnl: position.realized_pnl.to_string(),
+        }
+    }
+}
+
+impl From<&Order> for OrderResponse {
+    fn from(order: &Order) -> Self {
+        OrderResponse {
+            order_id: order.order_id,
+            client_order_id: order.client_order_id.clone(),
+            venue_order_id: order.venue_order_id.clone(),
+            event_time: format_time(&order.event_time),
+            strategy_id: order.strategy_id.to_string(),
+            instrument: order.instrument.to_string(),
+            order_type: order.order_type.to_string(),
+            quantity: order.quantity.to_string(),
+            quantity_filled: order.quantity_filled.to_string(),
+            avg_fill_price: order.avg_fill_price.map(|p| p.to_string()),
+            status: order.status.to_string(),
+        }
+    }
+}
+
+impl From<&Fill> for FillResponse {
+    fn from(fill: &Fill) -> Self {
+        FillResponse {
+            order_id: fill.order_id,
+            event_time: format_time(&fill.event_time),
+            strategy_id: fill.strategy_id.to_string(),
+            instrument: fill.instrument.to_string(),
+            price: fill.price.to_string(),
+            quantity: fill.quantity.to_string(),
+            commission: fill.commission.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FeatureResponse {
+    pub id: String,
+    pub instrument: String,
+    pub event_time: String,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SeriesStats {
+    pub instrument: String,
+    pub series: String,
+    pub entries: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StateStatsResponse {
+    pub entries: usize,
+    pub approx_bytes: usize,
+    pub events: Vec<SeriesStats>,
+    pub features: Vec<SeriesStats>,
+}
+
+#[derive(Deserialize)]
+struct FillsQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct FeaturesQuery {
+    instrument: Option<String>,
+}
+
+pub(crate) fn format_time(time: &time::OffsetDateTime) -> String {
+    time.format(TIMESTAMP_FORMAT).unwrap_or_default()
+}
+
+async fn positions(State(server): State<Arc<Server>>) -> Json<Vec<PositionResponse>> {
+    Json(server.positions().into_iter().map(|p| PositionResponse::from(&p)).collect())
+}
+
+async fn open_orders(State(server): State<Arc<Server>>) -> Json<Vec<OrderResponse>> {
+    Json(server.open_orders().into_iter().map(|o| OrderResponse::from(&o)).collect())
+}
+
+async fn fills(State(server): State<Arc<Server>>, Query(query): Query<FillsQuery>) -> Json<Vec<FillResponse>> {
+    Json(
+        server
+            .recent_fills(query.limit.unwrap_or(100))
+            .into_iter()
+            .map(|f| FillResponse::from(&f))
+            .collect(),
+    )
+}
+
+async fn features(State(server): State<Arc<Server>>, Query(query): Query<FeaturesQuery>) -> Json<Vec<FeatureResponse>> {
+    Json(
+        server
+            .latest_features()
+            .into_iter()
+            .map(|f| FeatureResponse {
+                id: f.id,
+                instrument: f.instrument.to_string(),
+                event_time: format_time(&f.event_time),
+                value: f.value,
+            })
+            .filter(|f| query.instrument.as_ref().is_none_or(|i| *i == f.instrument))
+            .collect(),
+    )
+}
+
+fn health_response(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+async fn liveness(State(server): State<Arc<Server>>) -> (StatusCode, Json<HealthReport>) {
+    health_response(server.liveness())
+}
+
+async fn readiness(State(server): State<Arc<Server>>) -> (StatusCode, Json<HealthReport>) {
+    health_response(server.readiness().await)
+}
+
+async fn state_stats(State(server): State<Arc<Server>>) -> Json<StateStatsResponse> {
+    let stats = server.state_stats();
+    let series = |stats: Vec<(String, String, usize)>| {
+        let mut series = stats
+            .into_iter()
+            .map(|(instrument, series, entries)| SeriesStats {
+                instrument,
+                series,
+                entries,
+            })
+            .collect::<Vec<_>>();
+        series.sort_by(|a, b| (&a.instrument, &a.series).cmp(&(&b.instrument, &b.series)));
+        series
+    };
+    Json(StateStatsResponse {
+        entries: stats.entries(),
+        approx_bytes: stats.approx_bytes(),
+        events: series(
+            stats
+                .events
+                .into_iter()
+                .map(|(i, t, s)| (i.to_string(), t.to_string(), s.entries))
+                .collect(),
+        ),
+        features: series(
+            stats
+                .features
+                .into_iter()
+                .map(|(i, f, s)| (i.to_string(), f, s.entries))
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, state::StateRecorder, test_utils};
+
+    #[tokio::test]
+    async fn test_api() {
+        let server = Arc::new(Server::builder().config(&config::load()).build());
+        let instrument = test_utils::test_perp_instrument();
+        let start = time::OffsetDateTime::now_utc() - time::Duration::minutes(5);
+        let mut recorder = StateRecorder::new(server.state().clone(), server.bus());
+        for event in test_utils::market_events(&instrument, start, 60) {
+            server.bus().publish_event(event);
+        }
+        recorder.drain();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(server)).await });
+
+        let get = |path: &str| reqwest::get(format!("http://{}{}", address, path));
+
+        let stats = get("/state/stats").await.unwrap().json::<StateStatsResponse>().await.unwrap();
+        assert!(stats.events.iter().any(|s| s.series == "Trade" && s.entries == 60));
+
+        let features = get(&format!("/features?instrument={}", instrument))
+            .await
+            .unwrap()
+            .json::<Vec<FeatureResponse>>()
+            .await
+            .unwrap();
+        assert!(!features.is_empty());
+
+        let positions = get("/positions").await.unwrap().json::<Vec<PositionResponse>>().await.unwrap();
+        assert!(positions.is_empty());
+        let orders = get("/orders/open").await.unwrap().json::<Vec<OrderResponse>>().await.unwrap();
+        assert!(orders.is_empty());
+        let fills = get("/fills?limit=10").await.unwrap().json::<Vec<FillResponse>>().await.unwrap();
+        assert!(fills.is_empty());
+
+        assert_eq!(get("/healthz").await.unwrap().status(), StatusCode::OK);
+        // Nothing ran the feed monitor, so there is no market data as far as readiness is concerned
+        let ready = get("/readyz").await.unwrap();
+        assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let report = ready.json::<HealthReport>().await.unwrap();
+        assert!(report.checks.iter().any(|c| c.name == "feeds" && !c.healthy));
+        assert!(report.checks.iter().any(|c| c.name == "execution.simulation" && c.healthy));
+    }
+}
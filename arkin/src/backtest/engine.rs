@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use rust_decimal::prelude::*;
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+
+use crate::{
+    allocation::AllocationManager,
+    bus::EventBus,
+    clock::{Clock, SimulatedClock},
+    config::GlobalConfig,
+    credentials::CredentialStore,
+    db::DBManager,
+    execution::{Execution, ExecutionManager},
+    metrics::METRICS,
+    models::{Event, EventType, Fill, Instrument, InstrumentRegistry, Price, Trade},
+    pipeline::Pipeline,
+    portfolio::Portfolio,
+    state::{LookaheadGuard, Retention, StateManager, StateRecorder},
+    strategies::StrategyManager,
+};
+
+use super::{result::equity_curve, BacktestResult, ReplayControl};
+
+pub struct BacktestEngine {
+    state: Arc<StateManager>,
+    bus: Arc<EventBus>,
+    recorder: StateRecorder,
+    clock: Arc<SimulatedClock>,
+    portfolio: Arc<Portfolio>,
+    pipeline: Pipeline,
+    strategy_manager: StrategyManager,
+    allocation_manager: AllocationManager,
+    execution_manager: ExecutionManager,
+    frequency: Duration,
+    replay: Arc<ReplayControl>,
+    events: VecDeque<Event>,
+}
+
+impl BacktestEngine {
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        let clock = Arc::new(SimulatedClock::new(OffsetDateTime::UNIX_EPOCH));
+        let guard = Arc::new(LookaheadGuard::new(clock.clone(), config.backtest.lookahead_guard));
+        let state = Arc::new(
+            StateManager::with_lookahead_guard(guard)
+                .with_retention(Retention::from_config(&config.state.retention))
+                .with_snapshot_window(Duration::from_secs(config.state.window)),
+        );
+        let bus = Arc::new(EventBus::from_config(&config.bus));
+        let recorder = StateRecorder::new(state.clone(), &bus);
+        let portfolio = Arc::new(Portfolio::new(state.clone(), config.backtest.capital.into()));
+        BacktestEngine {
+            pipeline: Pipeline::from_config(state.clone(), &config.feature_pipeline)
+                .expect("Failed to build the feature pipeline"),
+            strategy_manager: StrategyManager::from_config(&config.strategy_manager),
+            allocation_manager: AllocationManager::from_config(&config.allocation_manager),
+            execution_manager: ExecutionManager::from_config(
+                state.clone(),
+                bus.clone(),
+                portfolio.clone(),
+                config.backtest.seed,
+                &CredentialStore::from_config(&config.credentials),
+                &config.execution_manager,
+            )
+            .expect("Failed to build the execution endpoints")
+            .with_instruments(Arc::new(InstrumentRegistry::from_config(&config.instruments))),
+            frequency: Duration::from_secs(config.backtest.frequency),
+            replay: Arc::new(ReplayControl::new(config.backtest.speed)),
+            events: VecDeque::new(),
+            clock,
+            state,
+            bus,
+            recorder,
+            portfolio,
+        }
+    }
+
+    /// Load the stored ticks and trades between start and end into the replay queue
+    pub async fn load(&mut self, db: &DBManager, start: OffsetDateTime, end: OffsetDateTime) {
+        let events = load_events(db, start, end).await;
+        self.add_events(events);
+    }
+
+    /// Add events to the replay queue, the queue is kept in event time order
+    pub fn add_events(&mut self, events: Vec<Event>) {
+        self.events.extend(events);
+        self.events.make_contiguous().sort_by_key(|e| *e.event_time());
+    }
+
+    pub fn run(&mut self, start: OffsetDateTime, end: OffsetDateTime) -> BacktestResult {
+        info!("Running backtest from {} to {}", start, end);
+        self.clock.reset(start);
+        let guard = self.state.lookahead_guard().cloned();
+        if let Some(guard) = &guard {
+            guard.enable();
+        }
+        let mut marks = Vec::new();
+
+        while self.clock.now() + self.frequency <= end {
+            self.clock.advance(self.frequency);
+            let timestamp = self.clock.now();
+            self.replay.wait(timestamp);
+            debug!("----------------- {:?} -----------------", timestamp);
+            self.replay_until(&timestamp);
+            self.step(&timestamp);
+            marks.push((timestamp, self.last_prices(&timestamp)));
+        }
+
+        if let Some(guard) = &guard {
+            guard.disable();
+            if guard.violations() > 0 {
+                warn!("Backtest read {} times from the future", guard.violations());
+            }
+        }
+
+        let mut fills = self.state.events::<Fill>(&end).into_values().flatten().collect::<Vec<_>>();
+        fills.sort_by_key(|f| (f.event_time, f.order_id));
+
+        let capital = *self.portfolio.capital();
+        let result = BacktestResult {
+            start,
+            end,
+            capital,
+            equity_curve: equity_curve(capital, &fills, &marks),
+            fills,
+            // Positions as of just after the last step so the final fills are included
+            positions: self.portfolio.positions(&(end + self.frequency)),
+            last_prices: self.last_prices(&end),
+        };
+
+        METRICS
+            .pnl
+            .with_label_values(&["gross"])
+            .set(result.gross_pnl().value().to_f64().unwrap_or(0.));
+        METRICS
+            .pnl
+            .with_label_values(&["net"])
+            .set(result.net_pnl().value().to_f64().unwrap_or(0.));
+        METRICS.commission.set(result.total_commission().value().to_f64().unwrap_or(0.));
+        result
+    }
+
+    fn last_prices(&self, timestamp: &OffsetDateTime) -> HashMap<Instrument, Price> {
+        self.state
+            .latest_events::<Trade>(timestamp)
+            .into_iter()
+            .filter_map(|(i, t)| t.map(|t| (i, t.price)))
+            .collect()
+    }
+
+    /// Publish all queued events up to and including the timestamp
+    fn replay_until(&mut self, timestamp: &OffsetDateTime) {
+        while self.events.front().is_some_and(|e| e.event_time() <= timestamp) {
+            let event = self.events.pop_front().expect("Queue should not be empty");
+            self.bus.publish_event(event);
+            // Record right away so a busy step can't overflow the bus buffers
+            self.recorder.drain();
+        }
+    }
+
+    fn step(&mut self, timestamp: &OffsetDateTime) {
+        // The state keeps instruments in a hash map, sort them so every run places orders in the same order
+        let mut instruments = self.state.list_instruments(&EventType::Trade).into_iter().collect::<Vec<_>>();
+        instruments.sort_by_cached_key(|i| i.to_string());
+
+        let signals = instruments
+            .into_iter()
+            .flat_map(|instrument| {
+                let features = self.pipeline.calculate(instrument, *timestamp);
+                for feature in &features {
+                    self.bus.publish(feature.clone());
+                }
+                self.strategy_manager.calculate(&features)
+            })
+            .collect::<Vec<_>>();
+        for signal in &signals {
+            debug!(
+                instrument = %signal.instrument,
+                strategy_id = %signal.strategy_id,
+                signal = %signal.signal,
+                "Signal"
+            );
+            self.bus.publish(signal.clone());
+        }
+
+        let allocations = self.allocation_manager.calculate(&signals, timestamp);
+        for allocation in &allocations {
+            debug!(
+                instrument = %allocation.instrument,
+                strategy_id = %allocation.strategy_id,
+                notional = %allocation.notional,
+                "Allocation"
+            );
+            self.bus.publish(allocation.clone());
+        }
+
+        if !allocations.is_empty() {
+            self.execution_manager.allocate(&allocations);
+        }
+        self.recorder.drain();
+    }
+
+    /// Bus the engine publishes on, subscribe to follow a replay
+    pub fn bus(&self) -> &Arc<EventBus> {
+        &self.bus
+    }
+
+    pub fn state(&self) -> &Arc<StateManager> {
+        &self.state
+    }
+
+    pub fn clock(&self) -> &Arc<SimulatedClock> {
+        &self.clock
+    }
+
+    /// Handle to pause, step or change the speed of a running replay
+    pub fn replay_control(&self) -> Arc<ReplayControl> {
+        self.replay.clone()
+    }
+}
+
+/// Read the stored ticks and trades between start and end
+pub async fn load_events(db: &DBManager, start: OffsetDateTime, end: OffsetDateTime) -> Vec<Event> {
+    let trades = db.read_trades(start, end).await;
+    let ticks = db.read_ticks(start, end).await;
+    info!("Loaded {} trades and {} ticks for backtest", trades.len(), ticks.len());
+
+    trades
+        .into_iter()
+        .map(Event::Trade)
+        .chain(ticks.into_iter().map(Event::Tick))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, logging, synthetic::SyntheticMarket, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_backtest_engine() {
+        logging::init_test_tracing();
+
+        let config = config::load();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = datetime!(2024-01-01 00:03:00).assume_utc();
+
+        let events = test_utils::market_events(&instrument, start, 180);
+
+        let mut engine = BacktestEngine::from_config(&config);
+        engine.add_events(events);
+        let result = engine.run(start, end);
+        info!("{}", result);
+
+        assert!(!result.fills.is_empty());
+        assert!(result.fills.windows(2).all(|w| w[0].event_time <= w[1].event_time));
+        assert!(result.fills.iter().all(|f| f.event_time <= end));
+        assert_eq!(engine.clock().now(), end);
+        assert_eq!(result.equity_curve.len(), 180);
+        assert_eq!(result.equity_curve.last().unwrap().1, result.capital + result.net_pnl());
+    }
+
+    #[test]
+    fn test_backtest_determinism() {
+        let config = config::load();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = datetime!(2024-01-01 00:10:00).assume_utc();
+
+        let run = |seed: u64| {
+            let mut config = config.clone();
+            config.backtest.seed = seed;
+            let events =
+                SyntheticMarket::from_config(&config.backtest.synthetic, seed).generate(&instrument, start, end);
+            let mut engine = BacktestEngine::from_config(&config);
+            engine.add_events(events);
+            let result = engine.run(start, end);
+            let fills = result.fills.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+            (fills, result.equity_curve)
+        };
+
+        let (fills, equity) = run(7);
+        assert!(!fills.is_empty());
+        assert_eq!((fills.clone(), equity.clone()), run(7));
+        assert_ne!(fills, run(8).0);
+    }
+}
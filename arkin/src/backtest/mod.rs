@@ -0,0 +1,15 @@
+mod engine;
+mod monte_carlo;
+mod replay;
+mod result;
+mod run;
+mod sweep;
+mod walk_forward;
+
+pub use engine::{load_events, BacktestEngine};
+pub use monte_carlo::{max_drawdown, trade_pnls, Distribution, MonteCarlo, MonteCarloReport};
+pub use replay::ReplayControl;
+pub use result::BacktestResult;
+pub use run::{BacktestDiff, BacktestRun, ConfigChange};
+pub use sweep::{apply_parameters, ParameterSweep, Parameters, SweepReport, SweepRow};
+pub use walk_forward::{WalkForward, WalkForwardReport, WalkForwardWindow};
@@ -0,0 +1,208 @@
+use std::fmt;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::prelude::*;
+use rust_decimal::prelude::*;
+use tracing::info;
+
+use crate::{
+    config::{GlobalConfig, MonteCarloConfig, MonteCarloMethod},
+    models::Fill,
+    portfolio::PnlTracker,
+    utils::derive_seed,
+};
+
+use super::BacktestResult;
+
+/// Resamples the trades of a backtest to see how much of the result is down to their order and luck
+pub struct MonteCarlo {
+    config: MonteCarloConfig,
+    seed: u64,
+}
+
+impl MonteCarlo {
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        MonteCarlo {
+            config: config.backtest.monte_carlo.to_owned(),
+            seed: derive_seed(config.backtest.seed, "monte_carlo"),
+        }
+    }
+
+    pub fn run(&self, result: &BacktestResult) -> MonteCarloReport {
+        let trades = trade_pnls(&result.fills);
+        info!(
+            "Running {} monte carlo simulations on {} trades",
+            self.config.simulations,
+            trades.len()
+        );
+
+        let (final_pnl, max_drawdown): (Vec<_>, Vec<_>) = (0..self.config.simulations)
+            .into_par_iter()
+            .map(|i| {
+                // Every simulation has its own seed so the outcome doesn't depend on the thread scheduling
+                let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(i as u64));
+                let sample = match self.config.method {
+                    MonteCarloMethod::Bootstrap => (0..trades.len())
+                        .map(|_| trades[rng.gen_range(0..trades.len())])
+                        .collect::<Vec<_>>(),
+                    MonteCarloMethod::Shuffle => {
+                        let mut sample = trades.clone();
+                        sample.shuffle(&mut rng);
+                        sample
+                    }
+                };
+                (sample.iter().sum::<f64>(), max_drawdown(&sample))
+            })
+            .unzip();
+
+        MonteCarloReport {
+            simulations: self.config.simulations,
+            confidence: self.config.confidence,
+            final_pnl: Distribution::from_samples(final_pnl, self.config.confidence),
+            max_drawdown: Distribution::from_samples(max_drawdown, self.config.confidence),
+        }
+    }
+}
+
+/// Realized pnl net of commission for every fill, opening fills only carry their commission
+pub fn trade_pnls(fills: &[Fill]) -> Vec<f64> {
+    let mut tracker = PnlTracker::default();
+    fills.iter().map(|fill| tracker.update(fill).to_f64().unwrap_or(0.)).collect()
+}
+
+/// Largest drop of the cumulative pnl from its running peak
+pub fn max_drawdown(trades: &[f64]) -> f64 {
+    let mut equity = 0.;
+    let mut peak = 0.;
+    let mut drawdown: f64 = 0.;
+    for pnl in trades {
+        equity += pnl;
+        peak = f64::max(peak, equity);
+        drawdown = drawdown.max(peak - equity);
+    }
+    drawdown
+}
+
+pub struct Distribution {
+    pub mean: f64,
+    pub median: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Distribution {
+    pub fn from_samples(mut samples: Vec<f64>, confidence: f64) -> Self {
+        if samples.is_empty() {
+            return Distribution {
+                mean: 0.,
+                median: 0.,
+                lower: 0.,
+                upper: 0.,
+            };
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let tail = (1. - confidence) / 2.;
+        Distribution {
+            mean: samples.iter().sum::<f64>() / samples.len() as f64,
+            median: percentile(&samples, 0.5),
+            lower: percentile(&samples, tail),
+            upper: percentile(&samples, 1. - tail),
+        }
+    }
+}
+
+/// Nearest rank percentile of sorted samples
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mean: {:.2} median: {:.2} interval: [{:.2}, {:.2}]",
+            self.mean, self.median, self.lower, self.upper
+        )
+    }
+}
+
+pub struct MonteCarloReport {
+    pub simulations: usize,
+    pub confidence: f64,
+    pub final_pnl: Distribution,
+    pub max_drawdown: Distribution,
+}
+
+impl fmt::Display for MonteCarloReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "MONTE CARLO simulations: {} confidence: {}",
+            self.simulations, self.confidence
+        )?;
+        writeln!(f, "FINAL PNL {}", self.final_pnl)?;
+        write!(f, "MAX DRAWDOWN {}", self.max_drawdown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        models::{Notional, Quantity},
+        test_utils,
+    };
+    use std::collections::HashMap;
+    use time::macros::datetime;
+
+    fn fill(price: f64, quantity: f64) -> Fill {
+        Fill::new(
+            datetime!(2024-01-01 00:00:00).assume_utc(),
+            test_utils::test_perp_instrument(),
+            0,
+            "test".into(),
+            price.into(),
+            Quantity::from(quantity),
+            Notional::from(0.),
+        )
+    }
+
+    #[test]
+    fn test_trade_pnls() {
+        let fills = vec![fill(100., 1.), fill(110., -2.), fill(105., 1.)];
+        assert_eq!(trade_pnls(&fills), vec![0., 10., 5.]);
+        assert_eq!(max_drawdown(&[10., -5., -10., 20., -3.]), 15.);
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        let mut config = config::load();
+        config.backtest.monte_carlo.simulations = 200;
+        let result = BacktestResult {
+            start: datetime!(2024-01-01 00:00:00).assume_utc(),
+            end: datetime!(2024-01-01 01:00:00).assume_utc(),
+            capital: Notional::from(10000.),
+            fills: vec![fill(100., 1.), fill(110., -1.), fill(105., -1.), fill(108., 1.)],
+            equity_curve: Vec::new(),
+            positions: HashMap::new(),
+            last_prices: HashMap::new(),
+        };
+
+        let report = MonteCarlo::from_config(&config).run(&result);
+        assert!(report.final_pnl.lower <= report.final_pnl.median);
+        assert!(report.final_pnl.median <= report.final_pnl.upper);
+        assert!(report.max_drawdown.lower >= 0.);
+
+        // Same seed gives the same distribution
+        let again = MonteCarlo::from_config(&config).run(&result);
+        assert_eq!(report.final_pnl.mean, again.final_pnl.mean);
+
+        // Shuffling keeps the total
+        config.backtest.monte_carlo.method = MonteCarloMethod::Shuffle;
+        let report = MonteCarlo::from_config(&config).run(&result);
+        assert_eq!(report.final_pnl.lower, 7.);
+        assert_eq!(report.final_pnl.upper, 7.);
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::config::ReplaySpeed;
+
+/// Paces the replay and lets another thread pause, step and change the speed while it runs
+pub struct ReplayControl {
+    state: Mutex<ReplayState>,
+    condvar: Condvar,
+}
+
+struct ReplayState {
+    speed: ReplaySpeed,
+    paused: bool,
+    /// Steps that may run while paused
+    steps: u64,
+    /// Wall clock and replay time the pacing is measured from
+    anchor: Option<(Instant, OffsetDateTime)>,
+}
+
+impl ReplayControl {
+    pub fn new(speed: ReplaySpeed) -> Self {
+        ReplayControl {
+            state: Mutex::new(ReplayState {
+                speed,
+                paused: false,
+                steps: 0,
+                anchor: None,
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    pub fn speed(&self) -> ReplaySpeed {
+        self.state.lock().speed
+    }
+
+    pub fn set_speed(&self, speed: ReplaySpeed) {
+        info!("Setting replay speed to {}", speed);
+        let mut state = self.state.lock();
+        state.speed = speed;
+        state.anchor = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().paused
+    }
+
+    pub fn pause(&self) {
+        info!("Pausing replay");
+        let mut state = self.state.lock();
+        state.paused = true;
+        state.steps = 0;
+    }
+
+    pub fn resume(&self) {
+        info!("Resuming replay");
+        let mut state = self.state.lock();
+        state.paused = false;
+        state.anchor = None;
+        self.condvar.notify_all();
+    }
+
+    /// Let a paused replay run a single step, ignored while the replay runs
+    pub fn step(&self) {
+        let mut state = self.state.lock();
+        if !state.paused {
+            warn!("Ignoring step, the replay is not paused");
+            return;
+        }
+        state.steps += 1;
+        self.condvar.notify_all();
+    }
+
+    /// Block until the replay is allowed to process the given timestamp. It sleeps and waits on a condvar, so
+    /// async callers run the replay on a blocking thread.
+    pub fn wait(&self, timestamp: OffsetDateTime) {
+        let mut state = self.state.lock();
+        if state.paused {
+            while state.paused && state.steps == 0 {
+                self.condvar.wait(&mut state);
+            }
+            if state.paused {
+                state.steps -= 1;
+            }
+            // Don't try to catch up on the time spent paused
+            state.anchor = None;
+        }
+
+        if state.speed == ReplaySpeed::Max {
+            return;
+        }
+        let speed = state.speed;
+        let (wall_start, replay_start) = *state.anchor.get_or_insert((Instant::now(), timestamp));
+        drop(state);
+
+        let replay_elapsed = Duration::try_from(timestamp - replay_start).unwrap_or_default();
+        let Some(wall_elapsed) = speed.wall_time(replay_elapsed) else {
+            return;
+        };
+        let target = wall_start + wall_elapsed;
+        let now = Instant::now();
+        if target > now {
+            std::thread::sleep(target - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_replay_pacing() {
+        let control = ReplayControl::new(ReplaySpeed::Multiplier(100.));
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+
+        let timer = Instant::now();
+        for i in 0..=10 {
+            control.wait(start + time::Duration::seconds(i));
+        }
+        // 10 seconds at 100x is 100ms
+        assert!(timer.elapsed() >= Duration::from_millis(100));
+
+        control.set_speed(ReplaySpeed::Max);
+        let timer = Instant::now();
+        for i in 0..=1000 {
+            control.wait(start + time::Duration::seconds(i));
+        }
+        assert!(timer.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_replay_pause_step() {
+        let control = Arc::new(ReplayControl::new(ReplaySpeed::Max));
+        let processed = Arc::new(AtomicUsize::new(0));
+        control.pause();
+
+        let handle = {
+            let control = control.clone();
+            let processed = processed.clone();
+            thread::spawn(move || {
+                let start = datetime!(2024-01-01 00:00:00).assume_utc();
+                for i in 0..5 {
+                    control.wait(start + time::Duration::seconds(i));
+                    processed.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(processed.load(Ordering::SeqCst), 0);
+
+        control.step();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(processed.load(Ordering::SeqCst), 1);
+
+        control.resume();
+        handle.join().unwrap();
+        assert_eq!(processed.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_replay_step_while_running() {
+        let control = ReplayControl::new(ReplaySpeed::Max);
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+
+        // Steps while running don't carry over into the next pause
+        control.step();
+        control.pause();
+        let processed = Arc::new(AtomicUsize::new(0));
+        thread::scope(|s| {
+            s.spawn(|| {
+                control.wait(start);
+                processed.fetch_add(1, Ordering::SeqCst);
+            });
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(processed.load(Ordering::SeqCst), 0);
+            control.resume();
+        });
+        assert_eq!(processed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fmt};
+
+use time::OffsetDateTime;
+
+use crate::{
+    constants::TIMESTAMP_FORMAT,
+    models::{Fill, Instrument, Notional, Position, Price, Quantity},
+    strategies::StrategyId,
+};
+
+pub struct BacktestResult {
+    pub start: OffsetDateTime,
+    pub end: OffsetDateTime,
+    pub capital: Notional,
+    pub fills: Vec<Fill>,
+    /// Marked to market equity after every step
+    pub equity_curve: Vec<(OffsetDateTime, Notional)>,
+    pub positions: HashMap<(StrategyId, Instrument), Position>,
+    pub last_prices: HashMap<Instrument, Price>,
+}
+
+impl BacktestResult {
+    pub fn total_commission(&self) -> Notional {
+        self.fills.iter().map(|f| f.commission).sum()
+    }
+
+    /// Gross notional of the final positions, like the exposure of the portfolio
+    pub fn total_exposure(&self) -> Notional {
+        self.positions.values().map(|p| p.quantity.abs() * p.avg_price).sum()
+    }
+
+    /// Profit before commission, open positions are marked to the last traded price
+    pub fn gross_pnl(&self) -> Notional {
+        let cash_flow: Notional = self.fills.iter().map(|f| Notional::from(0.) - f.notional()).sum();
+        let open_value: Notional = self
+            .positions
+            .values()
+            .filter(|p| p.is_open())
+            .map(|p| p.quantity * self.last_prices.get(&p.instrument).copied().unwrap_or(p.avg_price))
+            .sum();
+        cash_flow + open_value
+    }
+
+    pub fn net_pnl(&self) -> Notional {
+        self.gross_pnl() - self.total_commission()
+    }
+
+    /// Largest drop of the equity curve from its running peak
+    pub fn max_drawdown(&self) -> Notional {
+        let mut peak = self.capital;
+        let mut drawdown = Notional::from(0.);
+        for (_, equity) in &self.equity_curve {
+            peak = peak.max(*equity);
+            drawdown = drawdown.max(peak - *equity);
+        }
+        drawdown
+    }
+}
+
+/// Replay the sorted fills against the mark prices to get the equity at every mark
+pub(super) fn equity_curve(
+    capital: Notional,
+    fills: &[Fill],
+    marks: &[(OffsetDateTime, HashMap<Instrument, Price>)],
+) -> Vec<(OffsetDateTime, Notional)> {
+    let mut cash = capital;
+    let mut holdings: HashMap<Instrument, (Quantity, Price)> = HashMap::new();
+    let mut fills = fills.iter().peekable();
+
+    marks
+        .iter()
+        .map(|(timestamp, prices)| {
+            while let Some(fill) = fills.next_if(|f| f.event_time <= *timestamp) {
+                cash = cash - fill.notional() - fill.commission;
+                let (quantity, price) = holdings
+                    .entry(fill.instrument.clone())
+                    .or_insert((Quantity::from(0.), fill.price));
+                *quantity += fill.quantity;
+                *price = fill.price;
+            }
+            let value: Notional = holdings
+                .iter()
+                .map(|(instrument, (quantity, price))| *quantity * prices.get(instrument).copied().unwrap_or(*price))
+                .sum();
+            (*timestamp, cash + value)
+        })
+        .collect()
+}
+
+impl fmt::Display for BacktestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BACKTEST {} - {} capital: {} fills: {} open positions: {} exposure: {} commission: {} net pnl: {}",
+            self.start.format(TIMESTAMP_FORMAT).unwrap(),
+            self.end.format(TIMESTAMP_FORMAT).unwrap(),
+            self.capital,
+            self.fills.len(),
+            self.positions.values().filter(|p| p.is_open()).count(),
+            self.total_exposure(),
+            self.total_commission(),
+            self.net_pnl()
+        )
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt, process::Command};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::{config::GlobalConfig, constants::TIMESTAMP_FORMAT, models::Notional};
+
+use super::BacktestResult;
+
+/// A backtest as it is stored, enough to tell what was run and how it did
+#[derive(Clone)]
+pub struct BacktestRun {
+    pub id: Option<i64>,
+    pub created_time: OffsetDateTime,
+    pub config_hash: String,
+    pub git_revision: Option<String>,
+    pub config: String,
+    pub start: OffsetDateTime,
+    pub end: OffsetDateTime,
+    pub capital: Notional,
+    pub fills: usize,
+    pub commission: Notional,
+    pub exposure: Notional,
+    pub gross_pnl: Notional,
+    pub net_pnl: Notional,
+    pub max_drawdown: Notional,
+    pub equity_curve: Vec<(OffsetDateTime, Notional)>,
+}
+
+impl BacktestRun {
+    pub fn new(config: &GlobalConfig, result: &BacktestResult) -> Self {
+        let mut config = serde_json::to_value(config).expect("Failed to serialize config");
+        // Connection details and credentials don't change the outcome and shouldn't end up in the database
+        if let Value::Object(map) = &mut config {
+            map.remove("db");
+            map.remove("ingestors");
+        }
+        let config = config.to_string();
+        BacktestRun {
+            id: None,
+            created_time: OffsetDateTime::now_utc(),
+            config_hash: format!("{:x}", Sha256::digest(config.as_bytes())),
+            git_revision: git_revision(),
+            config,
+            start: result.start,
+            end: result.end,
+            capital: result.capital,
+            fills: result.fills.len(),
+            commission: result.total_commission(),
+            exposure: result.total_exposure(),
+            gross_pnl: result.gross_pnl(),
+            net_pnl: result.net_pnl(),
+            max_drawdown: result.max_drawdown(),
+            equity_curve: result.equity_curve.clone(),
+        }
+    }
+
+    /// Compare this run against a baseline run
+    pub fn diff(&self, baseline: &BacktestRun) -> BacktestDiff {
+        let mut config_changes = Vec::new();
+        if self.config_hash != baseline.config_hash {
+            let current = serde_json::from_str(&self.config).unwrap_or(Value::Null);
+            let previous = serde_json::from_str(&baseline.config).unwrap_or(Value::Null);
+            diff_values("", &previous, &current, &mut config_changes);
+        }
+
+        BacktestDiff {
+            baseline: baseline.id,
+            run: self.id,
+            same_revision: self.git_revision == baseline.git_revision,
+            same_period: self.start == baseline.start && self.end == baseline.end,
+            config_changes,
+            fills: self.fills as i64 - baseline.fills as i64,
+            commission: self.commission - baseline.commission,
+            gross_pnl: self.gross_pnl - baseline.gross_pnl,
+            net_pnl: self.net_pnl - baseline.net_pnl,
+            max_drawdown: self.max_drawdown - baseline.max_drawdown,
+        }
+    }
+}
+
+impl fmt::Display for BacktestRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RUN {} {} - {} config: {} revision: {} fills: {} commission: {} net pnl: {} max drawdown: {}",
+            self.id.map(|id| id.to_string()).unwrap_or("unsaved".into()),
+            self.start.format(TIMESTAMP_FORMAT).unwrap(),
+            self.end.format(TIMESTAMP_FORMAT).unwrap(),
+            &self.config_hash[..8.min(self.config_hash.len())],
+            self.git_revision.as_deref().unwrap_or("unknown"),
+            self.fills,
+            self.commission,
+            self.net_pnl,
+            self.max_drawdown
+        )
+    }
+}
+
+fn git_revision() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Collect the paths that differ between two json documents
+fn diff_values(path: &str, previous: &Value, current: &Value, changes: &mut Vec<ConfigChange>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    match (previous, current) {
+        (Value::Object(a), Value::Object(b)) => {
+            for key in a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))) {
+                let (a, b) = (a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null));
+                diff_values(&join(key), a, b, changes);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                diff_values(&join(&i.to_string()), a, b, changes);
+            }
+        }
+        (a, b) if a != b => changes.push(ConfigChange {
+            path: path.to_string(),
+            previous: a.clone(),
+            current: b.clone(),
+        }),
+        _ => {}
+    }
+}
+
+pub struct ConfigChange {
+    pub path: String,
+    pub previous: Value,
+    pub current: Value,
+}
+
+/// Difference of a run against a baseline, metrics are run minus baseline
+pub struct BacktestDiff {
+    pub baseline: Option<i64>,
+    pub run: Option<i64>,
+    pub same_revision: bool,
+    pub same_period: bool,
+    pub config_changes: Vec<ConfigChange>,
+    pub fills: i64,
+    pub commission: Notional,
+    pub gross_pnl: Notional,
+    pub net_pnl: Notional,
+    pub max_drawdown: Notional,
+}
+
+impl fmt::Display for BacktestDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "DIFF {:?} vs {:?} same revision: {} same period: {}",
+            self.run, self.baseline, self.same_revision, self.same_period
+        )?;
+        for change in &self.config_changes {
+            writeln!(f, "CONFIG {}: {} -> {}", change.path, change.previous, change.current)?;
+        }
+        write!(
+            f,
+            "METRICS fills: {:+} commission: {} gross pnl: {} net pnl: {} max drawdown: {}",
+            self.fills, self.commission, self.gross_pnl, self.net_pnl, self.max_drawdown
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backtest::BacktestEngine, config, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_backtest_run_diff() {
+        let mut config = config::load();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = datetime!(2024-01-01 00:03:00).assume_utc();
+        let events = test_utils::market_events(&instrument, start, 180);
+
+        let mut engine = BacktestEngine::from_config(&config);
+        engine.add_events(events.clone());
+        let baseline = BacktestRun::new(&config, &engine.run(start, end));
+
+        config.backtest.frequency = 2;
+        let mut engine = BacktestEngine::from_config(&config);
+        engine.add_events(events);
+        let run = BacktestRun::new(&config, &engine.run(start, end));
+
+        assert_ne!(run.config_hash, baseline.config_hash);
+        let diff = run.diff(&baseline);
+        assert!(diff.same_period);
+        assert_eq!(diff.config_changes.len(), 1);
+        assert_eq!(diff.config_changes[0].path, "backtest.frequency");
+        assert_eq!(diff.net_pnl, run.net_pnl - baseline.net_pnl);
+
+        assert!(baseline.diff(&baseline).config_changes.is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{fmt, io::Write};
+
+use anyhow::{anyhow, Result};
+use rand::seq::index;
+use rayon::prelude::*;
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::{
+    config::{GlobalConfig, ReplaySpeed, SweepConfig, SweepMode},
+    models::{Event, Notional},
+    utils::seeded_rng,
+};
+
+use super::BacktestEngine;
+
+pub type Parameters = Vec<(String, Value)>;
+
+/// Runs a backtest for every parameter combination of the sweep config
+pub struct ParameterSweep {
+    config: GlobalConfig,
+    sweep: SweepConfig,
+    seed: u64,
+}
+
+impl ParameterSweep {
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        ParameterSweep {
+            config: config.to_owned(),
+            sweep: config.backtest.sweep.to_owned(),
+            seed: config.backtest.seed,
+        }
+    }
+
+    pub fn combinations(&self) -> Vec<Parameters> {
+        let size = self.sweep.parameters.iter().map(|p| p.values.len()).product::<usize>();
+        match self.sweep.mode {
+            SweepMode::Grid => (0..size).map(|i| self.combination(i)).collect(),
+            // Samples the indices so the grid is never built in full
+            SweepMode::Random => {
+                let mut rng = seeded_rng(self.seed, "sweep");
+                index::sample(&mut rng, size, self.sweep.samples.min(size))
+                    .into_iter()
+                    .map(|i| self.combination(i))
+                    .collect()
+            }
+        }
+    }
+
+    /// Combination at the index of the grid, the last parameter changes fastest
+    fn combination(&self, mut index: usize) -> Parameters {
+        let mut combination = self
+            .sweep
+            .parameters
+            .iter()
+            .rev()
+            .map(|param| {
+                let value = &param.values[index % param.values.len()];
+                index /= param.values.len();
+                (param.path.clone(), value.clone())
+            })
+            .collect::<Vec<_>>();
+        combination.reverse();
+        combination
+    }
+
+    /// Run all combinations in parallel on the same events
+    pub fn run(&self, events: &[Event], start: OffsetDateTime, end: OffsetDateTime) -> SweepReport {
+        let combinations = self.combinations();
+        info!("Running parameter sweep with {} combinations", combinations.len());
+
+        let mut rows = combinations
+            .into_par_iter()
+            .filter_map(|parameters| self.run_combination(parameters, events, start, end))
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|r| std::cmp::Reverse(r.net_pnl));
+
+        SweepReport { rows }
+    }
+
+    /// Backtest a single combination, returns None if the parameters can't be applied
+    pub fn run_combination(
+        &self,
+        parameters: Parameters,
+        events: &[Event],
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Option<SweepRow> {
+        let config = match apply_parameters(&self.config, &parameters) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Skipping combination {:?}: {}", parameters, e);
+                return None;
+            }
+        };
+        let mut engine = BacktestEngine::from_config(&config);
+        engine.replay_control().set_speed(ReplaySpeed::Max);
+        engine.add_events(events.to_vec());
+        let result = engine.run(start, end);
+        info!("Finished combination {:?}: {}", parameters, result);
+
+        Some(SweepRow {
+            parameters,
+            fills: result.fills.len(),
+            commission: result.total_commission(),
+            exposure: result.total_exposure(),
+            gross_pnl: result.gross_pnl(),
+            net_pnl: result.net_pnl(),
+        })
+    }
+}
+
+/// Override the values at the given paths, e.g. `feature_pipeline.features.5.sma.input.periods`
+pub fn apply_parameters(config: &GlobalConfig, parameters: &Parameters) -> Result<GlobalConfig> {
+    let mut root = serde_json::to_value(config)?;
+    for (path, value) in parameters {
+        let target = path.split('.').try_fold(&mut root, |node, key| match node {
+            Value::Object(map) => map.get_mut(key),
+            Value::Array(list) => key.parse::<usize>().ok().and_then(|i| list.get_mut(i)),
+            _ => None,
+        });
+        match target {
+            Some(target) => *target = value.clone(),
+            None => return Err(anyhow!("Unknown parameter path: {}", path)),
+        }
+    }
+    Ok(serde_json::from_value(root)?)
+}
+
+pub struct SweepRow {
+    pub parameters: Parameters,
+    pub fills: usize,
+    pub commission: Notional,
+    pub exposure: Notional,
+    pub gross_pnl: Notional,
+    pub net_pnl: Notional,
+}
+
+/// Summary of all combinations, best net pnl first
+pub struct SweepReport {
+    pub rows: Vec<SweepRow>,
+}
+
+impl SweepReport {
+    fn header(&self) -> Vec<String> {
+        let mut header = self
+            .rows
+            .first()
+            .map(|r| r.parameters.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        header.extend(["fills", "commission", "exposure", "gross_pnl", "net_pnl"].map(String::from));
+        header
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|r| {
+                let mut record = r.parameters.iter().map(|(_, v)| v.to_string()).collect::<Vec<_>>();
+                record.push(r.fills.to_string());
+                record.push(r.commission.to_string());
+                record.push(r.exposure.to_string());
+                record.push(r.gross_pnl.to_string());
+                record.push(r.net_pnl.to_string());
+                record
+            })
+            .collect()
+    }
+
+    /// Values with commas or quotes, like json arrays and strings of the grid, are quoted
+    pub fn write_csv(&self, writer: impl Write) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(self.header())?;
+        for record in self.records() {
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for SweepReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = self.header();
+        let records = self.records();
+        let widths = header
+            .iter()
+            .enumerate()
+            .map(|(i, h)| records.iter().map(|r| r[i].len()).chain([h.len()]).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        for row in [header].iter().chain(records.iter()) {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(v, w)| format!("{:>width$}", v, width = w))
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", line.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, logging, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_parameter_sweep() {
+        logging::init_test_tracing();
+
+        let config = config::load();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = datetime!(2024-01-01 00:03:00).assume_utc();
+        let events = test_utils::market_events(&instrument, start, 180);
+
+        let sweep = ParameterSweep::from_config(&config);
+        let report = sweep.run(&events, start, end);
+        info!("\n{}", report);
+
+        let expected = config
+            .backtest
+            .sweep
+            .parameters
+            .iter()
+            .map(|p| p.values.len())
+            .product::<usize>();
+        assert_eq!(report.rows.len(), expected);
+        assert!(report.rows.windows(2).all(|w| w[0].net_pnl >= w[1].net_pnl));
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), expected + 1);
+    }
+
+    #[test]
+    fn test_apply_parameters() {
+        let config = config::load();
+        let parameters = vec![("backtest.frequency".to_string(), Value::from(5))];
+        let updated = apply_parameters(&config, &parameters).unwrap();
+        assert_eq!(updated.backtest.frequency, 5);
+
+        let parameters = vec![("backtest.unknown".to_string(), Value::from(5))];
+        assert!(apply_parameters(&config, &parameters).is_err());
+    }
+
+    #[test]
+    fn test_sweep_combinations() {
+        let mut config = config::load();
+        let sweep = ParameterSweep::from_config(&config);
+        let grid = sweep.combinations();
+        let values = |c: &Parameters| c.iter().map(|(_, v)| v.as_u64().unwrap()).collect::<Vec<_>>();
+        assert_eq!(grid.iter().map(values).collect::<Vec<_>>()[..3], [[3, 30], [3, 60], [5, 30]]);
+
+        config.backtest.sweep.mode = SweepMode::Random;
+        config.backtest.sweep.samples = 4;
+        let sampled = ParameterSweep::from_config(&config).combinations();
+        assert_eq!(sampled.len(), 4);
+        assert!(sampled.iter().all(|c| grid.contains(c)));
+        assert!(sampled.iter().enumerate().all(|(i, c)| !sampled[i + 1..].contains(c)));
+    }
+
+    #[test]
+    fn test_sweep_csv() {
+        let report = SweepReport {
+            rows: vec![SweepRow {
+                parameters: vec![
+                    ("windows".into(), serde_json::json!([60, 300])),
+                    ("name".into(), Value::from("a \"quoted\", name")),
+                ],
+                fills: 2,
+                commission: Notional::from(1.),
+                exposure: Notional::from(0.),
+                gross_pnl: Notional::from(3.),
+                net_pnl: Notional::from(2.),
+            }],
+        };
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        assert_eq!(reader.headers().unwrap().len(), 7);
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(record.len(), 7);
+        assert_eq!(&record[0], "[60,300]");
+        assert_eq!(&record[1], "\"a \\\"quoted\\\", name\"");
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fmt, time::Duration};
+
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::{
+    config::GlobalConfig,
+    constants::TIMESTAMP_FORMAT,
+    models::{Event, Notional},
+};
+
+use super::{ParameterSweep, SweepRow};
+
+/// Rolling optimization: sweep on the in-sample window, trade the winner on the following out-of-sample window
+pub struct WalkForward {
+    sweep: ParameterSweep,
+    in_sample: Duration,
+    out_of_sample: Duration,
+}
+
+impl WalkForward {
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        WalkForward {
+            sweep: ParameterSweep::from_config(config),
+            in_sample: Duration::from_secs(config.backtest.walk_forward.in_sample),
+            out_of_sample: Duration::from_secs(config.backtest.walk_forward.out_of_sample),
+        }
+    }
+
+    pub fn run(&self, events: &[Event], start: OffsetDateTime, end: OffsetDateTime) -> WalkForwardReport {
+        let mut windows = Vec::new();
+        let mut window_start = start;
+
+        while window_start + self.in_sample + self.out_of_sample <= end {
+            let split = window_start + self.in_sample;
+            let window_end = split + self.out_of_sample;
+            info!(
+                "Walk forward window in sample {} - {} out of sample {} - {}",
+                window_start, split, split, window_end
+            );
+
+            let in_sample_events = events_between(events, window_start, split);
+            let report = self.sweep.run(&in_sample_events, window_start, split);
+            let Some(best) = report.rows.into_iter().next() else {
+                info!("No valid combination in sample, skipping window");
+                window_start += self.out_of_sample;
+                continue;
+            };
+
+            let out_of_sample_events = events_between(events, split, window_end);
+            if let Some(out_of_sample) =
+                self.sweep
+                    .run_combination(best.parameters.clone(), &out_of_sample_events, split, window_end)
+            {
+                windows.push(WalkForwardWindow {
+                    start: window_start,
+                    split,
+                    end: window_end,
+                    in_sample: best,
+                    out_of_sample,
+                });
+            }
+            window_start += self.out_of_sample;
+        }
+
+        WalkForwardReport { windows }
+    }
+}
+
+fn events_between(events: &[Event], start: OffsetDateTime, end: OffsetDateTime) -> Vec<Event> {
+    events
+        .iter()
+        .filter(|e| *e.event_time() >= start && *e.event_time() < end)
+        .cloned()
+        .collect()
+}
+
+pub struct WalkForwardWindow {
+    pub start: OffsetDateTime,
+    pub split: OffsetDateTime,
+    pub end: OffsetDateTime,
+    /// Best combination on the in sample window
+    pub in_sample: SweepRow,
+    /// The same combination traded on the out of sample window
+    pub out_of_sample: SweepRow,
+}
+
+pub struct WalkForwardReport {
+    pub windows: Vec<WalkForwardWindow>,
+}
+
+impl WalkForwardReport {
+    pub fn out_of_sample_net_pnl(&self) -> Notional {
+        self.windows.iter().map(|w| w.out_of_sample.net_pnl).sum()
+    }
+
+    pub fn out_of_sample_commission(&self) -> Notional {
+        self.windows.iter().map(|w| w.out_of_sample.commission).sum()
+    }
+
+    pub fn out_of_sample_fills(&self) -> usize {
+        self.windows.iter().map(|w| w.out_of_sample.fills).sum()
+    }
+
+    /// Share of windows with a positive out of sample net pnl
+    pub fn hit_rate(&self) -> f64 {
+        if self.windows.is_empty() {
+            return 0.;
+        }
+        let positive = self
+            .windows
+            .iter()
+            .filter(|w| w.out_of_sample.net_pnl > Notional::from(0.))
+            .count();
+        positive as f64 / self.windows.len() as f64
+    }
+}
+
+impl fmt::Display for WalkForwardReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for window in &self.windows {
+            writeln!(
+                f,
+                "WINDOW {} - {} - {} parameters: {:?} in sample pnl: {} out of sample pnl: {} fills: {}",
+                window.start.format(TIMESTAMP_FORMAT).unwrap(),
+                window.split.format(TIMESTAMP_FORMAT).unwrap(),
+                window.end.format(TIMESTAMP_FORMAT).unwrap(),
+                window.in_sample.parameters,
+                window.in_sample.net_pnl,
+                window.out_of_sample.net_pnl,
+                window.out_of_sample.fills
+            )?;
+        }
+        write!(
+            f,
+            "WALK FORWARD windows: {} out of sample pnl: {} commission: {} fills: {} hit rate: {:.2}",
+            self.windows.len(),
+            self.out_of_sample_net_pnl(),
+            self.out_of_sample_commission(),
+            self.out_of_sample_fills(),
+            self.hit_rate()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, logging, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_walk_forward() {
+        logging::init_test_tracing();
+
+        let mut config = config::load();
+        config.backtest.walk_forward.in_sample = 60;
+        config.backtest.walk_forward.out_of_sample = 30;
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = datetime!(2024-01-01 00:03:00).assume_utc();
+        let events = test_utils::market_events(&instrument, start, 180);
+
+        let report = WalkForward::from_config(&config).run(&events, start, end);
+        info!("{}", report);
+
+        assert_eq!(report.windows.len(), 4);
+        assert!(report.windows.iter().all(|w| w.split - w.start == time::Duration::seconds(60)));
+        assert!(report.windows.windows(2).all(|w| w[0].end == w[1].split));
+        assert!((0. ..=1.).contains(&report.hit_rate()));
+    }
+}
@@ -0,0 +1,520 @@
+use anyhow::{anyhow, Result};
+use arkin::backtest::load_events;
+use arkin::backtest::BacktestEngine;
+use arkin::backtest::BacktestRun;
+use arkin::backtest::MonteCarlo;
+use arkin::backtest::ParameterSweep;
+use arkin::backtest::ReplayControl;
+use arkin::backtest::WalkForward;
+use arkin::clock::TimeSync;
+use arkin::config;
+use arkin::config::ReplaySpeed;
+use arkin::db::DBManager;
+use arkin::db::ExportKind;
+use arkin::ingestors::BackfillKind;
+use arkin::ingestors::BinanceBackfill;
+use arkin::ingestors::BinanceParser;
+use arkin::ingestors::TardisChannel;
+use arkin::ingestors::TardisExchange;
+use arkin::ingestors::TardisRequest;
+use arkin::ingestors::TardisService;
+use arkin::logging::{self, LogFormat};
+use arkin::models::Instrument;
+use arkin::models::InstrumentRegistry;
+use arkin::models::Venue;
+use arkin::rest::RestClients;
+use arkin::server::Server;
+use arkin::synthetic::SyntheticMarket;
+use arkin::tui;
+use clap::Parser;
+use clap::Subcommand;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use mimalloc::MiMalloc;
+use std::sync::Arc;
+use std::time::Instant;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use time::PrimitiveDateTime;
+use tokio::pin;
+use tokio_rustls::rustls::crypto::aws_lc_rs;
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tracing::debug;
+use tracing::error;
+use tracing::info;
+
+/// Data collection, research and live trading in one binary
+#[derive(Parser)]
+#[clap(
+    name = "aurelion",
+    version = "0.1.0",
+    author = "Dorus Janssens",
+    about = "Collect market data, run backtests and trade live"
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Commands,
+
+    /// Log output: text or json
+    #[clap(long, global = true, default_value = "text")]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Download historical market data from tardis into the database
+    Ingest {
+        /// Sets the exchange to use
+        #[clap(long)]
+        exchange: TardisExchange,
+
+        /// Type of data to stream
+        #[clap(long)]
+        channel: TardisChannel,
+
+        #[clap(long, value_delimiter = ',')]
+        instruments: Vec<String>,
+
+        /// Start date for the data stream
+        #[clap(long)]
+        start: String,
+
+        /// End date for the data stream
+        #[clap(long)]
+        end: String,
+    },
+
+    /// Download historical trades or klines from the Binance REST api into the database
+    Backfill {
+        /// Data to download: trades or klines
+        #[clap(long, short, default_value = "trades")]
+        kind: BackfillKind,
+
+        /// Binance symbols like BTCUSDT
+        #[clap(long, value_delimiter = ',')]
+        instruments: Vec<String>,
+
+        /// Kline interval like 1m or 1h
+        #[clap(long, default_value = "1m")]
+        interval: String,
+
+        /// Filter on start date
+        #[clap(long, short)]
+        start: String,
+
+        /// Filter on end date
+        #[clap(long, short)]
+        end: String,
+    },
+
+    /// Run a backtest on the stored market data
+    Backtest {
+        /// Filter on start date
+        #[clap(long, short)]
+        start: String,
+
+        /// Filter on end date
+        #[clap(long, short)]
+        end: String,
+
+        /// Replay speed: max, realtime or a multiplier like 10x
+        #[clap(long)]
+        speed: Option<ReplaySpeed>,
+
+        /// Store the run in the database
+        #[clap(long)]
+        save: bool,
+
+        /// Replay generated market data instead of the stored data
+        #[clap(long)]
+        synthetic: bool,
+    },
+
+    /// Compare a stored backtest run against a baseline run
+    Compare {
+        /// Id of the baseline run
+        #[clap(long, short)]
+        baseline: i64,
+
+        /// Id of the run to compare
+        #[clap(long, short)]
+        run: i64,
+    },
+
+    /// Run a backtest for every parameter combination in the sweep config
+    Sweep {
+        /// Filter on start date
+        #[clap(long, short)]
+        start: String,
+
+        /// Filter on end date
+        #[clap(long, short)]
+        end: String,
+
+        /// Write the comparison table to this csv file
+        #[clap(long, short)]
+        output: Option<String>,
+    },
+
+    /// Run a rolling in sample sweep and evaluate the winners out of sample
+    WalkForward {
+        /// Filter on start date
+        #[clap(long, short)]
+        start: String,
+
+        /// Filter on end date
+        #[clap(long, short)]
+        end: String,
+    },
+
+    /// Trade live with the ingestors, strategies and control plane from the config
+    Live {
+        /// Show the monitoring dashboard in the terminal, logs go to the log file instead
+        #[clap(long)]
+        tui: bool,
+
+        #[clap(long, default_value = "aurelion.log")]
+        log_file: String,
+
+        /// Rebuild the state from the journal of the previous run, e.g. after a crash
+        #[clap(long)]
+        recover: bool,
+
+        /// Take the market data from the collector process instead of running the ingestors
+        #[clap(long)]
+        collector: bool,
+    },
+
+    /// Export stored market data to a csv file
+    Export {
+        /// Filter on start date
+        #[clap(long, short)]
+        start: String,
+
+        /// Filter on end date
+        #[clap(long, short)]
+        end: String,
+
+        /// Data to export: trades or ticks
+        #[clap(long, short, default_value = "trades")]
+        kind: ExportKind,
+
+        /// Csv file to write
+        #[clap(long, short)]
+        output: String,
+    },
+
+    /// Load the configuration and report whether it is valid
+    ValidateConfig,
+
+    /// Apply the pending database migrations
+    Migrate,
+}
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<()> {
+    let args = Cli::parse();
+    match &args.command {
+        Commands::Live {
+            tui: true,
+            log_file,
+            ..
+        } => logging::init_file_tracing(log_file, args.log_format)?,
+        _ => logging::init_tracing(args.log_format),
+    }
+    info!("Starting Arkin 🚀");
+
+    // Checked before anything else so a broken config gets a proper report instead of a panic
+    if let Commands::ValidateConfig = args.command {
+        return match config::try_load() {
+            Ok(_) => {
+                println!("Configuration is valid");
+                Ok(())
+            }
+            Err(e) => Err(anyhow::anyhow!("Invalid configuration: {}", e)),
+        };
+    }
+    let config = config::load();
+
+    if let Commands::Live {
+        tui,
+        recover,
+        collector,
+        ..
+    } = args.command
+    {
+        // Install the default CryptoProvider
+        CryptoProvider::install_default(aws_lc_rs::default_provider())
+            .expect("Failed to install default CryptoProvider");
+        // Debug output keeps the secrets redacted
+        debug!("Loaded configuration: {:#?}", config);
+
+        let server = Arc::new(
+            Server::builder()
+                .config(&config)
+                .recover(recover)
+                .collector_feed(collector)
+                .build(),
+        );
+        if tui {
+            let runner = server.clone();
+            let runner = tokio::spawn(async move { runner.run().await });
+            let result = tui::run(server.clone()).await;
+            // Leaving the dashboard stops trading the same way a signal does
+            server.request_shutdown();
+            runner.await?;
+            result?;
+        } else {
+            server.run().await;
+        }
+        return Ok(());
+    }
+
+    let manager = Arc::new(DBManager::from_config(&config.db).await);
+
+    match args.command {
+        Commands::Ingest {
+            exchange,
+            channel,
+            instruments,
+            start,
+            end,
+        } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            info!("Starting data stream");
+            info!("Exchange: {}", exchange);
+            info!("Channel: {}", exchange.channel_str(&channel)?);
+            info!("Instruments: {:?}", instruments);
+            info!("Start: {}", start);
+            info!("End: {}", end);
+
+            let req = TardisRequest::new(&exchange, &channel, &instruments, &start, &end);
+            let tardis = TardisService::builder()
+                .base_url("https://api.tardis.dev/v1/data-feeds".into())
+                .max_concurrent_requests(5)
+                .build();
+            let stream = tardis.stream(req);
+            pin!(stream);
+            // process_stream_concurrently(stream, manager.clone(), 10).await;
+
+            // batch insert 5000 events
+            let parser = BinanceParser::new(Arc::new(InstrumentRegistry::from_config(&config.instruments)));
+            let mut events = Vec::with_capacity(10000);
+            while let Some((_ts, json)) = stream.next().await {
+                let event = parser.parse_swap(json)?;
+                events.push(event);
+
+                if events.len() >= 10000 {
+                    if let Err(e) = manager.insert_events_batch(&events).await {
+                        error!("Failed to add events: {}", e);
+                    }
+                    info!("Inserted 10000 events");
+                    events.clear();
+                }
+            }
+        }
+        Commands::Backfill {
+            kind,
+            instruments,
+            interval,
+            start,
+            end,
+        } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            let registry = Arc::new(InstrumentRegistry::from_config(&config.instruments));
+            let rest = RestClients::from_config(&config.rest, &Arc::new(TimeSync::default()))
+                .get(&Venue::Binance)
+                .ok_or_else(|| anyhow!("No rest client of binance configured"))?;
+            let mut backfill = BinanceBackfill::from_config(&config.backfill, rest, registry);
+            for symbol in instruments {
+                backfill.run(&manager, kind, &symbol, &interval, start, end).await?;
+            }
+        }
+        Commands::Backtest {
+            start,
+            end,
+            speed,
+            save,
+            synthetic,
+        } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            info!(
+                "Starting backtest from {} to {}",
+                start.format(&format).expect("Failed to format date"),
+                end.format(&format).expect("Failed to format date")
+            );
+
+            let mut engine = BacktestEngine::from_config(&config);
+            if synthetic {
+                let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+                let mut market = SyntheticMarket::from_config(&config.backtest.synthetic, config.backtest.seed);
+                engine.add_events(market.generate(&instrument, start, end));
+            } else {
+                engine.load(&manager, start, end).await;
+            }
+            if let Some(speed) = speed {
+                engine.replay_control().set_speed(speed);
+            }
+            spawn_replay_controls(engine.replay_control());
+
+            // The replay blocks while it paces and pauses, so it runs off the async workers
+            let timer = Instant::now();
+            let result = tokio::task::spawn_blocking(move || engine.run(start, end)).await?;
+            info!("Elapsed time: {:?}", timer.elapsed());
+            info!("{}", result);
+            for fill in &result.fills {
+                debug!("{}", fill);
+            }
+
+            let monte_carlo = MonteCarlo::from_config(&config).run(&result);
+            info!("\n{}", monte_carlo);
+
+            if save {
+                let run = BacktestRun::new(&config, &result);
+                let run_id = manager.insert_backtest_run(&run).await?;
+                info!("Stored backtest run {}", run_id);
+            }
+        }
+        Commands::Compare { baseline, run } => {
+            let diff = manager.compare_backtest_runs(baseline, run).await?;
+            info!("\n{}", diff);
+        }
+        Commands::Sweep { start, end, output } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            let events = load_events(&manager, start, end).await;
+            let sweep = ParameterSweep::from_config(&config);
+
+            let timer = Instant::now();
+            let report = tokio::task::spawn_blocking(move || sweep.run(&events, start, end)).await?;
+            info!("Elapsed time: {:?}", timer.elapsed());
+            info!("\n{}", report);
+
+            if let Some(output) = output {
+                let file = std::fs::File::create(&output)?;
+                report.write_csv(std::io::BufWriter::new(file))?;
+                info!("Written comparison table to {}", output);
+            }
+        }
+        Commands::WalkForward { start, end } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            let events = load_events(&manager, start, end).await;
+            let walk_forward = WalkForward::from_config(&config);
+
+            let timer = Instant::now();
+            let report = tokio::task::spawn_blocking(move || walk_forward.run(&events, start, end)).await?;
+            info!("Elapsed time: {:?}", timer.elapsed());
+            info!("\n{}", report);
+        }
+        Commands::Export {
+            start,
+            end,
+            kind,
+            output,
+        } => {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]");
+            let start = PrimitiveDateTime::parse(&start, &format)?.assume_utc();
+            let end = PrimitiveDateTime::parse(&end, &format)?.assume_utc();
+
+            let file = std::fs::File::create(&output)?;
+            let rows = manager.export(kind, start, end, std::io::BufWriter::new(file)).await?;
+            info!("Exported {} {} to {}", rows, kind, output);
+        }
+        Commands::Migrate => {
+            manager.migrate().await?;
+            info!("Database is up to date");
+        }
+        Commands::Live { .. } | Commands::ValidateConfig => unreachable!("Handled before connecting to the database"),
+    }
+    Ok(())
+}
+
+/// Read replay commands from stdin: p(ause), r(esume), s(tep) or a new speed
+fn spawn_replay_controls(control: Arc<ReplayControl>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            match line.trim() {
+                "" => {}
+                "p" => control.pause(),
+                "r" => control.resume(),
+                "s" => control.step(),
+                speed => match speed.parse::<ReplaySpeed>() {
+                    Ok(speed) => control.set_speed(speed),
+                    Err(e) => error!("{}", e),
+                },
+            }
+        }
+    });
+}
+
+async fn _process_stream_concurrently(
+    stream: impl Stream<Item = (OffsetDateTime, String)>,
+    manager: Arc<DBManager>,
+    parser: BinanceParser,
+    concurrency: usize,
+) {
+    stream
+        .for_each_concurrent(concurrency, |(_, json)| {
+            let manager_clone = manager.clone(); // Clone manager for each concurrent operation
+            let parser = parser.clone();
+            async move {
+                // Attempt to parse the JSON
+                let res = parser.parse_swap(json);
+                match res {
+                    Ok(event) => {
+                        // On success, clone manager and add the event
+                        let manager_clone = manager_clone.clone();
+                        if let Err(e) = manager_clone.add_event(event).await {
+                            // Log error if adding event fails
+                            error!("Failed to add event: {}", e);
+                        }
+                    }
+                    Err(e) => {
+                        // Log error if parsing fails
+                        error!("Failed to parse event: {}", e);
+                    }
+                }
+            }
+        })
+        .await; // Await the completion of the concurrent operations
+}
+
+// async fn write_to_file(stream: impl Stream<Item = (OffsetDateTime, String)>) {
+//     // Create file in ./tests/data
+//     let filename = format!(
+//         "./tests/data/{}_{}_{}_{}_{}.json",
+//         exchange,
+//         channel,
+//         instruments.join("_"),
+//         start.format(format_description!("[year][month][day][hour][minute]"))?,
+//         end.format(format_description!("[year][month][day][hour][minute]"))?
+//     );
+
+//     //   Open file
+//     let file = std::fs::File::create(filename)?;
+//     let mut writer = std::io::BufWriter::new(file);
+//     while let Some((_ts, json)) = stream.next().await {
+//         // let event = BinanceParser::parse_swap(&json)?;
+//         // let event_json = serde_json::to_string(&event)?;
+//         writer.write_all(&json.into_bytes())?;
+//         writer.write_all(b"\n")?;
+//     }
+//     writer.flush()?;
+// }
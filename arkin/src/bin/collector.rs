@@ -0,0 +1,37 @@
+use anyhow::Result;
+use arkin::collector::Collector;
+use arkin::config;
+use arkin::logging::{self, LogFormat};
+use clap::Parser;
+use mimalloc::MiMalloc;
+use tokio_rustls::rustls::crypto::aws_lc_rs;
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tracing::info;
+
+/// Market data capture that keeps running while the trading engine restarts
+#[derive(Parser)]
+#[clap(
+    name = "collector",
+    version = "0.1.0",
+    author = "Dorus Janssens",
+    about = "Run the ingestors, store their data and serve it to the trading engine"
+)]
+struct Cli {
+    /// Log output: text or json
+    #[clap(long, default_value = "text")]
+    log_format: LogFormat,
+}
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<()> {
+    let args = Cli::parse();
+    logging::init_tracing(args.log_format);
+    info!("Starting collector 📡");
+
+    CryptoProvider::install_default(aws_lc_rs::default_provider()).expect("Failed to install default CryptoProvider");
+    let config = config::load();
+    Collector::from_config(&config).run().await
+}
@@ -0,0 +1,535 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+};
+
+use parking_lot::{Mutex, RwLock};
+use strum::Display;
+use tokio::sync::{
+    broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    },
+    mpsc,
+};
+use tracing::{error, warn};
+
+use crate::{
+    config::BusConfig,
+    features::FeatureEvent,
+    journal::Journal,
+    metrics::METRICS,
+    models::{
+        AccountUpdate, Allocation, Book, BookUpdate, Candle, DataGap, Event, Fill, FundingPayment, FundingRate,
+        Liquidation, Order, OrderUpdate, RiskEvent, Signal, Tick, Trade,
+    },
+};
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[strum(serialize_all = "snake_case")]
+pub enum Topic {
+    MarketData,
+    Features,
+    Signals,
+    Allocations,
+    Orders,
+    Fills,
+    Account,
+    Risk,
+}
+
+impl Topic {
+    /// Topics whose subscribers must see every message, they get a queue of their own instead of a shared buffer
+    pub fn lossless(&self) -> bool {
+        matches!(self, Topic::Orders | Topic::Fills)
+    }
+}
+
+/// A message that can be sent over the bus, every message type belongs to one topic
+pub trait BusMessage: Clone + Send + Sync + 'static {
+    fn topic() -> Topic;
+
+    /// The message as it is written to the journal, messages that are derived from events are not journaled
+    fn event(&self) -> Option<Event> {
+        None
+    }
+}
+
+macro_rules! bus_message {
+    ($topic:ident: $($message:ty),+) => {
+        $(impl BusMessage for $message {
+            fn topic() -> Topic {
+                Topic::$topic
+            }
+        })+
+    };
+    ($topic:ident, journaled: $($message:ident),+) => {
+        $(impl BusMessage for $message {
+            fn topic() -> Topic {
+                Topic::$topic
+            }
+
+            fn event(&self) -> Option<Event> {
+                Some(Event::$message(self.clone()))
+            }
+        })+
+    };
+}
+
+bus_message!(MarketData, journaled: Tick, Trade, Book, BookUpdate, FundingRate, Liquidation, Candle, DataGap);
+bus_message!(Features: FeatureEvent);
+bus_message!(Signals, journaled: Signal);
+bus_message!(Allocations, journaled: Allocation);
+bus_message!(Orders, journaled: Order);
+bus_message!(Fills, journaled: Fill);
+bus_message!(Account, journaled: OrderUpdate, AccountUpdate, FundingPayment);
+bus_message!(Risk: RiskEvent);
+
+/// A message with the sequence number the bus gave it, numbers increase with every publish so they order messages
+/// of different types that share an event time
+#[derive(Clone)]
+pub struct Sequenced<T> {
+    pub seq: u64,
+    pub message: T,
+}
+
+/// Type erased channel so the backlog can be read without knowing the message type
+trait Channel: Send + Sync {
+    fn topic(&self) -> Topic;
+    fn backlog(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Queue of one subscriber of a lossless topic with the number of messages waiting in it
+struct Queue<T> {
+    sender: mpsc::UnboundedSender<Sequenced<T>>,
+    depth: Arc<AtomicUsize>,
+}
+
+/// Every subscriber of a lossless topic gets its own queue, so a slow one falls behind without missing messages.
+///
+/// The queues are unbounded on purpose. Publishing is synchronous and happens on the trading loop and the
+/// execution manager, waiting for room in a full queue would stall trading, or deadlock when the slow subscriber is
+/// the publisher itself. A stalled subscriber grows its queue instead: the deepest queue is exported as
+/// `bus_queue_depth` on every send and receive, and an error is logged once a queue is `capacity` behind.
+struct Queues<T> {
+    queues: Mutex<Vec<Queue<T>>>,
+    capacity: usize,
+}
+
+impl<T: BusMessage> Queues<T> {
+    fn send(&self, message: Sequenced<T>) -> usize {
+        let mut queues = self.queues.lock();
+        queues.retain(|queue| {
+            let depth = queue.depth.fetch_add(1, Ordering::SeqCst) + 1;
+            if depth == self.capacity + 1 {
+                error!("Subscriber on {} is {} messages behind", T::topic(), self.capacity);
+            }
+            queue.sender.send(message.clone()).is_ok()
+        });
+        queues.len()
+    }
+
+    fn subscribe(self: &Arc<Self>) -> QueueReceiver<T> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let depth = Arc::new(AtomicUsize::new(0));
+        self.queues.lock().push(Queue {
+            sender,
+            depth: depth.clone(),
+        });
+        QueueReceiver {
+            receiver,
+            depth,
+            queues: Arc::downgrade(self),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.queues
+            .lock()
+            .iter()
+            .map(|q| q.depth.load(Ordering::SeqCst))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Receiving end of the queue of one subscriber on a lossless topic
+struct QueueReceiver<T> {
+    receiver: mpsc::UnboundedReceiver<Sequenced<T>>,
+    depth: Arc<AtomicUsize>,
+    /// Weak so the queue still closes once the bus is gone
+    queues: Weak<Queues<T>>,
+}
+
+impl<T: BusMessage> QueueReceiver<T> {
+    fn received(&self) {
+        self.depth.fetch_sub(1, Ordering::SeqCst);
+        if let Some(queues) = self.queues.upgrade() {
+            METRICS
+                .bus_queue_depth
+                .with_label_values(&[&T::topic().to_string()])
+                .set(queues.len() as i64);
+        }
+    }
+}
+
+/// Channel of one message type, a shared buffer or a queue per subscriber on lossless topics
+enum Sender<T> {
+    Broadcast(broadcast::Sender<Sequenced<T>>),
+    Queues(Arc<Queues<T>>),
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Sender::Broadcast(sender) => Sender::Broadcast(sender.clone()),
+            Sender::Queues(queues) => Sender::Queues(queues.clone()),
+        }
+    }
+}
+
+impl<T: BusMessage> Sender<T> {
+    fn new(capacity: usize) -> Self {
+        match T::topic().lossless() {
+            true => Sender::Queues(Arc::new(Queues {
+                queues: Mutex::new(Vec::new()),
+                capacity,
+            })),
+            false => Sender::Broadcast(broadcast::channel(capacity).0),
+        }
+    }
+
+    fn send(&self, message: Sequenced<T>) -> usize {
+        match self {
+            Sender::Broadcast(sender) => sender.send(message).unwrap_or(0),
+            Sender::Queues(queues) => queues.send(message),
+        }
+    }
+
+    fn subscribe(&self) -> Receiver<T> {
+        match self {
+            Sender::Broadcast(sender) => Receiver::Broadcast(sender.subscribe()),
+            Sender::Queues(queues) => Receiver::Queue(queues.subscribe()),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Sender::Broadcast(sender) => sender.len(),
+            Sender::Queues(queues) => queues.len(),
+        }
+    }
+}
+
+impl<T: BusMessage> Channel for Sender<T> {
+    fn topic(&self) -> Topic {
+        T::topic()
+    }
+
+    fn backlog(&self) -> usize {
+        self.len()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+enum Receiver<T> {
+    Broadcast(broadcast::Receiver<Sequenced<T>>),
+    Queue(QueueReceiver<T>),
+}
+
+/// Central publish and subscribe hub every subsystem communicates through.
+///
+/// Each message type has its own bounded broadcast channel sized by the buffer of its topic. Slow
+/// subscribers lose the oldest messages instead of blocking the publisher. Orders and fills are never lost,
+/// each of their subscribers gets its own queue and one that falls more than the buffer behind is logged.
+pub struct EventBus {
+    config: BusConfig,
+    channels: RwLock<HashMap<TypeId, Box<dyn Channel>>>,
+    journal: Option<Journal>,
+    sequence: AtomicU64,
+}
+
+impl EventBus {
+    pub fn from_config(config: &BusConfig) -> Self {
+        EventBus {
+            config: config.to_owned(),
+            channels: RwLock::new(HashMap::new()),
+            journal: None,
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Continue numbering after the last sequence number of a previous run, e.g. the last one of the journal
+    pub fn with_sequence(self, last: u64) -> Self {
+        self.sequence.store(last, Ordering::SeqCst);
+        self
+    }
+
+    /// Sequence number of the last published message
+    pub fn last_sequence(&self) -> u64 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    /// Write every event to the journal before it is delivered
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    fn sender<T: BusMessage>(&self) -> Sender<T> {
+        if let Some(sender) = self.channels.read().get(&TypeId::of::<T>()) {
+            return sender
+                .as_any()
+                .downcast_ref::<Sender<T>>()
+                .expect("Bus channel has wrong type")
+                .clone();
+        }
+
+        self.channels
+            .write()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Sender::<T>::new(self.config.capacity(T::topic()))))
+            .as_any()
+            .downcast_ref::<Sender<T>>()
+            .expect("Bus channel has wrong type")
+            .clone()
+    }
+
+    /// Messages not yet received by every subscriber, the fullest channel of each topic
+    pub fn backlog(&self) -> HashMap<Topic, usize> {
+        let mut backlog = HashMap::new();
+        for channel in self.channels.read().values() {
+            let depth = backlog.entry(channel.topic()).or_insert(0);
+            *depth = channel.backlog().max(*depth);
+        }
+        backlog
+    }
+
+    pub fn capacity(&self, topic: Topic) -> usize {
+        self.config.capacity(topic)
+    }
+
+    /// Publish a message to all current subscribers of its type, returns how many received it
+    pub fn publish<T: BusMessage>(&self, message: T) -> usize {
+        let sender = self.sender::<T>();
+        let seq = match self.journal.as_ref().zip(message.event()) {
+            Some((journal, event)) => {
+                let (seq, result) = journal.append_next(&self.sequence, &event);
+                if let Err(e) = result {
+                    // Trading goes on, the gap only matters if the engine crashes before the next restart
+                    error!("Failed to journal {}: {}", event.event_type(), e);
+                    METRICS.journal_errors.inc();
+                }
+                seq
+            }
+            None => self.sequence.fetch_add(1, Ordering::SeqCst) + 1,
+        };
+        let receivers = sender.send(Sequenced { seq, message });
+        let topic = T::topic().to_string();
+        METRICS.bus_published.with_label_values(&[&topic]).inc();
+        METRICS.bus_queue_depth.with_label_values(&[&topic]).set(sender.len() as i64);
+        receivers
+    }
+
+    /// Publish an event on the topic of its variant
+    pub fn publish_event(&self, event: Event) -> usize {
+        match event {
+            Event::Tick(e) => self.publish(e),
+            Event::Trade(e) => self.publish(e),
+            Event::Book(e) => self.publish(e),
+            Event::BookUpdate(e) => self.publish(e),
+            Event::FundingRate(e) => self.publish(e),
+            Event::Liquidation(e) => self.publish(e),
+            Event::Candle(e) => self.publish(e),
+            Event::DataGap(e) => self.publish(e),
+            Event::Order(e) => self.publish(e),
+            Event::Fill(e) => self.publish(e),
+            Event::Signal(e) => self.publish(e),
+            Event::Allocation(e) => self.publish(e),
+            Event::OrderUpdate(e) => self.publish(e),
+            Event::AccountUpdate(e) => self.publish(e),
+            Event::FundingPayment(e) => self.publish(e),
+        }
+    }
+
+    /// Receive every message of type T published from now on
+    pub fn subscribe<T: BusMessage>(&self) -> Subscription<T> {
+        Subscription {
+            receiver: self.sender::<T>().subscribe(),
+            filter: None,
+        }
+    }
+}
+
+type Filter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+pub struct Subscription<T> {
+    receiver: Receiver<T>,
+    filter: Option<Filter<T>>,
+}
+
+impl<T: BusMessage> Subscription<T> {
+    /// Only receive the messages the filter keeps, e.g. the trades of one instrument. The others still count
+    /// towards the buffer of the channel until they are skipped.
+    pub fn filter(mut self, filter: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    fn keeps(&self, message: &Sequenced<T>) -> bool {
+        self.filter.as_ref().is_none_or(|f| f(&message.message))
+    }
+
+    /// Wait for the next message, None once the bus is gone
+    pub async fn recv(&mut self) -> Option<T> {
+        self.recv_sequenced().await.map(|s| s.message)
+    }
+
+    /// Next message if one is waiting, for subscribers that are driven synchronously
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.try_recv_sequenced().map(|s| s.message)
+    }
+
+    /// Wait for the next message together with its sequence number
+    pub async fn recv_sequenced(&mut self) -> Option<Sequenced<T>> {
+        loop {
+            let message = match &mut self.receiver {
+                Receiver::Broadcast(receiver) => match receiver.recv().await {
+                    Ok(message) => message,
+                    Err(RecvError::Lagged(skipped)) => {
+                        lagged::<T>(skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                Receiver::Queue(queue) => {
+                    let message = queue.receiver.recv().await?;
+                    queue.received();
+                    message
+                }
+            };
+            if self.keeps(&message) {
+                return Some(message);
+            }
+        }
+    }
+
+    pub fn try_recv_sequenced(&mut self) -> Option<Sequenced<T>> {
+        loop {
+            let message = match &mut self.receiver {
+                Receiver::Broadcast(receiver) => match receiver.try_recv() {
+                    Ok(message) => message,
+                    Err(TryRecvError::Lagged(skipped)) => {
+                        lagged::<T>(skipped);
+                        continue;
+                    }
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return None,
+                },
+                Receiver::Queue(queue) => {
+                    let message = queue.receiver.try_recv().ok()?;
+                    queue.received();
+                    message
+                }
+            };
+            if self.keeps(&message) {
+                return Some(message);
+            }
+        }
+    }
+}
+
+fn lagged<T: BusMessage>(skipped: u64) {
+    warn!("Subscriber on {} lagged, skipped {} messages", T::topic(), skipped);
+    METRICS
+        .bus_dropped
+        .with_label_values(&[&T::topic().to_string()])
+        .inc_by(skipped);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        models::{Instrument, Venue},
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    #[tokio::test]
+    async fn test_event_bus() {
+        let mut config = config::load().bus;
+        config.fills = 2;
+        let bus = EventBus::from_config(&config);
+        let instrument = test_utils::test_perp_instrument();
+        let tick = |id| {
+            Tick::new(
+                datetime!(2024-01-01 00:00:00).assume_utc(),
+                instrument.clone(),
+                id,
+                100.0.into(),
+                1.0.into(),
+                101.0.into(),
+                1.0.into(),
+            )
+        };
+
+        // Nobody listens yet
+        assert_eq!(bus.publish(tick(0)), 0);
+
+        let mut ticks = bus.subscribe::<Tick>();
+        let mut trades = bus.subscribe::<Trade>();
+        assert_eq!(bus.publish_event(Event::Tick(tick(1))), 1);
+        let received = ticks.recv_sequenced().await.unwrap();
+        assert_eq!((received.seq, received.message.tick_id), (2, 1));
+        assert!(trades.try_recv().is_none());
+        assert_eq!(bus.last_sequence(), 2);
+
+        let other = Instrument::perpetual(Venue::Binance, "ETH".into(), "USDT".into());
+        let mut others = bus.subscribe::<Tick>().filter(move |t| t.instrument == other);
+        bus.publish(tick(2));
+        assert!(others.try_recv().is_none());
+        assert_eq!(ticks.try_recv().unwrap().tick_id, 2);
+
+        // The ticks buffer drops the oldest messages of a slow subscriber
+        config.market_data = 2;
+        let bus = EventBus::from_config(&config);
+        let mut ticks = bus.subscribe::<Tick>();
+        for i in 0..3 {
+            bus.publish(tick(i));
+        }
+        assert_eq!(ticks.try_recv().unwrap().tick_id, 1);
+
+        // Fills are never dropped, even past the buffer of the topic
+        let mut fills = bus.subscribe::<Fill>();
+        let mut late = None;
+        for i in 0..3 {
+            bus.publish(Fill::new(
+                datetime!(2024-01-01 00:00:00).assume_utc(),
+                instrument.clone(),
+                i,
+                "test".into(),
+                100.0.into(),
+                1.0.into(),
+                0.0.into(),
+            ));
+            late.get_or_insert_with(|| bus.subscribe::<Fill>());
+        }
+        assert_eq!(bus.backlog()[&Topic::Fills], 3);
+        assert_eq!(fills.try_recv().unwrap().order_id, 0);
+        assert_eq!(fills.recv().await.unwrap().order_id, 1);
+        assert_eq!(fills.try_recv().unwrap().order_id, 2);
+        assert!(fills.try_recv().is_none());
+        // A subscriber only gets what was published after it subscribed
+        assert_eq!(bus.backlog()[&Topic::Fills], 2);
+        assert_eq!(late.unwrap().try_recv().unwrap().order_id, 1);
+    }
+}
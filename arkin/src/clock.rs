@@ -3,24 +3,35 @@
 use parking_lot::RwLock;
 use std::{
     collections::HashMap,
+    sync::Arc,
     time::{Duration, Instant, UNIX_EPOCH},
 };
 use time::OffsetDateTime;
 use tokio::sync::broadcast::{self, Receiver, Sender};
 use tracing::{debug, error, info};
 
-use crate::{config::ClockConfig, constants::TIMESTAMP_FORMAT};
+use crate::{config::ClockConfig, constants::TIMESTAMP_FORMAT, models::Venue};
 
-pub struct Clock {
+/// Source of time for the engine.
+///
+/// Components ask the clock for the current time and subscribe to ticks instead of reading the
+/// wall clock directly, so the same code runs live and in a deterministic backtest.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+    fn subscribe(&self, frequency: Duration) -> Receiver<OffsetDateTime>;
+}
+
+/// Wall clock that ticks subscribers on the boundaries of their frequency.
+pub struct LiveClock {
     pub subscribers: RwLock<HashMap<Duration, Sender<OffsetDateTime>>>,
     pub tick_frequency: Duration,
 }
 
-impl Clock {
+impl LiveClock {
     pub fn from_config(config: &ClockConfig) -> Self {
         let tick_frequency = Duration::from_secs(config.tick_frequency);
         info!("Creating time component with tick frequency: {:?}", tick_frequency);
-        Clock {
+        LiveClock {
             subscribers: RwLock::new(HashMap::new()),
             tick_frequency,
         }
@@ -47,7 +58,7 @@ impl Clock {
     }
 
     pub fn calculate_next_tick(&self, interval: Duration) -> (Instant, OffsetDateTime) {
-        let now = OffsetDateTime::now_utc();
+        let now = self.now();
 
         // Calculate the difference between now and the epoch
         let difference = now - UNIX_EPOCH;
@@ -70,8 +81,14 @@ impl Clock {
         debug!("Start: {:?}", start);
         (start, next_tick_time)
     }
+}
+
+impl Clock for LiveClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
 
-    pub fn subscribe(&self, frequency: Duration) -> Receiver<OffsetDateTime> {
+    fn subscribe(&self, frequency: Duration) -> Receiver<OffsetDateTime> {
         info!("Subscribing to time component with frequency: {:?}", frequency);
         if let Some(sender) = self.subscribers.read().get(&frequency) {
             info!("Found existing subscriber for frequency: {:?}", frequency);
@@ -85,18 +102,128 @@ impl Clock {
     }
 }
 
+/// Offsets of the venues' server clocks from the local clock, measured by the
+/// [`SkewMonitor`](crate::skew::SkewMonitor). Unknown venues have no offset.
+#[derive(Default)]
+pub struct TimeSync {
+    offsets: RwLock<HashMap<Venue, time::Duration>>,
+}
+
+impl TimeSync {
+    pub fn offset(&self, venue: &Venue) -> time::Duration {
+        self.offsets.read().get(venue).copied().unwrap_or_default()
+    }
+
+    pub fn set_offset(&self, venue: &Venue, offset: time::Duration) {
+        self.offsets.write().insert(venue.clone(), offset);
+    }
+
+    pub fn clock(self: &Arc<Self>, venue: Venue) -> ServerClock {
+        ServerClock {
+            sync: self.clone(),
+            venue,
+        }
+    }
+}
+
+/// Wall clock moved by the offset of a venue, so received times compare to the venue's event times and
+/// signed requests carry a timestamp the venue accepts
+#[derive(Clone)]
+pub struct ServerClock {
+    sync: Arc<TimeSync>,
+    venue: Venue,
+}
+
+impl ServerClock {
+    /// Local time only, for tools that don't run the skew monitor
+    pub fn local(venue: Venue) -> Self {
+        Arc::new(TimeSync::default()).clock(venue)
+    }
+
+    pub fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc() + self.sync.offset(&self.venue)
+    }
+}
+
+/// Clock that only moves when it is advanced, used to replay history deterministically.
+pub struct SimulatedClock {
+    now: RwLock<OffsetDateTime>,
+    subscribers: RwLock<HashMap<Duration, Sender<OffsetDateTime>>>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: OffsetDateTime) -> Self {
+        SimulatedClock {
+            now: RwLock::new(start),
+            subscribers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Move the clock forward, emitting every tick boundary that is passed on the way.
+    pub fn advance_to(&self, time: OffsetDateTime) {
+        let previous = {
+            let mut now = self.now.write();
+            if time <= *now {
+                return;
+            }
+            std::mem::replace(&mut *now, time)
+        };
+
+        for (frequency, sender) in self.subscribers.read().iter() {
+            let frequency_nanos = frequency.as_nanos() as i128;
+            let mut tick = previous.unix_timestamp_nanos() - previous.unix_timestamp_nanos() % frequency_nanos;
+            tick += frequency_nanos;
+            while tick <= time.unix_timestamp_nanos() {
+                let tick_time = OffsetDateTime::from_unix_timestamp_nanos(tick).expect("Invalid tick time");
+                if let Err(e) = sender.send(tick_time) {
+                    debug!("No receivers for simulated time event: {:?}", e);
+                }
+                tick += frequency_nanos;
+            }
+        }
+    }
+
+    /// Jump to a time without emitting ticks, e.g. to start a new replay
+    pub fn reset(&self, time: OffsetDateTime) {
+        *self.now.write() = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let time = self.now() + duration;
+        self.advance_to(time);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.read()
+    }
+
+    fn subscribe(&self, frequency: Duration) -> Receiver<OffsetDateTime> {
+        if let Some(sender) = self.subscribers.read().get(&frequency) {
+            return sender.subscribe();
+        }
+
+        // Simulated time can jump multiple ticks at once so the channel needs some headroom
+        let (sender, receiver) = broadcast::channel(1024);
+        self.subscribers.write().insert(frequency, sender);
+        receiver
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::logging;
 
     use super::*;
+    use time::macros::datetime;
 
     #[tokio::test]
     async fn test_time_component() {
         logging::init_test_tracing();
         info!("Starting time component test...");
         let config = ClockConfig { tick_frequency: 1 };
-        let time_component = Clock::from_config(&config);
+        let time_component = LiveClock::from_config(&config);
         let mut rx_5_1 = time_component.subscribe(Duration::from_secs(5));
         let mut rx_5_2 = time_component.subscribe(Duration::from_secs(5));
         let mut rx_10_1 = time_component.subscribe(Duration::from_secs(10));
@@ -113,4 +240,33 @@ mod tests {
         let ts = rx_10_1.recv().await.unwrap();
         info!("Test received time event: {:?}", ts);
     }
+
+    #[test]
+    fn test_simulated_clock() {
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let clock = SimulatedClock::new(start);
+        let mut rx_5 = clock.subscribe(Duration::from_secs(5));
+
+        clock.advance(Duration::from_secs(12));
+        assert_eq!(clock.now(), datetime!(2024-01-01 00:00:12).assume_utc());
+        assert_eq!(rx_5.try_recv().unwrap(), datetime!(2024-01-01 00:00:05).assume_utc());
+        assert_eq!(rx_5.try_recv().unwrap(), datetime!(2024-01-01 00:00:10).assume_utc());
+        assert!(rx_5.try_recv().is_err());
+
+        // Moving backwards is ignored
+        clock.advance_to(start);
+        assert_eq!(clock.now(), datetime!(2024-01-01 00:00:12).assume_utc());
+    }
+
+    #[test]
+    fn test_server_clock() {
+        let sync = Arc::new(TimeSync::default());
+        let binance = sync.clock(Venue::Binance);
+        let coinbase = sync.clock(Venue::Coinbase);
+        sync.set_offset(&Venue::Binance, time::Duration::seconds(-30));
+        let behind = binance.now();
+        let diff = coinbase.now() - behind;
+        assert!(diff >= time::Duration::seconds(30) && diff < time::Duration::seconds(31));
+        assert_eq!(sync.offset(&Venue::Coinbase), time::Duration::ZERO);
+    }
 }
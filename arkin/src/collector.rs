@@ -0,0 +1,358 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    select,
+    time::{interval, sleep},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    bus::{EventBus, Subscription},
+    clock::{LiveClock, TimeSync},
+    config::{CollectorConfig, GlobalConfig},
+    credentials::CredentialStore,
+    db::DBManager,
+    ingestors::{Ingestor, IngestorFactory},
+    metrics::{self, METRICS},
+    models::{Book, BookUpdate, Candle, DataGap, Event, FundingRate, InstrumentRegistry, Liquidation, Tick, Trade},
+    shutdown::{self, wait_for_signal, Shutdown, ShutdownSignal},
+    skew::{SkewGuard, SkewMonitor},
+};
+
+/// Market data of the bus as one stream of events
+struct MarketFeed {
+    ticks: Subscription<Tick>,
+    trades: Subscription<Trade>,
+    books: Subscription<Book>,
+    book_updates: Subscription<BookUpdate>,
+    funding_rates: Subscription<FundingRate>,
+    liquidations: Subscription<Liquidation>,
+    candles: Subscription<Candle>,
+    data_gaps: Subscription<DataGap>,
+}
+
+impl MarketFeed {
+    fn new(bus: &EventBus) -> Self {
+        MarketFeed {
+            ticks: bus.subscribe(),
+            trades: bus.subscribe(),
+            books: bus.subscribe(),
+            book_updates: bus.subscribe(),
+            funding_rates: bus.subscribe(),
+            liquidations: bus.subscribe(),
+            candles: bus.subscribe(),
+            data_gaps: bus.subscribe(),
+        }
+    }
+
+    async fn next(&mut self) -> Option<Event> {
+        select! {
+            Some(tick) = self.ticks.recv() => Some(Event::Tick(tick)),
+            Some(trade) = self.trades.recv() => Some(Event::Trade(trade)),
+            Some(book) = self.books.recv() => Some(Event::Book(book)),
+            Some(update) = self.book_updates.recv() => Some(Event::BookUpdate(update)),
+            Some(funding) = self.funding_rates.recv() => Some(Event::FundingRate(funding)),
+            Some(liquidation) = self.liquidations.recv() => Some(Event::Liquidation(liquidation)),
+            Some(candle) = self.candles.recv() => Some(Event::Candle(candle)),
+            Some(gap) = self.data_gaps.recv() => Some(Event::DataGap(gap)),
+            else => None,
+        }
+    }
+}
+
+/// Serves the market data of the bus as json lines on a unix socket, every client gets all events
+pub struct FeedServer {
+    bus: Arc<EventBus>,
+    socket: String,
+}
+
+impl FeedServer {
+    pub fn from_config(bus: Arc<EventBus>, config: &CollectorConfig) -> Self {
+        FeedServer {
+            bus,
+            socket: config.socket.clone(),
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        // A socket left behind by a previous run would fail the bind
+        let _ = std::fs::remove_file(&self.socket);
+        let listener = UnixListener::bind(&self.socket)?;
+        info!("Serving market data on {}", self.socket);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            // Subscribed before the task starts so nothing published after the accept is missed
+            let feed = MarketFeed::new(&self.bus);
+            tokio::spawn(serve_client(stream, feed));
+        }
+    }
+}
+
+async fn serve_client(mut stream: UnixStream, mut feed: MarketFeed) {
+    info!("Trading engine connected");
+    METRICS.collector_clients.inc();
+    while let Some(event) = feed.next().await {
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize {}: {}", event.event_type(), e);
+                continue;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = stream.write_all(&line).await {
+            warn!("Trading engine disconnected: {}", e);
+            break;
+        }
+        METRICS.collector_events.with_label_values(&["sent"]).inc();
+    }
+    METRICS.collector_clients.dec();
+}
+
+/// Publishes the events of the collector on the bus of the trading engine, reconnects when the collector restarts
+pub struct FeedClient {
+    bus: Arc<EventBus>,
+    socket: String,
+    reconnect_interval: Duration,
+}
+
+impl FeedClient {
+    pub fn from_config(bus: Arc<EventBus>, config: &CollectorConfig) -> Self {
+        FeedClient {
+            bus,
+            socket: config.socket.clone(),
+            reconnect_interval: Duration::from_secs(config.reconnect_interval),
+        }
+    }
+
+    pub async fn run(self, mut shutdown: ShutdownSignal) {
+        select! {
+            _ = self.follow() => {}
+            _ = shutdown.wait() => info!("Disconnected from the collector"),
+        }
+    }
+
+    /// Only stops with the shutdown
+    async fn follow(&self) {
+        loop {
+            match UnixStream::connect(&self.socket).await {
+                Ok(stream) => {
+                    info!("Connected to the collector on {}", self.socket);
+                    if let Err(e) = self.receive(stream).await {
+                        warn!("Lost the collector: {}", e);
+                    } else {
+                        warn!("Collector closed the connection");
+                    }
+                }
+                Err(e) => warn!("Failed to connect to the collector on {}: {}", self.socket, e),
+            }
+            sleep(self.reconnect_interval).await;
+        }
+    }
+
+    async fn receive(&self, stream: UnixStream) -> Result<()> {
+        let mut lines = BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str::<Event>(&line) {
+                Ok(event) => {
+                    METRICS.collector_events.with_label_values(&["received"]).inc();
+                    self.bus.publish_event(event);
+                }
+                Err(e) => error!("Failed to parse event from the collector: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the market data of the bus to the database in batches
+struct Persister {
+    feed: MarketFeed,
+    db: Arc<DBManager>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl Persister {
+    async fn run(mut self) {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut flush = interval(self.flush_interval);
+        loop {
+            select! {
+                event = self.feed.next() => match event {
+                    Some(event) => {
+                        batch.push(event);
+                        if batch.len() >= self.batch_size {
+                            self.write(&mut batch).await;
+                        }
+                    }
+                    None => break,
+                },
+                _ = flush.tick() => self.write(&mut batch).await,
+            }
+        }
+        self.write(&mut batch).await;
+    }
+
+    async fn write(&self, batch: &mut Vec<Event>) {
+        if batch.is_empty() {
+            return;
+        }
+        match self.db.insert_events_batch(batch).await {
+            Ok(()) => info!("Stored {} market events", batch.len()),
+            Err(e) => error!("Failed to store {} market events: {}", batch.len(), e),
+        }
+        batch.clear();
+    }
+}
+
+/// Runs the ingestors and stores their data apart from the trading engine, so a crash or redeploy of the
+/// strategies doesn't leave a gap in the captured data
+pub struct Collector {
+    bus: Arc<EventBus>,
+    clock: Arc<LiveClock>,
+    credentials: CredentialStore,
+    time_sync: Arc<TimeSync>,
+    config: GlobalConfig,
+}
+
+impl Collector {
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        Collector {
+            bus: Arc::new(EventBus::from_config(&config.bus)),
+            clock: Arc::new(LiveClock::from_config(&config.clock)),
+            credentials: CredentialStore::from_config(&config.credentials),
+            time_sync: Arc::new(TimeSync::default()),
+            config: config.to_owned(),
+        }
+    }
+
+    /// Run until SIGINT or SIGTERM, the last partial batch is written before returning
+    pub async fn run(self) -> Result<()> {
+        let config = &self.config.collector;
+        let metrics_address = config.metrics_address.clone();
+        let mut services = vec![tokio::spawn(async move { metrics::serve(&metrics_address).await })];
+
+        // Stored received times are in server time, the same as in the trading server
+        let skew = SkewMonitor::from_config(
+            self.bus.clone(),
+            Arc::new(SkewGuard::default()),
+            self.time_sync.clone(),
+            &self.config.clock_skew,
+        );
+        services.push(tokio::spawn(skew.run()));
+
+        let server = FeedServer::from_config(self.bus.clone(), config);
+        services.push(tokio::spawn(async move {
+            if let Err(e) = server.run().await {
+                error!("Collector socket failed: {}", e);
+            }
+        }));
+
+        let persister = if config.persist {
+            let persister = Persister {
+                feed: MarketFeed::new(&self.bus),
+                db: Arc::new(DBManager::from_config(&self.config.db).await),
+                batch_size: config.batch_size,
+                flush_interval: Duration::from_secs(config.flush_interval),
+            };
+            Some(tokio::spawn(persister.run()))
+        } else {
+            None
+        };
+
+        let ingestors = IngestorFactory::from_config(
+            self.bus.clone(),
+            self.clock.clone(),
+            Arc::new(InstrumentRegistry::from_config(&self.config.instruments)),
+            &self.credentials,
+            &self.time_sync,
+            &self.config.ingestors,
+        );
+        let ingestor_stop = Shutdown::default();
+        let mut ingestor_tasks = Vec::new();
+        for ingestor in ingestors {
+            info!("Spawning {} ingestor...", ingestor);
+            let signal = ingestor_stop.subscribe();
+            ingestor_tasks.push(tokio::spawn(async move { ingestor.start(signal).await }));
+        }
+
+        wait_for_signal().await;
+        info!("Stopping the collector...");
+        // The sockets are closed and what they received is published before the persister is stopped
+        let limit = Duration::from_secs(self.config.server.shutdown_timeout);
+        let drained = shutdown::phase("draining ingestors", limit, async {
+            ingestor_stop.trigger();
+            join_all(ingestor_tasks.iter_mut()).await;
+        })
+        .await;
+        if drained.is_none() {
+            ingestor_tasks.iter().for_each(|task| task.abort());
+        }
+        for service in &services {
+            service.abort();
+        }
+        join_all(services).await;
+
+        // Dropping the bus closes the feed of the persister, which writes what is left
+        drop(self.bus);
+        if let Some(persister) = persister {
+            let _ = persister.await;
+        }
+        let _ = std::fs::remove_file(&config.socket);
+        info!("Collector stopped");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, test_utils};
+    use time::macros::datetime;
+
+    #[tokio::test]
+    async fn test_feed_over_socket() {
+        let mut config = config::load();
+        config.collector.socket = std::env::temp_dir()
+            .join(format!("aurelion_test_{}.sock", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        config.collector.reconnect_interval = 1;
+
+        let collector_bus = Arc::new(EventBus::from_config(&config.bus));
+        let trading_bus = Arc::new(EventBus::from_config(&config.bus));
+        let mut trades = trading_bus.subscribe::<Trade>();
+        tokio::spawn(FeedServer::from_config(collector_bus.clone(), &config.collector).run());
+        let stop = Shutdown::default();
+        let client = tokio::spawn(FeedClient::from_config(trading_bus, &config.collector).run(stop.subscribe()));
+
+        let instrument = test_utils::test_perp_instrument();
+        let events = test_utils::market_events(&instrument, datetime!(2024-01-01 00:00:00).assume_utc(), 1);
+        let Event::Trade(trade) = events[0].clone() else {
+            panic!("Expected a trade");
+        };
+
+        // Published until the client is connected, earlier events have no subscriber on the collector
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                collector_bus.publish(trade.clone());
+                if let Ok(Some(trade)) = tokio::time::timeout(Duration::from_millis(100), trades.recv()).await {
+                    return trade;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received.trade_id, trade.trade_id);
+        assert!(received.instrument == trade.instrument);
+
+        stop.trigger();
+        tokio::time::timeout(Duration::from_secs(1), client).await.unwrap().unwrap();
+        let _ = std::fs::remove_file(&config.collector.socket);
+    }
+}
@@ -0,0 +1,51 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::SecretSource;
+
+/// Rules are off when their threshold is left out
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertingConfig {
+    /// Seconds the same alert stays muted after it was sent
+    pub cooldown: u64,
+    /// Fills with a larger absolute notional
+    pub fill_notional_above: Option<Decimal>,
+    pub risk_events: bool,
+    /// Seconds without market data before an instrument counts as down
+    pub feed_outage_after: Option<u64>,
+    /// Drop of the realized pnl of a strategy from its peak, in the quote currency
+    pub drawdown_above: Option<Decimal>,
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum NotifierConfig {
+    #[serde(rename = "telegram")]
+    Telegram(TelegramNotifierConfig),
+    #[serde(rename = "slack")]
+    Slack(SlackNotifierConfig),
+    #[serde(rename = "email")]
+    Email(EmailNotifierConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramNotifierConfig {
+    pub bot_token: SecretSource,
+    pub chat_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlackNotifierConfig {
+    /// The url of an incoming webhook holds the token, so it is read like a secret
+    pub webhook_url: SecretSource,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailNotifierConfig {
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub username: SecretSource,
+    pub password: SecretSource,
+    pub from: String,
+    pub to: Vec<String>,
+}
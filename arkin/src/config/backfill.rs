@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use super::BinanceMarket;
+
+/// Historical market data paged from the Binance REST api with the `backfill` command
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackfillConfig {
+    #[serde(default)]
+    pub market: BinanceMarket,
+    pub trades_url: String,
+    pub klines_url: String,
+    /// Rows per request, Binance allows up to 1000 aggregate trades and 1500 klines
+    pub trades_limit: usize,
+    pub klines_limit: usize,
+    /// Request weight of one page as documented by Binance
+    pub trades_weight: u32,
+    pub klines_weight: u32,
+    /// Events inserted into the database per transaction
+    pub batch_size: usize,
+}
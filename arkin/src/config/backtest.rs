@@ -0,0 +1,129 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::SyntheticConfig;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BacktestConfig {
+    /// Seed for every random component, the same seed and data give the same result
+    pub seed: u64,
+    pub frequency: u64,
+    pub capital: Decimal,
+    pub speed: ReplaySpeed,
+    pub lookahead_guard: LookaheadMode,
+    pub sweep: SweepConfig,
+    pub walk_forward: WalkForwardConfig,
+    pub monte_carlo: MonteCarloConfig,
+    pub synthetic: SyntheticConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// As fast as possible
+    #[serde(rename = "max")]
+    Max,
+    /// Paced to the wall clock
+    #[serde(rename = "realtime")]
+    RealTime,
+    /// N times faster than the wall clock
+    #[serde(rename = "multiplier")]
+    Multiplier(f64),
+}
+
+impl ReplaySpeed {
+    /// Wall clock time a replay takes for the elapsed event time, none when it runs as fast as possible
+    pub fn wall_time(&self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            ReplaySpeed::Max => None,
+            ReplaySpeed::RealTime => Some(elapsed),
+            ReplaySpeed::Multiplier(n) => Some(elapsed.div_f64(*n)),
+        }
+    }
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(ReplaySpeed::Max),
+            "realtime" => Ok(ReplaySpeed::RealTime),
+            _ => match s.trim_end_matches('x').parse::<f64>() {
+                Ok(n) if n > 0. => Ok(ReplaySpeed::Multiplier(n)),
+                _ => Err(format!("Invalid replay speed: {}, expected max, realtime or e.g. 10x", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplaySpeed::Max => write!(f, "max"),
+            ReplaySpeed::RealTime => write!(f, "realtime"),
+            ReplaySpeed::Multiplier(n) => write!(f, "{}x", n),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LookaheadMode {
+    #[serde(rename = "off")]
+    Off,
+    /// Log and drop reads from the future
+    #[serde(rename = "warn")]
+    Warn,
+    /// Panic on the first read from the future
+    #[serde(rename = "strict")]
+    Strict,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SweepConfig {
+    pub mode: SweepMode,
+    /// Number of combinations drawn in random mode
+    pub samples: usize,
+    pub parameters: Vec<SweepParameterConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SweepMode {
+    #[serde(rename = "grid")]
+    Grid,
+    #[serde(rename = "random")]
+    Random,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SweepParameterConfig {
+    /// Dot separated path into the global config, list entries are addressed by index
+    pub path: String,
+    pub values: Vec<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WalkForwardConfig {
+    pub in_sample: u64,
+    pub out_of_sample: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonteCarloConfig {
+    pub method: MonteCarloMethod,
+    pub simulations: usize,
+    /// Two sided confidence level of the reported intervals, e.g. 0.95
+    pub confidence: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MonteCarloMethod {
+    /// Draw trades with replacement
+    #[serde(rename = "bootstrap")]
+    Bootstrap,
+    /// Reorder the trades, final pnl stays the same but the path changes
+    #[serde(rename = "shuffle")]
+    Shuffle,
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bus::Topic;
+
+/// Buffer size per topic, subscribers that fall further behind lose the oldest messages
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BusConfig {
+    pub market_data: usize,
+    pub features: usize,
+    pub signals: usize,
+    pub allocations: usize,
+    pub orders: usize,
+    pub fills: usize,
+    /// Order and account reports from the venues
+    pub account: usize,
+    pub risk: usize,
+}
+
+impl BusConfig {
+    pub fn capacity(&self, topic: Topic) -> usize {
+        match topic {
+            Topic::MarketData => self.market_data,
+            Topic::Features => self.features,
+            Topic::Signals => self.signals,
+            Topic::Allocations => self.allocations,
+            Topic::Orders => self.orders,
+            Topic::Fills => self.fills,
+            Topic::Account => self.account,
+            Topic::Risk => self.risk,
+        }
+    }
+}
@@ -1,6 +1,25 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::Venue;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClockConfig {
     pub tick_frequency: u64,
 }
+
+/// Comparison of the local clock with the venues, offsets are in ms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClockSkewConfig {
+    /// Seconds between server time requests
+    pub interval: u64,
+    pub warn_after: u64,
+    /// Orders to a venue are held back while its offset is larger, signed requests would be rejected anyway
+    pub block_after: u64,
+    pub venues: Vec<ServerTimeConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerTimeConfig {
+    pub venue: Venue,
+    pub url: String,
+}
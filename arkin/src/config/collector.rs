@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// Market data capture in its own process, the trading engine reads the events from the socket
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectorConfig {
+    /// Unix socket the collector serves the normalized events on
+    pub socket: String,
+    pub metrics_address: String,
+    /// Seconds the trading engine waits before connecting again
+    pub reconnect_interval: u64,
+    /// Store the market data in the database
+    pub persist: bool,
+    pub batch_size: usize,
+    /// Seconds after which a partial batch is written anyway
+    pub flush_interval: u64,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the key and secret of a named set of credentials are read from, the values never live in the config
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredentialConfig {
+    pub api_key: SecretSource,
+    pub api_secret: SecretSource,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum SecretSource {
+    /// Name of an environment variable
+    #[serde(rename = "env")]
+    Env(String),
+    /// Path of a file holding only the secret, e.g. a mounted docker or kubernetes secret
+    #[serde(rename = "file")]
+    File(String),
+    /// Program and arguments that print the secret, for external secret managers like vault
+    #[serde(rename = "command")]
+    Command(Vec<String>),
+}
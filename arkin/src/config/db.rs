@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
 
+use crate::credentials::Secret;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DatabaseConfig {
     pub host: String,
     pub port: u16,
     pub user: String,
-    pub password: String,
+    pub password: Secret,
     pub database: String,
     pub min_connections: u32,
     pub max_connections: u32,
@@ -0,0 +1,69 @@
+use serde_json::Value;
+
+use super::GlobalConfig;
+
+/// Dot separated paths of every value that differs between two configs, lists that changed length are
+/// reported as a whole
+pub fn changed_paths(old: &GlobalConfig, new: &GlobalConfig) -> Vec<String> {
+    let old = serde_json::to_value(old).expect("Config always serializes");
+    let new = serde_json::to_value(new).expect("Config always serializes");
+    let mut paths = Vec::new();
+    diff(&old, &new, String::new(), &mut paths);
+    paths
+}
+
+fn diff(old: &Value, new: &Value, path: String, paths: &mut Vec<String>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys = old.keys().chain(new.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                match (old.get(key), new.get(key)) {
+                    (Some(o), Some(n)) => diff(o, n, child(key), paths),
+                    _ => paths.push(child(key)),
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (i, (o, n)) in old.iter().zip(new).enumerate() {
+                diff(o, n, child(&i.to_string()), paths);
+            }
+        }
+        (old, new) if old != new => paths.push(path),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, StrategyConfig};
+
+    #[test]
+    fn test_changed_paths() {
+        let old = config::load();
+        let mut new = old.clone();
+        assert!(changed_paths(&old, &new).is_empty());
+
+        new.clock.tick_frequency += 1;
+        let StrategyConfig::Crossover(c) = &mut new.strategy_manager.strategies[0];
+        c.volume_spread_id = "other".into();
+        new.ingestors.clear();
+        assert_eq!(
+            changed_paths(&old, &new),
+            vec![
+                "clock.tick_frequency",
+                "ingestors",
+                "strategy_manager.strategies.0.crossover.volume_spread_id"
+            ]
+        );
+    }
+}
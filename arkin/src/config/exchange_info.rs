@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Venue;
+
+/// Trading rules loaded from the venues at startup, they replace the configured tick and lot sizes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExchangeInfoConfig {
+    /// Seconds between refreshes, venues change their filters without notice
+    pub interval: u64,
+    pub venues: Vec<ExchangeInfoSourceConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExchangeInfoSourceConfig {
+    pub venue: Venue,
+    /// Endpoint listing the instruments with their filters, e.g. the Binance exchangeInfo
+    pub url: String,
+    /// Request weight of the endpoint as documented by the venue
+    pub weight: u32,
+}
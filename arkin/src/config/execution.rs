@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::models::Venue;
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -20,15 +22,21 @@ pub enum ExecutionEndpointConfig {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SimulationConfig {
     pub latency: u64,
+    /// Random extra latency of up to this many ms
+    pub latency_jitter: u64,
     pub commission_maker: Decimal,
     pub commission_taker: Decimal,
     pub max_orders_per_minute: u64,
     pub max_order_size_notional: Decimal,
     pub min_order_size_notional: Decimal,
+    /// Starting balance of the simulated account per asset
+    pub balances: HashMap<String, Decimal>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BinanceExecutionConfig {
+    /// Name of the credentials to trade with
+    pub credentials: String,
     pub max_orders_per_minute: u64,
     pub max_order_size_notional: Decimal,
     pub min_order_size_notional: Decimal,
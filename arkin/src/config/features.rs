@@ -1,14 +1,31 @@
 use serde::{Deserialize, Serialize};
 
-use crate::features::{FeatureId, NodeId};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::features::{Expr, FeatureId, NodeId};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PipelineConfig {
     pub name: String,
     pub frequency: u64,
+    /// Step on the close of the time bars of this interval in seconds instead of on every clock tick
+    pub bar_close: Option<u64>,
+    /// Workers calculating the nodes of a level in parallel, one per core when left out
+    pub threads: Option<usize>,
+    /// Warm-up of nodes that need more than their inputs tell, outputs are only passed on once warm
+    #[serde(default)]
+    pub warm_up: HashMap<NodeId, WarmUpConfig>,
     pub features: Vec<FeatureConfig>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WarmUpConfig {
+    /// Values every input that reads more than the latest one needs
+    pub samples: Option<usize>,
+    /// Seconds the pipeline has to run for the instrument
+    pub lookback: Option<u64>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LatestInputConfig {
     pub from: NodeId,
@@ -43,6 +60,22 @@ pub enum FeatureConfig {
     SMA(SMAFeatureConfig),
     #[serde(rename = "spread")]
     Spread(SpreadFeatureConfig),
+    #[serde(rename = "trade_flow")]
+    TradeFlow(TradeFlowFeatureConfig),
+    #[serde(rename = "ols")]
+    OLS(OLSFeatureConfig),
+    #[serde(rename = "expr")]
+    Expr(ExprFeatureConfig),
+    #[serde(rename = "lag")]
+    Lag(LagFeatureConfig),
+    #[serde(rename = "diff")]
+    Diff(DiffFeatureConfig),
+    #[serde(rename = "percentile_rank")]
+    PercentileRank(PercentileRankFeatureConfig),
+    #[serde(rename = "calendar")]
+    Calendar(CalendarFeatureConfig),
+    #[serde(rename = "basis")]
+    Basis(BasisFeatureConfig),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -72,6 +105,21 @@ pub struct VWAPFeatureConfig {
     pub input_price: WindowInputConfig,
     pub input_quantity: WindowInputConfig,
     pub output: FeatureId,
+    /// Keep running sums per instrument and only read the trades since the previous run
+    #[serde(default)]
+    pub incremental: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TradeFlowFeatureConfig {
+    pub id: NodeId,
+    pub input_price: WindowInputConfig,
+    pub input_quantity: WindowInputConfig,
+    /// Trades of at least this quote notional count as large
+    pub large_notional: f64,
+    pub output_delta: FeatureId,
+    pub output_ratio: FeatureId,
+    pub output_large_trades: FeatureId,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -79,6 +127,108 @@ pub struct SMAFeatureConfig {
     pub id: NodeId,
     pub input: PeriodInputConfig,
     pub output: FeatureId,
+    /// Keep running sums per instrument and only read the values since the previous run
+    #[serde(default)]
+    pub incremental: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OLSFeatureConfig {
+    pub id: NodeId,
+    pub input_y: PeriodInputConfig,
+    /// Fit against the period index when left out
+    pub input_x: Option<PeriodInputConfig>,
+    pub output_slope: FeatureId,
+    pub output_intercept: FeatureId,
+    pub output_r2: FeatureId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExprFeatureConfig {
+    pub id: NodeId,
+    /// Latest value of each input under the name the expression uses for it
+    pub inputs: BTreeMap<String, LatestInputConfig>,
+    pub expression: Expr,
+    pub output: FeatureId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LagFeatureConfig {
+    pub id: NodeId,
+    pub input: LatestInputConfig,
+    pub periods: usize,
+    pub output: FeatureId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMethod {
+    Absolute,
+    /// As a fraction, 0.01 for a rise of 1%
+    Percent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffFeatureConfig {
+    pub id: NodeId,
+    pub input: LatestInputConfig,
+    pub periods: usize,
+    pub method: DiffMethod,
+    pub output: FeatureId,
+}
+
+/// How values equal to the latest one count towards its rank
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RankInterpolation {
+    /// Only the values below it
+    Strict,
+    /// The values below and equal to it
+    Weak,
+    /// The values below it and half of the equal ones
+    Mean,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PercentileRankFeatureConfig {
+    pub id: NodeId,
+    pub input: WindowInputConfig,
+    pub interpolation: RankInterpolation,
+    pub output: FeatureId,
+}
+
+/// Hours in UTC a market session is open, from start up to end, wrapping around midnight when start is later
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionConfig {
+    pub output: FeatureId,
+    pub start: u8,
+    pub end: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarFeatureConfig {
+    pub id: NodeId,
+    /// Seconds between the funding times, counted from midnight UTC
+    pub funding_interval: u64,
+    /// Outputs 1 while the session is open and 0 otherwise
+    pub sessions: Vec<SessionConfig>,
+    pub output_hour_sin: FeatureId,
+    pub output_hour_cos: FeatureId,
+    pub output_day_of_week: FeatureId,
+    pub output_since_funding: FeatureId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BasisFeatureConfig {
+    pub id: NodeId,
+    /// The spot_price of a spot pair or the index_price of the venue
+    pub input_spot: LatestInputConfig,
+    pub input_mark: LatestInputConfig,
+    pub input_funding: LatestInputConfig,
+    /// Seconds between the funding times, the carry is the rate times the fundings in a year
+    pub funding_interval: u64,
+    pub output_basis: FeatureId,
+    pub output_carry: FeatureId,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Thresholds of the `/healthz` and `/readyz` endpoints
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthConfig {
+    /// Seconds without a trading step before the engine counts as hung
+    pub stall_after: u64,
+    /// Seconds without market data from a venue before the engine is not ready
+    pub feed_stale_after: u64,
+    /// Fraction of a topic's buffer that may be waiting for subscribers
+    pub max_bus_backlog: f64,
+    /// Check that the database accepts connections, in ms, leave out to skip the check
+    pub db_timeout: Option<u64>,
+}
@@ -1,11 +1,31 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+use super::ReplaySpeed;
+use crate::{
+    models::{InstrumentType, Venue},
+    sinks::SinkTopic,
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum IngestorConfig {
     #[serde(rename = "backtest")]
     Backtest(BacktestIngestorConfig),
     #[serde(rename = "binance")]
     Binance(BinanceIngestorConfig),
+    #[serde(rename = "binance_depth")]
+    BinanceDepth(BinanceDepthIngestorConfig),
+    #[serde(rename = "coinbase")]
+    Coinbase(CoinbaseIngestorConfig),
+    #[serde(rename = "bybit")]
+    Bybit(BybitIngestorConfig),
+    #[serde(rename = "file")]
+    File(FileIngestorConfig),
+    #[serde(rename = "tardis_csv")]
+    TardisCsv(TardisCsvIngestorConfig),
+    #[serde(rename = "kafka")]
+    Kafka(KafkaIngestorConfig),
     // #[serde(rename = "tardis")]
     // Tardis(TardisIngestorConfig),
 }
@@ -15,14 +35,228 @@ pub struct BacktestIngestorConfig {
     pub market_data: bool,
 }
 
+/// Which Binance market the streams come from, the symbols look the same on spot and futures
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BinanceMarket {
+    Spot,
+    #[default]
+    Swaps,
+    /// European options of the EAPI, symbols like BTC-241025-60000-C
+    Options,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BinanceIngestorConfig {
+    #[serde(default)]
+    pub market: BinanceMarket,
     pub ws_url: String,
     pub ws_channels: Vec<String>,
-    pub api_key: Option<String>,
-    pub api_secret: Option<String>,
+    /// Connects to the combined endpoint with the channels in the url, messages name their stream
+    #[serde(default)]
+    pub combined_streams: bool,
+    /// Name of the credentials to connect with, public streams work without
+    pub credentials: Option<String>,
+    /// Redundant connections per shard, duplicates are dropped
     pub connections_per_manager: usize,
+    /// Channels are sharded over as many connections as needed to stay below this limit
+    pub max_streams_per_connection: usize,
     pub duplicate_lookback: usize,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+    /// Streams the order and account updates of the credentials
+    pub user_data: Option<UserDataConfig>,
+    /// Aggregate trades endpoint the trades missed in a gap of the trade ids are fetched from
+    pub gap_refetch_url: Option<String>,
+}
+
+/// Diff depth streams of Binance, maintained as local order books from the REST snapshot
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BinanceDepthIngestorConfig {
+    #[serde(default)]
+    pub market: BinanceMarket,
+    pub ws_url: String,
+    /// Diff depth channels like btcusdt@depth@100ms
+    pub ws_channels: Vec<String>,
+    /// REST depth endpoint the books are loaded from
+    pub snapshot_url: String,
+    pub snapshot_limit: usize,
+    /// Levels per side published with every update
+    pub depth: usize,
+    pub connections_per_manager: usize,
+    pub max_streams_per_connection: usize,
+    pub duplicate_lookback: usize,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+}
+
+/// Coinbase Exchange feed, every channel is subscribed for every product
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CoinbaseIngestorConfig {
+    pub ws_url: String,
+    pub product_ids: Vec<String>,
+    /// Supported are ticker and matches
+    pub channels: Vec<String>,
+    pub connections_per_manager: usize,
+    pub max_streams_per_connection: usize,
+    pub duplicate_lookback: usize,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+}
+
+/// Bybit v5 public streams of the linear perpetuals
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BybitIngestorConfig {
+    pub ws_url: String,
+    /// Topics like orderbook.1.BTCUSDT, publicTrade.BTCUSDT and tickers.BTCUSDT
+    pub ws_channels: Vec<String>,
+    pub connections_per_manager: usize,
+    pub max_streams_per_connection: usize,
+    pub duplicate_lookback: usize,
+    /// Bybit drops connections without a ping for a while, websocket pings don't count
+    pub ping_interval: u64, // In seconds
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserDataConfig {
+    /// Endpoint to create and extend the listen key
+    pub listen_key_url: String,
+    pub keepalive_interval: u64, // In seconds
+}
+
+/// Bounded queues between the connections, the deduplicator and the parser
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackpressureConfig {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10000,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// What happens to a message arriving at a full queue
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait for room, slowing down the reads from the socket
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+}
+
+/// Replays trades or ticks of one instrument from a csv or parquet file, the columns follow the
+/// `export` command and event times are either formatted timestamps or epoch milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileIngestorConfig {
+    /// Read as parquet when it ends with .parquet, as csv otherwise
+    pub path: String,
+    pub kind: FileDataKind,
+    pub venue: Venue,
+    pub symbol: String,
+    pub instrument_type: InstrumentType,
+    pub speed: ReplaySpeed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDataKind {
+    Trades,
+    Ticks,
+}
+
+/// Normalized market events of capture processes on other hosts, e.g. produced by their kafka sink
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KafkaIngestorConfig {
+    pub brokers: String,
+    /// Consumers of the same group share the partitions
+    pub group_id: String,
+    /// Kafka topic per event topic, only ticks and trades are market data
+    pub topics: HashMap<SinkTopic, String>,
+    #[serde(default)]
+    pub format: KafkaFormat,
+    /// Passed on to librdkafka as is, e.g. `auto.offset.reset`
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaFormat {
+    /// The json of the events as the kafka sink produces it
+    #[default]
+    Json,
+    /// The messages of proto/market.proto, instruments are resolved by their symbol
+    Protobuf,
+}
+
+/// Normalized csv data sets of tardis.dev, the venue and symbol of every row come from the file.
+/// The files are replayed together in the order of their exchange timestamps
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TardisCsvIngestorConfig {
+    pub files: Vec<TardisCsvFileConfig>,
+    /// Levels per side in the book updates of incremental_book_l2 files
+    pub depth: usize,
+    pub speed: ReplaySpeed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TardisCsvFileConfig {
+    /// Decompressed on the fly when it ends with .gz
+    pub path: String,
+    pub data_type: TardisDataType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TardisDataType {
+    Trades,
+    Quotes,
+    IncrementalBookL2,
+}
+
+/// Exponential backoff between reconnects of a websocket connection
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReconnectConfig {
+    pub initial_backoff: u64, // In ms
+    pub max_backoff: u64,     // In ms
+    /// Consecutive failed attempts before the connection gives up, retries forever without
+    pub max_retries: Option<u32>,
+    /// A connection without any message for this long is considered stalled and reconnected
+    pub stale_timeout: u64, // In ms
+    /// Unanswered (un)subscribe requests are sent again after this long
+    pub ack_timeout: u64, // In ms
+    /// Attempts before an unanswered request is reported as failed
+    pub max_subscribe_attempts: u32,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: 500,
+            max_backoff: 30000,
+            max_retries: None,
+            stale_timeout: 10000,
+            ack_timeout: 5000,
+            max_subscribe_attempts: 3,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
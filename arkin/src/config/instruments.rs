@@ -0,0 +1,29 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::models::{InstrumentType, OptionType, Venue};
+
+/// Reference data of an instrument, consulted to round orders and check their size
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstrumentConfig {
+    pub venue: Venue,
+    /// Name on the venue, incoming market data is matched on it
+    pub symbol: String,
+    pub instrument_type: InstrumentType,
+    pub base: String,
+    pub quote: String,
+    /// Futures and options only, e.g. 2024-12-27T08:00:00Z
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub maturity: Option<OffsetDateTime>,
+    /// Options only
+    #[serde(default)]
+    pub strike: Option<Decimal>,
+    #[serde(default)]
+    pub option_type: Option<OptionType>,
+    pub tick_size: Decimal,
+    pub lot_size: Decimal,
+    pub min_notional: Decimal,
+    pub contract_multiplier: Decimal,
+    pub settlement: String,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    pub path: String,
+    /// Sync every entry to disk instead of leaving it to the os, survives power loss but is a lot slower
+    pub sync: bool,
+}
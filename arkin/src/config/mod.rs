@@ -1,67 +1,120 @@
-use config::{Config, Environment, File};
+use config::{Config, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::{collections::HashMap, env};
+use thiserror::Error;
 use tracing::error;
 
+mod alerting;
 mod allocation;
+mod backfill;
+mod backtest;
+mod bus;
 mod clock;
+mod collector;
+mod credentials;
 mod db;
+mod diff;
+mod exchange_info;
 mod execution;
 mod features;
+mod health;
 mod ingestors;
+mod instruments;
+mod journal;
+mod rest;
 mod server;
+mod sinks;
 mod state;
 mod strategy;
+mod synthetic;
+mod validation;
 
+pub use alerting::*;
 pub use allocation::*;
+pub use backfill::*;
+pub use backtest::*;
+pub use bus::*;
 pub use clock::*;
+pub use collector::*;
+pub use credentials::*;
 pub use db::*;
+pub use diff::*;
+pub use exchange_info::*;
 pub use execution::*;
 pub use features::*;
+pub use health::*;
 pub use ingestors::*;
+pub use instruments::*;
+pub use journal::*;
+pub use rest::*;
 pub use server::*;
+pub use sinks::*;
 pub use state::*;
 pub use strategy::*;
+pub use synthetic::*;
+pub use validation::*;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GlobalConfig {
     pub server: ServerConfig,
     pub clock: ClockConfig,
+    pub clock_skew: ClockSkewConfig,
+    pub health: HealthConfig,
+    pub bus: BusConfig,
     pub state: StateConfig,
+    pub journal: JournalConfig,
     pub db: DatabaseConfig,
+    pub backfill: BackfillConfig,
+    pub exchange_info: ExchangeInfoConfig,
+    /// Rate limits of the REST clients shared per venue
+    pub rest: Vec<RestClientConfig>,
+    /// Named venue credentials, referenced by the ingestors and execution endpoints
+    pub credentials: HashMap<String, CredentialConfig>,
     pub ingestors: Vec<IngestorConfig>,
+    pub instruments: Vec<InstrumentConfig>,
+    pub collector: CollectorConfig,
     pub feature_pipeline: PipelineConfig,
     pub analytics_pipeline: PipelineConfig,
     pub strategy_manager: StrategyManagerConfig,
     pub allocation_manager: AllocationManagerConfig,
     pub execution_manager: ExecutionManagerConfig,
+    /// External systems the events are forwarded to
+    pub sinks: Vec<SinkConfig>,
+    pub alerting: AlertingConfig,
+    pub backtest: BacktestConfig,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigLoadError {
+    #[error("Failed to read configuration: {0}")]
+    Read(#[from] ConfigError),
+    #[error(transparent)]
+    Invalid(#[from] ValidationError),
 }
 
 pub fn load() -> GlobalConfig {
+    match try_load() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Configuration error: {}", e);
+            panic!("Failed to load configuration.");
+        }
+    }
+}
+
+/// Load and validate the configuration without panicking, used to reload a running engine
+pub fn try_load() -> Result<GlobalConfig, ConfigLoadError> {
     let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "dev".into());
 
-    let res = Config::builder()
+    let config = Config::builder()
         .add_source(File::with_name("configs/default"))
         .add_source(File::with_name(&format!("configs/{}", run_mode)).required(false))
         .add_source(File::with_name(&format!("configs/{}_secrets", run_mode)).required(false))
         .add_source(Environment::with_prefix("AURELION"))
-        .build();
-
-    let loaded_config = match res {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Configuration error: {:?}", e);
-            panic!("Failed to load configuration.");
-        }
-    };
-
-    match loaded_config.try_deserialize::<GlobalConfig>() {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Configuration error: {:?}", e);
-            panic!("Failed to load configuration.");
-        }
-    }
+        .build()?
+        .try_deserialize::<GlobalConfig>()?;
+    config.validate()?;
+    Ok(config)
 }
 
 #[cfg(test)]
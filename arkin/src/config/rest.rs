@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Venue;
+
+/// Budget of the REST client shared by everything that calls a venue's REST api from this process
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestClientConfig {
+    pub venue: Venue,
+    /// Request weight spent per minute at most, below the ip limit of the venue
+    pub max_weight_per_minute: u32,
+    /// Rate limited requests are retried this often before giving up
+    pub max_retries: u32,
+    /// In ms, signed requests older than this are rejected by the venue
+    pub recv_window: u64,
+}
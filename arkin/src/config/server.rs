@@ -1,6 +1,20 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub name: String,
+    pub metrics_address: String,
+    pub grpc_address: String,
+    pub api_address: String,
+    pub ws_address: String,
+    pub capital: Decimal,
+    /// Seconds between checks of the config files for changes, 0 only reloads on SIGHUP
+    pub config_poll_interval: u64,
+    /// Seconds each step of the shutdown may take before it is abandoned
+    pub shutdown_timeout: u64,
+    /// Where positions, open orders and fills are written on shutdown
+    pub state_snapshot: String,
+    /// Where the trading kill switches are kept across restarts
+    pub trading_flags: String,
 }
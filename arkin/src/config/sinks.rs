@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sinks::SinkTopic;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SinkConfig {
+    #[serde(rename = "redis")]
+    Redis(RedisSinkConfig),
+    #[serde(rename = "kafka")]
+    Kafka(KafkaSinkConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisMode {
+    /// Fire and forget, only subscribers that are connected at the time get the event
+    PubSub,
+    /// Kept in a capped stream so consumers can catch up after a disconnect
+    Stream,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedisSinkConfig {
+    pub url: String,
+    pub mode: RedisMode,
+    /// Channels and streams are named `<prefix>.<topic>`
+    pub prefix: String,
+    pub topics: Vec<SinkTopic>,
+    /// Streams are trimmed to roughly this many entries
+    pub stream_max_len: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KafkaSinkConfig {
+    pub brokers: String,
+    /// Kafka topic per event topic, events without a topic are not sent
+    pub topics: HashMap<SinkTopic, String>,
+    /// Passed on to librdkafka as is, e.g. `compression.type` or `acks`
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
@@ -1,6 +1,64 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StateConfig {
+    /// Seconds of trades in a snapshot of an instrument
     pub window: u64,
+    /// Bars built from the trades, each closed bar is published as a candle
+    pub bars: Vec<BarConfig>,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Seconds of stored market data loaded from the database at startup, 0 starts empty
+    #[serde(default)]
+    pub warm_up: u64,
+    #[serde(default)]
+    pub feature_store: FeatureStoreConfig,
+    /// Seconds between updates of the state metrics, 0 turns them off
+    #[serde(default)]
+    pub metrics_interval: u64,
+}
+
+/// Where the state keeps the feature series
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureStoreConfig {
+    /// Lost on restart and bounded by the retention
+    #[default]
+    Memory,
+    /// Kept in a redb file across restarts
+    Redb(RedbFeatureStoreConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedbFeatureStoreConfig {
+    pub path: String,
+    /// Seconds before the newest value of the series after which values are deleted, at startup and every
+    /// 10000 written values. Kept forever when not set
+    pub ttl: Option<u64>,
+    /// Compact the file at startup to give the space of the deleted values back to the os
+    #[serde(default)]
+    pub compact_on_start: bool,
+}
+
+/// How much market data and features the state keeps per instrument and series, unbounded when not set
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RetentionConfig {
+    /// Seconds before the newest entry of the series
+    pub max_age: Option<u64>,
+    pub max_count: Option<usize>,
+}
+
+/// When a bar closes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BarConfig {
+    /// Every interval in seconds, aligned to the epoch
+    Time(u64),
+    /// After this many trades
+    Tick(u64),
+    /// Once this much base quantity has traded
+    Volume(Decimal),
+    /// Once this much quote notional has traded
+    Dollar(Decimal),
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyntheticConfig {
+    pub start_price: f64,
+    /// Time between book updates in ms
+    pub interval: u64,
+    pub trades_per_second: f64,
+    pub avg_trade_quantity: f64,
+    pub avg_book_quantity: f64,
+    /// Average bid ask spread in basis points
+    pub spread_bps: f64,
+    pub process: PriceProcessConfig,
+}
+
+/// Volatility and drift are per second in log returns
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum PriceProcessConfig {
+    #[serde(rename = "random_walk")]
+    RandomWalk(RandomWalkConfig),
+    #[serde(rename = "trending")]
+    Trending(TrendingConfig),
+    #[serde(rename = "mean_reverting")]
+    MeanReverting(MeanRevertingConfig),
+    #[serde(rename = "regime_switching")]
+    RegimeSwitching(RegimeSwitchingConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RandomWalkConfig {
+    pub volatility: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendingConfig {
+    pub drift: f64,
+    pub volatility: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MeanRevertingConfig {
+    pub mean: f64,
+    /// Share of the distance to the mean that is closed per second
+    pub speed: f64,
+    pub volatility: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegimeSwitchingConfig {
+    /// Chance per second to move to another regime
+    pub switch_probability: f64,
+    pub regimes: Vec<PriceProcessConfig>,
+}
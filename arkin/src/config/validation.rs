@@ -0,0 +1,991 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use petgraph::{algo::tarjan_scc, graphmap::DiGraphMap};
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+use crate::{
+    constants::BASE_IDS,
+    features::FeatureId,
+    models::{InstrumentType, Venue},
+    sinks::SinkTopic,
+};
+
+use super::{
+    AllocationConfig, BackpressureConfig, BarConfig, BinanceMarket, ExecutionEndpointConfig, FeatureConfig,
+    FeatureStoreConfig, GlobalConfig, IngestorConfig, LatestInputConfig, NotifierConfig, PeriodInputConfig,
+    PipelineConfig, ReconnectConfig, RedisMode, ReplaySpeed, SinkConfig, StrategyConfig, WindowInputConfig,
+};
+
+/// A problem in the config, the path points into the yaml in the same format as the sweep parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Error, Debug)]
+pub struct ValidationError(pub Vec<ConfigIssue>);
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} problem(s) in the configuration", self.0.len())?;
+        for issue in &self.0 {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// An input of a feature with the amount of data it reads, if any
+struct Input<'a> {
+    field: String,
+    from: &'a str,
+    feature_id: &'a FeatureId,
+    amount: Option<(&'static str, u64)>,
+}
+
+impl<'a> Input<'a> {
+    fn latest(field: impl Into<String>, input: &'a LatestInputConfig) -> Self {
+        Input {
+            field: field.into(),
+            from: &input.from,
+            feature_id: &input.feature_id,
+            amount: None,
+        }
+    }
+
+    fn window(field: &'static str, input: &'a WindowInputConfig) -> Self {
+        Input {
+            field: field.into(),
+            from: &input.from,
+            feature_id: &input.feature_id,
+            amount: Some(("window", input.window)),
+        }
+    }
+
+    fn periods(field: &'static str, input: &'a PeriodInputConfig) -> Self {
+        Input {
+            field: field.into(),
+            from: &input.from,
+            feature_id: &input.feature_id,
+            amount: Some(("periods", input.periods as u64)),
+        }
+    }
+}
+
+/// Config key, node id, inputs and outputs of a feature
+fn describe(feature: &FeatureConfig) -> (&'static str, &str, Vec<Input<'_>>, Vec<&FeatureId>) {
+    match feature {
+        FeatureConfig::Count(c) => ("count", &c.id, vec![Input::window("input", &c.input)], vec![&c.output]),
+        FeatureConfig::Sum(c) => ("sum", &c.id, vec![Input::window("input", &c.input)], vec![&c.output]),
+        FeatureConfig::Mean(c) => ("mean", &c.id, vec![Input::window("input", &c.input)], vec![&c.output]),
+        FeatureConfig::VWAP(c) => (
+            "vwap",
+            &c.id,
+            vec![
+                Input::window("input_price", &c.input_price),
+                Input::window("input_quantity", &c.input_quantity),
+            ],
+            vec![&c.output],
+        ),
+        FeatureConfig::SMA(c) => ("sma", &c.id, vec![Input::periods("input", &c.input)], vec![&c.output]),
+        FeatureConfig::Spread(c) => (
+            "spread",
+            &c.id,
+            vec![
+                Input::latest("input_front", &c.input_front),
+                Input::latest("input_back", &c.input_back),
+            ],
+            vec![&c.output],
+        ),
+        FeatureConfig::TradeFlow(c) => (
+            "trade_flow",
+            &c.id,
+            vec![
+                Input::window("input_price", &c.input_price),
+                Input::window("input_quantity", &c.input_quantity),
+            ],
+            vec![&c.output_delta, &c.output_ratio, &c.output_large_trades],
+        ),
+        FeatureConfig::OLS(c) => (
+            "ols",
+            &c.id,
+            std::iter::once(Input::periods("input_y", &c.input_y))
+                .chain(c.input_x.iter().map(|x| Input::periods("input_x", x)))
+                .collect(),
+            vec![&c.output_slope, &c.output_intercept, &c.output_r2],
+        ),
+        FeatureConfig::Expr(c) => (
+            "expr",
+            &c.id,
+            c.inputs
+                .iter()
+                .map(|(name, input)| Input::latest(format!("inputs.{}", name), input))
+                .collect(),
+            vec![&c.output],
+        ),
+        FeatureConfig::Lag(c) => ("lag", &c.id, vec![Input::latest("input", &c.input)], vec![&c.output]),
+        FeatureConfig::Diff(c) => ("diff", &c.id, vec![Input::latest("input", &c.input)], vec![&c.output]),
+        FeatureConfig::PercentileRank(c) => (
+            "percentile_rank",
+            &c.id,
+            vec![Input::window("input", &c.input)],
+            vec![&c.output],
+        ),
+        FeatureConfig::Calendar(c) => (
+            "calendar",
+            &c.id,
+            Vec::new(),
+            [
+                &c.output_hour_sin,
+                &c.output_hour_cos,
+                &c.output_day_of_week,
+                &c.output_since_funding,
+            ]
+            .into_iter()
+            .chain(c.sessions.iter().map(|s| &s.output))
+            .collect(),
+        ),
+        FeatureConfig::Basis(c) => (
+            "basis",
+            &c.id,
+            vec![
+                Input::latest("input_spot", &c.input_spot),
+                Input::latest("input_mark", &c.input_mark),
+                Input::latest("input_funding", &c.input_funding),
+            ],
+            vec![&c.output_basis, &c.output_carry],
+        ),
+    }
+}
+
+/// Shortest way around a cycle from the node back to it, the node is at both ends
+fn cycle_path<'a>(graph: &DiGraphMap<&'a str, ()>, start: &'a str) -> Vec<&'a str> {
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors(node) {
+            if next == start {
+                let mut path = vec![start, node];
+                let mut current = node;
+                while current != start {
+                    current = parents[current];
+                    path.push(current);
+                }
+                path.reverse();
+                return path;
+            }
+            if !parents.contains_key(next) {
+                parents.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    vec![start]
+}
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<ConfigIssue>,
+}
+
+impl Validator {
+    fn issue(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ConfigIssue {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn positive(&mut self, path: &str, value: u64) {
+        if value == 0 {
+            self.issue(path, "must be greater than 0");
+        }
+    }
+
+    /// Pacing divides by the multiplier, config files skip the parsing of the command line
+    fn speed(&mut self, path: &str, speed: &ReplaySpeed) {
+        if let ReplaySpeed::Multiplier(n) = speed {
+            if !n.is_finite() || *n <= 0. {
+                self.issue(path, "multiplier must be finite and greater than 0");
+            }
+        }
+    }
+
+    fn websocket(
+        &mut self,
+        path: &str,
+        connections: usize,
+        max_streams: usize,
+        reconnect: &ReconnectConfig,
+        backpressure: &BackpressureConfig,
+    ) {
+        self.positive(&format!("{}.connections_per_manager", path), connections as u64);
+        self.positive(&format!("{}.max_streams_per_connection", path), max_streams as u64);
+        self.positive(&format!("{}.reconnect.initial_backoff", path), reconnect.initial_backoff);
+        self.positive(&format!("{}.reconnect.stale_timeout", path), reconnect.stale_timeout);
+        self.positive(&format!("{}.reconnect.ack_timeout", path), reconnect.ack_timeout);
+        self.positive(
+            &format!("{}.reconnect.max_subscribe_attempts", path),
+            reconnect.max_subscribe_attempts.into(),
+        );
+        if reconnect.max_backoff < reconnect.initial_backoff {
+            self.issue(format!("{}.reconnect.max_backoff", path), "smaller than the initial backoff");
+        }
+        self.positive(&format!("{}.backpressure.capacity", path), backpressure.capacity as u64);
+    }
+
+    fn credentials(&mut self, config: &GlobalConfig, path: &str, name: &str) {
+        if !config.credentials.contains_key(name) {
+            self.issue(path, format!("unknown credentials '{}'", name));
+        }
+    }
+
+    /// Returns the outputs of the pipeline so strategies can be checked against them
+    fn pipeline(&mut self, path: &str, config: &PipelineConfig) -> HashSet<FeatureId> {
+        self.positive(&format!("{}.frequency", path), config.frequency);
+        if config.bar_close == Some(0) {
+            self.issue(format!("{}.bar_close", path), "must be greater than 0");
+        }
+        if config.threads == Some(0) {
+            self.issue(format!("{}.threads", path), "must be greater than 0");
+        }
+        for id in config.warm_up.keys() {
+            if !config.features.iter().any(|f| describe(f).1 == id) {
+                self.issue(format!("{}.warm_up.{}", path, id), format!("unknown node '{}'", id));
+            }
+        }
+
+        let features = config.features.iter().map(describe).collect::<Vec<_>>();
+        let mut ids = HashSet::new();
+        for (i, (key, id, _, _)) in features.iter().enumerate() {
+            if !ids.insert(*id) {
+                self.issue(
+                    format!("{}.features.{}.{}.id", path, i, key),
+                    format!("duplicate node id '{}'", id),
+                );
+            }
+        }
+
+        let mut graph = DiGraphMap::<&str, ()>::new();
+        for (i, (key, id, inputs, outputs)) in features.iter().enumerate() {
+            graph.add_node(id);
+            // The data of a node is keyed by feature id, a second read of the same id would overwrite the first
+            let mut read = HashMap::new();
+            for input in inputs {
+                let input_path = format!("{}.features.{}.{}.{}", path, i, key, input.field);
+                if let Some(field) = read.insert(input.feature_id, &input.field) {
+                    self.issue(
+                        format!("{}.feature_id", input_path),
+                        format!("feature id '{}' is already read by {}", input.feature_id, field),
+                    );
+                }
+                let known_ids = match input.from {
+                    "base" => BASE_IDS.iter().collect::<Vec<_>>(),
+                    "self" => outputs.clone(),
+                    from => match features.iter().find(|(_, id, _, _)| *id == from) {
+                        Some((_, _, _, source_outputs)) => {
+                            graph.add_edge(from, id, ());
+                            source_outputs.clone()
+                        }
+                        None => {
+                            self.issue(format!("{}.from", input_path), format!("unknown source node '{}'", from));
+                            continue;
+                        }
+                    },
+                };
+                if !known_ids.contains(&input.feature_id) {
+                    let known = known_ids.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(", ");
+                    self.issue(
+                        format!("{}.feature_id", input_path),
+                        format!(
+                            "unknown feature id '{}', '{}' provides: {}",
+                            input.feature_id, input.from, known
+                        ),
+                    );
+                }
+                if let Some((field, amount)) = input.amount {
+                    self.positive(&format!("{}.{}", input_path, field), amount);
+                }
+            }
+
+            // Price and quantity have to cover the same trades
+            let windows = inputs.iter().filter_map(|i| i.amount).map(|(_, a)| a).collect::<HashSet<_>>();
+            if windows.len() > 1 {
+                self.issue(format!("{}.features.{}.{}", path, i, key), "inputs use different windows");
+            }
+        }
+
+        for (i, feature) in config.features.iter().enumerate() {
+            match feature {
+                FeatureConfig::TradeFlow(c) if c.large_notional <= 0. => {
+                    self.issue(
+                        format!("{}.features.{}.trade_flow.large_notional", path, i),
+                        "must be greater than 0",
+                    );
+                }
+                FeatureConfig::Expr(c) => {
+                    let variables = c.expression.variables();
+                    for name in variables.iter().filter(|v| !c.inputs.contains_key(**v)) {
+                        self.issue(
+                            format!("{}.features.{}.expr.expression", path, i),
+                            format!("'{}' is not one of the inputs", name),
+                        );
+                    }
+                    for name in c.inputs.keys().filter(|n| !variables.contains(n.as_str())) {
+                        self.issue(
+                            format!("{}.features.{}.expr.inputs.{}", path, i, name),
+                            "not used in the expression",
+                        );
+                    }
+                }
+                FeatureConfig::Lag(c) => {
+                    self.positive(&format!("{}.features.{}.lag.periods", path, i), c.periods as u64)
+                }
+                FeatureConfig::Diff(c) => {
+                    self.positive(&format!("{}.features.{}.diff.periods", path, i), c.periods as u64)
+                }
+                FeatureConfig::Calendar(c) => {
+                    let calendar_path = format!("{}.features.{}.calendar", path, i);
+                    self.positive(&format!("{}.funding_interval", calendar_path), c.funding_interval);
+                    for (j, session) in c.sessions.iter().enumerate() {
+                        for (field, hour) in [("start", session.start), ("end", session.end)] {
+                            if hour > 24 {
+                                self.issue(format!("{}.sessions.{}.{}", calendar_path, j, field), "must be at most 24");
+                            }
+                        }
+                    }
+                }
+                FeatureConfig::Basis(c) => {
+                    self.positive(&format!("{}.features.{}.basis.funding_interval", path, i), c.funding_interval)
+                }
+                _ => {}
+            }
+        }
+
+        for component in tarjan_scc(&graph) {
+            let start = component[0];
+            if component.len() > 1 || graph.contains_edge(start, start) {
+                self.issue(
+                    format!("{}.features", path),
+                    format!("cycle {}", cycle_path(&graph, start).join(" -> ")),
+                );
+            }
+        }
+
+        features.into_iter().flat_map(|(_, _, _, outputs)| outputs).cloned().collect()
+    }
+
+    fn validate(&mut self, config: &GlobalConfig) {
+        self.positive("clock.tick_frequency", config.clock.tick_frequency);
+        self.positive("state.window", config.state.window);
+        if let Some(max_age) = config.state.retention.max_age {
+            self.positive("state.retention.max_age", max_age);
+        }
+        if let Some(max_count) = config.state.retention.max_count {
+            self.positive("state.retention.max_count", max_count as u64);
+        }
+        if let FeatureStoreConfig::Redb(store) = &config.state.feature_store {
+            if store.path.is_empty() {
+                self.issue("state.feature_store.redb.path", "must not be empty");
+            }
+            if let Some(ttl) = store.ttl {
+                self.positive("state.feature_store.redb.ttl", ttl);
+            }
+        }
+        for (i, bar) in config.state.bars.iter().enumerate() {
+            let positive = match bar {
+                BarConfig::Time(interval) => *interval > 0,
+                BarConfig::Tick(trades) => *trades > 0,
+                BarConfig::Volume(threshold) | BarConfig::Dollar(threshold) => *threshold > Decimal::ZERO,
+            };
+            if !positive {
+                self.issue(format!("state.bars.{}", i), "must be greater than 0");
+            }
+        }
+        self.positive("health.stall_after", config.health.stall_after);
+        self.positive("health.feed_stale_after", config.health.feed_stale_after);
+        if !(0. ..=1.).contains(&config.health.max_bus_backlog) {
+            self.issue("health.max_bus_backlog", "must be between 0 and 1");
+        }
+        self.positive("clock_skew.interval", config.clock_skew.interval);
+        if config.clock_skew.warn_after > config.clock_skew.block_after {
+            self.issue("clock_skew.warn_after", "larger than clock_skew.block_after");
+        }
+        for (i, venue) in config.clock_skew.venues.iter().enumerate() {
+            if venue.url.is_empty() {
+                self.issue(format!("clock_skew.venues.{}.url", i), "missing server time url");
+            }
+        }
+        for (i, rest) in config.rest.iter().enumerate() {
+            self.positive(&format!("rest.{}.max_weight_per_minute", i), rest.max_weight_per_minute as u64);
+            if config.rest[..i].iter().any(|r| r.venue == rest.venue) {
+                self.issue(format!("rest.{}.venue", i), "duplicate rest client of the venue");
+            }
+        }
+        let has_rest = |venue: &Venue| config.rest.iter().any(|r| &r.venue == venue);
+        self.positive("exchange_info.interval", config.exchange_info.interval);
+        for (i, venue) in config.exchange_info.venues.iter().enumerate() {
+            if venue.url.is_empty() {
+                self.issue(format!("exchange_info.venues.{}.url", i), "missing exchange info url");
+            }
+            if !has_rest(&venue.venue) {
+                self.issue(format!("exchange_info.venues.{}.venue", i), "no rest client of the venue");
+            }
+        }
+        for (i, endpoint) in config.execution_manager.endpoints.iter().enumerate() {
+            if let ExecutionEndpointConfig::Binance(c) = endpoint {
+                let path = format!("execution_manager.endpoints.{}", i);
+                if !has_rest(&Venue::Binance) {
+                    self.issue(path.clone(), "no rest client of the venue");
+                }
+                self.credentials(config, &format!("{}.credentials", path), &c.credentials);
+            }
+        }
+        if config.journal.enabled && config.journal.path.is_empty() {
+            self.issue("journal.path", "missing path of the enabled journal");
+        }
+        if !(1..=1000).contains(&config.backfill.trades_limit) {
+            self.issue("backfill.trades_limit", "must be between 1 and 1000");
+        }
+        if !(1..=1500).contains(&config.backfill.klines_limit) {
+            self.issue("backfill.klines_limit", "must be between 1 and 1500");
+        }
+        self.positive("backfill.batch_size", config.backfill.batch_size as u64);
+        if config.backfill.market == BinanceMarket::Options {
+            self.issue("backfill.market", "no aggregate trades on the options market");
+        }
+        match config.rest.iter().find(|r| r.venue == Venue::Binance) {
+            Some(rest) => {
+                for (field, weight) in [
+                    ("trades_weight", config.backfill.trades_weight),
+                    ("klines_weight", config.backfill.klines_weight),
+                ] {
+                    if weight > rest.max_weight_per_minute {
+                        self.issue(
+                            format!("backfill.{}", field),
+                            "larger than rest max_weight_per_minute of binance",
+                        );
+                    }
+                }
+            }
+            None => self.issue("rest", "no rest client of binance for the backfill"),
+        }
+        for (topic, capacity) in [
+            ("market_data", config.bus.market_data),
+            ("features", config.bus.features),
+            ("signals", config.bus.signals),
+            ("allocations", config.bus.allocations),
+            ("orders", config.bus.orders),
+            ("fills", config.bus.fills),
+            ("account", config.bus.account),
+            ("risk", config.bus.risk),
+        ] {
+            self.positive(&format!("bus.{}", topic), capacity as u64);
+        }
+
+        for (i, ingestor) in config.ingestors.iter().enumerate() {
+            match ingestor {
+                IngestorConfig::Binance(c) => {
+                    let path = format!("ingestors.{}.binance", i);
+                    if c.ws_url.is_empty() {
+                        self.issue(format!("{}.ws_url", path), "missing websocket url");
+                    }
+                    if c.ws_channels.is_empty() {
+                        self.issue(format!("{}.ws_channels", path), "no channels to subscribe to");
+                    }
+                    self.websocket(
+                        &path,
+                        c.connections_per_manager,
+                        c.max_streams_per_connection,
+                        &c.reconnect,
+                        &c.backpressure,
+                    );
+                    if let Some(name) = &c.credentials {
+                        self.credentials(config, &format!("{}.credentials", path), name);
+                    }
+                    if let Some(user_data) = &c.user_data {
+                        let path = format!("{}.user_data", path);
+                        if c.credentials.is_none() {
+                            self.issue(path.clone(), "needs credentials for the listen key");
+                        }
+                        if c.market != BinanceMarket::Swaps {
+                            self.issue(path.clone(), "only supported on the swaps market");
+                        }
+                        self.positive(&format!("{}.keepalive_interval", path), user_data.keepalive_interval);
+                    }
+                }
+                IngestorConfig::BinanceDepth(c) => {
+                    let path = format!("ingestors.{}.binance_depth", i);
+                    if c.ws_url.is_empty() {
+                        self.issue(format!("{}.ws_url", path), "missing websocket url");
+                    }
+                    if c.snapshot_url.is_empty() {
+                        self.issue(format!("{}.snapshot_url", path), "missing snapshot url");
+                    }
+                    if c.market == BinanceMarket::Options {
+                        self.issue(
+                            format!("{}.market", path),
+                            "options stream books, use the depth channels of binance",
+                        );
+                    }
+                    if c.ws_channels.is_empty() {
+                        self.issue(format!("{}.ws_channels", path), "no channels to subscribe to");
+                    }
+                    for (j, channel) in c.ws_channels.iter().enumerate() {
+                        // Partial depth channels like @depth20 send snapshots, not diffs
+                        let diff = channel
+                            .split_once("@depth")
+                            .is_some_and(|(_, speed)| speed.is_empty() || speed.starts_with('@'));
+                        if !diff {
+                            self.issue(format!("{}.ws_channels.{}", path, j), "not a diff depth channel");
+                        }
+                    }
+                    self.positive(&format!("{}.snapshot_limit", path), c.snapshot_limit as u64);
+                    self.positive(&format!("{}.depth", path), c.depth as u64);
+                    if c.depth > c.snapshot_limit {
+                        self.issue(format!("{}.depth", path), "deeper than the snapshot");
+                    }
+                    self.websocket(
+                        &path,
+                        c.connections_per_manager,
+                        c.max_streams_per_connection,
+                        &c.reconnect,
+                        &c.backpressure,
+                    );
+                }
+                IngestorConfig::Coinbase(c) => {
+                    let path = format!("ingestors.{}.coinbase", i);
+                    if c.ws_url.is_empty() {
+                        self.issue(format!("{}.ws_url", path), "missing websocket url");
+                    }
+                    if c.product_ids.is_empty() {
+                        self.issue(format!("{}.product_ids", path), "no products to subscribe to");
+                    }
+                    if c.channels.is_empty() {
+                        self.issue(format!("{}.channels", path), "no channels to subscribe to");
+                    }
+                    self.websocket(
+                        &path,
+                        c.connections_per_manager,
+                        c.max_streams_per_connection,
+                        &c.reconnect,
+                        &c.backpressure,
+                    );
+                }
+                IngestorConfig::Bybit(c) => {
+                    let path = format!("ingestors.{}.bybit", i);
+                    if c.ws_url.is_empty() {
+                        self.issue(format!("{}.ws_url", path), "missing websocket url");
+                    }
+                    if c.ws_channels.is_empty() {
+                        self.issue(format!("{}.ws_channels", path), "no channels to subscribe to");
+                    }
+                    for (j, channel) in c.ws_channels.iter().enumerate() {
+                        if !["orderbook.1.", "publicTrade.", "tickers."]
+                            .iter()
+                            .any(|t| channel.starts_with(t))
+                        {
+                            self.issue(
+                                format!("{}.ws_channels.{}", path, j),
+                                "only orderbook.1, publicTrade and tickers are supported",
+                            );
+                        }
+                    }
+                    self.positive(&format!("{}.ping_interval", path), c.ping_interval);
+                    self.websocket(
+                        &path,
+                        c.connections_per_manager,
+                        c.max_streams_per_connection,
+                        &c.reconnect,
+                        &c.backpressure,
+                    );
+                }
+                IngestorConfig::Kafka(c) => {
+                    let path = format!("ingestors.{}.kafka", i);
+                    if c.brokers.is_empty() {
+                        self.issue(format!("{}.brokers", path), "missing kafka brokers");
+                    }
+                    if c.group_id.is_empty() {
+                        self.issue(format!("{}.group_id", path), "missing kafka consumer group");
+                    }
+                    if c.topics.is_empty() {
+                        self.issue(format!("{}.topics", path), "no topics to consume");
+                    }
+                    for (topic, name) in &c.topics {
+                        if name.is_empty() {
+                            self.issue(format!("{}.topics.{}", path, topic), "missing kafka topic name");
+                        }
+                        if !matches!(topic, SinkTopic::Ticks | SinkTopic::Trades) {
+                            self.issue(format!("{}.topics.{}", path, topic), "only ticks and trades are market data");
+                        }
+                    }
+                }
+                IngestorConfig::TardisCsv(c) => {
+                    let path = format!("ingestors.{}.tardis_csv", i);
+                    if c.files.is_empty() {
+                        self.issue(format!("{}.files", path), "no files to replay");
+                    }
+                    for (j, file) in c.files.iter().enumerate() {
+                        if file.path.is_empty() {
+                            self.issue(format!("{}.files.{}.path", path, j), "missing file to replay");
+                        }
+                    }
+                    self.positive(&format!("{}.depth", path), c.depth as u64);
+                    self.speed(&format!("{}.speed", path), &c.speed);
+                }
+                IngestorConfig::File(c) => {
+                    let path = format!("ingestors.{}.file", i);
+                    if c.path.is_empty() {
+                        self.issue(format!("{}.path", path), "missing file to replay");
+                    }
+                    let known = config
+                        .instruments
+                        .iter()
+                        .any(|i| i.venue == c.venue && i.symbol == c.symbol && i.instrument_type == c.instrument_type);
+                    if !known {
+                        self.issue(format!("{}.symbol", path), format!("unknown instrument {}", c.symbol));
+                    }
+                    self.speed(&format!("{}.speed", path), &c.speed);
+                }
+                _ => {}
+            }
+        }
+
+        for (i, instrument) in config.instruments.iter().enumerate() {
+            let path = format!("instruments.{}", i);
+            if instrument.symbol.is_empty() {
+                self.issue(format!("{}.symbol", path), "missing symbol");
+            }
+            let dated = matches!(instrument.instrument_type, InstrumentType::Future | InstrumentType::Option);
+            if dated != instrument.maturity.is_some() {
+                let issue = if dated {
+                    "missing maturity"
+                } else {
+                    "only futures and options have a maturity"
+                };
+                self.issue(format!("{}.maturity", path), issue);
+            }
+            let option = instrument.instrument_type == InstrumentType::Option;
+            for (field, present) in [
+                ("strike", instrument.strike.is_some()),
+                ("option_type", instrument.option_type.is_some()),
+            ] {
+                if option != present {
+                    let issue = if option {
+                        "missing for an option"
+                    } else {
+                        "only options have one"
+                    };
+                    self.issue(format!("{}.{}", path, field), issue);
+                }
+            }
+            if instrument.strike.is_some_and(|s| s <= Decimal::ZERO) {
+                self.issue(format!("{}.strike", path), "must be greater than 0");
+            }
+            for (field, value) in [
+                ("tick_size", instrument.tick_size),
+                ("lot_size", instrument.lot_size),
+                ("contract_multiplier", instrument.contract_multiplier),
+            ] {
+                if value <= Decimal::ZERO {
+                    self.issue(format!("{}.{}", path, field), "must be greater than 0");
+                }
+            }
+        }
+
+        if config.collector.socket.is_empty() {
+            self.issue("collector.socket", "missing socket path");
+        }
+        self.positive("collector.reconnect_interval", config.collector.reconnect_interval);
+        if config.collector.persist {
+            self.positive("collector.batch_size", config.collector.batch_size as u64);
+            self.positive("collector.flush_interval", config.collector.flush_interval);
+        }
+
+        let features = self.pipeline("feature_pipeline", &config.feature_pipeline);
+        self.pipeline("analytics_pipeline", &config.analytics_pipeline);
+
+        let mut strategies = HashSet::new();
+        for (i, strategy) in config.strategy_manager.strategies.iter().enumerate() {
+            match strategy {
+                StrategyConfig::Crossover(c) => {
+                    let path = format!("strategy_manager.strategies.{}.crossover", i);
+                    if !strategies.insert(c.id.clone()) {
+                        self.issue(format!("{}.id", path), format!("duplicate strategy id '{}'", c.id));
+                    }
+                    for (field, source) in [
+                        ("price_spread_id", &c.price_spread_id),
+                        ("volume_spread_id", &c.volume_spread_id),
+                    ] {
+                        if !features.contains(source) {
+                            self.issue(
+                                format!("{}.{}", path, field),
+                                format!("feature '{}' is not produced by the feature pipeline", source),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        for (i, allocation) in config.allocation_manager.allocations.iter().enumerate() {
+            let AllocationConfig::Equal(c) = allocation;
+            for (j, strategy) in c.strategies.iter().enumerate() {
+                if !strategies.contains(strategy) {
+                    self.issue(
+                        format!("allocation_manager.allocations.{}.equal.strategies.{}", i, j),
+                        format!("unknown strategy '{}'", strategy),
+                    );
+                }
+            }
+        }
+
+        let venues = config
+            .execution_manager
+            .endpoints
+            .iter()
+            .map(|e| match e {
+                ExecutionEndpointConfig::Simulation(_) => Venue::Simulation,
+                ExecutionEndpointConfig::Binance(_) => Venue::Binance,
+            })
+            .collect::<Vec<_>>();
+        if !venues.contains(&config.execution_manager.default_endpoint) {
+            self.issue(
+                "execution_manager.default_endpoint",
+                format!(
+                    "no endpoint configured for venue '{}'",
+                    config.execution_manager.default_endpoint
+                ),
+            );
+        }
+        for (i, endpoint) in config.execution_manager.endpoints.iter().enumerate() {
+            let (key, min, max) = match endpoint {
+                ExecutionEndpointConfig::Simulation(c) => {
+                    ("simulation", c.min_order_size_notional, c.max_order_size_notional)
+                }
+                ExecutionEndpointConfig::Binance(c) => {
+                    ("binance", c.min_order_size_notional, c.max_order_size_notional)
+                }
+            };
+            if let ExecutionEndpointConfig::Binance(c) = endpoint {
+                self.credentials(
+                    config,
+                    &format!("execution_manager.endpoints.{}.binance.credentials", i),
+                    &c.credentials,
+                );
+            }
+            if min > max {
+                self.issue(
+                    format!("execution_manager.endpoints.{}.{}.min_order_size_notional", i, key),
+                    format!("larger than max_order_size_notional ({} > {})", min, max),
+                );
+            }
+        }
+
+        for (i, sink) in config.sinks.iter().enumerate() {
+            match sink {
+                SinkConfig::Redis(c) => {
+                    let path = format!("sinks.{}.redis", i);
+                    if c.url.is_empty() {
+                        self.issue(format!("{}.url", path), "missing redis url");
+                    }
+                    if c.topics.is_empty() {
+                        self.issue(format!("{}.topics", path), "no topics to publish");
+                    }
+                    if c.mode == RedisMode::Stream {
+                        self.positive(&format!("{}.stream_max_len", path), c.stream_max_len as u64);
+                    }
+                }
+                SinkConfig::Kafka(c) => {
+                    let path = format!("sinks.{}.kafka", i);
+                    if c.brokers.is_empty() {
+                        self.issue(format!("{}.brokers", path), "missing kafka brokers");
+                    }
+                    if c.topics.is_empty() {
+                        self.issue(format!("{}.topics", path), "no topics to produce");
+                    }
+                    for (topic, name) in &c.topics {
+                        if name.is_empty() {
+                            self.issue(format!("{}.topics.{}", path, topic), "missing kafka topic name");
+                        }
+                    }
+                }
+            }
+        }
+
+        for (i, notifier) in config.alerting.notifiers.iter().enumerate() {
+            let path = format!("alerting.notifiers.{}", i);
+            match notifier {
+                NotifierConfig::Telegram(c) if c.chat_id.is_empty() => {
+                    self.issue(format!("{}.telegram.chat_id", path), "missing chat id")
+                }
+                NotifierConfig::Email(c) if c.to.is_empty() => {
+                    self.issue(format!("{}.email.to", path), "no recipients")
+                }
+                _ => {}
+            }
+        }
+
+        let backtest = &config.backtest;
+        self.positive("backtest.frequency", backtest.frequency);
+        self.speed("backtest.speed", &backtest.speed);
+        self.positive("backtest.walk_forward.in_sample", backtest.walk_forward.in_sample);
+        self.positive("backtest.walk_forward.out_of_sample", backtest.walk_forward.out_of_sample);
+        let confidence = backtest.monte_carlo.confidence;
+        if confidence <= 0. || confidence >= 1. {
+            self.issue("backtest.monte_carlo.confidence", "must be between 0 and 1");
+        }
+    }
+}
+
+impl Validator {
+    fn finish(self) -> Result<(), ValidationError> {
+        if self.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError(self.issues))
+        }
+    }
+}
+
+impl GlobalConfig {
+    /// Check the values and the references between sections, reports every problem at once
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        validator.validate(self);
+        validator.finish()
+    }
+}
+
+impl PipelineConfig {
+    /// Check the nodes of the pipeline by themselves, the paths start at the name of the pipeline
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        validator.pipeline(&self.name, self);
+        validator.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, ExprFeatureConfig, FileDataKind, FileIngestorConfig, SMAFeatureConfig};
+
+    #[test]
+    fn test_validate_config() {
+        let mut config = config::load();
+        assert!(config.validate().is_ok());
+
+        if let FeatureConfig::SMA(SMAFeatureConfig { input, .. }) = &mut config.feature_pipeline.features[1] {
+            input.from = "missing".into();
+        }
+        if let FeatureConfig::VWAP(c) = &mut config.feature_pipeline.features[4] {
+            c.input_price.window = 5;
+        }
+        if let FeatureConfig::Expr(c) = &mut config.feature_pipeline.features[10] {
+            let mean = c.inputs.remove("mean").unwrap();
+            c.inputs.insert("avg".into(), mean);
+        }
+        let StrategyConfig::Crossover(c) = &mut config.strategy_manager.strategies[0];
+        c.price_spread_id = "unknown".into();
+        config.execution_manager.endpoints.clear();
+        config.bus.fills = 0;
+        config.backtest.speed = ReplaySpeed::Multiplier(0.);
+        let instrument = &config.instruments[0];
+        config.ingestors.push(IngestorConfig::File(FileIngestorConfig {
+            path: "trades.csv".into(),
+            kind: FileDataKind::Trades,
+            venue: instrument.venue.clone(),
+            symbol: instrument.symbol.clone(),
+            instrument_type: instrument.instrument_type.clone(),
+            speed: ReplaySpeed::Multiplier(f64::NAN),
+        }));
+        let file_speed = format!("ingestors.{}.file.speed", config.ingestors.len() - 1);
+
+        let paths = config.validate().unwrap_err().0.into_iter().map(|i| i.path).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "bus.fills",
+                file_speed.as_str(),
+                "feature_pipeline.features.1.sma.input.from",
+                "feature_pipeline.features.4.vwap",
+                "feature_pipeline.features.10.expr.expression",
+                "feature_pipeline.features.10.expr.inputs.avg",
+                "strategy_manager.strategies.0.crossover.price_spread_id",
+                "execution_manager.default_endpoint",
+                "backtest.speed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_pipeline() {
+        let sma = |id: &str, from: &str, feature_id: &str| {
+            FeatureConfig::SMA(SMAFeatureConfig {
+                id: id.into(),
+                input: PeriodInputConfig {
+                    from: from.into(),
+                    feature_id: feature_id.into(),
+                    periods: 5,
+                },
+                output: id.into(),
+                incremental: false,
+            })
+        };
+        let config = PipelineConfig {
+            name: "test".into(),
+            frequency: 1,
+            bar_close: None,
+            threads: None,
+            warm_up: HashMap::new(),
+            features: vec![
+                sma("a", "c", "c"),
+                sma("b", "a", "a"),
+                sma("c", "b", "b"),
+                sma("d", "base", "trade_price"),
+                sma("d", "missing", "trade_price"),
+                FeatureConfig::Expr(ExprFeatureConfig {
+                    id: "e".into(),
+                    inputs: ["x", "y"]
+                        .map(|name| {
+                            let input = LatestInputConfig {
+                                from: "base".into(),
+                                feature_id: "trade_price".into(),
+                            };
+                            (name.to_string(), input)
+                        })
+                        .into(),
+                    expression: "x - y".to_string().try_into().unwrap(),
+                    output: "e".into(),
+                }),
+            ],
+        };
+
+        let issues = config.validate().unwrap_err().0;
+        let issues = issues.iter().map(|i| (i.path.as_str(), i.message.as_str())).collect::<Vec<_>>();
+        assert_eq!(issues[0], ("test.features.4.sma.id", "duplicate node id 'd'"));
+        assert_eq!(issues[1], ("test.features.4.sma.input.from", "unknown source node 'missing'"));
+        assert_eq!(
+            issues[2],
+            (
+                "test.features.5.expr.inputs.y.feature_id",
+                "feature id 'trade_price' is already read by inputs.x"
+            )
+        );
+        assert_eq!(issues[3].0, "test.features");
+        assert!(["cycle a -> b -> c -> a", "cycle b -> c -> a -> b", "cycle c -> a -> b -> c"].contains(&issues[3].1));
+        assert_eq!(issues.len(), 4);
+    }
+}
@@ -16,6 +16,17 @@ pub static TRADE_PRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::fro
 pub static TRADE_QUANTITY_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("trade_quantity"));
 pub static FILL_PRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("fill_price"));
 pub static FILL_QUANTITY_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("fill_quantity"));
+pub static BOOK_IMBALANCE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("book_imbalance"));
+pub static BOOK_MICROPRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("book_microprice"));
+pub static BOOK_WEIGHTED_MID_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("book_weighted_mid"));
+pub static MARK_PRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("mark_price"));
+pub static INDEX_PRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("index_price"));
+pub static FUNDING_RATE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("funding_rate"));
+/// Last trade price of the spot with the same venue, base and quote, recorded on the perpetual
+pub static SPOT_PRICE_ID: LazyLock<FeatureId> = LazyLock::new(|| FeatureId::from("spot_price"));
+
+/// Levels per side the book imbalance and weighted mid are calculated over
+pub const BOOK_LEVELS: usize = 5;
 
 pub static BASE_IDS: LazyLock<Vec<FeatureId>> = LazyLock::new(|| {
     vec![
@@ -23,5 +34,12 @@ pub static BASE_IDS: LazyLock<Vec<FeatureId>> = LazyLock::new(|| {
         TRADE_QUANTITY_ID.clone(),
         FILL_PRICE_ID.clone(),
         FILL_QUANTITY_ID.clone(),
+        BOOK_IMBALANCE_ID.clone(),
+        BOOK_MICROPRICE_ID.clone(),
+        BOOK_WEIGHTED_MID_ID.clone(),
+        MARK_PRICE_ID.clone(),
+        INDEX_PRICE_ID.clone(),
+        FUNDING_RATE_ID.clone(),
+        SPOT_PRICE_ID.clone(),
     ]
 });
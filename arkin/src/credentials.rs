@@ -0,0 +1,164 @@
+use std::{collections::HashMap, fmt, process::Command};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{CredentialConfig, SecretSource};
+
+/// A secret value that never shows up in logs or debug output
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// The actual value, only to be used where it is sent to the venue
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub api_key: Secret,
+    pub api_secret: Secret,
+}
+
+#[derive(Error, Debug)]
+pub enum CredentialsError {
+    #[error("Unknown credentials: {0}")]
+    Unknown(String),
+    #[error("Environment variable {0} is not set")]
+    MissingEnv(String),
+    #[error("Failed to read secret file {0}: {1}")]
+    File(String, std::io::Error),
+    #[error("Secret command {0} failed: {1}")]
+    Command(String, String),
+    #[error("Secret from {0} is empty")]
+    Empty(String),
+}
+
+/// Resolves the credentials in the config by name, each one is read once and then kept in memory
+#[derive(Default)]
+pub struct CredentialStore {
+    sources: HashMap<String, CredentialConfig>,
+    resolved: Mutex<HashMap<String, Credentials>>,
+}
+
+impl CredentialStore {
+    pub fn from_config(config: &HashMap<String, CredentialConfig>) -> Self {
+        CredentialStore {
+            sources: config.to_owned(),
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Credentials, CredentialsError> {
+        if let Some(credentials) = self.resolved.lock().get(name) {
+            return Ok(credentials.clone());
+        }
+
+        let source = self
+            .sources
+            .get(name)
+            .ok_or_else(|| CredentialsError::Unknown(name.to_owned()))?;
+        let credentials = Credentials {
+            api_key: resolve(&source.api_key)?,
+            api_secret: resolve(&source.api_secret)?,
+        };
+        self.resolved.lock().insert(name.to_owned(), credentials.clone());
+        Ok(credentials)
+    }
+}
+
+/// Read a single secret, for settings that are not venue credentials
+pub fn resolve(source: &SecretSource) -> Result<Secret, CredentialsError> {
+    let (origin, value) = match source {
+        SecretSource::Env(var) => (
+            format!("env {}", var),
+            std::env::var(var).map_err(|_| CredentialsError::MissingEnv(var.to_owned()))?,
+        ),
+        SecretSource::File(path) => (
+            format!("file {}", path),
+            std::fs::read_to_string(path).map_err(|e| CredentialsError::File(path.to_owned(), e))?,
+        ),
+        SecretSource::Command(command) => {
+            let name = command.join(" ");
+            let (program, args) = command
+                .split_first()
+                .ok_or_else(|| CredentialsError::Command(name.clone(), "no program given".into()))?;
+            let output = Command::new(program)
+                .args(args)
+                .output()
+                .map_err(|e| CredentialsError::Command(name.clone(), e.to_string()))?;
+            if !output.status.success() {
+                return Err(CredentialsError::Command(name, output.status.to_string()));
+            }
+            (
+                format!("command {}", name),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            )
+        }
+    };
+
+    // Files and command output usually end with a newline
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(CredentialsError::Empty(origin));
+    }
+    Ok(Secret::new(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_store() {
+        let path = std::env::temp_dir().join("aurelion_test_secret");
+        std::fs::write(&path, "file-secret\n").unwrap();
+        let config = HashMap::from([
+            (
+                "venue".to_string(),
+                CredentialConfig {
+                    api_key: SecretSource::Command(vec!["echo".into(), "command-key".into()]),
+                    api_secret: SecretSource::File(path.to_string_lossy().into_owned()),
+                },
+            ),
+            (
+                "missing".to_string(),
+                CredentialConfig {
+                    api_key: SecretSource::Env("AURELION_TEST_MISSING_KEY".into()),
+                    api_secret: SecretSource::Env("AURELION_TEST_MISSING_SECRET".into()),
+                },
+            ),
+        ]);
+        let store = CredentialStore::from_config(&config);
+
+        let credentials = store.get("venue").unwrap();
+        assert_eq!(credentials.api_key.expose(), "command-key");
+        assert_eq!(credentials.api_secret.expose(), "file-secret");
+        let debug = format!("{:?}", credentials);
+        assert!(!debug.contains("command-key") && !debug.contains("file-secret"));
+        assert_eq!(credentials.api_key.to_string(), "***");
+
+        assert!(matches!(store.get("missing"), Err(CredentialsError::MissingEnv(_))));
+        assert!(matches!(store.get("other"), Err(CredentialsError::Unknown(_))));
+    }
+}
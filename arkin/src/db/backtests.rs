@@ -0,0 +1,171 @@
+use super::DBManager;
+use crate::{backtest::BacktestRun, models::Notional};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+#[derive(sqlx::FromRow)]
+struct BacktestRunRow {
+    run_id: i64,
+    created_time: OffsetDateTime,
+    config_hash: String,
+    git_revision: Option<String>,
+    config: String,
+    start_time: OffsetDateTime,
+    end_time: OffsetDateTime,
+    capital: Decimal,
+    fills: i64,
+    commission: Decimal,
+    exposure: Decimal,
+    gross_pnl: Decimal,
+    net_pnl: Decimal,
+    max_drawdown: Decimal,
+}
+
+impl BacktestRunRow {
+    fn into_run(self, equity_curve: Vec<(OffsetDateTime, Notional)>) -> BacktestRun {
+        BacktestRun {
+            id: Some(self.run_id),
+            created_time: self.created_time,
+            config_hash: self.config_hash,
+            git_revision: self.git_revision,
+            config: self.config,
+            start: self.start_time,
+            end: self.end_time,
+            capital: self.capital.into(),
+            fills: self.fills as usize,
+            commission: self.commission.into(),
+            exposure: self.exposure.into(),
+            gross_pnl: self.gross_pnl.into(),
+            net_pnl: self.net_pnl.into(),
+            max_drawdown: self.max_drawdown.into(),
+            equity_curve,
+        }
+    }
+}
+
+impl DBManager {
+    /// Store the run and its equity curve, returns the id of the run
+    pub async fn insert_backtest_run(&self, run: &BacktestRun) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let run_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO backtest_runs (created_time, config_hash, git_revision, config, start_time, end_time, capital, fills, commission, exposure, gross_pnl, net_pnl, max_drawdown)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING run_id
+            "#,
+            run.created_time,
+            run.config_hash,
+            run.git_revision,
+            run.config,
+            run.start,
+            run.end,
+            run.capital.value(),
+            run.fills as i64,
+            run.commission.value(),
+            run.exposure.value(),
+            run.gross_pnl.value(),
+            run.net_pnl.value(),
+            run.max_drawdown.value(),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let (times, equity): (Vec<_>, Vec<_>) = run.equity_curve.iter().map(|(t, e)| (*t, e.value())).unzip();
+        sqlx::query!(
+            r#"
+            INSERT INTO backtest_equity (run_id, event_time, equity)
+            SELECT $1, * FROM UNNEST($2::timestamptz[], $3::numeric[])
+            "#,
+            run_id,
+            &times,
+            &equity,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(run_id)
+    }
+
+    pub async fn read_backtest_run(&self, run_id: i64) -> Result<BacktestRun> {
+        let row = sqlx::query_as!(
+            BacktestRunRow,
+            "SELECT run_id, created_time, config_hash, git_revision, config, start_time, end_time, capital, fills, commission, exposure, gross_pnl, net_pnl, max_drawdown FROM backtest_runs WHERE run_id = $1",
+            run_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let equity_curve = sqlx::query!(
+            "SELECT event_time, equity FROM backtest_equity WHERE run_id = $1 ORDER BY event_time",
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.event_time, r.equity.into()))
+        .collect();
+
+        Ok(row.into_run(equity_curve))
+    }
+
+    /// Runs with the given config hash, newest first
+    pub async fn list_backtest_runs(&self, config_hash: &str) -> Result<Vec<BacktestRun>> {
+        let rows = sqlx::query_as!(
+            BacktestRunRow,
+            "SELECT run_id, created_time, config_hash, git_revision, config, start_time, end_time, capital, fills, commission, exposure, gross_pnl, net_pnl, max_drawdown FROM backtest_runs WHERE config_hash = $1 ORDER BY created_time DESC",
+            config_hash,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_run(Vec::new())).collect())
+    }
+
+    pub async fn compare_backtest_runs(&self, baseline_id: i64, run_id: i64) -> Result<crate::backtest::BacktestDiff> {
+        let baseline = self.read_backtest_run(baseline_id).await?;
+        let run = self.read_backtest_run(run_id).await?;
+        Ok(run.diff(&baseline))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{backtest::BacktestResult, config};
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_backtest_run_roundtrip() {
+        let config = config::load();
+        let manager = DBManager::from_config(&config.db).await;
+
+        let start = OffsetDateTime::now_utc().replace_millisecond(0).unwrap();
+        let result = BacktestResult {
+            start,
+            end: start + time::Duration::minutes(1),
+            capital: Notional::from(10000.),
+            fills: Vec::new(),
+            equity_curve: vec![
+                (start, Notional::from(10000.)),
+                (start + time::Duration::seconds(1), Notional::from(9990.)),
+            ],
+            positions: HashMap::new(),
+            last_prices: HashMap::new(),
+        };
+        let run = BacktestRun::new(&config, &result);
+
+        let run_id = manager.insert_backtest_run(&run).await.unwrap();
+        let stored = manager.read_backtest_run(run_id).await.unwrap();
+        assert_eq!(stored.config_hash, run.config_hash);
+        assert_eq!(stored.equity_curve, run.equity_curve);
+        assert_eq!(stored.max_drawdown, Notional::from(10.));
+
+        let diff = manager.compare_backtest_runs(run_id, run_id).await.unwrap();
+        assert!(diff.config_changes.is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::models::{Candle, Instrument};
+use anyhow::Result;
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use tracing::error;
+
+use super::{instruments::InstrumentColumns, DBManager};
+
+#[derive(sqlx::FromRow)]
+struct CandleRow {
+    open_time: OffsetDateTime,
+    instrument_type: String,
+    venue: String,
+    base: String,
+    quote: String,
+    maturity: Option<OffsetDateTime>,
+    strike: Option<Decimal>,
+    option_type: Option<String>,
+    interval_ms: i64,
+    event_time: OffsetDateTime,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    quote_volume: Decimal,
+    trades: i64,
+    source: String,
+}
+
+impl From<CandleRow> for Candle {
+    fn from(row: CandleRow) -> Self {
+        let instrument = Instrument::try_from(InstrumentColumns {
+            instrument_type: row.instrument_type,
+            venue: row.venue,
+            base: row.base,
+            quote: row.quote,
+            maturity: row.maturity,
+            strike: row.strike,
+            option_type: row.option_type,
+        })
+        .expect("Invalid instrument");
+
+        Candle {
+            event_time: row.event_time,
+            instrument,
+            interval: time::Duration::milliseconds(row.interval_ms),
+            open_time: row.open_time,
+            open: row.open.into(),
+            high: row.high.into(),
+            low: row.low.into(),
+            close: row.close.into(),
+            volume: row.volume.into(),
+            quote_volume: row.quote_volume.into(),
+            trades: row.trades as u64,
+            // Only closed candles are stored
+            closed: true,
+            source: row.source.parse().expect("Invalid source"),
+        }
+    }
+}
+
+impl DBManager {
+    /// Candles that are already stored are skipped, so overlapping backfills can be rerun
+    pub async fn insert_candles_batch(&self, candles: Vec<Candle>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for candle in candles.into_iter().filter(|c| c.closed) {
+            sqlx::query!(
+                r#"
+                WITH existing_instrument AS (
+                    SELECT instrument_id
+                    FROM instruments
+                    WHERE instrument_type = $2
+                    AND venue = $3
+                    AND base = $4
+                    AND quote = $5
+                    AND maturity IS NOT DISTINCT FROM $6
+                    AND strike IS NOT DISTINCT FROM $7
+                    AND option_type IS NOT DISTINCT FROM $8
+                ), insert_instrument AS (
+                    INSERT INTO instruments (instrument_type, venue, base, quote, maturity, strike, option_type)
+                    SELECT $2, $3, $4, $5, $6, $7, $8
+                    WHERE NOT EXISTS (SELECT 1 FROM existing_instrument)
+                    RETURNING instrument_id
+                )
+                INSERT INTO candles (
+                    open_time, instrument_id, interval_ms, event_time, open, high, low, close, volume, quote_volume, trades, source
+                )
+                SELECT
+                    $1, COALESCE(ei.instrument_id, ii.instrument_id), $9, $10, $11, $12, $13, $14, $15, $16, $17, $18
+                FROM
+                    existing_instrument ei
+                FULL OUTER JOIN
+                    insert_instrument ii ON true
+                LIMIT 1
+                ON CONFLICT DO NOTHING
+                "#,
+                candle.open_time,
+                candle.instrument.instrument_type().to_string(),
+                candle.instrument.venue().to_string(),
+                candle.instrument.base().to_string(),
+                candle.instrument.quote().to_string(),
+                candle.instrument.maturity().map(|m| m.value()),
+                candle.instrument.strike().map(|s| s.value()),
+                candle.instrument.option_type().map(|ot| ot.to_string()),
+                candle.interval.whole_milliseconds() as i64,
+                candle.event_time,
+                candle.open.value(),
+                candle.high.value(),
+                candle.low.value(),
+                candle.close.value(),
+                candle.volume.value(),
+                candle.quote_volume.value(),
+                candle.trades as i64,
+                candle.source.to_string(),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn read_candles(&self, from: OffsetDateTime, to: OffsetDateTime) -> Vec<Candle> {
+        let stream = sqlx::query_as!(
+            CandleRow,
+            r#"
+            SELECT
+                candles.open_time,
+                instruments.instrument_type,
+                instruments.venue,
+                instruments.base,
+                instruments.quote,
+                instruments.maturity,
+                instruments.strike,
+                instruments.option_type,
+                candles.interval_ms,
+                candles.event_time,
+                candles.open,
+                candles.high,
+                candles.low,
+                candles.close,
+                candles.volume,
+                candles.quote_volume,
+                candles.trades,
+                candles.source
+            FROM candles
+            JOIN instruments ON candles.instrument_id = instruments.instrument_id
+            WHERE candles.open_time >= $1 AND candles.open_time < $2
+            ORDER BY candles.open_time
+            "#,
+            from,
+            to,
+        )
+        .fetch(&self.pool);
+
+        stream
+            .filter_map(|res| async {
+                match res {
+                    Ok(v) => Some(v.into()),
+                    Err(e) => {
+                        error!("Error reading candle: {:?}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::prelude::*;
+
+    use super::*;
+    use crate::{
+        config,
+        ingestors::IngestorID,
+        models::{Instrument, Venue},
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_insert_candles() {
+        let config = config::load();
+        let manager = DBManager::from_config(&config.db).await;
+
+        let open_time = OffsetDateTime::now_utc().replace_millisecond(0).unwrap();
+        let candle = Candle {
+            event_time: open_time + time::Duration::minutes(1),
+            instrument: Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
+            interval: time::Duration::minutes(1),
+            open_time,
+            open: Decimal::new(100, 0).into(),
+            high: Decimal::new(110, 0).into(),
+            low: Decimal::new(90, 0).into(),
+            close: Decimal::new(105, 0).into(),
+            volume: Decimal::new(10, 0).into(),
+            quote_volume: Decimal::new(1000, 0).into(),
+            trades: 42,
+            closed: true,
+            source: IngestorID::Test,
+        };
+        // Inserting twice keeps a single row
+        manager.insert_candles_batch(vec![candle.clone(), candle]).await.unwrap();
+
+        let candles = manager.read_candles(open_time, open_time + time::Duration::seconds(1)).await;
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].interval, time::Duration::minutes(1));
+        assert_eq!(candles[0].trades, 42);
+    }
+}
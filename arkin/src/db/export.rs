@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use anyhow::Result;
+use strum::{Display, EnumString};
+use time::OffsetDateTime;
+
+use super::DBManager;
+use crate::{
+    constants::TIMESTAMP_FORMAT,
+    models::{Tick, Trade},
+};
+
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportKind {
+    Trades,
+    Ticks,
+}
+
+impl DBManager {
+    /// Write the stored market data of a time range as csv, returns the number of rows
+    pub async fn export(
+        &self,
+        kind: ExportKind,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+        writer: impl Write,
+    ) -> Result<usize> {
+        match kind {
+            ExportKind::Trades => {
+                let mut trades = self.read_trades(from, to).await;
+                trades.sort_by_key(|t| (t.event_time, t.trade_id));
+                write_trades_csv(&trades, writer)
+            }
+            ExportKind::Ticks => {
+                let mut ticks = self.read_ticks(from, to).await;
+                ticks.sort_by_key(|t| (t.event_time, t.tick_id));
+                write_ticks_csv(&ticks, writer)
+            }
+        }
+    }
+
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        Ok(())
+    }
+}
+
+fn format_time(time: &OffsetDateTime) -> String {
+    time.format(TIMESTAMP_FORMAT).unwrap_or_default()
+}
+
+pub fn write_trades_csv(trades: &[Trade], mut writer: impl Write) -> Result<usize> {
+    writeln!(writer, "event_time,instrument,trade_id,price,quantity")?;
+    for trade in trades {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            format_time(&trade.event_time),
+            trade.instrument,
+            trade.trade_id,
+            trade.price,
+            trade.quantity
+        )?;
+    }
+    writer.flush()?;
+    Ok(trades.len())
+}
+
+pub fn write_ticks_csv(ticks: &[Tick], mut writer: impl Write) -> Result<usize> {
+    writeln!(
+        writer,
+        "event_time,instrument,tick_id,bid_price,bid_quantity,ask_price,ask_quantity"
+    )?;
+    for tick in ticks {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            format_time(&tick.event_time),
+            tick.instrument,
+            tick.tick_id,
+            tick.bid_price,
+            tick.bid_quantity,
+            tick.ask_price,
+            tick.ask_quantity
+        )?;
+    }
+    writer.flush()?;
+    Ok(ticks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::Event, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_write_trades_csv() {
+        let instrument = test_utils::test_perp_instrument();
+        let trades = test_utils::market_events(&instrument, datetime!(2024-01-01 00:00:00).assume_utc(), 3)
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut output = Vec::new();
+        assert_eq!(write_trades_csv(&trades, &mut output).unwrap(), 3);
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "event_time,instrument,trade_id,price,quantity");
+        assert!(lines[1].starts_with("2024-01-01 00:00:00"));
+        assert!(lines[1].contains(&instrument.to_string()));
+    }
+}
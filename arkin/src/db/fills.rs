@@ -19,6 +19,9 @@ struct FillRow {
     price: Decimal,
     quantity: Decimal,
     commission: Decimal,
+    commission_asset: String,
+    liquidity: String,
+    trade_id: Option<i64>,
 }
 
 impl From<Fill> for FillRow {
@@ -37,6 +40,9 @@ impl From<Fill> for FillRow {
             price: fill.price.value(),
             quantity: fill.quantity.value(),
             commission: fill.commission.value(),
+            commission_asset: fill.commission_asset().to_string(),
+            liquidity: fill.liquidity.to_string(),
+            trade_id: fill.trade_id.map(|id| id as i64),
         }
     }
 }
@@ -46,8 +52,8 @@ impl DBManager {
         let fill = FillRow::from(fill);
         sqlx::query!(
             r#"
-            INSERT INTO fills (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, order_id, strategy_id, price, quantity, commission)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            INSERT INTO fills (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, order_id, strategy_id, price, quantity, commission, commission_asset, liquidity, trade_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
             fill.event_time,
             fill.instrument_type,
@@ -62,6 +68,9 @@ impl DBManager {
             fill.price,
             fill.quantity,
             fill.commission,
+            fill.commission_asset,
+            fill.liquidity,
+            fill.trade_id,
         )
         .execute(&self.pool).await?;
 
@@ -77,7 +86,7 @@ mod tests {
     use super::*;
     use crate::{
         config,
-        models::{Instrument, Venue},
+        models::{Instrument, Liquidity, Venue},
     };
 
     #[tokio::test]
@@ -86,15 +95,17 @@ mod tests {
         let config = config::load();
         let manager = DBManager::from_config(&config.db).await;
 
-        let fill = Fill {
-            event_time: OffsetDateTime::now_utc(),
-            instrument: Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
-            order_id: 1,
-            strategy_id: "test".into(),
-            price: Decimal::new(10000, 2).into(),
-            quantity: Decimal::new(105, 1).into(),
-            commission: Decimal::new(10, 2).into(),
-        };
+        let fill = Fill::new(
+            OffsetDateTime::now_utc(),
+            Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
+            1,
+            "test".into(),
+            Decimal::new(10000, 2).into(),
+            Decimal::new(105, 1).into(),
+            Decimal::new(10, 2).into(),
+        )
+        .with_liquidity(Liquidity::Maker)
+        .with_trade_id(42);
 
         manager.insert_fill(fill).await.unwrap();
 
@@ -0,0 +1,180 @@
+use crate::models::{FundingRate, Instrument};
+use anyhow::Result;
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use tracing::error;
+
+use super::{instruments::InstrumentColumns, DBManager};
+
+#[derive(sqlx::FromRow)]
+struct FundingRateRow {
+    event_time: OffsetDateTime,
+    instrument_type: String,
+    venue: String,
+    base: String,
+    quote: String,
+    maturity: Option<OffsetDateTime>,
+    strike: Option<Decimal>,
+    option_type: Option<String>,
+    mark_price: Decimal,
+    index_price: Decimal,
+    funding_rate: Decimal,
+    next_funding_time: OffsetDateTime,
+    source: String,
+}
+
+impl From<FundingRateRow> for FundingRate {
+    fn from(row: FundingRateRow) -> Self {
+        let instrument = Instrument::try_from(InstrumentColumns {
+            instrument_type: row.instrument_type,
+            venue: row.venue,
+            base: row.base,
+            quote: row.quote,
+            maturity: row.maturity,
+            strike: row.strike,
+            option_type: row.option_type,
+        })
+        .expect("Invalid instrument");
+
+        FundingRate {
+            event_time: row.event_time,
+            instrument,
+            mark_price: row.mark_price.into(),
+            index_price: row.index_price.into(),
+            funding_rate: row.funding_rate,
+            next_funding_time: row.next_funding_time,
+            source: row.source.parse().expect("Invalid source"),
+        }
+    }
+}
+
+impl DBManager {
+    pub async fn insert_funding_rate(&self, funding: FundingRate) -> Result<()> {
+        sqlx::query!(
+            r#"
+            WITH existing_instrument AS (
+                SELECT instrument_id
+                FROM instruments
+                WHERE instrument_type = $2
+                AND venue = $3
+                AND base = $4
+                AND quote = $5
+                AND maturity IS NOT DISTINCT FROM $6
+                AND strike IS NOT DISTINCT FROM $7
+                AND option_type IS NOT DISTINCT FROM $8
+            ), insert_instrument AS (
+                INSERT INTO instruments (instrument_type, venue, base, quote, maturity, strike, option_type)
+                SELECT $2, $3, $4, $5, $6, $7, $8
+                WHERE NOT EXISTS (SELECT 1 FROM existing_instrument)
+                RETURNING instrument_id
+            )
+            INSERT INTO funding_rates (
+                event_time, instrument_id, mark_price, index_price, funding_rate, next_funding_time, source
+            )
+            SELECT 
+                $1, COALESCE(ei.instrument_id, ii.instrument_id), $9, $10, $11, $12, $13
+            FROM 
+                existing_instrument ei
+            FULL OUTER JOIN 
+                insert_instrument ii ON true
+            LIMIT 1
+            ON CONFLICT DO NOTHING
+            "#,
+            funding.event_time,
+            funding.instrument.instrument_type().to_string(),
+            funding.instrument.venue().to_string(),
+            funding.instrument.base().to_string(),
+            funding.instrument.quote().to_string(),
+            funding.instrument.maturity().map(|m| m.value()),
+            funding.instrument.strike().map(|s| s.value()),
+            funding.instrument.option_type().map(|ot| ot.to_string()),
+            funding.mark_price.value(),
+            funding.index_price.value(),
+            funding.funding_rate,
+            funding.next_funding_time,
+            funding.source.to_string(),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn read_funding_rates(&self, from: OffsetDateTime, to: OffsetDateTime) -> Vec<FundingRate> {
+        let stream = sqlx::query_as!(
+            FundingRateRow,
+            r#"
+            SELECT 
+                funding_rates.event_time, 
+                instruments.instrument_type, 
+                instruments.venue, 
+                instruments.base, 
+                instruments.quote, 
+                instruments.maturity, 
+                instruments.strike, 
+                instruments.option_type, 
+                funding_rates.mark_price, 
+                funding_rates.index_price, 
+                funding_rates.funding_rate, 
+                funding_rates.next_funding_time, 
+                funding_rates.source
+            FROM funding_rates
+            JOIN instruments ON funding_rates.instrument_id = instruments.instrument_id
+            WHERE funding_rates.event_time >= $1 AND funding_rates.event_time < $2
+            "#,
+            from,
+            to
+        )
+        .fetch(&self.pool);
+
+        stream
+            .filter_map(|res| async {
+                match res {
+                    Ok(v) => Some(v.into()),
+                    Err(e) => {
+                        error!("Error reading funding rate: {:?}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::prelude::*;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::{
+        config,
+        ingestors::IngestorID,
+        models::{Instrument, Venue},
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_insert_funding_rate() {
+        let config = config::load();
+        let manager = DBManager::from_config(&config.db).await;
+
+        let now = OffsetDateTime::now_utc();
+        let funding = FundingRate {
+            event_time: now,
+            instrument: Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
+            mark_price: Decimal::new(1179415, 2).into(),
+            index_price: Decimal::new(1178462, 2).into(),
+            funding_rate: Decimal::new(38167, 8),
+            next_funding_time: now + time::Duration::hours(8),
+            source: IngestorID::Test,
+        };
+        manager.insert_funding_rate(funding).await.unwrap();
+
+        let rates = manager.read_funding_rates(now, now + time::Duration::seconds(1)).await;
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].funding_rate, Decimal::new(38167, 8));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+use crate::models::Instrument;
+
+use super::DBManager;
+
+/// Columns an instrument is stored in, shared by the instruments table and every query joining it.
+#[derive(sqlx::FromRow)]
+pub(super) struct InstrumentColumns {
+    pub instrument_type: String,
+    pub venue: String,
+    pub base: String,
+    pub quote: String,
+    pub maturity: Option<OffsetDateTime>,
+    pub strike: Option<Decimal>,
+    pub option_type: Option<String>,
+}
+
+impl From<&Instrument> for InstrumentColumns {
+    fn from(instrument: &Instrument) -> Self {
+        Self {
+            instrument_type: instrument.instrument_type().to_string(),
+            venue: instrument.venue().to_string(),
+            base: instrument.base().to_string(),
+            quote: instrument.quote().to_string(),
+            maturity: instrument.maturity().map(|m| m.value()),
+            strike: instrument.strike().map(|s| s.value()),
+            option_type: instrument.option_type().map(|ot| ot.to_string()),
+        }
+    }
+}
+
+impl TryFrom<InstrumentColumns> for Instrument {
+    type Error = anyhow::Error;
+
+    /// Interns the stored instrument, rows of the same instrument all resolve to one handle.
+    fn try_from(columns: InstrumentColumns) -> Result<Self> {
+        Instrument::new(
+            &columns.instrument_type.parse()?,
+            columns.venue.parse()?,
+            columns.base.as_str().into(),
+            columns.quote.as_str().into(),
+            columns.maturity.map(|m| m.into()),
+            columns.strike.map(|s| s.into()),
+            columns.option_type.map(|ot| ot.parse()).transpose()?,
+        )
+    }
+}
+
+impl DBManager {
+    /// Every stored instrument by its `instrument_id`, to resolve rows that only reference the id.
+    pub async fn load_instruments(&self) -> Result<HashMap<i32, Instrument>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT instrument_id, instrument_type, venue, base, quote, maturity, strike, option_type
+            FROM instruments
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut instruments = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let columns = InstrumentColumns {
+                instrument_type: row.instrument_type,
+                venue: row.venue,
+                base: row.base,
+                quote: row.quote,
+                maturity: row.maturity,
+                strike: row.strike,
+                option_type: row.option_type,
+            };
+            let instrument = Instrument::try_from(columns)
+                .with_context(|| format!("Invalid instrument with id {}", row.instrument_id))?;
+            self.instrument_ids.insert(instrument.clone(), row.instrument_id);
+            instruments.insert(row.instrument_id, instrument);
+        }
+        Ok(instruments)
+    }
+
+    /// The `instrument_id` of an instrument, inserting it when it is not stored yet. Ids are cached per handle.
+    pub async fn instrument_id(&self, instrument: &Instrument) -> Result<i32> {
+        if let Some(id) = self.instrument_ids.get(instrument) {
+            return Ok(*id);
+        }
+
+        let columns = InstrumentColumns::from(instrument);
+        let id = sqlx::query_scalar!(
+            r#"
+            WITH existing_instrument AS (
+                SELECT instrument_id
+                FROM instruments
+                WHERE instrument_type = $1
+                AND venue = $2
+                AND base = $3
+                AND quote = $4
+                AND maturity IS NOT DISTINCT FROM $5
+                AND strike IS NOT DISTINCT FROM $6
+                AND option_type IS NOT DISTINCT FROM $7
+            ), insert_instrument AS (
+                INSERT INTO instruments (instrument_type, venue, base, quote, maturity, strike, option_type)
+                SELECT $1, $2, $3, $4, $5, $6, $7
+                WHERE NOT EXISTS (SELECT 1 FROM existing_instrument)
+                RETURNING instrument_id
+            )
+            SELECT instrument_id AS "instrument_id!" FROM existing_instrument
+            UNION ALL
+            SELECT instrument_id FROM insert_instrument
+            "#,
+            columns.instrument_type,
+            columns.venue,
+            columns.base,
+            columns.quote,
+            columns.maturity,
+            columns.strike,
+            columns.option_type,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.instrument_ids.insert(instrument.clone(), id);
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        models::{Maturity, OptionType, Price, Venue},
+    };
+    use time::macros::datetime;
+
+    #[test]
+    fn test_instrument_columns() {
+        let instrument = Instrument::option(
+            Venue::Binance,
+            "BTC".into(),
+            "USDT".into(),
+            Price::from(Decimal::from(60000)),
+            Maturity::from(datetime!(2024-12-27 08:00 UTC)),
+            OptionType::Call,
+        );
+        let columns = InstrumentColumns::from(&instrument);
+        assert_eq!(columns.option_type.as_deref(), Some("C"));
+        assert!(Instrument::try_from(columns).unwrap() == instrument);
+
+        let mut columns = InstrumentColumns::from(&Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()));
+        columns.venue = "unknown".into();
+        assert!(Instrument::try_from(columns).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_instrument_id() {
+        let config = config::load();
+        let manager = DBManager::from_config(&config.db).await;
+
+        let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let id = manager.instrument_id(&instrument).await.unwrap();
+        manager.instrument_ids.clear();
+        assert_eq!(manager.instrument_id(&instrument).await.unwrap(), id);
+
+        let instruments = manager.load_instruments().await.unwrap();
+        assert!(instruments[&id] == instrument);
+    }
+}
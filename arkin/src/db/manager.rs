@@ -1,5 +1,7 @@
+use crate::models::Instrument;
 use crate::{config::DatabaseConfig, models::Event};
 use anyhow::Result;
+use dashmap::DashMap;
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
     PgPool,
@@ -9,6 +11,8 @@ use tracing::{error, info};
 
 pub struct DBManager {
     pub pool: PgPool,
+    /// Cached `instrument_id` per interned instrument, filled by [`DBManager::instrument_id`]
+    pub(super) instrument_ids: DashMap<Instrument, i32>,
 }
 
 impl DBManager {
@@ -17,7 +21,7 @@ impl DBManager {
             .host(&config.host)
             .port(config.port)
             .username(&config.user)
-            .password(&config.password)
+            .password(config.password.expose())
             .database(&config.database)
             .ssl_mode(PgSslMode::Prefer);
 
@@ -36,7 +40,10 @@ impl DBManager {
             Err(e) => panic!("SQLX failed to connect to database: {}", e),
         };
 
-        Self { pool }
+        Self {
+            pool,
+            instrument_ids: DashMap::new(),
+        }
     }
 
     pub async fn test(&self) {
@@ -54,6 +61,8 @@ impl DBManager {
         match event {
             Event::Tick(t) => self.insert_tick(t).await?,
             Event::Trade(t) => self.insert_trade(t).await?,
+            Event::FundingRate(f) => self.insert_funding_rate(f).await?,
+            Event::Candle(c) => self.insert_candles_batch(vec![c]).await?,
             Event::Order(o) => self.insert_order(o).await?,
             Event::Fill(f) => self.insert_fill(f).await?,
             Event::Signal(s) => self.insert_signal(s).await?,
@@ -75,6 +84,30 @@ impl DBManager {
             .cloned()
             .collect::<Vec<_>>();
         self.insert_ticks_batch(ticks).await?;
+        let trades = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Trade(t) => Some(t),
+                _ => None,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.insert_trades_batch(trades).await?;
+        let candles = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Candle(c) => Some(c),
+                _ => None,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.insert_candles_batch(candles).await?;
+        // Funding arrives every few seconds per symbol, few enough to insert one by one
+        for event in events {
+            if let Event::FundingRate(funding) = event {
+                self.insert_funding_rate(funding.clone()).await?;
+            }
+        }
         Ok(())
     }
 }
@@ -1,9 +1,15 @@
 mod allocations;
+mod backtests;
+mod candles;
+mod export;
 mod fills;
+mod funding;
+mod instruments;
 mod manager;
 mod orders;
 mod signals;
 mod ticks;
 mod trades;
 
+pub use export::{write_ticks_csv, write_trades_csv, ExportKind};
 pub use manager::DBManager;
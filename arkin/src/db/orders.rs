@@ -15,13 +15,20 @@ struct OrderRow {
     strike: Option<Decimal>,
     option_type: Option<String>,
     order_id: i64,
+    client_order_id: String,
+    venue_order_id: Option<String>,
     strategy_id: String,
     order_type: String,
     price: Option<Decimal>,
+    trigger_price: Option<Decimal>,
+    time_in_force: String,
     avg_fill_price: Option<Decimal>,
     quantity: Decimal,
     quantity_filled: Decimal,
     status: String,
+    created_time: OffsetDateTime,
+    acked_time: Option<OffsetDateTime>,
+    filled_time: Option<OffsetDateTime>,
 }
 
 impl From<Order> for OrderRow {
@@ -36,13 +43,20 @@ impl From<Order> for OrderRow {
             strike: order.instrument.strike().map(|s| s.value()),
             option_type: order.instrument.option_type().map(|ot| ot.to_string()),
             order_id: order.order_id as i64,
+            client_order_id: order.client_order_id,
+            venue_order_id: order.venue_order_id,
             strategy_id: order.strategy_id.to_string(),
             order_type: order.order_type.to_string(),
             price: order.price.map(|p| p.value()),
+            trigger_price: order.trigger_price.map(|p| p.value()),
+            time_in_force: order.time_in_force.to_string(),
             avg_fill_price: order.avg_fill_price.map(|p| p.value()),
             quantity: order.quantity.value(),
             quantity_filled: order.quantity_filled.value(),
             status: order.status.to_string(),
+            created_time: order.created_time,
+            acked_time: order.acked_time,
+            filled_time: order.filled_time,
         }
     }
 }
@@ -52,8 +66,8 @@ impl DBManager {
         let order = OrderRow::from(order);
         sqlx::query!(
             r#"
-            INSERT INTO orders (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, order_id, strategy_id, order_type, price, avg_fill_price, quantity, quantity_filled, status)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            INSERT INTO orders (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, order_id, client_order_id, venue_order_id, strategy_id, order_type, price, trigger_price, time_in_force, avg_fill_price, quantity, quantity_filled, status, created_time, acked_time, filled_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23)
             "#,
             order.event_time,
             order.instrument_type,
@@ -64,13 +78,20 @@ impl DBManager {
             order.strike,
             order.option_type,
             order.order_id,
+            order.client_order_id,
+            order.venue_order_id,
             order.strategy_id,
             order.order_type,
             order.price,
+            order.trigger_price,
+            order.time_in_force,
             order.avg_fill_price,
             order.quantity,
             order.quantity_filled,
             order.status,
+            order.created_time,
+            order.acked_time,
+            order.filled_time,
         )
         .execute(&self.pool)
         .await?;
@@ -87,7 +108,7 @@ mod tests {
     use super::*;
     use crate::{
         config,
-        models::{Instrument, OrderStatus, OrderType, Venue},
+        models::{Instrument, OrderStatus, OrderType, TimeInForce, Venue},
     };
 
     #[tokio::test]
@@ -96,17 +117,25 @@ mod tests {
         let config = config::load();
         let manager = DBManager::from_config(&config.db).await;
 
+        let now = OffsetDateTime::now_utc();
         let order = Order {
-            event_time: OffsetDateTime::now_utc(),
+            event_time: now,
             instrument: Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
             order_id: 1,
+            client_order_id: "test-1".into(),
+            venue_order_id: Some("1001".into()),
             strategy_id: "test".into(),
             order_type: OrderType::Limit,
             price: Some(Decimal::new(10000, 2).into()),
+            trigger_price: None,
+            time_in_force: TimeInForce::Gtc,
             avg_fill_price: Some(Decimal::new(9990, 2).into()),
             quantity: Decimal::new(105, 1).into(),
             quantity_filled: Decimal::new(105, 1).into(),
             status: OrderStatus::Filled,
+            created_time: now,
+            acked_time: Some(now),
+            filled_time: Some(now),
         };
 
         manager.insert_order(order).await.unwrap();
@@ -16,6 +16,9 @@ struct SignalRow {
     option_type: Option<String>,
     strategy_id: String,
     signal: Decimal,
+    confidence: Option<Decimal>,
+    horizon: Option<i64>,
+    expires_at: Option<OffsetDateTime>,
 }
 
 impl From<Signal> for SignalRow {
@@ -31,6 +34,9 @@ impl From<Signal> for SignalRow {
             option_type: signal.instrument.option_type().map(|ot| ot.to_string()),
             strategy_id: signal.strategy_id.to_string(),
             signal: signal.signal.value(),
+            confidence: signal.confidence,
+            horizon: signal.horizon.map(|h| h.whole_seconds()),
+            expires_at: signal.expires_at,
         }
     }
 }
@@ -39,8 +45,8 @@ impl DBManager {
         let signal = SignalRow::from(signal);
         sqlx::query!(
             r#"
-            INSERT INTO signals (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, strategy_id, signal)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            INSERT INTO signals (event_time, instrument_type, venue, base, quote, maturity, strike, option_type, strategy_id, signal, confidence, horizon, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             "#,
             signal.event_time,
             signal.instrument_type,
@@ -52,6 +58,9 @@ impl DBManager {
             signal.option_type,
             signal.strategy_id,
             signal.signal,
+            signal.confidence,
+            signal.horizon,
+            signal.expires_at,
         )
         .execute(&self.pool)
         .await?;
@@ -77,12 +86,12 @@ mod tests {
         let config = config::load();
         let manager = DBManager::from_config(&config.db).await;
 
-        let signal = Signal {
-            event_time: OffsetDateTime::now_utc(),
-            instrument: Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
-            strategy_id: "test".into(),
-            signal: Decimal::new(1, 0).into(),
-        };
+        let signal = Signal::new(
+            OffsetDateTime::now_utc(),
+            Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
+            "test".into(),
+            Decimal::new(1, 0).into(),
+        );
 
         manager.insert_signal(signal).await.unwrap();
 
@@ -5,7 +5,7 @@ use rust_decimal::Decimal;
 use time::OffsetDateTime;
 use tracing::error;
 
-use super::DBManager;
+use super::{instruments::InstrumentColumns, DBManager};
 
 #[derive(Debug, sqlx::FromRow)]
 struct TickRow {
@@ -48,15 +48,15 @@ impl From<Tick> for TickRow {
 
 impl From<TickRow> for Tick {
     fn from(db_tick: TickRow) -> Self {
-        let instrument = Instrument::new(
-            &db_tick.instrument_type.parse().unwrap(),
-            db_tick.venue.parse().expect("Invalid venue"),
-            db_tick.base.as_str().into(),
-            db_tick.quote.as_str().into(),
-            db_tick.maturity.map(|m| m.into()),
-            db_tick.strike.map(|s| s.into()),
-            db_tick.option_type.map(|ot| ot.parse().unwrap()),
-        )
+        let instrument = Instrument::try_from(InstrumentColumns {
+            instrument_type: db_tick.instrument_type,
+            venue: db_tick.venue,
+            base: db_tick.base,
+            quote: db_tick.quote,
+            maturity: db_tick.maturity,
+            strike: db_tick.strike,
+            option_type: db_tick.option_type,
+        })
         .expect("Failed to create instrument");
 
         Tick {
@@ -5,7 +5,7 @@ use rust_decimal::Decimal;
 use time::OffsetDateTime;
 use tracing::error;
 
-use super::DBManager;
+use super::{instruments::InstrumentColumns, DBManager};
 
 #[derive(sqlx::FromRow)]
 struct TradeRow {
@@ -46,15 +46,15 @@ impl From<Trade> for TradeRow {
 
 impl From<TradeRow> for Trade {
     fn from(db_trade: TradeRow) -> Self {
-        let instrument = Instrument::new(
-            &db_trade.instrument_type.parse().unwrap(),
-            db_trade.venue.parse().expect("Invalid venue"),
-            db_trade.base.as_str().into(),
-            db_trade.quote.as_str().into(),
-            db_trade.maturity.map(|m| m.into()),
-            db_trade.strike.map(|s| s.into()),
-            db_trade.option_type.map(|ot| ot.parse().unwrap()),
-        )
+        let instrument = Instrument::try_from(InstrumentColumns {
+            instrument_type: db_trade.instrument_type,
+            venue: db_trade.venue,
+            base: db_trade.base,
+            quote: db_trade.quote,
+            maturity: db_trade.maturity,
+            strike: db_trade.strike,
+            option_type: db_trade.option_type,
+        })
         .expect("Invalid instrument");
 
         Trade::new(
@@ -120,6 +120,64 @@ impl DBManager {
         Ok(())
     }
 
+    /// Trades that are already stored are skipped, so overlapping backfills can be rerun
+    pub async fn insert_trades_batch(&self, trades: Vec<Trade>) -> Result<()> {
+        let trades = trades.into_iter().map(TradeRow::from).collect::<Vec<_>>();
+
+        let mut tx = self.pool.begin().await?;
+        for trade in trades {
+            sqlx::query(
+                r#"
+                WITH existing_instrument AS (
+                    SELECT instrument_id
+                    FROM instruments
+                    WHERE instrument_type = $3
+                    AND venue = $4
+                    AND base = $5
+                    AND quote = $6
+                    AND maturity IS NOT DISTINCT FROM $7
+                    AND strike IS NOT DISTINCT FROM $8
+                    AND option_type IS NOT DISTINCT FROM $9
+                ), insert_instrument AS (
+                    INSERT INTO instruments (instrument_type, venue, base, quote, maturity, strike, option_type)
+                    SELECT $3, $4, $5, $6, $7, $8, $9
+                    WHERE NOT EXISTS (SELECT 1 FROM existing_instrument)
+                    RETURNING instrument_id
+                )
+                INSERT INTO trades (
+                    received_time, event_time, instrument_id, trade_id, price, quantity, source
+                )
+                SELECT
+                    $1, $2, COALESCE(ei.instrument_id, ii.instrument_id), $10, $11, $12, $13
+                FROM
+                    existing_instrument ei
+                FULL OUTER JOIN
+                    insert_instrument ii ON true
+                LIMIT 1
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(trade.received_time)
+            .bind(trade.event_time)
+            .bind(trade.instrument_type)
+            .bind(trade.venue)
+            .bind(trade.base)
+            .bind(trade.quote)
+            .bind(trade.maturity)
+            .bind(trade.strike)
+            .bind(trade.option_type)
+            .bind(trade.trade_id)
+            .bind(trade.price)
+            .bind(trade.quantity)
+            .bind(trade.source)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     pub async fn read_trades(&self, from: OffsetDateTime, to: OffsetDateTime) -> Vec<Trade> {
         let stream = sqlx::query_as!(
             TradeRow,
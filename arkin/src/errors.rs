@@ -1,7 +1,65 @@
 use thiserror::Error;
 
+use crate::{
+    config::ConfigLoadError, credentials::CredentialsError, execution::ExecutionError, features::PipelineError,
+    flags::FlagError, ingestors::IngestorError, journal::JournalError, models::errors::ModelError, state::StateError,
+    strategies::StrategyError,
+};
+
+/// Every failure of the engine, match on the variant to tell the kinds apart
 #[derive(Error, Debug)]
-pub enum AppError {
-    #[error("Unknown error: {0}")]
-    Unknown(String),
+pub enum EngineError {
+    #[error(transparent)]
+    Config(#[from] ConfigLoadError),
+
+    #[error(transparent)]
+    Credentials(#[from] CredentialsError),
+
+    #[error(transparent)]
+    Ingestor(#[from] IngestorError),
+
+    #[error(transparent)]
+    Pipeline(#[from] PipelineError),
+
+    #[error(transparent)]
+    Strategy(#[from] StrategyError),
+
+    #[error(transparent)]
+    Execution(#[from] ExecutionError),
+
+    #[error(transparent)]
+    State(#[from] StateError),
+
+    #[error(transparent)]
+    Model(#[from] ModelError),
+
+    #[error(transparent)]
+    Journal(#[from] JournalError),
+
+    #[error(transparent)]
+    Flags(#[from] FlagError),
+}
+
+pub type EngineResult<T> = Result<T, EngineError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingestors::IngestorID, models::Venue};
+
+    fn parse_ingestor(id: &str) -> EngineResult<IngestorID> {
+        Ok(id.parse()?)
+    }
+
+    #[test]
+    fn test_engine_error() {
+        let Err(err) = parse_ingestor("kraken") else {
+            panic!("kraken is not an ingestor");
+        };
+        assert!(matches!(err, EngineError::Ingestor(IngestorError::UnknownIngestor(ref id)) if id == "kraken"));
+        assert_eq!(err.to_string(), "Unknown ingestor: kraken");
+
+        let err = EngineError::from(ExecutionError::ClockSkew(Venue::Binance));
+        assert!(matches!(err, EngineError::Execution(ExecutionError::ClockSkew(Venue::Binance))));
+    }
 }
@@ -0,0 +1,259 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use tokio::time::{interval_at, Instant};
+use tracing::{info, warn};
+
+use crate::{
+    config::{ExchangeInfoConfig, ExchangeInfoSourceConfig},
+    models::{InstrumentRegistry, InstrumentType, Venue},
+    rest::RestClients,
+};
+
+/// Filters of one symbol as the venue lists them
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingRules {
+    pub symbol: String,
+    /// Spot and derivatives can share a symbol, the venues list them on separate endpoints
+    pub spot: bool,
+    pub tick_size: Decimal,
+    pub lot_size: Decimal,
+    pub min_notional: Decimal,
+    pub contract_multiplier: Decimal,
+}
+
+/// Decimals are sent as strings by most venues
+fn decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Rules of every symbol in the response of a venue's instrument listing, symbols missing a filter are left out
+pub fn parse_rules(venue: &Venue, body: &Value) -> Result<Vec<TradingRules>> {
+    let rules = match venue {
+        Venue::Binance => body["symbols"]
+            .as_array()
+            .ok_or_else(|| anyhow!("No symbols in the exchange info of {}", venue))?
+            .iter()
+            .filter_map(|s| {
+                let filter = |name: &str| s["filters"].as_array()?.iter().find(|f| f["filterType"] == name).cloned();
+                // Spot calls the filter NOTIONAL, futures MIN_NOTIONAL with a differently named field
+                let min_notional = filter("NOTIONAL")
+                    .and_then(|f| decimal(&f["minNotional"]))
+                    .or_else(|| {
+                        let f = filter("MIN_NOTIONAL")?;
+                        decimal(&f["notional"]).or_else(|| decimal(&f["minNotional"]))
+                    })
+                    .unwrap_or_default();
+                Some(TradingRules {
+                    symbol: s["symbol"].as_str()?.to_owned(),
+                    spot: s.get("contractType").is_none(),
+                    tick_size: decimal(&filter("PRICE_FILTER")?["tickSize"])?,
+                    lot_size: decimal(&filter("LOT_SIZE")?["stepSize"])?,
+                    min_notional,
+                    contract_multiplier: decimal(&s["contractSize"]).unwrap_or(Decimal::ONE),
+                })
+            })
+            .collect(),
+        Venue::Bybit => {
+            let spot = body["result"]["category"] == "spot";
+            body["result"]["list"]
+                .as_array()
+                .ok_or_else(|| anyhow!("No instruments in the instruments info of {}", venue))?
+                .iter()
+                .filter_map(|s| {
+                    let lot = &s["lotSizeFilter"];
+                    Some(TradingRules {
+                        symbol: s["symbol"].as_str()?.to_owned(),
+                        spot,
+                        tick_size: decimal(&s["priceFilter"]["tickSize"])?,
+                        lot_size: decimal(&lot["qtyStep"]).or_else(|| decimal(&lot["basePrecision"]))?,
+                        min_notional: decimal(&lot["minNotionalValue"])
+                            .or_else(|| decimal(&lot["minOrderAmt"]))
+                            .unwrap_or_default(),
+                        contract_multiplier: Decimal::ONE,
+                    })
+                })
+                .collect()
+        }
+        Venue::Coinbase => body
+            .as_array()
+            .ok_or_else(|| anyhow!("No products in the response of {}", venue))?
+            .iter()
+            .filter_map(|p| {
+                Some(TradingRules {
+                    symbol: p["id"].as_str()?.to_owned(),
+                    spot: true,
+                    tick_size: decimal(&p["quote_increment"])?,
+                    lot_size: decimal(&p["base_increment"])?,
+                    min_notional: decimal(&p["min_market_funds"]).unwrap_or_default(),
+                    contract_multiplier: Decimal::ONE,
+                })
+            })
+            .collect(),
+        Venue::Simulation => return Err(anyhow!("No exchange info for {}", venue)),
+    };
+    Ok(rules)
+}
+
+/// Writes the rules into the registry entries of the instruments listed under their symbol, returns how many
+/// instruments were updated. Symbols that are not configured are skipped.
+pub fn apply_rules(instruments: &InstrumentRegistry, venue: &Venue, rules: &[TradingRules]) -> usize {
+    let mut updated = 0;
+    for rule in rules {
+        for instrument in instruments
+            .by_symbol(venue, &rule.symbol)
+            .into_iter()
+            .filter(|i| (i.instrument_type() == &InstrumentType::Spot) == rule.spot)
+        {
+            instruments.update(&instrument, |info| {
+                if info.tick_size.value() != rule.tick_size || info.lot_size.value() != rule.lot_size {
+                    info!(
+                        "Trading rules of {} changed to tick size {} and lot size {}",
+                        rule.symbol, rule.tick_size, rule.lot_size
+                    );
+                }
+                info.tick_size = rule.tick_size.into();
+                info.lot_size = rule.lot_size.into();
+                info.min_notional = rule.min_notional.into();
+                info.contract_multiplier = rule.contract_multiplier;
+            });
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Keeps the tick size, lot size, min notional and contract multiplier of the registry in line with the venues,
+/// so orders are rounded to what the venue accepts
+pub struct ExchangeInfoService {
+    instruments: Arc<InstrumentRegistry>,
+    config: ExchangeInfoConfig,
+    rest: RestClients,
+}
+
+impl ExchangeInfoService {
+    pub fn from_config(instruments: Arc<InstrumentRegistry>, rest: RestClients, config: &ExchangeInfoConfig) -> Self {
+        ExchangeInfoService {
+            instruments,
+            config: config.to_owned(),
+            rest,
+        }
+    }
+
+    /// Loads the rules of every venue, a venue that fails keeps its previous rules
+    pub async fn refresh(&self) -> usize {
+        let mut updated = 0;
+        for source in &self.config.venues {
+            match self.fetch(source).await {
+                Ok(rules) => updated += apply_rules(&self.instruments, &source.venue, &rules),
+                Err(e) => warn!("Failed to load the exchange info of {}: {}", source.venue, e),
+            }
+        }
+        info!(
+            "Loaded the trading rules of {} of {} instruments",
+            updated,
+            self.instruments.len()
+        );
+        updated
+    }
+
+    /// Refreshes at the interval, the first refresh is expected to have happened at startup
+    pub async fn run(self) {
+        let period = Duration::from_secs(self.config.interval);
+        let mut refresh = interval_at(Instant::now() + period, period);
+        loop {
+            refresh.tick().await;
+            self.refresh().await;
+        }
+    }
+
+    async fn fetch(&self, source: &ExchangeInfoSourceConfig) -> Result<Vec<TradingRules>> {
+        let rest = self
+            .rest
+            .get(&source.venue)
+            .ok_or_else(|| anyhow!("No rest client for {}", source.venue))?;
+        let body = rest.get::<Value>(&source.url, &[], source.weight).await?;
+        parse_rules(&source.venue, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::InstrumentConfig, models::Instrument};
+
+    #[test]
+    fn test_exchange_info() {
+        let futures = serde_json::json!({"symbols": [{
+            "symbol": "BTCUSDT",
+            "contractType": "PERPETUAL",
+            "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+                {"filterType": "LOT_SIZE", "minQty": "0.001", "maxQty": "1000", "stepSize": "0.001"},
+                {"filterType": "MIN_NOTIONAL", "notional": "100"}
+            ]
+        }]});
+        let rules = parse_rules(&Venue::Binance, &futures).unwrap();
+        assert_eq!(
+            rules,
+            vec![TradingRules {
+                symbol: "BTCUSDT".into(),
+                spot: false,
+                tick_size: Decimal::new(1, 1),
+                lot_size: Decimal::new(1, 3),
+                min_notional: Decimal::from(100),
+                contract_multiplier: Decimal::ONE,
+            }]
+        );
+        let spot = serde_json::json!({"symbols": [{
+            "symbol": "BTCUSDT",
+            "filters": [
+                {"filterType": "PRICE_FILTER", "tickSize": "0.01000000"},
+                {"filterType": "LOT_SIZE", "stepSize": "0.00001000"},
+                {"filterType": "NOTIONAL", "minNotional": "5.00000000"}
+            ]
+        }]});
+        assert!(parse_rules(&Venue::Binance, &spot).unwrap()[0].spot);
+        let bybit = serde_json::json!({"result": {"category": "linear", "list": [{
+            "symbol": "BTCUSDT",
+            "priceFilter": {"tickSize": "0.10"},
+            "lotSizeFilter": {"qtyStep": "0.001", "minNotionalValue": "5"}
+        }]}});
+        assert_eq!(parse_rules(&Venue::Bybit, &bybit).unwrap()[0].min_notional, Decimal::from(5));
+
+        // Only the perpetual takes the futures rules, the spot instrument shares its symbol
+        let config = |instrument_type| InstrumentConfig {
+            venue: Venue::Binance,
+            symbol: "BTCUSDT".into(),
+            instrument_type,
+            base: "btc".into(),
+            quote: "usdt".into(),
+            maturity: None,
+            strike: None,
+            option_type: None,
+            tick_size: Decimal::ONE,
+            lot_size: Decimal::ONE,
+            min_notional: Decimal::ZERO,
+            contract_multiplier: Decimal::ONE,
+            settlement: "usdt".into(),
+        };
+        let registry =
+            InstrumentRegistry::from_config(&[config(InstrumentType::Perpetual), config(InstrumentType::Spot)]);
+        assert_eq!(apply_rules(&registry, &Venue::Binance, &rules), 1);
+        let perpetual = registry
+            .get(&Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()))
+            .unwrap();
+        assert_eq!(perpetual.tick_size.value(), Decimal::new(1, 1));
+        assert_eq!(perpetual.min_notional.value(), Decimal::from(100));
+        let spot = registry
+            .get(&Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into()))
+            .unwrap();
+        assert_eq!(spot.tick_size.value(), Decimal::ONE);
+    }
+}
@@ -1,22 +1,26 @@
 use crate::{
     config::BinanceExecutionConfig,
+    credentials::Credentials,
     models::{Fill, Order, Venue},
 };
 use rust_decimal::Decimal;
+use tracing::warn;
 
-use super::ExecutionEndpoint;
+use super::{ExecutionEndpoint, ExecutionError};
 
 #[derive(Clone)]
 #[allow(unused)]
 pub struct BinanceEndpoint {
+    credentials: Credentials,
     max_orders_per_minute: u64,
     max_order_size_notional: Decimal,
     min_order_size_notional: Decimal,
 }
 
 impl BinanceEndpoint {
-    pub fn from_config(config: &BinanceExecutionConfig) -> Self {
+    pub fn from_config(credentials: Credentials, config: &BinanceExecutionConfig) -> Self {
         BinanceEndpoint {
+            credentials,
             max_orders_per_minute: config.max_orders_per_minute,
             max_order_size_notional: config.max_order_size_notional,
             min_order_size_notional: config.min_order_size_notional,
@@ -29,7 +33,18 @@ impl ExecutionEndpoint for BinanceEndpoint {
         &Venue::Binance
     }
 
-    fn place_orders(&self, _order: Vec<Order>) -> Vec<Fill> {
-        todo!()
+    /// Live orders are not sent to binance yet, only the simulation endpoint executes them
+    fn place_orders(&self, _orders: Vec<Order>) -> Result<Vec<Fill>, ExecutionError> {
+        Err(ExecutionError::NotSupported(Venue::Binance))
+    }
+
+    fn cancel_all(&self) -> usize {
+        warn!("Cancelling orders is not supported on binance yet");
+        0
+    }
+
+    fn is_healthy(&self) -> bool {
+        // Placing orders is not implemented yet
+        false
     }
 }
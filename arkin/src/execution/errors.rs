@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+use crate::{
+    credentials::CredentialsError,
+    models::{errors::ModelError, Venue},
+};
+
+#[derive(Error, Debug)]
+pub enum ExecutionError {
+    #[error("No execution endpoint for {0}")]
+    UnknownEndpoint(Venue),
+
+    #[error("Placing orders is not supported on {0} yet")]
+    NotSupported(Venue),
+
+    #[error("Orders to {0} held back because of clock skew")]
+    ClockSkew(Venue),
+
+    #[error(transparent)]
+    InvalidOrder(#[from] ModelError),
+
+    #[error("Execution endpoint of {0} needs credentials: {1}")]
+    MissingCredentials(Venue, CredentialsError),
+}
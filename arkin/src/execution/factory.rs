@@ -1,27 +1,58 @@
 use std::sync::Arc;
 
-use crate::{config::ExecutionEndpointConfig, state::StateManager};
+use crate::{config::ExecutionEndpointConfig, credentials::CredentialStore, models::Venue, state::StateManager};
 
-use super::{binance::BinanceEndpoint, ExecutionEndpoint, SimulationEndpoint};
+use super::{binance::BinanceEndpoint, ExecutionEndpoint, ExecutionError, SimulationEndpoint};
 
 pub struct ExecutionEndpointFactory {}
 
 impl ExecutionEndpointFactory {
     pub fn from_config(
         state: Arc<StateManager>,
+        seed: u64,
+        credentials: &CredentialStore,
         configs: &[ExecutionEndpointConfig],
-    ) -> Vec<Box<dyn ExecutionEndpoint>> {
+    ) -> Result<Vec<Box<dyn ExecutionEndpoint>>, ExecutionError> {
         configs
             .iter()
             .map(|config| {
                 let endpoint: Box<dyn ExecutionEndpoint> = match config {
                     ExecutionEndpointConfig::Simulation(c) => {
-                        Box::new(SimulationEndpoint::from_config(state.clone(), c))
+                        Box::new(SimulationEndpoint::from_config(state.clone(), seed, c))
+                    }
+                    ExecutionEndpointConfig::Binance(c) => {
+                        let credentials = credentials
+                            .get(&c.credentials)
+                            .map_err(|e| ExecutionError::MissingCredentials(Venue::Binance, e))?;
+                        Box::new(BinanceEndpoint::from_config(credentials, c))
                     }
-                    ExecutionEndpointConfig::Binance(c) => Box::new(BinanceEndpoint::from_config(c)),
                 };
-                endpoint
+                Ok(endpoint)
             })
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BinanceExecutionConfig;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_missing_credentials() {
+        let config = ExecutionEndpointConfig::Binance(BinanceExecutionConfig {
+            credentials: "binance".into(),
+            max_orders_per_minute: 60,
+            max_order_size_notional: Decimal::from(1000),
+            min_order_size_notional: Decimal::from(10),
+        });
+        let endpoints = ExecutionEndpointFactory::from_config(
+            Arc::new(StateManager::default()),
+            42,
+            &CredentialStore::default(),
+            &[config],
+        );
+        assert!(matches!(endpoints, Err(ExecutionError::MissingCredentials(Venue::Binance, _))));
+    }
+}
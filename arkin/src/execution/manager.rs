@@ -1,36 +1,133 @@
-use tracing::{debug, warn};
+use rust_decimal::Decimal;
+use tracing::{debug, error, info, warn};
 
-use super::{Execution, ExecutionEndpoint, ExecutionEndpointFactory};
+use super::{Execution, ExecutionEndpoint, ExecutionEndpointFactory, ExecutionError};
 use crate::{
-    config::ExecutionManagerConfig,
-    models::{Allocation, Event, Notional, Order, Position, Price, Tick, Venue},
+    bus::EventBus,
+    config::{ExecutionEndpointConfig, ExecutionManagerConfig},
+    credentials::CredentialStore,
+    flags::FlagRegistry,
+    metrics::METRICS,
+    models::{
+        Allocation, InstrumentRegistry, InstrumentType, Notional, Order, Price, Quantity, RiskEvent, Tick, Venue,
+    },
     portfolio::Portfolio,
+    skew::SkewGuard,
     state::StateManager,
 };
 use core::fmt;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 pub struct ExecutionManager {
     state: Arc<StateManager>,
+    bus: Arc<EventBus>,
     portfolio: Arc<Portfolio>,
     endpoints: HashMap<Venue, Box<dyn ExecutionEndpoint>>,
     default_endpoint: Venue,
     rebalance_threshold: Notional,
+    next_order_id: AtomicU64,
+    skew_guard: Option<Arc<SkewGuard>>,
+    flags: Option<Arc<FlagRegistry>>,
+    instruments: Arc<InstrumentRegistry>,
 }
 
 impl ExecutionManager {
-    pub fn from_config(state: Arc<StateManager>, portfolio: Arc<Portfolio>, config: &ExecutionManagerConfig) -> Self {
-        let endpoints = ExecutionEndpointFactory::from_config(state.clone(), &config.endpoints)
+    pub fn from_config(
+        state: Arc<StateManager>,
+        bus: Arc<EventBus>,
+        portfolio: Arc<Portfolio>,
+        seed: u64,
+        credentials: &CredentialStore,
+        config: &ExecutionManagerConfig,
+    ) -> Result<Self, ExecutionError> {
+        for endpoint in &config.endpoints {
+            if let ExecutionEndpointConfig::Simulation(c) = endpoint {
+                for (asset, amount) in &c.balances {
+                    portfolio.deposit(&Venue::Simulation, &asset.as_str().into(), *amount);
+                }
+            }
+        }
+        let endpoints = ExecutionEndpointFactory::from_config(state.clone(), seed, credentials, &config.endpoints)?
             .into_iter()
             .map(|endpoint| (endpoint.venue().clone(), endpoint))
             .collect();
-        Self {
+        Ok(Self {
             state,
+            bus,
             endpoints,
             portfolio,
             default_endpoint: config.default_endpoint.clone(),
             rebalance_threshold: config.rebalance_threshold.into(),
+            next_order_id: AtomicU64::new(1),
+            skew_guard: None,
+            flags: None,
+            instruments: Arc::new(InstrumentRegistry::default()),
+        })
+    }
+
+    /// Hold back orders to venues whose clock is too far off
+    pub fn with_skew_guard(mut self, guard: Arc<SkewGuard>) -> Self {
+        self.skew_guard = Some(guard);
+        self
+    }
+
+    /// Round orders to the lot size of the venue and drop the ones below its minimum notional
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    /// Skip allocations of strategies, venues and instruments that trading is switched off for
+    pub fn with_flags(mut self, flags: Arc<FlagRegistry>) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+}
+
+impl ExecutionManager {
+    /// Continue numbering orders after the ones that were placed before a restart
+    pub fn resume_order_ids(&self, last_order_id: u64) {
+        self.next_order_id.fetch_max(last_order_id + 1, Ordering::Relaxed);
+    }
+
+    /// Cancel the resting orders on every venue, returns how many were cancelled
+    pub fn cancel_all_orders(&self) -> usize {
+        self.endpoints
+            .iter()
+            .map(|(venue, endpoint)| {
+                let cancelled = endpoint.cancel_all();
+                info!(venue = %venue, cancelled, "Cancelled orders");
+                cancelled
+            })
+            .sum()
+    }
+
+    /// The endpoint orders are routed to, unless clock skew blocks it
+    fn endpoint(&self) -> Result<&dyn ExecutionEndpoint, ExecutionError> {
+        if self.skew_guard.as_ref().is_some_and(|g| g.is_blocked(&self.default_endpoint)) {
+            return Err(ExecutionError::ClockSkew(self.default_endpoint.clone()));
         }
+        self.endpoints
+            .get(&self.default_endpoint)
+            .map(|e| e.as_ref())
+            .ok_or_else(|| ExecutionError::UnknownEndpoint(self.default_endpoint.clone()))
+    }
+
+    /// Every endpoint and whether it can take orders, a venue blocked by clock skew can't
+    pub fn endpoint_health(&self) -> Vec<(Venue, bool)> {
+        self.endpoints
+            .iter()
+            .map(|(venue, endpoint)| {
+                let blocked = self.skew_guard.as_ref().is_some_and(|g| g.is_blocked(venue));
+                (venue.clone(), endpoint.is_healthy() && !blocked)
+            })
+            .collect()
     }
 }
 
@@ -42,14 +139,33 @@ impl Execution for ExecutionManager {
         }
 
         let positions = self.portfolio.positions(&allocations[0].event_time);
+        let allocations = allocations.iter().filter(|a| {
+            let flag = self.flags.as_ref().and_then(|f| f.blocking(&a.strategy_id, &a.instrument));
+            if let Some(flag) = &flag {
+                debug!(instrument = %a.instrument, strategy_id = %a.strategy_id, flag = %flag, "Trading disabled");
+            }
+            flag.is_none()
+        });
 
         // Difference between current position and allocation
-        let new_allocations = allocations.iter().filter_map(|a| {
-            let pos = positions
+        let new_allocations = allocations.filter_map(|a| {
+            if a.instrument.is_expired(a.event_time) {
+                debug!(instrument = %a.instrument, strategy_id = %a.strategy_id, "Instrument expired");
+                return None;
+            }
+            // No position yet means we are flat on this instrument
+            let quantity = positions
                 .get(&(a.strategy_id.clone(), a.instrument.clone()))
-                .expect("There should be a position");
+                .map(|p| p.quantity)
+                .unwrap_or(Quantity::from(0.));
             if let Some(tick) = self.state.latest_event_by_instrument::<Tick>(&a.instrument, &a.event_time) {
-                Some(EnrichedAllocation::new(tick.mid_price(), a.clone(), pos.clone()))
+                let multiplier = self.instruments.get(&a.instrument).map(|i| i.contract_multiplier);
+                Some(EnrichedAllocation::new(
+                    tick.mid_price(),
+                    a.clone(),
+                    quantity,
+                    multiplier.unwrap_or(Decimal::ONE),
+                ))
             } else {
                 warn!("No price found for instrument: {}", a.instrument);
                 None
@@ -63,29 +179,110 @@ impl Execution for ExecutionManager {
             .collect::<Vec<_>>();
 
         for a in &filtered_allocations {
-            debug!("Final allocation: {}", a);
+            debug!(
+                instrument = %a.allocation.instrument,
+                strategy_id = %a.allocation.strategy_id,
+                notional = %a.allocation.notional,
+                difference = %a.difference(),
+                "Final allocation"
+            );
         }
 
         // Create orders
-        let orders = filtered_allocations
+        let mut orders = filtered_allocations
             .into_iter()
-            .map(|a| {
-                let quantity = a.difference() / a.current_price;
-                Order::new_market(
+            .filter_map(|a| {
+                let mut quantity = Quantity::from((a.difference() / a.current_price).value() / a.multiplier);
+                // Spot can't go short, only sell what is held
+                if a.allocation.instrument.instrument_type() == &InstrumentType::Spot
+                    && (a.current_quantity + quantity).is_negative()
+                {
+                    quantity = Quantity::from(0.) - a.current_quantity;
+                }
+                if quantity.is_zero() {
+                    return None;
+                }
+                if let Some(info) = self.instruments.get(&a.allocation.instrument) {
+                    quantity = info.round_quantity(quantity);
+                    if quantity.is_zero() || !info.meets_min_notional(a.current_price, quantity) {
+                        debug!(
+                            instrument = %a.allocation.instrument,
+                            strategy_id = %a.allocation.strategy_id,
+                            quantity = %quantity,
+                            "Order below the minimum size of the venue"
+                        );
+                        return None;
+                    }
+                }
+                Some(Order::new_market(
                     a.allocation.event_time,
+                    self.next_order_id.fetch_add(1, Ordering::Relaxed),
                     a.allocation.instrument,
                     a.allocation.strategy_id,
                     quantity,
-                )
+                ))
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        // Mimick execution by filling all orders and update the state with fills
-        if let Some(endpoint) = self.endpoints.get(&self.default_endpoint) {
-            let fills = endpoint.place_orders(orders);
-            for fill in fills {
-                self.state.add_event(Event::Fill(fill));
+        let endpoint = match self.endpoint() {
+            Ok(endpoint) => endpoint,
+            Err(e @ ExecutionError::ClockSkew(_)) => {
+                warn!("{}", e);
+                for order in orders {
+                    self.bus.publish(RiskEvent::new(
+                        order.event_time,
+                        order.instrument,
+                        order.strategy_id,
+                        "clock skew",
+                    ));
+                }
+                return;
+            }
+            Err(e) => {
+                error!("{}", e);
+                return;
             }
+        };
+
+        // Mimick execution by filling all orders and publish the fills
+        let venue = self.default_endpoint.to_string();
+        for order in &mut orders {
+            // Fresh orders can always be submitted
+            let _ = order.submit(order.event_time);
+            self.bus.publish(order.clone());
+        }
+        METRICS.orders.with_label_values(&[&venue]).inc_by(orders.len() as u64);
+
+        let fills = endpoint.place_orders(orders.clone()).unwrap_or_else(|e| {
+            error!(venue = %venue, "Failed to place orders: {}", e);
+            Vec::new()
+        });
+        METRICS.fills.with_label_values(&[&venue]).inc_by(fills.len() as u64);
+
+        // Publish the final state of every order
+        for mut order in orders {
+            let order_fills = fills.iter().filter(|f| f.order_id == order.order_id).collect::<Vec<_>>();
+            if !order_fills.is_empty() {
+                for fill in order_fills {
+                    if let Err(e) = order.fill(fill.price, fill.quantity, fill.event_time) {
+                        warn!(venue = %venue, "Dropped fill: {}", e);
+                    }
+                }
+            } else {
+                METRICS.rejected_orders.with_label_values(&[&venue]).inc();
+                self.bus.publish(RiskEvent::new(
+                    order.event_time,
+                    order.instrument.clone(),
+                    order.strategy_id.clone(),
+                    "order rejected",
+                ));
+                let _ = order.reject(order.event_time);
+            }
+            self.bus.publish(order);
+        }
+        for fill in fills {
+            self.portfolio.apply_fill(endpoint.venue(), &fill);
+            self.bus.publish(fill);
         }
     }
 }
@@ -93,24 +290,27 @@ impl Execution for ExecutionManager {
 struct EnrichedAllocation {
     current_price: Price,
     allocation: Allocation,
-    position: Position,
+    current_quantity: Quantity,
+    /// Units of the base asset in one contract
+    multiplier: Decimal,
 }
 
 impl EnrichedAllocation {
-    fn new(current_price: Price, allocation: Allocation, position: Position) -> Self {
+    fn new(current_price: Price, allocation: Allocation, current_quantity: Quantity, multiplier: Decimal) -> Self {
         Self {
             current_price,
             allocation,
-            position,
+            current_quantity,
+            multiplier,
         }
     }
 
     fn difference(&self) -> Notional {
-        self.allocation.notional - self.current_price * self.position.quantity
+        self.allocation.notional - self.exposure()
     }
 
     fn exposure(&self) -> Notional {
-        self.current_price * self.position.quantity
+        (self.current_price * self.current_quantity) * self.multiplier
     }
 }
 
@@ -135,9 +335,9 @@ impl fmt::Display for EnrichedAllocation {
 mod tests {
     use super::*;
     use crate::{
-        config::{ExecutionEndpointConfig, SimulationConfig},
+        config::{self, SimulationConfig},
         logging,
-        models::Notional,
+        models::{Fill, Notional},
         portfolio::Portfolio,
         test_utils,
     };
@@ -152,23 +352,35 @@ mod tests {
 
         let state = test_utils::TestStateBuilder::default().add_ticks(&instrument).build();
         let portfolio = Arc::new(Portfolio::new(state.clone(), Notional::from(1000.)));
+        let bus = Arc::new(EventBus::from_config(&config::load().bus));
+        let mut fills = bus.subscribe::<Fill>();
         let manager = ExecutionManager::from_config(
             state,
+            bus,
             portfolio,
+            42,
+            &CredentialStore::default(),
             &ExecutionManagerConfig {
                 endpoints: vec![ExecutionEndpointConfig::Simulation(SimulationConfig {
                     latency: 200,
+                    latency_jitter: 50,
                     commission_maker: Decimal::from_f64(0.00015).unwrap(),
                     commission_taker: Decimal::from_f64(0.0003).unwrap(),
                     max_orders_per_minute: 60,
                     max_order_size_notional: Decimal::from_f64(2000.).unwrap(),
                     min_order_size_notional: Decimal::from_f64(10.).unwrap(),
+                    balances: [("usdt".into(), Decimal::from(1000))].into(),
                 })],
                 default_endpoint: Venue::Simulation,
                 rebalance_threshold: Decimal::from_f64(50.).unwrap(),
             },
-        );
+        )
+        .unwrap();
 
         manager.allocate(&allocations);
+        assert!(fills.try_recv().is_some());
+        // The simulated account paid the commission of the fills
+        let usdt = manager.portfolio.free_balance(&Venue::Simulation, &"usdt".into());
+        assert!(usdt > Decimal::ZERO && usdt < Decimal::from(1000));
     }
 }
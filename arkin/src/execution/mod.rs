@@ -1,8 +1,10 @@
 mod binance;
+mod errors;
 mod factory;
 mod manager;
 mod simulation;
 
+pub use errors::ExecutionError;
 pub use factory::ExecutionEndpointFactory;
 pub use manager::ExecutionManager;
 pub use simulation::SimulationEndpoint;
@@ -15,5 +17,9 @@ pub trait Execution: Send + Sync {
 
 pub trait ExecutionEndpoint: Send + Sync {
     fn venue(&self) -> &Venue;
-    fn place_orders(&self, order: Vec<Order>) -> Vec<Fill>;
+    fn place_orders(&self, order: Vec<Order>) -> Result<Vec<Fill>, ExecutionError>;
+    /// Cancel every resting order, returns how many were cancelled
+    fn cancel_all(&self) -> usize;
+    /// Whether the endpoint can take orders right now
+    fn is_healthy(&self) -> bool;
 }
@@ -1,28 +1,36 @@
 use std::{sync::Arc, time::Duration};
 
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng};
+
 use crate::{
     config::SimulationConfig,
-    models::{Fill, Order, Tick, Venue},
-    state::StateManager,
+    models::{Fill, Order, Price, Tick, TimeInForce, Venue},
+    state::{without_lookahead_guard, StateManager},
+    utils::seeded_rng,
 };
 use rust_decimal::prelude::*;
 use tracing::{debug, info, warn};
 
-use super::ExecutionEndpoint;
+use super::{ExecutionEndpoint, ExecutionError};
 
 pub struct SimulationEndpoint {
     state: Arc<StateManager>,
     latency: Duration,
+    latency_jitter: u64,
+    rng: Mutex<StdRng>,
     _commission_maker: Decimal,
     commission_taker: Decimal,
     _max_orders_per_minute: u64,
 }
 
 impl SimulationEndpoint {
-    pub fn from_config(state: Arc<StateManager>, config: &SimulationConfig) -> Self {
+    pub fn from_config(state: Arc<StateManager>, seed: u64, config: &SimulationConfig) -> Self {
         SimulationEndpoint {
             state,
             latency: Duration::from_millis(config.latency),
+            latency_jitter: config.latency_jitter,
+            rng: Mutex::new(seeded_rng(seed, "simulation")),
             _commission_maker: config.commission_maker,
             commission_taker: config.commission_taker,
             _max_orders_per_minute: config.max_orders_per_minute,
@@ -30,24 +38,78 @@ impl SimulationEndpoint {
     }
 }
 
+/// Price the order executes at against the tick, none if it doesn't execute right away. Nothing rests on the
+/// simulated book, so untriggered stops, limits away from the market and post only orders are not filled.
+fn execution_price(order: &Order, tick: &Tick) -> Option<Price> {
+    let buy = order.quantity.value().is_sign_positive();
+    let mid = tick.mid_price();
+    if let Some(trigger) = order.trigger_price {
+        let triggered = if buy {
+            mid.value() >= trigger.value()
+        } else {
+            mid.value() <= trigger.value()
+        };
+        if !triggered {
+            return None;
+        }
+    }
+    let Some(limit) = order.price else {
+        return Some(mid);
+    };
+    let marketable = if buy {
+        tick.ask_price.value() <= limit.value()
+    } else {
+        tick.bid_price.value() >= limit.value()
+    };
+    // Marketable limits fill at the mid like market orders, which is within the limit as the touch is
+    (marketable && order.time_in_force != TimeInForce::Gtx).then_some(mid)
+}
+
 impl ExecutionEndpoint for SimulationEndpoint {
     fn venue(&self) -> &Venue {
         &Venue::Simulation
     }
 
-    fn place_orders(&self, orders: Vec<Order>) -> Vec<Fill> {
+    fn place_orders(&self, orders: Vec<Order>) -> Result<Vec<Fill>, ExecutionError> {
         // Simulate order placement
-        orders
+        let fills = orders
             .into_iter()
             .filter_map(|o| {
-                if let Some(tick) = self
-                    .state
-                    .latest_event_by_instrument::<Tick>(&o.instrument, &(o.event_time + self.latency))
+                if let Err(e) = o.validate() {
+                    warn!(instrument = %o.instrument, strategy_id = %o.strategy_id, "Order rejected, {}", e);
+                    return None;
+                }
+                // The exchange sees the market after the latency, which is ahead of the strategy's clock
+                let jitter = Duration::from_millis(self.rng.lock().gen_range(0..=self.latency_jitter));
+                let fill_time = o.event_time + self.latency + jitter;
+                if let Some(tick) =
+                    without_lookahead_guard(|| self.state.latest_event_by_instrument::<Tick>(&o.instrument, &fill_time))
                 {
-                    debug!("Placing order: {}", o);
-                    Some((o, tick.mid_price()))
+                    debug!(
+                        instrument = %o.instrument,
+                        strategy_id = %o.strategy_id,
+                        order_id = o.order_id,
+                        order_type = %o.order_type,
+                        quantity = %o.quantity,
+                        "Placing order"
+                    );
+                    let price = execution_price(&o, &tick);
+                    if price.is_none() {
+                        info!(
+                            instrument = %o.instrument,
+                            strategy_id = %o.strategy_id,
+                            order_id = o.order_id,
+                            "Order not filled, it would have to rest on the book"
+                        );
+                    }
+                    price.map(|p| (o, p))
                 } else {
-                    warn!("Order rejected: {}", o);
+                    warn!(
+                        instrument = %o.instrument,
+                        strategy_id = %o.strategy_id,
+                        order_id = o.order_id,
+                        "Order rejected, no price"
+                    );
                     None
                 }
             })
@@ -62,10 +124,73 @@ impl ExecutionEndpoint for SimulationEndpoint {
                     (p * o.quantity) * self.commission_taker,
                 )
             })
-            .map(|f| {
-                info!("Order filled: {}", f);
-                f
+            .inspect(|f| {
+                info!(
+                    instrument = %f.instrument,
+                    strategy_id = %f.strategy_id,
+                    order_id = f.order_id,
+                    price = %f.price,
+                    quantity = %f.quantity,
+                    commission = %f.commission,
+                    "Order filled"
+                )
             })
-            .collect()
+            .collect();
+        Ok(fills)
+    }
+
+    fn cancel_all(&self) -> usize {
+        // Orders are filled or rejected right away, nothing is left resting
+        0
+    }
+
+    fn is_healthy(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_execution_price() {
+        let time = datetime!(2024-01-01 00:00 UTC);
+        let instrument = test_utils::test_perp_instrument();
+        let tick = Tick::new(
+            time,
+            instrument.clone(),
+            1,
+            Price::from(99.),
+            Decimal::ONE.into(),
+            Price::from(101.),
+            Decimal::ONE.into(),
+        );
+        let buy = Decimal::ONE.into();
+        let sell = Decimal::NEGATIVE_ONE.into();
+        let limit =
+            |quantity, price: f64| Order::new_limit(time, 1, instrument.clone(), "test".into(), quantity, price.into());
+        let stop = |quantity, trigger: f64| {
+            Order::new_stop(time, 1, instrument.clone(), "test".into(), quantity, trigger.into())
+        };
+
+        let market = Order::new_market(time, 1, instrument.clone(), "test".into(), buy);
+        assert_eq!(execution_price(&market, &tick), Some(Price::from(100.)));
+        // Only limits that cross the spread are filled
+        assert_eq!(execution_price(&limit(buy, 101.), &tick), Some(Price::from(100.)));
+        assert_eq!(execution_price(&limit(buy, 100.), &tick), None);
+        assert_eq!(execution_price(&limit(sell, 99.), &tick), Some(Price::from(100.)));
+        assert_eq!(
+            execution_price(&limit(buy, 101.).with_time_in_force(TimeInForce::Gtx), &tick),
+            None
+        );
+        // Stops fill once the market is through the trigger
+        assert_eq!(execution_price(&stop(buy, 100.), &tick), Some(Price::from(100.)));
+        assert_eq!(execution_price(&stop(buy, 102.), &tick), None);
+        assert_eq!(execution_price(&stop(sell, 98.), &tick), None);
+        let stop_limit = Order::new_stop_limit(time, 1, instrument, "test".into(), sell, 100.5.into(), 100.0.into());
+        assert_eq!(execution_price(&stop_limit, &tick), None);
     }
 }
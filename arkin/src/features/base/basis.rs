@@ -0,0 +1,107 @@
+use crate::config::BasisFeatureConfig;
+use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError};
+use std::collections::HashMap;
+use tracing::debug;
+
+const SECONDS_PER_YEAR: f64 = 365. * 24. * 60. * 60.;
+
+/// Basis of a perpetual over its spot and the yearly carry of holding the spot against a short perpetual
+#[derive(Debug)]
+pub struct BasisFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    fundings_per_year: f64,
+    output_basis: FeatureId,
+    output_carry: FeatureId,
+}
+
+impl BasisFeature {
+    pub fn from_config(config: &BasisFeatureConfig) -> Self {
+        BasisFeature {
+            id: config.id.to_owned(),
+            sources: vec![
+                config.input_spot.from.clone(),
+                config.input_mark.from.clone(),
+                config.input_funding.from.clone(),
+            ],
+            inputs: vec![
+                config.input_spot.to_owned().into(),
+                config.input_mark.to_owned().into(),
+                config.input_funding.to_owned().into(),
+            ],
+            fundings_per_year: SECONDS_PER_YEAR / config.funding_interval as f64,
+            output_basis: config.output_basis.to_owned(),
+            output_carry: config.output_carry.to_owned(),
+        }
+    }
+}
+
+impl Feature for BasisFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating basis with id: {}", self.id);
+        let value = |i: usize| data.last(self.inputs[i].feature_id()).unwrap_or(f64::NAN);
+        let (spot, mark, funding) = (value(0), value(1), value(2));
+
+        let mut res = HashMap::new();
+        res.insert(self.output_basis.clone(), mark / spot - 1.);
+        res.insert(self.output_carry.clone(), funding * self.fundings_per_year);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::LatestInputConfig, test_utils};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_basis() {
+        let input = |feature_id: &str| LatestInputConfig {
+            from: "base".into(),
+            feature_id: feature_id.into(),
+        };
+        let feature = BasisFeature::from_config(&BasisFeatureConfig {
+            id: "basis".into(),
+            input_spot: input("index_price"),
+            input_mark: input("mark_price"),
+            input_funding: input("funding_rate"),
+            funding_interval: 28800,
+            output_basis: "basis".into(),
+            output_carry: "carry".into(),
+        });
+
+        let instrument = test_utils::test_perp_instrument();
+        let data = FeatureDataResponse::new(
+            instrument.clone(),
+            OffsetDateTime::UNIX_EPOCH,
+            HashMap::from([
+                ("index_price".into(), vec![100.]),
+                ("mark_price".into(), vec![100.5]),
+                ("funding_rate".into(), vec![0.0001]),
+            ]),
+        );
+        let res = feature.calculate(data).unwrap();
+        assert!((res["basis"] - 0.005).abs() < 1e-12);
+        // Three fundings a day
+        assert!((res["carry"] - 0.1095).abs() < 1e-12);
+
+        let res = feature
+            .calculate(FeatureDataResponse::new(instrument, OffsetDateTime::UNIX_EPOCH, HashMap::new()))
+            .unwrap();
+        assert!(res["basis"].is_nan() && res["carry"].is_nan());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::config::{CalendarFeatureConfig, SessionConfig};
+use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError};
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use time::OffsetDateTime;
+use tracing::debug;
+
+/// Features of the time the pipeline runs for in UTC, it reads no inputs.
+/// The hour goes around the circle so 23:59 and 00:00 end up next to each other.
+#[derive(Debug)]
+pub struct CalendarFeature {
+    id: NodeId,
+    funding_interval: i64,
+    sessions: Vec<SessionConfig>,
+    output_hour_sin: FeatureId,
+    output_hour_cos: FeatureId,
+    output_day_of_week: FeatureId,
+    output_since_funding: FeatureId,
+}
+
+impl CalendarFeature {
+    pub fn from_config(config: &CalendarFeatureConfig) -> Self {
+        CalendarFeature {
+            id: config.id.to_owned(),
+            funding_interval: config.funding_interval as i64,
+            sessions: config.sessions.to_owned(),
+            output_hour_sin: config.output_hour_sin.to_owned(),
+            output_hour_cos: config.output_hour_cos.to_owned(),
+            output_day_of_week: config.output_day_of_week.to_owned(),
+            output_since_funding: config.output_since_funding.to_owned(),
+        }
+    }
+
+    fn encode(&self, time: &OffsetDateTime) -> HashMap<FeatureId, f64> {
+        let time = time.to_offset(time::UtcOffset::UTC);
+        let seconds = time.time().as_hms_nano();
+        let seconds = seconds.0 as f64 * 3600. + seconds.1 as f64 * 60. + seconds.2 as f64 + seconds.3 as f64 / 1e9;
+        let hour = seconds / 3600.;
+
+        let mut res = HashMap::new();
+        res.insert(self.output_hour_sin.clone(), (hour / 24. * TAU).sin());
+        res.insert(self.output_hour_cos.clone(), (hour / 24. * TAU).cos());
+        res.insert(self.output_day_of_week.clone(), time.weekday().number_days_from_monday() as f64);
+        // Funding times are counted from midnight UTC
+        res.insert(
+            self.output_since_funding.clone(),
+            time.unix_timestamp().rem_euclid(self.funding_interval) as f64,
+        );
+        for session in &self.sessions {
+            let (start, end) = (session.start as f64, session.end as f64);
+            let open = match start <= end {
+                true => hour >= start && hour < end,
+                false => hour >= start || hour < end,
+            };
+            res.insert(session.output.clone(), open as u8 as f64);
+        }
+        res
+    }
+}
+
+impl Feature for CalendarFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &[]
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &[]
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating calendar with id: {}", self.id);
+        Ok(self.encode(data.timestamp()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_calendar() {
+        let session = |output: &str, start, end| SessionConfig {
+            output: output.into(),
+            start,
+            end,
+        };
+        let feature = CalendarFeature::from_config(&CalendarFeatureConfig {
+            id: "calendar".into(),
+            funding_interval: 28800,
+            sessions: vec![session("asia", 23, 8), session("us", 13, 21)],
+            output_hour_sin: "hour_sin".into(),
+            output_hour_cos: "hour_cos".into(),
+            output_day_of_week: "day_of_week".into(),
+            output_since_funding: "since_funding".into(),
+        });
+
+        // A Wednesday at 18:00
+        let res = feature.encode(&datetime!(2024-01-03 18:00:00).assume_utc());
+        assert!((res["hour_sin"] + 1.).abs() < 1e-12 && res["hour_cos"].abs() < 1e-12);
+        assert_eq!(res["day_of_week"], 2.);
+        assert_eq!(res["since_funding"], 7200.);
+        assert_eq!((res["asia"], res["us"]), (0., 1.));
+
+        let res = feature.encode(&datetime!(2024-01-07 23:30:00).assume_utc());
+        assert_eq!(res["day_of_week"], 6.);
+        assert_eq!((res["asia"], res["us"]), (1., 0.));
+    }
+}
@@ -1,8 +1,7 @@
 use crate::{
     config::CountFeatureConfig,
-    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId},
+    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError},
 };
-use anyhow::Result;
 use std::collections::HashMap;
 use tracing::debug;
 
@@ -38,7 +37,7 @@ impl Feature for CountFeature {
         &self.inputs
     }
 
-    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>> {
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
         debug!("Calculating count with id: {}", self.id);
         let count = data.count(self.inputs[0].feature_id()).unwrap_or(0.);
         let mut res = HashMap::new();
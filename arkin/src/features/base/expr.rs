@@ -0,0 +1,308 @@
+use crate::config::ExprFeatureConfig;
+use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{message} at position {position} in '{expression}'")]
+pub struct ExprError {
+    pub expression: String,
+    pub position: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, value: &impl Fn(&str) -> f64) -> f64 {
+        match self {
+            Node::Number(n) => *n,
+            Node::Variable(name) => value(name),
+            Node::Neg(node) => -node.eval(value),
+            Node::Binary(op, left, right) => {
+                let (l, r) = (left.eval(value), right.eval(value));
+                match op {
+                    '+' => l + r,
+                    '-' => l - r,
+                    '*' => l * r,
+                    _ => l / r,
+                }
+            }
+        }
+    }
+
+    fn variables<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+        match self {
+            Node::Number(_) => {}
+            Node::Variable(name) => {
+                names.insert(name);
+            }
+            Node::Neg(node) => node.variables(names),
+            Node::Binary(_, left, right) => {
+                left.variables(names);
+                right.variables(names);
+            }
+        }
+    }
+}
+
+/// Arithmetic over named values with + - * /, unary minus and parentheses, like `(a - b) / c`.
+/// It is parsed when the config is loaded, so a typo fails the start instead of the pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let root = parser.expression()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(Expr {
+            source: source.to_owned(),
+            root,
+        })
+    }
+
+    /// Names the expression reads
+    pub fn variables(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        self.root.variables(&mut names);
+        names
+    }
+
+    pub fn eval(&self, value: impl Fn(&str) -> f64) -> f64 {
+        self.root.eval(&value)
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = ExprError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Expr::parse(&source)
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> Self {
+        expr.source
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '+' | '-' | '*' | '/' => Token::Op(c),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = pos + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.') {
+                    end = i + c.len_utf8();
+                }
+                match source[pos..end].parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => {
+                        return Err(ExprError {
+                            expression: source.to_owned(),
+                            position: pos,
+                            message: format!("invalid number '{}'", &source[pos..end]),
+                        })
+                    }
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = pos + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_') {
+                    end = i + c.len_utf8();
+                }
+                Token::Name(source[pos..end].to_owned())
+            }
+            c => {
+                return Err(ExprError {
+                    expression: source.to_owned(),
+                    position: pos,
+                    message: format!("unexpected character '{}'", c),
+                })
+            }
+        };
+        tokens.push((pos, token));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, * and / bind tighter than + and -, all of them from the left
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ExprError {
+        ExprError {
+            expression: self.source.to_owned(),
+            position: self.tokens.get(self.pos).map(|(p, _)| *p).unwrap_or(self.source.len()),
+            message: message.to_owned(),
+        }
+    }
+
+    fn peek_op(&self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some((_, Token::Op(op))) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.term()?;
+        while let Some(op) = self.peek_op(&['+', '-']) {
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.factor()?;
+        while let Some(op) = self.peek_op(&['*', '/']) {
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.factor()?));
+        }
+        Ok(node)
+    }
+
+    fn factor(&mut self) -> Result<Node, ExprError> {
+        let Some((_, token)) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.error("unexpected end"));
+        };
+        let node = match token {
+            Token::Number(n) => Node::Number(n),
+            Token::Name(name) => Node::Variable(name),
+            Token::Op('-') => {
+                self.pos += 1;
+                return Ok(Node::Neg(Box::new(self.factor()?)));
+            }
+            Token::Open => {
+                self.pos += 1;
+                let node = self.expression()?;
+                if !matches!(self.tokens.get(self.pos), Some((_, Token::Close))) {
+                    return Err(self.error("expected ')'"));
+                }
+                node
+            }
+            Token::Op(_) | Token::Close => return Err(self.error("unexpected token")),
+        };
+        self.pos += 1;
+        Ok(node)
+    }
+}
+
+/// Evaluates an expression over the latest values of its named inputs, missing values make it NaN
+#[derive(Debug)]
+pub struct ExprFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    names: Vec<String>,
+    inputs: Vec<FeatureDataRequest>,
+    expression: Expr,
+    output: FeatureId,
+}
+
+impl ExprFeature {
+    pub fn from_config(config: &ExprFeatureConfig) -> Self {
+        ExprFeature {
+            id: config.id.to_owned(),
+            sources: config.inputs.values().map(|i| i.from.clone()).collect(),
+            names: config.inputs.keys().cloned().collect(),
+            inputs: config.inputs.values().map(|i| i.to_owned().into()).collect(),
+            expression: config.expression.clone(),
+            output: config.output.to_owned(),
+        }
+    }
+}
+
+impl Feature for ExprFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating expression with id: {}", self.id);
+        let value = self.expression.eval(|name| {
+            self.names
+                .iter()
+                .position(|n| n == name)
+                .and_then(|i| data.last(self.inputs[i].feature_id()))
+                .unwrap_or(f64::NAN)
+        });
+
+        let mut res = HashMap::new();
+        res.insert(self.output.clone(), value);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expr() {
+        let expr = Expr::parse("(a - b) / c + -2 * a").unwrap();
+        assert_eq!(expr.variables().into_iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+        let values = HashMap::from([("a", 3.), ("b", 1.), ("c", 4.)]);
+        assert_eq!(expr.eval(|name| values[name]), -5.5);
+        assert_eq!(Expr::parse("1 - 2 - 3").unwrap().eval(|_| 0.), -4.);
+
+        assert_eq!(Expr::parse("a * (b + 1").unwrap_err().position, 10);
+        assert_eq!(Expr::parse("a $ b").unwrap_err().position, 2);
+        assert_eq!(Expr::parse("a b").unwrap_err().position, 2);
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("1.2.3").is_err());
+    }
+}
@@ -1,11 +1,19 @@
+mod basis;
+mod calendar;
 mod count;
+mod expr;
 mod mean;
 mod spread;
 mod sum;
+mod trade_flow;
 mod vwap;
 
+pub use basis::BasisFeature;
+pub use calendar::CalendarFeature;
 pub use count::CountFeature;
+pub use expr::{Expr, ExprError, ExprFeature};
 pub use mean::MeanFeature;
 pub use spread::SpreadFeature;
 pub use sum::SumFeature;
+pub use trade_flow::TradeFlowFeature;
 pub use vwap::VWAPFeature;
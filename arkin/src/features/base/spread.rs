@@ -1,6 +1,5 @@
 use crate::config::SpreadFeatureConfig;
-use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId};
-use anyhow::Result;
+use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError};
 use std::collections::HashMap;
 use tracing::debug;
 
@@ -38,7 +37,7 @@ impl Feature for SpreadFeature {
         &self.inputs
     }
 
-    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>> {
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
         debug!("Calculating spread with id: {}", self.id);
         let front = data.last(self.inputs[0].feature_id()).unwrap_or(0.);
         let back = data.last(self.inputs[1].feature_id()).unwrap_or(0.);
@@ -0,0 +1,117 @@
+use crate::config::TradeFlowFeatureConfig;
+use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Volume delta, buy to sell volume ratio and number of large trades in the window. The trade quantities are
+/// signed by the aggressor, buys are positive.
+#[derive(Debug)]
+pub struct TradeFlowFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    large_notional: f64,
+    output_delta: FeatureId,
+    output_ratio: FeatureId,
+    output_large_trades: FeatureId,
+}
+
+impl TradeFlowFeature {
+    pub fn from_config(config: &TradeFlowFeatureConfig) -> Self {
+        TradeFlowFeature {
+            id: config.id.to_owned(),
+            sources: vec![config.input_price.from.clone(), config.input_quantity.from.clone()],
+            inputs: vec![config.input_price.to_owned().into(), config.input_quantity.to_owned().into()],
+            large_notional: config.large_notional,
+            output_delta: config.output_delta.to_owned(),
+            output_ratio: config.output_ratio.to_owned(),
+            output_large_trades: config.output_large_trades.to_owned(),
+        }
+    }
+}
+
+impl Feature for TradeFlowFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating trade flow with id: {}", self.id);
+        let price = data.get(self.inputs[0].feature_id());
+        let quantity = data.get(self.inputs[1].feature_id());
+        if price.len() != quantity.len() {
+            return Err(PipelineError::InputMismatch {
+                feature: self.id.clone(),
+                left: price.len(),
+                right: quantity.len(),
+            });
+        }
+
+        let (mut buy, mut sell, mut large) = (0., 0., 0.);
+        price.iter().zip(&quantity).for_each(|(p, q)| {
+            if *q > 0. {
+                buy += q;
+            } else {
+                sell -= q;
+            }
+            if (p * q).abs() >= self.large_notional {
+                large += 1.;
+            }
+        });
+        // Without sells the ratio is undefined, like the vwap without trades
+        let ratio = if sell == 0. { f64::NAN } else { buy / sell };
+
+        let mut res = HashMap::new();
+        res.insert(self.output_delta.clone(), buy - sell);
+        res.insert(self.output_ratio.clone(), ratio);
+        res.insert(self.output_large_trades.clone(), large);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::WindowInputConfig, test_utils};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_trade_flow() {
+        let input = |feature_id: &str| WindowInputConfig {
+            from: "base".into(),
+            feature_id: feature_id.into(),
+            window: 60,
+        };
+        let feature = TradeFlowFeature::from_config(&TradeFlowFeatureConfig {
+            id: "flow".into(),
+            input_price: input("trade_price"),
+            input_quantity: input("trade_quantity"),
+            large_notional: 1000.,
+            output_delta: "delta".into(),
+            output_ratio: "ratio".into(),
+            output_large_trades: "large".into(),
+        });
+        let data = FeatureDataResponse::new(
+            test_utils::test_perp_instrument(),
+            OffsetDateTime::UNIX_EPOCH,
+            HashMap::from([
+                ("trade_price".into(), vec![100., 101., 99.]),
+                ("trade_quantity".into(), vec![3., -12., 1.]),
+            ]),
+        );
+
+        let res = feature.calculate(data).unwrap();
+        assert_eq!(res["delta"], -8.);
+        assert_eq!(res["ratio"], 4. / 12.);
+        // Only the sell of 1212 counts as large
+        assert_eq!(res["large"], 1.);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::config::VWAPFeatureConfig;
-use crate::features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId};
-use anyhow::Result;
+use crate::features::{
+    rolling::{InstrumentState, RollingWindow},
+    Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError,
+};
 use rust_decimal::prelude::*;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 use tracing::debug;
 
 #[derive(Debug)]
@@ -11,15 +13,28 @@ pub struct VWAPFeature {
     sources: Vec<NodeId>,
     inputs: Vec<FeatureDataRequest>,
     output: FeatureId,
+    /// Running quantity and notional when incremental, the window is read once and then only the new trades
+    incremental: Option<(Duration, InstrumentState<RollingWindow<2>>)>,
 }
 
 impl VWAPFeature {
     pub fn from_config(config: &VWAPFeatureConfig) -> Self {
+        let window = Duration::from_secs(config.input_price.window);
+        let inputs = match config.incremental {
+            true => [&config.input_price, &config.input_quantity]
+                .map(|i| FeatureDataRequest::WindowUpdates {
+                    feature_id: i.feature_id.clone(),
+                    window,
+                })
+                .into(),
+            false => vec![config.input_price.to_owned().into(), config.input_quantity.to_owned().into()],
+        };
         VWAPFeature {
             id: config.id.to_owned(),
             sources: vec![config.input_price.from.clone(), config.input_quantity.from.clone()],
-            inputs: vec![config.input_price.to_owned().into(), config.input_quantity.to_owned().into()],
+            inputs,
             output: config.output.to_owned(),
+            incremental: config.incremental.then(|| (window, InstrumentState::default())),
         }
     }
 }
@@ -37,20 +52,39 @@ impl Feature for VWAPFeature {
         &self.inputs
     }
 
-    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>> {
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
         debug!("Calculating VWAP with id: {}", self.id);
         // Check if both trade_price and trade_quantity are present
         let price = data.get(self.inputs[0].feature_id());
         let quantity = data.get(self.inputs[1].feature_id());
-        assert_eq!(price.len(), quantity.len());
-
-        let mut total_quantity = f64::zero();
-        let mut total_notional = f64::zero();
+        if price.len() != quantity.len() {
+            return Err(PipelineError::InputMismatch {
+                feature: self.id.clone(),
+                left: price.len(),
+                right: quantity.len(),
+            });
+        }
 
-        price.iter().zip(quantity).for_each(|(p, q)| {
-            total_quantity += q;
-            total_notional += p * q.abs();
-        });
+        let (total_quantity, total_notional) = match &self.incremental {
+            Some((window, state)) => state.update(data.instrument(), data.resumed(), |rolling| {
+                let times = data.timed(self.inputs[0].feature_id());
+                times.iter().zip(price.iter().zip(quantity)).for_each(|((t, _), (p, q))| {
+                    rolling.push(*t, [q, p * q.abs()]);
+                });
+                rolling.expire(*data.timestamp() - *window);
+                let [quantity, notional] = *rolling.sums();
+                (quantity, notional)
+            }),
+            None => {
+                let mut total_quantity = f64::zero();
+                let mut total_notional = f64::zero();
+                price.iter().zip(quantity).for_each(|(p, q)| {
+                    total_quantity += q;
+                    total_notional += p * q.abs();
+                });
+                (total_quantity, total_notional)
+            }
+        };
 
         let vwap = if total_quantity.is_zero() {
             f64::NAN
@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+use crate::config::ValidationError;
+
+use super::NodeId;
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error("Invalid pipeline: {0}")]
+    Invalid(#[from] ValidationError),
+
+    #[error("Feature {feature} reads from unknown node {input}")]
+    UnknownSource { feature: NodeId, input: NodeId },
+
+    #[error("Cycle through node {0}")]
+    Cycle(NodeId),
+
+    #[error("Failed to build the thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+
+    #[error("Feature {feature} got {left} and {right} values for inputs that should line up")]
+    InputMismatch {
+        feature: NodeId,
+        left: usize,
+        right: usize,
+    },
+}
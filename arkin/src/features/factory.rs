@@ -1,6 +1,9 @@
 use crate::config::FeatureConfig;
 
-use super::{CountFeature, Feature, MeanFeature, SMAFeature, SpreadFeature, SumFeature, VWAPFeature};
+use super::{
+    BasisFeature, CalendarFeature, CountFeature, DiffFeature, ExprFeature, Feature, LagFeature, MeanFeature,
+    OLSFeature, PercentileRankFeature, SMAFeature, SpreadFeature, SumFeature, TradeFlowFeature, VWAPFeature,
+};
 
 pub struct FeatureFactory {}
 
@@ -17,6 +20,14 @@ impl FeatureFactory {
                 FeatureConfig::VWAP(c) => Box::new(VWAPFeature::from_config(c)),
                 FeatureConfig::SMA(c) => Box::new(SMAFeature::from_config(c)),
                 FeatureConfig::Spread(c) => Box::new(SpreadFeature::from_config(c)),
+                FeatureConfig::TradeFlow(c) => Box::new(TradeFlowFeature::from_config(c)),
+                FeatureConfig::OLS(c) => Box::new(OLSFeature::from_config(c)),
+                FeatureConfig::Expr(c) => Box::new(ExprFeature::from_config(c)),
+                FeatureConfig::Lag(c) => Box::new(LagFeature::from_config(c)),
+                FeatureConfig::Diff(c) => Box::new(DiffFeature::from_config(c)),
+                FeatureConfig::PercentileRank(c) => Box::new(PercentileRankFeature::from_config(c)),
+                FeatureConfig::Calendar(c) => Box::new(CalendarFeature::from_config(c)),
+                FeatureConfig::Basis(c) => Box::new(BasisFeature::from_config(c)),
             };
             features.push(f);
         });
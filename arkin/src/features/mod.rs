@@ -1,20 +1,25 @@
+use crate::config::WarmUpConfig;
 use crate::constants::TIMESTAMP_FORMAT;
 use crate::models::Instrument;
 use crate::state::{FeatureDataRequest, FeatureDataResponse};
-use anyhow::Result;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
+use std::time::Duration;
 use time::OffsetDateTime;
 
 mod base;
+mod errors;
 mod factory;
 mod risk;
+mod rolling;
 mod ta;
 
 use base::*;
 use ta::*;
 
+pub use base::{Expr, ExprError};
+pub use errors::PipelineError;
 pub use factory::FeatureFactory;
 
 pub type NodeId = String;
@@ -46,9 +51,62 @@ impl fmt::Display for FeatureEvent {
     }
 }
 
+/// What a feature has to read before its outputs are trusted, the values per input and the time the pipeline
+/// ran for the instrument
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmUp {
+    pub samples: Vec<usize>,
+    pub lookback: Duration,
+}
+
+impl WarmUp {
+    /// A value per input or the periods of the period inputs, and the longest window as the lookback
+    pub fn from_requests(requests: &[FeatureDataRequest]) -> Self {
+        let samples = requests
+            .iter()
+            .map(|r| match r {
+                FeatureDataRequest::Period { periods, .. } | FeatureDataRequest::PeriodUpdates { periods, .. } => {
+                    *periods
+                }
+                _ => 1,
+            })
+            .collect();
+        let lookback = requests
+            .iter()
+            .filter_map(|r| match r {
+                FeatureDataRequest::Window { window, .. } | FeatureDataRequest::WindowUpdates { window, .. } => {
+                    Some(*window)
+                }
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default();
+        WarmUp { samples, lookback }
+    }
+
+    /// Replaces the values of the inputs that read more than the latest one and the lookback where configured
+    pub fn with_config(mut self, requests: &[FeatureDataRequest], config: &WarmUpConfig) -> Self {
+        if let Some(samples) = config.samples {
+            self.samples
+                .iter_mut()
+                .zip(requests)
+                .filter(|(_, r)| !matches!(r, FeatureDataRequest::Latest { .. }))
+                .for_each(|(s, _)| *s = samples);
+        }
+        if let Some(lookback) = config.lookback {
+            self.lookback = Duration::from_secs(lookback);
+        }
+        self
+    }
+}
+
 pub trait Feature: Debug + Send + Sync {
     fn id(&self) -> &NodeId;
     fn sources(&self) -> &[NodeId];
     fn data(&self) -> &[FeatureDataRequest];
-    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>>;
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError>;
+
+    fn warm_up(&self) -> WarmUp {
+        WarmUp::from_requests(self.data())
+    }
 }
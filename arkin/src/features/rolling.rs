@@ -0,0 +1,206 @@
+use std::{collections::VecDeque, fmt};
+
+use dashmap::DashMap;
+use time::OffsetDateTime;
+
+use crate::models::Instrument;
+
+/// Running state of an incremental feature per instrument
+pub struct InstrumentState<V> {
+    states: DashMap<Instrument, V>,
+}
+
+impl<V: Default> InstrumentState<V> {
+    /// Updates the state of the instrument, starting from an empty one unless the read resumed
+    pub fn update<R>(&self, instrument: &Instrument, resumed: bool, f: impl FnOnce(&mut V) -> R) -> R {
+        let mut state = self.states.entry(instrument.clone()).or_default();
+        if !resumed {
+            *state = V::default();
+        }
+        f(&mut state)
+    }
+}
+
+impl<V> Default for InstrumentState<V> {
+    fn default() -> Self {
+        InstrumentState {
+            states: DashMap::new(),
+        }
+    }
+}
+
+impl<V> fmt::Debug for InstrumentState<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InstrumentState({} instruments)", self.states.len())
+    }
+}
+
+/// Sum of the last values up to a capacity
+#[derive(Default)]
+pub struct RollingPeriods {
+    values: VecDeque<f64>,
+    sum: f64,
+}
+
+impl RollingPeriods {
+    pub fn push(&mut self, value: f64, capacity: usize) {
+        while self.values.len() >= capacity {
+            match self.values.pop_front() {
+                Some(old) => self.sum -= old,
+                None => return,
+            }
+        }
+        self.values.push_back(value);
+        self.sum += value;
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self.values.is_empty() {
+            true => f64::NAN,
+            false => self.sum / self.values.len() as f64,
+        }
+    }
+}
+
+/// Sums of the values in a time window, the values are added as they come in and removed as they fall out
+pub struct RollingWindow<const N: usize> {
+    values: VecDeque<(OffsetDateTime, [f64; N])>,
+    sums: [f64; N],
+}
+
+impl<const N: usize> Default for RollingWindow<N> {
+    fn default() -> Self {
+        RollingWindow {
+            values: VecDeque::new(),
+            sums: [0.; N],
+        }
+    }
+}
+
+impl<const N: usize> RollingWindow<N> {
+    pub fn push(&mut self, time: OffsetDateTime, values: [f64; N]) {
+        self.sums.iter_mut().zip(values).for_each(|(s, v)| *s += v);
+        self.values.push_back((time, values));
+    }
+
+    /// Removes the values before the start of the window
+    pub fn expire(&mut self, start: OffsetDateTime) {
+        while self.values.front().is_some_and(|(t, _)| *t < start) {
+            if let Some((_, values)) = self.values.pop_front() {
+                self.sums.iter_mut().zip(values).for_each(|(s, v)| *s -= v);
+            }
+        }
+        // Drop the rounding errors the removals left behind
+        if self.values.is_empty() {
+            self.sums = [0.; N];
+        }
+    }
+
+    pub fn sums(&self) -> &[f64; N] {
+        &self.sums
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{PeriodInputConfig, SMAFeatureConfig, VWAPFeatureConfig, WindowInputConfig},
+        features::{Feature, FeatureEvent, SMAFeature, VWAPFeature},
+        state::StateManager,
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    #[test]
+    fn test_rolling() {
+        let mut periods = RollingPeriods::default();
+        assert!(periods.mean().is_nan());
+        for value in [1., 2., 3., 4.] {
+            periods.push(value, 3);
+        }
+        assert_eq!(periods.mean(), 3.);
+
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+        let mut window = RollingWindow::<2>::default();
+        for (seconds, value) in [(0, 1.), (1, 2.), (2, 4.)] {
+            window.push(at(seconds), [value, 1.]);
+        }
+        window.expire(at(1));
+        assert_eq!(window.sums(), &[6., 2.]);
+        window.expire(at(3));
+        assert_eq!(window.sums(), &[0., 0.]);
+    }
+
+    #[test]
+    fn test_incremental_matches_windowed() {
+        let state = StateManager::default();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |millis: i64| start + time::Duration::milliseconds(millis);
+        // Irregular trades, some at the same time
+        for i in 0..300_i64 {
+            let time = at(i * 700 / 3 * 3);
+            let price = 100. + ((i * 37) % 11) as f64;
+            let quantity = ((i * 13) % 7) as f64 - 3.;
+            state.add_feature(FeatureEvent::new("price".into(), instrument.clone(), time, price));
+            state.add_feature(FeatureEvent::new("quantity".into(), instrument.clone(), time, quantity));
+        }
+
+        let window = |feature_id: &str| WindowInputConfig {
+            from: "base".into(),
+            feature_id: feature_id.into(),
+            window: 10,
+        };
+        let vwap = |incremental| {
+            VWAPFeature::from_config(&VWAPFeatureConfig {
+                id: "vwap".into(),
+                input_price: window("price"),
+                input_quantity: window("quantity"),
+                output: "vwap".into(),
+                incremental,
+            })
+        };
+        let sma = |incremental| {
+            SMAFeature::from_config(&SMAFeatureConfig {
+                id: "sma".into(),
+                input: PeriodInputConfig {
+                    from: "base".into(),
+                    feature_id: "price".into(),
+                    periods: 20,
+                },
+                output: "sma".into(),
+                incremental,
+            })
+        };
+        let features: [(Box<dyn Feature>, Box<dyn Feature>); 2] = [
+            (Box::new(vwap(false)), Box::new(vwap(true))),
+            (Box::new(sma(false)), Box::new(sma(true))),
+        ];
+
+        // Runs every second with a gap longer than the window and a restart back in time
+        let runs = (0..120).chain(150..180).chain(60..90).map(|s| at(s * 1000 + 500));
+        let mut previous = None;
+        for time in runs {
+            let resumed = previous.filter(|p| *p < time);
+            for (windowed, incremental) in &features {
+                let expected = windowed
+                    .calculate(state.read_features(&instrument, &time, windowed.data()))
+                    .unwrap();
+                let data = state.read_features_since(&instrument, &time, resumed.as_ref(), incremental.data());
+                let value = incremental.calculate(data).unwrap();
+                let (expected, value) = (expected[windowed.id()], value[incremental.id()]);
+                assert!(
+                    (expected.is_nan() && value.is_nan()) || (expected - value).abs() < 1e-9,
+                    "{} at {}: {} != {}",
+                    windowed.id(),
+                    time,
+                    expected,
+                    value
+                );
+            }
+            previous = Some(time);
+        }
+    }
+}
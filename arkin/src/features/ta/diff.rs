@@ -0,0 +1,85 @@
+use crate::{
+    config::{DiffFeatureConfig, DiffMethod},
+    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError},
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Change of the input over the given number of periods, NaN until there are that many. The percent change
+/// is NaN when the earlier value is 0
+#[derive(Debug)]
+pub struct DiffFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    periods: usize,
+    method: DiffMethod,
+    output: FeatureId,
+}
+
+impl DiffFeature {
+    pub fn from_config(config: &DiffFeatureConfig) -> Self {
+        DiffFeature {
+            id: config.id.to_owned(),
+            sources: vec![config.input.from.clone()],
+            inputs: vec![FeatureDataRequest::Period {
+                feature_id: config.input.feature_id.clone(),
+                periods: config.periods + 1,
+            }],
+            periods: config.periods,
+            method: config.method,
+            output: config.output.to_owned(),
+        }
+    }
+}
+
+fn diff(values: &[f64], periods: usize, method: DiffMethod) -> f64 {
+    if values.len() <= periods {
+        return f64::NAN;
+    }
+    let (current, previous) = (values[values.len() - 1], values[values.len() - 1 - periods]);
+    match method {
+        DiffMethod::Absolute => current - previous,
+        DiffMethod::Percent if previous == 0. => f64::NAN,
+        DiffMethod::Percent => current / previous - 1.,
+    }
+}
+
+impl Feature for DiffFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating diff with id: {}", self.id);
+        let values = data.get(self.inputs[0].feature_id());
+
+        let mut res = HashMap::new();
+        res.insert(self.output.clone(), diff(&values, self.periods, self.method));
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let values = [4., 5., 6.];
+        assert_eq!(diff(&values, 2, DiffMethod::Absolute), 2.);
+        assert_eq!(diff(&values, 2, DiffMethod::Percent), 0.5);
+        assert_eq!(diff(&values, 1, DiffMethod::Absolute), 1.);
+        assert!(diff(&values, 3, DiffMethod::Absolute).is_nan());
+        assert!(diff(&[0., 5.], 1, DiffMethod::Percent).is_nan());
+        assert_eq!(diff(&[0., 5.], 1, DiffMethod::Absolute), 5.);
+    }
+}
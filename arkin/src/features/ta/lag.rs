@@ -0,0 +1,76 @@
+use crate::{
+    config::LagFeatureConfig,
+    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError},
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Value of the input the given number of periods ago, NaN until there are that many
+#[derive(Debug)]
+pub struct LagFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    periods: usize,
+    output: FeatureId,
+}
+
+impl LagFeature {
+    pub fn from_config(config: &LagFeatureConfig) -> Self {
+        LagFeature {
+            id: config.id.to_owned(),
+            sources: vec![config.input.from.clone()],
+            inputs: vec![FeatureDataRequest::Period {
+                feature_id: config.input.feature_id.clone(),
+                periods: config.periods + 1,
+            }],
+            periods: config.periods,
+            output: config.output.to_owned(),
+        }
+    }
+}
+
+fn lag(values: &[f64], periods: usize) -> f64 {
+    match values.len() > periods {
+        true => values[values.len() - 1 - periods],
+        false => f64::NAN,
+    }
+}
+
+impl Feature for LagFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating lag with id: {}", self.id);
+        let values = data.get(self.inputs[0].feature_id());
+
+        let mut res = HashMap::new();
+        res.insert(self.output.clone(), lag(&values, self.periods));
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lag() {
+        let values = [4., 5., 6.];
+        assert_eq!(lag(&values, 0), 6.);
+        assert_eq!(lag(&values, 1), 5.);
+        assert_eq!(lag(&values, 2), 4.);
+        assert!(lag(&values, 3).is_nan());
+        assert!(lag(&[], 1).is_nan());
+    }
+}
@@ -1,3 +1,11 @@
+mod diff;
+mod lag;
+mod ols;
+mod percentile_rank;
 mod sma;
 
+pub use diff::DiffFeature;
+pub use lag::LagFeature;
+pub use ols::OLSFeature;
+pub use percentile_rank::PercentileRankFeature;
 pub use sma::SMAFeature;
@@ -0,0 +1,115 @@
+use crate::{
+    config::OLSFeatureConfig,
+    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError},
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Least squares fit of the last periods of a series against another series, or against the period index
+/// when there is none. The slope of a price against its index is the trend, against another price the hedge
+/// ratio, and the R² tells how well the line fits.
+#[derive(Debug)]
+pub struct OLSFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    output_slope: FeatureId,
+    output_intercept: FeatureId,
+    output_r2: FeatureId,
+}
+
+impl OLSFeature {
+    pub fn from_config(config: &OLSFeatureConfig) -> Self {
+        let mut sources = vec![config.input_y.from.clone()];
+        let mut inputs = vec![config.input_y.to_owned().into()];
+        if let Some(x) = &config.input_x {
+            sources.push(x.from.clone());
+            inputs.push(x.to_owned().into());
+        }
+        OLSFeature {
+            id: config.id.to_owned(),
+            sources,
+            inputs,
+            output_slope: config.output_slope.to_owned(),
+            output_intercept: config.output_intercept.to_owned(),
+            output_r2: config.output_r2.to_owned(),
+        }
+    }
+}
+
+/// Slope, intercept and R² of y against x, undefined without at least two distinct x
+fn ols(x: &[f64], y: &[f64]) -> (f64, f64, f64) {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let (mut sxx, mut sxy, mut syy) = (0., 0., 0.);
+    x.iter().zip(y).for_each(|(x, y)| {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    });
+    if x.len() < 2 || sxx == 0. {
+        return (f64::NAN, f64::NAN, f64::NAN);
+    }
+    let slope = sxy / sxx;
+    // A flat y lies on the line exactly
+    let r2 = if syy == 0. {
+        1.
+    } else {
+        sxy * sxy / (sxx * syy)
+    };
+    (slope, mean_y - slope * mean_x, r2)
+}
+
+impl Feature for OLSFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating OLS with id: {}", self.id);
+        let y = data.get(self.inputs[0].feature_id());
+        let x = match self.inputs.get(1) {
+            Some(input) => data.get(input.feature_id()),
+            None => (0..y.len()).map(|i| i as f64).collect(),
+        };
+        if x.len() != y.len() {
+            return Err(PipelineError::InputMismatch {
+                feature: self.id.clone(),
+                left: y.len(),
+                right: x.len(),
+            });
+        }
+
+        let (slope, intercept, r2) = ols(&x, &y);
+        let mut res = HashMap::new();
+        res.insert(self.output_slope.clone(), slope);
+        res.insert(self.output_intercept.clone(), intercept);
+        res.insert(self.output_r2.clone(), r2);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ols() {
+        assert_eq!(ols(&[0., 1., 2., 3.], &[1., 3., 5., 7.]), (2., 1., 1.));
+        let (slope, intercept, r2) = ols(&[0., 1., 2.], &[0., 2., 1.]);
+        assert_eq!((slope, intercept), (0.5, 0.5));
+        assert!((r2 - 0.25).abs() < 1e-12);
+        assert!(ols(&[1., 1.], &[1., 2.]).0.is_nan());
+        assert_eq!(ols(&[0., 1.], &[4., 4.]), (0., 4., 1.));
+    }
+}
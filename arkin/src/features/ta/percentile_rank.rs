@@ -0,0 +1,80 @@
+use crate::{
+    config::{PercentileRankFeatureConfig, RankInterpolation},
+    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError},
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Percentile from 0 to 100 of the latest value among the values in the window, NaN without values
+#[derive(Debug)]
+pub struct PercentileRankFeature {
+    id: NodeId,
+    sources: Vec<NodeId>,
+    inputs: Vec<FeatureDataRequest>,
+    interpolation: RankInterpolation,
+    output: FeatureId,
+}
+
+impl PercentileRankFeature {
+    pub fn from_config(config: &PercentileRankFeatureConfig) -> Self {
+        PercentileRankFeature {
+            id: config.id.to_owned(),
+            sources: vec![config.input.from.clone()],
+            inputs: vec![config.input.to_owned().into()],
+            interpolation: config.interpolation,
+            output: config.output.to_owned(),
+        }
+    }
+}
+
+fn percentile_rank(values: &[f64], interpolation: RankInterpolation) -> f64 {
+    let Some(current) = values.last() else {
+        return f64::NAN;
+    };
+    let below = values.iter().filter(|v| *v < current).count() as f64;
+    let equal = values.iter().filter(|v| *v == current).count() as f64;
+    let rank = match interpolation {
+        RankInterpolation::Strict => below,
+        RankInterpolation::Weak => below + equal,
+        RankInterpolation::Mean => below + equal / 2.,
+    };
+    rank / values.len() as f64 * 100.
+}
+
+impl Feature for PercentileRankFeature {
+    fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    fn sources(&self) -> &[NodeId] {
+        &self.sources
+    }
+
+    fn data(&self) -> &[FeatureDataRequest] {
+        &self.inputs
+    }
+
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
+        debug!("Calculating percentile rank with id: {}", self.id);
+        let values = data.get(self.inputs[0].feature_id());
+
+        let mut res = HashMap::new();
+        res.insert(self.output.clone(), percentile_rank(&values, self.interpolation));
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_rank() {
+        let values = [3., 1., 2., 2.];
+        assert_eq!(percentile_rank(&values, RankInterpolation::Strict), 25.);
+        assert_eq!(percentile_rank(&values, RankInterpolation::Weak), 75.);
+        assert_eq!(percentile_rank(&values, RankInterpolation::Mean), 50.);
+        assert_eq!(percentile_rank(&[1., 5.], RankInterpolation::Weak), 100.);
+        assert!(percentile_rank(&[], RankInterpolation::Mean).is_nan());
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
     config::SMAFeatureConfig,
-    features::{Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId},
+    features::{
+        rolling::{InstrumentState, RollingPeriods},
+        Feature, FeatureDataRequest, FeatureDataResponse, FeatureId, NodeId, PipelineError,
+    },
 };
-use anyhow::Result;
 use std::collections::HashMap;
 use tracing::debug;
 
@@ -12,18 +14,26 @@ pub struct SMAFeature {
     sources: Vec<NodeId>,
     inputs: Vec<FeatureDataRequest>,
     output: FeatureId,
+    /// Running sums when incremental, the periods are read once and then only the new values
+    incremental: Option<(usize, InstrumentState<RollingPeriods>)>,
 }
 
 impl SMAFeature {
     pub fn from_config(config: &SMAFeatureConfig) -> Self {
         let sources = vec![config.input.from.clone()];
-        let data = vec![config.input.to_owned().into()];
-
+        let data = match config.incremental {
+            true => FeatureDataRequest::PeriodUpdates {
+                feature_id: config.input.feature_id.clone(),
+                periods: config.input.periods,
+            },
+            false => config.input.to_owned().into(),
+        };
         SMAFeature {
             id: config.id.to_owned(),
             sources,
-            inputs: data,
+            inputs: vec![data],
             output: config.output.to_owned(),
+            incremental: config.incremental.then(|| (config.input.periods, InstrumentState::default())),
         }
     }
 }
@@ -41,12 +51,18 @@ impl Feature for SMAFeature {
         &self.inputs
     }
 
-    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>> {
+    fn calculate(&self, data: FeatureDataResponse) -> Result<HashMap<FeatureId, f64>, PipelineError> {
         debug!("Calculating mean with id: {}", self.id);
-        let sum = data.mean(self.inputs[0].feature_id()).unwrap_or(0.);
-        let count = data.count(self.inputs[0].feature_id()).unwrap_or(0.);
+        let values = data.get(self.inputs[0].feature_id());
+        let mean = match &self.incremental {
+            Some((periods, state)) => state.update(data.instrument(), data.resumed(), |rolling| {
+                values.iter().for_each(|v| rolling.push(*v, *periods));
+                rolling.mean()
+            }),
+            None if values.is_empty() => f64::NAN,
+            None => values.iter().sum::<f64>() / values.len() as f64,
+        };
 
-        let mean = if count == 0. { f64::NAN } else { sum / count };
         let mut res = HashMap::new();
         res.insert(self.output.clone(), mean);
         Ok(res)
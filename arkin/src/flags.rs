@@ -0,0 +1,160 @@
+use std::{collections::BTreeSet, fmt, fs, io, path::Path};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{models::Instrument, strategies::StrategyId};
+
+#[derive(Error, Debug)]
+pub enum FlagError {
+    #[error("Failed to write trading flags: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to read trading flags from {0}: {1}")]
+    Read(String, String),
+    #[error("Failed to serialize trading flags: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum FlagScope {
+    Strategy,
+    Venue,
+    Instrument,
+}
+
+/// A strategy, venue or instrument that trading is switched off for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Flag {
+    pub scope: FlagScope,
+    pub key: String,
+}
+
+impl Flag {
+    pub fn new(scope: FlagScope, key: &str) -> Self {
+        Flag {
+            scope,
+            key: key.to_lowercase(),
+        }
+    }
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.scope, self.key)
+    }
+}
+
+/// Kill switches that stop trading a strategy, venue or instrument without a config change.
+///
+/// Every change is written to disk right away so a restart keeps the switches as the operator left them.
+pub struct FlagRegistry {
+    path: String,
+    disabled: RwLock<BTreeSet<Flag>>,
+}
+
+impl FlagRegistry {
+    /// Load the switches of the previous run, a missing file means everything is enabled. A file that can't be
+    /// read fails instead, a damaged file must not switch trading back on.
+    pub fn load(path: &str) -> Result<Self, FlagError> {
+        let disabled = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<BTreeSet<Flag>>(&content)
+                .map_err(|e| FlagError::Read(path.into(), e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(FlagError::Read(path.into(), e.to_string())),
+        };
+        for flag in &disabled {
+            warn!("Trading is disabled for {}", flag);
+        }
+        Ok(FlagRegistry {
+            path: path.to_owned(),
+            disabled: RwLock::new(disabled),
+        })
+    }
+
+    /// Switch trading on or off, returns whether anything changed
+    pub fn set_trading_enabled(&self, flag: Flag, enabled: bool) -> Result<bool, FlagError> {
+        let mut disabled = self.disabled.write();
+        let mut updated = disabled.clone();
+        let changed = if enabled {
+            updated.remove(&flag)
+        } else {
+            updated.insert(flag.clone())
+        };
+        if changed {
+            // Saved before the switch takes effect so a failed write changes neither, through a temporary file so
+            // a crash never leaves half a file behind
+            let tmp = Path::new(&self.path).with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string_pretty(&updated)?)?;
+            fs::rename(&tmp, &self.path)?;
+            *disabled = updated;
+            info!(flag = %flag, enabled, "Trading flag changed");
+        }
+        Ok(changed)
+    }
+
+    pub fn disabled(&self) -> Vec<Flag> {
+        self.disabled.read().iter().cloned().collect()
+    }
+
+    /// The switch that stops this strategy from trading the instrument, if any
+    pub fn blocking(&self, strategy_id: &StrategyId, instrument: &Instrument) -> Option<Flag> {
+        let disabled = self.disabled.read();
+        if disabled.is_empty() {
+            return None;
+        }
+        [
+            Flag::new(FlagScope::Strategy, &strategy_id.to_string()),
+            Flag::new(FlagScope::Venue, &instrument.venue().to_string()),
+            Flag::new(FlagScope::Instrument, &instrument.to_string()),
+        ]
+        .into_iter()
+        .find(|flag| disabled.contains(flag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_flag_registry() {
+        let path = std::env::temp_dir().join(format!("aurelion_flags_{}.json", std::process::id()));
+        let path = path.to_string_lossy();
+        let _ = fs::remove_file(path.as_ref());
+        let instrument = test_utils::test_perp_instrument();
+        let strategy = StrategyId::from("crossover");
+
+        let flags = FlagRegistry::load(&path).unwrap();
+        assert!(flags.blocking(&strategy, &instrument).is_none());
+
+        let flag = Flag::new(FlagScope::Instrument, &instrument.to_string().to_uppercase());
+        assert!(flags.set_trading_enabled(flag.clone(), false).unwrap());
+        assert!(!flags.set_trading_enabled(flag.clone(), false).unwrap());
+        assert_eq!(flags.blocking(&strategy, &instrument), Some(flag.clone()));
+        assert!(flags
+            .blocking(&StrategyId::from("other"), &test_utils::test_multi_perp_instrument()[1])
+            .is_none());
+
+        // A restart keeps the switch off
+        let flags = FlagRegistry::load(&path).unwrap();
+        assert_eq!(flags.disabled(), vec![flag.clone()]);
+        flags.set_trading_enabled(flag.clone(), true).unwrap();
+        assert!(FlagRegistry::load(&path).unwrap().disabled().is_empty());
+
+        // A damaged file keeps the server from starting instead of enabling everything
+        fs::write(path.as_ref(), "[{\"scope\": \"venue\"").unwrap();
+        assert!(matches!(FlagRegistry::load(&path), Err(FlagError::Read(..))));
+        let _ = fs::remove_file(path.as_ref());
+
+        // A switch that can't be saved doesn't change
+        let flags = FlagRegistry::load("/nonexistent/aurelion_flags.json").unwrap();
+        assert!(flags.set_trading_enabled(flag, false).is_err());
+        assert!(flags.disabled().is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::{
+    constants::TIMESTAMP_FORMAT,
+    flags::{Flag, FlagScope},
+    logging,
+    models::Venue,
+    server::Server,
+    strategies::StrategyId,
+};
+
+use super::proto::{
+    control_server::Control, CancelAllOrdersRequest, CancelAllOrdersResponse, HealthRequest, HealthResponse,
+    ListStrategiesRequest, ListStrategiesResponse, ListTradingFlagsRequest, LogFilterRequest, LogFilterResponse,
+    Position, PositionsRequest, PositionsResponse, ReloadConfigRequest, ReloadConfigResponse, StrategyRequest,
+    StrategyStatus, TradingFlag, TradingFlagRequest, TradingFlagsResponse,
+};
+
+/// Control plane of a running server
+pub struct ControlService {
+    server: Arc<Server>,
+}
+
+impl ControlService {
+    pub fn new(server: Arc<Server>) -> Self {
+        ControlService { server }
+    }
+
+    fn strategy_status(&self, id: &StrategyId) -> StrategyStatus {
+        let running = self
+            .server
+            .strategies()
+            .into_iter()
+            .any(|(strategy, running)| strategy == *id && running);
+        StrategyStatus {
+            id: id.to_string(),
+            running,
+        }
+    }
+
+    fn trading_flags(&self) -> TradingFlagsResponse {
+        let disabled = self
+            .server
+            .trading_disabled()
+            .into_iter()
+            .map(|f| TradingFlag {
+                scope: f.scope.to_string(),
+                key: f.key,
+            })
+            .collect();
+        TradingFlagsResponse { disabled }
+    }
+}
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    async fn list_strategies(
+        &self,
+        _request: Request<ListStrategiesRequest>,
+    ) -> Result<Response<ListStrategiesResponse>, Status> {
+        let strategies = self
+            .server
+            .strategies()
+            .into_iter()
+            .map(|(id, running)| StrategyStatus {
+                id: id.to_string(),
+                running,
+            })
+            .collect();
+        Ok(Response::new(ListStrategiesResponse { strategies }))
+    }
+
+    async fn start_strategy(&self, request: Request<StrategyRequest>) -> Result<Response<StrategyStatus>, Status> {
+        let id = StrategyId::from(request.into_inner().id);
+        self.server.start_strategy(&id).map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(self.strategy_status(&id)))
+    }
+
+    async fn stop_strategy(&self, request: Request<StrategyRequest>) -> Result<Response<StrategyStatus>, Status> {
+        let id = StrategyId::from(request.into_inner().id);
+        self.server.stop_strategy(&id).map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(self.strategy_status(&id)))
+    }
+
+    async fn reload_config(
+        &self,
+        _request: Request<ReloadConfigRequest>,
+    ) -> Result<Response<ReloadConfigResponse>, Status> {
+        let report = self
+            .server
+            .reload_config()
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+        Ok(Response::new(ReloadConfigResponse {
+            applied: report.applied,
+            restart_required: report.restart_required,
+        }))
+    }
+
+    async fn get_positions(&self, _request: Request<PositionsRequest>) -> Result<Response<PositionsResponse>, Status> {
+        let positions = self
+            .server
+            .positions()
+            .into_iter()
+            .map(|p| Position {
+                strategy_id: p.strategy_id.to_string(),
+                instrument: p.instrument.to_string(),
+                quantity: p.quantity.to_string(),
+                avg_price: p.avg_price.to_string(),
+                commission: p.commission.to_string(),
+                realized_pnl: p.realized_pnl.to_string(),
+            })
+            .collect();
+        Ok(Response::new(PositionsResponse { positions }))
+    }
+
+    async fn cancel_all_orders(
+        &self,
+        _request: Request<CancelAllOrdersRequest>,
+    ) -> Result<Response<CancelAllOrdersResponse>, Status> {
+        let cancelled = self.server.cancel_all_orders() as u64;
+        Ok(Response::new(CancelAllOrdersResponse { cancelled }))
+    }
+
+    async fn health(&self, _request: Request<HealthRequest>) -> Result<Response<HealthResponse>, Status> {
+        let health = self.server.health();
+        Ok(Response::new(HealthResponse {
+            status: "serving".into(),
+            uptime_seconds: health.uptime.as_secs(),
+            strategies_running: health.strategies_running as u32,
+            last_step: health
+                .last_step
+                .map(|t| t.format(TIMESTAMP_FORMAT).unwrap_or_default())
+                .unwrap_or_default(),
+        }))
+    }
+
+    async fn set_log_filter(&self, request: Request<LogFilterRequest>) -> Result<Response<LogFilterResponse>, Status> {
+        let filter = request.into_inner().filter;
+        let active = logging::log_filter().map_err(|e| Status::failed_precondition(e.to_string()))?;
+        let active = if filter.is_empty() {
+            active
+        } else {
+            let active = logging::set_log_filter(&filter).map_err(|e| Status::invalid_argument(e.to_string()))?;
+            info!(filter = %active, "Log filter changed");
+            active
+        };
+        Ok(Response::new(LogFilterResponse { filter: active }))
+    }
+
+    async fn set_trading_enabled(
+        &self,
+        request: Request<TradingFlagRequest>,
+    ) -> Result<Response<TradingFlagsResponse>, Status> {
+        let request = request.into_inner();
+        let flag = request.flag.ok_or_else(|| Status::invalid_argument("missing flag"))?;
+        let scope = flag
+            .scope
+            .parse::<FlagScope>()
+            .map_err(|_| Status::invalid_argument(format!("unknown scope '{}'", flag.scope)))?;
+        // Typos would silently switch off nothing
+        match scope {
+            FlagScope::Strategy => {
+                let id = StrategyId::from(flag.key.as_str());
+                if !self.server.strategies().iter().any(|(s, _)| *s == id) {
+                    return Err(Status::not_found(format!("unknown strategy '{}'", flag.key)));
+                }
+            }
+            FlagScope::Venue => {
+                flag.key
+                    .to_lowercase()
+                    .parse::<Venue>()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            }
+            FlagScope::Instrument => {}
+        }
+        self.server
+            .set_trading_enabled(Flag::new(scope, &flag.key), request.enabled)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(self.trading_flags()))
+    }
+
+    async fn list_trading_flags(
+        &self,
+        _request: Request<ListTradingFlagsRequest>,
+    ) -> Result<Response<TradingFlagsResponse>, Status> {
+        Ok(Response::new(self.trading_flags()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        grpc::{proto::control_client::ControlClient, serve_with_listener},
+    };
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_control_service() {
+        let mut config = config::load();
+        let flags = std::env::temp_dir().join(format!("aurelion_control_flags_{}.json", std::process::id()));
+        config.server.trading_flags = flags.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&flags);
+        let server = Arc::new(Server::builder().config(&config).build());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_with_listener(listener, server.clone()));
+
+        let mut client = ControlClient::connect(format!("http://{}", address)).await.unwrap();
+
+        let strategies = client.list_strategies(ListStrategiesRequest {}).await.unwrap().into_inner();
+        assert_eq!(strategies.strategies.len(), 1);
+        let id = strategies.strategies[0].id.clone();
+        assert!(strategies.strategies[0].running);
+
+        let status = client
+            .stop_strategy(StrategyRequest { id: id.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!status.running);
+        assert_eq!(
+            client.health(HealthRequest {}).await.unwrap().into_inner().strategies_running,
+            0
+        );
+
+        // A reload keeps the strategy stopped
+        client.reload_config(ReloadConfigRequest {}).await.unwrap();
+        assert!(!server.strategies()[0].1);
+
+        let status = client.start_strategy(StrategyRequest { id }).await.unwrap().into_inner();
+        assert!(status.running);
+
+        let unknown = client
+            .start_strategy(StrategyRequest {
+                id: "unknown".into(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+
+        assert!(client
+            .get_positions(PositionsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .positions
+            .is_empty());
+        assert_eq!(
+            client
+                .cancel_all_orders(CancelAllOrdersRequest {})
+                .await
+                .unwrap()
+                .into_inner()
+                .cancelled,
+            0
+        );
+
+        // Only the binary installs the reloadable logger
+        let status = client
+            .set_log_filter(LogFilterRequest {
+                filter: "debug".into(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let flag = |scope: &str, key: &str, enabled| TradingFlagRequest {
+            flag: Some(TradingFlag {
+                scope: scope.into(),
+                key: key.into(),
+            }),
+            enabled,
+        };
+        let disabled = client
+            .set_trading_enabled(flag("venue", "Binance", false))
+            .await
+            .unwrap()
+            .into_inner()
+            .disabled;
+        assert_eq!(disabled[0].key, "binance");
+        let unknown = client
+            .set_trading_enabled(flag("strategy", "unknown", false))
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+        client.set_trading_enabled(flag("venue", "binance", true)).await.unwrap();
+        assert!(client
+            .list_trading_flags(ListTradingFlagsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .disabled
+            .is_empty());
+        let _ = std::fs::remove_file(&flags);
+    }
+}
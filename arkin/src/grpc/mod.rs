@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tracing::{error, info};
+
+use crate::server::Server;
+
+mod control;
+pub mod proto;
+
+pub use control::ControlService;
+
+/// Serve the control plane until the task is dropped
+pub async fn serve(address: &str, server: Arc<Server>) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind grpc endpoint to {}: {}", address, e);
+            return;
+        }
+    };
+    info!("Serving grpc control plane on {}", address);
+    if let Err(e) = serve_with_listener(listener, server).await {
+        error!("Grpc endpoint failed: {}", e);
+    }
+}
+
+pub async fn serve_with_listener(listener: TcpListener, server: Arc<Server>) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(proto::control_server::ControlServer::new(ControlService::new(server)))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
@@ -0,0 +1,124 @@
+//! Messages of the control service, keep in sync with proto/control.proto
+#![allow(clippy::all)]
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListStrategiesRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListStrategiesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub strategies: Vec<StrategyStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StrategyRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StrategyStatus {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(bool, tag = "2")]
+    pub running: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReloadConfigRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReloadConfigResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub applied: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub restart_required: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PositionsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Position {
+    #[prost(string, tag = "1")]
+    pub strategy_id: String,
+    #[prost(string, tag = "2")]
+    pub instrument: String,
+    #[prost(string, tag = "3")]
+    pub quantity: String,
+    #[prost(string, tag = "4")]
+    pub avg_price: String,
+    #[prost(string, tag = "5")]
+    pub commission: String,
+    #[prost(string, tag = "6")]
+    pub realized_pnl: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PositionsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub positions: Vec<Position>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CancelAllOrdersRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CancelAllOrdersResponse {
+    #[prost(uint64, tag = "1")]
+    pub cancelled: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HealthRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HealthResponse {
+    #[prost(string, tag = "1")]
+    pub status: String,
+    #[prost(uint64, tag = "2")]
+    pub uptime_seconds: u64,
+    #[prost(uint32, tag = "3")]
+    pub strategies_running: u32,
+    #[prost(string, tag = "4")]
+    pub last_step: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LogFilterRequest {
+    #[prost(string, tag = "1")]
+    pub filter: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LogFilterResponse {
+    #[prost(string, tag = "1")]
+    pub filter: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TradingFlag {
+    #[prost(string, tag = "1")]
+    pub scope: String,
+    #[prost(string, tag = "2")]
+    pub key: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TradingFlagRequest {
+    #[prost(message, optional, tag = "1")]
+    pub flag: Option<TradingFlag>,
+    #[prost(bool, tag = "2")]
+    pub enabled: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListTradingFlagsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TradingFlagsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub disabled: Vec<TradingFlag>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/arkin.control.Control.rs"));
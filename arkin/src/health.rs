@@ -0,0 +1,89 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpStream, select, time::Instant};
+
+use crate::{
+    bus::EventBus,
+    config::DatabaseConfig,
+    models::{Book, Tick, Trade, Venue},
+};
+
+/// Outcome of one subsystem check
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, healthy: bool, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            healthy,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub checks: Vec<Check>,
+}
+
+impl HealthReport {
+    pub fn new(checks: Vec<Check>) -> Self {
+        HealthReport {
+            healthy: checks.iter().all(|c| c.healthy),
+            checks,
+        }
+    }
+}
+
+/// When market data last arrived per venue
+#[derive(Default)]
+pub struct FeedActivity {
+    last_seen: RwLock<HashMap<Venue, Instant>>,
+}
+
+impl FeedActivity {
+    pub fn seen(&self, venue: &Venue) {
+        self.last_seen.write().insert(venue.clone(), Instant::now());
+    }
+
+    /// Time since the last market data of every venue that sent some
+    pub fn silence(&self) -> Vec<(Venue, Duration)> {
+        self.last_seen
+            .read()
+            .iter()
+            .map(|(venue, seen)| (venue.clone(), seen.elapsed()))
+            .collect()
+    }
+
+    pub async fn run(self: Arc<Self>, bus: Arc<EventBus>) {
+        let mut ticks = bus.subscribe::<Tick>();
+        let mut trades = bus.subscribe::<Trade>();
+        let mut books = bus.subscribe::<Book>();
+        loop {
+            select! {
+                Some(tick) = ticks.recv() => self.seen(tick.instrument.venue()),
+                Some(trade) = trades.recv() => self.seen(trade.instrument.venue()),
+                Some(book) = books.recv() => self.seen(book.instrument.venue()),
+                else => break,
+            }
+        }
+    }
+}
+
+/// Whether the database accepts connections within the timeout
+pub async fn check_database(config: &DatabaseConfig, timeout: Duration) -> Check {
+    let address = format!("{}:{}", config.host, config.port);
+    match tokio::time::timeout(timeout, TcpStream::connect(&address)).await {
+        Ok(Ok(_)) => Check::new("database", true, format!("{} reachable", address)),
+        Ok(Err(e)) => Check::new("database", false, format!("{}: {}", address, e)),
+        Err(_) => Check::new("database", false, format!("{}: no answer within {:?}", address, timeout)),
+    }
+}
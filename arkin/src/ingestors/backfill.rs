@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use strum::{Display, EnumString};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::{
+    config::BackfillConfig,
+    db::DBManager,
+    models::{Event, InstrumentRegistry},
+    rest::RestClient,
+};
+
+use super::{
+    models::{BinanceAggTrade, BinanceKline},
+    BinanceParser, IngestorError,
+};
+
+/// Binance caps the time range of aggregate trade requests that are not paged by id
+const TRADES_WINDOW: time::Duration = time::Duration::hours(1);
+
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum BackfillKind {
+    Trades,
+    Klines,
+}
+
+/// Pages through the Binance REST history of aggregate trades and klines and stores it
+pub struct BinanceBackfill {
+    rest: Arc<RestClient>,
+    config: BackfillConfig,
+    parser: BinanceParser,
+}
+
+impl BinanceBackfill {
+    pub fn from_config(config: &BackfillConfig, rest: Arc<RestClient>, instruments: Arc<InstrumentRegistry>) -> Self {
+        Self {
+            rest,
+            config: config.to_owned(),
+            parser: BinanceParser::new(instruments),
+        }
+    }
+
+    /// Store the history of the symbol between start and end, returns the number of stored events
+    pub async fn run(
+        &mut self,
+        manager: &DBManager,
+        kind: BackfillKind,
+        symbol: &str,
+        interval: &str,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<usize> {
+        let mut stored = 0;
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut cursor = Cursor::Time(start);
+        while let Some(events) = match kind {
+            BackfillKind::Trades => self.trades(symbol, &mut cursor, end).await?,
+            BackfillKind::Klines => self.klines(symbol, interval, &mut cursor, end).await?,
+        } {
+            batch.extend(events);
+            if batch.len() >= self.config.batch_size {
+                stored += batch.len();
+                manager.insert_events_batch(&batch).await?;
+                info!("Stored {} {} of {}", stored, kind, symbol);
+                batch.clear();
+            }
+        }
+        stored += batch.len();
+        manager.insert_events_batch(&batch).await?;
+        info!("Backfill of {} {} done, stored {}", symbol, kind, stored);
+        Ok(stored)
+    }
+
+    /// The next page of trades, none once the end is reached. Empty hours are skipped by time until
+    /// the first trade, from there on the pages follow the trade ids so no trade is missed
+    async fn trades(
+        &mut self,
+        symbol: &str,
+        cursor: &mut Cursor,
+        end: OffsetDateTime,
+    ) -> Result<Option<Vec<Event>>, IngestorError> {
+        let limit = self.config.trades_limit.to_string();
+        let mut query = vec![("symbol", symbol.to_owned()), ("limit", limit)];
+        match *cursor {
+            Cursor::Time(from) if from >= end => return Ok(None),
+            Cursor::Time(from) => {
+                let till = (from + TRADES_WINDOW).min(end) - time::Duration::milliseconds(1);
+                query.push(("startTime", timestamp(from)));
+                query.push(("endTime", timestamp(till)));
+            }
+            Cursor::Id(id) => query.push(("fromId", id.to_string())),
+        }
+
+        let url = self.config.trades_url.clone();
+        let page = self
+            .rest
+            .get::<Vec<BinanceAggTrade>>(&url, &query, self.config.trades_weight)
+            .await?;
+        let Some(last) = page.last() else {
+            return Ok(match *cursor {
+                Cursor::Time(from) => {
+                    *cursor = Cursor::Time(from + TRADES_WINDOW);
+                    Some(Vec::new())
+                }
+                Cursor::Id(_) => None,
+            });
+        };
+        *cursor = if last.transaction_time >= end {
+            Cursor::Time(end)
+        } else {
+            Cursor::Id(last.agg_trade_id + 1)
+        };
+
+        let instrument = self.parser.instrument(self.config.market, symbol)?;
+        Ok(Some(
+            page.into_iter()
+                .filter(|t| t.transaction_time < end)
+                .map(|t| Event::Trade(t.into_trade(instrument.clone())))
+                .collect(),
+        ))
+    }
+
+    /// The next page of klines, none once the end is reached
+    async fn klines(
+        &mut self,
+        symbol: &str,
+        interval: &str,
+        cursor: &mut Cursor,
+        end: OffsetDateTime,
+    ) -> Result<Option<Vec<Event>>, IngestorError> {
+        let Cursor::Time(from) = *cursor else {
+            unreachable!("Klines are paged by time");
+        };
+        if from >= end {
+            return Ok(None);
+        }
+        let query = [
+            ("symbol", symbol.to_owned()),
+            ("interval", interval.to_owned()),
+            ("startTime", timestamp(from)),
+            ("endTime", timestamp(end - time::Duration::milliseconds(1))),
+            ("limit", self.config.klines_limit.to_string()),
+        ];
+        let url = self.config.klines_url.clone();
+        let page = self
+            .rest
+            .get::<Vec<BinanceKline>>(&url, &query, self.config.klines_weight)
+            .await?;
+        let Some(last) = page.last() else {
+            return Ok(None);
+        };
+        *cursor = Cursor::Time(last.close_time() + time::Duration::milliseconds(1));
+
+        let instrument = self.parser.instrument(self.config.market, symbol)?;
+        let now = OffsetDateTime::now_utc();
+        Ok(Some(
+            page.into_iter()
+                .map(|k| Event::Candle(k.into_candle(instrument.clone(), now)))
+                .collect(),
+        ))
+    }
+}
+
+/// Where the next page starts
+#[derive(Debug, Clone, Copy)]
+enum Cursor {
+    Time(OffsetDateTime),
+    Id(u64),
+}
+
+fn timestamp(time: OffsetDateTime) -> String {
+    (time.unix_timestamp_nanos() / 1_000_000).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::Instrument, models::Venue};
+    use rust_decimal::Decimal;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_parse_klines() {
+        let json = r#"[[1499040000000,"0.01634790","0.80000000","0.01575800","0.01577100","148976.11427815",1499040059999,"2434.19055334",308,"1756.87402397","28.46694368","0"]]"#;
+        let klines = serde_json::from_str::<Vec<BinanceKline>>(json).unwrap();
+        assert_eq!(klines.len(), 1);
+
+        let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let now = datetime!(2017-07-03 00:01:00).assume_utc();
+        let candle = klines.into_iter().next().unwrap().into_candle(instrument, now);
+        assert_eq!(candle.interval, time::Duration::minutes(1));
+        assert_eq!(candle.close_time(), now);
+        assert_eq!(candle.high.value(), Decimal::new(8, 1));
+        assert_eq!(candle.trades, 308);
+        assert!(candle.closed);
+    }
+}
@@ -1,15 +1,18 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use rust_decimal::Decimal;
-use time::OffsetDateTime;
-use tracing::info;
+use tokio::{select, sync::broadcast::error::RecvError};
+use tracing::{info, warn};
 
 use crate::{
+    bus::EventBus,
+    clock::Clock,
     config::BacktestIngestorConfig,
     ingestors::IngestorID,
-    models::{Event, Instrument, Trade, Venue},
-    state::StateManager,
+    metrics::METRICS,
+    models::{Instrument, Trade, Venue},
+    shutdown::ShutdownSignal,
 };
 
 use super::Ingestor;
@@ -17,14 +20,16 @@ use super::Ingestor;
 #[derive(Clone)]
 #[allow(unused)]
 pub struct BacktestIngestor {
-    state: Arc<StateManager>,
+    bus: Arc<EventBus>,
+    clock: Arc<dyn Clock>,
     market_data: bool,
 }
 
 impl BacktestIngestor {
-    pub fn new(state: Arc<StateManager>, config: &BacktestIngestorConfig) -> Self {
+    pub fn new(bus: Arc<EventBus>, clock: Arc<dyn Clock>, config: &BacktestIngestorConfig) -> Self {
         BacktestIngestor {
-            state,
+            bus,
+            clock,
             market_data: config.market_data,
         }
     }
@@ -32,24 +37,36 @@ impl BacktestIngestor {
 
 #[async_trait]
 impl Ingestor for BacktestIngestor {
-    async fn start(&self) {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
         info!("Starting backtest ingestor...");
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+        let mut ticks = self.clock.subscribe(Duration::from_secs(5));
 
         let mut trade_id = 0;
 
         loop {
-            interval.tick().await;
+            let tick = select! {
+                tick = ticks.recv() => tick,
+                _ = shutdown.wait() => break,
+            };
+            let event_time = match tick {
+                Ok(time) => time,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Backtest ingestor skipped {} clock ticks", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
             let trade = Trade::new(
-                OffsetDateTime::now_utc(),
-                OffsetDateTime::now_utc(),
+                self.clock.now(),
+                event_time,
                 Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()),
                 trade_id,
                 Decimal::new(50000, 0).into(),
                 Decimal::new(1, 0).into(),
                 IngestorID::Backtest,
             );
-            self.state.add_event(Event::Trade(trade));
+            METRICS.ingested_events.with_label_values(&["backtest"]).inc();
+            self.bus.publish(trade);
             trade_id += 1;
         }
     }
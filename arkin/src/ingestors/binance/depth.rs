@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::{error, info};
+
+use super::{order_book::OrderBookManager, provider::BinanceProtocol};
+use crate::{
+    bus::EventBus,
+    clock::ServerClock,
+    config::{BinanceDepthIngestorConfig, BinanceMarket},
+    ingestors::{
+        models::BinanceParser,
+        queue::queue,
+        ws::{ConnectionMetrics, WebSocketManager},
+        Ingestor,
+    },
+    metrics::METRICS,
+    models::InstrumentRegistry,
+    shutdown::ShutdownSignal,
+};
+
+/// Streams the Binance diff depth channels into local order books and publishes their top levels
+#[derive(Clone)]
+pub struct BinanceDepthIngestor {
+    bus: Arc<EventBus>,
+    parser: BinanceParser,
+    market: BinanceMarket,
+    ws: Arc<WebSocketManager>,
+    snapshot_url: String,
+    snapshot_limit: usize,
+    depth: usize,
+}
+
+impl BinanceDepthIngestor {
+    pub fn new(
+        bus: Arc<EventBus>,
+        instruments: Arc<InstrumentRegistry>,
+        clock: ServerClock,
+        config: &BinanceDepthIngestorConfig,
+    ) -> Self {
+        let ws = WebSocketManager::new(
+            Arc::new(BinanceProtocol::default()),
+            config.ws_url.parse().expect("Failed to parse ws binance URL"),
+            config.connections_per_manager,
+            config.max_streams_per_connection,
+            config.duplicate_lookback,
+            config.reconnect.clone(),
+            config.backpressure.clone(),
+        )
+        .with_metrics(ConnectionMetrics::new("binance_depth"));
+        ws.subscribe(&config.ws_channels);
+
+        Self {
+            bus,
+            parser: BinanceParser::new(instruments).with_clock(clock),
+            market: config.market,
+            ws: Arc::new(ws),
+            snapshot_url: config.snapshot_url.clone(),
+            snapshot_limit: config.snapshot_limit,
+            depth: config.depth,
+        }
+    }
+}
+
+#[async_trait]
+impl Ingestor for BinanceDepthIngestor {
+    async fn start(&self, shutdown: ShutdownSignal) {
+        info!("Starting binance depth ingestor...");
+
+        let (tx, rx) = queue("binance_depth", &self.ws.backpressure);
+        let ws = self.ws.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ws.run(tx, shutdown).await {
+                error!("Binance depth websocket manager stopped: {}", e);
+            }
+        });
+
+        let mut books = OrderBookManager::new(self.snapshot_url.clone(), self.snapshot_limit, self.depth);
+        loop {
+            // The websocket manager is gone, after a shutdown once the queue is drained
+            let Ok((connection, data)) = rx.recv_async().await else {
+                break;
+            };
+            // Stamped before the snapshot request, updates that waited for it arrived earlier
+            let received_time = self.parser.now();
+            let res = match self.parser.parse_depth(self.market, data) {
+                Ok((instrument, update)) => {
+                    self.ws.metrics().observe(connection, &update.event_time, received_time);
+                    books.update(instrument, update, received_time).await
+                }
+                Err(e) => {
+                    self.ws.metrics().failed(connection);
+                    Err(e)
+                }
+            };
+            match res {
+                Ok(Some(update)) => {
+                    METRICS.ingested_events.with_label_values(&["binance_depth"]).inc();
+                    self.bus.publish(update);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    METRICS.ingest_errors.with_label_values(&["binance_depth"]).inc();
+                    error!("{}", e)
+                }
+            }
+        }
+        info!("Binance depth ingestor stopped");
+    }
+}
@@ -1,4 +1,7 @@
+mod depth;
+mod order_book;
 mod provider;
+mod user_data;
 
+pub use depth::BinanceDepthIngestor;
 pub use provider::BinanceIngestor;
-pub use provider::Subscription;
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use reqwest::Client;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::{
+    ingestors::{
+        models::{BinanceDepthSnapshot, BinanceDepthUpdate},
+        IngestorError, IngestorID,
+    },
+    metrics::METRICS,
+    models::{BookSide, BookUpdate, BookUpdateSide, Instrument, OrderBook},
+};
+
+/// Outcome of applying a diff depth update to a local book
+#[derive(Debug, PartialEq)]
+pub enum DepthSync {
+    Applied,
+    /// Already contained in the snapshot
+    Stale,
+    /// Updates were missed, the book needs a new snapshot
+    Gap {
+        last_update_id: u64,
+        first_update_id: u64,
+    },
+}
+
+/// Price levels of one symbol, kept in sync with the diff depth stream
+#[derive(Default)]
+pub struct LocalBook {
+    /// None until a snapshot is loaded
+    last_update_id: Option<u64>,
+    /// Whether an update was applied on top of the snapshot
+    bridged: bool,
+    book: OrderBook,
+}
+
+impl LocalBook {
+    pub fn is_synced(&self) -> bool {
+        self.last_update_id.is_some()
+    }
+
+    pub fn load(&mut self, snapshot: BinanceDepthSnapshot) {
+        self.book.clear();
+        for (side, levels) in [(BookSide::Bid, snapshot.bids), (BookSide::Ask, snapshot.asks)] {
+            for (price, quantity) in levels {
+                self.book.update(side, price, quantity);
+            }
+        }
+        self.last_update_id = Some(snapshot.last_update_id);
+        self.bridged = false;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The first update after the snapshot has to straddle it, every later one has to follow the
+    /// previous without a gap
+    pub fn apply(&mut self, update: &BinanceDepthUpdate) -> DepthSync {
+        let Some(last) = self.last_update_id else {
+            return DepthSync::Gap {
+                last_update_id: 0,
+                first_update_id: update.first_update_id,
+            };
+        };
+        // Futures ids are not consecutive, the update spanning the snapshot id is the bridge
+        let bridge = if update.previous_update_id.is_some() {
+            last
+        } else {
+            last + 1
+        };
+        if !self.bridged && update.final_update_id < bridge {
+            return DepthSync::Stale;
+        }
+        let in_sequence = match (self.bridged, update.previous_update_id) {
+            (false, _) => update.first_update_id <= bridge,
+            (true, Some(previous)) => previous == last,
+            (true, None) => update.first_update_id == last + 1,
+        };
+        if !in_sequence {
+            return DepthSync::Gap {
+                last_update_id: last,
+                first_update_id: update.first_update_id,
+            };
+        }
+
+        for (side, changes) in [(BookSide::Bid, &update.bids), (BookSide::Ask, &update.asks)] {
+            for (price, quantity) in changes {
+                self.book.update(side, *price, *quantity);
+            }
+        }
+        self.last_update_id = Some(update.final_update_id);
+        self.bridged = true;
+        DepthSync::Applied
+    }
+
+    /// Best levels first, at most `depth` per side
+    pub fn levels(&self, depth: usize) -> (Vec<BookUpdateSide>, Vec<BookUpdateSide>) {
+        self.book.levels(depth)
+    }
+}
+
+/// Local order books of the Binance diff depth streams. Books are loaded from the REST snapshot on
+/// the first update and reloaded whenever a gap in the update ids shows that updates were missed
+pub struct OrderBookManager {
+    client: Client,
+    snapshot_url: String,
+    snapshot_limit: usize,
+    depth: usize,
+    books: HashMap<String, LocalBook>,
+}
+
+impl OrderBookManager {
+    pub fn new(snapshot_url: String, snapshot_limit: usize, depth: usize) -> Self {
+        Self {
+            client: Client::new(),
+            snapshot_url,
+            snapshot_limit,
+            depth,
+            books: HashMap::new(),
+        }
+    }
+
+    /// The top of the book after the update, none while the update doesn't apply. Updates that
+    /// arrive while the snapshot loads wait in the ingestor queue, so nothing is lost in between
+    pub async fn update(
+        &mut self,
+        instrument: Instrument,
+        update: BinanceDepthUpdate,
+        received_time: OffsetDateTime,
+    ) -> Result<Option<BookUpdate>, IngestorError> {
+        let book = self.books.entry(update.instrument.clone()).or_default();
+        if !book.is_synced() {
+            info!("Loading {} order book snapshot", update.instrument);
+            let snapshot = self
+                .client
+                .get(&self.snapshot_url)
+                .query(&[
+                    ("symbol", update.instrument.as_str()),
+                    ("limit", &self.snapshot_limit.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<BinanceDepthSnapshot>()
+                .await?;
+            book.load(snapshot);
+        }
+
+        match book.apply(&update) {
+            DepthSync::Applied => {
+                let (bids, asks) = book.levels(self.depth);
+                Ok(Some(BookUpdate {
+                    received_time,
+                    event_time: update.event_time,
+                    instrument,
+                    update_id: update.final_update_id,
+                    bids,
+                    asks,
+                    source: IngestorID::Binance,
+                }))
+            }
+            DepthSync::Stale => Ok(None),
+            DepthSync::Gap {
+                last_update_id,
+                first_update_id,
+            } => {
+                warn!(
+                    "Gap in the {} order book after update {}, next starts at {}, resyncing",
+                    update.instrument, last_update_id, first_update_id
+                );
+                METRICS.book_resyncs.with_label_values(&[&update.instrument]).inc();
+                book.reset();
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Price;
+    use rust_decimal::Decimal;
+    use time::macros::datetime;
+
+    fn update(
+        first: u64,
+        last: u64,
+        previous: Option<u64>,
+        bids: &[(i64, i64)],
+        asks: &[(i64, i64)],
+    ) -> BinanceDepthUpdate {
+        let levels =
+            |levels: &[(i64, i64)]| levels.iter().map(|(p, q)| (Decimal::from(*p), Decimal::from(*q))).collect();
+        BinanceDepthUpdate {
+            event_time: datetime!(2024-01-01 00:00:00).assume_utc(),
+            instrument: "BTCUSDT".into(),
+            first_update_id: first,
+            final_update_id: last,
+            previous_update_id: previous,
+            bids: levels(bids),
+            asks: levels(asks),
+        }
+    }
+
+    fn snapshot(last_update_id: u64) -> BinanceDepthSnapshot {
+        BinanceDepthSnapshot {
+            last_update_id,
+            bids: vec![(Decimal::from(99), Decimal::ONE), (Decimal::from(98), Decimal::TWO)],
+            asks: vec![(Decimal::from(101), Decimal::ONE)],
+        }
+    }
+
+    #[test]
+    fn test_local_book() {
+        // Futures chain the updates through the previous final id
+        let mut book = LocalBook::default();
+        book.load(snapshot(100));
+        assert_eq!(book.apply(&update(90, 99, Some(89), &[], &[])), DepthSync::Stale);
+        assert_eq!(
+            book.apply(&update(95, 105, Some(99), &[(100, 3)], &[(101, 0)])),
+            DepthSync::Applied
+        );
+        assert_eq!(
+            book.apply(&update(108, 110, Some(105), &[(99, 0)], &[(102, 4)])),
+            DepthSync::Applied
+        );
+        let (bids, asks) = book.levels(1);
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].price, Price::from(100.));
+        assert_eq!(asks[0].price, Price::from(102.));
+        assert_eq!(book.levels(10).0.len(), 2);
+        assert!(matches!(
+            book.apply(&update(115, 120, Some(112), &[], &[])),
+            DepthSync::Gap {
+                last_update_id: 110,
+                ..
+            }
+        ));
+
+        // Spot ids are consecutive and the snapshot has to be bridged by the first update
+        book.reset();
+        assert!(!book.is_synced());
+        book.load(snapshot(100));
+        assert_eq!(book.apply(&update(95, 100, None, &[], &[])), DepthSync::Stale);
+        assert_eq!(book.apply(&update(101, 103, None, &[], &[])), DepthSync::Applied);
+        assert!(matches!(book.apply(&update(105, 106, None, &[], &[])), DepthSync::Gap { .. }));
+        book.load(snapshot(100));
+        assert!(matches!(book.apply(&update(102, 104, None, &[], &[])), DepthSync::Gap { .. }));
+    }
+}
@@ -2,105 +2,346 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use async_tungstenite::tungstenite::Message;
-use serde::Serialize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use tracing::{error, info, warn};
 use url::Url;
 
+use super::user_data::UserDataStream;
 use crate::{
-    config::BinanceIngestorConfig,
-    ingestors::{models::BinanceParser, ws::WebSocketManager, Ingestor},
-    state::StateManager,
+    bus::EventBus,
+    clock::ServerClock,
+    config::{BinanceIngestorConfig, BinanceMarket},
+    credentials::Credentials,
+    ingestors::{
+        models::{BinanceAggTrade, BinanceParser},
+        queue::queue,
+        ws::{
+            ConnectionMetrics, Subscription, SubscriptionFailure, SubscriptionResponse, WebSocketManager, WsProtocol,
+        },
+        Ingestor, IngestorError, IngestorID, SequenceCheck, SequenceTracker,
+    },
+    metrics::METRICS,
+    models::{DataGap, InstrumentRegistry},
+    shutdown::ShutdownSignal,
 };
 
 #[derive(Clone)]
 pub struct BinanceIngestor {
-    state: Arc<StateManager>,
-    url: Url,
-    channels: Vec<String>,
-    api_key: Option<String>,
-    api_secret: Option<String>,
-    connections_per_manager: usize,
-    duplicate_lookback: usize,
+    bus: Arc<EventBus>,
+    parser: BinanceParser,
+    market: BinanceMarket,
+    ws: Arc<WebSocketManager>,
+    credentials: Option<Credentials>,
+    user_data: Option<Arc<UserDataStream>>,
+    instruments: Arc<InstrumentRegistry>,
+    client: Client,
+    gap_refetch_url: Option<String>,
 }
 
 impl BinanceIngestor {
-    pub fn new(state: Arc<StateManager>, config: &BinanceIngestorConfig) -> Self {
+    pub fn new(
+        bus: Arc<EventBus>,
+        instruments: Arc<InstrumentRegistry>,
+        credentials: Option<Credentials>,
+        clock: ServerClock,
+        config: &BinanceIngestorConfig,
+    ) -> Self {
+        let ws = WebSocketManager::new(
+            Arc::new(BinanceProtocol::new(config.combined_streams)),
+            config.ws_url.parse().expect("Failed to parse ws binance URL"),
+            config.connections_per_manager,
+            config.max_streams_per_connection,
+            config.duplicate_lookback,
+            config.reconnect.clone(),
+            config.backpressure.clone(),
+        )
+        .with_metrics(ConnectionMetrics::new("binance"));
+        ws.subscribe(&config.ws_channels);
+
+        let parser = BinanceParser::new(instruments.clone()).with_clock(clock);
+        let user_data = config
+            .user_data
+            .as_ref()
+            .zip(credentials.clone())
+            .map(|(user_data, credentials)| {
+                Arc::new(UserDataStream::new(
+                    bus.clone(),
+                    parser.clone(),
+                    credentials,
+                    config.ws_url.clone(),
+                    config.reconnect.clone(),
+                    config.backpressure.clone(),
+                    user_data,
+                ))
+            });
+
         Self {
-            state,
-            url: config.ws_url.parse().expect("Failed to parse ws binance URL"),
-            channels: config.ws_channels.to_owned(),
-            api_key: config.api_key.to_owned(),
-            api_secret: config.api_secret.to_owned(),
-            connections_per_manager: config.connections_per_manager,
-            duplicate_lookback: config.duplicate_lookback,
+            bus,
+            parser,
+            market: config.market,
+            ws: Arc::new(ws),
+            credentials,
+            user_data,
+            instruments,
+            client: Client::new(),
+            gap_refetch_url: config.gap_refetch_url.clone(),
         }
     }
+
+    /// Publish the trades missed in the gap from the REST api, they arrive after the trades that
+    /// revealed the gap
+    fn refetch(&self, gap: &DataGap) {
+        let Some(url) = self.gap_refetch_url.clone() else {
+            return;
+        };
+        let Some(info) = self.instruments.get(&gap.instrument) else {
+            return;
+        };
+        let client = self.client.clone();
+        let bus = self.bus.clone();
+        let gap = gap.clone();
+        tokio::spawn(async move {
+            match refetch_trades(&client, &url, &info.symbol, &gap).await {
+                Ok(trades) => {
+                    info!("Refetched {} of {} missed {} trades", trades.len(), gap.missing(), info.symbol);
+                    for trade in trades {
+                        bus.publish(trade.into_trade(gap.instrument.clone()));
+                    }
+                }
+                Err(e) => warn!("Failed to refetch the missed {} trades: {}", info.symbol, e),
+            }
+        });
+    }
+
+    /// Streams additional channels on the live connections
+    pub fn subscribe(&self, channels: &[String]) -> Vec<u64> {
+        self.ws.subscribe(channels)
+    }
+
+    pub fn unsubscribe(&self, channels: &[String]) -> Vec<u64> {
+        self.ws.unsubscribe(channels)
+    }
+
+    pub fn channels(&self) -> Vec<String> {
+        self.ws.shards().concat()
+    }
+
+    /// Ids of the subscription requests Binance has not confirmed yet
+    pub fn pending_requests(&self) -> Vec<u64> {
+        self.ws.pending()
+    }
+
+    /// Subscriptions Binance rejected, e.g. for invalid stream names, or never answered
+    pub fn failed_subscriptions(&self) -> Vec<SubscriptionFailure> {
+        self.ws.failures()
+    }
 }
 
 #[async_trait]
 impl Ingestor for BinanceIngestor {
-    async fn start(&self) {
+    async fn start(&self, shutdown: ShutdownSignal) {
         info!("Starting binance ingestor...");
 
         // Check for API key and secret
-        if self.api_key.is_none() || self.api_secret.is_none() {
+        if self.credentials.is_none() {
             warn!("API key and secret are required for faster connection on Binance ingestor");
         }
 
-        let mut ws_manager =
-            WebSocketManager::new(self.url.clone(), self.connections_per_manager, self.duplicate_lookback);
-
-        let (tx, rx) = flume::unbounded();
-        let subscription = Subscription::new(self.channels.iter().map(|c| c.as_str()).collect());
+        if let Some(user_data) = self.user_data.clone() {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move { user_data.run(shutdown).await });
+        }
 
+        let (tx, rx) = queue("binance", &self.ws.backpressure);
+        let ws = self.ws.clone();
         tokio::spawn(async move {
-            ws_manager.run(tx, subscription).await.unwrap();
+            if let Err(e) = ws.run(tx, shutdown).await {
+                error!("Binance websocket manager stopped: {}", e);
+            }
         });
 
+        let mut sequence = SequenceTracker::default();
         loop {
             let res = rx.recv_async().await;
             match res {
-                Ok(data) => {
-                    let res = BinanceParser::parse_swap(&data);
+                Ok((connection, data)) => {
+                    let received_time = self.parser.now();
+                    let res = self.parser.parse(self.market, data);
                     match res {
                         Ok(event) => {
-                            self.state.add_event(event);
+                            METRICS.ingested_events.with_label_values(&["binance"]).inc();
+                            self.ws.metrics().observe(connection, event.event_time(), received_time);
+                            let check = sequence.check(&event);
+                            match check {
+                                SequenceCheck::InOrder => {}
+                                // Stale, a newer trade or tick of the instrument was published already
+                                SequenceCheck::OutOfOrder { last_id, id } => {
+                                    METRICS.out_of_order_events.with_label_values(&["binance"]).inc();
+                                    warn!("Dropped {} {} received after {}", event.event_type(), id, last_id);
+                                    continue;
+                                }
+                                SequenceCheck::Gap { .. } => {
+                                    if let Some(gap) = SequenceTracker::gap(&event, &check, IngestorID::Binance) {
+                                        METRICS.missed_trades.with_label_values(&["binance"]).inc_by(gap.missing());
+                                        warn!("{}", gap);
+                                        self.refetch(&gap);
+                                        self.bus.publish(gap);
+                                    }
+                                }
+                            }
+                            self.bus.publish_event(event);
+                        }
+                        Err(e) => {
+                            METRICS.ingest_errors.with_label_values(&["binance"]).inc();
+                            self.ws.metrics().failed(connection);
+                            error!("{}", e)
                         }
-                        Err(e) => error!("{}", e),
                     }
                 }
-                Err(e) => {
-                    error!("{}", e);
-                    break;
-                }
+                // The websocket manager is gone, after a shutdown once the queue is drained
+                Err(_) => break,
             }
         }
+        info!("Binance ingestor stopped");
     }
 }
 
-#[derive(Serialize, Clone)]
-pub struct Subscription {
-    method: String,
-    params: Vec<String>,
+/// The trades of the gap, Binance returns at most 1000 per request
+async fn refetch_trades(
+    client: &Client,
+    url: &str,
+    symbol: &str,
+    gap: &DataGap,
+) -> Result<Vec<BinanceAggTrade>, IngestorError> {
+    let trades = client
+        .get(url)
+        .query(&[
+            ("symbol", symbol.to_owned()),
+            ("fromId", gap.first_missing_id.to_string()),
+            ("limit", gap.missing().min(1000).to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<BinanceAggTrade>>()
+        .await?;
+    Ok(trades.into_iter().filter(|t| t.agg_trade_id <= gap.last_missing_id).collect())
+}
+
+/// Binance frames (un)subscriptions as `{"method": "SUBSCRIBE", "params": [..], "id": 1}`
+#[derive(Default)]
+pub struct BinanceProtocol {
+    /// Connects to the combined endpoint `/stream?streams=a/b`, every message names its stream
+    combined: bool,
+}
+
+impl BinanceProtocol {
+    pub fn new(combined: bool) -> Self {
+        Self { combined }
+    }
+}
+
+#[derive(Serialize)]
+struct BinanceRequest<'a> {
+    method: &'a str,
+    params: &'a [String],
     id: u64,
 }
 
-impl Subscription {
-    pub fn new(channels: Vec<&str>) -> Self {
-        Self {
-            method: "SUBSCRIBE".to_string(),
-            params: channels.iter().map(|c| c.to_string()).collect(),
-            id: 0,
+#[derive(Deserialize)]
+struct BinanceResponse {
+    id: u64,
+    #[serde(default)]
+    error: Option<BinanceResponseError>,
+}
+
+#[derive(Deserialize)]
+struct BinanceResponseError {
+    code: i64,
+    msg: String,
+}
+
+impl WsProtocol for BinanceProtocol {
+    fn request(&self, subscription: &Subscription) -> Message {
+        let request = BinanceRequest {
+            method: if subscription.subscribe {
+                "SUBSCRIBE"
+            } else {
+                "UNSUBSCRIBE"
+            },
+            params: &subscription.channels,
+            id: subscription.id,
+        };
+        Message::Text(serde_json::to_string(&request).expect("Failed to serialize subscription"))
+    }
+
+    /// Market data never carries a top level id, so only those messages are parsed
+    fn response(&self, text: &str) -> Option<SubscriptionResponse> {
+        if !text.contains("\"id\"") {
+            return None;
         }
+        let response = serde_json::from_str::<BinanceResponse>(text).ok()?;
+        Some(SubscriptionResponse {
+            id: Some(response.id),
+            error: response.error.map(|e| format!("{} (code {})", e.msg, e.code)),
+        })
     }
 
-    pub fn update_id(&mut self, id: u64) {
-        self.id = id;
+    fn url(&self, url: &Url, channels: &[String]) -> Option<Url> {
+        if !self.combined {
+            return None;
+        }
+        let mut url = url.clone();
+        url.set_path("/stream");
+        url.set_query(
+            (!channels.is_empty())
+                .then(|| format!("streams={}", channels.join("/")))
+                .as_deref(),
+        );
+        Some(url)
     }
 }
 
-impl From<Subscription> for Message {
-    fn from(sub: Subscription) -> Self {
-        Message::Text(serde_json::to_string(&sub).expect("Failed to serialize subscription"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let request = BinanceProtocol::default().request(&Subscription {
+            id: 3,
+            subscribe: false,
+            channels: vec!["btcusdt@aggTrade".into()],
+        });
+        assert_eq!(
+            request.to_string(),
+            r#"{"method":"UNSUBSCRIBE","params":["btcusdt@aggTrade"],"id":3}"#
+        );
+
+        let response = BinanceProtocol::default().response(r#"{"result":null,"id":3}"#).unwrap();
+        assert_eq!(response.id, Some(3));
+        assert!(response.error.is_none());
+        let response = BinanceProtocol::default()
+            .response(r#"{"error":{"code":2,"msg":"Invalid request"},"id":4}"#)
+            .unwrap();
+        assert_eq!(response.error.unwrap(), "Invalid request (code 2)");
+        assert!(BinanceProtocol::default()
+            .response(r#"{"e":"aggTrade","s":"BTCUSDT"}"#)
+            .is_none());
+    }
+
+    #[test]
+    fn test_combined_url() {
+        let url = "wss://fstream.binance.com/ws".parse().unwrap();
+        let channels = vec!["btcusdt@aggTrade".to_owned(), "ethusdt@markPrice@1s".to_owned()];
+        assert!(BinanceProtocol::default().url(&url, &channels).is_none());
+        let combined = BinanceProtocol::new(true).url(&url, &channels).unwrap();
+        assert_eq!(
+            combined.as_str(),
+            "wss://fstream.binance.com/stream?streams=btcusdt@aggTrade/ethusdt@markPrice@1s"
+        );
+        let empty = BinanceProtocol::new(true).url(&url, &[]).unwrap();
+        assert_eq!(empty.as_str(), "wss://fstream.binance.com/stream");
     }
 }
@@ -0,0 +1,169 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::{
+    select,
+    time::{interval, sleep},
+};
+use tracing::{error, info, warn};
+use url::Url;
+
+use super::provider::BinanceProtocol;
+use crate::{
+    bus::EventBus,
+    config::{BackpressureConfig, ReconnectConfig, UserDataConfig},
+    credentials::Credentials,
+    ingestors::{
+        models::BinanceParser,
+        queue::queue,
+        ws::{ConnectionMetrics, WebSocketManager},
+        IngestorError,
+    },
+    metrics::METRICS,
+    shutdown::ShutdownSignal,
+};
+
+/// The stream is quiet between account changes, only the pings every 3 minutes show it is alive
+const MIN_STALE_TIMEOUT: u64 = 5 * 60 * 1000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenKey {
+    listen_key: String,
+}
+
+/// Order and account updates of one account, streamed over a listen key that is kept alive over REST
+pub struct UserDataStream {
+    bus: Arc<EventBus>,
+    parser: BinanceParser,
+    client: Client,
+    credentials: Credentials,
+    listen_key_url: String,
+    ws_url: String,
+    keepalive_interval: Duration,
+    reconnect: ReconnectConfig,
+    backpressure: BackpressureConfig,
+}
+
+impl UserDataStream {
+    pub fn new(
+        bus: Arc<EventBus>,
+        parser: BinanceParser,
+        credentials: Credentials,
+        ws_url: String,
+        reconnect: ReconnectConfig,
+        backpressure: BackpressureConfig,
+        config: &UserDataConfig,
+    ) -> Self {
+        Self {
+            bus,
+            parser,
+            client: Client::new(),
+            credentials,
+            listen_key_url: config.listen_key_url.clone(),
+            ws_url,
+            keepalive_interval: Duration::from_secs(config.keepalive_interval),
+            reconnect: ReconnectConfig {
+                stale_timeout: reconnect.stale_timeout.max(MIN_STALE_TIMEOUT),
+                ..reconnect
+            },
+            backpressure,
+        }
+    }
+
+    /// Restarts with a new listen key whenever the stream or the keepalive fails, until the shutdown
+    pub async fn run(&self, mut shutdown: ShutdownSignal) {
+        loop {
+            if let Err(e) = self.stream(shutdown.clone()).await {
+                error!("Binance user data stream stopped: {}", e);
+            }
+            if shutdown.is_triggered() {
+                return;
+            }
+            let delay = Duration::from_millis(self.reconnect.max_backoff);
+            info!("Requesting a new listen key in {:?}", delay);
+            select! {
+                _ = sleep(delay) => {}
+                _ = shutdown.wait() => return,
+            }
+        }
+    }
+
+    async fn stream(&self, shutdown: ShutdownSignal) -> Result<(), IngestorError> {
+        let listen_key = self.listen_key().await?;
+        let url: Url = format!("{}/{}", self.ws_url.trim_end_matches('/'), listen_key)
+            .parse()
+            .expect("Failed to parse user data URL");
+        let ws = WebSocketManager::new(
+            Arc::new(BinanceProtocol::default()),
+            url,
+            1,
+            1,
+            1,
+            self.reconnect.clone(),
+            self.backpressure.clone(),
+        )
+        .with_metrics(ConnectionMetrics::new("binance_user"));
+        let metrics = ws.metrics().clone();
+        let (tx, rx) = queue("binance_user", &self.backpressure);
+        info!("Streaming Binance user data");
+
+        let run = ws.run(tx, shutdown);
+        tokio::pin!(run);
+        let mut keepalive = interval(self.keepalive_interval);
+        // The first tick completes immediately
+        keepalive.tick().await;
+        loop {
+            select! {
+                res = &mut run => {
+                    // Fills received before the socket closed still reach the portfolio
+                    rx.drain().for_each(|(connection, data)| self.handle(&metrics, connection, data));
+                    return res;
+                }
+                _ = keepalive.tick() => self.keepalive(&listen_key).await?,
+                Ok((connection, data)) = rx.recv_async() => self.handle(&metrics, connection, data),
+            }
+        }
+    }
+
+    fn handle(&self, metrics: &ConnectionMetrics, connection: u64, data: String) {
+        let received_time = self.parser.now();
+        match self.parser.parse_user(data) {
+            Ok(Some(event)) => {
+                METRICS.ingested_events.with_label_values(&["binance_user"]).inc();
+                metrics.observe(connection, event.event_time(), received_time);
+                self.bus.publish_event(event);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                METRICS.ingest_errors.with_label_values(&["binance_user"]).inc();
+                metrics.failed(connection);
+                warn!("{}", e);
+            }
+        }
+    }
+
+    async fn listen_key(&self) -> Result<String, IngestorError> {
+        let res = self
+            .client
+            .post(&self.listen_key_url)
+            .header("X-MBX-APIKEY", self.credentials.api_key.expose())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json::<ListenKey>().await?.listen_key)
+    }
+
+    /// Extends the listen key by another hour, fails once it expired
+    async fn keepalive(&self, listen_key: &str) -> Result<(), IngestorError> {
+        self.client
+            .put(&self.listen_key_url)
+            .header("X-MBX-APIKEY", self.credentials.api_key.expose())
+            .query(&[("listenKey", listen_key)])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use async_tungstenite::tungstenite::Message;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use crate::{
+    bus::EventBus,
+    config::BybitIngestorConfig,
+    ingestors::{
+        models::BybitParser,
+        queue::queue,
+        ws::{ConnectionMetrics, Subscription, SubscriptionResponse, WebSocketManager, WsProtocol},
+        Ingestor,
+    },
+    metrics::METRICS,
+    models::InstrumentRegistry,
+    shutdown::ShutdownSignal,
+};
+
+#[derive(Clone)]
+pub struct BybitIngestor {
+    bus: Arc<EventBus>,
+    parser: BybitParser,
+    ws: Arc<WebSocketManager>,
+}
+
+impl BybitIngestor {
+    pub fn new(bus: Arc<EventBus>, instruments: Arc<InstrumentRegistry>, config: &BybitIngestorConfig) -> Self {
+        let ws = WebSocketManager::new(
+            Arc::new(BybitProtocol {
+                ping_interval: Duration::from_secs(config.ping_interval),
+            }),
+            config.ws_url.parse().expect("Failed to parse ws bybit URL"),
+            config.connections_per_manager,
+            config.max_streams_per_connection,
+            config.duplicate_lookback,
+            config.reconnect.clone(),
+            config.backpressure.clone(),
+        )
+        .with_metrics(ConnectionMetrics::new("bybit"));
+        ws.subscribe(&config.ws_channels);
+
+        Self {
+            bus,
+            parser: BybitParser::new(instruments),
+            ws: Arc::new(ws),
+        }
+    }
+}
+
+#[async_trait]
+impl Ingestor for BybitIngestor {
+    async fn start(&self, shutdown: ShutdownSignal) {
+        info!("Starting bybit ingestor...");
+
+        let (tx, rx) = queue("bybit", &self.ws.backpressure);
+        let ws = self.ws.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ws.run(tx, shutdown).await {
+                error!("Bybit websocket manager stopped: {}", e);
+            }
+        });
+
+        // Ends once the websocket manager is gone, after a shutdown once the queue is drained
+        while let Ok((connection, data)) = rx.recv_async().await {
+            let received_time = OffsetDateTime::now_utc();
+            match self.parser.parse(&data) {
+                Ok(events) => {
+                    for event in events {
+                        METRICS.ingested_events.with_label_values(&["bybit"]).inc();
+                        self.ws.metrics().observe(connection, event.event_time(), received_time);
+                        self.bus.publish_event(event);
+                    }
+                }
+                Err(e) => {
+                    METRICS.ingest_errors.with_label_values(&["bybit"]).inc();
+                    self.ws.metrics().failed(connection);
+                    error!("{}", e)
+                }
+            }
+        }
+        info!("Bybit ingestor stopped");
+    }
+}
+
+/// Bybit frames (un)subscriptions as `{"req_id": "1", "op": "subscribe", "args": [..]}` and closes
+/// connections that don't send `{"op": "ping"}` regularly
+pub struct BybitProtocol {
+    ping_interval: Duration,
+}
+
+#[derive(Serialize)]
+struct BybitRequest<'a> {
+    req_id: String,
+    op: &'a str,
+    args: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct BybitResponse {
+    #[serde(default)]
+    success: bool,
+    #[serde(default)]
+    ret_msg: String,
+    #[serde(default)]
+    req_id: Option<String>,
+    op: String,
+}
+
+impl WsProtocol for BybitProtocol {
+    fn request(&self, subscription: &Subscription) -> Message {
+        let request = BybitRequest {
+            req_id: subscription.id.to_string(),
+            op: if subscription.subscribe {
+                "subscribe"
+            } else {
+                "unsubscribe"
+            },
+            args: &subscription.channels,
+        };
+        Message::Text(serde_json::to_string(&request).expect("Failed to serialize subscription"))
+    }
+
+    /// Data always carries a topic and control messages an op
+    fn response(&self, text: &str) -> Option<SubscriptionResponse> {
+        if !text.contains("\"op\"") {
+            return None;
+        }
+        let response = serde_json::from_str::<BybitResponse>(text).ok()?;
+        if !matches!(response.op.as_str(), "subscribe" | "unsubscribe") {
+            return None;
+        }
+        Some(SubscriptionResponse {
+            id: response.req_id.and_then(|id| id.parse().ok()),
+            error: (!response.success).then_some(response.ret_msg),
+        })
+    }
+
+    fn ping(&self) -> Option<(Duration, Message)> {
+        Some((self.ping_interval, Message::Text(r#"{"op":"ping"}"#.into())))
+    }
+
+    /// Derivatives answer with op ping and ret_msg pong, spot with op pong
+    fn is_pong(&self, text: &str) -> bool {
+        text.contains("\"op\"")
+            && serde_json::from_str::<BybitResponse>(text).is_ok_and(|r| matches!(r.op.as_str(), "ping" | "pong"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let protocol = BybitProtocol {
+            ping_interval: Duration::from_secs(20),
+        };
+        let request = protocol.request(&Subscription {
+            id: 7,
+            subscribe: true,
+            channels: vec!["orderbook.1.BTCUSDT".into(), "publicTrade.BTCUSDT".into()],
+        });
+        assert_eq!(
+            request.to_string(),
+            r#"{"req_id":"7","op":"subscribe","args":["orderbook.1.BTCUSDT","publicTrade.BTCUSDT"]}"#
+        );
+
+        let response = protocol
+            .response(
+                r#"{"success":true,"ret_msg":"","conn_id":"cejreaspqfh3sjdnldmg-p","req_id":"7","op":"subscribe"}"#,
+            )
+            .unwrap();
+        assert_eq!(response.id, Some(7));
+        assert!(response.error.is_none());
+        let response = protocol
+            .response(r#"{"success":false,"ret_msg":"error:handler not found,topic:foo.BTCUSDT","conn_id":"c","req_id":"8","op":"subscribe"}"#)
+            .unwrap();
+        assert_eq!(response.error.unwrap(), "error:handler not found,topic:foo.BTCUSDT");
+
+        let pong = r#"{"success":true,"ret_msg":"pong","conn_id":"cejreaspqfh3sjdnldmg-p","req_id":"","op":"ping"}"#;
+        assert!(protocol.is_pong(pong));
+        assert!(protocol.response(pong).is_none());
+        let trade = r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1,"data":[]}"#;
+        assert!(!protocol.is_pong(trade));
+        assert!(protocol.response(trade).is_none());
+        assert_eq!(protocol.ping().unwrap().1.to_string(), r#"{"op":"ping"}"#);
+    }
+}
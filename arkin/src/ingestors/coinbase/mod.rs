@@ -0,0 +1,3 @@
+mod provider;
+
+pub use provider::CoinbaseIngestor;
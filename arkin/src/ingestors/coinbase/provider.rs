@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use async_tungstenite::tungstenite::Message;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use crate::{
+    bus::EventBus,
+    config::CoinbaseIngestorConfig,
+    ingestors::{
+        models::CoinbaseParser,
+        queue::queue,
+        ws::{ConnectionMetrics, Subscription, SubscriptionResponse, WebSocketManager, WsProtocol},
+        Ingestor,
+    },
+    metrics::METRICS,
+    models::InstrumentRegistry,
+    shutdown::ShutdownSignal,
+};
+
+#[derive(Clone)]
+pub struct CoinbaseIngestor {
+    bus: Arc<EventBus>,
+    parser: CoinbaseParser,
+    ws: Arc<WebSocketManager>,
+}
+
+impl CoinbaseIngestor {
+    pub fn new(bus: Arc<EventBus>, instruments: Arc<InstrumentRegistry>, config: &CoinbaseIngestorConfig) -> Self {
+        let ws = WebSocketManager::new(
+            Arc::new(CoinbaseProtocol),
+            config.ws_url.parse().expect("Failed to parse ws coinbase URL"),
+            config.connections_per_manager,
+            config.max_streams_per_connection,
+            config.duplicate_lookback,
+            config.reconnect.clone(),
+            config.backpressure.clone(),
+        )
+        .with_metrics(ConnectionMetrics::new("coinbase"));
+        let streams = config
+            .channels
+            .iter()
+            .flat_map(|channel| config.product_ids.iter().map(move |product| format!("{}:{}", channel, product)))
+            .collect::<Vec<_>>();
+        ws.subscribe(&streams);
+
+        Self {
+            bus,
+            parser: CoinbaseParser::new(instruments),
+            ws: Arc::new(ws),
+        }
+    }
+}
+
+#[async_trait]
+impl Ingestor for CoinbaseIngestor {
+    async fn start(&self, shutdown: ShutdownSignal) {
+        info!("Starting coinbase ingestor...");
+
+        let (tx, rx) = queue("coinbase", &self.ws.backpressure);
+        let ws = self.ws.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ws.run(tx, shutdown).await {
+                error!("Coinbase websocket manager stopped: {}", e);
+            }
+        });
+
+        // Ends once the websocket manager is gone, after a shutdown once the queue is drained
+        while let Ok((connection, data)) = rx.recv_async().await {
+            let received_time = OffsetDateTime::now_utc();
+            match self.parser.parse(&data) {
+                Ok(Some(event)) => {
+                    METRICS.ingested_events.with_label_values(&["coinbase"]).inc();
+                    self.ws.metrics().observe(connection, event.event_time(), received_time);
+                    self.bus.publish_event(event);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    METRICS.ingest_errors.with_label_values(&["coinbase"]).inc();
+                    self.ws.metrics().failed(connection);
+                    error!("{}", e)
+                }
+            }
+        }
+        info!("Coinbase ingestor stopped");
+    }
+}
+
+/// Streams are named `{channel}:{product_id}` and grouped per channel into
+/// `{"type": "subscribe", "channels": [{"name": .., "product_ids": [..]}]}`
+pub struct CoinbaseProtocol;
+
+#[derive(Serialize)]
+struct CoinbaseRequest<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    channels: Vec<CoinbaseChannel<'a>>,
+}
+
+#[derive(Serialize)]
+struct CoinbaseChannel<'a> {
+    name: &'a str,
+    product_ids: Vec<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CoinbaseResponse {
+    Subscriptions,
+    Error {
+        message: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+impl WsProtocol for CoinbaseProtocol {
+    fn request(&self, subscription: &Subscription) -> Message {
+        let mut channels = BTreeMap::<&str, Vec<&str>>::new();
+        for stream in &subscription.channels {
+            let (name, product) = stream.split_once(':').expect("Coinbase streams are named channel:product");
+            channels.entry(name).or_default().push(product);
+        }
+        let request = CoinbaseRequest {
+            kind: if subscription.subscribe {
+                "subscribe"
+            } else {
+                "unsubscribe"
+            },
+            channels: channels
+                .into_iter()
+                .map(|(name, product_ids)| CoinbaseChannel { name, product_ids })
+                .collect(),
+        };
+        Message::Text(serde_json::to_string(&request).expect("Failed to serialize subscription"))
+    }
+
+    /// Coinbase echoes no request id, the answer belongs to the oldest pending request
+    fn response(&self, text: &str) -> Option<SubscriptionResponse> {
+        if !text.contains("\"subscriptions\"") && !text.contains("\"error\"") {
+            return None;
+        }
+        match serde_json::from_str::<CoinbaseResponse>(text).ok()? {
+            CoinbaseResponse::Subscriptions => Some(SubscriptionResponse {
+                id: None,
+                error: None,
+            }),
+            CoinbaseResponse::Error { message, reason } => Some(SubscriptionResponse {
+                id: None,
+                error: Some(reason.map_or(message.clone(), |reason| format!("{}: {}", message, reason))),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let request = CoinbaseProtocol.request(&Subscription {
+            id: 1,
+            subscribe: true,
+            channels: vec!["ticker:BTC-USD".into(), "matches:BTC-USD".into(), "ticker:ETH-USD".into()],
+        });
+        assert_eq!(
+            request.to_string(),
+            r#"{"type":"subscribe","channels":[{"name":"matches","product_ids":["BTC-USD"]},{"name":"ticker","product_ids":["BTC-USD","ETH-USD"]}]}"#
+        );
+
+        let response = CoinbaseProtocol
+            .response(r#"{"type":"subscriptions","channels":[{"name":"ticker","product_ids":["BTC-USD"]}]}"#)
+            .unwrap();
+        assert!(response.id.is_none() && response.error.is_none());
+        let response = CoinbaseProtocol
+            .response(r#"{"type":"error","message":"Failed to subscribe","reason":"XYZ-USD is not a valid product"}"#)
+            .unwrap();
+        assert_eq!(response.error.unwrap(), "Failed to subscribe: XYZ-USD is not a valid product");
+        assert!(CoinbaseProtocol
+            .response(r#"{"type":"ticker","product_id":"BTC-USD","price":"1"}"#)
+            .is_none());
+    }
+}
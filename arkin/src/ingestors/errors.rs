@@ -1 +1,95 @@
+use async_tungstenite::tungstenite;
+use thiserror::Error;
 
+use crate::models::Venue;
+
+#[derive(Error, Debug)]
+pub enum IngestorError {
+    #[error("Unknown ingestor: {0}")]
+    UnknownIngestor(String),
+
+    #[error("Unknown exchange: {0}")]
+    UnknownExchange(String),
+
+    #[error("Unknown channel: {0}")]
+    UnknownChannel(String),
+
+    #[error("Channel {channel} not supported on {exchange}")]
+    UnsupportedChannel { exchange: String, channel: String },
+
+    #[error("Unknown symbol {symbol} on {venue}")]
+    UnknownSymbol { venue: Venue, symbol: String },
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    #[error("Websocket failed: {0}")]
+    WebSocket(Box<tungstenite::Error>),
+
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Rest(#[from] crate::rest::RestError),
+
+    #[error("Invalid header: {0}")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+
+    #[error("Failed to parse message: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("Failed to decode message: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+
+    #[cfg(feature = "simd-json")]
+    #[error("Failed to parse message: {0}")]
+    SimdParse(#[from] simd_json::Error),
+
+    #[error("Io failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read csv: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("Failed to read parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("Unsupported value in column {0}")]
+    UnsupportedColumn(String),
+
+    #[error("Ingestor channel closed")]
+    ChannelClosed,
+
+    #[error("No websocket message received for {0:?}")]
+    Stale(std::time::Duration),
+
+    #[error("Gave up reconnecting after {0} attempts")]
+    RetriesExhausted(u32),
+}
+
+impl From<tungstenite::Error> for IngestorError {
+    fn from(err: tungstenite::Error) -> Self {
+        IngestorError::WebSocket(Box::new(err))
+    }
+}
+
+impl<T> From<flume::SendError<T>> for IngestorError {
+    fn from(_: flume::SendError<T>) -> Self {
+        IngestorError::ChannelClosed
+    }
+}
+
+impl From<flume::RecvError> for IngestorError {
+    fn from(_: flume::RecvError) -> Self {
+        IngestorError::ChannelClosed
+    }
+}
+
+impl From<tokio::sync::AcquireError> for IngestorError {
+    fn from(_: tokio::sync::AcquireError) -> Self {
+        IngestorError::ChannelClosed
+    }
+}
@@ -1,19 +1,81 @@
 use std::sync::Arc;
 
-use crate::{config::IngestorConfig, state::StateManager};
+use tracing::error;
 
-use super::{backtest::BacktestIngestor, binance::BinanceIngestor, IngestorType};
+use crate::{
+    bus::EventBus,
+    clock::{Clock, TimeSync},
+    config::IngestorConfig,
+    credentials::CredentialStore,
+    models::{InstrumentRegistry, Venue},
+};
+
+use super::{
+    backtest::BacktestIngestor,
+    binance::{BinanceDepthIngestor, BinanceIngestor},
+    bybit::BybitIngestor,
+    coinbase::CoinbaseIngestor,
+    file::FileIngestor,
+    kafka::KafkaIngestor,
+    tardis::TardisCsvIngestor,
+    IngestorType,
+};
 
 pub struct IngestorFactory {}
 
 impl IngestorFactory {
-    pub fn from_config(state: Arc<StateManager>, config: &[IngestorConfig]) -> Vec<IngestorType> {
+    pub fn from_config(
+        bus: Arc<EventBus>,
+        clock: Arc<dyn Clock>,
+        instruments: Arc<InstrumentRegistry>,
+        credentials: &CredentialStore,
+        time_sync: &Arc<TimeSync>,
+        config: &[IngestorConfig],
+    ) -> Vec<IngestorType> {
         let mut ingestors = Vec::new();
 
         for config in config {
             let ingestor = match config {
-                IngestorConfig::Backtest(c) => IngestorType::Backtest(BacktestIngestor::new(state.to_owned(), c)),
-                IngestorConfig::Binance(c) => IngestorType::Binance(BinanceIngestor::new(state.to_owned(), c)),
+                IngestorConfig::Backtest(c) => {
+                    IngestorType::Backtest(BacktestIngestor::new(bus.to_owned(), clock.to_owned(), c))
+                }
+                IngestorConfig::Binance(c) => {
+                    // The public streams still work, so fall back to an anonymous connection
+                    let credentials = c.credentials.as_ref().and_then(|name| {
+                        credentials
+                            .get(name)
+                            .inspect_err(|e| error!("Binance ingestor runs without credentials: {}", e))
+                            .ok()
+                    });
+                    IngestorType::Binance(BinanceIngestor::new(
+                        bus.to_owned(),
+                        instruments.clone(),
+                        credentials,
+                        time_sync.clock(Venue::Binance),
+                        c,
+                    ))
+                }
+                IngestorConfig::BinanceDepth(c) => IngestorType::BinanceDepth(BinanceDepthIngestor::new(
+                    bus.to_owned(),
+                    instruments.clone(),
+                    time_sync.clock(Venue::Binance),
+                    c,
+                )),
+                IngestorConfig::Coinbase(c) => {
+                    IngestorType::Coinbase(CoinbaseIngestor::new(bus.to_owned(), instruments.clone(), c))
+                }
+                IngestorConfig::Bybit(c) => {
+                    IngestorType::Bybit(BybitIngestor::new(bus.to_owned(), instruments.clone(), c))
+                }
+                IngestorConfig::File(c) => {
+                    IngestorType::File(FileIngestor::new(bus.to_owned(), instruments.clone(), c))
+                }
+                IngestorConfig::TardisCsv(c) => {
+                    IngestorType::TardisCsv(TardisCsvIngestor::new(bus.to_owned(), instruments.clone(), c))
+                }
+                IngestorConfig::Kafka(c) => {
+                    IngestorType::Kafka(KafkaIngestor::new(bus.to_owned(), instruments.clone(), c))
+                }
             };
             ingestors.push(ingestor);
         }
@@ -0,0 +1,313 @@
+use std::{fs::File, path::Path, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::Field,
+};
+use rust_decimal::Decimal;
+use serde::{de::IntoDeserializer, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::{select, time::Instant};
+use tracing::{error, info};
+
+use crate::{
+    bus::EventBus,
+    config::{FileDataKind, FileIngestorConfig, ReplaySpeed},
+    constants::TIMESTAMP_FORMAT,
+    metrics::METRICS,
+    models::{Event, Instrument, InstrumentRegistry, Tick, Trade},
+    shutdown::ShutdownSignal,
+    utils::custom_serde,
+};
+
+use super::{Ingestor, IngestorError, IngestorID};
+
+/// Replays the trades or ticks of a file through the bus as if they arrived live
+#[derive(Clone)]
+pub struct FileIngestor {
+    bus: Arc<EventBus>,
+    instruments: Arc<InstrumentRegistry>,
+    config: FileIngestorConfig,
+}
+
+impl FileIngestor {
+    pub fn new(bus: Arc<EventBus>, instruments: Arc<InstrumentRegistry>, config: &FileIngestorConfig) -> Self {
+        Self {
+            bus,
+            instruments,
+            config: config.to_owned(),
+        }
+    }
+
+    async fn replay(&self) -> Result<usize, IngestorError> {
+        let instrument = self
+            .instruments
+            .by_symbol(&self.config.venue, &self.config.symbol)
+            .into_iter()
+            .find(|i| i.instrument_type() == &self.config.instrument_type)
+            .ok_or_else(|| IngestorError::UnknownSymbol {
+                venue: self.config.venue.clone(),
+                symbol: self.config.symbol.clone(),
+            })?;
+
+        // Files are read on a blocking thread, the bounded channel keeps the reader close to the replay
+        let (tx, rx) = flume::bounded(10000);
+        let config = self.config.clone();
+        let reader = tokio::task::spawn_blocking(move || read_events(&config, &instrument, |e| tx.send(e).is_ok()));
+
+        let mut pace = Pace::new(self.config.speed);
+        let mut replayed = 0;
+        while let Ok(event) = rx.recv_async().await {
+            pace.wait(*event.event_time()).await;
+            METRICS.ingested_events.with_label_values(&["file"]).inc();
+            self.bus.publish_event(event);
+            replayed += 1;
+        }
+        reader.await.map_err(|_| IngestorError::ChannelClosed)??;
+        Ok(replayed)
+    }
+}
+
+#[async_trait]
+impl Ingestor for FileIngestor {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
+        info!("Starting file ingestor for {}...", self.config.path);
+        // Dropping the replay stops the reader at its next event
+        let res = select! {
+            res = self.replay() => res,
+            _ = shutdown.wait() => {
+                info!("Stopped replaying {}", self.config.path);
+                return;
+            }
+        };
+        match res {
+            Ok(replayed) => info!("Replayed {} events from {}", replayed, self.config.path),
+            Err(e) => error!("Failed to replay {}: {}", self.config.path, e),
+        }
+    }
+}
+
+/// Spreads the events over the wall clock as far apart as their event times
+pub(super) struct Pace {
+    speed: ReplaySpeed,
+    start: Option<(OffsetDateTime, Instant)>,
+}
+
+impl Pace {
+    pub(super) fn new(speed: ReplaySpeed) -> Self {
+        Self { speed, start: None }
+    }
+
+    pub(super) async fn wait(&mut self, event_time: OffsetDateTime) {
+        let (first, started) = *self.start.get_or_insert((event_time, Instant::now()));
+        let offset = Duration::try_from(event_time - first).unwrap_or_default();
+        if let Some(wall_time) = self.speed.wall_time(offset) {
+            tokio::time::sleep_until(started + wall_time).await;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TradeRow {
+    #[serde(deserialize_with = "file_time")]
+    event_time: OffsetDateTime,
+    trade_id: u64,
+    price: Decimal,
+    quantity: Decimal,
+}
+
+#[derive(Deserialize)]
+struct TickRow {
+    #[serde(deserialize_with = "file_time")]
+    event_time: OffsetDateTime,
+    tick_id: u64,
+    bid_price: Decimal,
+    bid_quantity: Decimal,
+    ask_price: Decimal,
+    ask_quantity: Decimal,
+}
+
+/// Timestamps as the export command writes them or epoch seconds, milliseconds, micro or nanoseconds
+fn file_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FileTime {
+        Epoch(i64),
+        Text(String),
+    }
+
+    match FileTime::deserialize(deserializer)? {
+        FileTime::Epoch(epoch) => custom_serde::timestamp::deserialize(epoch.into_deserializer()),
+        FileTime::Text(text) if text.bytes().all(|b| b.is_ascii_digit()) => {
+            custom_serde::timestamp::deserialize(text.into_deserializer())
+        }
+        FileTime::Text(text) => PrimitiveDateTime::parse(&text, TIMESTAMP_FORMAT)
+            .map(|t| t.assume_utc())
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+impl TradeRow {
+    fn into_event(self, instrument: &Instrument) -> Event {
+        Event::Trade(Trade::new(
+            self.event_time,
+            self.event_time,
+            instrument.clone(),
+            self.trade_id,
+            self.price.into(),
+            self.quantity.into(),
+            IngestorID::File,
+        ))
+    }
+}
+
+impl TickRow {
+    fn into_event(self, instrument: &Instrument) -> Event {
+        let mut tick = Tick::new(
+            self.event_time,
+            instrument.clone(),
+            self.tick_id,
+            self.bid_price.into(),
+            self.bid_quantity.into(),
+            self.ask_price.into(),
+            self.ask_quantity.into(),
+        );
+        tick.source = IngestorID::File;
+        Event::Tick(tick)
+    }
+}
+
+/// Hand every event of the file to `emit` in file order until it returns false
+fn read_events(
+    config: &FileIngestorConfig,
+    instrument: &Instrument,
+    mut emit: impl FnMut(Event) -> bool,
+) -> Result<(), IngestorError> {
+    let path = Path::new(&config.path);
+    let mut emit_row = |row: Value| -> Result<bool, IngestorError> {
+        let event = match config.kind {
+            FileDataKind::Trades => serde_json::from_value::<TradeRow>(row)?.into_event(instrument),
+            FileDataKind::Ticks => serde_json::from_value::<TickRow>(row)?.into_event(instrument),
+        };
+        Ok(emit(event))
+    };
+
+    if path.extension().is_some_and(|e| e == "parquet") {
+        let reader = SerializedFileReader::new(File::open(path)?)?;
+        for row in reader.get_row_iter(None)? {
+            let mut columns = Map::new();
+            for (name, field) in row?.get_column_iter() {
+                columns.insert(name.to_owned(), parquet_value(name, field)?);
+            }
+            if !emit_row(Value::Object(columns))? {
+                break;
+            }
+        }
+    } else {
+        let mut reader = csv::Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        for record in reader.records() {
+            let record = record?;
+            let columns = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(name, value)| (name.to_owned(), csv_value(value)))
+                .collect::<Map<_, _>>();
+            if !emit_row(Value::Object(columns))? {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Csv has no types, whole numbers are ids or epochs and everything else stays text for the decimals
+fn csv_value(value: &str) -> Value {
+    value
+        .parse::<u64>()
+        .map(Value::from)
+        .unwrap_or_else(|_| Value::String(value.to_owned()))
+}
+
+/// Floats keep their text so prices are not rounded through a float on the way to a decimal
+fn parquet_value(name: &str, field: &Field) -> Result<Value, IngestorError> {
+    Ok(match field {
+        Field::Null => Value::Null,
+        Field::Str(s) => Value::String(s.clone()),
+        Field::Int(i) => Value::from(*i),
+        Field::Long(i) => Value::from(*i),
+        Field::UInt(i) => Value::from(*i),
+        Field::ULong(i) => Value::from(*i),
+        Field::Float(f) => Value::String(f.to_string()),
+        Field::Double(f) => Value::String(f.to_string()),
+        Field::TimestampMillis(ms) => Value::from(*ms),
+        Field::TimestampMicros(us) => Value::from(*us),
+        _ => return Err(IngestorError::UnsupportedColumn(name.to_owned())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::write_trades_csv,
+        models::{InstrumentType, Venue},
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    #[test]
+    fn test_read_exported_trades() {
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let trades = test_utils::market_events(&instrument, start, 3)
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let path = std::env::temp_dir().join(format!("aurelion_file_ingestor_{}.csv", std::process::id()));
+        write_trades_csv(&trades, File::create(&path).unwrap()).unwrap();
+
+        let config = FileIngestorConfig {
+            path: path.to_string_lossy().into_owned(),
+            kind: FileDataKind::Trades,
+            venue: Venue::Binance,
+            symbol: "BTCUSDT".into(),
+            instrument_type: InstrumentType::Perpetual,
+            speed: ReplaySpeed::Max,
+        };
+        let mut events = Vec::new();
+        read_events(&config, &instrument, |e| {
+            events.push(e);
+            true
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), trades.len());
+        for (event, trade) in events.iter().zip(&trades) {
+            let Event::Trade(replayed) = event else {
+                panic!("Expected a trade");
+            };
+            assert_eq!(replayed.event_time, trade.event_time);
+            assert_eq!(replayed.trade_id, trade.trade_id);
+            // The export writes prices and quantities as they are displayed
+            assert_eq!(replayed.price.to_string(), trade.price.to_string());
+            assert_eq!(replayed.quantity.to_string(), trade.quantity.to_string());
+        }
+    }
+
+    #[test]
+    fn test_file_time() {
+        let parse = |value: &str| file_time(Value::String(value.into())).unwrap();
+        let time = datetime!(2024-01-01 00:00:01.5).assume_utc();
+        assert_eq!(parse("2024-01-01 00:00:01.5"), time);
+        assert_eq!(parse("1704067201500"), time);
+        assert_eq!(parse("1704067201500000"), time);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{Consumer, StreamConsumer},
+    Message,
+};
+use tokio::select;
+use tracing::{error, info, warn};
+
+use crate::{
+    bus::EventBus,
+    config::{KafkaFormat, KafkaIngestorConfig},
+    metrics::METRICS,
+    models::{Event, InstrumentRegistry, Tick, Trade},
+    shutdown::ShutdownSignal,
+    sinks::SinkTopic,
+};
+
+use super::{models::decode_market_event, Ingestor, IngestorError};
+
+/// Consumes the ticks and trades of capture processes on other hosts from kafka and publishes them on the bus,
+/// so the trading core doesn't need its own venue connections
+#[derive(Clone)]
+pub struct KafkaIngestor {
+    bus: Arc<EventBus>,
+    instruments: Arc<InstrumentRegistry>,
+    config: KafkaIngestorConfig,
+}
+
+impl KafkaIngestor {
+    pub fn new(bus: Arc<EventBus>, instruments: Arc<InstrumentRegistry>, config: &KafkaIngestorConfig) -> Self {
+        Self {
+            bus,
+            instruments,
+            config: config.to_owned(),
+        }
+    }
+
+    fn consumer(&self) -> Result<StreamConsumer, rdkafka::error::KafkaError> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", &self.config.brokers)
+            .set("group.id", &self.config.group_id)
+            // Market data is only worth its latest values after a restart
+            .set("auto.offset.reset", "latest");
+        for (key, value) in &self.config.properties {
+            config.set(key, value);
+        }
+        config.create()
+    }
+
+    /// The event of a message on the kafka topic of the event topic
+    pub fn decode(&self, topic: SinkTopic, payload: &[u8]) -> Result<Event, IngestorError> {
+        match self.config.format {
+            KafkaFormat::Json => match topic {
+                SinkTopic::Ticks => Ok(Event::Tick(serde_json::from_slice::<Tick>(payload)?)),
+                SinkTopic::Trades => Ok(Event::Trade(serde_json::from_slice::<Trade>(payload)?)),
+                _ => Err(IngestorError::UnsupportedChannel {
+                    exchange: "kafka".into(),
+                    channel: topic.to_string(),
+                }),
+            },
+            KafkaFormat::Protobuf => decode_market_event(&self.instruments, payload),
+        }
+    }
+}
+
+#[async_trait]
+impl Ingestor for KafkaIngestor {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
+        let consumer = match self.consumer() {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                error!("Failed to create kafka consumer for {}: {}", self.config.brokers, e);
+                return;
+            }
+        };
+        let topics = self
+            .config
+            .topics
+            .iter()
+            .map(|(topic, name)| (name.as_str(), *topic))
+            .collect::<HashMap<_, _>>();
+        if let Err(e) = consumer.subscribe(&topics.keys().copied().collect::<Vec<_>>()) {
+            error!("Failed to subscribe to kafka topics {:?}: {}", topics.keys(), e);
+            return;
+        }
+        info!("Consuming {:?} from kafka at {}", topics.keys(), self.config.brokers);
+
+        loop {
+            let message = select! {
+                message = consumer.recv() => message,
+                _ = shutdown.wait() => break,
+            };
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("Failed to consume from kafka: {}", e);
+                    METRICS.ingest_errors.with_label_values(&["kafka"]).inc();
+                    continue;
+                }
+            };
+            let Some(topic) = topics.get(message.topic()) else {
+                continue;
+            };
+            match self.decode(*topic, message.payload().unwrap_or_default()) {
+                Ok(event) => {
+                    METRICS.ingested_events.with_label_values(&["kafka"]).inc();
+                    self.bus.publish_event(event);
+                }
+                Err(e) => {
+                    warn!("Failed to decode kafka message of {}: {}", message.topic(), e);
+                    METRICS.ingest_errors.with_label_values(&["kafka"]).inc();
+                }
+            }
+        }
+        // Dropping the consumer commits the offsets of what was published
+        info!("Kafka ingestor stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, ingestors::IngestorID, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_decode_json() {
+        let config = config::load();
+        let ingestor = KafkaIngestor::new(
+            Arc::new(EventBus::from_config(&config.bus)),
+            Arc::new(InstrumentRegistry::default()),
+            &KafkaIngestorConfig {
+                brokers: "127.0.0.1:9092".into(),
+                group_id: "arkin".into(),
+                topics: HashMap::from([(SinkTopic::Trades, "arkin.trades".into())]),
+                format: KafkaFormat::Json,
+                properties: HashMap::new(),
+            },
+        );
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        let trade = Trade::new(
+            time,
+            time,
+            test_utils::test_perp_instrument(),
+            7,
+            100.5.into(),
+            (-0.25).into(),
+            IngestorID::Binance,
+        );
+        let payload = serde_json::to_vec(&trade).unwrap();
+        let Event::Trade(decoded) = ingestor.decode(SinkTopic::Trades, &payload).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(decoded.trade_id, 7);
+        assert!(ingestor.decode(SinkTopic::Ticks, &payload).is_err());
+        assert!(matches!(
+            ingestor.decode(SinkTopic::Fills, &payload),
+            Err(IngestorError::UnsupportedChannel { .. })
+        ));
+    }
+}
@@ -1,39 +1,68 @@
-use anyhow::anyhow;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::{fmt, str::FromStr};
 
+use crate::shutdown::ShutdownSignal;
+
+mod backfill;
 mod backtest;
 mod binance;
+mod bybit;
+mod coinbase;
 mod errors;
 mod factory;
+mod file;
+mod kafka;
 mod models;
+mod queue;
+mod sequence;
 mod tardis;
 mod ws;
 
 use backtest::BacktestIngestor;
-use binance::BinanceIngestor;
+use binance::{BinanceDepthIngestor, BinanceIngestor};
+use bybit::BybitIngestor;
+use coinbase::CoinbaseIngestor;
+use file::FileIngestor;
+use kafka::KafkaIngestor;
 
+pub use backfill::{BackfillKind, BinanceBackfill};
+pub use errors::IngestorError;
 pub use factory::IngestorFactory;
-pub use models::BinanceParser;
+pub use models::{decode_market_event, encode_market_event, BinanceParser, BybitParser, CoinbaseParser};
+pub use sequence::{SequenceCheck, SequenceTracker};
 pub use tardis::*;
 
 #[async_trait]
 pub trait Ingestor {
-    async fn start(&self);
+    /// Runs until the shutdown, what was already received is published before returning
+    async fn start(&self, shutdown: ShutdownSignal);
 }
 
 #[derive(Clone)]
 pub enum IngestorType {
     Backtest(BacktestIngestor),
     Binance(BinanceIngestor),
+    BinanceDepth(BinanceDepthIngestor),
+    Coinbase(CoinbaseIngestor),
+    Bybit(BybitIngestor),
+    File(FileIngestor),
+    TardisCsv(TardisCsvIngestor),
+    Kafka(KafkaIngestor),
 }
 
 #[async_trait]
 impl Ingestor for IngestorType {
-    async fn start(&self) {
+    async fn start(&self, shutdown: ShutdownSignal) {
         match self {
-            IngestorType::Backtest(b) => b.start().await,
-            IngestorType::Binance(b) => b.start().await,
+            IngestorType::Backtest(b) => b.start(shutdown).await,
+            IngestorType::Binance(b) => b.start(shutdown).await,
+            IngestorType::BinanceDepth(b) => b.start(shutdown).await,
+            IngestorType::Coinbase(c) => c.start(shutdown).await,
+            IngestorType::Bybit(b) => b.start(shutdown).await,
+            IngestorType::File(f) => f.start(shutdown).await,
+            IngestorType::TardisCsv(t) => t.start(shutdown).await,
+            IngestorType::Kafka(k) => k.start(shutdown).await,
         }
     }
 }
@@ -43,26 +72,43 @@ impl fmt::Display for IngestorType {
         match self {
             IngestorType::Backtest(_) => write!(f, "backtest"),
             IngestorType::Binance(_) => write!(f, "binance"),
+            IngestorType::BinanceDepth(_) => write!(f, "binance_depth"),
+            IngestorType::Coinbase(_) => write!(f, "coinbase"),
+            IngestorType::Bybit(_) => write!(f, "bybit"),
+            IngestorType::File(_) => write!(f, "file"),
+            IngestorType::TardisCsv(_) => write!(f, "tardis_csv"),
+            IngestorType::Kafka(_) => write!(f, "kafka"),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum IngestorID {
     Backtest,
     Binance,
+    Coinbase,
+    Bybit,
+    File,
+    Tardis,
+    Synthetic,
     Test,
 }
 
 impl FromStr for IngestorID {
-    type Err = anyhow::Error;
+    type Err = IngestorError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "backtest" => Ok(IngestorID::Backtest),
             "binance" => Ok(IngestorID::Binance),
+            "coinbase" => Ok(IngestorID::Coinbase),
+            "bybit" => Ok(IngestorID::Bybit),
+            "file" => Ok(IngestorID::File),
+            "tardis" => Ok(IngestorID::Tardis),
+            "synthetic" => Ok(IngestorID::Synthetic),
             "test" => Ok(IngestorID::Test),
-            _ => Err(anyhow!("Unknown ingestor ID: {}", s)),
+            _ => Err(IngestorError::UnknownIngestor(s.into())),
         }
     }
 }
@@ -72,6 +118,11 @@ impl fmt::Display for IngestorID {
         match self {
             IngestorID::Backtest => write!(f, "backtest"),
             IngestorID::Binance => write!(f, "binance"),
+            IngestorID::Coinbase => write!(f, "coinbase"),
+            IngestorID::Bybit => write!(f, "bybit"),
+            IngestorID::File => write!(f, "file"),
+            IngestorID::Tardis => write!(f, "tardis"),
+            IngestorID::Synthetic => write!(f, "synthetic"),
             IngestorID::Test => write!(f, "test"),
         }
     }
@@ -0,0 +1,51 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::utils::custom_serde;
+
+/// Diff depth update, levels as [price, quantity] where a quantity of 0 removes the level.
+/// Futures also send the final id of the previous update, spot ids are consecutive instead
+#[derive(Debug, Deserialize)]
+pub struct BinanceDepthUpdate {
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "pu", default)]
+    pub previous_update_id: Option<u64>,
+    #[serde(rename = "b")]
+    pub bids: Vec<(Decimal, Decimal)>,
+    #[serde(rename = "a")]
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+/// Raw streams send the update as is, combined streams wrap it
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BinanceDepthEvent {
+    Stream { data: BinanceDepthUpdate },
+    Update(BinanceDepthUpdate),
+}
+
+impl BinanceDepthEvent {
+    pub fn into_update(self) -> BinanceDepthUpdate {
+        match self {
+            BinanceDepthEvent::Stream { data } => data,
+            BinanceDepthEvent::Update(update) => update,
+        }
+    }
+}
+
+/// Response of the REST depth endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceDepthSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
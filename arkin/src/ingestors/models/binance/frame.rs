@@ -0,0 +1,48 @@
+use serde::{de::DeserializeOwned, Deserializer};
+
+use crate::ingestors::IngestorError;
+
+/// Payloads of the combined stream endpoint, the name of the stream tells their type
+pub trait FromStream: DeserializeOwned {
+    fn from_stream<'de, D: Deserializer<'de>>(stream: &str, data: D) -> Result<Self, D::Error>;
+}
+
+#[cfg(not(feature = "simd-json"))]
+#[derive(serde::Deserialize)]
+struct CombinedStream<'a> {
+    stream: &'a str,
+    #[serde(borrow)]
+    data: &'a serde_json::value::RawValue,
+}
+
+/// Parses a whole frame. simd-json parses in place, so the frame is scratch space afterwards
+pub fn from_frame<T: DeserializeOwned>(frame: &mut [u8]) -> Result<T, IngestorError> {
+    #[cfg(feature = "simd-json")]
+    return Ok(simd_json::serde::from_slice(frame)?);
+    #[cfg(not(feature = "simd-json"))]
+    return Ok(serde_json::from_slice(frame)?);
+}
+
+/// Parses a raw stream frame or demultiplexes a combined stream frame `{"stream": .., "data": ..}`
+/// by its stream name
+pub fn from_stream_frame<T: FromStream>(frame: &mut [u8]) -> Result<T, IngestorError> {
+    #[cfg(feature = "simd-json")]
+    {
+        use simd_json::prelude::*;
+
+        let mut value = simd_json::to_borrowed_value(frame)?;
+        let (stream, data) = match value.as_object_mut() {
+            Some(object) if object.contains_key("stream") => (object.remove("stream"), object.remove("data")),
+            _ => (None, None),
+        };
+        match (stream.as_ref().and_then(|s| s.as_str()), data) {
+            (Some(stream), Some(data)) => Ok(T::from_stream(stream, data)?),
+            _ => Ok(T::deserialize(value)?),
+        }
+    }
+    #[cfg(not(feature = "simd-json"))]
+    match serde_json::from_slice::<CombinedStream>(frame) {
+        Ok(combined) => Ok(T::from_stream(combined.stream, combined.data)?),
+        Err(_) => Ok(serde_json::from_slice(frame)?),
+    }
+}
@@ -1,6 +1,12 @@
-// mod options;
-// mod spot;
+mod depth;
+mod frame;
+mod options;
 mod parser;
+mod rest;
+mod spot;
 mod swaps;
+mod user;
 
+pub use depth::{BinanceDepthSnapshot, BinanceDepthUpdate};
 pub use parser::BinanceParser;
+pub use rest::{BinanceAggTrade, BinanceKline};
@@ -1,8 +1,75 @@
-use crate::utils::custom_serde;
+use std::{fmt, str::FromStr};
+
+use crate::{
+    ingestors::IngestorID,
+    models::{Book, BookUpdateSide, Event, Instrument, Tick, Trade},
+    utils::custom_serde,
+};
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer};
 use time::OffsetDateTime;
 
+use super::frame::FromStream;
+
+/// Symbols look like BTC-241025-60000-C, the instrument registry knows their strike, maturity and type
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BinanceOptionsEvent {
+    TradeStream(BinanceOptionsTrade),
+    Trade(BinanceOptionsTradeData),
+    BookStream(BinanceOptionsBook),
+    Book(BinanceOptionsBookData),
+    TickStream(BinanceOptionsTick),
+    Tick(BinanceOptionsTickData),
+}
+
+impl FromStream for BinanceOptionsEvent {
+    fn from_stream<'de, D: Deserializer<'de>>(stream: &str, data: D) -> Result<Self, D::Error> {
+        let channel = stream.split_once('@').map(|(_, channel)| channel).unwrap_or_default();
+        Ok(match channel {
+            "trade" => BinanceOptionsEvent::Trade(Deserialize::deserialize(data)?),
+            "ticker" => BinanceOptionsEvent::Tick(Deserialize::deserialize(data)?),
+            c if c.starts_with("depth") => BinanceOptionsEvent::Book(Deserialize::deserialize(data)?),
+            _ => Deserialize::deserialize(data)?,
+        })
+    }
+}
+
+impl BinanceOptionsEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            BinanceOptionsEvent::TradeStream(data) => &data.data.instrument,
+            BinanceOptionsEvent::Trade(data) => &data.instrument,
+            BinanceOptionsEvent::BookStream(data) => &data.data.instrument,
+            BinanceOptionsEvent::Book(data) => &data.instrument,
+            BinanceOptionsEvent::TickStream(data) => &data.data.instrument,
+            BinanceOptionsEvent::Tick(data) => &data.instrument,
+        }
+    }
+
+    /// The received time is stamped by the parser, which knows the Binance server time
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        match self {
+            BinanceOptionsEvent::TradeStream(data) => data.data.into_event(instrument, received_time),
+            BinanceOptionsEvent::Trade(data) => data.into_event(instrument, received_time),
+            BinanceOptionsEvent::BookStream(data) => data.data.into_event(instrument, received_time),
+            BinanceOptionsEvent::Book(data) => data.into_event(instrument, received_time),
+            BinanceOptionsEvent::TickStream(data) => data.data.into_event(instrument),
+            BinanceOptionsEvent::Tick(data) => data.into_event(instrument),
+        }
+    }
+}
+
+/// Ids and the trade side are sent as strings on the options streams
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+}
+
 // https://binance-docs.github.io/apidocs/voptions/en/#websocket-market-streams
 // https://api.tardis.dev/v1/exchanges
 // {
@@ -21,12 +88,14 @@ use time::OffsetDateTime;
 //     ]
 // }
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsTrade {
     pub stream: String,
     pub data: BinanceOptionsTradeData,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsTradeData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -36,27 +105,43 @@ pub struct BinanceOptionsTradeData {
     pub event_type: String,
     #[serde(rename = "s")]
     pub instrument: String,
-    #[serde(rename = "t")]
-    pub trade_id: i64,
+    #[serde(rename = "t", deserialize_with = "number")]
+    pub trade_id: u64,
     #[serde(rename = "p")]
     pub price: Decimal,
     #[serde(rename = "q")]
     pub quantity: Decimal,
-    #[serde(rename = "S")]
-    pub side: i64,
-    #[serde(rename = "b")]
-    pub bid_order_id: i64,
-    #[serde(rename = "a")]
-    pub ask_order_id: i64,
+    #[serde(rename = "S", deserialize_with = "number")]
+    pub side: i64, // 1 for a buying taker, -1 for a selling one
+    #[serde(rename = "b", deserialize_with = "number")]
+    pub bid_order_id: u64,
+    #[serde(rename = "a", deserialize_with = "number")]
+    pub ask_order_id: u64,
+}
+
+impl BinanceOptionsTradeData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        Event::Trade(Trade::new(
+            received_time,
+            self.event_time,
+            instrument,
+            self.trade_id,
+            self.price.into(),
+            (Decimal::from(self.side.signum()) * self.quantity.abs()).into(),
+            IngestorID::Binance,
+        ))
+    }
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsBook {
     pub stream: String,
     pub data: BinanceOptionsBookData,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsBookData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -76,19 +161,42 @@ pub struct BinanceOptionsBookData {
     pub asks: Vec<BinanceOptionsBookUpdate>,
 }
 
+impl BinanceOptionsBookData {
+    /// The depth channels send the top levels of the book, not diffs
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        let side = |levels: Vec<BinanceOptionsBookUpdate>| {
+            levels
+                .into_iter()
+                .map(|l| BookUpdateSide::new(l.price.into(), l.quantity.into()))
+                .collect()
+        };
+        Event::Book(Book::new(
+            received_time,
+            self.event_time,
+            instrument,
+            side(self.bids),
+            side(self.asks),
+            IngestorID::Binance,
+        ))
+    }
+}
+
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsBookUpdate {
     pub price: Decimal,
     pub quantity: Decimal,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsTick {
     pub stream: String,
     pub data: BinanceOptionsTickData,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsTickData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -116,10 +224,10 @@ pub struct BinanceOptionsTickData {
     pub price_change: Decimal,
     #[serde(rename = "Q")]
     pub last_trade_quantity: Decimal,
-    #[serde(rename = "F")]
-    pub first_trade_id: i64,
-    #[serde(rename = "L")]
-    pub last_trade_id: i64,
+    #[serde(rename = "F", deserialize_with = "number")]
+    pub first_trade_id: u64,
+    #[serde(rename = "L", deserialize_with = "number")]
+    pub last_trade_id: u64,
     #[serde(rename = "n")]
     pub num_trades: i64,
     #[serde(rename = "bo")]
@@ -154,7 +262,24 @@ pub struct BinanceOptionsTickData {
     pub estimated_strike_price: Decimal,
 }
 
+impl BinanceOptionsTickData {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        Event::Tick(Tick {
+            event_time: self.event_time,
+            instrument,
+            // The ticker has no update id, it is pushed once per second so the event time orders it
+            tick_id: (self.event_time.unix_timestamp_nanos() / 1_000_000) as u64,
+            bid_price: self.bid_price.into(),
+            bid_quantity: self.bid_amount.into(),
+            ask_price: self.ask_price.into(),
+            ask_quantity: self.ask_amount.into(),
+            source: IngestorID::Binance,
+        })
+    }
+}
+
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceOptionsOpenInterest {
     pub stream: String,
     pub data: Vec<BinanceOptionsOpenInterestData>,
@@ -162,6 +287,7 @@ pub struct BinanceOptionsOpenInterest {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[allow(unused)]
 pub struct BinanceOptionsOpenInterestData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -1,26 +1,304 @@
-use crate::models::{Event, Instrument, Venue};
-use anyhow::Result;
-use tracing::error;
+use std::sync::Arc;
 
-use super::swaps::BinanceSwapsEvent;
+use crate::{
+    clock::ServerClock,
+    config::BinanceMarket,
+    ingestors::IngestorError,
+    models::{Event, Instrument, InstrumentRegistry, InstrumentType, Venue},
+};
+use time::OffsetDateTime;
+use tracing::{error, warn};
 
-pub struct BinanceParser {}
+use super::{
+    depth::BinanceDepthEvent,
+    frame::{from_frame, from_stream_frame},
+    options::BinanceOptionsEvent,
+    spot::BinanceSpotEvent,
+    swaps::BinanceSwapsEvent,
+    user::BinanceUserEvent,
+    BinanceDepthUpdate,
+};
+
+/// Turns Binance messages into events, symbols are resolved through the instrument registry
+#[derive(Clone)]
+pub struct BinanceParser {
+    instruments: Arc<InstrumentRegistry>,
+    clock: ServerClock,
+}
 
 impl BinanceParser {
-    pub fn parse_swap(data: &str) -> Result<Event> {
-        let event = match serde_json::from_str::<BinanceSwapsEvent>(data) {
-            Ok(e) => e,
-            Err(e) => {
-                error!("Failed to parse Binance event: {}", e);
-                error!("Data: {}", data);
-                return Err(e.into());
+    pub fn new(instruments: Arc<InstrumentRegistry>) -> Self {
+        Self {
+            instruments,
+            clock: ServerClock::local(Venue::Binance),
+        }
+    }
+
+    /// Stamp the received time with the Binance server time instead of the local time
+    pub fn with_clock(mut self, clock: ServerClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Received time of a message that arrives now
+    pub fn now(&self) -> OffsetDateTime {
+        self.clock.now()
+    }
+
+    /// Frames are taken by value so owned frames are parsed without a copy
+    pub fn parse(&self, market: BinanceMarket, frame: impl Into<Vec<u8>>) -> Result<Event, IngestorError> {
+        match market {
+            BinanceMarket::Spot => self.parse_spot(frame),
+            BinanceMarket::Swaps => self.parse_swap(frame),
+            BinanceMarket::Options => self.parse_options(frame),
+        }
+    }
+
+    pub fn parse_swap(&self, frame: impl Into<Vec<u8>>) -> Result<Event, IngestorError> {
+        let mut frame = frame.into();
+        let event = from_stream_frame::<BinanceSwapsEvent>(&mut frame).inspect_err(|e| {
+            error!("Failed to parse Binance event: {}", e);
+            error!("Data: {}", String::from_utf8_lossy(&frame));
+        })?;
+        let instrument = self.instrument(BinanceMarket::Swaps, event.symbol())?;
+        Ok(event.into_event(instrument, self.now()))
+    }
+
+    pub fn parse_spot(&self, frame: impl Into<Vec<u8>>) -> Result<Event, IngestorError> {
+        let mut frame = frame.into();
+        let event = from_stream_frame::<BinanceSpotEvent>(&mut frame).inspect_err(|e| {
+            error!("Failed to parse Binance spot event: {}", e);
+            error!("Data: {}", String::from_utf8_lossy(&frame));
+        })?;
+        let instrument = self.instrument(BinanceMarket::Spot, event.symbol())?;
+        Ok(event.into_event(instrument, self.now()))
+    }
+
+    pub fn parse_options(&self, frame: impl Into<Vec<u8>>) -> Result<Event, IngestorError> {
+        let mut frame = frame.into();
+        let event = from_stream_frame::<BinanceOptionsEvent>(&mut frame).inspect_err(|e| {
+            error!("Failed to parse Binance options event: {}", e);
+            error!("Data: {}", String::from_utf8_lossy(&frame));
+        })?;
+        let instrument = self.instrument(BinanceMarket::Options, event.symbol())?;
+        Ok(event.into_event(instrument, self.now()))
+    }
+
+    /// Order and account updates of the futures user data stream, none for the events that are ignored
+    pub fn parse_user(&self, frame: impl Into<Vec<u8>>) -> Result<Option<Event>, IngestorError> {
+        let mut frame = frame.into();
+        let event = from_frame::<BinanceUserEvent>(&mut frame).inspect_err(|e| {
+            error!("Failed to parse Binance user data event: {}", e);
+            error!("Data: {}", String::from_utf8_lossy(&frame));
+        })?;
+        match event {
+            BinanceUserEvent::OrderTradeUpdate(update) => {
+                let instrument = self.instrument(BinanceMarket::Swaps, &update.order.instrument)?;
+                Ok(Some(update.into_event(instrument)))
             }
+            BinanceUserEvent::AccountUpdate(update) => Ok(Some(update.into_event(|symbol| {
+                self.instrument(BinanceMarket::Swaps, symbol)
+                    .inspect_err(|e| warn!("Position left out of the account update: {}", e))
+                    .ok()
+            }))),
+            BinanceUserEvent::Other => Ok(None),
+        }
+    }
+
+    /// Diff depth updates are applied to a local book instead of becoming events directly
+    pub fn parse_depth(
+        &self,
+        market: BinanceMarket,
+        frame: impl Into<Vec<u8>>,
+    ) -> Result<(Instrument, BinanceDepthUpdate), IngestorError> {
+        let mut frame = frame.into();
+        let update = from_frame::<BinanceDepthEvent>(&mut frame)
+            .inspect_err(|e| {
+                error!("Failed to parse Binance depth update: {}", e);
+                error!("Data: {}", String::from_utf8_lossy(&frame));
+            })?
+            .into_update();
+        let instrument = self.instrument(market, &update.instrument)?;
+        Ok((instrument, update))
+    }
+
+    /// Spot and perpetuals share symbols like BTCUSDT, the market tells them apart
+    pub fn instrument(&self, market: BinanceMarket, symbol: &str) -> Result<Instrument, IngestorError> {
+        self.instruments
+            .by_symbol(&Venue::Binance, symbol)
+            .into_iter()
+            .find(|i| match market {
+                BinanceMarket::Spot => i.instrument_type() == &InstrumentType::Spot,
+                BinanceMarket::Swaps => i.instrument_type() != &InstrumentType::Spot,
+                BinanceMarket::Options => i.instrument_type() == &InstrumentType::Option,
+            })
+            .ok_or_else(|| IngestorError::UnknownSymbol {
+                venue: Venue::Binance,
+                symbol: symbol.to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::InstrumentConfig,
+        models::{Liquidity, OptionType, OrderStatus, Price, Quantity, Side},
+    };
+    use rust_decimal::Decimal;
+    use time::macros::datetime;
+
+    fn config(symbol: &str, instrument_type: InstrumentType, base: &str, quote: &str) -> InstrumentConfig {
+        InstrumentConfig {
+            venue: Venue::Binance,
+            symbol: symbol.into(),
+            instrument_type,
+            base: base.into(),
+            quote: quote.into(),
+            maturity: None,
+            strike: None,
+            option_type: None,
+            tick_size: Decimal::ONE,
+            lot_size: Decimal::ONE,
+            min_notional: Decimal::ZERO,
+            contract_multiplier: Decimal::ONE,
+            settlement: quote.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_symbols() {
+        let mut option = config("BTC-241025-60000-C", InstrumentType::Option, "btc", "usdt");
+        option.maturity = Some(datetime!(2024-10-25 08:00 UTC));
+        option.strike = Some(Decimal::from(60000));
+        option.option_type = Some(OptionType::Call);
+        let registry = InstrumentRegistry::from_config(&[
+            config("BTCUSDT", InstrumentType::Perpetual, "btc", "usdt"),
+            config("BTCUSDT", InstrumentType::Spot, "btc", "usdt"),
+            config("1000PEPEUSDT", InstrumentType::Perpetual, "1000pepe", "usdt"),
+            config("ETHFDUSD", InstrumentType::Spot, "eth", "fdusd"),
+            option,
+        ]);
+        let parser = BinanceParser::new(Arc::new(registry));
+
+        let perpetual = parser.instrument(BinanceMarket::Swaps, "BTCUSDT").unwrap();
+        assert!(perpetual == Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into()));
+        let spot = parser.instrument(BinanceMarket::Spot, "btcusdt").unwrap();
+        assert!(spot == Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into()));
+        let pepe = parser.instrument(BinanceMarket::Swaps, "1000PEPEUSDT").unwrap();
+        assert_eq!(pepe.base().to_string(), "1000pepe");
+        let eth = parser.instrument(BinanceMarket::Spot, "ETHFDUSD").unwrap();
+        assert_eq!(eth.quote().to_string(), "fdusd");
+        let option = parser.instrument(BinanceMarket::Swaps, "BTC-241025-60000-C").unwrap();
+        assert_eq!(option.strike(), Some(&Price::from(60000.)));
+
+        assert!(matches!(
+            parser.instrument(BinanceMarket::Spot, "ETHUSDT"),
+            Err(IngestorError::UnknownSymbol { .. })
+        ));
+        let trade = r#"{"stream":"ethusdt@aggTrade","data":{"e":"aggTrade","E":1698796800043,"a":3863267,"s":"ETHUSDT","p":"6.279000","q":"141.2","f":15146241,"l":15146244,"T":1698796799890,"m":false}}"#;
+        assert!(matches!(parser.parse_swap(trade), Err(IngestorError::UnknownSymbol { .. })));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let mut option = config("ETH-231215-2150-C", InstrumentType::Option, "eth", "usdt");
+        option.maturity = Some(datetime!(2023-12-15 08:00 UTC));
+        option.strike = Some(Decimal::from(2150));
+        option.option_type = Some(OptionType::Call);
+        let parser = BinanceParser::new(Arc::new(InstrumentRegistry::from_config(&[option])));
+
+        let trade = r#"{"stream":"ETH-231215-2150-C@trade","data":{"e":"trade","E":1701388852591,"s":"ETH-231215-2150-C","t":"20","p":"6.9","q":"0.17","b":"4674893542539575296","a":"4692907941024256001","T":1701388852588,"S":"1"}}"#;
+        let Event::Trade(trade) = parser.parse(BinanceMarket::Options, trade).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.trade_id, 20);
+        assert_eq!(trade.aggressor(), Side::Buy);
+        assert_eq!(trade.instrument.option_type(), Some(&OptionType::Call));
+        assert_eq!(trade.instrument.maturity().unwrap().value(), datetime!(2023-12-15 08:00 UTC));
+
+        let ticker = r#"{"stream":"ETH-231215-2150-C@ticker","data":{"e":"24hrTicker","E":1701388809057,"T":1701388809000,"s":"ETH-231215-2150-C","o":"44.1","h":"44.1","l":"44.1","c":"44.1","V":"0","A":"0","P":"0","p":"0","Q":"6.3","F":"0","L":"0","n":0,"bo":"40.2","ao":"41","bq":"6.97","aq":"31.97","b":"0.48034787","a":"0.48577666","d":"0.33050441","t":"-2.48327689","g":"0.00184462","v":"1.47365585","vo":"0.48306227","mp":"40.5","hl":"321.7","ll":"0.1","eep":"0"}}"#;
+        let Event::Tick(tick) = parser.parse(BinanceMarket::Options, ticker).unwrap() else {
+            panic!("Expected a tick");
         };
-        Ok(event.into())
+        assert_eq!(tick.bid_price, Price::from(40.2));
+        assert_eq!(tick.ask_quantity, Quantity::from(31.97));
+
+        let depth = r#"{"stream":"ETH-231215-2150-C@depth100@100ms","data":{"e":"depth","E":1701388808343,"T":1701388808341,"s":"ETH-231215-2150-C","u":2690466,"pu":2690466,"b":[["91.5","38.87"],["13.2","4.17"]],"a":[["142.4","31"]]}}"#;
+        let Event::Book(book) = parser.parse(BinanceMarket::Options, depth).unwrap() else {
+            panic!("Expected a book");
+        };
+        assert_eq!(book.bids.len(), 2);
+
+        // Only option instruments resolve on the options market
+        assert!(matches!(
+            parser.instrument(BinanceMarket::Options, "ETHUSDT"),
+            Err(IngestorError::UnknownSymbol { .. })
+        ));
     }
 
-    pub fn parse_instrument(instrument: &str) -> Instrument {
-        let (base, quote) = instrument.split_at(instrument.len() - 4);
-        Instrument::perpetual(Venue::Binance, base.into(), quote.into())
+    #[test]
+    fn test_parse_combined_streams() {
+        let registry = InstrumentRegistry::from_config(&[
+            config("BTCUSDT", InstrumentType::Perpetual, "btc", "usdt"),
+            config("BTCUSDT", InstrumentType::Spot, "btc", "usdt"),
+        ]);
+        let parser = BinanceParser::new(Arc::new(registry));
+
+        let trade = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1698796800043,"a":3863267,"s":"BTCUSDT","p":"6.279000","q":"141.2","f":15146241,"l":15146244,"T":1698796799890,"m":false}}"#;
+        let Event::Trade(trade) = parser.parse_swap(trade).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.trade_id, 3863267);
+        assert_eq!(trade.aggressor(), Side::Buy);
+        // The buyer was the maker, so the taker sold
+        let sell = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1698796800043,"a":3863268,"s":"BTCUSDT","p":"6.279000","q":"141.2","f":15146245,"l":15146245,"T":1698796799890,"m":true}}"#;
+        let Event::Trade(trade) = parser.parse_swap(sell).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.aggressor(), Side::Sell);
+        assert_eq!(trade.quantity, Quantity::from(-141.2));
+        let kline = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1638747660000,"s":"BTCUSDT","k":{"t":1638747660000,"T":1638747719999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","n":100,"x":true,"q":"1.0000","V":"500","Q":"0.500","B":"123456"}}}"#;
+        assert!(matches!(parser.parse_swap(kline).unwrap(), Event::Candle(_)));
+        let tick = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}}"#;
+        assert!(matches!(parser.parse_spot(tick).unwrap(), Event::Tick(_)));
+
+        // The payload has to match the type named by the stream
+        let mislabeled = r#"{"stream":"btcusdt@bookTicker","data":{"e":"aggTrade","E":1698796800043,"a":3863267,"s":"BTCUSDT","p":"6.279000","q":"141.2","f":15146241,"l":15146244,"T":1698796799890,"m":false}}"#;
+        assert!(parser.parse_swap(mislabeled).is_err());
+    }
+
+    #[test]
+    fn test_parse_user_data() {
+        let registry = InstrumentRegistry::from_config(&[config("BTCUSDT", InstrumentType::Perpetual, "btc", "usdt")]);
+        let parser = BinanceParser::new(Arc::new(registry));
+
+        let order = r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"test-3","S":"SELL","o":"LIMIT","f":"GTC","q":"0.002","p":"7100.5","ap":"7100.5","sp":"0","x":"TRADE","X":"PARTIALLY_FILLED","i":8886774,"l":"0.001","z":"0.001","L":"7100.5","N":"USDT","n":"0.0028","T":1568879465650,"t":42,"b":"0","a":"0","m":true,"R":false,"wt":"CONTRACT_PRICE","ot":"LIMIT","ps":"BOTH","cp":false,"rp":"0"}}"#;
+        let Some(Event::OrderUpdate(update)) = parser.parse_user(order).unwrap() else {
+            panic!("Expected an order update");
+        };
+        assert_eq!(update.client_order_id, "test-3");
+        assert_eq!(update.venue_order_id, "8886774");
+        assert!(matches!(update.status, OrderStatus::PartiallyFilled));
+        assert_eq!(update.quantity, Quantity::from(-0.002));
+        let fill = update.last_fill.unwrap();
+        assert_eq!(fill.trade_id, 42);
+        assert_eq!(fill.quantity, Quantity::from(-0.001));
+        assert_eq!(fill.commission_asset.to_string(), "usdt");
+        assert_eq!(fill.liquidity, Liquidity::Maker);
+
+        let account = r#"{"e":"ACCOUNT_UPDATE","E":1564745798939,"T":1564745798938,"a":{"m":"ORDER","B":[{"a":"USDT","wb":"122624.12345678","cw":"100.12345678","bc":"50.12345678"}],"P":[{"s":"BTCUSDT","pa":"0.5","ep":"7000","bep":"0","cr":"200","up":"50","mt":"cross","iw":"0","ps":"BOTH"},{"s":"ETHUSDT","pa":"1","ep":"300","bep":"0","cr":"0","up":"0","mt":"cross","iw":"0","ps":"BOTH"}]}}"#;
+        let Some(Event::AccountUpdate(update)) = parser.parse_user(account).unwrap() else {
+            panic!("Expected an account update");
+        };
+        assert_eq!(update.balances[0].asset.to_string(), "usdt");
+        assert_eq!(update.positions.len(), 1);
+        assert_eq!(update.positions[0].quantity, Quantity::from(0.5));
+
+        assert!(parser
+            .parse_user(r#"{"e":"listenKeyExpired","E":1576653824250}"#)
+            .unwrap()
+            .is_none());
     }
 }
@@ -0,0 +1,114 @@
+use rust_decimal::Decimal;
+use serde::{de::IgnoredAny, Deserialize};
+use time::OffsetDateTime;
+
+use crate::{
+    ingestors::IngestorID,
+    models::{Candle, Instrument, Side, Trade},
+    utils::custom_serde,
+};
+
+// [
+//   {
+//     "a": 26129,         // Aggregate tradeId
+//     "p": "0.01633102",  // Price
+//     "q": "4.70443515",  // Quantity
+//     "f": 27781,         // First tradeId
+//     "l": 27781,         // Last tradeId
+//     "T": 1498793709153, // Timestamp
+//     "m": true           // Was the buyer the maker?
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceAggTrade {
+    #[serde(rename = "a")]
+    pub agg_trade_id: u64,
+    #[serde(rename = "p")]
+    pub price: Decimal,
+    #[serde(rename = "q")]
+    pub quantity: Decimal,
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub transaction_time: OffsetDateTime,
+    #[serde(rename = "m")]
+    pub maker: bool,
+}
+
+impl BinanceAggTrade {
+    /// Historical trades were never received live, the trade time stands in for the received time
+    pub fn into_trade(self, instrument: Instrument) -> Trade {
+        Trade::new(
+            self.transaction_time,
+            self.transaction_time,
+            instrument,
+            self.agg_trade_id,
+            self.price.into(),
+            Side::from_buyer_maker(self.maker).signed(self.quantity).into(),
+            IngestorID::Binance,
+        )
+    }
+}
+
+// [
+//   [
+//     1499040000000,      // Open time
+//     "0.01634790",       // Open
+//     "0.80000000",       // High
+//     "0.01575800",       // Low
+//     "0.01577100",       // Close
+//     "148976.11427815",  // Volume
+//     1499644799999,      // Close time
+//     "2434.19055334",    // Quote asset volume
+//     308,                // Number of trades
+//     "1756.87402397",    // Taker buy base asset volume
+//     "28.46694368",      // Taker buy quote asset volume
+//     "17928899.62484339" // Ignore
+//   ]
+// ]
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceKline(
+    #[serde(with = "custom_serde::timestamp")] pub OffsetDateTime,
+    pub Decimal,
+    pub Decimal,
+    pub Decimal,
+    pub Decimal,
+    pub Decimal,
+    #[serde(with = "custom_serde::timestamp")] pub OffsetDateTime,
+    pub Decimal,
+    pub u64,
+    pub Decimal,
+    pub Decimal,
+    pub IgnoredAny,
+);
+
+impl BinanceKline {
+    /// Last millisecond of the kline
+    pub fn close_time(&self) -> OffsetDateTime {
+        self.6
+    }
+
+    /// The kline that is still open at `now` is returned with `closed` unset
+    pub fn into_candle(self, instrument: Instrument, now: OffsetDateTime) -> Candle {
+        let interval = self.6 - self.0 + time::Duration::milliseconds(1);
+        Candle {
+            event_time: self.0 + interval,
+            instrument,
+            interval,
+            open_time: self.0,
+            open: self.1.into(),
+            high: self.2.into(),
+            low: self.3.into(),
+            close: self.4.into(),
+            volume: self.5.into(),
+            quote_volume: self.7.into(),
+            trades: self.8,
+            closed: self.6 < now,
+            source: IngestorID::Binance,
+        }
+    }
+}
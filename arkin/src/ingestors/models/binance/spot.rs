@@ -1,16 +1,65 @@
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use time::OffsetDateTime;
 
-use crate::utils::custom_serde;
+use crate::{
+    ingestors::IngestorID,
+    models::{Event, Instrument, Side, Tick, Trade},
+    utils::custom_serde,
+};
+
+use super::frame::FromStream;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BinanceSpotEvent {
+    AggTradeStream(BinanceSpotAggTrade),
+    AggTrade(BinanceSpotAggTradeData),
+    TickStream(BinanceSpotTick),
+    Tick(BinanceSpotTickData),
+}
+
+impl FromStream for BinanceSpotEvent {
+    /// Picks the payload type from the channel of a combined stream name like btcusdt@aggTrade
+    fn from_stream<'de, D: Deserializer<'de>>(stream: &str, data: D) -> Result<Self, D::Error> {
+        Ok(match stream.split_once('@').map(|(_, channel)| channel) {
+            Some("aggTrade") => BinanceSpotEvent::AggTrade(Deserialize::deserialize(data)?),
+            Some("bookTicker") => BinanceSpotEvent::Tick(Deserialize::deserialize(data)?),
+            _ => Deserialize::deserialize(data)?,
+        })
+    }
+}
+
+impl BinanceSpotEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            BinanceSpotEvent::AggTradeStream(data) => &data.data.instrument,
+            BinanceSpotEvent::AggTrade(data) => &data.instrument,
+            BinanceSpotEvent::TickStream(data) => &data.data.instrument,
+            BinanceSpotEvent::Tick(data) => &data.instrument,
+        }
+    }
+
+    /// The received time is stamped by the parser, which knows the Binance server time
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        match self {
+            BinanceSpotEvent::AggTradeStream(data) => data.data.into_event(instrument, received_time),
+            BinanceSpotEvent::AggTrade(data) => data.into_event(instrument, received_time),
+            BinanceSpotEvent::TickStream(data) => data.data.into_event(instrument, received_time),
+            BinanceSpotEvent::Tick(data) => data.into_event(instrument, received_time),
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSpotAggTrade {
     pub stream: String,
     pub data: BinanceSpotAggTradeData,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSpotAggTradeData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -31,14 +80,77 @@ pub struct BinanceSpotAggTradeData {
     #[serde(rename = "q")]
     pub quantity: Decimal,
     #[serde(rename = "m")]
-    pub maker: bool, // The true = sell, false = buy
+    pub maker: bool, // Buyer is the maker, so the taker sold
     #[serde(rename = "M")]
     pub ignore: bool,
 }
 
+impl BinanceSpotAggTradeData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        Event::Trade(Trade::new(
+            received_time,
+            self.event_time,
+            instrument,
+            self.agg_trade_id,
+            self.price.into(),
+            Side::from_buyer_maker(self.maker).signed(self.quantity).into(),
+            IngestorID::Binance,
+        ))
+    }
+}
+
+// The spot book ticker has no event time, so it is stamped on arrival
+// {
+//     "u":400900217,     // order book updateId
+//     "s":"BNBUSDT",     // symbol
+//     "b":"25.35190000", // best bid price
+//     "B":"31.21000000", // best bid qty
+//     "a":"25.36520000", // best ask price
+//     "A":"40.66000000"  // best ask qty
+// }
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSpotTick {
+    pub stream: String,
+    pub data: BinanceSpotTickData,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSpotTickData {
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "b")]
+    pub bid_price: Decimal,
+    #[serde(rename = "B")]
+    pub bid_quantity: Decimal,
+    #[serde(rename = "a")]
+    pub ask_price: Decimal,
+    #[serde(rename = "A")]
+    pub ask_quantity: Decimal,
+}
+
+impl BinanceSpotTickData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        Event::Tick(Tick {
+            event_time: received_time,
+            instrument,
+            tick_id: self.update_id,
+            bid_price: self.bid_price.into(),
+            bid_quantity: self.bid_quantity.into(),
+            ask_price: self.ask_price.into(),
+            ask_quantity: self.ask_quantity.into(),
+            source: IngestorID::Binance,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::Venue;
 
     #[test]
     fn test_binance_futures_agg_trade_1() {
@@ -51,4 +163,19 @@ mod tests {
         let json_data = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1704895018786,"s":"BTCUSDT","a":2822117879,"p":"45128.00000000","q":"0.01303000","f":3362959266,"l":3362959266,"T":1704895018785,"m":false,"M":true}}"#;
         let _ = serde_json::from_str::<BinanceSpotAggTrade>(json_data).unwrap();
     }
+
+    #[test]
+    fn test_binance_spot_ticker() {
+        let json_data = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}}"#;
+        let event = serde_json::from_str::<BinanceSpotEvent>(json_data).unwrap();
+        assert_eq!(event.symbol(), "BTCUSDT");
+        let instrument = Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into());
+        assert!(
+            *event
+                .into_event(instrument.clone(), OffsetDateTime::UNIX_EPOCH)
+                .instrument()
+                .unwrap()
+                == instrument
+        );
+    }
 }
@@ -1,13 +1,13 @@
 use crate::{
     ingestors::IngestorID,
-    models::{Book, BookUpdateSide, Event, Tick, Trade},
+    models::{Book, BookUpdateSide, Candle, Event, FundingRate, Instrument, Liquidation, Side, Tick, Trade},
     utils::custom_serde,
 };
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use time::OffsetDateTime;
 
-use super::parser::BinanceParser;
+use super::frame::FromStream;
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
@@ -20,19 +20,69 @@ pub enum BinanceSwapsEvent {
     Book(BinanceSwapsBookData),
     TickStream(BinanceSwapsTick),
     Tick(BinanceSwapsTickData),
+    MarkPriceStream(BinanceSwapsMarkPrice),
+    MarkPrice(BinanceSwapsMarkPriceData),
+    LiquidationStream(BinanceSwapsLiquidation),
+    Liquidation(BinanceSwapsLiquidationData),
+    KlineStream(BinanceSwapsKline),
+    Kline(BinanceSwapsKlineData),
 }
 
-impl From<BinanceSwapsEvent> for Event {
-    fn from(event: BinanceSwapsEvent) -> Self {
-        match event {
-            BinanceSwapsEvent::TradeStream(data) => Event::from(data.data),
-            BinanceSwapsEvent::Trade(data) => Event::from(data),
-            BinanceSwapsEvent::AggTradeStream(data) => Event::from(data.data),
-            BinanceSwapsEvent::AggTrade(data) => Event::from(data),
-            BinanceSwapsEvent::BookStream(data) => Event::from(data.data),
-            BinanceSwapsEvent::Book(data) => Event::from(data),
-            BinanceSwapsEvent::TickStream(data) => Event::from(data.data),
-            BinanceSwapsEvent::Tick(data) => Event::from(data),
+impl FromStream for BinanceSwapsEvent {
+    /// Picks the payload type from the channel of a combined stream name like btcusdt@aggTrade,
+    /// unknown channels try every type
+    fn from_stream<'de, D: Deserializer<'de>>(stream: &str, data: D) -> Result<Self, D::Error> {
+        let channel = stream.split_once('@').map(|(_, channel)| channel).unwrap_or_default();
+        Ok(match channel {
+            "trade" => BinanceSwapsEvent::Trade(Deserialize::deserialize(data)?),
+            "aggTrade" => BinanceSwapsEvent::AggTrade(Deserialize::deserialize(data)?),
+            "bookTicker" => BinanceSwapsEvent::Tick(Deserialize::deserialize(data)?),
+            "forceOrder" => BinanceSwapsEvent::Liquidation(Deserialize::deserialize(data)?),
+            c if c.starts_with("depth") => BinanceSwapsEvent::Book(Deserialize::deserialize(data)?),
+            c if c.starts_with("markPrice") => BinanceSwapsEvent::MarkPrice(Deserialize::deserialize(data)?),
+            c if c.starts_with("kline_") => BinanceSwapsEvent::Kline(Deserialize::deserialize(data)?),
+            _ => Deserialize::deserialize(data)?,
+        })
+    }
+}
+
+impl BinanceSwapsEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            BinanceSwapsEvent::TradeStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::Trade(data) => &data.instrument,
+            BinanceSwapsEvent::AggTradeStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::AggTrade(data) => &data.instrument,
+            BinanceSwapsEvent::BookStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::Book(data) => &data.instrument,
+            BinanceSwapsEvent::TickStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::Tick(data) => &data.instrument,
+            BinanceSwapsEvent::MarkPriceStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::MarkPrice(data) => &data.instrument,
+            BinanceSwapsEvent::LiquidationStream(data) => &data.data.order.instrument,
+            BinanceSwapsEvent::Liquidation(data) => &data.order.instrument,
+            BinanceSwapsEvent::KlineStream(data) => &data.data.instrument,
+            BinanceSwapsEvent::Kline(data) => &data.instrument,
+        }
+    }
+
+    /// The received time is stamped by the parser, which knows the Binance server time
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
+        match self {
+            BinanceSwapsEvent::TradeStream(data) => data.data.into_event(instrument, received_time),
+            BinanceSwapsEvent::Trade(data) => data.into_event(instrument, received_time),
+            BinanceSwapsEvent::AggTradeStream(data) => data.data.into_event(instrument, received_time),
+            BinanceSwapsEvent::AggTrade(data) => data.into_event(instrument, received_time),
+            BinanceSwapsEvent::BookStream(data) => data.data.into_event(instrument, received_time),
+            BinanceSwapsEvent::Book(data) => data.into_event(instrument, received_time),
+            BinanceSwapsEvent::TickStream(data) => data.data.into_event(instrument),
+            BinanceSwapsEvent::Tick(data) => data.into_event(instrument),
+            BinanceSwapsEvent::MarkPriceStream(data) => data.data.into_event(instrument),
+            BinanceSwapsEvent::MarkPrice(data) => data.into_event(instrument),
+            BinanceSwapsEvent::LiquidationStream(data) => data.data.into_event(instrument),
+            BinanceSwapsEvent::Liquidation(data) => data.into_event(instrument),
+            BinanceSwapsEvent::KlineStream(data) => data.data.into_event(instrument),
+            BinanceSwapsEvent::Kline(data) => data.into_event(instrument),
         }
     }
 }
@@ -70,6 +120,7 @@ pub struct BinanceSwapsTrade {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSwapsTradeData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -88,19 +139,18 @@ pub struct BinanceSwapsTradeData {
     #[serde(rename = "X")]
     pub trade_type: String,
     #[serde(rename = "m")]
-    pub maker: bool, // The true = sell, false = buy
+    pub maker: bool, // Buyer is the maker, so the taker sold
 }
 
-impl From<BinanceSwapsTradeData> for Event {
-    fn from(data: BinanceSwapsTradeData) -> Self {
-        let instrument = BinanceParser::parse_instrument(&data.instrument);
+impl BinanceSwapsTradeData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
         Event::Trade(Trade {
-            received_time: OffsetDateTime::now_utc(),
-            event_time: data.event_time,
+            received_time,
+            event_time: self.event_time,
             instrument,
-            trade_id: data.trade_id,
-            price: data.price.into(), // TODO: Fix this
-            quantity: data.quantity.into(),
+            trade_id: self.trade_id,
+            price: self.price.into(), // TODO: Fix this
+            quantity: self.quantity.into(),
             source: IngestorID::Binance,
         })
     }
@@ -114,6 +164,7 @@ pub struct BinanceSwapsAggTrade {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSwapsAggTradeData {
     #[serde(rename = "E", with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
@@ -137,16 +188,15 @@ pub struct BinanceSwapsAggTradeData {
     pub maker: bool, // The true = sell, false = buy
 }
 
-impl From<BinanceSwapsAggTradeData> for Event {
-    fn from(data: BinanceSwapsAggTradeData) -> Self {
-        let instrument = BinanceParser::parse_instrument(&data.instrument);
+impl BinanceSwapsAggTradeData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
         Event::Trade(Trade::new(
-            OffsetDateTime::now_utc(),
-            data.event_time,
+            received_time,
+            self.event_time,
             instrument,
-            data.agg_trade_id,
-            data.price.into(), // TODO: Fix this
-            data.quantity.into(),
+            self.agg_trade_id,
+            self.price.into(), // TODO: Fix this
+            Side::from_buyer_maker(self.maker).signed(self.quantity).into(),
             IngestorID::Binance,
         ))
     }
@@ -160,6 +210,7 @@ pub struct BinanceSwapsBook {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSwapsBookData {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -187,17 +238,17 @@ pub struct BinanceSwapsBookUpdate {
     pub quantity: Decimal,
 }
 
-impl From<BinanceSwapsBookData> for Event {
-    fn from(data: BinanceSwapsBookData) -> Self {
-        let instrument = BinanceParser::parse_instrument(&data.instrument);
+impl BinanceSwapsBookData {
+    pub fn into_event(self, instrument: Instrument, received_time: OffsetDateTime) -> Event {
         Event::Book(Book::new(
-            data.event_time,
+            received_time,
+            self.event_time,
             instrument,
-            data.bids
+            self.bids
                 .iter()
                 .map(|b| BookUpdateSide::new(b.price.into(), b.quantity.into()))
                 .collect(),
-            data.asks
+            self.asks
                 .iter()
                 .map(|a| BookUpdateSide::new(a.price.into(), a.quantity.into()))
                 .collect(),
@@ -226,6 +277,7 @@ pub struct BinanceSwapsTick {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(unused)]
 pub struct BinanceSwapsTickData {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -247,17 +299,235 @@ pub struct BinanceSwapsTickData {
     pub ask_quantity: Decimal,
 }
 
-impl From<BinanceSwapsTickData> for Event {
-    fn from(data: BinanceSwapsTickData) -> Self {
-        let instrument = BinanceParser::parse_instrument(&data.instrument);
+impl BinanceSwapsTickData {
+    pub fn into_event(self, instrument: Instrument) -> Event {
         Event::Tick(Tick {
-            event_time: data.event_time,
+            event_time: self.event_time,
+            instrument,
+            tick_id: self.update_id,
+            bid_price: self.bid_price.into(), // TODO: Fix this
+            bid_quantity: self.bid_quantity.into(),
+            ask_price: self.ask_price.into(), // TODO: Fix this
+            ask_quantity: self.ask_quantity.into(),
+            source: IngestorID::Binance,
+        })
+    }
+}
+
+// {
+//     "e": "markPriceUpdate",     // Event type
+//     "E": 1562305380000,         // Event time
+//     "s": "BTCUSDT",             // Symbol
+//     "p": "11794.15000000",      // Mark price
+//     "i": "11784.62659091",      // Index price
+//     "P": "11784.25641265",      // Estimated Settle Price
+//     "r": "0.00038167",          // Funding rate
+//     "T": 1562306400000          // Next funding time
+// }
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsMarkPrice {
+    pub stream: String,
+    pub data: BinanceSwapsMarkPriceData,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsMarkPriceData {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "p")]
+    pub mark_price: Decimal,
+    #[serde(rename = "i")]
+    pub index_price: Decimal,
+    #[serde(rename = "r")]
+    pub funding_rate: Decimal,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub next_funding_time: OffsetDateTime,
+}
+
+impl BinanceSwapsMarkPriceData {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        Event::FundingRate(FundingRate {
+            event_time: self.event_time,
+            instrument,
+            mark_price: self.mark_price.into(),
+            index_price: self.index_price.into(),
+            funding_rate: self.funding_rate,
+            next_funding_time: self.next_funding_time,
+            source: IngestorID::Binance,
+        })
+    }
+}
+
+// {
+//     "e":"forceOrder",                   // Event Type
+//     "E":1568014460893,                  // Event Time
+//     "o":{
+//         "s":"BTCUSDT",                  // Symbol
+//         "S":"SELL",                     // Side
+//         "o":"LIMIT",                    // Order Type
+//         "f":"IOC",                      // Time in Force
+//         "q":"0.014",                    // Original Quantity
+//         "p":"9910",                     // Price
+//         "ap":"9910",                    // Average Price
+//         "X":"FILLED",                   // Order Status
+//         "l":"0.014",                    // Order Last Filled Quantity
+//         "z":"0.014",                    // Order Filled Accumulated Quantity
+//         "T":1568014460893,              // Order Trade Time
+//     }
+// }
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsLiquidation {
+    pub stream: String,
+    pub data: BinanceSwapsLiquidationData,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsLiquidationData {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "o")]
+    pub order: BinanceSwapsLiquidationOrder,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsLiquidationOrder {
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "S")]
+    pub side: String,
+    #[serde(rename = "q")]
+    pub quantity: Decimal,
+    #[serde(rename = "p")]
+    pub price: Decimal,
+    #[serde(rename = "ap")]
+    pub avg_price: Decimal,
+    #[serde(rename = "X")]
+    pub status: String,
+    #[serde(rename = "z")]
+    pub quantity_filled: Decimal,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub trade_time: OffsetDateTime,
+}
+
+impl BinanceSwapsLiquidationData {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        let order = self.order;
+        let sign = if order.side == "SELL" {
+            Decimal::NEGATIVE_ONE
+        } else {
+            Decimal::ONE
+        };
+        Event::Liquidation(Liquidation {
+            event_time: order.trade_time,
             instrument,
-            tick_id: data.update_id,
-            bid_price: data.bid_price.into(), // TODO: Fix this
-            bid_quantity: data.bid_quantity.into(),
-            ask_price: data.ask_price.into(), // TODO: Fix this
-            ask_quantity: data.ask_quantity.into(),
+            price: order.price.into(),
+            avg_price: order.avg_price.into(),
+            quantity: (order.quantity * sign).into(),
+            quantity_filled: (order.quantity_filled * sign).into(),
+            source: IngestorID::Binance,
+        })
+    }
+}
+
+// {
+//     "e": "kline",                // Event type
+//     "E": 1638747660000,          // Event time
+//     "s": "BTCUSDT",              // Symbol
+//     "k": {
+//         "t": 1638747660000,      // Kline start time
+//         "T": 1638747719999,      // Kline close time
+//         "s": "BTCUSDT",          // Symbol
+//         "i": "1m",               // Interval
+//         "f": 100,                // First trade ID
+//         "L": 200,                // Last trade ID
+//         "o": "0.0010",           // Open price
+//         "c": "0.0020",           // Close price
+//         "h": "0.0025",           // High price
+//         "l": "0.0015",           // Low price
+//         "v": "1000",             // Base asset volume
+//         "n": 100,                // Number of trades
+//         "x": false,              // Is this kline closed?
+//         "q": "1.0000",           // Quote asset volume
+//         "V": "500",              // Taker buy base asset volume
+//         "Q": "0.500",            // Taker buy quote asset volume
+//         "B": "123456"            // Ignore
+//     }
+// }
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsKline {
+    pub stream: String,
+    pub data: BinanceSwapsKlineData,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsKlineData {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "k")]
+    pub kline: BinanceSwapsKlineBar,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceSwapsKlineBar {
+    #[serde(rename = "t", with = "custom_serde::timestamp")]
+    pub open_time: OffsetDateTime,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub close_time: OffsetDateTime,
+    #[serde(rename = "i")]
+    pub interval: String,
+    #[serde(rename = "o")]
+    pub open: Decimal,
+    #[serde(rename = "h")]
+    pub high: Decimal,
+    #[serde(rename = "l")]
+    pub low: Decimal,
+    #[serde(rename = "c")]
+    pub close: Decimal,
+    #[serde(rename = "v")]
+    pub volume: Decimal,
+    #[serde(rename = "q")]
+    pub quote_volume: Decimal,
+    #[serde(rename = "n")]
+    pub trades: u64,
+    #[serde(rename = "x")]
+    pub closed: bool,
+}
+
+impl BinanceSwapsKlineData {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        let kline = self.kline;
+        Event::Candle(Candle {
+            event_time: self.event_time,
+            instrument,
+            // The close time is the last millisecond of the bar, which also covers months of different length
+            interval: kline.close_time - kline.open_time + time::Duration::milliseconds(1),
+            open_time: kline.open_time,
+            open: kline.open.into(),
+            high: kline.high.into(),
+            low: kline.low.into(),
+            close: kline.close.into(),
+            volume: kline.volume.into(),
+            quote_volume: kline.quote_volume.into(),
+            trades: kline.trades,
+            closed: kline.closed,
             source: IngestorID::Binance,
         })
     }
@@ -292,6 +562,47 @@ mod tests {
         let _ = serde_json::from_str::<BinanceSwapsTick>(json_data).unwrap();
     }
 
+    #[test]
+    fn test_binance_futures_mark_price() {
+        let json_data = r#"{"stream":"btcusdt@markPrice@1s","data":{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT","p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}}"#;
+        let event = serde_json::from_str::<BinanceSwapsEvent>(json_data).unwrap();
+        assert_eq!(event.symbol(), "BTCUSDT");
+        let instrument = Instrument::perpetual(crate::models::Venue::Binance, "BTC".into(), "USDT".into());
+        let Event::FundingRate(funding) = event.into_event(instrument, OffsetDateTime::UNIX_EPOCH) else {
+            panic!("Expected a funding rate");
+        };
+        assert_eq!(funding.funding_rate, Decimal::new(38167, 8));
+        assert_eq!(funding.next_funding_time.unix_timestamp(), 1562306400);
+    }
+
+    #[test]
+    fn test_binance_futures_liquidation() {
+        let json_data = r#"{"stream":"btcusdt@forceOrder","data":{"e":"forceOrder","E":1568014460893,"o":{"s":"BTCUSDT","S":"SELL","o":"LIMIT","f":"IOC","q":"0.014","p":"9910","ap":"9910","X":"FILLED","l":"0.014","z":"0.014","T":1568014460893}}}"#;
+        let event = serde_json::from_str::<BinanceSwapsEvent>(json_data).unwrap();
+        assert_eq!(event.symbol(), "BTCUSDT");
+        let instrument = Instrument::perpetual(crate::models::Venue::Binance, "BTC".into(), "USDT".into());
+        let Event::Liquidation(liquidation) = event.into_event(instrument, OffsetDateTime::UNIX_EPOCH) else {
+            panic!("Expected a liquidation");
+        };
+        assert_eq!(liquidation.quantity_filled.value(), Decimal::new(-14, 3));
+        assert_eq!(liquidation.notional(), Decimal::new(13874, 2));
+    }
+
+    #[test]
+    fn test_binance_futures_kline() {
+        let json_data = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1638747660000,"s":"BTCUSDT","k":{"t":1638747660000,"T":1638747719999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","n":100,"x":true,"q":"1.0000","V":"500","Q":"0.500","B":"123456"}}}"#;
+        let event = serde_json::from_str::<BinanceSwapsEvent>(json_data).unwrap();
+        assert_eq!(event.symbol(), "BTCUSDT");
+        let instrument = Instrument::perpetual(crate::models::Venue::Binance, "BTC".into(), "USDT".into());
+        let Event::Candle(candle) = event.into_event(instrument, OffsetDateTime::UNIX_EPOCH) else {
+            panic!("Expected a candle");
+        };
+        assert_eq!(candle.interval, time::Duration::minutes(1));
+        assert_eq!(candle.close_time().unix_timestamp(), 1638747720);
+        assert_eq!(candle.high.value(), Decimal::new(25, 4));
+        assert!(candle.closed);
+    }
+
     #[test]
     #[ignore]
     fn test_binance_futures_ticker_2() {
@@ -0,0 +1,238 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    models::{
+        AccountUpdate, BalanceUpdate, Event, Instrument, Liquidity, OrderStatus, OrderType, OrderUpdate,
+        PositionUpdate, Quantity, Venue, VenueFill,
+    },
+    utils::custom_serde,
+};
+
+/// Events of the futures user data stream, the ones not listed are ignored
+#[derive(Debug, Deserialize)]
+#[serde(tag = "e")]
+pub enum BinanceUserEvent {
+    #[serde(rename = "ORDER_TRADE_UPDATE")]
+    OrderTradeUpdate(BinanceOrderTradeUpdate),
+    #[serde(rename = "ACCOUNT_UPDATE")]
+    AccountUpdate(BinanceAccountUpdate),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceOrderTradeUpdate {
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub transaction_time: OffsetDateTime,
+    #[serde(rename = "o")]
+    pub order: BinanceOrderData,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceOrderType {
+    Market,
+    Limit,
+    Stop,
+    StopMarket,
+    TakeProfit,
+    TakeProfitMarket,
+    TrailingStopMarket,
+    Liquidation,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceOrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceOrderData {
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "i")]
+    pub order_id: u64,
+    #[serde(rename = "S")]
+    pub side: BinanceSide,
+    #[serde(rename = "o")]
+    pub order_type: BinanceOrderType,
+    #[serde(rename = "x")]
+    pub execution_type: String,
+    #[serde(rename = "X")]
+    pub status: BinanceOrderStatus,
+    #[serde(rename = "p")]
+    pub price: Decimal,
+    #[serde(rename = "q")]
+    pub quantity: Decimal,
+    #[serde(rename = "ap")]
+    pub avg_price: Decimal,
+    #[serde(rename = "z")]
+    pub quantity_filled: Decimal,
+    #[serde(rename = "L")]
+    pub last_price: Decimal,
+    #[serde(rename = "l")]
+    pub last_quantity: Decimal,
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+    #[serde(rename = "n", default)]
+    pub commission: Option<Decimal>,
+    #[serde(rename = "N", default)]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "m", default)]
+    pub maker: bool,
+}
+
+impl BinanceOrderTradeUpdate {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        let order = self.order;
+        // Quantities are signed, sells are negative
+        let signed = |quantity: Decimal| -> Quantity {
+            match order.side {
+                BinanceSide::Buy => quantity.into(),
+                BinanceSide::Sell => (-quantity).into(),
+            }
+        };
+        let last_fill = (order.execution_type == "TRADE").then(|| VenueFill {
+            trade_id: order.trade_id,
+            price: order.last_price.into(),
+            quantity: signed(order.last_quantity),
+            commission: order.commission.unwrap_or_default(),
+            commission_asset: order.commission_asset.as_deref().unwrap_or_default().into(),
+            liquidity: if order.maker {
+                Liquidity::Maker
+            } else {
+                Liquidity::Taker
+            },
+        });
+
+        Event::OrderUpdate(OrderUpdate {
+            event_time: self.event_time,
+            instrument,
+            client_order_id: order.client_order_id,
+            venue_order_id: order.order_id.to_string(),
+            order_type: match order.order_type {
+                BinanceOrderType::Market | BinanceOrderType::Liquidation => OrderType::Market,
+                BinanceOrderType::Limit => OrderType::Limit,
+                BinanceOrderType::StopMarket
+                | BinanceOrderType::TakeProfitMarket
+                | BinanceOrderType::TrailingStopMarket => OrderType::Stop,
+                BinanceOrderType::Stop | BinanceOrderType::TakeProfit => OrderType::StopLimit,
+            },
+            status: match order.status {
+                BinanceOrderStatus::New => OrderStatus::Open,
+                BinanceOrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+                BinanceOrderStatus::Filled => OrderStatus::Filled,
+                BinanceOrderStatus::Canceled => OrderStatus::Canceled,
+                BinanceOrderStatus::Expired | BinanceOrderStatus::ExpiredInMatch => OrderStatus::Expired,
+                BinanceOrderStatus::Rejected => OrderStatus::Rejected,
+            },
+            price: (!order.price.is_zero()).then(|| order.price.into()),
+            quantity: signed(order.quantity),
+            quantity_filled: signed(order.quantity_filled),
+            avg_fill_price: (!order.avg_price.is_zero()).then(|| order.avg_price.into()),
+            last_fill,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinanceAccountUpdate {
+    #[serde(rename = "E", with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub transaction_time: OffsetDateTime,
+    #[serde(rename = "a")]
+    pub account: BinanceAccountData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BinanceAccountData {
+    #[serde(rename = "m")]
+    pub reason: String,
+    #[serde(rename = "B")]
+    pub balances: Vec<BinanceBalance>,
+    #[serde(rename = "P")]
+    pub positions: Vec<BinancePosition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BinanceBalance {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "wb")]
+    pub wallet: Decimal,
+    #[serde(rename = "cw")]
+    pub cross_wallet: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BinancePosition {
+    #[serde(rename = "s")]
+    pub instrument: String,
+    #[serde(rename = "pa")]
+    pub quantity: Decimal,
+    #[serde(rename = "ep")]
+    pub entry_price: Decimal,
+    #[serde(rename = "up")]
+    pub unrealized_pnl: Decimal,
+    #[serde(rename = "ps")]
+    pub position_side: String,
+}
+
+impl BinanceAccountUpdate {
+    /// Positions in symbols the resolver doesn't know are left out
+    pub fn into_event(self, resolve: impl Fn(&str) -> Option<Instrument>) -> Event {
+        Event::AccountUpdate(AccountUpdate {
+            event_time: self.event_time,
+            venue: Venue::Binance,
+            reason: self.account.reason,
+            balances: self
+                .account
+                .balances
+                .into_iter()
+                .map(|b| BalanceUpdate {
+                    asset: b.asset.as_str().into(),
+                    wallet: b.wallet,
+                    cross_wallet: b.cross_wallet,
+                })
+                .collect(),
+            positions: self
+                .account
+                .positions
+                .into_iter()
+                .filter_map(|p| {
+                    Some(PositionUpdate {
+                        instrument: resolve(&p.instrument)?,
+                        quantity: p.quantity.into(),
+                        entry_price: p.entry_price.into(),
+                        unrealized_pnl: p.unrealized_pnl.into(),
+                    })
+                })
+                .collect(),
+        })
+    }
+}
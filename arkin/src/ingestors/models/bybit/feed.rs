@@ -0,0 +1,89 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::utils::{self, custom_serde};
+
+/// Envelope of the v5 public topics, the data is parsed once the topic is known
+#[derive(Debug, Deserialize)]
+pub struct BybitMessage {
+    pub topic: String,
+    #[serde(rename = "type")]
+    pub kind: BybitUpdateKind,
+    #[serde(with = "custom_serde::timestamp")]
+    pub ts: OffsetDateTime,
+    pub data: Value,
+    /// Cross sequence, only set on tickers
+    #[serde(default)]
+    pub cs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BybitUpdateKind {
+    Snapshot,
+    /// Only the fields that changed are sent
+    Delta,
+}
+
+/// Levels as [price, size], a size of 0 removes the level
+#[derive(Debug, Deserialize)]
+pub struct BybitOrderbook {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b")]
+    pub bids: Vec<(Decimal, Decimal)>,
+    #[serde(rename = "a")]
+    pub asks: Vec<(Decimal, Decimal)>,
+    #[serde(rename = "u")]
+    pub update_id: u64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub enum BybitSide {
+    Buy,
+    Sell,
+}
+
+/// Side is the taker side
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct BybitTrade {
+    #[serde(rename = "T", with = "custom_serde::timestamp")]
+    pub trade_time: OffsetDateTime,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "S")]
+    pub side: BybitSide,
+    #[serde(rename = "v")]
+    pub size: Decimal,
+    #[serde(rename = "p")]
+    pub price: Decimal,
+    #[serde(rename = "i")]
+    pub trade_id: String,
+    #[serde(rename = "BT", default)]
+    pub block_trade: bool,
+}
+
+impl BybitTrade {
+    /// Ids are numeric on spot and uuids on derivatives, the latter are hashed with FNV-1a
+    /// so the same trade gets the same id on every connection
+    pub fn id(&self) -> u64 {
+        self.trade_id.parse().unwrap_or_else(|_| utils::fnv1a(&self.trade_id))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitTicker {
+    pub symbol: String,
+    #[serde(default, rename = "bid1Price")]
+    pub bid_price: Option<Decimal>,
+    #[serde(default, rename = "bid1Size")]
+    pub bid_size: Option<Decimal>,
+    #[serde(default, rename = "ask1Price")]
+    pub ask_price: Option<Decimal>,
+    #[serde(default, rename = "ask1Size")]
+    pub ask_size: Option<Decimal>,
+}
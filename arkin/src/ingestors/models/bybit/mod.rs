@@ -0,0 +1,4 @@
+mod feed;
+mod parser;
+
+pub use parser::BybitParser;
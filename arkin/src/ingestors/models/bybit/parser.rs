@@ -0,0 +1,200 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use tracing::error;
+
+use crate::{
+    ingestors::{IngestorError, IngestorID},
+    models::{Event, Instrument, InstrumentRegistry, Tick, Trade, Venue},
+};
+
+use super::feed::{BybitMessage, BybitOrderbook, BybitSide, BybitTicker, BybitTrade, BybitUpdateKind};
+
+/// Best bid and ask of a symbol, merged from the deltas of the orderbook and tickers topics
+#[derive(Default)]
+struct TopOfBook {
+    bid: Option<(Decimal, Decimal)>,
+    ask: Option<(Decimal, Decimal)>,
+}
+
+impl TopOfBook {
+    fn tick(&self, event_time: OffsetDateTime, instrument: Instrument, tick_id: u64) -> Option<Event> {
+        let ((bid_price, bid_quantity), (ask_price, ask_quantity)) = self.bid.zip(self.ask)?;
+        Some(Event::Tick(Tick {
+            event_time,
+            instrument,
+            tick_id,
+            bid_price: bid_price.into(),
+            bid_quantity: bid_quantity.into(),
+            ask_price: ask_price.into(),
+            ask_quantity: ask_quantity.into(),
+            source: IngestorID::Bybit,
+        }))
+    }
+}
+
+/// Turns Bybit v5 public messages into events. Deltas only carry what changed, so the parser keeps
+/// the top of book per symbol and emits a tick once both sides are known
+#[derive(Clone)]
+pub struct BybitParser {
+    instruments: Arc<InstrumentRegistry>,
+    books: Arc<Mutex<HashMap<String, TopOfBook>>>,
+}
+
+impl BybitParser {
+    pub fn new(instruments: Arc<InstrumentRegistry>) -> Self {
+        Self {
+            instruments,
+            books: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// A trade message can hold several trades, book updates without a full top of book none
+    pub fn parse(&self, data: &str) -> Result<Vec<Event>, IngestorError> {
+        let message = serde_json::from_str::<BybitMessage>(data).inspect_err(|e| {
+            error!("Failed to parse Bybit event: {}", e);
+            error!("Data: {}", data);
+        })?;
+        let (channel, _) = message.topic.split_once('.').unwrap_or((&message.topic, ""));
+        match channel {
+            "publicTrade" => {
+                let trades = serde_json::from_value::<Vec<BybitTrade>>(message.data)?;
+                trades
+                    .into_iter()
+                    .map(|trade| {
+                        let quantity = match trade.side {
+                            BybitSide::Buy => trade.size,
+                            BybitSide::Sell => -trade.size,
+                        };
+                        Ok(Event::Trade(Trade::new(
+                            OffsetDateTime::now_utc(),
+                            trade.trade_time,
+                            self.instrument(&trade.symbol)?,
+                            trade.id(),
+                            trade.price.into(),
+                            quantity.into(),
+                            IngestorID::Bybit,
+                        )))
+                    })
+                    .collect()
+            }
+            // Deeper books need every level to find the top, only depth 1 maps to ticks
+            "orderbook" if message.topic.starts_with("orderbook.1.") => {
+                let book = serde_json::from_value::<BybitOrderbook>(message.data)?;
+                let instrument = self.instrument(&book.symbol)?;
+                let mut books = self.books.lock();
+                let top = books.entry(book.symbol).or_default();
+                if message.kind == BybitUpdateKind::Snapshot {
+                    *top = TopOfBook::default();
+                }
+                for (side, levels) in [(&mut top.bid, book.bids), (&mut top.ask, book.asks)] {
+                    if let Some(&(price, size)) = levels.first() {
+                        *side = (!size.is_zero()).then_some((price, size));
+                    }
+                }
+                Ok(top.tick(message.ts, instrument, book.update_id).into_iter().collect())
+            }
+            "tickers" => {
+                let ticker = serde_json::from_value::<BybitTicker>(message.data)?;
+                let instrument = self.instrument(&ticker.symbol)?;
+                let mut books = self.books.lock();
+                let top = books.entry(ticker.symbol).or_default();
+                if let Some(bid) = ticker.bid_price.zip(ticker.bid_size) {
+                    top.bid = Some(bid);
+                }
+                if let Some(ask) = ticker.ask_price.zip(ticker.ask_size) {
+                    top.ask = Some(ask);
+                }
+                // Deltas without a change of the best levels are not ticks
+                if ticker.bid_price.is_none() && ticker.ask_price.is_none() {
+                    return Ok(Vec::new());
+                }
+                let tick_id = message.cs.unwrap_or_default();
+                Ok(top.tick(message.ts, instrument, tick_id).into_iter().collect())
+            }
+            _ => Err(IngestorError::UnsupportedChannel {
+                exchange: Venue::Bybit.to_string(),
+                channel: message.topic,
+            }),
+        }
+    }
+
+    pub fn instrument(&self, symbol: &str) -> Result<Instrument, IngestorError> {
+        self.instruments
+            .by_symbol(&Venue::Bybit, symbol)
+            .into_iter()
+            .next()
+            .ok_or_else(|| IngestorError::UnknownSymbol {
+                venue: Venue::Bybit,
+                symbol: symbol.to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::InstrumentConfig,
+        models::{InstrumentType, Price, Quantity},
+    };
+
+    #[test]
+    fn test_parse_feed() {
+        let registry = InstrumentRegistry::from_config(&[InstrumentConfig {
+            venue: Venue::Bybit,
+            symbol: "BTCUSDT".into(),
+            instrument_type: InstrumentType::Perpetual,
+            base: "btc".into(),
+            quote: "usdt".into(),
+            maturity: None,
+            strike: None,
+            option_type: None,
+            tick_size: Decimal::new(1, 1),
+            lot_size: Decimal::new(1, 3),
+            min_notional: Decimal::from(5),
+            contract_multiplier: Decimal::ONE,
+            settlement: "usdt".into(),
+        }]);
+        let parser = BybitParser::new(Arc::new(registry));
+
+        let trades = r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false},{"T":1672304486865,"s":"BTCUSDT","S":"Sell","v":"0.002","p":"16578.00","L":"MinusTick","i":"6b6b1a8e-1c1f-5a2e-8d3e-0e7c0b8d4a11","BT":false}]}"#;
+        let events = parser.parse(trades).unwrap();
+        assert_eq!(events.len(), 2);
+        let Event::Trade(trade) = &events[1] else {
+            panic!("Expected a trade");
+        };
+        assert!(trade.instrument == Instrument::perpetual(Venue::Bybit, "BTC".into(), "USDT".into()));
+        assert_eq!(trade.quantity, Quantity::from(-0.002));
+        // Uuids hash to the same id on every connection
+        let Event::Trade(again) = &parser.parse(trades).unwrap()[1] else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.trade_id, again.trade_id);
+
+        // A delta of one side completes the book only after the snapshot
+        let bid = r#"{"topic":"orderbook.1.BTCUSDT","type":"delta","ts":1672304484978,"data":{"s":"BTCUSDT","b":[["16493.50","0.006"]],"a":[],"u":18521289,"seq":7961638724},"cts":1672304484976}"#;
+        assert!(parser.parse(bid).unwrap().is_empty());
+        let snapshot = r#"{"topic":"orderbook.1.BTCUSDT","type":"snapshot","ts":1672304484978,"data":{"s":"BTCUSDT","b":[["16493.50","0.006"]],"a":[["16611.00","0.029"]],"u":18521288,"seq":7961638724},"cts":1672304484976}"#;
+        let Event::Tick(tick) = &parser.parse(snapshot).unwrap()[0] else {
+            panic!("Expected a tick");
+        };
+        assert_eq!(tick.ask_price, Price::from(16611.));
+
+        let ticker = r#"{"topic":"tickers.BTCUSDT","type":"delta","data":{"symbol":"BTCUSDT","ask1Price":"16600.50","ask1Size":"1.5","lastPrice":"16600.00"},"cs":24987956059,"ts":1673272861686}"#;
+        let Event::Tick(tick) = &parser.parse(ticker).unwrap()[0] else {
+            panic!("Expected a tick");
+        };
+        assert_eq!(tick.tick_id, 24987956059);
+        assert_eq!(tick.bid_price, Price::from(16493.5));
+        assert_eq!(tick.ask_quantity, Quantity::from(1.5));
+        let funding = r#"{"topic":"tickers.BTCUSDT","type":"delta","data":{"symbol":"BTCUSDT","fundingRate":"0.0001"},"cs":24987956060,"ts":1673272861687}"#;
+        assert!(parser.parse(funding).unwrap().is_empty());
+
+        let deep =
+            r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1,"data":{"s":"BTCUSDT","b":[],"a":[],"u":1}}"#;
+        assert!(matches!(parser.parse(deep), Err(IngestorError::UnsupportedChannel { .. })));
+    }
+}
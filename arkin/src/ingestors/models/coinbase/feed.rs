@@ -0,0 +1,89 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    ingestors::IngestorID,
+    models::{Event, Instrument, Tick, Trade},
+};
+
+/// Messages of the Coinbase Exchange feed, the channels not listed are ignored
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CoinbaseEvent {
+    Ticker(CoinbaseTicker),
+    Match(CoinbaseMatch),
+    /// Sent once after subscribing to the matches channel
+    LastMatch(CoinbaseMatch),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct CoinbaseTicker {
+    pub sequence: u64,
+    pub product_id: String,
+    pub price: Decimal,
+    pub best_bid: Decimal,
+    pub best_bid_size: Decimal,
+    pub best_ask: Decimal,
+    pub best_ask_size: Decimal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+impl CoinbaseTicker {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        Event::Tick(Tick {
+            event_time: self.time,
+            instrument,
+            tick_id: self.sequence,
+            bid_price: self.best_bid.into(),
+            bid_quantity: self.best_bid_size.into(),
+            ask_price: self.best_ask.into(),
+            ask_quantity: self.best_ask_size.into(),
+            source: IngestorID::Coinbase,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinbaseSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct CoinbaseMatch {
+    pub trade_id: u64,
+    pub sequence: u64,
+    pub product_id: String,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// Side of the maker order
+    pub side: CoinbaseSide,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+impl CoinbaseMatch {
+    pub fn into_event(self, instrument: Instrument) -> Event {
+        // The taker sold into a resting buy order
+        let quantity = match self.side {
+            CoinbaseSide::Buy => -self.size,
+            CoinbaseSide::Sell => self.size,
+        };
+        Event::Trade(Trade::new(
+            OffsetDateTime::now_utc(),
+            self.time,
+            instrument,
+            self.trade_id,
+            self.price.into(),
+            quantity.into(),
+            IngestorID::Coinbase,
+        ))
+    }
+}
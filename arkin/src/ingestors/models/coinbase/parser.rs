@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use tracing::error;
+
+use crate::{
+    ingestors::IngestorError,
+    models::{Event, Instrument, InstrumentRegistry, Venue},
+};
+
+use super::feed::CoinbaseEvent;
+
+/// Turns Coinbase feed messages into events, products are resolved through the instrument registry
+#[derive(Clone)]
+pub struct CoinbaseParser {
+    instruments: Arc<InstrumentRegistry>,
+}
+
+impl CoinbaseParser {
+    pub fn new(instruments: Arc<InstrumentRegistry>) -> Self {
+        Self { instruments }
+    }
+
+    /// None for the messages that carry no market data
+    pub fn parse(&self, data: &str) -> Result<Option<Event>, IngestorError> {
+        let event = serde_json::from_str::<CoinbaseEvent>(data).inspect_err(|e| {
+            error!("Failed to parse Coinbase event: {}", e);
+            error!("Data: {}", data);
+        })?;
+        let event = match event {
+            CoinbaseEvent::Ticker(ticker) => {
+                let instrument = self.instrument(&ticker.product_id)?;
+                ticker.into_event(instrument)
+            }
+            CoinbaseEvent::Match(trade) | CoinbaseEvent::LastMatch(trade) => {
+                let instrument = self.instrument(&trade.product_id)?;
+                trade.into_event(instrument)
+            }
+            CoinbaseEvent::Other => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    pub fn instrument(&self, product_id: &str) -> Result<Instrument, IngestorError> {
+        self.instruments
+            .by_symbol(&Venue::Coinbase, product_id)
+            .into_iter()
+            .next()
+            .ok_or_else(|| IngestorError::UnknownSymbol {
+                venue: Venue::Coinbase,
+                symbol: product_id.to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::InstrumentConfig,
+        models::{InstrumentType, Price, Quantity},
+    };
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_parse_feed() {
+        let registry = InstrumentRegistry::from_config(&[InstrumentConfig {
+            venue: Venue::Coinbase,
+            symbol: "ETH-USD".into(),
+            instrument_type: InstrumentType::Spot,
+            base: "eth".into(),
+            quote: "usd".into(),
+            maturity: None,
+            strike: None,
+            option_type: None,
+            tick_size: Decimal::new(1, 2),
+            lot_size: Decimal::new(1, 8),
+            min_notional: Decimal::ONE,
+            contract_multiplier: Decimal::ONE,
+            settlement: "usd".into(),
+        }]);
+        let parser = CoinbaseParser::new(Arc::new(registry));
+
+        let ticker = r#"{"type":"ticker","sequence":37475248783,"product_id":"ETH-USD","price":"1285.22","open_24h":"1310.79","volume_24h":"245532.79269678","low_24h":"1280.52","high_24h":"1313.8","volume_30d":"9788783.60117027","best_bid":"1285.04","best_bid_size":"0.46688654","best_ask":"1285.27","best_ask_size":"1.56637040","side":"buy","time":"2022-10-19T23:28:22.061769Z","trade_id":370843401,"last_size":"11.4396987"}"#;
+        let Some(Event::Tick(tick)) = parser.parse(ticker).unwrap() else {
+            panic!("Expected a tick");
+        };
+        assert!(tick.instrument == Instrument::spot(Venue::Coinbase, "ETH".into(), "USD".into()));
+        assert_eq!(tick.tick_id, 37475248783);
+        assert_eq!(tick.ask_price, Price::from(1285.27));
+
+        let trade = r#"{"type":"match","trade_id":10,"sequence":50,"maker_order_id":"ac928c66-ca53-498f-9c13-a110027a60e8","taker_order_id":"132fb6ae-456b-4654-b4e0-d681ac05cea1","time":"2014-11-07T08:19:27.028459Z","product_id":"ETH-USD","size":"5.23512","price":"400.23","side":"buy"}"#;
+        let Some(Event::Trade(trade)) = parser.parse(trade).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.trade_id, 10);
+        assert_eq!(trade.quantity, Quantity::from(-5.23512));
+
+        assert!(parser.parse(r#"{"type":"heartbeat","sequence":90}"#).unwrap().is_none());
+        let unknown = trade_for("BTC-USD");
+        assert!(matches!(parser.parse(&unknown), Err(IngestorError::UnknownSymbol { .. })));
+    }
+
+    fn trade_for(product_id: &str) -> String {
+        format!(
+            r#"{{"type":"match","trade_id":1,"sequence":1,"time":"2024-01-01T00:00:00Z","product_id":"{}","size":"1","price":"1","side":"sell"}}"#,
+            product_id
+        )
+    }
+}
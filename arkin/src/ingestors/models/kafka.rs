@@ -0,0 +1,237 @@
+//! Protobuf encoding of market events, keep in sync with proto/market.proto
+use prost::Message;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+use crate::{
+    ingestors::IngestorError,
+    models::{Event, Instrument, InstrumentRegistry, InstrumentType, Tick, Trade, Venue},
+};
+
+#[allow(clippy::all)]
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarketEvent {
+        #[prost(oneof = "market_event::Event", tags = "1, 2")]
+        pub event: Option<market_event::Event>,
+    }
+
+    pub mod market_event {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Event {
+            #[prost(message, tag = "1")]
+            Tick(super::Tick),
+            #[prost(message, tag = "2")]
+            Trade(super::Trade),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstrumentRef {
+        #[prost(string, tag = "1")]
+        pub venue: String,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(string, tag = "3")]
+        pub instrument_type: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Decimal {
+        #[prost(sint64, tag = "1")]
+        pub mantissa: i64,
+        #[prost(uint32, tag = "2")]
+        pub scale: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Tick {
+        #[prost(int64, tag = "1")]
+        pub event_time: i64,
+        #[prost(message, optional, tag = "2")]
+        pub instrument: Option<InstrumentRef>,
+        #[prost(uint64, tag = "3")]
+        pub tick_id: u64,
+        #[prost(message, optional, tag = "4")]
+        pub bid_price: Option<Decimal>,
+        #[prost(message, optional, tag = "5")]
+        pub bid_quantity: Option<Decimal>,
+        #[prost(message, optional, tag = "6")]
+        pub ask_price: Option<Decimal>,
+        #[prost(message, optional, tag = "7")]
+        pub ask_quantity: Option<Decimal>,
+        #[prost(string, tag = "8")]
+        pub source: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Trade {
+        #[prost(int64, tag = "1")]
+        pub received_time: i64,
+        #[prost(int64, tag = "2")]
+        pub event_time: i64,
+        #[prost(message, optional, tag = "3")]
+        pub instrument: Option<InstrumentRef>,
+        #[prost(uint64, tag = "4")]
+        pub trade_id: u64,
+        #[prost(message, optional, tag = "5")]
+        pub price: Option<Decimal>,
+        #[prost(message, optional, tag = "6")]
+        pub quantity: Option<Decimal>,
+        #[prost(string, tag = "7")]
+        pub source: String,
+    }
+}
+
+fn invalid(message: impl Into<String>) -> IngestorError {
+    IngestorError::InvalidMessage(message.into())
+}
+
+fn time(nanos: i64) -> Result<OffsetDateTime, IngestorError> {
+    OffsetDateTime::from_unix_timestamp_nanos(nanos.into()).map_err(|e| invalid(e.to_string()))
+}
+
+fn decimal(value: Option<proto::Decimal>) -> Result<Decimal, IngestorError> {
+    let value = value.ok_or_else(|| invalid("missing decimal"))?;
+    Decimal::try_from_i128_with_scale(value.mantissa.into(), value.scale).map_err(|e| invalid(e.to_string()))
+}
+
+fn instrument(
+    instruments: &InstrumentRegistry,
+    instrument: Option<proto::InstrumentRef>,
+) -> Result<Instrument, IngestorError> {
+    let instrument = instrument.ok_or_else(|| invalid("missing instrument"))?;
+    let venue = instrument.venue.parse::<Venue>().map_err(|e| invalid(e.to_string()))?;
+    let instrument_type = instrument
+        .instrument_type
+        .parse::<InstrumentType>()
+        .map_err(|e| invalid(e.to_string()))?;
+    instruments
+        .by_symbol(&venue, &instrument.symbol)
+        .into_iter()
+        .find(|i| i.instrument_type() == &instrument_type)
+        .ok_or(IngestorError::UnknownSymbol {
+            venue,
+            symbol: instrument.symbol,
+        })
+}
+
+/// Tick or trade of a protobuf `MarketEvent`, the instrument has to be in the registry
+pub fn decode_market_event(instruments: &InstrumentRegistry, payload: &[u8]) -> Result<Event, IngestorError> {
+    let event = proto::MarketEvent::decode(payload)?;
+    match event.event.ok_or_else(|| invalid("empty market event"))? {
+        proto::market_event::Event::Tick(tick) => Ok(Event::Tick(Tick {
+            event_time: time(tick.event_time)?,
+            instrument: instrument(instruments, tick.instrument)?,
+            tick_id: tick.tick_id,
+            bid_price: decimal(tick.bid_price)?.into(),
+            bid_quantity: decimal(tick.bid_quantity)?.into(),
+            ask_price: decimal(tick.ask_price)?.into(),
+            ask_quantity: decimal(tick.ask_quantity)?.into(),
+            source: tick.source.parse()?,
+        })),
+        proto::market_event::Event::Trade(trade) => Ok(Event::Trade(Trade {
+            received_time: time(trade.received_time)?,
+            event_time: time(trade.event_time)?,
+            instrument: instrument(instruments, trade.instrument)?,
+            trade_id: trade.trade_id,
+            price: decimal(trade.price)?.into(),
+            quantity: decimal(trade.quantity)?.into(),
+            source: trade.source.parse()?,
+        })),
+    }
+}
+
+/// The protobuf `MarketEvent` of a tick or trade, none for other events, unknown instruments and decimals
+/// beyond 64 bits
+pub fn encode_market_event(instruments: &InstrumentRegistry, event: &Event) -> Option<Vec<u8>> {
+    let reference = |instrument: &Instrument| {
+        Some(proto::InstrumentRef {
+            venue: instrument.venue().to_string(),
+            symbol: instruments.get(instrument)?.symbol,
+            instrument_type: instrument.instrument_type().to_string(),
+        })
+    };
+    let decimal = |value: Decimal| {
+        Some(proto::Decimal {
+            mantissa: value.mantissa().try_into().ok()?,
+            scale: value.scale(),
+        })
+    };
+    let nanos = |time: OffsetDateTime| i64::try_from(time.unix_timestamp_nanos()).ok();
+    let event = match event {
+        Event::Tick(tick) => proto::market_event::Event::Tick(proto::Tick {
+            event_time: nanos(tick.event_time)?,
+            instrument: Some(reference(&tick.instrument)?),
+            tick_id: tick.tick_id,
+            bid_price: Some(decimal(tick.bid_price.value())?),
+            bid_quantity: Some(decimal(tick.bid_quantity.value())?),
+            ask_price: Some(decimal(tick.ask_price.value())?),
+            ask_quantity: Some(decimal(tick.ask_quantity.value())?),
+            source: tick.source.to_string(),
+        }),
+        Event::Trade(trade) => proto::market_event::Event::Trade(proto::Trade {
+            received_time: nanos(trade.received_time)?,
+            event_time: nanos(trade.event_time)?,
+            instrument: Some(reference(&trade.instrument)?),
+            trade_id: trade.trade_id,
+            price: Some(decimal(trade.price.value())?),
+            quantity: Some(decimal(trade.quantity.value())?),
+            source: trade.source.to_string(),
+        }),
+        _ => return None,
+    };
+    Some(proto::MarketEvent { event: Some(event) }.encode_to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::InstrumentConfig, ingestors::IngestorID, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_market_event_round_trip() {
+        let instrument = test_utils::test_perp_instrument();
+        let instruments = InstrumentRegistry::from_config(&[InstrumentConfig {
+            venue: Venue::Binance,
+            symbol: "BTCUSDT".into(),
+            instrument_type: InstrumentType::Perpetual,
+            base: "btc".into(),
+            quote: "usdt".into(),
+            maturity: None,
+            strike: None,
+            option_type: None,
+            tick_size: Decimal::new(1, 1),
+            lot_size: Decimal::new(1, 3),
+            min_notional: Decimal::ZERO,
+            contract_multiplier: Decimal::ONE,
+            settlement: "usdt".into(),
+        }]);
+        let time = datetime!(2024-01-01 00:00:00.123).assume_utc();
+        let trade = Event::Trade(Trade::new(
+            time,
+            time,
+            instrument.clone(),
+            7,
+            Decimal::new(1005, 1).into(),
+            Decimal::new(-25, 2).into(),
+            IngestorID::Binance,
+        ));
+        let payload = encode_market_event(&instruments, &trade).unwrap();
+        let Event::Trade(decoded) = decode_market_event(&instruments, &payload).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert!(decoded.instrument == instrument);
+        assert_eq!(decoded.event_time, time);
+        assert_eq!(decoded.quantity.value(), Decimal::new(-25, 2));
+
+        // Smaller than the json of the same trade
+        assert!(payload.len() < serde_json::to_vec(&trade).unwrap().len() / 2);
+        assert!(matches!(
+            decode_market_event(&InstrumentRegistry::default(), &payload),
+            Err(IngestorError::UnknownSymbol { .. })
+        ));
+        assert!(decode_market_event(&instruments, b"garbage").is_err());
+    }
+}
@@ -1,3 +1,9 @@
 mod binance;
+mod bybit;
+mod coinbase;
+mod kafka;
 
 pub use binance::*;
+pub use bybit::*;
+pub use coinbase::*;
+pub use kafka::*;
@@ -0,0 +1,102 @@
+use flume::{Receiver, Sender, TrySendError};
+
+use crate::{
+    config::{BackpressureConfig, OverflowPolicy},
+    metrics::METRICS,
+};
+
+use super::IngestorError;
+
+/// Sending half of a bounded ingestor queue, a full queue is handled by the overflow policy
+pub struct QueueSender<T> {
+    name: &'static str,
+    policy: OverflowPolicy,
+    tx: Sender<T>,
+    /// Only kept to evict the oldest message
+    rx: Option<Receiver<T>>,
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            policy: self.policy,
+            tx: self.tx.clone(),
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+pub fn queue<T>(name: &'static str, config: &BackpressureConfig) -> (QueueSender<T>, Receiver<T>) {
+    let (tx, rx) = flume::bounded(config.capacity);
+    let sender = QueueSender {
+        name,
+        policy: config.overflow,
+        tx,
+        rx: (config.overflow == OverflowPolicy::DropOldest).then(|| rx.clone()),
+    };
+    (sender, rx)
+}
+
+impl<T> QueueSender<T> {
+    pub async fn send(&self, msg: T) -> Result<(), IngestorError> {
+        match self.policy {
+            OverflowPolicy::Block => self.tx.send_async(msg).await?,
+            OverflowPolicy::DropNewest => match self.tx.try_send(msg) {
+                Ok(_) => {}
+                Err(TrySendError::Full(_)) => self.dropped(),
+                Err(TrySendError::Disconnected(_)) => return Err(IngestorError::ChannelClosed),
+            },
+            OverflowPolicy::DropOldest => {
+                let mut msg = msg;
+                loop {
+                    match self.tx.try_send(msg) {
+                        Ok(_) => break,
+                        Err(TrySendError::Full(m)) => {
+                            if let Some(Ok(_)) = self.rx.as_ref().map(|rx| rx.try_recv()) {
+                                self.dropped();
+                            }
+                            msg = m;
+                        }
+                        Err(TrySendError::Disconnected(_)) => return Err(IngestorError::ChannelClosed),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn dropped(&self) {
+        METRICS.ingest_dropped.with_label_values(&[self.name]).inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_overflow() {
+        for (overflow, expected) in [
+            (OverflowPolicy::DropNewest, vec![0, 1]),
+            (OverflowPolicy::DropOldest, vec![2, 3]),
+        ] {
+            let (tx, rx) = queue(
+                "test",
+                &BackpressureConfig {
+                    capacity: 2,
+                    overflow,
+                },
+            );
+            for i in 0..4 {
+                tx.send(i).await.unwrap();
+            }
+            assert_eq!(rx.drain().collect::<Vec<_>>(), expected);
+        }
+        assert_eq!(METRICS.ingest_dropped.with_label_values(&["test"]).get(), 4);
+
+        let (tx, rx) = queue("test_block", &BackpressureConfig::default());
+        drop(rx);
+        assert!(tx.send(1).await.is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::models::{DataGap, Event, Instrument};
+
+use super::IngestorID;
+
+#[derive(Debug, PartialEq)]
+pub enum SequenceCheck {
+    InOrder,
+    /// Older than or the same as the last id of the instrument, e.g. replayed after a reconnect
+    OutOfOrder {
+        last_id: u64,
+        id: u64,
+    },
+    Gap {
+        first_missing_id: u64,
+        last_missing_id: u64,
+    },
+}
+
+/// Follows the trade and tick ids of every instrument. Trade ids are consecutive per instrument, so
+/// a jump means trades were missed. Tick ids only increase, which leaves out of order delivery
+#[derive(Default)]
+pub struct SequenceTracker {
+    trades: HashMap<Instrument, u64>,
+    ticks: HashMap<Instrument, u64>,
+}
+
+impl SequenceTracker {
+    pub fn check(&mut self, event: &Event) -> SequenceCheck {
+        match event {
+            Event::Trade(trade) => {
+                let Some(last_id) = self.trades.get_mut(&trade.instrument) else {
+                    self.trades.insert(trade.instrument.clone(), trade.trade_id);
+                    return SequenceCheck::InOrder;
+                };
+                let id = trade.trade_id;
+                if id <= *last_id {
+                    return SequenceCheck::OutOfOrder {
+                        last_id: *last_id,
+                        id,
+                    };
+                }
+                let check = if id == *last_id + 1 {
+                    SequenceCheck::InOrder
+                } else {
+                    SequenceCheck::Gap {
+                        first_missing_id: *last_id + 1,
+                        last_missing_id: id - 1,
+                    }
+                };
+                *last_id = id;
+                check
+            }
+            Event::Tick(tick) => {
+                let last_id = self.ticks.entry(tick.instrument.clone()).or_default();
+                if tick.tick_id <= *last_id {
+                    return SequenceCheck::OutOfOrder {
+                        last_id: *last_id,
+                        id: tick.tick_id,
+                    };
+                }
+                *last_id = tick.tick_id;
+                SequenceCheck::InOrder
+            }
+            _ => SequenceCheck::InOrder,
+        }
+    }
+
+    /// The gap as an event, none for events that are in order
+    pub fn gap(event: &Event, check: &SequenceCheck, source: IngestorID) -> Option<DataGap> {
+        let SequenceCheck::Gap {
+            first_missing_id,
+            last_missing_id,
+        } = *check
+        else {
+            return None;
+        };
+        Some(DataGap {
+            event_time: *event.event_time(),
+            instrument: event.instrument()?.clone(),
+            first_missing_id,
+            last_missing_id,
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::Trade, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_trade_gaps() {
+        let instrument = test_utils::test_perp_instrument();
+        let trade = |id| {
+            let time = datetime!(2024-01-01 00:00:00).assume_utc();
+            Event::Trade(Trade::new(
+                time,
+                time,
+                instrument.clone(),
+                id,
+                100.0.into(),
+                1.0.into(),
+                IngestorID::Test,
+            ))
+        };
+
+        let mut tracker = SequenceTracker::default();
+        assert_eq!(tracker.check(&trade(10)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&trade(11)), SequenceCheck::InOrder);
+        let check = tracker.check(&trade(15));
+        assert_eq!(
+            check,
+            SequenceCheck::Gap {
+                first_missing_id: 12,
+                last_missing_id: 14
+            }
+        );
+        let gap = SequenceTracker::gap(&trade(15), &check, IngestorID::Test).unwrap();
+        assert_eq!(gap.missing(), 3);
+
+        // Late trades don't move the sequence back
+        assert_eq!(
+            tracker.check(&trade(13)),
+            SequenceCheck::OutOfOrder {
+                last_id: 15,
+                id: 13
+            }
+        );
+        assert_eq!(tracker.check(&trade(16)), SequenceCheck::InOrder);
+    }
+}
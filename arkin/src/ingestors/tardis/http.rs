@@ -1,6 +1,5 @@
 use bytes::Bytes;
 
-use anyhow::Result;
 use backoff::ExponentialBackoff;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
@@ -10,6 +9,8 @@ use serde::Serialize;
 use time::OffsetDateTime;
 use tracing::debug;
 
+use crate::ingestors::IngestorError;
+
 #[derive(Debug, Clone)]
 pub struct TardisHttpClient {
     pub base_url: String,
@@ -28,7 +29,7 @@ impl TardisHttpClient {
         symbols: Vec<String>,
         date: OffsetDateTime,
         offset: i64,
-    ) -> Result<Bytes> {
+    ) -> Result<Bytes, IngestorError> {
         let url = format!("{}/{}", self.base_url, exchange);
         let query = QueryParams::new(channel, symbols, date, offset);
         let res = backoff::future::retry(ExponentialBackoff::default(), || async {
@@ -71,14 +72,14 @@ impl TardisHttpClientBuilder {
     }
 }
 
-pub fn get_client(api_secret: &Option<String>) -> Result<Client> {
+pub fn get_client(api_secret: &Option<String>) -> Result<Client, IngestorError> {
     // Set api bearer token if provided
     let headers = create_headers(api_secret)?;
     let client = Client::builder().default_headers(headers).build()?;
     Ok(client)
 }
 
-fn create_headers(api_secret: &Option<String>) -> anyhow::Result<HeaderMap> {
+fn create_headers(api_secret: &Option<String>) -> Result<HeaderMap, IngestorError> {
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_str("application/json")?);
     if let Some(api_key) = api_secret {
@@ -1,4 +1,6 @@
 mod http;
+mod normalized;
 mod service;
 
+pub use normalized::{TardisCsvIngestor, TardisCsvReader};
 pub use service::{TardisChannel, TardisExchange, TardisRequest, TardisService};
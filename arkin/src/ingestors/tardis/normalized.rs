@@ -0,0 +1,445 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufReader, Read},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use csv::{StringRecord, StringRecordsIntoIter};
+use flate2::read::GzDecoder;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use time::OffsetDateTime;
+use tokio::select;
+use tracing::{error, info};
+
+use crate::{
+    bus::EventBus,
+    config::{TardisCsvIngestorConfig, TardisDataType},
+    ingestors::{file::Pace, Ingestor, IngestorError, IngestorID},
+    metrics::METRICS,
+    models::{
+        BookSide, BookUpdate, Event, Instrument, InstrumentRegistry, InstrumentType, OrderBook, Side, Tick, Trade,
+        Venue,
+    },
+    shutdown::ShutdownSignal,
+    utils::{self, custom_serde},
+};
+
+// exchange,symbol,timestamp,local_timestamp,id,side,price,amount
+#[derive(Debug, Deserialize)]
+struct TardisTrade {
+    exchange: String,
+    symbol: String,
+    #[serde(with = "custom_serde::timestamp")]
+    timestamp: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp")]
+    local_timestamp: OffsetDateTime,
+    id: String,
+    /// Taker side, `unknown` for venues that don't report it
+    side: String,
+    price: Decimal,
+    amount: Decimal,
+}
+
+// exchange,symbol,timestamp,local_timestamp,ask_amount,ask_price,bid_price,bid_amount
+#[derive(Debug, Deserialize)]
+struct TardisQuote {
+    exchange: String,
+    symbol: String,
+    #[serde(with = "custom_serde::timestamp")]
+    timestamp: OffsetDateTime,
+    ask_amount: Option<Decimal>,
+    ask_price: Option<Decimal>,
+    bid_price: Option<Decimal>,
+    bid_amount: Option<Decimal>,
+}
+
+// exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount
+#[derive(Debug, Deserialize)]
+struct TardisBookLevel {
+    exchange: String,
+    symbol: String,
+    #[serde(with = "custom_serde::timestamp")]
+    timestamp: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp")]
+    local_timestamp: OffsetDateTime,
+    is_snapshot: bool,
+    side: String,
+    price: Decimal,
+    amount: Decimal,
+}
+
+#[derive(Default)]
+struct TardisBook {
+    book: OrderBook,
+    in_snapshot: bool,
+}
+
+/// One update of the book, rows of the same instrument and timestamp belong to the same update
+struct PendingUpdate {
+    instrument: Instrument,
+    event_time: OffsetDateTime,
+    received_time: OffsetDateTime,
+}
+
+/// Reads one normalized tardis csv file as events in file order
+pub struct TardisCsvReader {
+    records: StringRecordsIntoIter<Box<dyn Read + Send>>,
+    headers: StringRecord,
+    data_type: TardisDataType,
+    depth: usize,
+    instruments: Arc<InstrumentRegistry>,
+    resolved: HashMap<(String, String), Instrument>,
+    books: HashMap<Instrument, TardisBook>,
+    pending: Option<PendingUpdate>,
+    events: VecDeque<Event>,
+    next_id: u64,
+}
+
+impl TardisCsvReader {
+    pub fn new(
+        reader: Box<dyn Read + Send>,
+        data_type: TardisDataType,
+        depth: usize,
+        instruments: Arc<InstrumentRegistry>,
+    ) -> Result<Self, IngestorError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let headers = reader.headers()?.clone();
+        Ok(Self {
+            records: reader.into_records(),
+            headers,
+            data_type,
+            depth,
+            instruments,
+            resolved: HashMap::new(),
+            books: HashMap::new(),
+            pending: None,
+            events: VecDeque::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Tardis publishes the data sets gzipped, other files are read as they are
+    pub fn open(
+        path: &str,
+        data_type: TardisDataType,
+        depth: usize,
+        instruments: Arc<InstrumentRegistry>,
+    ) -> Result<Self, IngestorError> {
+        let file = BufReader::new(File::open(path)?);
+        let reader: Box<dyn Read + Send> = if path.ends_with(".gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Self::new(reader, data_type, depth, instruments)
+    }
+
+    /// The exchange names of tardis tell the market apart where the symbols are the same
+    fn instrument(&mut self, exchange: &str, symbol: &str) -> Result<Instrument, IngestorError> {
+        if let Some(instrument) = self.resolved.get(&(exchange.to_owned(), symbol.to_owned())) {
+            return Ok(instrument.clone());
+        }
+        let (venue, spot) = match exchange {
+            "binance" => (Venue::Binance, true),
+            "binance-futures" => (Venue::Binance, false),
+            "coinbase" => (Venue::Coinbase, true),
+            "bybit" => (Venue::Bybit, false),
+            "bybit-spot" => (Venue::Bybit, true),
+            _ => return Err(IngestorError::UnknownExchange(exchange.to_owned())),
+        };
+        let instrument = self
+            .instruments
+            .by_symbol(&venue, symbol)
+            .into_iter()
+            .find(|i| (i.instrument_type() == &InstrumentType::Spot) == spot)
+            .ok_or_else(|| IngestorError::UnknownSymbol {
+                venue,
+                symbol: symbol.to_owned(),
+            })?;
+        self.resolved
+            .insert((exchange.to_owned(), symbol.to_owned()), instrument.clone());
+        Ok(instrument)
+    }
+
+    fn process(&mut self, record: StringRecord) -> Result<(), IngestorError> {
+        match self.data_type {
+            TardisDataType::Trades => {
+                let row = record.deserialize::<TardisTrade>(Some(&self.headers))?;
+                let instrument = self.instrument(&row.exchange, &row.symbol)?;
+                let id = row.id.parse().unwrap_or_else(|_| utils::fnv1a(&row.id));
+                let amount = match row.side.as_str() {
+                    "sell" => Side::Sell.signed(row.amount),
+                    _ => row.amount,
+                };
+                self.events.push_back(Event::Trade(Trade::new(
+                    row.local_timestamp,
+                    row.timestamp,
+                    instrument,
+                    id,
+                    row.price.into(),
+                    amount.into(),
+                    IngestorID::Tardis,
+                )));
+            }
+            TardisDataType::Quotes => {
+                let row = record.deserialize::<TardisQuote>(Some(&self.headers))?;
+                // One sided quotes happen when a side of the book is empty, they make no tick
+                let (Some(bid_price), Some(bid_amount), Some(ask_price), Some(ask_amount)) =
+                    (row.bid_price, row.bid_amount, row.ask_price, row.ask_amount)
+                else {
+                    return Ok(());
+                };
+                let instrument = self.instrument(&row.exchange, &row.symbol)?;
+                let mut tick = Tick::new(
+                    row.timestamp,
+                    instrument,
+                    self.next_id,
+                    bid_price.into(),
+                    bid_amount.into(),
+                    ask_price.into(),
+                    ask_amount.into(),
+                );
+                tick.source = IngestorID::Tardis;
+                self.next_id += 1;
+                self.events.push_back(Event::Tick(tick));
+            }
+            TardisDataType::IncrementalBookL2 => {
+                let row = record.deserialize::<TardisBookLevel>(Some(&self.headers))?;
+                let instrument = self.instrument(&row.exchange, &row.symbol)?;
+                let next = self
+                    .pending
+                    .as_ref()
+                    .is_some_and(|p| p.instrument != instrument || p.event_time != row.timestamp);
+                if next {
+                    self.flush();
+                }
+
+                let book = self.books.entry(instrument.clone()).or_default();
+                // A snapshot replaces the book, it starts at its first row
+                if row.is_snapshot && !book.in_snapshot {
+                    book.book.clear();
+                }
+                book.in_snapshot = row.is_snapshot;
+                let side = if row.side == "bid" {
+                    BookSide::Bid
+                } else {
+                    BookSide::Ask
+                };
+                book.book.update(side, row.price, row.amount);
+                self.pending = Some(PendingUpdate {
+                    instrument,
+                    event_time: row.timestamp,
+                    received_time: row.local_timestamp,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish the book of the update that is complete
+    fn flush(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let Some(book) = self.books.get(&pending.instrument) else {
+            return;
+        };
+        let (bids, asks) = book.book.levels(self.depth);
+        self.events.push_back(Event::BookUpdate(BookUpdate {
+            received_time: pending.received_time,
+            event_time: pending.event_time,
+            instrument: pending.instrument,
+            update_id: self.next_id,
+            bids,
+            asks,
+            source: IngestorID::Tardis,
+        }));
+        self.next_id += 1;
+    }
+}
+
+impl Iterator for TardisCsvReader {
+    type Item = Result<Event, IngestorError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.records.next() {
+                Some(Ok(record)) => {
+                    if let Err(e) = self.process(record) {
+                        return Some(Err(e));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e.into())),
+                None if self.pending.is_some() => self.flush(),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// Hand the events of all readers to `emit` ordered by event time until it returns false. Every
+/// file is sorted on its own, so the next event is always at the head of one of the readers
+pub fn merge_events(
+    mut readers: Vec<TardisCsvReader>,
+    mut emit: impl FnMut(Event) -> bool,
+) -> Result<(), IngestorError> {
+    let mut heads = readers
+        .iter_mut()
+        .map(|r| r.next().transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    loop {
+        let next = heads
+            .iter()
+            .enumerate()
+            .filter_map(|(i, head)| head.as_ref().map(|e| (i, *e.event_time())))
+            .min_by_key(|(_, time)| *time);
+        let Some((i, _)) = next else {
+            return Ok(());
+        };
+        let event = std::mem::replace(&mut heads[i], readers[i].next().transpose()?);
+        if !event.is_some_and(&mut emit) {
+            return Ok(());
+        }
+    }
+}
+
+/// Replays normalized tardis data sets through the bus as if they arrived live
+#[derive(Clone)]
+pub struct TardisCsvIngestor {
+    bus: Arc<EventBus>,
+    instruments: Arc<InstrumentRegistry>,
+    config: TardisCsvIngestorConfig,
+}
+
+impl TardisCsvIngestor {
+    pub fn new(bus: Arc<EventBus>, instruments: Arc<InstrumentRegistry>, config: &TardisCsvIngestorConfig) -> Self {
+        Self {
+            bus,
+            instruments,
+            config: config.to_owned(),
+        }
+    }
+
+    async fn replay(&self) -> Result<usize, IngestorError> {
+        let readers = self
+            .config
+            .files
+            .iter()
+            .map(|f| TardisCsvReader::open(&f.path, f.data_type, self.config.depth, self.instruments.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (tx, rx) = flume::bounded(10000);
+        let reader = tokio::task::spawn_blocking(move || merge_events(readers, |e| tx.send(e).is_ok()));
+
+        let mut pace = Pace::new(self.config.speed);
+        let mut replayed = 0;
+        while let Ok(event) = rx.recv_async().await {
+            pace.wait(*event.event_time()).await;
+            METRICS.ingested_events.with_label_values(&["tardis"]).inc();
+            self.bus.publish_event(event);
+            replayed += 1;
+        }
+        reader.await.map_err(|_| IngestorError::ChannelClosed)??;
+        Ok(replayed)
+    }
+}
+
+#[async_trait]
+impl Ingestor for TardisCsvIngestor {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
+        info!("Starting tardis csv ingestor...");
+        // Dropping the replay stops the readers at their next event
+        let res = select! {
+            res = self.replay() => res,
+            _ = shutdown.wait() => {
+                info!("Stopped replaying the tardis data sets");
+                return;
+            }
+        };
+        match res {
+            Ok(replayed) => info!("Replayed {} tardis events", replayed),
+            Err(e) => error!("Failed to replay the tardis data sets: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    fn reader(data_type: TardisDataType, csv: &'static str) -> TardisCsvReader {
+        let instruments = Arc::new(InstrumentRegistry::from_config(&config::load().instruments));
+        TardisCsvReader::new(Box::new(csv.as_bytes()), data_type, 2, instruments).unwrap()
+    }
+
+    #[test]
+    fn test_merge_trades_and_book() {
+        let trades = reader(
+            TardisDataType::Trades,
+            "exchange,symbol,timestamp,local_timestamp,id,side,price,amount
+binance-futures,BTCUSDT,1717200000150000,1717200000151000,42,sell,67000.5,0.25
+",
+        );
+        let book = reader(
+            TardisDataType::IncrementalBookL2,
+            "exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount
+binance-futures,BTCUSDT,1717200000100000,1717200000101000,true,bid,67000,1
+binance-futures,BTCUSDT,1717200000100000,1717200000101000,true,bid,66999,2
+binance-futures,BTCUSDT,1717200000100000,1717200000101000,true,bid,66998,3
+binance-futures,BTCUSDT,1717200000100000,1717200000101000,true,ask,67001,1
+binance-futures,BTCUSDT,1717200000200000,1717200000201000,false,bid,67000,0
+binance-futures,BTCUSDT,1717200000200000,1717200000201000,false,ask,67000.5,4
+",
+        );
+
+        let mut events = Vec::new();
+        merge_events(vec![trades, book], |e| {
+            events.push(e);
+            true
+        })
+        .unwrap();
+        assert_eq!(events.len(), 3);
+
+        // The snapshot is one update cut to the depth, then the trade and the next update
+        let Event::BookUpdate(snapshot) = &events[0] else {
+            panic!("Expected a book update");
+        };
+        assert_eq!(snapshot.bids.len(), 2);
+        assert_eq!(snapshot.best_bid().unwrap().price.value(), Decimal::from(67000));
+        let Event::Trade(trade) = &events[1] else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(trade.trade_id, 42);
+        assert_eq!(trade.aggressor(), Side::Sell);
+        assert_eq!(trade.event_time.unix_timestamp_nanos(), 1717200000150000000);
+        let Event::BookUpdate(update) = &events[2] else {
+            panic!("Expected a book update");
+        };
+        assert_eq!(update.best_bid().unwrap().price.value(), Decimal::from(66999));
+        assert_eq!(update.best_ask().unwrap().price.value(), Decimal::new(670005, 1));
+    }
+
+    #[test]
+    fn test_one_sided_quotes_are_skipped() {
+        let quotes = reader(
+            TardisDataType::Quotes,
+            "exchange,symbol,timestamp,local_timestamp,ask_amount,ask_price,bid_price,bid_amount
+binance-futures,BTCUSDT,1717200000100000,1717200000101000,1,67001,67000,2
+binance-futures,BTCUSDT,1717200000200000,1717200000201000,,,67000,2
+",
+        );
+        let events = quotes.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events.len(), 1);
+        let Event::Tick(tick) = &events[0] else {
+            panic!("Expected a tick");
+        };
+        assert_eq!(tick.ask_price.value(), Decimal::from(67001));
+    }
+}
@@ -1,6 +1,3 @@
-use anyhow::bail;
-use anyhow::Error;
-use anyhow::Result;
 use bytes::Bytes;
 use futures_util::stream;
 use futures_util::Future;
@@ -18,6 +15,7 @@ use tracing::debug;
 use tracing::error;
 
 use crate::config::TardisIngestorConfig;
+use crate::ingestors::IngestorError;
 use crate::utils;
 
 use super::http::TardisHttpClient;
@@ -54,7 +52,7 @@ impl Display for TardisExchange {
 }
 
 impl FromStr for TardisExchange {
-    type Err = Error;
+    type Err = IngestorError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
@@ -66,27 +64,27 @@ impl FromStr for TardisExchange {
             "okex-swaps" => Ok(TardisExchange::OkxSwap),
             "okex-futures" => Ok(TardisExchange::OkxFutures),
             "okex-options" => Ok(TardisExchange::OkxOptions),
-            _ => Err(Error::msg("invalid exchange")),
+            _ => Err(IngestorError::UnknownExchange(s.into())),
         }
     }
 }
 
 impl TardisExchange {
-    pub fn channel_str(&self, channel: &TardisChannel) -> Result<String> {
+    pub fn channel_str(&self, channel: &TardisChannel) -> Result<String, IngestorError> {
         match self {
             TardisExchange::BinanceUSDM => match channel {
                 TardisChannel::Book => Ok("depth".to_string()),
                 TardisChannel::Trade => Ok("trade".to_string()),
                 TardisChannel::AggTrade => Ok("aggTrade".to_string()),
                 TardisChannel::Tick => Ok("bookTicker".to_string()),
-                _ => bail!("Channel not supported for Binance exchange"),
+                _ => Err(self.unsupported(channel)),
             },
             TardisExchange::BinanceOptions => match channel {
                 TardisChannel::Book => Ok("depth100".to_string()),
                 TardisChannel::Trade => Ok("trade".to_string()),
                 TardisChannel::Tick => Ok("ticker".to_string()),
                 TardisChannel::OpenInterest => Ok("openInterest".to_string()),
-                _ => bail!("Channel not supported for Binance exchange".to_string()),
+                _ => Err(self.unsupported(channel)),
             },
             TardisExchange::OkxSwap => match channel {
                 TardisChannel::Book => Ok("books".to_string()),
@@ -94,16 +92,23 @@ impl TardisExchange {
                 TardisChannel::Tick => Ok("tickers".to_string()),
                 TardisChannel::OpenInterest => Ok("open-interest".to_string()),
                 TardisChannel::FundingRate => Ok("funding-rate".to_string()),
-                _ => bail!("Channel not supported for Okex exchange"),
+                _ => Err(self.unsupported(channel)),
             },
             TardisExchange::OkxOptions => match channel {
                 TardisChannel::Book => Ok("books".to_string()),
                 TardisChannel::Trade => Ok("trades-all".to_string()),
                 TardisChannel::Tick => Ok("opt-summary".to_string()),
                 TardisChannel::OpenInterest => Ok("open-interest".to_string()),
-                _ => bail!("Channel not supported for Okex exchange"),
+                _ => Err(self.unsupported(channel)),
             },
-            _ => bail!("Exchange not supported yet"),
+            _ => Err(self.unsupported(channel)),
+        }
+    }
+
+    fn unsupported(&self, channel: &TardisChannel) -> IngestorError {
+        IngestorError::UnsupportedChannel {
+            exchange: self.to_string(),
+            channel: channel.to_string(),
         }
     }
 }
@@ -142,7 +147,7 @@ impl fmt::Display for TardisChannel {
 }
 
 impl FromStr for TardisChannel {
-    type Err = Error;
+    type Err = IngestorError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
@@ -155,7 +160,7 @@ impl FromStr for TardisChannel {
             "snapshots" => Ok(TardisChannel::Snapshot),
             "open-interest" => Ok(TardisChannel::OpenInterest),
             "funding" => Ok(TardisChannel::FundingRate),
-            _ => Err(Error::msg("invalid channel")),
+            _ => Err(IngestorError::UnknownChannel(s.into())),
         }
     }
 }
@@ -245,7 +250,7 @@ impl TardisService {
     pub fn download_stream(
         &self,
         req: TardisRequest,
-    ) -> impl Stream<Item = impl Future<Output = Result<Vec<(OffsetDateTime, String)>>> + '_> + '_ {
+    ) -> impl Stream<Item = impl Future<Output = Result<Vec<(OffsetDateTime, String)>, IngestorError>> + '_> + '_ {
         let dates = utils::datetime_range_minute(&req.start, &req.end).expect("Invalid date range");
         stream::iter(dates.into_iter().map(move |datetime| {
             let client = self.client.clone();
@@ -322,7 +327,7 @@ impl TardisService {
     }
 }
 
-fn parse_line(line: &str) -> Result<(OffsetDateTime, String)> {
+fn parse_line(line: &str) -> Result<(OffsetDateTime, String), IngestorError> {
     let mut parts = line.splitn(2, ' ');
 
     // Timestamp part
@@ -338,7 +343,7 @@ fn parse_line(line: &str) -> Result<(OffsetDateTime, String)> {
 
     let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond]Z");
     let Ok(ts) = time::PrimitiveDateTime::parse(timestamp, format) else {
-        bail!("Invalid timestamp: {}", &timestamp);
+        return Err(IngestorError::InvalidTimestamp(timestamp.into()));
     };
     let ts = ts.assume_utc();
 
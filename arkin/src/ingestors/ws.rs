@@ -1,107 +1,595 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use anyhow::Result;
 use async_tungstenite::{
     stream::Stream,
     tokio::{connect_async, TokioAdapter},
     tungstenite::Message,
     WebSocketStream,
 };
-use flume::Sender;
 use futures_util::{SinkExt, StreamExt};
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use time::OffsetDateTime;
 use tokio::{
     net::TcpStream,
     select,
-    sync::{OwnedSemaphorePermit, Semaphore},
-    time::sleep,
+    sync::{
+        broadcast::{self, error::RecvError},
+        Notify,
+    },
+    task::JoinSet,
+    time::{interval_at, sleep, timeout, Instant},
 };
 use tokio_rustls::client::TlsStream;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use url::Url;
 
-use crate::utils::Deduplicator;
+use crate::{
+    config::{BackpressureConfig, ReconnectConfig},
+    metrics::METRICS,
+    shutdown::ShutdownSignal,
+    utils::Deduplicator,
+};
+
+use super::{
+    queue::{queue, QueueSender},
+    IngestorError,
+};
+
+/// How long a closing connection waits for the venue to answer the close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// (Un)subscribe request for a set of channels, framed by the protocol of the venue
+#[derive(Clone)]
+pub struct Subscription {
+    pub id: u64,
+    pub subscribe: bool,
+    pub channels: Vec<String>,
+}
+
+/// Answer of the venue to a subscription request
+pub struct SubscriptionResponse {
+    /// Venues that don't echo the id answer the oldest open request
+    pub id: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// A request the venue rejected or never answered, its channels receive nothing
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionFailure {
+    pub id: u64,
+    pub channels: Vec<String>,
+    pub reason: String,
+}
+
+impl fmt::Display for SubscriptionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request {} for {:?} failed: {}", self.id, self.channels, self.reason)
+    }
+}
+
+/// How a venue frames subscriptions and answers them
+pub trait WsProtocol: Send + Sync {
+    fn request(&self, subscription: &Subscription) -> Message;
+
+    /// Parses the text if it is a response to a request, data messages return none
+    fn response(&self, text: &str) -> Option<SubscriptionResponse>;
+
+    /// Application level heartbeat sent at the interval, for venues that drop idle connections
+    /// despite websocket pings
+    fn ping(&self) -> Option<(Duration, Message)> {
+        None
+    }
+
+    /// Answers to the heartbeat are neither data nor subscription responses
+    fn is_pong(&self, _text: &str) -> bool {
+        false
+    }
+
+    /// Url carrying the channels of the shard for venues that stream them from the url, they are
+    /// not subscribed again once connected
+    fn url(&self, _url: &Url, _channels: &[String]) -> Option<Url> {
+        None
+    }
+}
+
+/// Message counts, bytes, parse failures and latency per connection of an ingestor, so a degraded connection
+/// stands out from the other connections of its shard. Connections are labelled by their id.
+#[derive(Clone)]
+pub struct ConnectionMetrics {
+    ingestor: &'static str,
+}
+
+impl ConnectionMetrics {
+    pub fn new(ingestor: &'static str) -> Self {
+        Self { ingestor }
+    }
+
+    fn received(&self, connection: u64, bytes: usize) {
+        let connection = connection.to_string();
+        let labels = [self.ingestor, &connection];
+        METRICS.connection_messages.with_label_values(&labels).inc();
+        METRICS.connection_bytes.with_label_values(&labels).inc_by(bytes as u64);
+    }
 
-use super::binance::Subscription;
+    /// A message of the connection the ingestor failed to parse
+    pub fn failed(&self, connection: u64) {
+        METRICS
+            .connection_parse_errors
+            .with_label_values(&[self.ingestor, &connection.to_string()])
+            .inc();
+    }
+
+    fn subscription_failed(&self) {
+        METRICS.subscription_failures.with_label_values(&[self.ingestor]).inc();
+    }
+
+    /// Latency of a parsed message, from the venue's event time to when it was received
+    pub fn observe(&self, connection: u64, event_time: &OffsetDateTime, received_time: OffsetDateTime) {
+        METRICS
+            .connection_latency
+            .with_label_values(&[self.ingestor, &connection.to_string()])
+            .observe((received_time - *event_time).as_seconds_f64());
+    }
+}
 
 /// A WebSocket manager handles multiple WebSocket connections.
 pub struct WebSocketManager {
     pub url: Url,
 
-    /// Deduplicator
-    pub deduplicator: Deduplicator,
+    /// Number of messages remembered to drop the duplicates between connections
+    pub deduplicate_lookback: usize,
 
-    /// Limit the max number of connections.
-    ///
-    /// A `Semaphore` is used to limit the max number of connections. Before
-    /// attempo accept a new connection, a permit is acquired from the
-    /// semaphore. If none are available, the listener waits for one.
-    ///
-    /// When handlers complete processing a connection, the permit is returned
-    /// to the semaphore.
-    pub limit_connections: Arc<Semaphore>,
+    /// Number of connections streaming the same shard, duplicates are dropped by the deduplicator.
+    pub connections: usize,
+
+    /// How a dropped connection is retried
+    pub reconnect: ReconnectConfig,
+
+    /// Bounds the queue from the connections to the deduplicator
+    pub backpressure: BackpressureConfig,
+
+    subscriptions: Arc<Subscriptions>,
+
+    /// (Un)subscribe requests forwarded to the live connections of the shard
+    requests: broadcast::Sender<(usize, Subscription)>,
+
+    /// Wakes up the manager to open connections for a new shard
+    new_shard: Notify,
+
+    protocol: Arc<dyn WsProtocol>,
+
+    metrics: ConnectionMetrics,
 }
 
 impl WebSocketManager {
-    pub fn new(url: Url, connections: usize, deduplicate_lookback: usize) -> Self {
+    pub fn new(
+        protocol: Arc<dyn WsProtocol>,
+        url: Url,
+        connections: usize,
+        max_streams_per_connection: usize,
+        deduplicate_lookback: usize,
+        reconnect: ReconnectConfig,
+        backpressure: BackpressureConfig,
+    ) -> Self {
         Self {
             url,
-            deduplicator: Deduplicator::new(deduplicate_lookback),
-            limit_connections: Arc::new(Semaphore::new(connections)),
+            deduplicate_lookback,
+            connections,
+            reconnect,
+            backpressure,
+            subscriptions: Arc::new(Subscriptions::new(max_streams_per_connection)),
+            requests: broadcast::channel(64).0,
+            new_shard: Notify::new(),
+            protocol,
+            metrics: ConnectionMetrics::new("websocket"),
         }
     }
 
-    pub async fn run(&mut self, manager_tx: Sender<String>, subscription: Subscription) -> Result<()> {
-        // Use select for new data in receiver or spawn new connection on permit
+    /// Labels the metrics of the connections with the ingestor
+    pub fn with_metrics(mut self, metrics: ConnectionMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics(&self) -> &ConnectionMetrics {
+        &self.metrics
+    }
+
+    /// Adds channels to the least loaded shards, returns the ids of the requests sent
+    pub fn subscribe(&self, channels: &[String]) -> Vec<u64> {
+        let shards = self.subscriptions.shard_count();
+        let ids = self.send(self.subscriptions.request(true, channels));
+        if self.subscriptions.shard_count() > shards {
+            self.new_shard.notify_one();
+        }
+        ids
+    }
+
+    /// Removes channels from the shards owning them, returns the ids of the requests sent
+    pub fn unsubscribe(&self, channels: &[String]) -> Vec<u64> {
+        self.send(self.subscriptions.request(false, channels))
+    }
+
+    fn send(&self, requests: Vec<(usize, Subscription)>) -> Vec<u64> {
+        requests
+            .into_iter()
+            .map(|(shard, request)| {
+                let id = request.id;
+                // Without live connections the channels are subscribed once they connect
+                let _ = self.requests.send((shard, request));
+                id
+            })
+            .collect()
+    }
+
+    /// Channels owned by each shard
+    pub fn shards(&self) -> Vec<Vec<String>> {
+        self.subscriptions.shards.read().clone()
+    }
+
+    /// Ids of the requests not yet confirmed by every connection
+    pub fn pending(&self) -> Vec<u64> {
+        self.subscriptions.pending()
+    }
+
+    /// Requests the venue rejected, e.g. for invalid stream names, or that went unanswered after every attempt
+    pub fn failures(&self) -> Vec<SubscriptionFailure> {
+        self.subscriptions.failures.lock().clone()
+    }
+
+    /// Runs until every connection gave up on reconnecting or the shutdown closed them, in which case the
+    /// messages received before the close are forwarded first. Messages are tagged with the connection that
+    /// delivered them first.
+    pub async fn run(
+        &self,
+        manager_tx: QueueSender<(u64, String)>,
+        shutdown: ShutdownSignal,
+    ) -> Result<(), IngestorError> {
         info!("Starting WebSocket manager...");
-        let (sender, receiver) = flume::unbounded::<Message>();
+        let (sender, receiver) = queue::<(u64, String)>("websocket", &self.backpressure);
+        let mut deduplicator = Deduplicator::new(self.deduplicate_lookback);
 
+        let mut connections = JoinSet::new();
+        let mut sharded = 0;
         loop {
+            while !shutdown.is_triggered() && sharded < self.subscriptions.shard_count() {
+                for replica in 0..self.connections {
+                    let connection = Connection {
+                        id: (sharded * self.connections + replica) as u64,
+                        shard: sharded,
+                        url: self.url.clone(),
+                        subscriptions: self.subscriptions.clone(),
+                        protocol: self.protocol.clone(),
+                        metrics: self.metrics.clone(),
+                        requests: self.requests.subscribe(),
+                        stale_timeout: Duration::from_millis(self.reconnect.stale_timeout),
+                        backoff: Backoff::new(self.reconnect.clone()),
+                    };
+                    connections.spawn(connection.run(sender.clone(), shutdown.clone()));
+                }
+                info!("Opened {} connections for shard {}", self.connections, sharded);
+                sharded += 1;
+            }
+
             select! {
-                msg = receiver.recv_async() => {
-                    let msg = msg?;
-                    // let bin_data = msg.into_data();
-                    let data = msg.to_string();
-                    if self.deduplicator.check(&data) {
-                        manager_tx.send_async(data).await.unwrap();
+                Ok((connection, data)) = receiver.recv_async() => {
+                    if deduplicator.check(&data) {
+                        manager_tx.send((connection, data)).await?;
                     }
+                }
+                _ = self.new_shard.notified() => {}
+                res = connections.join_next() => match res {
+                    Some(Ok(Err(e))) => error!("Websocket connection stopped: {}", e),
+                    Some(_) => {}
+                    None if shutdown.is_triggered() => break,
+                    None => return Err(IngestorError::ChannelClosed),
                 },
-                permit = self.limit_connections.clone().acquire_owned() => {
-                    // This should never fail, as the semaphore is never closed.
-                    let permit = permit?;
-                    debug!("Acquired permit: {:?}", permit);
-                    match self.start_handler(permit, sender.clone(), subscription.clone()).await {
-                        Ok(_) => info!("Started new handler"),
-                        Err(e) => {
-                            error!("Failed to start new handler: {:?}", e);
-                            sleep(Duration::from_secs(5)).await;
-                        }
-                    }
+            }
+        }
+
+        for (connection, data) in receiver.drain() {
+            if deduplicator.check(&data) {
+                manager_tx.send((connection, data)).await?;
+            }
+        }
+        info!("WebSocket manager stopped");
+        Ok(())
+    }
+}
+
+/// Channels streamed by each shard and the requests the venue has not answered yet
+struct Subscriptions {
+    max_streams: usize,
+    /// Indexed by shard, every shard is streamed by its own connections
+    shards: RwLock<Vec<Vec<String>>>,
+    next_id: AtomicU64,
+    /// Keyed by connection and request id
+    pending: Mutex<HashMap<(u64, u64), Pending>>,
+    failures: Mutex<Vec<SubscriptionFailure>>,
+}
+
+struct Pending {
+    request: Subscription,
+    sent: Instant,
+    attempts: u32,
+}
+
+impl Subscriptions {
+    fn new(max_streams: usize) -> Self {
+        Self {
+            max_streams,
+            shards: RwLock::new(vec![Vec::new()]),
+            next_id: AtomicU64::new(0),
+            pending: Mutex::new(HashMap::new()),
+            failures: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn shard_count(&self) -> usize {
+        self.shards.read().len()
+    }
+
+    /// Assigns the channels to shards and builds the request per shard for the ones that changed
+    fn request(&self, subscribe: bool, channels: &[String]) -> Vec<(usize, Subscription)> {
+        let mut shards = self.shards.write();
+        let mut changed = BTreeMap::<usize, Vec<String>>::new();
+        for channel in channels {
+            let owner = shards.iter().position(|s| s.contains(channel));
+            match (subscribe, owner) {
+                (true, None) => {
+                    // New streams fill the least loaded shard, which also refills shards emptied by unsubscribes
+                    let shard = shards
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, s)| s.len() < self.max_streams)
+                        .min_by_key(|(_, s)| s.len())
+                        .map(|(i, _)| i)
+                        .unwrap_or_else(|| {
+                            shards.push(Vec::new());
+                            shards.len() - 1
+                        });
+                    shards[shard].push(channel.clone());
+                    changed.entry(shard).or_default().push(channel.clone());
+                }
+                (false, Some(shard)) => {
+                    shards[shard].retain(|c| c != channel);
+                    changed.entry(shard).or_default().push(channel.clone());
                 }
+                _ => {}
             }
         }
+
+        changed
+            .into_iter()
+            .map(|(shard, channels)| {
+                let request = Subscription {
+                    id: self.next_id(),
+                    subscribe,
+                    channels,
+                };
+                (shard, request)
+            })
+            .collect()
     }
 
-    async fn start_handler(
+    /// Subscription for the channels the shard owns now, sent when a connection (re)connects
+    fn resubscribe(&self, connection: u64, shard: usize) -> Option<Subscription> {
+        self.pending.lock().retain(|(c, _), _| *c != connection);
+        let shards = self.shards.read();
+        let channels = shards.get(shard).filter(|c| !c.is_empty())?;
+        Some(Subscription {
+            id: self.next_id(),
+            subscribe: true,
+            channels: channels.clone(),
+        })
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Sending a request again counts as another attempt
+    fn sent(&self, connection: u64, request: &Subscription) {
+        self.pending
+            .lock()
+            .entry((connection, request.id))
+            .and_modify(|pending| {
+                pending.sent = Instant::now();
+                pending.attempts += 1;
+            })
+            .or_insert_with(|| Pending {
+                request: request.clone(),
+                sent: Instant::now(),
+                attempts: 1,
+            });
+    }
+
+    /// Returns the failure when the venue rejected the request
+    fn confirm(&self, connection: u64, response: SubscriptionResponse) -> Option<SubscriptionFailure> {
+        let mut pending = self.pending.lock();
+        let id = response
+            .id
+            .or_else(|| pending.keys().filter(|(c, _)| *c == connection).map(|(_, id)| *id).min());
+        let Some(Pending { request, .. }) = id.and_then(|id| pending.remove(&(connection, id))) else {
+            warn!(connection, "Response to unknown request {:?}", response.id);
+            return None;
+        };
+        drop(pending);
+        match response.error {
+            Some(reason) => self.fail(request, reason),
+            None => {
+                info!(connection, "Request {} for {:?} confirmed", request.id, request.channels);
+                None
+            }
+        }
+    }
+
+    /// Requests of the connection unanswered for longer than the timeout, those with attempts left are to be
+    /// sent again and the others are given up on
+    fn expired(
         &self,
-        permit: OwnedSemaphorePermit,
-        sender: Sender<Message>,
-        subscription: Subscription,
-    ) -> Result<()> {
-        let mut handle = Handler::new(&self.url, sender, subscription).await?;
-        tokio::spawn(async move {
-            if let Err(err) = handle.run().await {
-                error!("Websocket handler: {:?}", err);
+        connection: u64,
+        timeout: Duration,
+        max_attempts: u32,
+    ) -> (Vec<Subscription>, Vec<SubscriptionFailure>) {
+        let mut pending = self.pending.lock();
+        let expired = pending
+            .iter()
+            .filter(|((c, _), p)| *c == connection && p.sent.elapsed() >= timeout)
+            .map(|(key, p)| (*key, p.attempts < max_attempts))
+            .collect::<Vec<_>>();
+        let mut retries = Vec::new();
+        let mut given_up = Vec::new();
+        for (key, retry) in expired {
+            if retry {
+                retries.push(pending[&key].request.clone());
+            } else if let Some(p) = pending.remove(&key) {
+                given_up.push((p.request, format!("no answer after {} attempts", p.attempts)));
             }
-            drop(permit)
-        });
-        Ok(())
+        }
+        drop(pending);
+        let failures = given_up
+            .into_iter()
+            .filter_map(|(request, reason)| self.fail(request, reason))
+            .collect();
+        (retries, failures)
+    }
+
+    /// Every connection of the shard reports the same request, only the first report is returned. The channels
+    /// stay with their shard, venues reject the whole request for one invalid stream.
+    fn fail(&self, request: Subscription, reason: String) -> Option<SubscriptionFailure> {
+        let mut failures = self.failures.lock();
+        if failures.iter().any(|f| f.id == request.id) {
+            return None;
+        }
+        let failure = SubscriptionFailure {
+            id: request.id,
+            channels: request.channels,
+            reason,
+        };
+        error!("{}", failure);
+        failures.push(failure.clone());
+        Some(failure)
+    }
+
+    fn pending(&self) -> Vec<u64> {
+        let mut ids = self.pending.lock().keys().map(|(_, id)| *id).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// One logical connection that reconnects and resubscribes until it runs out of retries
+struct Connection {
+    id: u64,
+    shard: usize,
+    url: Url,
+    subscriptions: Arc<Subscriptions>,
+    protocol: Arc<dyn WsProtocol>,
+    metrics: ConnectionMetrics,
+    requests: broadcast::Receiver<(usize, Subscription)>,
+    stale_timeout: Duration,
+    backoff: Backoff,
+}
+
+impl Connection {
+    async fn run(
+        mut self,
+        sender: QueueSender<(u64, String)>,
+        mut shutdown: ShutdownSignal,
+    ) -> Result<(), IngestorError> {
+        loop {
+            // Requests queued while disconnected are covered by the resubscribe
+            let requests = self.requests.resubscribe();
+            let channels = self.subscriptions.shards.read().get(self.shard).cloned().unwrap_or_default();
+            let url = self.protocol.url(&self.url, &channels);
+            let handler = Handler::new(&self, url.as_ref().unwrap_or(&self.url), sender.clone(), requests);
+            match handler.await {
+                Ok(mut handler) => {
+                    info!(connection = self.id, "Websocket connected");
+                    self.backoff.reset();
+                    match handler.run(self.stale_timeout, url.is_none(), &mut shutdown).await {
+                        Ok(_) if shutdown.is_triggered() => return Ok(()),
+                        // Binance closes every connection after 24 hours
+                        Ok(_) => info!(connection = self.id, "Websocket closed"),
+                        Err(IngestorError::ChannelClosed) => return Ok(()),
+                        Err(e) => warn!(connection = self.id, "Websocket handler: {}", e),
+                    }
+                }
+                Err(e) => warn!(connection = self.id, "Failed to connect websocket: {}", e),
+            }
+            if shutdown.is_triggered() {
+                return Ok(());
+            }
+
+            let Some(delay) = self.backoff.next_delay() else {
+                return Err(IngestorError::RetriesExhausted(self.backoff.attempts));
+            };
+            info!(
+                connection = self.id,
+                attempt = self.backoff.attempts,
+                "Reconnecting in {:?}",
+                delay
+            );
+            select! {
+                _ = sleep(delay) => {}
+                _ = shutdown.wait() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Exponential backoff with jitter, so connections dropped together don't reconnect in lockstep
+struct Backoff {
+    config: ReconnectConfig,
+    attempts: u32,
+}
+
+impl Backoff {
+    fn new(config: ReconnectConfig) -> Self {
+        Self {
+            config,
+            attempts: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Delay before the next attempt, none once the retries are used up
+    fn next_delay(&mut self) -> Option<Duration> {
+        if self.config.max_retries.is_some_and(|max| self.attempts >= max) {
+            return None;
+        }
+        let exponential = self.config.initial_backoff.saturating_mul(1 << self.attempts.min(32));
+        let ceiling = exponential.min(self.config.max_backoff);
+        self.attempts += 1;
+        Some(Duration::from_millis(rand::thread_rng().gen_range(ceiling / 2..=ceiling)))
     }
 }
 
 /// Per-connection handler. Reads requests from `connection` or sends requests
 pub struct Handler {
     id: u64,
-    subscription: Subscription,
+    shard: usize,
+    subscriptions: Arc<Subscriptions>,
+    protocol: Arc<dyn WsProtocol>,
+    metrics: ConnectionMetrics,
+    requests: broadcast::Receiver<(usize, Subscription)>,
+    /// Unanswered requests are sent again after this long, until they used up their attempts
+    ack_timeout: Duration,
+    max_attempts: u32,
     /// The TCP connection decorated with the redis protocol encoder / decoder
     /// implemented using a buffered `TcpStream`.
     ///
@@ -112,19 +600,30 @@ pub struct Handler {
     stream: WebSocketStream<Stream<TokioAdapter<TcpStream>, TokioAdapter<TlsStream<TcpStream>>>>,
 
     /// Send messages to the WebSocket Manager
-    sender: Sender<Message>,
+    sender: QueueSender<(u64, String)>,
 }
 
 impl Handler {
-    pub async fn new(url: &Url, sender: Sender<Message>, subscription: Subscription) -> Result<Self> {
+    async fn new(
+        connection: &Connection,
+        url: &Url,
+        sender: QueueSender<(u64, String)>,
+        requests: broadcast::Receiver<(usize, Subscription)>,
+    ) -> Result<Self, IngestorError> {
         let (mut stream, _) = connect_async(url.to_string()).await?;
         // Send ping
         let ping = Message::Ping(vec![]);
         stream.send(ping).await?;
 
         Ok(Self {
-            id: 0,
-            subscription,
+            id: connection.id,
+            shard: connection.shard,
+            subscriptions: connection.subscriptions.clone(),
+            protocol: connection.protocol.clone(),
+            metrics: connection.metrics.clone(),
+            requests,
+            ack_timeout: Duration::from_millis(connection.backoff.config.ack_timeout),
+            max_attempts: connection.backoff.config.max_subscribe_attempts,
             stream,
             sender,
         })
@@ -140,25 +639,112 @@ impl Handler {
     /// interleaving frames. See for more details:
     /// https://redis.io/topics/pipelining
     ///
-    /// When the shutdown signal is received, the connection is processed until
-    /// it reaches a safe state, at which point it is terminated.
-    async fn run(&mut self) -> Result<()> {
-        let mut sub = self.subscription.clone();
-        sub.update_id(self.id);
-        self.stream.send(sub.into()).await?;
-
-        while let Some(msg) = self.stream.next().await {
+    /// When the shutdown signal is received, a close frame is sent and the messages the venue sent before
+    /// answering it are still forwarded.
+    ///
+    /// Fails with [`IngestorError::Stale`] when nothing arrives within `stale_timeout`, a silently
+    /// stalled socket otherwise looks healthy while the quotes go stale.
+    async fn run(
+        &mut self,
+        stale_timeout: Duration,
+        resubscribe: bool,
+        shutdown: &mut ShutdownSignal,
+    ) -> Result<(), IngestorError> {
+        // The channels are subscribed again on every reconnect, unless the url carried them
+        let request = self.subscriptions.resubscribe(self.id, self.shard);
+        if let Some(request) = request.filter(|_| resubscribe) {
+            self.send_request(request).await?;
+        }
+
+        let (period, ping) = self.protocol.ping().unzip();
+        let period = period.unwrap_or(stale_timeout);
+        let mut heartbeat = interval_at(Instant::now() + period, period);
+        let mut acks = interval_at(Instant::now() + self.ack_timeout, self.ack_timeout);
+        // One deadline for the whole connection, only pushed back by messages and not by the ticks
+        let stale = sleep(stale_timeout);
+        tokio::pin!(stale);
+
+        loop {
+            select! {
+                _ = heartbeat.tick(), if ping.is_some() => {
+                    if let Some(ping) = ping.clone() {
+                        self.stream.send(ping).await?;
+                    }
+                }
+                msg = self.stream.next() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    stale.as_mut().reset(Instant::now() + stale_timeout);
+                    self.handle_message(msg?).await?;
+                }
+                _ = &mut stale => return Err(IngestorError::Stale(stale_timeout)),
+                request = self.requests.recv() => match request {
+                    Ok((shard, request)) if shard == self.shard => self.send_request(request).await?,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        // Reconnecting resubscribes the current channels
+                        warn!(connection = self.id, "Missed {} subscription requests", missed);
+                        return Ok(());
+                    }
+                    Err(RecvError::Closed) => return Err(IngestorError::ChannelClosed),
+                },
+                _ = acks.tick() => self.retry_unanswered().await?,
+                _ = shutdown.wait() => return self.close().await,
+            }
+        }
+        Ok(())
+    }
+
+    async fn retry_unanswered(&mut self) -> Result<(), IngestorError> {
+        let (retries, failures) = self.subscriptions.expired(self.id, self.ack_timeout, self.max_attempts);
+        failures.iter().for_each(|_| self.metrics.subscription_failed());
+        for request in retries {
+            warn!(
+                connection = self.id,
+                "Request {} was not answered, sending it again", request.id
+            );
+            self.send_request(request).await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), IngestorError> {
+        info!(connection = self.id, "Closing websocket");
+        self.stream.close(None).await?;
+        // The stream ends once the venue answered the close frame
+        while let Ok(Some(msg)) = timeout(CLOSE_TIMEOUT, self.stream.next()).await {
+            // Pings can't be answered after the close frame, only data is forwarded
             let msg = msg?;
-            self.handle_message(msg).await?;
+            if let Message::Text(_) = msg {
+                self.handle_message(msg).await?;
+            }
         }
         Ok(())
     }
 
-    async fn handle_message(&mut self, msg: Message) -> Result<()> {
+    async fn send_request(&mut self, request: Subscription) -> Result<(), IngestorError> {
+        self.subscriptions.sent(self.id, &request);
+        self.stream.send(self.protocol.request(&request)).await?;
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, msg: Message) -> Result<(), IngestorError> {
         match msg {
             Message::Text(text) => {
                 debug!("Hanlder received text: {:?}", text);
-                self.sender.send_async(Message::Text(text)).await?;
+                self.metrics.received(self.id, text.len());
+                if self.protocol.is_pong(&text) {
+                    return Ok(());
+                }
+                match self.protocol.response(&text) {
+                    Some(response) => {
+                        if self.subscriptions.confirm(self.id, response).is_some() {
+                            self.metrics.subscription_failed();
+                        }
+                    }
+                    None => self.sender.send((self.id, text)).await?,
+                }
             }
             Message::Ping(ping) => {
                 debug!("Handler received ping: {:?}", ping);
@@ -171,3 +757,222 @@ impl Handler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestProtocol {
+        ping: Option<Duration>,
+    }
+
+    impl WsProtocol for TestProtocol {
+        fn request(&self, _subscription: &Subscription) -> Message {
+            Message::Text("subscribe".into())
+        }
+
+        fn response(&self, _text: &str) -> Option<SubscriptionResponse> {
+            None
+        }
+
+        fn ping(&self) -> Option<(Duration, Message)> {
+            self.ping.map(|period| (period, Message::Text("ping".into())))
+        }
+    }
+
+    /// Runs a handler against a local venue that accepts the connection and never sends anything
+    async fn run_silent(protocol: TestProtocol, stale_timeout: Duration) -> Result<(), IngestorError> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let _stream = async_tungstenite::tokio::accept_async(socket).await.unwrap();
+            sleep(Duration::from_secs(60)).await;
+        });
+
+        let (requests_tx, requests) = broadcast::channel(16);
+        let connection = Connection {
+            id: 0,
+            shard: 0,
+            url: url.clone(),
+            subscriptions: Arc::new(Subscriptions::new(10)),
+            protocol: Arc::new(protocol),
+            metrics: ConnectionMetrics::new("test_ws_silent"),
+            requests: requests.resubscribe(),
+            stale_timeout,
+            backoff: Backoff::new(ReconnectConfig {
+                ack_timeout: 20,
+                ..Default::default()
+            }),
+        };
+        let (sender, _receiver) = queue("test_ws_silent", &BackpressureConfig::default());
+        let mut handler = Handler::new(&connection, &url, sender, requests).await.unwrap();
+        // An unanswered request keeps the acks busy
+        handler
+            .send_request(Subscription {
+                id: 1,
+                subscribe: true,
+                channels: vec!["btcusdt@aggTrade".into()],
+            })
+            .await
+            .unwrap();
+
+        let shutdown = crate::shutdown::Shutdown::default();
+        let res = timeout(
+            Duration::from_secs(5),
+            handler.run(stale_timeout, false, &mut shutdown.subscribe()),
+        )
+        .await;
+        drop(requests_tx);
+        res.expect("A silent stream was never reported as stale")
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_acks_tick() {
+        let stale_timeout = Duration::from_millis(200);
+        let res = run_silent(TestProtocol { ping: None }, stale_timeout).await;
+        assert!(matches!(res, Err(IngestorError::Stale(timeout)) if timeout == stale_timeout));
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_pinging() {
+        let stale_timeout = Duration::from_millis(200);
+        let protocol = TestProtocol {
+            ping: Some(Duration::from_millis(30)),
+        };
+        let res = run_silent(protocol, stale_timeout).await;
+        assert!(matches!(res, Err(IngestorError::Stale(timeout)) if timeout == stale_timeout));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(ReconnectConfig {
+            initial_backoff: 100,
+            max_backoff: 1000,
+            max_retries: Some(6),
+            stale_timeout: 10000,
+            ack_timeout: 5000,
+            max_subscribe_attempts: 3,
+        });
+        let delays = (0..6)
+            .map(|_| backoff.next_delay().unwrap().as_millis() as u64)
+            .collect::<Vec<_>>();
+        for (delay, ceiling) in delays.iter().zip([100, 200, 400, 800, 1000, 1000]) {
+            assert!(*delay >= ceiling / 2 && *delay <= ceiling, "{} outside of {}", delay, ceiling);
+        }
+        assert!(backoff.next_delay().is_none());
+
+        backoff.reset();
+        assert!(backoff.next_delay().unwrap() <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_unanswered_requests() {
+        let subscriptions = Subscriptions::new(10);
+        let channels = ["btcusdt@aggTrade".to_string()];
+        let (_, request) = subscriptions.request(true, &channels).remove(0);
+        subscriptions.sent(0, &request);
+        subscriptions.sent(1, &request);
+
+        // Not due yet, then sent again while attempts are left
+        assert!(subscriptions.expired(0, Duration::from_secs(60), 2).0.is_empty());
+        let (retries, failures) = subscriptions.expired(0, Duration::ZERO, 2);
+        assert_eq!(retries.len(), 1);
+        assert!(failures.is_empty());
+        subscriptions.sent(0, &retries[0]);
+
+        let (retries, failures) = subscriptions.expired(0, Duration::ZERO, 2);
+        assert!(retries.is_empty());
+        assert_eq!(failures[0].channels, channels);
+        assert_eq!(failures[0].reason, "no answer after 2 attempts");
+        // The other connection of the shard rejecting the same request is not reported twice
+        let rejected = SubscriptionResponse {
+            id: Some(request.id),
+            error: Some("Invalid request".into()),
+        };
+        assert!(subscriptions.confirm(1, rejected).is_none());
+        assert!(subscriptions.pending().is_empty());
+
+        let (_, invalid) = subscriptions.request(true, &["btcusdt@invalid".to_string()]).remove(0);
+        subscriptions.sent(0, &invalid);
+        let rejected = SubscriptionResponse {
+            id: Some(invalid.id),
+            error: Some("Invalid request".into()),
+        };
+        let failure = subscriptions.confirm(0, rejected).unwrap();
+        assert_eq!(
+            failure.to_string(),
+            format!("Request {} for [\"btcusdt@invalid\"] failed: Invalid request", invalid.id)
+        );
+        assert_eq!(subscriptions.failures.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_connection_metrics() {
+        let metrics = ConnectionMetrics::new("test_ws");
+        metrics.received(3, 120);
+        metrics.received(3, 80);
+        metrics.failed(3);
+        let received = time::macros::datetime!(2024-01-01 00:00:00.250).assume_utc();
+        metrics.observe(3, &time::macros::datetime!(2024-01-01 00:00:00).assume_utc(), received);
+
+        let labels = ["test_ws", "3"];
+        assert_eq!(METRICS.connection_messages.with_label_values(&labels).get(), 2);
+        assert_eq!(METRICS.connection_bytes.with_label_values(&labels).get(), 200);
+        assert_eq!(METRICS.connection_parse_errors.with_label_values(&labels).get(), 1);
+        let latency = METRICS.connection_latency.with_label_values(&labels);
+        assert_eq!(latency.get_sample_count(), 1);
+        assert_eq!(latency.get_sample_sum(), 0.25);
+        // Other connections of the ingestor are counted apart
+        assert_eq!(METRICS.connection_messages.with_label_values(&["test_ws", "4"]).get(), 0);
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let subscriptions = Subscriptions::new(2);
+        let channels = ["btcusdt@aggTrade", "ethusdt@aggTrade", "solusdt@aggTrade"].map(String::from);
+
+        let requests = subscriptions.request(true, &channels);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1.channels, &channels[..2]);
+        assert_eq!(requests[1].1.channels, &channels[2..]);
+        assert_eq!(subscriptions.shard_count(), 2);
+        assert!(subscriptions.request(true, &channels[..1]).is_empty());
+        let (_, request) = &requests[0];
+        let resubscribe = subscriptions.resubscribe(0, 0).unwrap();
+        assert_ne!(resubscribe.id, request.id);
+
+        subscriptions.sent(0, request);
+        subscriptions.sent(1, request);
+        subscriptions.confirm(
+            0,
+            SubscriptionResponse {
+                id: Some(request.id),
+                error: None,
+            },
+        );
+        assert_eq!(subscriptions.pending(), vec![request.id]);
+        // A reconnect forgets the requests of the old socket
+        subscriptions.resubscribe(1, 0);
+        assert!(subscriptions.pending().is_empty());
+
+        let requests = subscriptions.request(false, &channels[..1]);
+        assert!(!requests[0].1.subscribe);
+        // The freed slot is refilled before opening a third shard
+        let requests = subscriptions.request(true, &["xrpusdt@aggTrade".to_string()]);
+        assert_eq!(requests[0].0, 0);
+        assert_eq!(subscriptions.shard_count(), 2);
+
+        // Without an id the oldest request of the connection is answered
+        subscriptions.sent(0, &requests[0].1);
+        subscriptions.sent(0, &resubscribe);
+        subscriptions.confirm(
+            0,
+            SubscriptionResponse {
+                id: None,
+                error: None,
+            },
+        );
+        assert_eq!(subscriptions.pending(), vec![requests[0].1.id]);
+    }
+}
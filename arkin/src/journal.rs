@@ -0,0 +1,202 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::{bus::Sequenced, config::JournalConfig, models::Event};
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("Journal io failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to serialize event: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Journal entry on line {line} is corrupt: {source}")]
+    Corrupt {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// An event with its sequence number on the bus, `{"seq": 1, "type": "trade", "data": {..}}`
+#[derive(Serialize, Deserialize)]
+struct JournalEntry<E> {
+    /// Zero for entries written before events were numbered
+    #[serde(default)]
+    seq: u64,
+    #[serde(flatten)]
+    event: E,
+}
+
+/// Append-only log of the events published on the bus, one json entry per line.
+///
+/// Entries are written before the subscribers get the event, so after a crash the journal holds everything
+/// the engine has acted on and the state can be rebuilt from it.
+pub struct Journal {
+    sync: bool,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Journal {
+    /// Start a new journal, a previous one is moved aside with the current time appended to its name
+    pub fn create(config: &JournalConfig) -> Result<Self, JournalError> {
+        let path = Path::new(&config.path);
+        if path.exists() {
+            let archived = format!("{}.{}", config.path, OffsetDateTime::now_utc().unix_timestamp());
+            fs::rename(path, &archived)?;
+            info!("Previous journal moved to {}", archived);
+        }
+        Self::open(config)
+    }
+
+    /// Read the events of an existing journal in sequence order and continue writing behind them.
+    ///
+    /// A torn last entry from a crash in the middle of a write is cut off, anything else that does not parse
+    /// is an error so a damaged journal is never silently skipped. The file is read one entry at a time.
+    pub fn recover(config: &JournalConfig) -> Result<(Self, Vec<Sequenced<Event>>), JournalError> {
+        let mut events = Vec::new();
+        let mut valid = 0;
+        match File::open(&config.path) {
+            Ok(file) => valid = Self::read(BufReader::new(file), &mut events)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!("No journal at {}, starting empty", config.path);
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // Entries are written in sequence order, the sort only matters for journals written before that
+        events.sort_by_key(|e| e.seq);
+
+        let journal = Self::open(config)?;
+        journal.writer.lock().get_ref().set_len(valid)?;
+        info!("Recovered {} events from {}", events.len(), config.path);
+        Ok((journal, events))
+    }
+
+    /// Reads the entries up to a torn last one, returns the length of the valid part of the file
+    fn read(mut reader: impl BufRead, events: &mut Vec<Sequenced<Event>>) -> Result<u64, JournalError> {
+        let mut valid = 0;
+        let mut entry = String::new();
+        for i in 0.. {
+            entry.clear();
+            if reader.read_line(&mut entry)? == 0 {
+                break;
+            }
+            let last = reader.fill_buf()?.is_empty();
+            match serde_json::from_str::<JournalEntry<Event>>(&entry) {
+                Ok(parsed) if entry.ends_with('\n') => events.push(Sequenced {
+                    seq: parsed.seq,
+                    message: parsed.event,
+                }),
+                Ok(_) => {
+                    warn!("Dropping unterminated last journal entry");
+                    break;
+                }
+                Err(e) if last => {
+                    warn!("Dropping torn last journal entry: {}", e);
+                    break;
+                }
+                Err(source) => {
+                    return Err(JournalError::Corrupt {
+                        line: i + 1,
+                        source,
+                    })
+                }
+            }
+            valid += entry.len() as u64;
+        }
+        Ok(valid)
+    }
+
+    fn open(config: &JournalConfig) -> Result<Self, JournalError> {
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        Ok(Journal {
+            sync: config.sync,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn append(&self, seq: u64, event: &Event) -> Result<(), JournalError> {
+        let mut writer = self.writer.lock();
+        self.write(&mut writer, seq, event)
+    }
+
+    /// Takes the next sequence number while the journal is held, so the entries are written in sequence order
+    pub fn append_next(&self, sequence: &AtomicU64, event: &Event) -> (u64, Result<(), JournalError>) {
+        let mut writer = self.writer.lock();
+        let seq = sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        (seq, self.write(&mut writer, seq, event))
+    }
+
+    fn write(&self, writer: &mut BufWriter<File>, seq: u64, event: &Event) -> Result<(), JournalError> {
+        let mut entry = serde_json::to_string(&JournalEntry { seq, event })?;
+        entry.push('\n');
+        writer.write_all(entry.as_bytes())?;
+        writer.flush()?;
+        if self.sync {
+            writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_journal_recovery() {
+        let config = JournalConfig {
+            enabled: true,
+            path: std::env::temp_dir()
+                .join(format!("journal_{}.jsonl", std::process::id()))
+                .to_string_lossy()
+                .into(),
+            sync: false,
+        };
+        let instrument = test_utils::test_perp_instrument();
+        let events = test_utils::market_events(&instrument, datetime!(2024-01-01 00:00:00).assume_utc(), 3);
+
+        let journal = Journal::create(&config).unwrap();
+        for (seq, event) in events.iter().enumerate().rev() {
+            journal.append(seq as u64 + 1, event).unwrap();
+        }
+        drop(journal);
+
+        // A crash in the middle of a write leaves half an entry behind
+        let mut file = OpenOptions::new().append(true).open(&config.path).unwrap();
+        file.write_all(br#"{"Tick":{"event_time":"#).unwrap();
+
+        let (journal, recovered) = Journal::recover(&config).unwrap();
+        assert_eq!(recovered.len(), events.len());
+        // Appended out of order, recovered in sequence order
+        assert!(recovered.iter().map(|e| e.seq).eq(1..=events.len() as u64));
+        assert_eq!(recovered[0].message.event_time(), events[0].event_time());
+        journal.append(events.len() as u64 + 1, &events[0]).unwrap();
+        drop(journal);
+        let (journal, recovered) = Journal::recover(&config).unwrap();
+        assert_eq!(recovered.len(), events.len() + 1);
+
+        // The next sequence number is taken while the journal is held
+        let sequence = AtomicU64::new(events.len() as u64 + 1);
+        let (seq, result) = journal.append_next(&sequence, &events[1]);
+        assert!(seq == events.len() as u64 + 2 && result.is_ok());
+        drop(journal);
+        let (_, recovered) = Journal::recover(&config).unwrap();
+        assert_eq!(recovered.last().unwrap().seq, seq);
+
+        fs::write(&config.path, "garbage\n{}\n").unwrap();
+        assert!(matches!(Journal::recover(&config), Err(JournalError::Corrupt { line: 1, .. })));
+        fs::remove_file(&config.path).unwrap();
+    }
+}
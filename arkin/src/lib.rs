@@ -1,18 +1,37 @@
+pub mod alerting;
 pub mod allocation;
+pub mod api;
+pub mod backtest;
+pub mod bus;
 pub mod clock;
+pub mod collector;
 pub mod config;
 pub mod constants;
+pub mod credentials;
 pub mod db;
 pub mod errors;
+pub mod exchange_info;
 pub mod execution;
 pub mod features;
+pub mod flags;
+pub mod grpc;
+pub mod health;
 pub mod ingestors;
+pub mod journal;
 pub mod logging;
+pub mod metrics;
 pub mod models;
 pub mod pipeline;
 pub mod portfolio;
+pub mod rest;
 pub mod server;
+pub mod shutdown;
+pub mod sinks;
+pub mod skew;
 pub mod state;
 pub mod strategies;
+pub mod synthetic;
 pub mod test_utils;
+pub mod tui;
 pub mod utils;
+pub mod ws;
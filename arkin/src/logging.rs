@@ -1,18 +1,77 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use strum::{Display, EnumString};
 use tracing::subscriber::set_global_default;
-use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+use tracing_subscriber::{
+    fmt::{self, format::FmtSpan, writer::BoxMakeWriter},
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
 
-pub fn init_tracing() {
-    tracing_subscriber::fmt::Subscriber::builder()
-        .with_span_events(FmtSpan::CLOSE)
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_thread_ids(false)
-        .with_target(false)
-        // .with_span_events(FmtSpan::NONE)
-        .with_line_number(true)
-        .with_file(true)
-        .with_ansi(true)
-        .compact()
-        .init();
+/// Filter of the running process, swapped at runtime through the control api
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum LogFormat {
+    /// Compact lines for humans
+    Text,
+    /// One json object per line with the event fields at the top level, for log aggregation
+    Json,
+}
+
+pub fn init_tracing(format: LogFormat) {
+    init(format, BoxMakeWriter::new(std::io::stdout), true);
+}
+
+/// Log to a file instead of the terminal, for when the terminal is taken by the dashboard
+pub fn init_file_tracing(path: &str, format: LogFormat) -> std::io::Result<()> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    init(format, BoxMakeWriter::new(std::sync::Mutex::new(file)), false);
+    Ok(())
+}
+
+fn init(format: LogFormat, writer: BoxMakeWriter, ansi: bool) {
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env());
+    let output = match format {
+        LogFormat::Text => fmt::layer()
+            .with_span_events(FmtSpan::CLOSE)
+            .with_thread_ids(false)
+            .with_target(false)
+            .with_line_number(true)
+            .with_file(true)
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .compact()
+            .boxed(),
+        LogFormat::Json => fmt::layer()
+            .with_target(true)
+            .with_writer(writer)
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .boxed(),
+    };
+    tracing_subscriber::registry().with(filter).with(output).init();
+    let _ = FILTER.set(handle);
+}
+
+/// Replace the log filter of the running process, e.g. `info,arkin::execution=debug`, returns the new filter
+pub fn set_log_filter(directives: &str) -> Result<String> {
+    let handle = FILTER.get().ok_or(anyhow!("Logging is not initialized"))?;
+    let filter = EnvFilter::try_new(directives)?;
+    let active = filter.to_string();
+    handle.reload(filter)?;
+    Ok(active)
+}
+
+/// The active log filter
+pub fn log_filter() -> Result<String> {
+    let handle = FILTER.get().ok_or(anyhow!("Logging is not initialized"))?;
+    Ok(handle.with_current(|filter| filter.to_string())?)
 }
 
 pub fn init_test_tracing() {
@@ -26,5 +85,6 @@ pub fn init_test_tracing() {
         .with_test_writer() // This is the important part
         .compact()
         .finish();
-    set_global_default(subscriber).expect("Failed to set global default subscriber");
+    // Tests share a process, so only the first call installs the subscriber
+    let _ = set_global_default(subscriber);
 }
@@ -0,0 +1,304 @@
+use std::sync::LazyLock;
+
+use axum::{http::header, routing::get, Router};
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+/// Process wide metrics, exposed in the prometheus text format on `/metrics`
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub ingested_events: IntCounterVec,
+    pub ingest_errors: IntCounterVec,
+    pub ingest_dropped: IntCounterVec,
+    pub book_resyncs: IntCounterVec,
+    pub missed_trades: IntCounterVec,
+    pub out_of_order_events: IntCounterVec,
+    pub connection_messages: IntCounterVec,
+    pub connection_bytes: IntCounterVec,
+    pub connection_parse_errors: IntCounterVec,
+    pub connection_latency: HistogramVec,
+    pub subscription_failures: IntCounterVec,
+    pub bus_published: IntCounterVec,
+    pub bus_dropped: IntCounterVec,
+    pub bus_queue_depth: IntGaugeVec,
+    pub journal_errors: IntCounter,
+    pub sink_events: IntCounterVec,
+    pub sink_errors: IntCounterVec,
+    pub alerts: IntCounterVec,
+    pub alert_errors: IntCounterVec,
+    pub clock_offset: GaugeVec,
+    pub event_delay: GaugeVec,
+    pub collector_events: IntCounterVec,
+    pub collector_clients: IntGauge,
+    pub pipeline_latency: Histogram,
+    pub state_series_entries: IntGaugeVec,
+    pub state_series_span: GaugeVec,
+    pub state_series_age: GaugeVec,
+    pub state_bytes: IntGaugeVec,
+    pub orders: IntCounterVec,
+    pub fills: IntCounterVec,
+    pub rejected_orders: IntCounterVec,
+    pub pnl: GaugeVec,
+    pub commission: Gauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("arkin".into()), None).expect("Failed to create metrics registry");
+        let metrics = Metrics {
+            ingested_events: IntCounterVec::new(
+                Opts::new("ingested_events_total", "Market data events received per ingestor"),
+                &["ingestor"],
+            )
+            .unwrap(),
+            ingest_errors: IntCounterVec::new(
+                Opts::new("ingest_errors_total", "Messages an ingestor failed to parse"),
+                &["ingestor"],
+            )
+            .unwrap(),
+            ingest_dropped: IntCounterVec::new(
+                Opts::new("ingest_dropped_total", "Messages shed by a full ingestor queue"),
+                &["queue"],
+            )
+            .unwrap(),
+            book_resyncs: IntCounterVec::new(
+                Opts::new("book_resyncs_total", "Local order books reloaded from a snapshot after a gap"),
+                &["symbol"],
+            )
+            .unwrap(),
+            missed_trades: IntCounterVec::new(
+                Opts::new("missed_trades_total", "Trades skipped over by a jump in the trade ids"),
+                &["ingestor"],
+            )
+            .unwrap(),
+            out_of_order_events: IntCounterVec::new(
+                Opts::new("out_of_order_events_total", "Trades and ticks older than one already received"),
+                &["ingestor"],
+            )
+            .unwrap(),
+            connection_messages: IntCounterVec::new(
+                Opts::new("connection_messages_total", "Messages received per websocket connection"),
+                &["ingestor", "connection"],
+            )
+            .unwrap(),
+            connection_bytes: IntCounterVec::new(
+                Opts::new("connection_bytes_total", "Bytes received per websocket connection"),
+                &["ingestor", "connection"],
+            )
+            .unwrap(),
+            connection_parse_errors: IntCounterVec::new(
+                Opts::new(
+                    "connection_parse_errors_total",
+                    "Messages of a websocket connection the ingestor failed to parse",
+                ),
+                &["ingestor", "connection"],
+            )
+            .unwrap(),
+            connection_latency: HistogramVec::new(
+                HistogramOpts::new(
+                    "connection_latency_seconds",
+                    "Receive time minus event time of the messages a websocket connection delivered first",
+                )
+                .buckets(prometheus::exponential_buckets(0.001, 2., 12).unwrap()),
+                &["ingestor", "connection"],
+            )
+            .unwrap(),
+            subscription_failures: IntCounterVec::new(
+                Opts::new(
+                    "subscription_failures_total",
+                    "Subscription requests a venue rejected or never answered",
+                ),
+                &["ingestor"],
+            )
+            .unwrap(),
+            bus_published: IntCounterVec::new(
+                Opts::new("bus_published_total", "Messages published on the bus per topic"),
+                &["topic"],
+            )
+            .unwrap(),
+            bus_dropped: IntCounterVec::new(
+                Opts::new("bus_dropped_total", "Messages lagging subscribers missed per topic"),
+                &["topic"],
+            )
+            .unwrap(),
+            bus_queue_depth: IntGaugeVec::new(
+                Opts::new("bus_queue_depth", "Messages not yet received by every subscriber per topic"),
+                &["topic"],
+            )
+            .unwrap(),
+            journal_errors: IntCounter::new("journal_errors_total", "Events that could not be written to the journal")
+                .unwrap(),
+            sink_events: IntCounterVec::new(
+                Opts::new("sink_events_total", "Events forwarded to external systems per sink"),
+                &["sink"],
+            )
+            .unwrap(),
+            sink_errors: IntCounterVec::new(
+                Opts::new("sink_errors_total", "Events a sink failed to forward"),
+                &["sink"],
+            )
+            .unwrap(),
+            alerts: IntCounterVec::new(Opts::new("alerts_total", "Alerts raised per severity"), &["severity"]).unwrap(),
+            alert_errors: IntCounterVec::new(
+                Opts::new("alert_errors_total", "Alerts a notifier failed to send"),
+                &["notifier"],
+            )
+            .unwrap(),
+            clock_offset: GaugeVec::new(
+                Opts::new("clock_offset_seconds", "Server time of the venue minus the local time"),
+                &["venue"],
+            )
+            .unwrap(),
+            event_delay: GaugeVec::new(
+                Opts::new("event_delay_seconds", "Average receive time minus event time of market data"),
+                &["venue"],
+            )
+            .unwrap(),
+            collector_events: IntCounterVec::new(
+                Opts::new("collector_events_total", "Events sent or received over the collector socket"),
+                &["direction"],
+            )
+            .unwrap(),
+            collector_clients: IntGauge::new("collector_clients", "Trading engines connected to the collector")
+                .unwrap(),
+            pipeline_latency: Histogram::with_opts(
+                HistogramOpts::new(
+                    "pipeline_latency_seconds",
+                    "Time to calculate the feature pipeline for an instrument",
+                )
+                .buckets(prometheus::exponential_buckets(0.00001, 4., 10).unwrap()),
+            )
+            .unwrap(),
+            state_series_entries: IntGaugeVec::new(
+                Opts::new("state_series_entries", "Entries the state keeps per series"),
+                &["instrument", "kind", "series"],
+            )
+            .unwrap(),
+            state_series_span: GaugeVec::new(
+                Opts::new("state_series_span_seconds", "Newest minus oldest entry time of a state series"),
+                &["instrument", "kind", "series"],
+            )
+            .unwrap(),
+            state_series_age: GaugeVec::new(
+                Opts::new("state_series_age_seconds", "Time since the newest entry of a state series"),
+                &["instrument", "kind", "series"],
+            )
+            .unwrap(),
+            state_bytes: IntGaugeVec::new(
+                Opts::new("state_bytes", "Estimated memory of the state entries per instrument"),
+                &["instrument"],
+            )
+            .unwrap(),
+            orders: IntCounterVec::new(Opts::new("orders_total", "Orders sent per venue"), &["venue"]).unwrap(),
+            fills: IntCounterVec::new(Opts::new("fills_total", "Fills received per venue"), &["venue"]).unwrap(),
+            rejected_orders: IntCounterVec::new(
+                Opts::new("rejected_orders_total", "Orders that were not filled per venue"),
+                &["venue"],
+            )
+            .unwrap(),
+            pnl: GaugeVec::new(Opts::new("pnl", "Profit and loss of the last run"), &["kind"]).unwrap(),
+            commission: Gauge::new("commission", "Commission paid in the last run").unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 33] = [
+            Box::new(metrics.ingested_events.clone()),
+            Box::new(metrics.ingest_errors.clone()),
+            Box::new(metrics.ingest_dropped.clone()),
+            Box::new(metrics.book_resyncs.clone()),
+            Box::new(metrics.missed_trades.clone()),
+            Box::new(metrics.out_of_order_events.clone()),
+            Box::new(metrics.connection_messages.clone()),
+            Box::new(metrics.connection_bytes.clone()),
+            Box::new(metrics.connection_parse_errors.clone()),
+            Box::new(metrics.connection_latency.clone()),
+            Box::new(metrics.subscription_failures.clone()),
+            Box::new(metrics.bus_published.clone()),
+            Box::new(metrics.bus_dropped.clone()),
+            Box::new(metrics.bus_queue_depth.clone()),
+            Box::new(metrics.journal_errors.clone()),
+            Box::new(metrics.sink_events.clone()),
+            Box::new(metrics.sink_errors.clone()),
+            Box::new(metrics.alerts.clone()),
+            Box::new(metrics.alert_errors.clone()),
+            Box::new(metrics.clock_offset.clone()),
+            Box::new(metrics.event_delay.clone()),
+            Box::new(metrics.collector_events.clone()),
+            Box::new(metrics.collector_clients.clone()),
+            Box::new(metrics.pipeline_latency.clone()),
+            Box::new(metrics.state_series_entries.clone()),
+            Box::new(metrics.state_series_span.clone()),
+            Box::new(metrics.state_series_age.clone()),
+            Box::new(metrics.state_bytes.clone()),
+            Box::new(metrics.orders.clone()),
+            Box::new(metrics.fills.clone()),
+            Box::new(metrics.rejected_orders.clone()),
+            Box::new(metrics.pnl.clone()),
+            Box::new(metrics.commission.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("Failed to register metric");
+        }
+        metrics
+    }
+
+    /// Current values in the prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not valid utf8")
+    }
+}
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/metrics",
+        get(|| async { ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], METRICS.render()) }),
+    )
+}
+
+/// Serve the metrics endpoint until the task is dropped
+pub async fn serve(address: &str) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind metrics endpoint to {}: {}", address, e);
+            return;
+        }
+    };
+    info!("Serving metrics on http://{}/metrics", address);
+    if let Err(e) = axum::serve(listener, router()).await {
+        error!("Metrics endpoint failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        METRICS.orders.with_label_values(&["simulation"]).inc();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router()).await });
+
+        let body = reqwest::get(format!("http://{}/metrics", address))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("arkin_orders_total{venue=\"simulation\"}"));
+        assert!(body.contains("arkin_pipeline_latency_seconds_bucket"));
+    }
+}
@@ -1,7 +1,12 @@
-use crate::{constants::TIMESTAMP_FORMAT, strategies::StrategyId};
+use crate::{constants::TIMESTAMP_FORMAT, strategies::StrategyId, utils::custom_serde};
 
-use super::{Event, EventType, EventTypeOf, Instrument, Notional, Price, Quantity, Venue};
-use std::fmt;
+use super::{
+    errors::ModelError, Asset, Event, EventType, EventTypeOf, FundingRate, Instrument, InstrumentType, Notional, Price,
+    Quantity, Venue,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
 use time::OffsetDateTime;
 
 #[derive(Clone)]
@@ -10,17 +15,24 @@ pub struct Account {
     pub venue: Venue,
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Position {
     pub strategy_id: StrategyId,
     pub instrument: Instrument,
+    #[serde(with = "custom_serde::timestamp")]
     pub start_time: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp::option")]
     pub exit_time: Option<OffsetDateTime>,
     pub entry_price: Price,
     pub exit_price: Option<Price>,
+    /// Average entry price of the open quantity
     pub avg_price: Price,
     pub quantity: Quantity,
+    /// Fees of every fill of the position
     pub commission: Notional,
+    /// Pnl of the closed quantity before fees
+    #[serde(default)]
+    pub realized_pnl: Notional,
 }
 
 impl Position {
@@ -41,6 +53,7 @@ impl Position {
             avg_price: entry_price,
             quantity,
             commission: Notional::from(0.),
+            realized_pnl: Notional::from(0.),
         }
     }
 
@@ -55,45 +68,79 @@ impl Position {
             avg_price: fill.price,
             quantity: fill.quantity,
             commission: fill.commission,
+            realized_pnl: Notional::from(0.),
         }
     }
-    pub fn update(&mut self, fill: &Fill) -> Option<Fill> {
+
+    /// Adds the fill, a fill that flips the position closes it and returns the excess to open the next one with
+    pub fn apply_fill(&mut self, fill: &Fill) -> Option<Fill> {
         let new_quantity = self.quantity + fill.quantity;
-        self.commission += fill.commission;
+        let increasing = self.quantity.is_zero() || self.quantity.is_positive() == fill.quantity.is_positive();
 
-        match (new_quantity.is_zero(), self.quantity.is_positive(), new_quantity.is_negative()) {
-            // Quantity is zero so we close the position
+        match (
+            increasing,
+            new_quantity.is_zero(),
+            new_quantity.is_positive() == self.quantity.is_positive(),
+        ) {
+            // Fill adds to the position so we update the average price
             (true, _, _) => {
-                self.avg_price = (self.notional() + fill.notional()) / (self.quantity + fill.quantity.abs());
-                self.exit_price = Some(fill.price);
-                self.exit_time = Some(fill.event_time);
+                self.avg_price = (self.notional() + fill.notional()) / new_quantity;
+                self.quantity = new_quantity;
+                self.commission += fill.commission;
                 None
             }
-            // Position flips
-            (_, true, false) | (_, false, true) => {
-                let fillable = self.quantity - (fill.quantity - self.quantity);
-                self.avg_price = (self.notional() + fill.price * fillable) / (self.quantity + fillable.abs());
+            // Quantity is zero so we close the position
+            (false, true, _) => {
+                self.realize(fill.price, fill.quantity);
+                self.quantity = new_quantity;
+                self.commission += fill.commission;
                 self.exit_price = Some(fill.price);
                 self.exit_time = Some(fill.event_time);
-                Some(Fill::new(
-                    fill.event_time,
-                    fill.instrument.clone(),
-                    fill.order_id,
-                    fill.strategy_id.clone(),
-                    fill.price,
-                    fill.quantity - fillable,
-                    fill.commission * (fillable / fill.quantity),
-                ))
+                None
             }
-            // Position is still open
-            _ => {
-                self.avg_price = (self.notional() + fill.notional()) / (self.quantity + fill.quantity.abs());
-                self.quantity += fill.quantity;
+            // Fill reduces the position but keeps it open
+            (false, false, true) => {
+                self.realize(fill.price, fill.quantity);
+                self.quantity = new_quantity;
+                self.commission += fill.commission;
                 None
             }
+            // Position flips, close it and return the excess as a new fill
+            (false, false, false) => {
+                let closing_share = self.quantity.abs() / fill.quantity.abs();
+                self.commission += fill.commission * closing_share;
+                self.realize(fill.price, (-self.quantity.value()).into());
+                self.quantity = Quantity::from(0.);
+                self.exit_price = Some(fill.price);
+                self.exit_time = Some(fill.event_time);
+                Some(Fill {
+                    quantity: new_quantity,
+                    commission: fill.commission * (Decimal::ONE - closing_share),
+                    ..fill.clone()
+                })
+            }
         }
     }
 
+    /// Books the pnl of closing the quantity, which has the opposite sign of the position
+    fn realize(&mut self, price: Price, quantity: Quantity) {
+        self.realized_pnl += ((self.avg_price.value() - price.value()) * quantity.value()).into();
+    }
+
+    /// Unrealized pnl of the open quantity at the mark price
+    pub fn mark(&self, price: Price) -> Notional {
+        ((price.value() - self.avg_price.value()) * self.quantity.value()).into()
+    }
+
+    /// Realized and unrealized pnl at the mark price net of fees
+    pub fn total_pnl(&self, price: Price) -> Notional {
+        self.realized_pnl + self.mark(price) - self.commission
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.quantity.is_zero()
+    }
+
     pub fn notional(&self) -> Notional {
         self.avg_price * self.quantity
     }
@@ -112,29 +159,46 @@ impl fmt::Display for Position {
     }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum PositionStatus {
     Open,
     Closed,
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Order {
+    #[serde(with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
     pub instrument: Instrument,
     pub order_id: u64,
+    pub client_order_id: String,
+    pub venue_order_id: Option<String>,
     pub strategy_id: StrategyId,
     pub order_type: OrderType,
+    /// Limit price of limit and stop-limit orders
     pub price: Option<Price>,
+    /// Price that activates stop and stop-limit orders
+    #[serde(default)]
+    pub trigger_price: Option<Price>,
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
     pub avg_fill_price: Option<Price>,
     pub quantity: Quantity,
     pub quantity_filled: Quantity,
     pub status: OrderStatus,
+    #[serde(with = "custom_serde::timestamp")]
+    pub created_time: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp::option")]
+    pub acked_time: Option<OffsetDateTime>,
+    #[serde(with = "custom_serde::timestamp::option")]
+    pub filled_time: Option<OffsetDateTime>,
 }
 
 impl Order {
     pub fn new_market(
         event_time: OffsetDateTime,
+        order_id: u64,
         instrument: Instrument,
         strategy_id: StrategyId,
         quantity: Quantity,
@@ -142,15 +206,195 @@ impl Order {
         Self {
             event_time,
             instrument,
-            order_id: 0,
+            order_id,
+            client_order_id: format!("{}-{}", strategy_id, order_id),
+            venue_order_id: None,
             strategy_id,
             order_type: OrderType::Market,
             price: None,
+            trigger_price: None,
+            time_in_force: TimeInForce::Gtc,
             avg_fill_price: None,
             quantity,
             quantity_filled: Quantity::from(0.),
             status: OrderStatus::New,
+            created_time: event_time,
+            acked_time: None,
+            filled_time: None,
+        }
+    }
+
+    pub fn new_limit(
+        event_time: OffsetDateTime,
+        order_id: u64,
+        instrument: Instrument,
+        strategy_id: StrategyId,
+        quantity: Quantity,
+        price: Price,
+    ) -> Self {
+        Self {
+            order_type: OrderType::Limit,
+            price: Some(price),
+            ..Self::new_market(event_time, order_id, instrument, strategy_id, quantity)
+        }
+    }
+
+    /// Market order once the trigger price trades
+    pub fn new_stop(
+        event_time: OffsetDateTime,
+        order_id: u64,
+        instrument: Instrument,
+        strategy_id: StrategyId,
+        quantity: Quantity,
+        trigger_price: Price,
+    ) -> Self {
+        Self {
+            order_type: OrderType::Stop,
+            trigger_price: Some(trigger_price),
+            ..Self::new_market(event_time, order_id, instrument, strategy_id, quantity)
+        }
+    }
+
+    /// Limit order at the price once the trigger price trades
+    pub fn new_stop_limit(
+        event_time: OffsetDateTime,
+        order_id: u64,
+        instrument: Instrument,
+        strategy_id: StrategyId,
+        quantity: Quantity,
+        trigger_price: Price,
+        price: Price,
+    ) -> Self {
+        Self {
+            order_type: OrderType::StopLimit,
+            price: Some(price),
+            trigger_price: Some(trigger_price),
+            ..Self::new_market(event_time, order_id, instrument, strategy_id, quantity)
+        }
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Checks that the prices and time in force fit the order type
+    pub fn validate(&self) -> Result<(), ModelError> {
+        let invalid = |reason: &str| Err(ModelError::InvalidOrder(self.order_id, reason.into()));
+        if self.quantity.is_zero() {
+            return invalid("zero quantity");
+        }
+        let (needs_price, needs_trigger) = match self.order_type {
+            OrderType::Market => (false, false),
+            OrderType::Limit => (true, false),
+            OrderType::Stop => (false, true),
+            OrderType::StopLimit => (true, true),
+        };
+        match (needs_price, self.price) {
+            (true, None) => return invalid("missing limit price"),
+            (false, Some(_)) => return invalid("limit price on an order without one"),
+            (true, Some(price)) if price.value() <= Decimal::ZERO => return invalid("limit price not positive"),
+            _ => {}
+        }
+        match (needs_trigger, self.trigger_price) {
+            (true, None) => return invalid("missing trigger price"),
+            (false, Some(_)) => return invalid("trigger price on an order without one"),
+            (true, Some(price)) if price.value() <= Decimal::ZERO => return invalid("trigger price not positive"),
+            _ => {}
+        }
+        // Post only has to rest on the book, which takes a limit price
+        if self.time_in_force == TimeInForce::Gtx && !needs_price {
+            return invalid("post only without a limit price");
         }
+        Ok(())
+    }
+
+    /// Moves to the status if the lifecycle allows it, the order is left as is otherwise
+    fn transition(&mut self, status: OrderStatus, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        if !self.status.can_transition_to(status) {
+            return Err(ModelError::OrderTransition {
+                order_id: self.order_id,
+                from: self.status,
+                to: status,
+            });
+        }
+        self.status = status;
+        self.event_time = event_time;
+        Ok(())
+    }
+
+    /// Sent to the venue
+    pub fn submit(&mut self, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        self.transition(OrderStatus::Submitted, event_time)
+    }
+
+    /// Venue accepted the order
+    pub fn ack(&mut self, venue_order_id: Option<String>, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        self.transition(OrderStatus::Open, event_time)?;
+        self.venue_order_id = venue_order_id;
+        self.acked_time = Some(event_time);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        self.transition(OrderStatus::Canceled, event_time)
+    }
+
+    pub fn reject(&mut self, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        self.transition(OrderStatus::Rejected, event_time)
+    }
+
+    /// Time in force ran out before the order was filled
+    pub fn expire(&mut self, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        self.transition(OrderStatus::Expired, event_time)
+    }
+
+    /// Add a (partial) fill, keeping the average fill price weighted by quantity
+    pub fn fill(&mut self, price: Price, quantity: Quantity, event_time: OffsetDateTime) -> Result<(), ModelError> {
+        let status = if (self.quantity_filled + quantity).abs().value() >= self.quantity.abs().value() {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        self.transition(status, event_time)?;
+        let filled = self.quantity_filled.abs().value();
+        let added = quantity.abs().value();
+        let total = filled + added;
+        if !total.is_zero() {
+            let avg = self.avg_fill_price.map(|p| p.value()).unwrap_or_default();
+            self.avg_fill_price = Some(((avg * filled + price.value() * added) / total).into());
+        }
+        self.quantity_filled += quantity;
+        self.acked_time.get_or_insert(event_time);
+        if status == OrderStatus::Filled {
+            self.filled_time = Some(event_time);
+        }
+        Ok(())
+    }
+
+    /// Takes over the state the venue reports, the report has to follow the lifecycle of the order
+    pub fn apply_update(&mut self, update: &OrderUpdate) -> Result<(), ModelError> {
+        if update.status != self.status {
+            self.transition(update.status, update.event_time)?;
+        }
+        self.event_time = update.event_time;
+        self.venue_order_id = Some(update.venue_order_id.clone());
+        self.quantity_filled = update.quantity_filled;
+        self.avg_fill_price = update.avg_fill_price.or(self.avg_fill_price);
+        if update.status != OrderStatus::Submitted {
+            self.acked_time.get_or_insert(update.event_time);
+        }
+        if update.status == OrderStatus::Filled {
+            self.filled_time = Some(update.event_time);
+        }
+        Ok(())
+    }
+}
+
+impl Order {
+    /// Sent but not yet filled, cancelled or rejected
+    pub fn is_open(&self) -> bool {
+        !self.status.is_final()
     }
 }
 
@@ -204,7 +448,33 @@ impl fmt::Display for Order {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeInForce {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Immediate or cancel, the rest of the quantity is cancelled
+    Ioc,
+    /// Fill or kill, filled completely right away or not at all
+    Fok,
+    /// Good till crossing, post only and rejected when it would take liquidity
+    Gtx,
+}
+
+impl fmt::Display for TimeInForce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeInForce::Gtc => write!(f, "gtc"),
+            TimeInForce::Ioc => write!(f, "ioc"),
+            TimeInForce::Fok => write!(f, "fok"),
+            TimeInForce::Gtx => write!(f, "gtx"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderType {
     Market,
     Limit,
@@ -223,33 +493,62 @@ impl fmt::Display for OrderType {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
     New,
-    Send,
+    #[serde(alias = "send")]
+    Submitted,
+    /// Acknowledged by the venue and resting
     Open,
     PartiallyFilled,
     Filled,
     Canceled,
     Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    /// Nothing happens to the order anymore
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired
+        )
+    }
+
+    /// Whether an order can move from this status to the next, a venue can skip the acknowledgement and report
+    /// the fill right away
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+        match self {
+            New => matches!(next, Submitted | Canceled | Rejected),
+            Submitted => matches!(next, Open | PartiallyFilled | Filled | Canceled | Rejected | Expired),
+            Open => matches!(next, PartiallyFilled | Filled | Canceled | Expired),
+            PartiallyFilled => matches!(next, PartiallyFilled | Filled | Canceled | Expired),
+            Filled | Canceled | Rejected | Expired => false,
+        }
+    }
 }
 
 impl fmt::Display for OrderStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             OrderStatus::New => write!(f, "new"),
-            OrderStatus::Send => write!(f, "send"),
+            OrderStatus::Submitted => write!(f, "submitted"),
             OrderStatus::Open => write!(f, "open"),
             OrderStatus::PartiallyFilled => write!(f, "partially_filled"),
             OrderStatus::Filled => write!(f, "filled"),
             OrderStatus::Canceled => write!(f, "canceled"),
             OrderStatus::Rejected => write!(f, "rejected"),
+            OrderStatus::Expired => write!(f, "expired"),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Fill {
+    #[serde(with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
     pub instrument: Instrument,
     pub order_id: u64,
@@ -257,6 +556,14 @@ pub struct Fill {
     pub price: Price,
     pub quantity: Quantity,
     pub commission: Notional,
+    /// Asset the commission was paid in, the quote asset when the venue doesn't say
+    #[serde(default)]
+    pub commission_asset: Option<Asset>,
+    #[serde(default)]
+    pub liquidity: Liquidity,
+    /// Id of the trade at the venue, simulated fills have none
+    #[serde(default)]
+    pub trade_id: Option<u64>,
 }
 
 impl Fill {
@@ -277,12 +584,52 @@ impl Fill {
             price,
             quantity,
             commission,
+            commission_asset: None,
+            liquidity: Liquidity::Taker,
+            trade_id: None,
         }
     }
 
+    pub fn with_commission_asset(mut self, asset: Asset) -> Self {
+        self.commission_asset = Some(asset);
+        self
+    }
+
+    pub fn with_liquidity(mut self, liquidity: Liquidity) -> Self {
+        self.liquidity = liquidity;
+        self
+    }
+
+    pub fn with_trade_id(mut self, trade_id: u64) -> Self {
+        self.trade_id = Some(trade_id);
+        self
+    }
+
     pub fn notional(&self) -> Notional {
         self.price * self.quantity
     }
+
+    pub fn commission_asset(&self) -> &Asset {
+        self.commission_asset.as_ref().unwrap_or(self.instrument.quote())
+    }
+}
+
+/// Whether a fill added liquidity to the book or took it, venues charge the two differently
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Liquidity {
+    Maker,
+    #[default]
+    Taker,
+}
+
+impl fmt::Display for Liquidity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Liquidity::Maker => write!(f, "maker"),
+            Liquidity::Taker => write!(f, "taker"),
+        }
+    }
 }
 
 impl EventTypeOf for Fill {
@@ -307,13 +654,529 @@ impl fmt::Display for Fill {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "FILL {} {} strategy: {} avg price: {} quantity: {} commission: {}",
+            "FILL {} {} strategy: {} avg price: {} quantity: {} commission: {} {} {}",
             self.event_time.format(TIMESTAMP_FORMAT).unwrap(),
             self.instrument,
             self.strategy_id,
             self.price,
             self.quantity,
-            self.commission
+            self.commission,
+            self.commission_asset(),
+            self.liquidity
+        )
+    }
+}
+
+/// Order report from the venue, the source of truth for what happened to an order
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OrderUpdate {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub client_order_id: String,
+    pub venue_order_id: String,
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    pub price: Option<Price>,
+    pub quantity: Quantity,
+    pub quantity_filled: Quantity,
+    pub avg_fill_price: Option<Price>,
+    /// Set when the update is caused by a trade
+    pub last_fill: Option<VenueFill>,
+}
+
+/// A single trade of an order as reported by the venue
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VenueFill {
+    pub trade_id: u64,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub commission: Decimal,
+    pub commission_asset: Asset,
+    #[serde(default)]
+    pub liquidity: Liquidity,
+}
+
+impl OrderUpdate {
+    /// The fill of the trade behind the update, attributed to the order it belongs to
+    pub fn fill(&self, order: &Order) -> Option<Fill> {
+        let venue_fill = self.last_fill.as_ref()?;
+        let fill = Fill::new(
+            self.event_time,
+            self.instrument.clone(),
+            order.order_id,
+            order.strategy_id.clone(),
+            venue_fill.price,
+            venue_fill.quantity,
+            venue_fill.commission.into(),
+        );
+        Some(
+            fill.with_commission_asset(venue_fill.commission_asset.clone())
+                .with_liquidity(venue_fill.liquidity)
+                .with_trade_id(venue_fill.trade_id),
         )
     }
 }
+
+impl EventTypeOf for OrderUpdate {
+    fn event_type() -> EventType {
+        EventType::OrderUpdate
+    }
+}
+
+impl TryFrom<Event> for OrderUpdate {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::OrderUpdate(update) = event {
+            Ok(update)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for OrderUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ORDER UPDATE {} {} {} type: {} quantity: {}/{} status: {}",
+            self.event_time.format(TIMESTAMP_FORMAT).unwrap(),
+            self.instrument,
+            self.client_order_id,
+            self.order_type,
+            self.quantity_filled,
+            self.quantity,
+            self.status
+        )
+    }
+}
+
+/// Balances and positions the venue reports after they changed
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AccountUpdate {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub venue: Venue,
+    pub reason: String,
+    pub balances: Vec<BalanceUpdate>,
+    pub positions: Vec<PositionUpdate>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BalanceUpdate {
+    pub asset: Asset,
+    pub wallet: Decimal,
+    /// Wallet balance excluding the isolated margin
+    pub cross_wallet: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PositionUpdate {
+    pub instrument: Instrument,
+    pub quantity: Quantity,
+    pub entry_price: Price,
+    pub unrealized_pnl: Notional,
+}
+
+/// Amount of an asset held at a venue, locked is reserved by open orders or isolated margin
+#[derive(Clone, PartialEq)]
+pub struct Balance {
+    pub venue: Venue,
+    pub asset: Asset,
+    pub free: Decimal,
+    pub locked: Decimal,
+}
+
+impl Balance {
+    pub fn total(&self) -> Decimal {
+        self.free + self.locked
+    }
+}
+
+/// Balances per asset per venue, set by the account updates of the venues and moved by the fills in between
+#[derive(Clone, Default)]
+pub struct AccountState {
+    balances: HashMap<(Venue, Asset), Balance>,
+}
+
+impl AccountState {
+    pub fn balance(&self, venue: &Venue, asset: &Asset) -> Option<&Balance> {
+        self.balances.get(&(venue.clone(), asset.clone()))
+    }
+
+    /// What is available to new orders, zero for assets that aren't held
+    pub fn free(&self, venue: &Venue, asset: &Asset) -> Decimal {
+        self.balance(venue, asset).map(|b| b.free).unwrap_or_default()
+    }
+
+    pub fn balances(&self) -> Vec<Balance> {
+        self.balances.values().cloned().collect()
+    }
+
+    fn entry(&mut self, venue: &Venue, asset: &Asset) -> &mut Balance {
+        self.balances.entry((venue.clone(), asset.clone())).or_insert_with(|| Balance {
+            venue: venue.clone(),
+            asset: asset.clone(),
+            free: Decimal::ZERO,
+            locked: Decimal::ZERO,
+        })
+    }
+
+    /// Adds to the free amount, negative amounts withdraw
+    pub fn deposit(&mut self, venue: &Venue, asset: &Asset, amount: Decimal) {
+        self.entry(venue, asset).free += amount;
+    }
+
+    /// Moves free to locked, fails without changing anything when not enough is free
+    pub fn lock(&mut self, venue: &Venue, asset: &Asset, amount: Decimal) -> Result<(), ModelError> {
+        let balance = self.entry(venue, asset);
+        if balance.free < amount {
+            return Err(ModelError::InsufficientBalance {
+                asset: asset.to_string(),
+                required: amount,
+                free: balance.free,
+            });
+        }
+        balance.free -= amount;
+        balance.locked += amount;
+        Ok(())
+    }
+
+    /// Moves locked back to free, at most what is locked
+    pub fn unlock(&mut self, venue: &Venue, asset: &Asset, amount: Decimal) {
+        let balance = self.entry(venue, asset);
+        let amount = amount.min(balance.locked);
+        balance.locked -= amount;
+        balance.free += amount;
+    }
+
+    /// The venue's balances replace ours, margin held by isolated positions counts as locked
+    pub fn apply_update(&mut self, update: &AccountUpdate) {
+        for reported in &update.balances {
+            let balance = self.entry(&update.venue, &reported.asset);
+            balance.free = reported.cross_wallet;
+            balance.locked = reported.wallet - reported.cross_wallet;
+        }
+    }
+
+    /// Funding settles in the quote asset of the perpetual at its venue
+    pub fn apply_funding(&mut self, payment: &FundingPayment) {
+        let instrument = &payment.instrument;
+        self.deposit(instrument.venue(), instrument.quote(), payment.amount.value());
+    }
+
+    /// Settles a fill executed at the venue. Spot fills swap base against quote, derivatives only pay their
+    /// commission here as their margin and pnl settle with the next account update.
+    pub fn apply_fill(&mut self, venue: &Venue, fill: &Fill) {
+        if fill.instrument.instrument_type() == &InstrumentType::Spot {
+            self.deposit(venue, fill.instrument.base(), fill.quantity.value());
+            self.deposit(venue, fill.instrument.quote(), -fill.notional().value());
+        }
+        self.deposit(venue, fill.commission_asset(), -fill.commission.value());
+    }
+}
+
+impl EventTypeOf for AccountUpdate {
+    fn event_type() -> EventType {
+        EventType::AccountUpdate
+    }
+}
+
+impl TryFrom<Event> for AccountUpdate {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::AccountUpdate(update) = event {
+            Ok(update)
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Funding exchanged for a perpetual position at a funding time, the amount is in the quote asset and negative
+/// when the position paid
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FundingPayment {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub strategy_id: StrategyId,
+    pub instrument: Instrument,
+    pub quantity: Quantity,
+    pub mark_price: Price,
+    pub funding_rate: Decimal,
+    pub amount: Notional,
+}
+
+impl FundingPayment {
+    /// Longs pay shorts when the rate is positive
+    pub fn new(event_time: OffsetDateTime, position: &Position, funding: &FundingRate) -> Self {
+        let amount = -(position.quantity.value() * funding.mark_price.value() * funding.funding_rate);
+        Self {
+            event_time,
+            strategy_id: position.strategy_id.clone(),
+            instrument: position.instrument.clone(),
+            quantity: position.quantity,
+            mark_price: funding.mark_price,
+            funding_rate: funding.funding_rate,
+            amount: amount.into(),
+        }
+    }
+}
+
+impl EventTypeOf for FundingPayment {
+    fn event_type() -> EventType {
+        EventType::FundingPayment
+    }
+}
+
+impl TryFrom<Event> for FundingPayment {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::FundingPayment(payment) = event {
+            Ok(payment)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for FundingPayment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} quantity: {} rate: {} amount: {}",
+            self.event_time.format(TIMESTAMP_FORMAT).expect("Unable to format timestamp"),
+            self.strategy_id,
+            self.instrument,
+            self.quantity,
+            self.funding_rate,
+            self.amount
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_order_fills() {
+        let created = datetime!(2024-01-01 00:00 UTC);
+        let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let mut order = Order::new_market(created, 3, instrument, "test".into(), Quantity::from(-2.));
+        assert_eq!(order.client_order_id, "test-3");
+
+        order.submit(created).unwrap();
+        order
+            .ack(Some("abc".into()), created + time::Duration::milliseconds(5))
+            .unwrap();
+        assert!(matches!(order.status, OrderStatus::Open));
+
+        order
+            .fill(
+                Price::from(100.),
+                Quantity::from(-1.5),
+                created + time::Duration::milliseconds(10),
+            )
+            .unwrap();
+        assert!(matches!(order.status, OrderStatus::PartiallyFilled));
+        assert!(order.filled_time.is_none());
+
+        let filled = created + time::Duration::milliseconds(20);
+        order.fill(Price::from(104.), Quantity::from(-0.5), filled).unwrap();
+        assert!(matches!(order.status, OrderStatus::Filled));
+        assert_eq!(order.quantity_filled, Quantity::from(-2.));
+        assert_eq!(order.avg_fill_price, Some(Price::from(101.)));
+        assert_eq!(order.created_time, created);
+        assert_eq!(order.filled_time, Some(filled));
+    }
+
+    #[test]
+    fn test_position_pnl() {
+        let time = datetime!(2024-01-01 00:00 UTC);
+        let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let fill = |price: f64, quantity: f64| {
+            Fill::new(
+                time,
+                instrument.clone(),
+                1,
+                "test".into(),
+                price.into(),
+                quantity.into(),
+                Notional::from(1.),
+            )
+        };
+
+        let mut position = Position::from_fill(&fill(100., 2.));
+        assert!(position.apply_fill(&fill(110., 2.)).is_none());
+        assert_eq!(position.avg_price, Price::from(105.));
+        assert_eq!(position.mark(Price::from(100.)), Notional::from(-20.));
+
+        // Half is sold at a profit, the rest is still marked against the average entry
+        assert!(position.apply_fill(&fill(115., -2.)).is_none());
+        assert_eq!(position.realized_pnl, Notional::from(20.));
+        assert_eq!(position.mark(Price::from(115.)), Notional::from(20.));
+        assert_eq!(position.total_pnl(Price::from(115.)), Notional::from(37.));
+
+        // Flipping realizes the rest and hands over the excess with its share of the fee
+        let excess = position.apply_fill(&fill(100., -4.)).unwrap();
+        assert_eq!(position.realized_pnl, Notional::from(10.));
+        assert!(!position.is_open() && position.mark(Price::from(90.)) == Notional::from(0.));
+        assert_eq!(position.commission, Notional::from(3.5));
+        assert_eq!(excess.quantity, Quantity::from(-2.));
+        assert_eq!(excess.commission, Notional::from(0.5));
+    }
+
+    #[test]
+    fn test_account_state() {
+        let time = datetime!(2024-01-01 00:00 UTC);
+        let (usdt, btc): (Asset, Asset) = ("usdt".into(), "btc".into());
+        let mut account = AccountState::default();
+        account.deposit(&Venue::Binance, &usdt, Decimal::from(1000));
+
+        // Spot swaps quote for base, the fee is paid in the asset the venue charged
+        let spot = Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into());
+        let fill = Fill::new(
+            time,
+            spot,
+            1,
+            "test".into(),
+            Price::from(100.),
+            Quantity::from(2.),
+            Notional::from(1.),
+        );
+        account.apply_fill(&Venue::Binance, &fill);
+        assert_eq!(account.free(&Venue::Binance, &usdt), Decimal::from(799));
+        assert_eq!(account.free(&Venue::Binance, &btc), Decimal::from(2));
+
+        let perp = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let fill = Fill::new(
+            time,
+            perp,
+            2,
+            "test".into(),
+            Price::from(100.),
+            Quantity::from(5.),
+            Notional::from(2.),
+        )
+        .with_commission_asset("bnb".into());
+        account.apply_fill(&Venue::Binance, &fill);
+        assert_eq!(account.free(&Venue::Binance, &usdt), Decimal::from(799));
+        assert_eq!(account.free(&Venue::Binance, &"bnb".into()), Decimal::from(-2));
+
+        assert!(matches!(
+            account.lock(&Venue::Binance, &usdt, Decimal::from(800)),
+            Err(ModelError::InsufficientBalance { .. })
+        ));
+        account.lock(&Venue::Binance, &usdt, Decimal::from(300)).unwrap();
+        account.unlock(&Venue::Binance, &usdt, Decimal::from(500));
+        assert_eq!(account.free(&Venue::Binance, &usdt), Decimal::from(799));
+
+        // The venue's word is final
+        account.apply_update(&AccountUpdate {
+            event_time: time,
+            venue: Venue::Binance,
+            reason: "ORDER".into(),
+            balances: vec![BalanceUpdate {
+                asset: usdt.clone(),
+                wallet: Decimal::from(900),
+                cross_wallet: Decimal::from(850),
+            }],
+            positions: vec![],
+        });
+        let balance = account.balance(&Venue::Binance, &usdt).unwrap();
+        assert_eq!((balance.free, balance.locked), (Decimal::from(850), Decimal::from(50)));
+        assert_eq!(balance.total(), Decimal::from(900));
+        assert!(account.balance(&Venue::Bybit, &usdt).is_none());
+
+        // A short receives the funding of a positive rate
+        let perp = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let short = Position::new("test".into(), perp.clone(), time, Price::from(100.), Quantity::from(-5.));
+        let rate = FundingRate {
+            event_time: time,
+            instrument: perp,
+            mark_price: Price::from(200.),
+            index_price: Price::from(200.),
+            funding_rate: Decimal::new(1, 3),
+            next_funding_time: time,
+            source: crate::ingestors::IngestorID::Test,
+        };
+        let payment = FundingPayment::new(time, &short, &rate);
+        assert_eq!(payment.amount, Notional::from(1.));
+        account.apply_funding(&payment);
+        assert_eq!(account.free(&Venue::Binance, &usdt), Decimal::from(851));
+    }
+
+    #[test]
+    fn test_order_transitions() {
+        let created = datetime!(2024-01-01 00:00 UTC);
+        let instrument = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let mut order = Order::new_market(created, 1, instrument.clone(), "test".into(), Quantity::from(1.));
+
+        // Nothing is acknowledged or filled before it is sent
+        assert!(matches!(
+            order.ack(None, created),
+            Err(ModelError::OrderTransition {
+                from: OrderStatus::New,
+                to: OrderStatus::Open,
+                ..
+            })
+        ));
+        assert!(order.fill(Price::from(100.), Quantity::from(1.), created).is_err());
+        assert!(order.quantity_filled.is_zero() && order.avg_fill_price.is_none());
+
+        order.submit(created).unwrap();
+        order.expire(created).unwrap();
+        assert!(!order.is_open());
+        assert!(order.cancel(created).is_err() && order.submit(created).is_err());
+        assert_eq!(order.status, OrderStatus::Expired);
+
+        // Venue reports move the order along, a filled order can't be cancelled anymore
+        let mut order = Order::new_market(created, 2, instrument.clone(), "test".into(), Quantity::from(1.));
+        order.submit(created).unwrap();
+        let client_order_id = order.client_order_id.clone();
+        let update = |status, quantity_filled: f64| OrderUpdate {
+            event_time: created,
+            instrument: instrument.clone(),
+            client_order_id: client_order_id.clone(),
+            venue_order_id: "42".into(),
+            order_type: OrderType::Market,
+            status,
+            price: None,
+            quantity: Quantity::from(1.),
+            quantity_filled: Quantity::from(quantity_filled),
+            avg_fill_price: Some(Price::from(100.)),
+            last_fill: None,
+        };
+        let (partial, filled, canceled) = (
+            update(OrderStatus::PartiallyFilled, 0.4),
+            update(OrderStatus::Filled, 1.),
+            update(OrderStatus::Canceled, 1.),
+        );
+        order.apply_update(&partial).unwrap();
+        order.apply_update(&filled).unwrap();
+        assert!(order.apply_update(&canceled).is_err());
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.venue_order_id.as_deref(), Some("42"));
+        assert_eq!(order.filled_time, Some(created));
+
+        // The trade of the update becomes a fill of the order with the fee the venue charged
+        let mut traded = update(OrderStatus::Filled, 1.);
+        assert!(traded.fill(&order).is_none());
+        traded.last_fill = Some(VenueFill {
+            trade_id: 7,
+            price: Price::from(100.),
+            quantity: Quantity::from(1.),
+            commission: Decimal::new(2, 2),
+            commission_asset: "bnb".into(),
+            liquidity: Liquidity::Maker,
+        });
+        let fill = traded.fill(&order).unwrap();
+        assert!(fill.order_id == 2 && fill.trade_id == Some(7) && fill.liquidity == Liquidity::Maker);
+        assert_eq!(fill.commission_asset().to_string(), "bnb");
+    }
+}
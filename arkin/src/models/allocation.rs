@@ -1,10 +1,12 @@
 use super::{Event, EventType, EventTypeOf, Instrument, Notional};
-use crate::strategies::StrategyId;
+use crate::{strategies::StrategyId, utils::custom_serde};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use time::OffsetDateTime;
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Allocation {
+    #[serde(with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
     pub instrument: Instrument,
     pub strategy_id: StrategyId,
@@ -29,7 +31,7 @@ impl Allocation {
 
 impl EventTypeOf for Allocation {
     fn event_type() -> EventType {
-        EventType::Tick
+        EventType::Allocation
     }
 }
 
@@ -1,5 +1,8 @@
+use rust_decimal::Decimal;
 use thiserror::Error;
 
+use super::OrderStatus;
+
 #[derive(Error, Debug)]
 pub enum ModelError {
     #[error("Direction not between bounds: {0}")]
@@ -10,4 +13,21 @@ pub enum ModelError {
 
     #[error("Model price: {0}")]
     PriceError(String),
+
+    #[error("Not enough {asset} free, {required} required and {free} free")]
+    InsufficientBalance {
+        asset: String,
+        required: Decimal,
+        free: Decimal,
+    },
+
+    #[error("Order {0} is invalid: {1}")]
+    InvalidOrder(u64, String),
+
+    #[error("Order {order_id} can't go from {from} to {to}")]
+    OrderTransition {
+        order_id: u64,
+        from: OrderStatus,
+        to: OrderStatus,
+    },
 }
@@ -1,23 +1,37 @@
+use serde::{Deserialize, Serialize};
 use strum::{Display, EnumDiscriminants, EnumString};
 use time::OffsetDateTime;
 
-use super::{Allocation, Book, Fill, Instrument, Order, Signal, Tick, Trade};
+use super::{
+    AccountUpdate, Allocation, Book, BookUpdate, Candle, DataGap, Fill, FundingPayment, FundingRate, Instrument,
+    Liquidation, Order, OrderUpdate, Signal, Tick, Trade,
+};
 
 pub trait EventTypeOf {
     fn event_type() -> EventType;
 }
 
-#[derive(Display, Clone, EnumDiscriminants)]
+/// Serialized as `{"type": "trade", "data": {..}}`, timestamps are unix nanoseconds and decimals strings
+#[derive(Serialize, Deserialize, Display, Clone, EnumDiscriminants)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 #[strum_discriminants(name(EventType))]
 #[strum_discriminants(derive(Hash, EnumString, Display))]
 pub enum Event {
     Tick(Tick),
     Trade(Trade),
     Book(Book),
+    BookUpdate(BookUpdate),
+    FundingRate(FundingRate),
+    Liquidation(Liquidation),
+    Candle(Candle),
+    DataGap(DataGap),
     Order(Order),
     Fill(Fill),
     Signal(Signal),
     Allocation(Allocation),
+    OrderUpdate(OrderUpdate),
+    AccountUpdate(AccountUpdate),
+    FundingPayment(FundingPayment),
 }
 
 impl Event {
@@ -27,22 +41,57 @@ impl Event {
             Event::Tick(e) => &e.event_time,
             Event::Trade(e) => &e.event_time,
             Event::Book(e) => &e.event_time,
+            Event::BookUpdate(e) => &e.event_time,
+            Event::FundingRate(e) => &e.event_time,
+            Event::Liquidation(e) => &e.event_time,
+            Event::Candle(e) => &e.event_time,
+            Event::DataGap(e) => &e.event_time,
             Event::Order(e) => &e.event_time,
             Event::Fill(e) => &e.event_time,
             Event::Signal(e) => &e.event_time,
             Event::Allocation(e) => &e.event_time,
+            Event::OrderUpdate(e) => &e.event_time,
+            Event::AccountUpdate(e) => &e.event_time,
+            Event::FundingPayment(e) => &e.event_time,
         }
     }
 
-    pub fn instrument(&self) -> &Instrument {
+    /// Time the event was received, only known for market data
+    pub fn received_time(&self) -> Option<&OffsetDateTime> {
         match self {
-            Event::Tick(e) => &e.instrument,
-            Event::Trade(e) => &e.instrument,
-            Event::Book(e) => &e.instrument,
-            Event::Order(e) => &e.instrument,
-            Event::Fill(e) => &e.instrument,
-            Event::Signal(e) => &e.instrument,
-            Event::Allocation(e) => &e.instrument,
+            Event::Trade(e) => Some(&e.received_time),
+            Event::Book(e) => Some(&e.received_time),
+            Event::BookUpdate(e) => Some(&e.received_time),
+            _ => None,
+        }
+    }
+
+    /// Time from which the event was known, the later of its event and received time
+    pub fn known_time(&self) -> &OffsetDateTime {
+        match self.received_time() {
+            Some(received_time) if received_time > self.event_time() => received_time,
+            _ => self.event_time(),
+        }
+    }
+
+    /// Account updates span several instruments and have none
+    pub fn instrument(&self) -> Option<&Instrument> {
+        match self {
+            Event::Tick(e) => Some(&e.instrument),
+            Event::Trade(e) => Some(&e.instrument),
+            Event::Book(e) => Some(&e.instrument),
+            Event::BookUpdate(e) => Some(&e.instrument),
+            Event::FundingRate(e) => Some(&e.instrument),
+            Event::Liquidation(e) => Some(&e.instrument),
+            Event::Candle(e) => Some(&e.instrument),
+            Event::DataGap(e) => Some(&e.instrument),
+            Event::Order(e) => Some(&e.instrument),
+            Event::Fill(e) => Some(&e.instrument),
+            Event::Signal(e) => Some(&e.instrument),
+            Event::Allocation(e) => Some(&e.instrument),
+            Event::OrderUpdate(e) => Some(&e.instrument),
+            Event::FundingPayment(e) => Some(&e.instrument),
+            Event::AccountUpdate(_) => None,
         }
     }
 
@@ -50,3 +99,60 @@ impl Event {
         self.into()
     }
 }
+
+impl EventType {
+    /// Published by the venues for everyone, unlike the events of our own trading
+    pub fn is_market_data(&self) -> bool {
+        matches!(
+            self,
+            EventType::Tick
+                | EventType::Trade
+                | EventType::Book
+                | EventType::BookUpdate
+                | EventType::FundingRate
+                | EventType::Liquidation
+                | EventType::Candle
+                | EventType::DataGap
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingestors::IngestorID, models::Position, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_wire_format() {
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        let instrument = test_utils::test_perp_instrument();
+        let trade = Event::Trade(Trade::new(
+            time,
+            time,
+            instrument.clone(),
+            7,
+            100.5.into(),
+            (-0.25).into(),
+            IngestorID::Binance,
+        ));
+
+        // Consumers outside this repo parse this, changing it needs a new schema version in the sinks
+        let json = serde_json::to_string(&trade).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"trade","data":{"received_time":1704067200000000000,"event_time":1704067200000000000,"instrument":{"perpetual":{"venue":"binance","base":{"underlier":"btc"},"quote":{"underlier":"usdt"}}},"trade_id":7,"price":"100.5","quantity":"-0.25","source":"binance"}}"#
+        );
+        let Event::Trade(parsed) = serde_json::from_str::<Event>(&json).unwrap() else {
+            panic!("Expected a trade");
+        };
+        assert_eq!(parsed.event_time, time);
+        assert!(parsed.instrument == instrument);
+
+        let position = Position::new("test".into(), instrument, time, 100.0.into(), 1.0.into());
+        let json = serde_json::to_string(&position).unwrap();
+        let parsed: Position = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.start_time, time);
+        assert!(parsed.exit_time.is_none());
+    }
+}
@@ -2,10 +2,24 @@ use crate::constants;
 
 use super::{types::Maturity, Price, Venue};
 use anyhow::{anyhow, Result};
-use std::{fmt, str::FromStr};
-
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub enum Instrument {
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::FromStr,
+    sync::{Arc, LazyLock},
+};
+use time::OffsetDateTime;
+
+/// Every instrument that was created, so equal instruments share one allocation
+static INSTRUMENTS: LazyLock<Mutex<HashSet<Arc<InstrumentSpec>>>> = LazyLock::new(Default::default);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum InstrumentSpec {
     Holding(Holding),
     Spot(SpotContract),
     Perpetual(PerpetualContract),
@@ -13,10 +27,66 @@ pub enum Instrument {
     Option(OptionContract),
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// Interned handle to an [`InstrumentSpec`].
+///
+/// Instruments are attached to every tick, trade and feature, so a clone is only a reference count and
+/// comparing or hashing one only looks at the pointer. Equal specs always resolve to the same handle.
+#[derive(Clone)]
+pub struct Instrument(Arc<InstrumentSpec>);
+
+impl Instrument {
+    pub fn intern(spec: InstrumentSpec) -> Self {
+        let mut instruments = INSTRUMENTS.lock();
+        if let Some(existing) = instruments.get(&spec) {
+            return Instrument(existing.clone());
+        }
+        let spec = Arc::new(spec);
+        instruments.insert(spec.clone());
+        Instrument(spec)
+    }
+}
+
+impl Deref for Instrument {
+    type Target = InstrumentSpec;
+
+    fn deref(&self) -> &InstrumentSpec {
+        &self.0
+    }
+}
+
+impl PartialEq for Instrument {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Instrument {}
+
+impl Hash for Instrument {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state)
+    }
+}
+
+impl Serialize for Instrument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Instrument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        InstrumentSpec::deserialize(deserializer).map(Instrument::intern)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum InstrumentType {
     Holding,
     Spot,
+    /// Written as perp like its display, configs may still spell it out
+    #[serde(rename = "perp", alias = "perpetual")]
     Perpetual,
     Future,
     Option,
@@ -78,19 +148,19 @@ impl Instrument {
         Ok(instrument)
     }
     pub fn holding(venue: Venue, asset: Asset) -> Self {
-        Instrument::Holding(Holding::new(venue, asset))
+        Instrument::intern(InstrumentSpec::Holding(Holding::new(venue, asset)))
     }
 
     pub fn spot(venue: Venue, base: Asset, quote: Asset) -> Self {
-        Instrument::Spot(SpotContract::new(venue, base, quote))
+        Instrument::intern(InstrumentSpec::Spot(SpotContract::new(venue, base, quote)))
     }
 
     pub fn perpetual(venue: Venue, base: Asset, quote: Asset) -> Self {
-        Instrument::Perpetual(PerpetualContract::new(venue, base, quote))
+        Instrument::intern(InstrumentSpec::Perpetual(PerpetualContract::new(venue, base, quote)))
     }
 
     pub fn future(venue: Venue, base: Asset, quote: Asset, maturity: Maturity) -> Self {
-        Instrument::Future(FutureContract::new(venue, base, quote, maturity))
+        Instrument::intern(InstrumentSpec::Future(FutureContract::new(venue, base, quote, maturity)))
     }
 
     pub fn option(
@@ -101,85 +171,105 @@ impl Instrument {
         maturity: Maturity,
         option_type: OptionType,
     ) -> Self {
-        Instrument::Option(OptionContract::new(venue, base, quote, strike, maturity, option_type))
+        Instrument::intern(InstrumentSpec::Option(OptionContract::new(
+            venue,
+            base,
+            quote,
+            strike,
+            maturity,
+            option_type,
+        )))
     }
+}
 
+impl InstrumentSpec {
     pub fn instrument_type(&self) -> &InstrumentType {
         match self {
-            Instrument::Holding(_) => &InstrumentType::Holding,
-            Instrument::Spot(_) => &InstrumentType::Spot,
-            Instrument::Perpetual(_) => &InstrumentType::Perpetual,
-            Instrument::Future(_) => &InstrumentType::Future,
-            Instrument::Option(_) => &InstrumentType::Option,
+            InstrumentSpec::Holding(_) => &InstrumentType::Holding,
+            InstrumentSpec::Spot(_) => &InstrumentType::Spot,
+            InstrumentSpec::Perpetual(_) => &InstrumentType::Perpetual,
+            InstrumentSpec::Future(_) => &InstrumentType::Future,
+            InstrumentSpec::Option(_) => &InstrumentType::Option,
         }
     }
 
     pub fn venue(&self) -> &Venue {
         match self {
-            Instrument::Holding(holding) => &holding.venue,
-            Instrument::Spot(spot) => &spot.venue,
-            Instrument::Perpetual(perpetual) => &perpetual.venue,
-            Instrument::Future(future) => &future.venue,
-            Instrument::Option(option) => &option.venue,
+            InstrumentSpec::Holding(holding) => &holding.venue,
+            InstrumentSpec::Spot(spot) => &spot.venue,
+            InstrumentSpec::Perpetual(perpetual) => &perpetual.venue,
+            InstrumentSpec::Future(future) => &future.venue,
+            InstrumentSpec::Option(option) => &option.venue,
         }
     }
 
     pub fn base(&self) -> &Asset {
         match self {
-            Instrument::Holding(holding) => &holding.asset,
-            Instrument::Spot(spot) => &spot.base,
-            Instrument::Perpetual(perpetual) => &perpetual.base,
-            Instrument::Future(future) => &future.base,
-            Instrument::Option(option) => &option.base,
+            InstrumentSpec::Holding(holding) => &holding.asset,
+            InstrumentSpec::Spot(spot) => &spot.base,
+            InstrumentSpec::Perpetual(perpetual) => &perpetual.base,
+            InstrumentSpec::Future(future) => &future.base,
+            InstrumentSpec::Option(option) => &option.base,
         }
     }
 
     pub fn quote(&self) -> &Asset {
         match self {
-            Instrument::Holding(holding) => &holding.asset,
-            Instrument::Spot(spot) => &spot.quote,
-            Instrument::Perpetual(perpetual) => &perpetual.quote,
-            Instrument::Future(future) => &future.quote,
-            Instrument::Option(option) => &option.quote,
+            InstrumentSpec::Holding(holding) => &holding.asset,
+            InstrumentSpec::Spot(spot) => &spot.quote,
+            InstrumentSpec::Perpetual(perpetual) => &perpetual.quote,
+            InstrumentSpec::Future(future) => &future.quote,
+            InstrumentSpec::Option(option) => &option.quote,
         }
     }
 
     pub fn maturity(&self) -> Option<&Maturity> {
         match self {
-            Instrument::Future(future) => Some(&future.maturity),
-            Instrument::Option(option) => Some(&option.maturity),
+            InstrumentSpec::Future(future) => Some(&future.maturity),
+            InstrumentSpec::Option(option) => Some(&option.maturity),
             _ => None,
         }
     }
 
     pub fn strike(&self) -> Option<&Price> {
         match self {
-            Instrument::Option(option) => Some(&option.strike),
+            InstrumentSpec::Option(option) => Some(&option.strike),
             _ => None,
         }
     }
 
     pub fn option_type(&self) -> Option<&OptionType> {
         match self {
-            Instrument::Option(option) => Some(&option.option_type),
+            InstrumentSpec::Option(option) => Some(&option.option_type),
             _ => None,
         }
     }
+
+    /// Futures and options can't be traded from their maturity on
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.maturity().is_some_and(|m| m.value() <= now)
+    }
 }
 
-impl fmt::Display for Instrument {
+impl fmt::Display for InstrumentSpec {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Instrument::Holding(holding) => write!(f, "holding_{}", holding),
-            Instrument::Spot(spot) => write!(f, "spot_{}", spot),
-            Instrument::Perpetual(perpetual) => write!(f, "perp_{}", perpetual),
-            Instrument::Future(future) => write!(f, "future_{}", future),
-            Instrument::Option(option) => write!(f, "option_{}", option),
+            InstrumentSpec::Holding(holding) => write!(f, "holding_{}", holding),
+            InstrumentSpec::Spot(spot) => write!(f, "spot_{}", spot),
+            InstrumentSpec::Perpetual(perpetual) => write!(f, "perp_{}", perpetual),
+            InstrumentSpec::Future(future) => write!(f, "future_{}", future),
+            InstrumentSpec::Option(option) => write!(f, "option_{}", option),
         }
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Asset {
     pub underlier: String,
 }
@@ -198,7 +288,7 @@ impl fmt::Display for Asset {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Holding {
     pub venue: Venue,
     pub asset: Asset,
@@ -216,7 +306,7 @@ impl fmt::Display for Holding {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct SpotContract {
     pub venue: Venue,
     pub base: Asset,
@@ -235,7 +325,7 @@ impl fmt::Display for SpotContract {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct PerpetualContract {
     pub venue: Venue,
     pub base: Asset,
@@ -254,7 +344,7 @@ impl fmt::Display for PerpetualContract {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct FutureContract {
     pub venue: Venue,
     pub base: Asset,
@@ -284,7 +374,7 @@ impl fmt::Display for FutureContract {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct OptionContract {
     pub venue: Venue,
     pub base: Asset,
@@ -330,7 +420,8 @@ impl fmt::Display for OptionContract {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum OptionType {
     Call,
     Put,
@@ -358,3 +449,33 @@ impl fmt::Display for OptionType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument_interning() {
+        let a = Instrument::perpetual(Venue::Binance, "BTC".into(), "USDT".into());
+        let b = Instrument::perpetual(Venue::Binance, "btc".into(), "usdt".into());
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert!(a != Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into()));
+
+        // Deserialized instruments resolve to the same handle
+        let json = serde_json::to_string(&a).unwrap();
+        let c: Instrument = serde_json::from_str(&json).unwrap();
+        assert!(Arc::ptr_eq(&a.0, &c.0));
+        assert_eq!(c.to_string(), "perp_btc_usdt@binance");
+    }
+
+    #[test]
+    fn test_instrument_type_strings() {
+        use InstrumentType::*;
+        for instrument_type in [Holding, Spot, Perpetual, Future, Option] {
+            let json = serde_json::to_string(&instrument_type).unwrap();
+            assert_eq!(json, format!("\"{}\"", instrument_type));
+            assert_eq!(instrument_type.to_string().parse::<InstrumentType>().unwrap(), instrument_type);
+        }
+        assert_eq!(serde_json::from_str::<InstrumentType>("\"perpetual\"").unwrap(), Perpetual);
+    }
+}
@@ -0,0 +1,554 @@
+use crate::{ingestors::IngestorID, utils::custom_serde};
+
+mod order_book;
+
+pub use order_book::{BookSide, OrderBook};
+
+use super::{Event, EventType, EventTypeOf, Instrument, Notional, Price, Quantity};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use time::{Duration, OffsetDateTime};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Tick {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub tick_id: u64,
+    pub bid_price: Price,
+    pub bid_quantity: Quantity,
+    pub ask_price: Price,
+    pub ask_quantity: Quantity,
+    pub source: IngestorID,
+}
+
+impl Tick {
+    pub fn new(
+        event_time: OffsetDateTime,
+        instrument: Instrument,
+        tick_id: u64,
+        bid_price: Price,
+        bid_quantity: Quantity,
+        ask_price: Price,
+        ask_quantity: Quantity,
+    ) -> Self {
+        Self {
+            event_time,
+            instrument,
+            tick_id,
+            bid_price,
+            bid_quantity,
+            ask_price,
+            ask_quantity,
+            source: IngestorID::Test,
+        }
+    }
+
+    pub fn spread(&self) -> Decimal {
+        self.ask_price - self.bid_price
+    }
+
+    pub fn mid_price(&self) -> Price {
+        ((self.bid_price + self.ask_price).value() / Decimal::from(2)).into()
+    }
+}
+
+impl EventTypeOf for Tick {
+    fn event_type() -> EventType {
+        EventType::Tick
+    }
+}
+
+impl TryFrom<Event> for Tick {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::Tick(tick) = event {
+            Ok(tick)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} bid: {} {} ask: {} {}",
+            self.instrument, self.event_time, self.bid_price, self.bid_quantity, self.ask_price, self.ask_quantity
+        )
+    }
+}
+
+/// Side of the taker of a trade, the sign of the trade quantity carries it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// Venues like Binance flag whether the buyer was the maker, then the taker sold
+    pub fn from_buyer_maker(buyer_is_maker: bool) -> Self {
+        if buyer_is_maker {
+            Side::Sell
+        } else {
+            Side::Buy
+        }
+    }
+
+    /// The unsigned quantity with the sign of the side
+    pub fn signed(&self, quantity: Decimal) -> Decimal {
+        match self {
+            Side::Buy => quantity.abs(),
+            Side::Sell => -quantity.abs(),
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Buy => write!(f, "buy"),
+            Side::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trade {
+    #[serde(with = "custom_serde::timestamp")]
+    pub received_time: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub trade_id: u64,
+    pub price: Price,
+    pub quantity: Quantity, // Negative for sell, positive for buy
+    pub source: IngestorID,
+}
+
+impl Trade {
+    pub fn new(
+        received_time: OffsetDateTime,
+        event_time: OffsetDateTime,
+        instrument: Instrument,
+        trade_id: u64,
+        price: Price,
+        quantity: Quantity,
+        source: IngestorID,
+    ) -> Self {
+        Self {
+            received_time,
+            event_time,
+            instrument,
+            trade_id,
+            price,
+            quantity,
+            source,
+        }
+    }
+
+    /// Side of the taker
+    pub fn aggressor(&self) -> Side {
+        if self.quantity.is_negative() {
+            Side::Sell
+        } else {
+            Side::Buy
+        }
+    }
+}
+
+impl EventTypeOf for Trade {
+    fn event_type() -> EventType {
+        EventType::Trade
+    }
+}
+
+impl TryFrom<Event> for Trade {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::Trade(trade) = event {
+            Ok(trade)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.instrument, self.event_time, self.price, self.quantity)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Book {
+    #[serde(with = "custom_serde::timestamp")]
+    pub received_time: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub bids: Vec<BookUpdateSide>,
+    pub asks: Vec<BookUpdateSide>,
+    pub source: IngestorID,
+}
+
+impl Book {
+    pub fn new(
+        received_time: OffsetDateTime,
+        event_time: OffsetDateTime,
+        instrument: Instrument,
+        bids: Vec<BookUpdateSide>,
+        asks: Vec<BookUpdateSide>,
+        source: IngestorID,
+    ) -> Self {
+        Self {
+            received_time,
+            event_time,
+            instrument,
+            bids,
+            asks,
+            source,
+        }
+    }
+
+    /// The best levels of each side, bids from the highest and asks from the lowest price
+    pub fn top(&self, levels: usize) -> (Vec<&BookUpdateSide>, Vec<&BookUpdateSide>) {
+        let mut bids = self.bids.iter().filter(|l| !l.quantity.value().is_zero()).collect::<Vec<_>>();
+        let mut asks = self.asks.iter().filter(|l| !l.quantity.value().is_zero()).collect::<Vec<_>>();
+        bids.sort_by_key(|l| std::cmp::Reverse(l.price.value()));
+        asks.sort_by_key(|l| l.price.value());
+        bids.truncate(levels);
+        asks.truncate(levels);
+        (bids, asks)
+    }
+
+    /// Bid minus ask quantity of the best levels relative to their sum, between -1 and 1
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        let (bids, asks) = self.top(levels);
+        let bid = bids.iter().map(|l| l.quantity.value()).sum::<Decimal>();
+        let ask = asks.iter().map(|l| l.quantity.value()).sum::<Decimal>();
+        let total = bid + ask;
+        (!total.is_zero()).then(|| (bid - ask) / total)
+    }
+
+    /// Mid of the best bid and ask weighted towards the side with less quantity, where the price is more
+    /// likely to move next
+    pub fn microprice(&self) -> Option<Price> {
+        let (bids, asks) = self.top(1);
+        let (bid, ask) = (bids.first()?, asks.first()?);
+        let total = bid.quantity.value() + ask.quantity.value();
+        let price = (bid.price.value() * ask.quantity.value() + ask.price.value() * bid.quantity.value()) / total;
+        Some(price.into())
+    }
+
+    /// Mid of the volume weighted prices of each side over the best levels
+    pub fn weighted_mid(&self, levels: usize) -> Option<Price> {
+        let (bids, asks) = self.top(levels);
+        let vwap = |side: &[&BookUpdateSide]| {
+            let quantity = side.iter().map(|l| l.quantity.value()).sum::<Decimal>();
+            let notional = side.iter().map(|l| l.price.value() * l.quantity.value()).sum::<Decimal>();
+            (!quantity.is_zero()).then(|| notional / quantity)
+        };
+        Some(((vwap(&bids)? + vwap(&asks)?) / Decimal::TWO).into())
+    }
+}
+
+impl EventTypeOf for Book {
+    fn event_type() -> EventType {
+        EventType::Book
+    }
+}
+
+impl TryFrom<Event> for Book {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::Book(book) = event {
+            Ok(book)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Book {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} bid: {} ask: {}",
+            self.instrument,
+            self.event_time,
+            self.bids.len(),
+            self.asks.len()
+        )
+    }
+}
+
+/// Mark price and funding of a perpetual, the rate is paid by longs to shorts at the next funding time
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FundingRate {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub mark_price: Price,
+    pub index_price: Price,
+    pub funding_rate: Decimal,
+    #[serde(with = "custom_serde::timestamp")]
+    pub next_funding_time: OffsetDateTime,
+    pub source: IngestorID,
+}
+
+impl EventTypeOf for FundingRate {
+    fn event_type() -> EventType {
+        EventType::FundingRate
+    }
+}
+
+impl TryFrom<Event> for FundingRate {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::FundingRate(funding) = event {
+            Ok(funding)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for FundingRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} mark: {} funding: {} next: {}",
+            self.instrument, self.event_time, self.mark_price, self.funding_rate, self.next_funding_time
+        )
+    }
+}
+
+/// Position closed by the venue's liquidation engine, a sell liquidates a long and has a negative quantity
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Liquidation {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub price: Price,
+    pub avg_price: Price,
+    pub quantity: Quantity,
+    pub quantity_filled: Quantity,
+    pub source: IngestorID,
+}
+
+impl Liquidation {
+    pub fn notional(&self) -> Decimal {
+        self.avg_price.value() * self.quantity_filled.value().abs()
+    }
+}
+
+impl EventTypeOf for Liquidation {
+    fn event_type() -> EventType {
+        EventType::Liquidation
+    }
+}
+
+impl TryFrom<Event> for Liquidation {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::Liquidation(liquidation) = event {
+            Ok(liquidation)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Liquidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} liquidation: {} @ {}",
+            self.instrument, self.event_time, self.quantity_filled, self.avg_price
+        )
+    }
+}
+
+/// Trades the venue published but the ingestor never received, found from a jump in the trade ids
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DataGap {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub first_missing_id: u64,
+    pub last_missing_id: u64,
+    pub source: IngestorID,
+}
+
+impl DataGap {
+    pub fn missing(&self) -> u64 {
+        self.last_missing_id - self.first_missing_id + 1
+    }
+}
+
+impl EventTypeOf for DataGap {
+    fn event_type() -> EventType {
+        EventType::DataGap
+    }
+}
+
+impl TryFrom<Event> for DataGap {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::DataGap(gap) = event {
+            Ok(gap)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for DataGap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} gap of {} trades: {} to {}",
+            self.instrument,
+            self.event_time,
+            self.missing(),
+            self.first_missing_id,
+            self.last_missing_id
+        )
+    }
+}
+
+/// OHLCV bar of a fixed interval, updated while it is open and final once closed
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Candle {
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    #[serde(with = "custom_serde::duration_from_nanos")]
+    pub interval: Duration,
+    #[serde(with = "custom_serde::timestamp")]
+    pub open_time: OffsetDateTime,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Quantity,
+    pub quote_volume: Notional,
+    pub trades: u64,
+    pub closed: bool,
+    pub source: IngestorID,
+}
+
+impl Candle {
+    pub fn close_time(&self) -> OffsetDateTime {
+        self.open_time + self.interval
+    }
+}
+
+impl EventTypeOf for Candle {
+    fn event_type() -> EventType {
+        EventType::Candle
+    }
+}
+
+impl TryFrom<Event> for Candle {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::Candle(candle) = event {
+            Ok(candle)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Candle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} o: {} h: {} l: {} c: {} v: {}",
+            self.instrument, self.open_time, self.interval, self.open, self.high, self.low, self.close, self.volume
+        )
+    }
+}
+
+/// Top levels of a locally maintained order book after an update was applied, bids best first
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BookUpdate {
+    #[serde(with = "custom_serde::timestamp")]
+    pub received_time: OffsetDateTime,
+    #[serde(with = "custom_serde::timestamp")]
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub update_id: u64,
+    pub bids: Vec<BookUpdateSide>,
+    pub asks: Vec<BookUpdateSide>,
+    pub source: IngestorID,
+}
+
+impl BookUpdate {
+    pub fn best_bid(&self) -> Option<&BookUpdateSide> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&BookUpdateSide> {
+        self.asks.first()
+    }
+}
+
+impl EventTypeOf for BookUpdate {
+    fn event_type() -> EventType {
+        EventType::BookUpdate
+    }
+}
+
+impl TryFrom<Event> for BookUpdate {
+    type Error = ();
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        if let Event::BookUpdate(update) = event {
+            Ok(update)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for BookUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} update: {}", self.instrument, self.event_time, self.update_id)?;
+        if let (Some(bid), Some(ask)) = (self.best_bid(), self.best_ask()) {
+            write!(f, " bid: {} ask: {}", bid, ask)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BookUpdateSide {
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+impl BookUpdateSide {
+    pub fn new(price: Price, quantity: Quantity) -> Self {
+        Self { price, quantity }
+    }
+}
+
+impl fmt::Display for BookUpdateSide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.price, self.quantity)
+    }
+}
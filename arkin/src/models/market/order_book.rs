@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use super::{Book, BookUpdate, BookUpdateSide};
+use crate::models::{Price, Quantity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// L2 price levels of one instrument, sorted so the best levels of both sides are at hand
+#[derive(Clone, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    /// Book of the levels of a snapshot
+    pub fn from_levels(bids: &[BookUpdateSide], asks: &[BookUpdateSide]) -> Self {
+        let mut book = Self::default();
+        book.apply(bids, asks);
+        book
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Sets the quantity at a price, a zero quantity removes the level
+    pub fn update(&mut self, side: BookSide, price: Decimal, quantity: Decimal) {
+        let levels = match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        };
+        if quantity.is_zero() {
+            levels.remove(&price);
+        } else {
+            levels.insert(price, quantity);
+        }
+    }
+
+    /// Applies the changed levels of both sides
+    pub fn apply(&mut self, bids: &[BookUpdateSide], asks: &[BookUpdateSide]) {
+        for (side, levels) in [(BookSide::Bid, bids), (BookSide::Ask, asks)] {
+            for level in levels {
+                self.update(side, level.price.value(), level.quantity.value());
+            }
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<BookUpdateSide> {
+        self.bids.iter().next_back().map(level)
+    }
+
+    pub fn best_ask(&self) -> Option<BookUpdateSide> {
+        self.asks.iter().next().map(level)
+    }
+
+    pub fn spread(&self) -> Option<Price> {
+        Some((self.best_ask()?.price.value() - self.best_bid()?.price.value()).into())
+    }
+
+    pub fn mid(&self) -> Option<Price> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        Some(((bid.price.value() + ask.price.value()) / Decimal::TWO).into())
+    }
+
+    /// Mid weighted by the opposite quantities, it leans towards the side with less quantity left to take
+    pub fn microprice(&self) -> Option<Price> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        let (bid_quantity, ask_quantity) = (bid.quantity.value(), ask.quantity.value());
+        let price =
+            (bid.price.value() * ask_quantity + ask.price.value() * bid_quantity) / (bid_quantity + ask_quantity);
+        Some(price.into())
+    }
+
+    /// Best levels first, at most `depth` per side
+    pub fn levels(&self, depth: usize) -> (Vec<BookUpdateSide>, Vec<BookUpdateSide>) {
+        (
+            self.bids.iter().rev().take(depth).map(level).collect(),
+            self.asks.iter().take(depth).map(level).collect(),
+        )
+    }
+
+    /// Quantity resting on a side within the best `depth` levels
+    pub fn depth(&self, side: BookSide, depth: usize) -> Quantity {
+        let quantity = match side {
+            BookSide::Bid => self.bids.values().rev().take(depth).sum::<Decimal>(),
+            BookSide::Ask => self.asks.values().take(depth).sum::<Decimal>(),
+        };
+        quantity.into()
+    }
+
+    /// Bid minus ask quantity over their sum within the best `depth` levels, from -1 with only asks to 1 with
+    /// only bids
+    pub fn imbalance(&self, depth: usize) -> Option<Decimal> {
+        let bids = self.depth(BookSide::Bid, depth).value();
+        let asks = self.depth(BookSide::Ask, depth).value();
+        let total = bids + asks;
+        (!total.is_zero()).then(|| (bids - asks) / total)
+    }
+}
+
+fn level((price, quantity): (&Decimal, &Decimal)) -> BookUpdateSide {
+    BookUpdateSide::new((*price).into(), (*quantity).into())
+}
+
+impl From<&Book> for OrderBook {
+    fn from(book: &Book) -> Self {
+        Self::from_levels(&book.bids, &book.asks)
+    }
+}
+
+/// Only the top levels the update carries
+impl From<&BookUpdate> for OrderBook {
+    fn from(update: &BookUpdate) -> Self {
+        Self::from_levels(&update.bids, &update.asks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_book() {
+        let side = |price: i64, quantity: i64| {
+            BookUpdateSide::new(Decimal::from(price).into(), Decimal::from(quantity).into())
+        };
+        let mut book = OrderBook::from_levels(&[side(99, 3), side(100, 1), side(98, 4)], &[side(101, 3), side(102, 2)]);
+        assert_eq!(book.best_bid().unwrap().price.value(), Decimal::from(100));
+        assert_eq!(book.best_ask().unwrap().price.value(), Decimal::from(101));
+        assert_eq!(book.spread().unwrap().value(), Decimal::ONE);
+        assert_eq!(book.mid().unwrap().value(), Decimal::new(1005, 1));
+        // One lot bid against three offered, the price is more likely to tick down
+        assert_eq!(book.microprice().unwrap().value(), Decimal::new(10025, 2));
+
+        assert_eq!(book.depth(BookSide::Bid, 2).value(), Decimal::from(4));
+        assert_eq!(book.depth(BookSide::Ask, 10).value(), Decimal::from(5));
+        assert_eq!(book.imbalance(1).unwrap(), Decimal::new(-5, 1));
+        let (bids, asks) = book.levels(2);
+        assert_eq!(
+            bids.iter().map(|l| l.price.value()).collect::<Vec<_>>(),
+            [100, 99].map(Decimal::from)
+        );
+        assert_eq!(asks.len(), 2);
+
+        // The best bid is taken out and the next level moves up
+        book.apply(&[side(100, 0), side(97, 1)], &[]);
+        assert_eq!(book.best_bid().unwrap().price.value(), Decimal::from(99));
+        assert_eq!(book.levels(10).0.len(), 3);
+
+        book.update(BookSide::Ask, Decimal::from(101), Decimal::ZERO);
+        book.update(BookSide::Ask, Decimal::from(102), Decimal::ZERO);
+        assert!(book.best_ask().is_none() && book.mid().is_none());
+        assert_eq!(book.imbalance(5).unwrap(), Decimal::ONE);
+        book.clear();
+        assert!(book.is_empty() && book.imbalance(5).is_none());
+    }
+}
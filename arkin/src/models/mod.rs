@@ -5,6 +5,9 @@ mod allocation;
 mod events;
 mod instrument;
 mod market;
+mod pricing;
+mod reference;
+mod risk;
 mod strategy;
 mod types;
 mod venue;
@@ -14,6 +17,9 @@ pub use allocation::*;
 pub use events::*;
 pub use instrument::*;
 pub use market::*;
+pub use pricing::*;
+pub use reference::*;
+pub use risk::*;
 pub use strategy::*;
 pub use types::*;
 pub use venue::*;
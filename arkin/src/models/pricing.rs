@@ -0,0 +1,142 @@
+use std::f64::consts::{PI, SQRT_2};
+
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::{OptionContract, OptionType, Price};
+
+/// Black-76 value and sensitivities of one option, vega per unit of volatility and theta per year
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Greeks {
+    pub price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+}
+
+/// Black-76 on the forward, which is the perpetual or future price for crypto options
+pub fn black76(option_type: &OptionType, forward: f64, strike: f64, volatility: f64, rate: f64, years: f64) -> Greeks {
+    let discount = (-rate * years).exp();
+    let sign = match option_type {
+        OptionType::Call => 1.,
+        OptionType::Put => -1.,
+    };
+
+    // Expired or without volatility the option is worth its discounted intrinsic value
+    if years <= 0. || volatility <= 0. {
+        let in_the_money = sign * (forward - strike) > 0.;
+        return Greeks {
+            price: discount * (sign * (forward - strike)).max(0.),
+            delta: if in_the_money { sign * discount } else { 0. },
+            gamma: 0.,
+            vega: 0.,
+            theta: 0.,
+        };
+    }
+
+    let deviation = volatility * years.sqrt();
+    let d1 = ((forward / strike).ln() + deviation * deviation / 2.) / deviation;
+    let d2 = d1 - deviation;
+    let price = discount * sign * (forward * norm_cdf(sign * d1) - strike * norm_cdf(sign * d2));
+    Greeks {
+        price,
+        delta: sign * discount * norm_cdf(sign * d1),
+        gamma: discount * norm_pdf(d1) / (forward * deviation),
+        vega: forward * discount * norm_pdf(d1) * years.sqrt(),
+        theta: -forward * discount * norm_pdf(d1) * volatility / (2. * years.sqrt()) + rate * price,
+    }
+}
+
+impl OptionContract {
+    /// Value if exercised against the given underlying price
+    pub fn intrinsic_value(&self, underlying: Price) -> Price {
+        let value = match self.option_type {
+            OptionType::Call => underlying.value() - self.strike.value(),
+            OptionType::Put => self.strike.value() - underlying.value(),
+        };
+        value.max(Decimal::ZERO).into()
+    }
+
+    pub fn greeks(&self, forward: Price, volatility: f64, rate: f64, now: OffsetDateTime) -> Greeks {
+        black76(
+            &self.option_type,
+            forward.value().to_f64().unwrap_or(f64::NAN),
+            self.strike.value().to_f64().unwrap_or(f64::NAN),
+            volatility,
+            rate,
+            self.maturity.time_to_maturity_in_years(now).max(0.),
+        )
+    }
+}
+
+fn norm_pdf(x: f64) -> f64 {
+    (-x * x / 2.).exp() / (2. * PI).sqrt()
+}
+
+fn norm_cdf(x: f64) -> f64 {
+    erfc(-x / SQRT_2) / 2.
+}
+
+/// Complementary error function with a relative error below 1.2e-7 (Numerical Recipes)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + z / 2.);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0. {
+        r
+    } else {
+        2. - r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Instrument, InstrumentSpec, Venue};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_black76() {
+        let call = black76(&OptionType::Call, 100., 100., 0.2, 0.05, 1.);
+        let put = black76(&OptionType::Put, 100., 100., 0.2, 0.05, 1.);
+        // At the money: e^-rT * F * (2N(sigma/2) - 1)
+        assert!((call.price - 7.5771).abs() < 1e-3);
+        // Put-call parity: C - P = e^-rT (F - K)
+        assert!((call.price - put.price).abs() < 1e-9);
+        assert!((call.delta - put.delta - (-0.05f64).exp()).abs() < 1e-9);
+        assert!((call.gamma - put.gamma).abs() < 1e-12);
+        assert!(call.vega > 0. && call.theta < 0.);
+
+        let expired = black76(&OptionType::Put, 90., 100., 0.2, 0.05, 0.);
+        assert_eq!(expired.price, 10.);
+        assert_eq!(expired.delta, -1.);
+
+        let now = datetime!(2024-10-01 08:00 UTC);
+        let option = Instrument::option(
+            Venue::Binance,
+            "BTC".into(),
+            "USDT".into(),
+            Price::from(60000.),
+            datetime!(2024-10-25 08:00 UTC).into(),
+            OptionType::Call,
+        );
+        let InstrumentSpec::Option(contract) = &*option else {
+            panic!("not an option");
+        };
+        assert_eq!(contract.intrinsic_value(Price::from(65000.)), Price::from(5000.));
+        assert_eq!(contract.intrinsic_value(Price::from(55000.)), Price::from(0.));
+        assert!(!option.is_expired(now));
+        assert!(option.is_expired(datetime!(2024-10-25 08:00 UTC)));
+        assert!(contract.greeks(Price::from(65000.), 0.5, 0., now).price > 5000.);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::InstrumentConfig;
+
+use super::{Asset, Instrument, Notional, Price, Quantity, Venue};
+
+/// Trading rules of an instrument as the venue publishes them
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstrumentInfo {
+    /// Name of the instrument on the venue, e.g. BTCUSDT
+    pub symbol: String,
+    pub tick_size: Price,
+    pub lot_size: Quantity,
+    pub min_notional: Notional,
+    /// Units of the base asset one contract is worth
+    pub contract_multiplier: Decimal,
+    pub settlement: Asset,
+}
+
+impl InstrumentInfo {
+    /// Nearest price the venue accepts
+    pub fn round_price(&self, price: Price) -> Price {
+        price.round_to_tick(self.tick_size)
+    }
+
+    /// Limit price on the tick away from the market, down for buys and up for sells, so rounding never makes an
+    /// order more aggressive
+    pub fn round_limit_price(&self, price: Price, quantity: Quantity) -> Price {
+        if quantity.is_positive() {
+            price.floor_to_tick(self.tick_size)
+        } else {
+            price.ceil_to_tick(self.tick_size)
+        }
+    }
+
+    /// Quantity rounded towards zero to a whole lot, so an order never ends up larger than intended
+    pub fn round_quantity(&self, quantity: Quantity) -> Quantity {
+        quantity.floor_to_step(self.lot_size)
+    }
+
+    /// Whole lots worth at most the notional at the price, signed like the notional
+    pub fn quantity_for(&self, notional: Notional, price: Price) -> Quantity {
+        if price.value().is_zero() || self.contract_multiplier.is_zero() {
+            return Quantity::from(0.);
+        }
+        self.round_quantity((notional.value() / price.value() / self.contract_multiplier).into())
+    }
+
+    /// Value of the quantity in the quote currency
+    pub fn notional(&self, price: Price, quantity: Quantity) -> Notional {
+        (price.value() * quantity.value() * self.contract_multiplier).abs().into()
+    }
+
+    pub fn meets_min_notional(&self, price: Price, quantity: Quantity) -> bool {
+        self.notional(price, quantity) >= self.min_notional
+    }
+}
+
+/// Reference data of every known instrument, instruments without an entry are traded unrounded
+#[derive(Default)]
+pub struct InstrumentRegistry {
+    instruments: RwLock<HashMap<Instrument, InstrumentInfo>>,
+    /// Spot and derivatives can share a symbol on the same venue
+    symbols: RwLock<HashMap<(Venue, String), Vec<Instrument>>>,
+}
+
+impl InstrumentRegistry {
+    /// Registry of the configured instruments, entries that don't describe a valid instrument are skipped
+    pub fn from_config(config: &[InstrumentConfig]) -> Self {
+        let registry = InstrumentRegistry::default();
+        for c in config {
+            match Instrument::new(
+                &c.instrument_type,
+                c.venue.clone(),
+                c.base.as_str().into(),
+                c.quote.as_str().into(),
+                c.maturity.map(|m| m.into()),
+                c.strike.map(|s| s.into()),
+                c.option_type.clone(),
+            ) {
+                Ok(instrument) => registry.insert(
+                    instrument,
+                    InstrumentInfo {
+                        symbol: c.symbol.clone(),
+                        tick_size: c.tick_size.into(),
+                        lot_size: c.lot_size.into(),
+                        min_notional: c.min_notional.into(),
+                        contract_multiplier: c.contract_multiplier,
+                        settlement: c.settlement.as_str().into(),
+                    },
+                ),
+                Err(e) => warn!("Skipping reference data of {}_{}: {}", c.base, c.quote, e),
+            }
+        }
+        registry
+    }
+
+    pub fn insert(&self, instrument: Instrument, info: InstrumentInfo) {
+        let key = (instrument.venue().clone(), info.symbol.to_uppercase());
+        let mut symbols = self.symbols.write();
+        let entry = symbols.entry(key).or_default();
+        if !entry.contains(&instrument) {
+            entry.push(instrument.clone());
+        }
+        self.instruments.write().insert(instrument, info);
+    }
+
+    /// Instruments listed under the symbol on the venue
+    pub fn by_symbol(&self, venue: &Venue, symbol: &str) -> Vec<Instrument> {
+        self.symbols
+            .read()
+            .get(&(venue.clone(), symbol.to_uppercase()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Changes the entry of a known instrument, returns false for instruments without one
+    pub fn update(&self, instrument: &Instrument, update: impl FnOnce(&mut InstrumentInfo)) -> bool {
+        match self.instruments.write().get_mut(instrument) {
+            Some(info) => {
+                update(info);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, instrument: &Instrument) -> Option<InstrumentInfo> {
+        self.instruments.read().get(instrument).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument_info() {
+        let info = InstrumentInfo {
+            symbol: "BTCUSDT".into(),
+            tick_size: 0.1.into(),
+            lot_size: 0.001.into(),
+            min_notional: Notional::from(100.),
+            contract_multiplier: Decimal::ONE,
+            settlement: "usdt".into(),
+        };
+        assert_eq!(info.round_price(Price::from(50000.04)), Price::from(50000.0));
+        assert_eq!(info.round_price(Price::from(50000.06)), Price::from(50000.1));
+        assert_eq!(info.round_quantity(Quantity::from(0.0129)), Quantity::from(0.012));
+        assert_eq!(info.round_quantity(Quantity::from(-0.0129)), Quantity::from(-0.012));
+        assert!(info.meets_min_notional(Price::from(50000.), Quantity::from(-0.002)));
+        assert!(!info.meets_min_notional(Price::from(50000.), Quantity::from(0.001)));
+
+        assert_eq!(
+            info.round_limit_price(Price::from(50000.07), Quantity::from(1.)),
+            Price::from(50000.0)
+        );
+        assert_eq!(
+            info.round_limit_price(Price::from(50000.01), Quantity::from(-1.)),
+            Price::from(50000.1)
+        );
+        // 1000 / 30000 is 0.0333.. which no venue takes
+        assert_eq!(
+            info.quantity_for(Notional::from(-1000.), Price::from(30000.)),
+            Quantity::from(-0.033)
+        );
+        let contracts = InstrumentInfo {
+            contract_multiplier: Decimal::from(100),
+            lot_size: 1.0.into(),
+            ..info
+        };
+        assert_eq!(
+            contracts.quantity_for(Notional::from(25000.), Price::from(60.)),
+            Quantity::from(4.)
+        );
+    }
+}
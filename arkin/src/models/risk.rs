@@ -0,0 +1,39 @@
+use std::fmt;
+use time::OffsetDateTime;
+
+use crate::{constants::TIMESTAMP_FORMAT, strategies::StrategyId};
+
+use super::Instrument;
+
+/// Raised when an order or position breaks a limit
+#[derive(Clone)]
+pub struct RiskEvent {
+    pub event_time: OffsetDateTime,
+    pub instrument: Instrument,
+    pub strategy_id: StrategyId,
+    pub reason: String,
+}
+
+impl RiskEvent {
+    pub fn new(event_time: OffsetDateTime, instrument: Instrument, strategy_id: StrategyId, reason: &str) -> Self {
+        RiskEvent {
+            event_time,
+            instrument,
+            strategy_id,
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for RiskEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RISK {} {} {} {}",
+            self.event_time.format(TIMESTAMP_FORMAT).unwrap(),
+            self.strategy_id,
+            self.instrument,
+            self.reason
+        )
+    }
+}
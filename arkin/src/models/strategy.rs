@@ -1,16 +1,28 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 
-use crate::strategies::StrategyId;
+use crate::{strategies::StrategyId, utils::custom_serde};
 
 use super::{Event, EventType, EventTypeOf, Instrument, Weight};
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Signal {
+    #[serde(with = "custom_serde::timestamp")]
     pub event_time: OffsetDateTime,
     pub instrument: Instrument,
     pub strategy_id: StrategyId,
     pub signal: Weight,
+    /// How sure the strategy is of the signal, between 0 and 1
+    #[serde(default)]
+    pub confidence: Option<Decimal>,
+    /// How long the strategy expects the signal to play out
+    #[serde(default, with = "custom_serde::duration_from_nanos::option")]
+    pub horizon: Option<Duration>,
+    /// From then on the signal no longer holds and counts as flat
+    #[serde(default, with = "custom_serde::timestamp::option")]
+    pub expires_at: Option<OffsetDateTime>,
 }
 
 impl Signal {
@@ -20,6 +32,38 @@ impl Signal {
             instrument,
             strategy_id,
             signal,
+            confidence: None,
+            horizon: None,
+            expires_at: None,
+        }
+    }
+
+    pub fn with_confidence(mut self, confidence: Decimal) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    pub fn with_horizon(mut self, horizon: Duration) -> Self {
+        self.horizon = Some(horizon);
+        self
+    }
+
+    /// Expires the signal the time to live after it was emitted
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(self.event_time + ttl);
+        self
+    }
+
+    pub fn is_expired(&self, timestamp: &OffsetDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| *timestamp >= expires_at)
+    }
+
+    /// The weight that holds at the timestamp, flat once the signal expired
+    pub fn weight_at(&self, timestamp: &OffsetDateTime) -> Weight {
+        if self.is_expired(timestamp) {
+            Weight::from(0.)
+        } else {
+            self.signal.clone()
         }
     }
 }
@@ -51,3 +95,32 @@ impl TryFrom<Event> for Signal {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_signal_expiry() {
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        let signal = Signal::new(time, test_utils::test_perp_instrument(), "test".into(), 0.5.into())
+            .with_confidence(Decimal::new(8, 1))
+            .with_horizon(Duration::hours(4))
+            .with_ttl(Duration::minutes(5));
+
+        assert!(signal.weight_at(&(time + Duration::minutes(4))) == Weight::from(0.5));
+        assert!(signal.is_expired(&(time + Duration::minutes(5))));
+        assert!(signal.weight_at(&(time + Duration::minutes(5))) == Weight::from(0.));
+
+        let parsed: Signal = serde_json::from_str(&serde_json::to_string(&signal).unwrap()).unwrap();
+        assert_eq!(parsed.horizon, Some(Duration::hours(4)));
+        assert_eq!(parsed.expires_at, signal.expires_at);
+
+        // Signals written before the metadata existed still parse
+        let json = r#"{"event_time":1704067200000000000,"instrument":{"perpetual":{"venue":"binance","base":{"underlier":"btc"},"quote":{"underlier":"usdt"}}},"strategy_id":"test","signal":"1"}"#;
+        let parsed: Signal = serde_json::from_str(json).unwrap();
+        assert!(parsed.confidence.is_none() && !parsed.is_expired(&(time + Duration::days(365))));
+    }
+}
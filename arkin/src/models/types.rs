@@ -1,17 +1,17 @@
 use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, Mul, Sub};
 use time::OffsetDateTime;
 
-use crate::constants;
+use crate::{constants, utils::custom_serde};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Maturity(OffsetDateTime);
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Maturity(#[serde(with = "custom_serde::timestamp")] OffsetDateTime);
 
 impl Maturity {
-    pub fn time_to_maturity_in_years(&self) -> f64 {
-        let now = OffsetDateTime::now_utc();
+    pub fn time_to_maturity_in_years(&self, now: OffsetDateTime) -> f64 {
         let duration = self.0 - now;
         duration.whole_seconds() as f64 / 60.0 / 60.0 / 24.0 / 365.0
     }
@@ -38,13 +38,33 @@ impl fmt::Display for Maturity {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Price(Decimal);
 
 impl Price {
     pub fn value(&self) -> Decimal {
         self.0
     }
+
+    /// Nearest multiple of the tick, a zero tick leaves the price as is
+    pub fn round_to_tick(&self, tick: Price) -> Price {
+        to_step(self.0, tick.0, Decimal::round)
+    }
+
+    pub fn floor_to_tick(&self, tick: Price) -> Price {
+        to_step(self.0, tick.0, Decimal::floor)
+    }
+
+    pub fn ceil_to_tick(&self, tick: Price) -> Price {
+        to_step(self.0, tick.0, Decimal::ceil)
+    }
+}
+
+fn to_step<T: From<Decimal>>(value: Decimal, step: Decimal, round: fn(&Decimal) -> Decimal) -> T {
+    if step.is_zero() {
+        return value.into();
+    }
+    (round(&(value / step)) * step).into()
 }
 
 impl From<f64> for Price {
@@ -111,7 +131,7 @@ impl Div<Quantity> for Price {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Quantity(Decimal);
 
 impl Quantity {
@@ -135,6 +155,11 @@ impl Quantity {
         Self::from(self.0.abs())
     }
 
+    /// Whole steps towards zero, so the quantity never ends up larger than intended
+    pub fn floor_to_step(&self, step: Quantity) -> Quantity {
+        to_step(self.0, step.0, Decimal::trunc)
+    }
+
     fn round(decimal: Decimal) -> Decimal {
         decimal.round_dp(8)
     }
@@ -219,7 +244,7 @@ impl Div<Quantity> for Quantity {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
 pub struct Notional(Decimal);
 
 impl Notional {
@@ -319,7 +344,7 @@ impl Sum<Notional> for Notional {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Weight(Decimal);
 
 impl Weight {
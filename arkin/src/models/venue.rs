@@ -5,9 +5,12 @@ use serde::{Deserialize, Serialize};
 use super::errors::ModelError;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum Venue {
     Simulation,
     Binance,
+    Coinbase,
+    Bybit,
 }
 
 impl fmt::Display for Venue {
@@ -15,6 +18,8 @@ impl fmt::Display for Venue {
         match self {
             Venue::Simulation => write!(f, "simulation"),
             Venue::Binance => write!(f, "binance"),
+            Venue::Coinbase => write!(f, "coinbase"),
+            Venue::Bybit => write!(f, "bybit"),
         }
     }
 }
@@ -26,6 +31,8 @@ impl FromStr for Venue {
         match s {
             "simulation" => Ok(Venue::Simulation),
             "binance" => Ok(Venue::Binance),
+            "coinbase" => Ok(Venue::Coinbase),
+            "bybit" => Ok(Venue::Bybit),
             _ => Err(ModelError::UnknownVenueError(s.into())),
         }
     }
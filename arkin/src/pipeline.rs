@@ -1,28 +1,56 @@
 use crate::config::PipelineConfig;
-use crate::features::{Feature, FeatureEvent, FeatureFactory};
+use crate::features::{Feature, FeatureEvent, FeatureFactory, PipelineError, WarmUp};
+use crate::metrics::METRICS;
 use crate::models::Instrument;
 use crate::state::StateManager;
-use parking_lot::Mutex;
+use dashmap::DashMap;
 use petgraph::graph::NodeIndex;
 use petgraph::{
     algo::toposort,
     dot::{Config, Dot},
     graph::DiGraph,
 };
-use rayon::ThreadPoolBuilder;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 use std::sync::Arc;
 use time::OffsetDateTime;
 use tracing::{debug, info};
 
-#[derive(Default)]
 pub struct Pipeline {
     state: Arc<StateManager>,
     graph: Arc<DiGraph<Box<dyn Feature>, ()>>,
-    order: Vec<NodeIndex>,
+    /// Inputs from other nodes per node, every run starts from a copy
+    in_degrees: Vec<usize>,
+    /// Nodes without inputs from other nodes in topological order
+    roots: Vec<NodeIndex>,
+    pool: ThreadPool,
+    /// Time of the previous run per instrument, incremental features read from there on
+    last_run: DashMap<Instrument, OffsetDateTime>,
+    /// Warm-up requirement per node
+    warm_up: Vec<WarmUp>,
+    warm_up_progress: DashMap<Instrument, WarmUpProgress>,
+}
+
+/// How far the nodes are with their warm-up for one instrument
+struct WarmUpProgress {
+    start: OffsetDateTime,
+    /// Values read per node and input, summed over the runs for the inputs that only read the updates
+    samples: Vec<Vec<usize>>,
+    warm: Vec<bool>,
+}
+
+impl WarmUpProgress {
+    fn new(start: OffsetDateTime, warm_up: &[WarmUp]) -> Self {
+        WarmUpProgress {
+            start,
+            samples: warm_up.iter().map(|w| vec![0; w.samples.len()]).collect(),
+            warm: vec![false; warm_up.len()],
+        }
+    }
 }
 
 impl Pipeline {
-    pub fn from_config(state: Arc<StateManager>, config: &PipelineConfig) -> Self {
+    pub fn from_config(state: Arc<StateManager>, config: &PipelineConfig) -> Result<Self, PipelineError> {
+        config.validate()?;
         let mut graph = DiGraph::new();
 
         // Create features
@@ -40,10 +68,12 @@ impl Pipeline {
                 if source == "base" || source == "self" {
                     continue;
                 }
-                let source_node = graph
-                    .node_indices()
-                    .find(|i| graph[*i].id() == source)
-                    .expect("Failed to find node from config");
+                let source_node = graph.node_indices().find(|i| graph[*i].id() == source).ok_or_else(|| {
+                    PipelineError::UnknownSource {
+                        feature: graph[target_node].id().clone(),
+                        input: source.clone(),
+                    }
+                })?;
                 edges_to_add.push((source_node, target_node));
             }
         }
@@ -51,94 +81,181 @@ impl Pipeline {
             graph.add_edge(source, target, ());
         }
 
-        // Save down the topological order for parallel processing
-        let order = toposort(&graph, None).expect("Cycle detected in graph");
+        // Sort topologically, which also finds the cycles
+        let order = toposort(&graph, None).map_err(|c| PipelineError::Cycle(graph[c.node_id()].id().clone()))?;
+
+        let mut in_degrees = vec![0; graph.node_count()];
+        for edge in graph.edge_indices() {
+            let target = graph.edge_endpoints(edge).unwrap().1;
+            in_degrees[target.index()] += 1;
+        }
+        debug!("In-Degree count: {:?}", in_degrees);
+        let roots = order.iter().filter(|n| in_degrees[n.index()] == 0).cloned().collect();
+
+        // Zero threads is one per core
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.threads.unwrap_or(0))
+            .thread_name({
+                let name = config.name.clone();
+                move |i| format!("{}-pipeline-{}", name, i)
+            })
+            .build()?;
+
+        let warm_up = graph
+            .node_weights()
+            .map(|f| match config.warm_up.get(f.id()) {
+                Some(c) => f.warm_up().with_config(f.data(), c),
+                None => f.warm_up(),
+            })
+            .collect();
 
         info!("{:?}", Dot::with_config(&graph, &[Config::EdgeIndexLabel]));
-        Pipeline {
+        Ok(Pipeline {
             state,
             graph: Arc::new(graph),
-            order,
-        }
+            in_degrees,
+            roots,
+            pool,
+            last_run: DashMap::new(),
+            warm_up,
+            warm_up_progress: DashMap::new(),
+        })
     }
 
-    // Topological Sorting in parallel, which can be efficiently implemented using Kahn's algorithm
+    // Topological Sorting in parallel, which can be efficiently implemented using Kahn's algorithm.
+    // Nodes that did not warm up yet or read from one that did not still store their outputs, so the nodes
+    // after them warm up too, but they are left out of the result.
     pub fn calculate(&self, instrument: Instrument, event_time: OffsetDateTime) -> Vec<FeatureEvent> {
-        // Step 1: Calculate in-degrees
-        let in_degrees = Arc::new(Mutex::new(vec![0; self.graph.node_count()]));
-        for edge in self.graph.edge_indices() {
-            let target = self.graph.edge_endpoints(edge).unwrap().1;
-            in_degrees.lock()[target.index()] += 1;
-        }
-        debug!("In-Degree count: {:?}", in_degrees);
+        let _timer = METRICS.pipeline_latency.start_timer();
+
+        // Step 1: Start from the in-degrees and the nodes with zero in-degree of the graph
+        let mut in_degrees = self.in_degrees.clone();
+        let mut ready = self.roots.clone();
+
+        // A run at or before the previous one starts the incremental features over
+        let previous = self
+            .last_run
+            .insert(instrument.clone(), event_time)
+            .filter(|previous| *previous < event_time);
+        let mut progress = match (previous, self.warm_up_progress.remove(&instrument)) {
+            (Some(_), Some((_, progress))) => progress,
+            _ => WarmUpProgress::new(event_time, &self.warm_up),
+        };
+        let was_warm = progress.warm.iter().all(|w| *w);
+
+        // Step 2: Process each level of ready nodes in parallel
+        let mut pipeline_result = Vec::new();
+        while !ready.is_empty() {
+            debug!("Ready nodes: {:?}", ready);
+            let results = self.pool.install(|| {
+                ready
+                    .par_iter()
+                    .map(|node| self.calculate_node(*node, &instrument, event_time, previous.as_ref()))
+                    .collect::<Vec<_>>()
+            });
+
+            // The outputs of the level are stored in one write before the next level reads them
+            let outputs = results
+                .iter()
+                .flat_map(|(events, _)| events.iter().cloned())
+                .collect::<Vec<_>>();
+            if !outputs.is_empty() {
+                self.state.add_features(outputs);
+            }
+            for (node, (events, samples)) in ready.iter().zip(results) {
+                if self.update_warm_up(&mut progress, *node, &samples, previous.is_some(), event_time) {
+                    pipeline_result.extend(events);
+                } else {
+                    debug!("Node {} is warming up", self.graph[*node].id());
+                }
+            }
 
-        // Step 2: Enqueue nodes with zero in-degree
-        let (queue_tx, queue_rx) = flume::unbounded();
-        for node in &self.order {
-            if in_degrees.lock()[node.index()] == 0 {
-                debug!("Ready node: {:?}", self.graph[*node]);
-                queue_tx.send(Some(*node)).expect("Failed to send ready node");
+            // Update in-degrees of neighbors and collect the next level of zero in-degree nodes
+            let mut next = Vec::new();
+            for node in ready {
+                for neighbor in self.graph.neighbors_directed(node, petgraph::Outgoing) {
+                    in_degrees[neighbor.index()] -= 1;
+                    if in_degrees[neighbor.index()] == 0 {
+                        next.push(neighbor);
+                    }
+                }
             }
+            ready = next;
         }
+        debug!("Finished graph calculation");
+        if !was_warm && progress.warm.iter().all(|w| *w) {
+            info!("Pipeline warmed up for {}", instrument);
+        }
+        self.warm_up_progress.insert(instrument, progress);
+        pipeline_result
+    }
 
-        // Step 3: Parallel processing
-        let pipeline_result = Arc::new(Mutex::new(Vec::new()));
-        let pool = ThreadPoolBuilder::new().build().expect("Failed to create thread pool");
-        pool.scope(|s| {
-            while let Some(node) = queue_rx.recv().expect("Failed to receive data") {
-                let state = self.state.clone();
-                let instrument = instrument.clone();
-                let graph = Arc::clone(&self.graph);
-                let in_degrees = Arc::clone(&in_degrees);
-                let queue_tx = queue_tx.clone();
-                let pipeline_result = Arc::clone(&pipeline_result);
-
-                s.spawn(move |_| {
-                    // Process the node
-                    let feature = &graph[node];
-
-                    // Query the data
-                    let data = state.read_features(&instrument, &event_time, feature.data());
-
-                    // Calculate the feature
-                    let res = feature.calculate(data);
-                    match res {
-                        Ok(data) => {
-                            debug!("Calculated: {:?}", data);
-
-                            // Save data to state and result set
-                            data.into_iter().for_each(|(id, value)| {
-                                debug!("Saving: {} => {}", id, value);
-                                let event = FeatureEvent::new(id, instrument.to_owned(), event_time, value);
-                                state.add_feature(event.clone());
-                                pipeline_result.lock().push(event);
-                            });
-                        }
-                        Err(e) => {
-                            info!("Failed to calculate: {:?}", e);
-                        }
-                    }
+    /// Adds the values the node read and returns whether it is warm. Once warm it stays warm until a run at or
+    /// before the previous one of the instrument starts the warm-up over
+    fn update_warm_up(
+        &self,
+        progress: &mut WarmUpProgress,
+        node: NodeIndex,
+        samples: &[usize],
+        resumed: bool,
+        event_time: OffsetDateTime,
+    ) -> bool {
+        let requests = self.graph[node].data();
+        for ((seen, read), request) in progress.samples[node.index()].iter_mut().zip(samples).zip(requests) {
+            match request.is_update() && resumed {
+                true => *seen += read,
+                false => *seen = *read,
+            }
+        }
+        if !progress.warm[node.index()] {
+            let warm_up = &self.warm_up[node.index()];
+            progress.warm[node.index()] = event_time - progress.start >= warm_up.lookback
+                && progress.samples[node.index()]
+                    .iter()
+                    .zip(&warm_up.samples)
+                    .all(|(seen, needed)| seen >= needed)
+                && self
+                    .graph
+                    .neighbors_directed(node, petgraph::Incoming)
+                    .all(|source| progress.warm[source.index()]);
+        }
+        progress.warm[node.index()]
+    }
 
-                    // Update in-degrees of neighbors and enqueue new zero in-degree nodes
-                    for neighbor in graph.neighbors_directed(node, petgraph::Outgoing) {
-                        let mut in_degrees = in_degrees.lock();
-                        in_degrees[neighbor.index()] -= 1;
-                        if in_degrees[neighbor.index()] == 0 {
-                            debug!("Ready node: {:?}", graph[neighbor]);
-                            queue_tx.send(Some(neighbor)).expect("Failed to send ready node");
-                        }
-                    }
-                    debug!("Dependency count: {:?}", in_degrees);
-                    if in_degrees.lock().iter().all(|&x| x == 0) {
-                        debug!("All nodes processed");
-                        queue_tx.send(None).expect("Failed to send exit message");
-                    }
-                });
+    fn calculate_node(
+        &self,
+        node: NodeIndex,
+        instrument: &Instrument,
+        event_time: OffsetDateTime,
+        previous: Option<&OffsetDateTime>,
+    ) -> (Vec<FeatureEvent>, Vec<usize>) {
+        let feature = &self.graph[node];
+
+        // Query the data
+        let data = self
+            .state
+            .read_features_since(instrument, &event_time, previous, feature.data());
+        let samples = feature
+            .data()
+            .iter()
+            .map(|r| data.count(r.feature_id()).unwrap_or(0.) as usize)
+            .collect();
+
+        // Calculate the feature
+        let events = match feature.calculate(data) {
+            Ok(data) => {
+                debug!("Calculated: {:?}", data);
+
+                data.into_iter()
+                    .map(|(id, value)| FeatureEvent::new(id, instrument.to_owned(), event_time, value))
+                    .collect()
             }
-        });
-        debug!("Finished graph calculation");
-        let res = pipeline_result.lock().iter().cloned().collect();
-        res
+            Err(e) => {
+                info!("Failed to calculate: {:?}", e);
+                Vec::new()
+            }
+        };
+        (events, samples)
     }
 
     // COULD BE USED IN THE FUTURE IF WE HAVE ASYNC FEATURES
@@ -316,3 +433,102 @@ impl Pipeline {
 //     pipeline.calculate();
 // }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{
+            DiffFeatureConfig, DiffMethod, FeatureConfig, LatestInputConfig, PeriodInputConfig, SMAFeatureConfig,
+            VWAPFeatureConfig, WarmUpConfig, WindowInputConfig,
+        },
+        test_utils,
+    };
+    use std::collections::HashMap;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_pipeline_warm_up() {
+        let state = Arc::new(StateManager::default());
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+
+        let window = |feature_id: &str| WindowInputConfig {
+            from: "base".into(),
+            feature_id: feature_id.into(),
+            window: 5,
+        };
+        let config = PipelineConfig {
+            name: "test".into(),
+            frequency: 1,
+            bar_close: None,
+            threads: Some(1),
+            // The vwap is trusted after a second instead of its 5 second window
+            warm_up: HashMap::from([(
+                "vwap".into(),
+                WarmUpConfig {
+                    samples: None,
+                    lookback: Some(1),
+                },
+            )]),
+            features: vec![
+                FeatureConfig::VWAP(VWAPFeatureConfig {
+                    id: "vwap".into(),
+                    input_price: window("trade_price"),
+                    input_quantity: window("trade_quantity"),
+                    output: "vwap".into(),
+                    incremental: false,
+                }),
+                FeatureConfig::SMA(SMAFeatureConfig {
+                    id: "sma".into(),
+                    input: PeriodInputConfig {
+                        from: "base".into(),
+                        feature_id: "trade_price".into(),
+                        periods: 4,
+                    },
+                    output: "sma".into(),
+                    incremental: true,
+                }),
+                FeatureConfig::Diff(DiffFeatureConfig {
+                    id: "sma_change".into(),
+                    input: LatestInputConfig {
+                        from: "sma".into(),
+                        feature_id: "sma".into(),
+                    },
+                    periods: 1,
+                    method: DiffMethod::Absolute,
+                    output: "sma_change".into(),
+                }),
+            ],
+        };
+        let pipeline = Pipeline::from_config(state.clone(), &config).unwrap();
+
+        let mut outputs = Vec::new();
+        for seconds in (0..5).chain(0..1) {
+            if outputs.len() < 5 {
+                state.add_feature(FeatureEvent::new("trade_price".into(), instrument.clone(), at(seconds), 100.));
+                state.add_feature(FeatureEvent::new("trade_quantity".into(), instrument.clone(), at(seconds), 1.));
+            }
+            let mut ids = pipeline
+                .calculate(instrument.clone(), at(seconds))
+                .into_iter()
+                .map(|e| e.id)
+                .collect::<Vec<_>>();
+            ids.sort();
+            outputs.push(ids);
+        }
+
+        let expected: [&[&str]; 6] = [
+            &[],
+            &["vwap"],
+            &["vwap"],
+            // The change of the sma had its two values before, it waits for the sma
+            &["sma", "sma_change", "vwap"],
+            &["sma", "sma_change", "vwap"],
+            // Going back in time starts over
+            &[],
+        ];
+        assert_eq!(outputs, expected);
+    }
+}
@@ -3,24 +3,36 @@ use std::{
     sync::Arc,
 };
 
+use rust_decimal::prelude::*;
 use time::OffsetDateTime;
 
+use parking_lot::RwLock;
+
 use crate::{
-    models::{Fill, Instrument, Notional, Position},
+    bus::EventBus,
+    models::{
+        AccountState, AccountUpdate, Asset, Fill, FundingPayment, FundingRate, Instrument, Notional, Position, Tick,
+        Venue,
+    },
     state::StateManager,
     strategies::StrategyId,
 };
 
-// The hirarchy for positions is as followed:
+// Positions are reconstructed from the fills that happened strictly before the requested time
 
 pub struct Portfolio {
     state: Arc<StateManager>,
     capital: Notional,
+    account: RwLock<AccountState>,
 }
 
 impl Portfolio {
     pub fn new(state: Arc<StateManager>, capital: Notional) -> Self {
-        Self { state, capital }
+        Self {
+            state,
+            capital,
+            account: RwLock::new(AccountState::default()),
+        }
     }
 }
 
@@ -33,6 +45,7 @@ impl Portfolio {
         self.capital - self.total_exposure(event_time)
     }
 
+    /// Gross notional of the positions, shorts add to it like longs
     pub fn total_exposure(&self, event_time: &OffsetDateTime) -> Notional {
         let positions = self.positions(event_time);
         positions
@@ -42,7 +55,7 @@ impl Portfolio {
     }
 
     pub fn positions(&self, timestamp: &OffsetDateTime) -> HashMap<(StrategyId, Instrument), Position> {
-        let fills = self.state.events::<Fill>(timestamp);
+        let fills = self.state.events_before::<Fill>(timestamp);
 
         let strategies_instruments = fills
             .values()
@@ -60,7 +73,7 @@ impl Portfolio {
     }
 
     pub fn all_positions(&self, timestamp: &OffsetDateTime) -> HashMap<(StrategyId, Instrument), Vec<Position>> {
-        let fills = self.state.events::<Fill>(timestamp);
+        let fills = self.state.events_before::<Fill>(timestamp);
 
         let strategies_instruments = fills
             .values()
@@ -76,33 +89,167 @@ impl Portfolio {
         })
     }
 
+    /// Pnl of the open positions marked at the mid of the latest tick, positions without a tick are left out
+    pub fn unrealized_pnl(&self, timestamp: &OffsetDateTime) -> Notional {
+        self.positions(timestamp)
+            .values()
+            .filter_map(|p| {
+                let tick = self.state.latest_event_by_instrument::<Tick>(&p.instrument, timestamp)?;
+                Some(p.mark(tick.mid_price()))
+            })
+            .fold(Notional::from(0.), |acc, x| acc + x)
+    }
+
+    /// Commission paid before the time per asset it was paid in, commission in another asset than the quote
+    /// needs a conversion before it can be netted against the pnl
+    pub fn commissions(&self, timestamp: &OffsetDateTime) -> HashMap<Asset, Decimal> {
+        self.state
+            .events_before::<Fill>(timestamp)
+            .values()
+            .flatten()
+            .fold(HashMap::new(), |mut acc, fill| {
+                *acc.entry(fill.commission_asset().clone()).or_default() += fill.commission.value();
+                acc
+            })
+    }
+
+    /// Balances of every venue as of now
+    pub fn account(&self) -> AccountState {
+        self.account.read().clone()
+    }
+
+    pub fn free_balance(&self, venue: &Venue, asset: &Asset) -> Decimal {
+        self.account.read().free(venue, asset)
+    }
+
+    pub fn deposit(&self, venue: &Venue, asset: &Asset, amount: Decimal) {
+        self.account.write().deposit(venue, asset, amount);
+    }
+
+    pub fn apply_fill(&self, venue: &Venue, fill: &Fill) {
+        self.account.write().apply_fill(venue, fill);
+    }
+
+    /// Funding owed by the positions open at the funding time of the rate
+    pub fn funding_payments(&self, funding: &FundingRate, funding_time: &OffsetDateTime) -> Vec<FundingPayment> {
+        self.positions(funding_time)
+            .values()
+            .filter(|p| p.instrument == funding.instrument && p.is_open())
+            .map(|p| FundingPayment::new(*funding_time, p, funding))
+            .collect()
+    }
+
+    /// Keeps the balances in line with the account updates of the venues and the funding in between
+    pub async fn track_account(self: Arc<Self>, bus: Arc<EventBus>) {
+        let mut updates = bus.subscribe::<AccountUpdate>();
+        let mut funding = bus.subscribe::<FundingPayment>();
+        loop {
+            tokio::select! {
+                Some(update) = updates.recv() => self.account.write().apply_update(&update),
+                Some(payment) = funding.recv() => self.account.write().apply_funding(&payment),
+                else => break,
+            }
+        }
+    }
+
+    /// Publishes the funding payments of the open positions once a funding time passed, the rate that applies is
+    /// the last one announced for it
+    pub async fn settle_funding(self: Arc<Self>, bus: Arc<EventBus>) {
+        let mut rates = bus.subscribe::<FundingRate>();
+        let mut pending = HashMap::<Instrument, FundingRate>::new();
+        while let Some(rate) = rates.recv().await {
+            if let Some(due) = pending.get(&rate.instrument) {
+                if rate.event_time >= due.next_funding_time {
+                    let due = pending.remove(&rate.instrument).expect("Pending funding rate");
+                    for payment in self.funding_payments(&due, &due.next_funding_time) {
+                        bus.publish(payment);
+                    }
+                }
+            }
+            pending.insert(rate.instrument.clone(), rate);
+        }
+    }
+
     fn calculate_positions_from_fills(&self, fills: Vec<&Fill>) -> Vec<Position> {
         let mut positions = Vec::new();
         let mut current_position = Option::<Position>::None;
         for fill in fills {
-            // Fill the position
-            let (excess, position) = match current_position {
-                None => (None, Position::from_fill(fill)),
+            let position = match current_position.take() {
+                None => Position::from_fill(fill),
                 Some(mut p) => {
-                    let excess = p.update(fill);
-                    (excess, p)
+                    // A flip closes the position and opens a new one with the excess
+                    if let Some(excess) = p.apply_fill(fill) {
+                        positions.push(p);
+                        Position::from_fill(&excess)
+                    } else {
+                        p
+                    }
                 }
             };
-            if excess.is_some() {
-                positions.push(position);
-                current_position = None;
-            } else {
+            if position.is_open() {
                 current_position = Some(position);
+            } else {
+                positions.push(position);
             }
         }
+        if let Some(position) = current_position {
+            positions.push(position);
+        }
         positions
     }
 }
 
+/// Running realized pnl per strategy, fed one fill at a time in the order they happened
+#[derive(Default)]
+pub struct PnlTracker {
+    positions: HashMap<(StrategyId, Instrument), (Decimal, Decimal)>,
+    realized: HashMap<StrategyId, Decimal>,
+}
+
+impl PnlTracker {
+    /// Add a fill and return its realized pnl net of commission, opening fills only carry their commission
+    pub fn update(&mut self, fill: &Fill) -> Decimal {
+        let (quantity, avg_price) = self
+            .positions
+            .entry((fill.strategy_id.clone(), fill.instrument.clone()))
+            .or_insert((Decimal::ZERO, Decimal::ZERO));
+        let fill_quantity = fill.quantity.value();
+        let fill_price = fill.price.value();
+        let new_quantity = *quantity + fill_quantity;
+
+        let mut realized = Decimal::ZERO;
+        if quantity.is_zero() || quantity.is_sign_positive() == fill_quantity.is_sign_positive() {
+            *avg_price = (*quantity * *avg_price + fill_quantity * fill_price) / new_quantity;
+        } else {
+            let closed = fill_quantity.abs().min(quantity.abs()) * quantity.signum();
+            realized = closed * (fill_price - *avg_price);
+            if new_quantity.is_zero() {
+                *avg_price = Decimal::ZERO;
+            } else if new_quantity.is_sign_positive() != quantity.is_sign_positive() {
+                *avg_price = fill_price;
+            }
+        }
+        *quantity = new_quantity;
+
+        let pnl = realized - fill.commission.value();
+        *self.realized.entry(fill.strategy_id.clone()).or_default() += pnl;
+        pnl
+    }
+
+    /// Realized pnl net of commission of all fills of the strategy so far
+    pub fn realized(&self, strategy_id: &StrategyId) -> Decimal {
+        self.realized.get(strategy_id).copied().unwrap_or_default()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{logging, test_utils};
+    use crate::{
+        logging,
+        models::{Event, Price, Quantity},
+        test_utils,
+    };
     use time::macros::datetime;
     use tracing::info;
 
@@ -160,8 +307,9 @@ mod tests {
         }
         // assert_eq!(position.avg_price, Price::from(100.));
         // assert_eq!(position.quantity, Quantity::from(-10.));
-        assert_eq!(portfolio.buying_power(&event_time), Notional::from(3000.));
-        assert_eq!(portfolio.total_exposure(&event_time), Notional::from(-1000.));
+        // The short uses up buying power like a long of the same size
+        assert_eq!(portfolio.buying_power(&event_time), Notional::from(1000.));
+        assert_eq!(portfolio.total_exposure(&event_time), Notional::from(1000.));
 
         event_time = datetime!(2024-01-01 00:05:00).assume_utc();
         for ((s, i), v) in portfolio.positions(&event_time).iter() {
@@ -171,5 +319,44 @@ mod tests {
         // assert_eq!(position.quantity, Quantity::from(0.));
         assert_eq!(portfolio.buying_power(&event_time), Notional::from(2000.));
         assert_eq!(portfolio.total_exposure(&event_time), Notional::from(0.));
+        assert_eq!(portfolio.commissions(&event_time)[instrument[0].quote()], Decimal::from(8));
+    }
+
+    #[test]
+    fn test_position_reduce_and_flip() {
+        let state = Arc::new(StateManager::default());
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |minutes: i64| start + time::Duration::minutes(minutes);
+        for (minutes, price, quantity) in [(0, 100., 2.), (1, 110., -1.), (2, 120., -3.), (3, 110., 2.)] {
+            state.add_event(Event::Fill(Fill::new(
+                at(minutes),
+                instrument.clone(),
+                minutes as u64,
+                "test".into(),
+                price.into(),
+                quantity.into(),
+                Notional::from(0.),
+            )));
+        }
+        let portfolio = Portfolio::new(state, Notional::from(1000.));
+
+        // Reducing keeps the entry price and realizes the sold part
+        let position = portfolio.positions(&at(2)).into_values().next().unwrap();
+        assert_eq!((position.quantity, position.avg_price), (Quantity::from(1.), Price::from(100.)));
+        assert_eq!(position.realized_pnl, Notional::from(10.));
+
+        // The flip closes the long and opens a short with the excess at the fill price
+        let position = portfolio.positions(&at(3)).into_values().next().unwrap();
+        assert_eq!(
+            (position.quantity, position.avg_price),
+            (Quantity::from(-2.), Price::from(120.))
+        );
+
+        let positions = portfolio.all_positions(&at(4)).into_values().next().unwrap();
+        assert_eq!(positions.len(), 2);
+        assert!(positions.iter().all(|p| !p.is_open()));
+        assert_eq!(positions[0].realized_pnl, Notional::from(30.));
+        assert_eq!(positions[1].realized_pnl, Notional::from(20.));
     }
 }
@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use reqwest::{header::HeaderMap, Client, Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{
+    clock::{ServerClock, TimeSync},
+    config::RestClientConfig,
+    credentials::Credentials,
+    models::Venue,
+};
+
+#[derive(Error, Debug)]
+pub enum RestError {
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Still rate limited by {0} after {1} retries")]
+    RetriesExhausted(Venue, u32),
+
+    #[error("Signed requests are not supported on {0}")]
+    SigningNotSupported(Venue),
+}
+
+/// Keeps the request weight within a budget per minute
+pub struct WeightLimiter {
+    max_per_minute: u32,
+    used: u32,
+    window_start: Instant,
+    /// Set by a 429 or 418, every request waits until the venue accepts requests again
+    blocked_until: Option<Instant>,
+}
+
+impl WeightLimiter {
+    pub fn new(max_per_minute: u32) -> Self {
+        Self {
+            max_per_minute,
+            used: 0,
+            window_start: Instant::now(),
+            blocked_until: None,
+        }
+    }
+
+    /// How long to wait before a request of this weight fits, the weight is spent when it fits now
+    fn delay(&mut self, weight: u32, now: Instant) -> Option<Duration> {
+        if let Some(until) = self.blocked_until.filter(|until| *until > now) {
+            return Some(until - now);
+        }
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(60) {
+            self.window_start = now;
+            self.used = 0;
+        }
+        if self.used + weight > self.max_per_minute {
+            return Some(Duration::from_secs(60) - now.duration_since(self.window_start));
+        }
+        self.used += weight;
+        None
+    }
+
+    /// Binance reports the weight used by the whole ip, which includes requests of other processes
+    pub fn observe(&mut self, headers: &HeaderMap) {
+        let used = headers
+            .get("x-mbx-used-weight-1m")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok());
+        if let Some(used) = used {
+            self.used = self.used.max(used);
+        }
+    }
+
+    fn block(&mut self, until: Instant) {
+        self.blocked_until = Some(until);
+    }
+}
+
+/// REST client of one venue that spends its requests from a shared weight budget, backs off when the venue
+/// rate limits and signs requests. Consumers in the same process share the client so they share the budget.
+pub struct RestClient {
+    venue: Venue,
+    client: Client,
+    limiter: Mutex<WeightLimiter>,
+    max_retries: u32,
+    recv_window: u64,
+    /// Signatures are timestamped in server time, Binance rejects them when they fall outside the recv window
+    clock: ServerClock,
+}
+
+impl RestClient {
+    pub fn from_config(config: &RestClientConfig, clock: ServerClock) -> Self {
+        RestClient {
+            venue: config.venue.clone(),
+            clock,
+            client: Client::new(),
+            limiter: Mutex::new(WeightLimiter::new(config.max_weight_per_minute)),
+            max_retries: config.max_retries,
+            recv_window: config.recv_window,
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        weight: u32,
+    ) -> Result<T, RestError> {
+        self.send(weight, || Ok(self.client.get(url).query(query))).await
+    }
+
+    /// Request on behalf of the account of the credentials, signed the way the venue expects it
+    pub async fn signed<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        query: &[(&str, String)],
+        weight: u32,
+        credentials: &Credentials,
+    ) -> Result<T, RestError> {
+        // Signed again for every attempt, the timestamp has to be recent
+        self.send(weight, || self.sign(method.clone(), url, query, credentials)).await
+    }
+
+    fn sign(
+        &self,
+        method: Method,
+        url: &str,
+        query: &[(&str, String)],
+        credentials: &Credentials,
+    ) -> Result<RequestBuilder, RestError> {
+        match self.venue {
+            Venue::Binance => {
+                let timestamp = (self.clock.now().unix_timestamp_nanos() / 1_000_000).to_string();
+                let query = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(query)
+                    .append_pair("recvWindow", &self.recv_window.to_string())
+                    .append_pair("timestamp", &timestamp)
+                    .finish();
+                let signature = sign_hmac_sha256(credentials.api_secret.expose(), &query);
+                Ok(self
+                    .client
+                    .request(method, format!("{}?{}&signature={}", url, query, signature))
+                    .header("X-MBX-APIKEY", credentials.api_key.expose()))
+            }
+            _ => Err(RestError::SigningNotSupported(self.venue.clone())),
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        weight: u32,
+        request: impl Fn() -> Result<RequestBuilder, RestError>,
+    ) -> Result<T, RestError> {
+        let mut retries = 0;
+        loop {
+            self.acquire(weight).await;
+            let res = request()?.send().await?;
+            self.limiter.lock().observe(res.headers());
+            // Too many requests, Binance bans the ip when the requests go on after a 429
+            if matches!(res.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT) {
+                if retries == self.max_retries {
+                    return Err(RestError::RetriesExhausted(self.venue.clone(), retries));
+                }
+                retries += 1;
+                let retry_after = res
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(60);
+                warn!("Rate limited by {}, retrying after {} seconds", self.venue, retry_after);
+                self.limiter.lock().block(Instant::now() + Duration::from_secs(retry_after));
+                continue;
+            }
+            return Ok(res.error_for_status()?.json::<T>().await?);
+        }
+    }
+
+    async fn acquire(&self, weight: u32) {
+        loop {
+            let Some(delay) = self.limiter.lock().delay(weight, Instant::now()) else {
+                return;
+            };
+            info!("Request weight budget of {} spent, waiting {:?}", self.venue, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn sign_hmac_sha256(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Hmac takes keys of any length");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// The shared client of every configured venue
+#[derive(Default, Clone)]
+pub struct RestClients {
+    clients: HashMap<Venue, Arc<RestClient>>,
+}
+
+impl RestClients {
+    pub fn from_config(config: &[RestClientConfig], time_sync: &Arc<TimeSync>) -> Self {
+        RestClients {
+            clients: config
+                .iter()
+                .map(|c| {
+                    let clock = time_sync.clock(c.venue.clone());
+                    (c.venue.clone(), Arc::new(RestClient::from_config(c, clock)))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, venue: &Venue) -> Option<Arc<RestClient>> {
+        self.clients.get(venue).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight_limiter() {
+        let mut limiter = WeightLimiter::new(50);
+        let start = limiter.window_start;
+        assert_eq!(limiter.delay(20, start), None);
+        assert_eq!(limiter.delay(20, start + Duration::from_secs(10)), None);
+        assert_eq!(
+            limiter.delay(20, start + Duration::from_secs(15)),
+            Some(Duration::from_secs(45))
+        );
+
+        // Weight spent by other processes on the same ip counts as well
+        let mut headers = HeaderMap::new();
+        headers.insert("x-mbx-used-weight-1m", "45".parse().unwrap());
+        limiter.observe(&headers);
+        assert!(limiter.delay(10, start + Duration::from_secs(20)).is_some());
+
+        // A new minute starts with a fresh budget
+        assert_eq!(limiter.delay(20, start + Duration::from_secs(60)), None);
+
+        // Rate limited requests hold back every request until the venue accepts them again
+        limiter.block(start + Duration::from_secs(90));
+        assert_eq!(limiter.delay(1, start + Duration::from_secs(70)), Some(Duration::from_secs(20)));
+        assert_eq!(limiter.delay(1, start + Duration::from_secs(90)), None);
+    }
+
+    #[test]
+    fn test_sign() {
+        // Example of the Binance api documentation
+        let signature = sign_hmac_sha256(
+            "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j",
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559",
+        );
+        assert_eq!(signature, "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71");
+    }
+}
@@ -1,19 +1,138 @@
-use std::sync::Arc;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
-use tracing::info;
+use anyhow::Result;
+use futures_util::future::join_all;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::{
+    select,
+    signal::unix::{signal, SignalKind},
+    sync::broadcast::error::RecvError,
+    task::JoinHandle,
+};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    clock::Clock,
-    config::GlobalConfig,
+    alerting::AlertManager,
+    allocation::AllocationManager,
+    api::{self, format_time, FillResponse, OrderResponse, PositionResponse},
+    bus::EventBus,
+    clock::{Clock, LiveClock, TimeSync},
+    collector::FeedClient,
+    config::{self, ExecutionEndpointConfig, FeatureStoreConfig, GlobalConfig},
+    credentials::CredentialStore,
+    db::DBManager,
+    exchange_info::ExchangeInfoService,
+    execution::{Execution, ExecutionManager},
+    features::{FeatureEvent, PipelineError},
+    flags::{Flag, FlagError, FlagRegistry},
+    grpc,
+    health::{self, Check, FeedActivity, HealthReport},
     ingestors::{Ingestor, IngestorFactory, IngestorType},
-    state::StateManager,
+    journal::Journal,
+    metrics,
+    models::{Candle, Event, EventType, Fill, Instrument, InstrumentRegistry, Order, Position, Signal, Tick, Trade},
+    pipeline::Pipeline,
+    portfolio::Portfolio,
+    rest::RestClients,
+    shutdown::{self, wait_for_signal, Shutdown, ShutdownSignal},
+    sinks::{Sink, SinkFactory},
+    skew::{SkewGuard, SkewMonitor},
+    state::{warm_up, BarService, RedbFeatureStore, Retention, StateManager, StateRecorder, StateStats},
+    strategies::{StrategyError, StrategyId, StrategyManager},
+    ws,
 };
 
 pub struct Server {
     state: Arc<StateManager>,
-    _clock: Arc<Clock>,
-    // _pubsub: Arc<PubSub>,
-    config: GlobalConfig,
+    clock: Arc<LiveClock>,
+    bus: Arc<EventBus>,
+    portfolio: Arc<Portfolio>,
+    credentials: Arc<CredentialStore>,
+    rest: RestClients,
+    time_sync: Arc<TimeSync>,
+    trading: RwLock<Arc<Trading>>,
+    execution_manager: ExecutionManager,
+    skew_guard: Arc<SkewGuard>,
+    instruments: Arc<InstrumentRegistry>,
+    collector_feed: bool,
+    feeds: Arc<FeedActivity>,
+    flags: Arc<FlagRegistry>,
+    started: Instant,
+    last_step: RwLock<Option<OffsetDateTime>>,
+    config: RwLock<GlobalConfig>,
+    shutdown: Shutdown,
+}
+
+/// What is left of the trading session, written on shutdown
+#[derive(Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub time: String,
+    pub positions: Vec<PositionResponse>,
+    pub open_orders: Vec<OrderResponse>,
+    pub fills: Vec<FillResponse>,
+}
+
+/// The parts of the trading loop that can be swapped by a config reload
+struct Trading {
+    pipeline: Pipeline,
+    strategy_manager: StrategyManager,
+    allocation_manager: AllocationManager,
+}
+
+impl Trading {
+    fn from_config(state: Arc<StateManager>, config: &GlobalConfig) -> Result<Self, PipelineError> {
+        Ok(Trading {
+            pipeline: Pipeline::from_config(state, &config.feature_pipeline)?,
+            strategy_manager: StrategyManager::from_config(&config.strategy_manager),
+            allocation_manager: AllocationManager::from_config(&config.allocation_manager),
+        })
+    }
+}
+
+/// Sections of the config that a running server picks up on reload
+const RELOADABLE: [&str; 3] = ["feature_pipeline", "strategy_manager", "allocation_manager"];
+
+/// Changed config paths split by whether the reload applied them
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    pub applied: Vec<String>,
+    pub restart_required: Vec<String>,
+}
+
+impl fmt::Display for ReloadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.applied.is_empty() && self.restart_required.is_empty() {
+            return write!(f, "no changes");
+        }
+        write!(
+            f,
+            "applied [{}], requires restart [{}]",
+            self.applied.join(", "),
+            self.restart_required.join(", ")
+        )
+    }
+}
+
+/// Latest modification time of the files in the config directory
+fn config_modified() -> Option<SystemTime> {
+    std::fs::read_dir("configs")
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+pub struct HealthStatus {
+    pub uptime: Duration,
+    pub strategies_running: usize,
+    pub last_step: Option<OffsetDateTime>,
 }
 
 impl Server {
@@ -21,63 +140,554 @@ impl Server {
         ServerBuilder::default()
     }
 
-    pub async fn run(&self) {
-        let ingestors = IngestorFactory::from_config(self.state.clone(), &self.config.ingestors);
-        Server::ingestor_task(ingestors).await;
+    /// Run until SIGINT, SIGTERM or [`Server::request_shutdown`], then stop in order: ingestors once drained,
+    /// trading loop, open orders, state recorder, sinks once flushed and finally the control plane after the state
+    /// is written to disk
+    pub async fn run(self: &Arc<Self>) {
+        let config = self.config.read().clone();
+
+        let clock = self.clock.clone();
+        let mut services = vec![tokio::spawn(async move { clock.start().await })];
+
+        let metrics_address = config.server.metrics_address.clone();
+        services.push(tokio::spawn(async move { metrics::serve(&metrics_address).await }));
+
+        let server = self.clone();
+        let grpc_address = config.server.grpc_address.clone();
+        services.push(tokio::spawn(async move { grpc::serve(&grpc_address, server).await }));
+
+        let server = self.clone();
+        let api_address = config.server.api_address.clone();
+        services.push(tokio::spawn(async move { api::serve(&api_address, server).await }));
+
+        let server = self.clone();
+        let ws_address = config.server.ws_address.clone();
+        services.push(tokio::spawn(async move { ws::serve(&ws_address, server).await }));
+
+        services.push(tokio::spawn(self.clone().config_watch_task()));
+        if config.state.metrics_interval > 0 {
+            services.push(tokio::spawn(self.clone().state_metrics_task(config.state.metrics_interval)));
+        }
+        services.push(tokio::spawn(self.feeds.clone().run(self.bus.clone())));
+        services.push(tokio::spawn(self.portfolio.clone().track_account(self.bus.clone())));
+        // Venues charge the funding of live positions themselves and report it with their account updates
+        let simulated = config
+            .execution_manager
+            .endpoints
+            .iter()
+            .all(|e| matches!(e, ExecutionEndpointConfig::Simulation(_)));
+        if simulated {
+            services.push(tokio::spawn(self.portfolio.clone().settle_funding(self.bus.clone())));
+        }
+
+        let alerts = AlertManager::from_config(self.bus.clone(), &config.alerting);
+        services.push(tokio::spawn(alerts.run()));
+
+        let skew = SkewMonitor::from_config(
+            self.bus.clone(),
+            self.skew_guard.clone(),
+            self.time_sync.clone(),
+            &config.clock_skew,
+        );
+        services.push(tokio::spawn(skew.run()));
+
+        // Orders are rounded with the venue's rules from the start
+        let exchange_info =
+            ExchangeInfoService::from_config(self.instruments.clone(), self.rest.clone(), &config.exchange_info);
+        exchange_info.refresh().await;
+        services.push(tokio::spawn(exchange_info.run()));
+
+        // Sinks stop last so they still forward what happens during the shutdown
+        let sink_stop = Shutdown::default();
+        let mut sinks = Vec::new();
+        for sink in SinkFactory::from_config(self.bus.clone(), &config.sinks) {
+            info!("Spawning {} sink...", sink);
+            let signal = sink_stop.subscribe();
+            sinks.push(tokio::spawn(async move { sink.start(signal).await }));
+        }
+
+        let recorder_stop = Shutdown::default();
+        let recorder = StateRecorder::new(self.state.clone(), &self.bus);
+        // Loaded before anything trades. Live data published meanwhile queues on the bus for the recorder, market data
+        // past the capacity of its broadcast is dropped, orders and fills are kept.
+        if config.state.warm_up > 0 {
+            let db = DBManager::from_config(&config.db).await;
+            let end = self.clock.now();
+            let start = end - time::Duration::seconds(config.state.warm_up as i64);
+            // Stepped like the trading loop, so the pipeline that trades is the one that warmed up
+            let frequency = config.feature_pipeline.bar_close.unwrap_or(config.clock.tick_frequency);
+            let trading = self.trading.read().clone();
+            warm_up(
+                &db,
+                &recorder,
+                &trading.pipeline,
+                start,
+                end,
+                time::Duration::seconds(frequency as i64),
+            )
+            .await;
+        }
+        let recorder = tokio::spawn(recorder.run(recorder_stop.subscribe()));
+        let bars = BarService::from_config(self.bus.clone(), self.clock.clone(), &config.state.bars);
+        let bars = tokio::spawn(bars.run(recorder_stop.subscribe()));
+
+        let ingestor_stop = Shutdown::default();
+        let mut ingestors = if self.collector_feed {
+            let feed = FeedClient::from_config(self.bus.clone(), &config.collector);
+            vec![tokio::spawn(feed.run(ingestor_stop.subscribe()))]
+        } else {
+            let ingestors = IngestorFactory::from_config(
+                self.bus.clone(),
+                self.clock.clone(),
+                self.instruments.clone(),
+                &self.credentials,
+                &self.time_sync,
+                &config.ingestors,
+            );
+            Server::ingestor_task(ingestors, &ingestor_stop).await
+        };
 
-        // let features = FeatureFactory::from_config(self.state.clone(), &self.config.features);
-        // tokio::spawn(Server::feature_task(features));
+        let trading_stop = Shutdown::default();
+        let server = self.clone();
+        let signal = trading_stop.subscribe();
+        let trading = tokio::spawn(async move { server.trading_task(signal).await });
 
-        // let strategies = StrategyFactory::from_config(self.state.clone(), &self.config.strategies);
-        // tokio::spawn(Server::strategy_task(strategies));
+        let mut requested = self.shutdown.subscribe();
+        select! {
+            _ = wait_for_signal() => {}
+            _ = requested.wait() => info!("Shutdown requested"),
+        }
+        self.request_shutdown();
+        tokio::spawn(async {
+            wait_for_signal().await;
+            error!("Received a second signal, exiting without finishing the shutdown");
+            std::process::exit(1);
+        });
 
-        // let allocation = AllocationFactory::from_config(self.state.clone(), &self.config.allocation);
-        // tokio::spawn(Server::allocation_task(allocation));
+        let limit = Duration::from_secs(config.server.shutdown_timeout);
+        // Sockets are closed and what they received is published, so the strategies see the last events
+        let drained = shutdown::phase("draining ingestors", limit, async {
+            ingestor_stop.trigger();
+            join_all(ingestors.iter_mut()).await;
+        })
+        .await;
+        if drained.is_none() {
+            ingestors.iter().for_each(|ingestor| ingestor.abort());
+        }
+        // The step in progress finishes so no allocation is left half executed
+        shutdown::phase("stopping pipeline and strategies", limit, async {
+            trading_stop.trigger();
+            let _ = trading.await;
+        })
+        .await;
+        shutdown::phase("cancelling open orders", limit, async {
+            let cancelled = self.cancel_all_orders();
+            info!("Cancelled {} open orders", cancelled);
+        })
+        .await;
+        shutdown::phase("flushing the state recorder", limit, async {
+            recorder_stop.trigger();
+            let _ = tokio::join!(bars, recorder);
+        })
+        .await;
+        shutdown::phase("persisting state", limit, async {
+            let path = &config.server.state_snapshot;
+            match self.persist_state(path) {
+                Ok(()) => info!("State written to {}", path),
+                Err(e) => error!("Failed to write state to {}: {}", path, e),
+            }
+        })
+        .await;
+        shutdown::phase("flushing sinks", limit, async {
+            sink_stop.trigger();
+            join_all(sinks).await;
+        })
+        .await;
+        shutdown::phase("stopping services", limit, async {
+            for service in &services {
+                service.abort();
+            }
+            join_all(services).await;
+        })
+        .await;
+        info!("Shutdown complete");
+    }
 
-        // let execution = ExecutionEndpointFactory::from_config(self.state.clone(), &self.config.execution);
-        // tokio::spawn(Server::execution_task(execution));
+    /// Start the graceful shutdown of a running server
+    pub fn request_shutdown(&self) {
+        self.shutdown.trigger();
+    }
 
-        // Wait for interrupt signal
-        tokio::signal::ctrl_c().await.expect("Failed to listen for event");
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.subscribe().is_triggered()
     }
 
-    async fn ingestor_task(ingestors: Vec<IngestorType>) {
+    /// Write positions, open orders and fills as json so they can be inspected after the process is gone
+    pub fn persist_state(&self, path: &str) -> Result<()> {
+        let snapshot = StateSnapshot {
+            time: format_time(&self.now()),
+            positions: self.positions().iter().map(PositionResponse::from).collect(),
+            open_orders: self.open_orders().iter().map(OrderResponse::from).collect(),
+            fills: self.fills().iter().map(FillResponse::from).collect(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+        Ok(())
+    }
+
+    async fn ingestor_task(ingestors: Vec<IngestorType>, stop: &Shutdown) -> Vec<JoinHandle<()>> {
         info!("Spawning ingestor tasks...");
-        for ingestor in ingestors {
-            tokio::spawn(async move { ingestor.start().await });
-        }
-    }
-
-    // async fn feature_task(features: Vec<FeatureType>) {
-    //     info!("Spawning feature tasks...");
-    //     for feature in features {
-    //         tokio::spawn(async move { feature.start().await });
-    //     }
-    // }
-
-    // async fn strategy_task(strategies: Vec<StrategyType>) {
-    //     info!("Spawning trader tasks...");
-    //     for strategy in strategies {
-    //         tokio::spawn(async move { strategy.start().await });
-    //     }
-    // }
-
-    // async fn allocation_task(allocation: AllocationType) {
-    //     info!("Spawning allocation tasks...");
-    //     tokio::spawn(async move { allocation.start().await });
-    // }
-
-    // async fn execution_task(executors: Vec<ExecutionType>) {
-    //     info!("Spawning execution tasks...");
-    //     for executor in executors {
-    //         tokio::spawn(async move { executor.start().await });
-    //     }
-    // }
+        ingestors
+            .into_iter()
+            .map(|ingestor| {
+                let signal = stop.subscribe();
+                tokio::spawn(async move { ingestor.start(signal).await })
+            })
+            .collect()
+    }
+
+    async fn trading_task(&self, mut shutdown: ShutdownSignal) {
+        info!("Spawning trading task...");
+        let frequency = Duration::from_secs(self.config.read().clock.tick_frequency);
+        let mut ticks = self.clock.subscribe(frequency);
+        let mut bars = self.bus.subscribe::<Candle>();
+        loop {
+            let bar_close = self
+                .config
+                .read()
+                .feature_pipeline
+                .bar_close
+                .map(|secs| time::Duration::seconds(secs as i64));
+            select! {
+                _ = shutdown.wait() => break,
+                Some(bar) = bars.recv() => {
+                    if bar.closed && Some(bar.interval) == bar_close {
+                        self.step_instruments(&bar.close_time(), vec![bar.instrument]);
+                    }
+                }
+                tick = ticks.recv() => match tick {
+                    Ok(timestamp) if bar_close.is_none() => self.step(&timestamp),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => warn!("Trading task skipped {} clock ticks", skipped),
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+        info!("Trading task stopped");
+    }
+
+    fn step(&self, timestamp: &OffsetDateTime) {
+        let instruments = self.state.list_instruments(&EventType::Trade);
+        self.step_instruments(timestamp, instruments);
+    }
+
+    /// Run the pipeline, strategies and allocations of the instruments as of the timestamp
+    fn step_instruments(&self, timestamp: &OffsetDateTime, instruments: impl IntoIterator<Item = Instrument>) {
+        let trading = self.trading.read().clone();
+
+        let signals = instruments
+            .into_iter()
+            .flat_map(|instrument| {
+                let features = trading.pipeline.calculate(instrument, *timestamp);
+                for feature in &features {
+                    self.bus.publish(feature.clone());
+                }
+                trading.strategy_manager.calculate(&features)
+            })
+            .collect::<Vec<_>>();
+        for signal in &signals {
+            debug!(
+                instrument = %signal.instrument,
+                strategy_id = %signal.strategy_id,
+                signal = %signal.signal,
+                "Signal"
+            );
+            self.bus.publish(signal.clone());
+        }
+
+        let allocations = trading.allocation_manager.calculate(&signals, timestamp);
+        for allocation in &allocations {
+            debug!(
+                instrument = %allocation.instrument,
+                strategy_id = %allocation.strategy_id,
+                notional = %allocation.notional,
+                "Allocation"
+            );
+            self.bus.publish(allocation.clone());
+        }
+
+        if !allocations.is_empty() {
+            self.execution_manager.allocate(&allocations);
+        }
+        *self.last_step.write() = Some(*timestamp);
+    }
+
+    /// All strategies and whether they are running
+    pub fn strategies(&self) -> Vec<(StrategyId, bool)> {
+        self.trading.read().strategy_manager.strategies()
+    }
+
+    pub fn start_strategy(&self, id: &StrategyId) -> Result<(), StrategyError> {
+        self.trading.read().strategy_manager.start(id)
+    }
+
+    pub fn stop_strategy(&self, id: &StrategyId) -> Result<(), StrategyError> {
+        self.trading.read().strategy_manager.stop(id)
+    }
+
+    /// Reload the pipeline, strategies and allocations from the config files, stopped strategies stay stopped.
+    ///
+    /// Changes to any other section are reported but only take effect after a restart.
+    pub fn reload_config(&self) -> Result<ReloadReport> {
+        let report = self.apply_config(config::try_load()?)?;
+        info!("Reloaded configuration: {}", report);
+        Ok(report)
+    }
+
+    fn apply_config(&self, new: GlobalConfig) -> Result<ReloadReport, PipelineError> {
+        let mut config = self.config.write();
+        let (applied, restart_required) = config::changed_paths(&config, &new)
+            .into_iter()
+            .partition::<Vec<_>, _>(|path| RELOADABLE.iter().any(|section| path.starts_with(section)));
+
+        if !applied.is_empty() {
+            let trading = Trading::from_config(self.state.clone(), &new)?;
+            for (id, running) in self.strategies() {
+                if !running {
+                    // The strategy might be gone from the new config
+                    let _ = trading.strategy_manager.stop(&id);
+                }
+            }
+            *self.trading.write() = Arc::new(trading);
+            config.feature_pipeline = new.feature_pipeline;
+            config.strategy_manager = new.strategy_manager;
+            config.allocation_manager = new.allocation_manager;
+        }
+
+        Ok(ReloadReport {
+            applied,
+            restart_required,
+        })
+    }
+
+    /// Reload the config on SIGHUP or when a file in the config directory changes
+    async fn config_watch_task(self: Arc<Self>) {
+        let interval = self.config.read().server.config_poll_interval;
+        let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+        let mut poll = tokio::time::interval(Duration::from_secs(interval.max(1)));
+        let mut modified = config_modified();
+        loop {
+            select! {
+                _ = hangup.recv() => info!("Received SIGHUP, reloading configuration..."),
+                _ = poll.tick(), if interval > 0 => {
+                    let latest = config_modified();
+                    if latest == modified {
+                        continue;
+                    }
+                    modified = latest;
+                    info!("Configuration files changed, reloading configuration...");
+                }
+            }
+            if let Err(e) = self.reload_config() {
+                error!("Failed to reload configuration: {}", e);
+            }
+        }
+    }
+
+    async fn state_metrics_task(self: Arc<Self>, interval: u64) {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            interval.tick().await;
+            self.state.stats().export_metrics(self.clock.now());
+        }
+    }
+
+    pub fn positions(&self) -> Vec<Position> {
+        let mut positions = self
+            .portfolio
+            .positions(&self.clock.now())
+            .into_values()
+            .filter(|p| p.is_open())
+            .collect::<Vec<_>>();
+        positions.sort_by_cached_key(|p| (p.strategy_id.to_string(), p.instrument.to_string()));
+        positions
+    }
+
+    /// Orders that are neither filled, cancelled nor rejected
+    pub fn open_orders(&self) -> Vec<Order> {
+        let mut orders = self
+            .state
+            .events::<Order>(&self.clock.now())
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+        orders.sort_by_key(|o| o.event_time);
+        // Every status change is stored, the last one is the current state
+        let latest = orders.into_iter().map(|o| (o.order_id, o)).collect::<HashMap<_, _>>();
+        let mut open = latest.into_values().filter(|o| o.is_open()).collect::<Vec<_>>();
+        open.sort_by_key(|o| (o.event_time, o.order_id));
+        open
+    }
+
+    /// All fills in the order they happened
+    pub fn fills(&self) -> Vec<Fill> {
+        let mut fills = self
+            .state
+            .events::<Fill>(&self.clock.now())
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+        fills.sort_by_key(|f| (f.event_time, f.order_id));
+        fills
+    }
+
+    /// The most recent fills, newest first
+    pub fn recent_fills(&self, limit: usize) -> Vec<Fill> {
+        self.fills().into_iter().rev().take(limit).collect()
+    }
+
+    /// The most recent signals, newest first
+    pub fn recent_signals(&self, limit: usize) -> Vec<Signal> {
+        let mut signals = self
+            .state
+            .events::<Signal>(&self.clock.now())
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+        signals.sort_by_key(|s| Reverse(s.event_time));
+        signals.truncate(limit);
+        signals
+    }
+
+    /// Time of the last tick or trade of every instrument that has market data
+    pub fn feeds(&self) -> Vec<(Instrument, OffsetDateTime)> {
+        let now = self.clock.now();
+        let mut instruments = self.state.list_instruments(&EventType::Trade);
+        instruments.extend(self.state.list_instruments(&EventType::Tick));
+        let mut feeds = instruments
+            .into_iter()
+            .filter_map(|instrument| {
+                let trade = self
+                    .state
+                    .latest_event_by_instrument::<Trade>(&instrument, &now)
+                    .map(|t| t.event_time);
+                let tick = self
+                    .state
+                    .latest_event_by_instrument::<Tick>(&instrument, &now)
+                    .map(|t| t.event_time);
+                trade.max(tick).map(|last| (instrument, last))
+            })
+            .collect::<Vec<_>>();
+        feeds.sort_by_cached_key(|(instrument, _)| instrument.to_string());
+        feeds
+    }
+
+    pub fn latest_features(&self) -> Vec<FeatureEvent> {
+        let mut features = self.state.latest_features(&self.clock.now());
+        features.sort_by_cached_key(|f| (f.instrument.to_string(), f.id.clone()));
+        features
+    }
+
+    pub fn state_stats(&self) -> StateStats {
+        self.state.stats()
+    }
+
+    pub fn cancel_all_orders(&self) -> usize {
+        self.execution_manager.cancel_all_orders()
+    }
+
+    pub fn now(&self) -> OffsetDateTime {
+        self.clock.now()
+    }
+
+    pub fn state(&self) -> &Arc<StateManager> {
+        &self.state
+    }
+
+    pub fn bus(&self) -> &Arc<EventBus> {
+        &self.bus
+    }
+
+    pub fn health(&self) -> HealthStatus {
+        HealthStatus {
+            uptime: self.started.elapsed(),
+            strategies_running: self.strategies().iter().filter(|(_, running)| *running).count(),
+            last_step: *self.last_step.read(),
+        }
+    }
+
+    /// Switch trading on or off for a strategy, venue or instrument, kept across restarts
+    pub fn set_trading_enabled(&self, flag: Flag, enabled: bool) -> Result<bool, FlagError> {
+        self.flags.set_trading_enabled(flag, enabled)
+    }
+
+    pub fn trading_disabled(&self) -> Vec<Flag> {
+        self.flags.disabled()
+    }
+
+    /// Whether the trading loop is still stepping, a failure means the process should be restarted
+    pub fn liveness(&self) -> HealthReport {
+        let config = self.config.read().health.clone();
+        let limit = Duration::from_secs(config.stall_after);
+        let check = match *self.last_step.read() {
+            Some(step) => {
+                let behind = self.now() - step;
+                Check::new(
+                    "trading",
+                    behind <= limit,
+                    format!("last step {:.0}s ago", behind.as_seconds_f64()),
+                )
+            }
+            None => Check::new("trading", self.started.elapsed() <= limit, "no step yet"),
+        };
+        HealthReport::new(vec![check])
+    }
+
+    /// Whether the engine can trade: fresh market data, a reachable database, endpoints that take orders and a
+    /// bus that keeps up
+    pub async fn readiness(&self) -> HealthReport {
+        let config = self.config.read().clone();
+        let mut checks = vec![Check::new("shutdown", !self.is_shutting_down(), "")];
+
+        let limit = Duration::from_secs(config.health.feed_stale_after);
+        let silence = self.feeds.silence();
+        if silence.is_empty() {
+            checks.push(Check::new("feeds", false, "no market data received yet"));
+        }
+        for (venue, silent) in silence {
+            checks.push(Check::new(
+                format!("feeds.{}", venue),
+                silent <= limit,
+                format!("last market data {:.0}s ago", silent.as_secs_f64()),
+            ));
+        }
+
+        if let Some(timeout) = config.health.db_timeout {
+            checks.push(health::check_database(&config.db, Duration::from_millis(timeout)).await);
+        }
+
+        for (venue, healthy) in self.execution_manager.endpoint_health() {
+            checks.push(Check::new(format!("execution.{}", venue), healthy, ""));
+        }
+
+        let mut backlog = self.bus.backlog().into_iter().collect::<Vec<_>>();
+        backlog.sort_by_key(|(topic, _)| topic.to_string());
+        for (topic, depth) in backlog {
+            let capacity = self.bus.capacity(topic);
+            checks.push(Check::new(
+                format!("bus.{}", topic),
+                depth as f64 <= capacity as f64 * config.health.max_bus_backlog,
+                format!("{}/{} queued", depth, capacity),
+            ));
+        }
+        HealthReport::new(checks)
+    }
 }
 
 #[derive(Default)]
 pub struct ServerBuilder {
     config: Option<GlobalConfig>,
+    recover: bool,
+    collector_feed: bool,
 }
 
 impl ServerBuilder {
@@ -86,13 +696,192 @@ impl ServerBuilder {
         self
     }
 
+    /// Rebuild the state from the journal of a previous run instead of starting a new journal
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Take the market data from a separate collector process instead of running the ingestors
+    pub fn collector_feed(mut self, collector_feed: bool) -> Self {
+        self.collector_feed = collector_feed;
+        self
+    }
+
     pub fn build(self) -> Server {
         let config = self.config.unwrap();
-        Server {
-            state: Arc::new(StateManager::default()),
-            _clock: Arc::new(Clock::from_config(&config.clock)),
-            // _pubsub: Arc::new(PubSub::default()),
-            config,
+        let mut state = StateManager::default()
+            .with_retention(Retention::from_config(&config.state.retention))
+            .with_snapshot_window(Duration::from_secs(config.state.window));
+        if let FeatureStoreConfig::Redb(store) = &config.state.feature_store {
+            let store = RedbFeatureStore::open(store).expect("Failed to open the feature store");
+            state = state.with_feature_store(Box::new(store));
+        }
+        let state = Arc::new(state);
+        let mut bus = EventBus::from_config(&config.bus);
+        let mut recovered = Vec::new();
+        if config.journal.enabled {
+            let journal = if self.recover {
+                let (journal, events) = Journal::recover(&config.journal).expect("Failed to recover from the journal");
+                recovered = events;
+                journal
+            } else {
+                Journal::create(&config.journal).expect("Failed to create the journal")
+            };
+            bus = bus.with_journal(journal);
+        } else if self.recover {
+            warn!("Recovery requested but the journal is disabled, starting empty");
         }
+        // New events are numbered after the recovered ones
+        let last_seq = recovered.iter().map(|e| e.seq).max().unwrap_or_default();
+        let bus = Arc::new(bus.with_sequence(last_seq));
+        let portfolio = Arc::new(Portfolio::new(state.clone(), config.server.capital.into()));
+        let credentials = Arc::new(CredentialStore::from_config(&config.credentials));
+        let time_sync = Arc::new(TimeSync::default());
+        let rest = RestClients::from_config(&config.rest, &time_sync);
+        let skew_guard = Arc::new(SkewGuard::default());
+        let instruments = Arc::new(InstrumentRegistry::from_config(&config.instruments));
+        let flags =
+            Arc::new(FlagRegistry::load(&config.server.trading_flags).expect("Failed to load the trading flags"));
+
+        // Replayed straight into the state, the events already happened and are not published again
+        let recorder = StateRecorder::new(state.clone(), &bus);
+        let mut last_order_id = 0;
+        for event in recovered {
+            if let Event::Order(order) = &event.message {
+                last_order_id = last_order_id.max(order.order_id);
+            }
+            recorder.record_sequenced(event.seq, event.message);
+        }
+
+        let server = Server {
+            clock: Arc::new(LiveClock::from_config(&config.clock)),
+            trading: RwLock::new(Arc::new(
+                Trading::from_config(state.clone(), &config).expect("Failed to build the feature pipeline"),
+            )),
+            // Live fills don't need to be reproducible
+            execution_manager: ExecutionManager::from_config(
+                state.clone(),
+                bus.clone(),
+                portfolio.clone(),
+                rand::random(),
+                &credentials,
+                &config.execution_manager,
+            )
+            .expect("Failed to build the execution endpoints")
+            .with_skew_guard(skew_guard.clone())
+            .with_flags(flags.clone())
+            .with_instruments(instruments.clone()),
+            instruments,
+            skew_guard,
+            collector_feed: self.collector_feed,
+            feeds: Arc::new(FeedActivity::default()),
+            flags,
+            started: Instant::now(),
+            last_step: RwLock::new(None),
+            config: RwLock::new(config),
+            shutdown: Shutdown::default(),
+            state,
+            bus,
+            portfolio,
+            credentials,
+            rest,
+            time_sync,
+        };
+        server.execution_manager.resume_order_ids(last_order_id);
+        server
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{JournalConfig, StrategyConfig};
+
+    #[test]
+    fn test_apply_config() {
+        let config = config::load();
+        let server = Server::builder().config(&config).build();
+        let id = server.strategies()[0].0.clone();
+        server.stop_strategy(&id).unwrap();
+
+        let mut new = config.clone();
+        let StrategyConfig::Crossover(c) = &mut new.strategy_manager.strategies[0];
+        c.volume_spread_id = "spread_sma_vwap".into();
+        new.clock.tick_frequency = 5;
+
+        let report = server.apply_config(new.clone()).unwrap();
+        assert_eq!(report.applied, vec!["strategy_manager.strategies.0.crossover.volume_spread_id"]);
+        assert_eq!(report.restart_required, vec!["clock.tick_frequency"]);
+        assert!(!server.strategies()[0].1);
+
+        // Changes that need a restart are reported until the server is restarted
+        let report = server.apply_config(new).unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.restart_required, vec!["clock.tick_frequency"]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_state() {
+        let server = Server::builder().config(&config::load()).build();
+        let recorder = StateRecorder::new(server.state().clone(), server.bus());
+        let instrument = crate::test_utils::test_perp_instrument();
+        server.bus().publish(Fill::new(
+            server.now(),
+            instrument,
+            1,
+            "test".into(),
+            100.0.into(),
+            1.0.into(),
+            0.5.into(),
+        ));
+
+        // Events still queued on the bus are recorded before the recorder stops
+        let stop = Shutdown::default();
+        stop.trigger();
+        recorder.run(stop.subscribe()).await;
+        assert_eq!(server.fills().len(), 1);
+
+        let path = std::env::temp_dir().join(format!("state_snapshot_{}.json", std::process::id()));
+        server.persist_state(path.to_str().unwrap()).unwrap();
+        let snapshot: StateSnapshot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(snapshot.positions.len(), 1);
+        assert_eq!(snapshot.fills[0].order_id, 1);
+    }
+
+    #[test]
+    fn test_recover_from_journal() {
+        let mut config = config::load();
+        config.journal = JournalConfig {
+            enabled: true,
+            path: std::env::temp_dir()
+                .join(format!("server_journal_{}.jsonl", std::process::id()))
+                .to_string_lossy()
+                .into(),
+            sync: false,
+        };
+        let instrument = crate::test_utils::test_perp_instrument();
+        let server = Server::builder().config(&config).build();
+        let event_time = server.now();
+        server
+            .bus()
+            .publish(Order::new_market(event_time, 7, instrument.clone(), "test".into(), 1.0.into()));
+        server.bus().publish(Fill::new(
+            event_time,
+            instrument,
+            7,
+            "test".into(),
+            100.0.into(),
+            1.0.into(),
+            0.5.into(),
+        ));
+        // The process dies before anything is recorded
+        drop(server);
+
+        let server = Server::builder().config(&config).recover(true).build();
+        std::fs::remove_file(&config.journal.path).unwrap();
+        assert_eq!(server.fills().len(), 1);
+        assert_eq!(server.positions().len(), 1);
     }
 }
@@ -0,0 +1,98 @@
+use std::{future::Future, time::Duration};
+
+use tokio::{
+    select,
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+    time::{timeout, Instant},
+};
+use tracing::{info, warn};
+
+/// Tells a long running task to stop, the task decides where it is safe to do so
+#[derive(Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub fn is_triggered(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the shutdown is triggered
+    pub async fn wait(&mut self) {
+        // The sender is only dropped with the owner of the task, treat that as a shutdown too
+        let _ = self.0.wait_for(|triggered| *triggered).await;
+    }
+}
+
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            sender: watch::channel(false).0,
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn subscribe(&self) -> ShutdownSignal {
+        ShutdownSignal(self.sender.subscribe())
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
+/// Wait for SIGINT or SIGTERM
+pub async fn wait_for_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    select! {
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+        _ = terminate.recv() => info!("Received SIGTERM"),
+    }
+}
+
+/// Run one step of the shutdown, a step that hangs is abandoned so the next ones still run
+pub async fn phase<F: Future>(name: &str, limit: Duration, step: F) -> Option<F::Output> {
+    info!("Shutdown: {}...", name);
+    let start = Instant::now();
+    match timeout(limit, step).await {
+        Ok(output) => {
+            info!("Shutdown: {} done in {:?}", name, start.elapsed());
+            Some(output)
+        }
+        Err(_) => {
+            warn!("Shutdown: {} did not finish within {:?}, moving on", name, limit);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let shutdown = Shutdown::default();
+        let mut signal = shutdown.subscribe();
+        assert!(!signal.is_triggered());
+
+        let task = tokio::spawn(async move {
+            signal.wait().await;
+            signal.is_triggered()
+        });
+        shutdown.trigger();
+        assert!(task.await.unwrap());
+
+        // Late subscribers see the shutdown straight away
+        shutdown.subscribe().wait().await;
+
+        let limit = Duration::from_millis(10);
+        assert_eq!(phase("quick", limit, async { 1 }).await, Some(1));
+        assert_eq!(phase("hanging", limit, std::future::pending::<()>()).await, None);
+    }
+}
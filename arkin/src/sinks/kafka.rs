@@ -0,0 +1,103 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use rdkafka::{
+    config::ClientConfig,
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord, Producer},
+};
+use tracing::{error, info, warn};
+
+use super::{Outbound, Sink, SinkFeed, SinkTopic, SCHEMA_VERSION};
+use crate::{bus::EventBus, config::KafkaSinkConfig, metrics::METRICS, shutdown::ShutdownSignal};
+
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Produces events to kafka keyed by instrument, so every instrument stays ordered within its partition.
+///
+/// The payload is the json of the event, the `schema` and `schema_version` headers tell consumers how to read it.
+pub struct KafkaSink {
+    bus: Arc<EventBus>,
+    config: KafkaSinkConfig,
+}
+
+impl KafkaSink {
+    pub fn new(bus: Arc<EventBus>, config: &KafkaSinkConfig) -> Self {
+        KafkaSink {
+            bus,
+            config: config.to_owned(),
+        }
+    }
+
+    fn producer(&self) -> Result<FutureProducer, rdkafka::error::KafkaError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &self.config.brokers);
+        for (key, value) in &self.config.properties {
+            config.set(key, value);
+        }
+        config.create()
+    }
+
+    fn send(&self, producer: &FutureProducer, topics: &HashMap<SinkTopic, String>, event: &Outbound) {
+        let Some(topic) = topics.get(&event.topic) else {
+            return;
+        };
+        let version = SCHEMA_VERSION.to_string();
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "schema",
+                value: Some(event.topic.schema()),
+            })
+            .insert(Header {
+                key: "schema_version",
+                value: Some(&version),
+            })
+            .insert(Header {
+                key: "content_type",
+                value: Some("application/json"),
+            });
+        let record = FutureRecord::to(topic).key(&event.key).payload(&event.payload).headers(headers);
+        match producer.send_result(record) {
+            // Delivery is confirmed in the background so a slow broker doesn't hold up the next event
+            Ok(delivery) => {
+                tokio::spawn(async move {
+                    match delivery.await {
+                        Ok(Ok(_)) => METRICS.sink_events.with_label_values(&["kafka"]).inc(),
+                        Ok(Err((e, _))) => {
+                            warn!("Kafka failed to deliver event: {}", e);
+                            METRICS.sink_errors.with_label_values(&["kafka"]).inc();
+                        }
+                        Err(_) => METRICS.sink_errors.with_label_values(&["kafka"]).inc(),
+                    }
+                });
+            }
+            Err((e, _)) => {
+                warn!("Failed to queue {} for kafka: {}", event.topic, e);
+                METRICS.sink_errors.with_label_values(&["kafka"]).inc();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
+        let producer = match self.producer() {
+            Ok(producer) => producer,
+            Err(e) => {
+                error!("Failed to create kafka producer for {}: {}", self.config.brokers, e);
+                return;
+            }
+        };
+        let topics = self.config.topics.keys().copied().collect::<Vec<_>>();
+        let mut feed = SinkFeed::new(&self.bus, &topics);
+        info!("Producing {:?} to kafka at {}", topics, self.config.brokers);
+
+        while let Some(event) = feed.next_until(&mut shutdown).await {
+            self.send(&producer, &self.config.topics, &event);
+        }
+        if let Err(e) = producer.flush(FLUSH_TIMEOUT) {
+            warn!("Failed to flush kafka producer: {}", e);
+        }
+    }
+}
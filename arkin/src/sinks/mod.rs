@@ -0,0 +1,195 @@
+use std::{fmt, future::pending, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tokio::select;
+
+mod kafka;
+mod redis;
+
+pub use kafka::KafkaSink;
+pub use redis::RedisSink;
+
+use crate::{
+    bus::{BusMessage, EventBus, Subscription},
+    config::SinkConfig,
+    models::{Fill, Instrument, Order, Signal, Tick, Trade},
+    shutdown::ShutdownSignal,
+};
+
+/// Forwards events from the bus to a system outside of the engine
+#[async_trait]
+pub trait Sink {
+    /// Runs until the shutdown or until the bus is gone, buffered events are flushed before returning
+    async fn start(&self, shutdown: ShutdownSignal);
+}
+
+pub enum SinkType {
+    Redis(RedisSink),
+    Kafka(KafkaSink),
+}
+
+#[async_trait]
+impl Sink for SinkType {
+    async fn start(&self, shutdown: ShutdownSignal) {
+        match self {
+            SinkType::Redis(s) => s.start(shutdown).await,
+            SinkType::Kafka(s) => s.start(shutdown).await,
+        }
+    }
+}
+
+impl fmt::Display for SinkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkType::Redis(_) => write!(f, "redis"),
+            SinkType::Kafka(_) => write!(f, "kafka"),
+        }
+    }
+}
+
+pub struct SinkFactory {}
+
+impl SinkFactory {
+    pub fn from_config(bus: Arc<EventBus>, config: &[SinkConfig]) -> Vec<SinkType> {
+        config
+            .iter()
+            .map(|config| match config {
+                SinkConfig::Redis(c) => SinkType::Redis(RedisSink::new(bus.clone(), c)),
+                SinkConfig::Kafka(c) => SinkType::Kafka(KafkaSink::new(bus.clone(), c)),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SinkTopic {
+    Ticks,
+    Trades,
+    Signals,
+    Orders,
+    Fills,
+}
+
+impl SinkTopic {
+    /// Name of the json schema of the payload, bumped with the version when the models change
+    pub fn schema(&self) -> &'static str {
+        match self {
+            SinkTopic::Ticks => "arkin.tick",
+            SinkTopic::Trades => "arkin.trade",
+            SinkTopic::Signals => "arkin.signal",
+            SinkTopic::Orders => "arkin.order",
+            SinkTopic::Fills => "arkin.fill",
+        }
+    }
+}
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// An event serialized as json, keyed by its instrument
+pub struct Outbound {
+    pub topic: SinkTopic,
+    pub key: String,
+    pub payload: String,
+}
+
+impl Outbound {
+    fn new(topic: SinkTopic, instrument: &Instrument, event: &impl Serialize) -> Self {
+        Outbound {
+            topic,
+            key: instrument.to_string(),
+            payload: serde_json::to_string(event).expect("Events always serialize"),
+        }
+    }
+}
+
+/// The topics a sink forwards, only those are subscribed on the bus
+pub struct SinkFeed {
+    ticks: Option<Subscription<Tick>>,
+    trades: Option<Subscription<Trade>>,
+    signals: Option<Subscription<Signal>>,
+    orders: Option<Subscription<Order>>,
+    fills: Option<Subscription<Fill>>,
+}
+
+impl SinkFeed {
+    pub fn new(bus: &EventBus, topics: &[SinkTopic]) -> Self {
+        let subscribe = |topic| topics.contains(&topic);
+        SinkFeed {
+            ticks: subscribe(SinkTopic::Ticks).then(|| bus.subscribe()),
+            trades: subscribe(SinkTopic::Trades).then(|| bus.subscribe()),
+            signals: subscribe(SinkTopic::Signals).then(|| bus.subscribe()),
+            orders: subscribe(SinkTopic::Orders).then(|| bus.subscribe()),
+            fills: subscribe(SinkTopic::Fills).then(|| bus.subscribe()),
+        }
+    }
+
+    /// Next event to forward, None once the bus is gone
+    pub async fn next(&mut self) -> Option<Outbound> {
+        select! {
+            Some(e) = next(&mut self.ticks) => Some(Outbound::new(SinkTopic::Ticks, &e.instrument, &e)),
+            Some(e) = next(&mut self.trades) => Some(Outbound::new(SinkTopic::Trades, &e.instrument, &e)),
+            Some(e) = next(&mut self.signals) => Some(Outbound::new(SinkTopic::Signals, &e.instrument, &e)),
+            Some(e) = next(&mut self.orders) => Some(Outbound::new(SinkTopic::Orders, &e.instrument, &e)),
+            Some(e) = next(&mut self.fills) => Some(Outbound::new(SinkTopic::Fills, &e.instrument, &e)),
+            else => None,
+        }
+    }
+
+    /// Next event to forward, None once the bus is gone or the shutdown started
+    pub async fn next_until(&mut self, shutdown: &mut ShutdownSignal) -> Option<Outbound> {
+        select! {
+            event = self.next() => event,
+            _ = shutdown.wait() => None,
+        }
+    }
+}
+
+/// Next message of an optional subscription, never resolves without one
+async fn next<T: BusMessage>(subscription: &mut Option<Subscription<T>>) -> Option<T> {
+    match subscription {
+        Some(subscription) => subscription.recv().await,
+        None => pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, test_utils};
+    use time::macros::datetime;
+
+    #[tokio::test]
+    async fn test_sink_feed() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let mut feed = SinkFeed::new(&bus, &[SinkTopic::Ticks, SinkTopic::Fills]);
+        let instrument = test_utils::test_perp_instrument();
+        let event_time = datetime!(2024-01-01 00:00:00).assume_utc();
+
+        // Signals are not forwarded so nobody listens for them
+        let signal = Signal::new(event_time, instrument.clone(), "test".into(), 1.0.into());
+        assert_eq!(bus.publish(signal), 0);
+        bus.publish(Tick::new(
+            event_time,
+            instrument.clone(),
+            1,
+            100.0.into(),
+            1.0.into(),
+            101.0.into(),
+            1.0.into(),
+        ));
+
+        let outbound = feed.next().await.unwrap();
+        assert_eq!(outbound.topic, SinkTopic::Ticks);
+        assert_eq!(outbound.key, instrument.to_string());
+        let tick: Tick = serde_json::from_str(&outbound.payload).unwrap();
+        assert_eq!(tick.tick_id, 1);
+
+        let stop = crate::shutdown::Shutdown::default();
+        stop.trigger();
+        assert!(feed.next_until(&mut stop.subscribe()).await.is_none());
+    }
+}
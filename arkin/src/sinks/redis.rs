@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, streams::StreamMaxlen, AsyncCommands, Client, RedisResult};
+use tracing::{error, info, warn};
+
+use super::{Outbound, Sink, SinkFeed};
+use crate::{
+    bus::EventBus,
+    config::{RedisMode, RedisSinkConfig},
+    metrics::METRICS,
+    shutdown::ShutdownSignal,
+};
+
+/// Publishes events to redis channels or streams named `<prefix>.<topic>`
+pub struct RedisSink {
+    bus: Arc<EventBus>,
+    config: RedisSinkConfig,
+}
+
+impl RedisSink {
+    pub fn new(bus: Arc<EventBus>, config: &RedisSinkConfig) -> Self {
+        RedisSink {
+            bus,
+            config: config.to_owned(),
+        }
+    }
+
+    async fn send(&self, connection: &mut ConnectionManager, event: &Outbound) -> RedisResult<()> {
+        let key = format!("{}.{}", self.config.prefix, event.topic);
+        match self.config.mode {
+            RedisMode::PubSub => connection.publish(key, &event.payload).await,
+            RedisMode::Stream => {
+                let max_len = StreamMaxlen::Approx(self.config.stream_max_len);
+                let fields = [("instrument", &event.key), ("data", &event.payload)];
+                connection.xadd_maxlen::<_, _, _, _, ()>(key, max_len, "*", &fields).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for RedisSink {
+    async fn start(&self, mut shutdown: ShutdownSignal) {
+        let client = match Client::open(self.config.url.as_str()) {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Invalid redis url {}: {}", self.config.url, e);
+                return;
+            }
+        };
+        // Subscribe first so nothing published while connecting is missed
+        let mut feed = SinkFeed::new(&self.bus, &self.config.topics);
+        // The connection manager reconnects on its own after the first connection succeeded
+        let mut connection = match ConnectionManager::new(client).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to connect to redis at {}: {}", self.config.url, e);
+                return;
+            }
+        };
+        info!("Publishing {:?} to redis at {}", self.config.topics, self.config.url);
+
+        while let Some(event) = feed.next_until(&mut shutdown).await {
+            match self.send(&mut connection, &event).await {
+                Ok(()) => METRICS.sink_events.with_label_values(&["redis"]).inc(),
+                Err(e) => {
+                    warn!("Failed to publish {} to redis: {}", event.topic, e);
+                    METRICS.sink_errors.with_label_values(&["redis"]).inc();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use parking_lot::RwLock;
+use serde_json::Value;
+use time::OffsetDateTime;
+use tokio::{select, time::interval};
+use tracing::{error, info, warn};
+
+use crate::{
+    bus::EventBus,
+    clock::TimeSync,
+    config::{ClockSkewConfig, ServerTimeConfig},
+    metrics::METRICS,
+    models::{Book, Trade, Venue},
+};
+
+/// Weight of a new sample in the average event delay
+const DELAY_SMOOTHING: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkewLevel {
+    Ok,
+    Warn,
+    Block,
+}
+
+/// Venues whose clock is too far from ours to send them orders
+#[derive(Default)]
+pub struct SkewGuard {
+    blocked: RwLock<HashSet<Venue>>,
+}
+
+impl SkewGuard {
+    pub fn is_blocked(&self, venue: &Venue) -> bool {
+        self.blocked.read().contains(venue)
+    }
+
+    fn set_blocked(&self, venue: &Venue, blocked: bool) {
+        if blocked {
+            self.blocked.write().insert(venue.clone());
+        } else {
+            self.blocked.write().remove(venue);
+        }
+    }
+}
+
+/// Offset of the server clock from ours, assuming the request took as long to get there as to come back
+pub fn clock_offset(sent: OffsetDateTime, received: OffsetDateTime, server: OffsetDateTime) -> time::Duration {
+    server - (sent + (received - sent) / 2)
+}
+
+/// Server time of a venue's time endpoint
+fn parse_server_time(venue: &Venue, body: &Value) -> Result<OffsetDateTime> {
+    let millis = match venue {
+        Venue::Binance => body["serverTime"].as_i64(),
+        Venue::Coinbase => body["epoch"].as_f64().map(|s| (s * 1000.) as i64),
+        Venue::Bybit => body["time"].as_i64(),
+        Venue::Simulation => None,
+    }
+    .ok_or_else(|| anyhow!("No server time in response of {}", venue))?;
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000)?)
+}
+
+/// Checks the local clock against the venues' server time and how late market data arrives.
+///
+/// Every offset is handed to the [`TimeSync`], so received times and signatures follow the venue's clock.
+/// Offsets beyond `warn_after` are logged, beyond `block_after` the venue is blocked in the [`SkewGuard`] until
+/// the clocks agree again. The delay between event and receive time only warns, it is the network latency.
+pub struct SkewMonitor {
+    bus: Arc<EventBus>,
+    guard: Arc<SkewGuard>,
+    time_sync: Arc<TimeSync>,
+    config: ClockSkewConfig,
+    client: reqwest::Client,
+    levels: HashMap<Venue, SkewLevel>,
+    delays: HashMap<Venue, (f64, SkewLevel)>,
+}
+
+impl SkewMonitor {
+    pub fn from_config(
+        bus: Arc<EventBus>,
+        guard: Arc<SkewGuard>,
+        time_sync: Arc<TimeSync>,
+        config: &ClockSkewConfig,
+    ) -> Self {
+        SkewMonitor {
+            bus,
+            guard,
+            time_sync,
+            config: config.to_owned(),
+            client: reqwest::Client::new(),
+            levels: HashMap::new(),
+            delays: HashMap::new(),
+        }
+    }
+
+    pub fn level(&self, offset: time::Duration) -> SkewLevel {
+        let millis = offset.whole_milliseconds().unsigned_abs();
+        if millis > self.config.block_after as u128 {
+            SkewLevel::Block
+        } else if millis > self.config.warn_after as u128 {
+            SkewLevel::Warn
+        } else {
+            SkewLevel::Ok
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!("Starting clock skew monitor...");
+        let mut trades = self.bus.subscribe::<Trade>();
+        let mut books = self.bus.subscribe::<Book>();
+        let mut check = interval(Duration::from_secs(self.config.interval));
+        loop {
+            select! {
+                _ = check.tick() => {
+                    for server in self.config.venues.clone() {
+                        match self.measure(&server).await {
+                            Ok(offset) => self.update_offset(&server.venue, offset),
+                            Err(e) => warn!("Failed to get server time of {}: {}", server.venue, e),
+                        }
+                    }
+                }
+                Some(trade) = trades.recv() => {
+                    self.update_delay(trade.instrument.venue(), trade.received_time - trade.event_time)
+                }
+                Some(book) = books.recv() => self.update_delay(book.instrument.venue(), book.received_time - book.event_time),
+            }
+        }
+    }
+
+    async fn measure(&self, server: &ServerTimeConfig) -> Result<time::Duration> {
+        let sent = OffsetDateTime::now_utc();
+        let body = self
+            .client
+            .get(&server.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+        let received = OffsetDateTime::now_utc();
+        Ok(clock_offset(sent, received, parse_server_time(&server.venue, &body)?))
+    }
+
+    pub fn update_offset(&mut self, venue: &Venue, offset: time::Duration) {
+        METRICS
+            .clock_offset
+            .with_label_values(&[&venue.to_string()])
+            .set(offset.as_seconds_f64());
+        self.time_sync.set_offset(venue, offset);
+        let level = self.level(offset);
+        let previous = self.levels.insert(venue.clone(), level).unwrap_or(SkewLevel::Ok);
+        self.guard.set_blocked(venue, level == SkewLevel::Block);
+        if level == previous {
+            return;
+        }
+        let millis = offset.whole_milliseconds();
+        match level {
+            SkewLevel::Block => error!(venue = %venue, offset_ms = millis, "Clock skew too large, orders are blocked"),
+            SkewLevel::Warn => warn!(venue = %venue, offset_ms = millis, "Clock skew is growing"),
+            SkewLevel::Ok => info!(venue = %venue, offset_ms = millis, "Clock skew is back to normal"),
+        }
+    }
+
+    fn update_delay(&mut self, venue: &Venue, delay: time::Duration) {
+        let delay = delay.as_seconds_f64();
+        let (average, previous) = self.delays.get(venue).copied().unwrap_or((delay, SkewLevel::Ok));
+        let average = average + DELAY_SMOOTHING * (delay - average);
+        METRICS.event_delay.with_label_values(&[&venue.to_string()]).set(average);
+
+        // Late data is not ours to fix, it only warns and the crossing is logged once
+        let average = time::Duration::seconds_f64(average);
+        let level = match self.level(average) {
+            SkewLevel::Ok => SkewLevel::Ok,
+            _ => SkewLevel::Warn,
+        };
+        self.delays.insert(venue.clone(), (average.as_seconds_f64(), level));
+        if level == previous {
+            return;
+        }
+        let millis = average.whole_milliseconds();
+        if level == SkewLevel::Warn {
+            warn!(venue = %venue, delay_ms = millis, "Market data arrives late or event times are ahead of our clock");
+        } else {
+            info!(venue = %venue, delay_ms = millis, "Market data delay is back to normal");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use serde_json::json;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_clock_skew() {
+        let sent = datetime!(2024-01-01 00:00:00).assume_utc();
+        let received = sent + time::Duration::milliseconds(100);
+        let server = parse_server_time(&Venue::Binance, &json!({ "serverTime": 1704067201050_i64 })).unwrap();
+        assert_eq!(clock_offset(sent, received, server), time::Duration::milliseconds(1000));
+        assert!(parse_server_time(&Venue::Binance, &json!({})).is_err());
+
+        let config = config::load();
+        let bus = Arc::new(EventBus::from_config(&config.bus));
+        let guard = Arc::new(SkewGuard::default());
+        let time_sync = Arc::new(TimeSync::default());
+        let mut monitor = SkewMonitor::from_config(bus, guard.clone(), time_sync.clone(), &config.clock_skew);
+        assert_eq!(monitor.level(time::Duration::milliseconds(-500)), SkewLevel::Warn);
+
+        monitor.update_offset(&Venue::Binance, time::Duration::milliseconds(1500));
+        assert!(guard.is_blocked(&Venue::Binance));
+        assert_eq!(time_sync.offset(&Venue::Binance), time::Duration::milliseconds(1500));
+        assert!(!guard.is_blocked(&Venue::Simulation));
+        monitor.update_offset(&Venue::Binance, time::Duration::milliseconds(20));
+        assert!(!guard.is_blocked(&Venue::Binance));
+    }
+}
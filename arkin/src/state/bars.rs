@@ -0,0 +1,239 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rust_decimal::Decimal;
+use time::{Duration, OffsetDateTime};
+use tokio::{select, sync::broadcast::error::RecvError};
+use tracing::{info, warn};
+
+use crate::{
+    bus::{EventBus, Subscription},
+    clock::Clock,
+    config::BarConfig,
+    models::{Candle, Instrument, Trade},
+    shutdown::ShutdownSignal,
+};
+
+/// Builds bars of one kind per instrument from the trades
+pub struct BarAggregator {
+    bar: BarConfig,
+    open: HashMap<Instrument, Candle>,
+}
+
+impl BarAggregator {
+    pub fn new(bar: BarConfig) -> Self {
+        BarAggregator {
+            bar,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Adds the trade to the open bar of its instrument, returns the bar it closed. A time bar closes on the
+    /// first trade after its interval, intervals without trades are left out.
+    pub fn update(&mut self, trade: &Trade) -> Option<Candle> {
+        let mut closed = None;
+        if let BarConfig::Time(interval) = self.bar {
+            let interval = Duration::seconds(interval as i64);
+            if let Some(bar) = self.open.get(&trade.instrument) {
+                if trade.event_time >= bar.close_time() {
+                    closed = self.open.remove(&trade.instrument).map(|bar| close(bar, None));
+                }
+            }
+            let open_time = align(trade.event_time, interval);
+            let bar = self
+                .open
+                .entry(trade.instrument.clone())
+                .or_insert_with(|| open(trade, open_time, interval));
+            add(bar, trade);
+            return closed;
+        }
+
+        let bar = self
+            .open
+            .entry(trade.instrument.clone())
+            .or_insert_with(|| open(trade, trade.event_time, Duration::ZERO));
+        add(bar, trade);
+        let full = match self.bar {
+            BarConfig::Tick(trades) => bar.trades >= trades,
+            BarConfig::Volume(quantity) => bar.volume.value() >= quantity,
+            BarConfig::Dollar(notional) => bar.quote_volume.value() >= notional,
+            BarConfig::Time(_) => unreachable!(),
+        };
+        if full {
+            closed = self
+                .open
+                .remove(&trade.instrument)
+                .map(|bar| close(bar, Some(trade.event_time)));
+        }
+        closed
+    }
+
+    /// Closes the time bars whose interval ended by now, so a bar does not wait for the next trade
+    pub fn close_due(&mut self, now: OffsetDateTime) -> Vec<Candle> {
+        if !matches!(self.bar, BarConfig::Time(_)) {
+            return Vec::new();
+        }
+        let due = self
+            .open
+            .iter()
+            .filter(|(_, bar)| bar.close_time() <= now)
+            .map(|(instrument, _)| instrument.clone())
+            .collect::<Vec<_>>();
+        due.iter()
+            .filter_map(|instrument| self.open.remove(instrument))
+            .map(|bar| close(bar, None))
+            .collect()
+    }
+}
+
+fn align(time: OffsetDateTime, interval: Duration) -> OffsetDateTime {
+    let nanos = time.unix_timestamp_nanos();
+    let interval = interval.whole_nanoseconds();
+    OffsetDateTime::from_unix_timestamp_nanos(nanos - nanos.rem_euclid(interval)).unwrap_or(time)
+}
+
+fn open(trade: &Trade, open_time: OffsetDateTime, interval: Duration) -> Candle {
+    Candle {
+        event_time: trade.event_time,
+        instrument: trade.instrument.clone(),
+        interval,
+        open_time,
+        open: trade.price,
+        high: trade.price,
+        low: trade.price,
+        close: trade.price,
+        volume: Decimal::ZERO.into(),
+        quote_volume: Decimal::ZERO.into(),
+        trades: 0,
+        closed: false,
+        source: trade.source.clone(),
+    }
+}
+
+fn add(bar: &mut Candle, trade: &Trade) {
+    let quantity = trade.quantity.value().abs();
+    bar.event_time = trade.event_time;
+    bar.high = bar.high.value().max(trade.price.value()).into();
+    bar.low = bar.low.value().min(trade.price.value()).into();
+    bar.close = trade.price;
+    bar.volume = (bar.volume.value() + quantity).into();
+    bar.quote_volume += (trade.price.value() * quantity).into();
+    bar.trades += 1;
+}
+
+/// Time bars close at the end of their interval, the others at their last trade and span the time it took
+fn close(mut bar: Candle, last_trade: Option<OffsetDateTime>) -> Candle {
+    let close_time = match last_trade {
+        Some(time) => {
+            bar.interval = time - bar.open_time;
+            time
+        }
+        None => bar.close_time(),
+    };
+    bar.event_time = close_time;
+    bar.closed = true;
+    bar
+}
+
+/// Publishes the closed bars of the trades on the bus as candles, which the pipeline can step on
+pub struct BarService {
+    bus: Arc<EventBus>,
+    clock: Arc<dyn Clock>,
+    aggregators: Vec<BarAggregator>,
+    trades: Subscription<Trade>,
+}
+
+impl BarService {
+    pub fn from_config(bus: Arc<EventBus>, clock: Arc<dyn Clock>, config: &[BarConfig]) -> Self {
+        BarService {
+            trades: bus.subscribe(),
+            bus,
+            clock,
+            aggregators: config.iter().copied().map(BarAggregator::new).collect(),
+        }
+    }
+
+    pub async fn run(mut self, mut shutdown: ShutdownSignal) {
+        info!("Starting bar aggregation of {} bar kinds...", self.aggregators.len());
+        let mut ticks = self.clock.subscribe(std::time::Duration::from_secs(1));
+        loop {
+            select! {
+                _ = shutdown.wait() => break,
+                trade = self.trades.recv() => match trade {
+                    Some(trade) => {
+                        let closed = self.aggregators.iter_mut().filter_map(|a| a.update(&trade)).collect::<Vec<_>>();
+                        closed.into_iter().for_each(|bar| { self.bus.publish(bar); });
+                    }
+                    None => break,
+                },
+                tick = ticks.recv() => match tick {
+                    Ok(now) => {
+                        let closed = self.aggregators.iter_mut().flat_map(|a| a.close_due(now)).collect::<Vec<_>>();
+                        closed.into_iter().for_each(|bar| { self.bus.publish(bar); });
+                    }
+                    Err(RecvError::Lagged(skipped)) => warn!("Bar aggregation skipped {} clock ticks", skipped),
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+        info!("Bar aggregation stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingestors::IngestorID, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_bar_aggregation() {
+        let instrument = test_utils::test_perp_instrument();
+        let trade = |time: OffsetDateTime, price: i64, quantity: i64| {
+            Trade::new(
+                time,
+                time,
+                instrument.clone(),
+                0,
+                Decimal::from(price).into(),
+                Decimal::from(quantity).into(),
+                IngestorID::Test,
+            )
+        };
+        let start = datetime!(2024-01-01 00:00:10).assume_utc();
+
+        let mut minutes = BarAggregator::new(BarConfig::Time(60));
+        assert!(minutes.update(&trade(start, 100, 1)).is_none());
+        assert!(minutes.update(&trade(start + Duration::seconds(20), 103, -2)).is_none());
+        assert!(minutes.update(&trade(start + Duration::seconds(30), 99, 1)).is_none());
+        let bar = minutes.update(&trade(start + Duration::seconds(55), 101, 1)).unwrap();
+        assert_eq!(bar.open_time, datetime!(2024-01-01 00:00:00).assume_utc());
+        assert_eq!(bar.event_time, datetime!(2024-01-01 00:01:00).assume_utc());
+        assert_eq!(
+            [bar.open, bar.high, bar.low, bar.close].map(|p| p.value()),
+            [100, 103, 99, 99].map(Decimal::from)
+        );
+        // Sells count towards the volume as well
+        assert_eq!(bar.volume.value(), Decimal::from(4));
+        assert_eq!(bar.quote_volume.value(), Decimal::from(405));
+        assert!(bar.closed && bar.trades == 3);
+
+        // The clock closes the bar of the quiet minute
+        assert!(minutes.close_due(datetime!(2024-01-01 00:01:59).assume_utc()).is_empty());
+        let bar = minutes.close_due(datetime!(2024-01-01 00:02:00).assume_utc()).remove(0);
+        assert!(bar.trades == 1 && bar.close_time() == datetime!(2024-01-01 00:02:00).assume_utc());
+
+        let mut volume = BarAggregator::new(BarConfig::Volume(Decimal::from(3)));
+        assert!(volume.update(&trade(start, 100, 2)).is_none());
+        let bar = volume.update(&trade(start + Duration::seconds(5), 102, -1)).unwrap();
+        assert_eq!(bar.interval, Duration::seconds(5));
+        assert_eq!(bar.close.value(), Decimal::from(102));
+        assert!(volume.close_due(start + Duration::seconds(3600)).is_empty());
+
+        let mut ticks = BarAggregator::new(BarConfig::Tick(2));
+        assert!(ticks.update(&trade(start, 100, 1)).is_none());
+        assert!(ticks.update(&trade(start, 100, 1)).is_some());
+        let mut dollars = BarAggregator::new(BarConfig::Dollar(Decimal::from(150)));
+        assert!(dollars.update(&trade(start, 100, 1)).is_none());
+        assert!(dollars.update(&trade(start, 100, -1)).is_some());
+    }
+}
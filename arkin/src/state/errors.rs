@@ -0,0 +1,46 @@
+use thiserror::Error;
+use time::OffsetDateTime;
+
+use crate::models::EventType;
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("Lookahead bias: query at {query} while the clock is at {now}")]
+    LookaheadQuery {
+        query: OffsetDateTime,
+        now: OffsetDateTime,
+    },
+
+    #[error("Lookahead bias: read {event_type} event at {event_time} received at {received_time} while the clock is at {now}")]
+    LookaheadEvent {
+        event_type: EventType,
+        event_time: OffsetDateTime,
+        received_time: OffsetDateTime,
+        now: OffsetDateTime,
+    },
+
+    #[error("Feature store error: {0}")]
+    FeatureStore(Box<redb::Error>),
+}
+
+macro_rules! feature_store_errors {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for StateError {
+                fn from(err: $error) -> Self {
+                    StateError::FeatureStore(Box::new(err.into()))
+                }
+            }
+        )*
+    };
+}
+
+feature_store_errors!(
+    redb::Error,
+    redb::DatabaseError,
+    redb::CompactionError,
+    redb::TransactionError,
+    redb::TableError,
+    redb::StorageError,
+    redb::CommitError
+);
@@ -1,7 +1,4 @@
-use std::{
-    collections::{BTreeMap, HashSet},
-    time::Duration,
-};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use dashmap::DashMap;
 use time::OffsetDateTime;
@@ -11,21 +8,89 @@ use crate::{
     utils::CompositeIndex,
 };
 
+use super::{LookaheadGuard, Retention, SeriesStats, TimeSeries};
+
+/// Every instrument and event type is a series of its own behind the lock of its map shard, so writers of
+/// different instruments rarely wait on each other
 #[derive(Default)]
 pub struct EventState {
-    events: DashMap<(Instrument, EventType), BTreeMap<CompositeIndex, Event>>,
+    events: DashMap<(Instrument, EventType), TimeSeries<Event>>,
+    /// Events that span instruments, like the account updates of a venue
+    account_events: DashMap<EventType, TimeSeries<Event>>,
+    guard: Option<Arc<LookaheadGuard>>,
+    /// Only bounds market data, positions are rebuilt from every fill
+    retention: Retention,
 }
 
 impl EventState {
-    pub fn add_event(&self, event: Event) {
-        let key = (event.instrument().clone(), event.event_type());
-        let mut composit_key = CompositeIndex::new(event.event_time());
+    pub fn with_guard(guard: Arc<LookaheadGuard>) -> Self {
+        EventState {
+            events: DashMap::new(),
+            account_events: DashMap::new(),
+            guard: Some(guard),
+            retention: Retention::default(),
+        }
+    }
+
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    /// Reads are as of their timestamp, events that were only received after it are left out so a read in a
+    /// replay returns what a live read at that time did
+    fn is_visible(&self, event: &Event, as_of: &OffsetDateTime) -> bool {
+        debug_assert!(
+            event.event_time() <= as_of,
+            "Lookahead bias: read {} event at {} as of {}",
+            event.event_type(),
+            event.event_time(),
+            as_of
+        );
+        event.known_time() <= as_of && self.guard.as_ref().is_none_or(|g| g.is_visible(event))
+    }
 
-        let mut entry = self.events.entry(key).or_default();
-        while entry.get(&composit_key).is_some() {
-            composit_key.increment();
+    /// Events at the same time are kept in the order of their sequence numbers, unnumbered ones in the order
+    /// they were added
+    pub fn add_sequenced_event(&self, seq: u64, event: Event) {
+        let index = CompositeIndex::with_index(event.event_time(), seq);
+        match event.instrument() {
+            Some(instrument) => {
+                let event_type = event.event_type();
+                let mut series = self.events.entry((instrument.clone(), event_type)).or_default();
+                series.insert(index, event);
+                if event_type.is_market_data() {
+                    series.prune(&self.retention);
+                }
+            }
+            None => {
+                self.account_events.entry(event.event_type()).or_default().insert(index, event);
+            }
         }
-        entry.insert(composit_key, event);
+    }
+
+    /// Events without an instrument up to and including the timestamp, oldest first
+    pub fn list_account_entries<T>(&self, timestamp: &OffsetDateTime) -> Vec<T>
+    where
+        T: TryFrom<Event, Error = ()> + EventTypeOf,
+    {
+        self.account_events
+            .get(&T::event_type())
+            .map(|series| {
+                series
+                    .until(timestamp)
+                    .filter(|(_, event)| self.is_visible(event, timestamp))
+                    .filter_map(|(_, event)| event.clone().try_into().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Number of stored events per instrument and event type
+    pub fn stats(&self) -> Vec<(Instrument, EventType, SeriesStats)> {
+        self.events
+            .iter()
+            .map(|entry| (entry.key().0.clone(), entry.key().1, entry.value().stats()))
+            .collect()
     }
 
     pub fn list_instruments(&self, event_type: &EventType) -> HashSet<Instrument> {
@@ -45,26 +110,67 @@ impl EventState {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
-        let event_type = T::event_type();
-        let index = CompositeIndex::new_max(timestamp);
         self.events
-            .get(&(instrument.clone(), event_type))
-            .and_then(|tree| tree.value().range(..index).next_back().map(|entry| entry.1.clone()))
+            .get(&(instrument.clone(), T::event_type()))
+            .and_then(|series| {
+                series
+                    .until(timestamp)
+                    .rev()
+                    .find(|(_, entry)| self.is_visible(entry, timestamp))
+                    .map(|entry| entry.1.clone())
+            })
             .and_then(|event| event.try_into().ok())
     }
 
+    /// The last n entries up to and including the timestamp, oldest first
+    pub fn last_n_entries<T>(&self, instrument: &Instrument, timestamp: &OffsetDateTime, n: usize) -> Vec<T>
+    where
+        T: TryFrom<Event, Error = ()> + EventTypeOf,
+    {
+        let mut entries = self
+            .events
+            .get(&(instrument.clone(), T::event_type()))
+            .map(|series| {
+                series
+                    .until(timestamp)
+                    .rev()
+                    .filter(|(_, entry)| self.is_visible(entry, timestamp))
+                    .take(n)
+                    .filter_map(|(_, entry)| entry.clone().try_into().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.reverse();
+        entries
+    }
+
     pub fn list_entries_since_start<T>(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<T>
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
-        let event_type = T::event_type();
-        let index = CompositeIndex::new_max(timestamp);
+        self.events
+            .get(&(instrument.clone(), T::event_type()))
+            .map(|series| {
+                series
+                    .until(timestamp)
+                    .filter(|(_, entry)| self.is_visible(entry, timestamp))
+                    .filter_map(|(_, entry)| entry.clone().try_into().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
+    pub fn list_entries_before<T>(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<T>
+    where
+        T: TryFrom<Event, Error = ()> + EventTypeOf,
+    {
         self.events
-            .get(&(instrument.clone(), event_type))
-            .map(|set| {
-                // Perform a range query up to the maximum key
-                set.range(..index)
+            .get(&(instrument.clone(), T::event_type()))
+            .map(|series| {
+                // Excludes the events at the timestamp
+                series
+                    .before(timestamp)
+                    .filter(|(_, entry)| self.is_visible(entry, timestamp))
                     .filter_map(|(_, entry)| entry.clone().try_into().ok())
                     .collect()
             })
@@ -80,15 +186,12 @@ impl EventState {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
-        let event_type = T::event_type();
-        let index = CompositeIndex::new_max(timestamp);
-        let end_index = CompositeIndex::new(&(*timestamp - *window));
-
         self.events
-            .get(&(instrument.clone(), event_type))
-            .map(|set| {
-                // Perform a range query up to the maximum key
-                set.range(end_index..index)
+            .get(&(instrument.clone(), T::event_type()))
+            .map(|series| {
+                series
+                    .window(timestamp, window)
+                    .filter(|(_, entry)| self.is_visible(entry, timestamp))
                     .filter_map(|(_, entry)| entry.clone().try_into().ok())
                     .collect()
             })
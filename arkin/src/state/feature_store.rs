@@ -0,0 +1,431 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use redb::{
+    AccessGuard, Database, Durability, ReadableTable, ReadableTableMetadata, StorageError, TableDefinition,
+    WriteTransaction,
+};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::RedbFeatureStoreConfig,
+    features::{FeatureEvent, FeatureId},
+    models::Instrument,
+};
+
+use super::{FeatureStore, SeriesStats, StateError};
+
+/// Instrument and feature of a series as json, to the id its values are stored under
+const SERIES: TableDefinition<&str, u64> = TableDefinition::new("series");
+/// Values by series id, event time in nanoseconds and index among the values at the same time
+const FEATURES: TableDefinition<(u64, i128, u64), f64> = TableDefinition::new("features");
+/// Number of stored values by series id, kept with every write so the stats don't scan the values
+const COUNTS: TableDefinition<u64, u64> = TableDefinition::new("counts");
+/// Values written between the deletions of the values past the ttl
+const PRUNE_EVERY: usize = 10_000;
+
+/// Key and value read from the features table
+type Entry<'a> = Result<(AccessGuard<'a, (u64, i128, u64)>, AccessGuard<'a, f64>), StorageError>;
+
+/// Event time of the key read from the features table
+fn entry_time(entry: Option<Entry>) -> Result<Option<OffsetDateTime>, StorageError> {
+    let entry = entry.transpose()?;
+    Ok(entry.and_then(|(k, _)| OffsetDateTime::from_unix_timestamp_nanos(k.value().1).ok()))
+}
+
+/// Feature series in a redb file, so they survive a restart and are not bounded by the memory.
+/// Failed reads and writes are logged and read as empty, like a series that was never written.
+pub struct RedbFeatureStore {
+    db: Database,
+    series: DashMap<(Instrument, FeatureId), u64>,
+    /// Features with a series per instrument
+    ids: DashMap<Instrument, Vec<FeatureId>>,
+    ttl: Option<i128>,
+    written: AtomicUsize,
+}
+
+impl RedbFeatureStore {
+    pub fn open(config: &RedbFeatureStoreConfig) -> Result<Self, StateError> {
+        let mut db = Database::create(&config.path)?;
+        if config.compact_on_start {
+            db.compact()?;
+        }
+        let tx = db.begin_write()?;
+        tx.open_table(SERIES)?;
+        tx.open_table(FEATURES)?;
+        tx.open_table(COUNTS)?;
+        tx.commit()?;
+
+        let store = RedbFeatureStore {
+            db,
+            series: DashMap::new(),
+            ids: DashMap::new(),
+            ttl: config.ttl.map(|s| s as i128 * 1_000_000_000),
+            written: AtomicUsize::new(0),
+        };
+        store.load_series()?;
+        store.count_series()?;
+        let tx = store.db.begin_write()?;
+        store.prune(&tx)?;
+        tx.commit()?;
+        info!("Opened the feature store {} with {} series", config.path, store.series.len());
+        Ok(store)
+    }
+
+    fn load_series(&self) -> Result<(), StateError> {
+        let tx = self.db.begin_read()?;
+        for entry in tx.open_table(SERIES)?.iter()? {
+            let (key, id) = entry?;
+            match serde_json::from_str::<(Instrument, FeatureId)>(key.value()) {
+                Ok(series) => self.add_series(series, id.value()),
+                Err(e) => warn!("Skipping feature series {}: {}", key.value(), e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the values of the series without a count once, for files written before the counts were kept
+    fn count_series(&self) -> Result<(), StateError> {
+        let tx = self.db.begin_write()?;
+        {
+            let features = tx.open_table(FEATURES)?;
+            let mut counts = tx.open_table(COUNTS)?;
+            for id in self.series.iter().map(|e| *e.value()) {
+                if counts.get(id)?.is_none() {
+                    let count = features.range((id, i128::MIN, 0)..=(id, i128::MAX, u64::MAX))?.count() as u64;
+                    counts.insert(id, count)?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn add_series(&self, (instrument, feature_id): (Instrument, FeatureId), id: u64) {
+        if self.series.insert((instrument.clone(), feature_id.clone()), id).is_none() {
+            self.ids.entry(instrument).or_default().push(feature_id);
+        }
+    }
+
+    /// Latest value of the series at the time in nanoseconds
+    fn latest(&self, instrument: &Instrument, feature_id: &FeatureId, end: i128) -> Option<FeatureEvent> {
+        let (time, value) = self.values(instrument, feature_id, i128::MIN, end, Some(1)).ok()?.pop()?;
+        let time = OffsetDateTime::from_unix_timestamp_nanos(time).ok()?;
+        Some(FeatureEvent::new(feature_id.clone(), instrument.clone(), time, value))
+    }
+
+    /// Id of the series, assigned in the transaction when it is new
+    fn series_id(
+        &self,
+        tx: &WriteTransaction,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+    ) -> Result<u64, StateError> {
+        let key = serde_json::to_string(&(instrument, feature_id)).expect("Failed to serialize the series");
+        let mut series = tx.open_table(SERIES)?;
+        if let Some(id) = series.get(key.as_str())? {
+            return Ok(id.value());
+        }
+        let id = series.len()?;
+        series.insert(key.as_str(), id)?;
+        Ok(id)
+    }
+
+    /// Writes the values in one transaction, the values past the ttl are deleted every so many values
+    fn insert(&self, events: &[FeatureEvent]) -> Result<(), StateError> {
+        let mut tx = self.db.begin_write()?;
+        tx.set_durability(Durability::Eventual);
+        let mut new_series = Vec::new();
+        {
+            let mut features = tx.open_table(FEATURES)?;
+            let mut counts = tx.open_table(COUNTS)?;
+            for event in events {
+                let series = (event.instrument.clone(), event.id.clone());
+                let id = match self.series.get(&series).map(|id| *id) {
+                    Some(id) => id,
+                    None => {
+                        let id = self.series_id(&tx, &event.instrument, &event.id)?;
+                        new_series.push((series, id));
+                        id
+                    }
+                };
+                let time = event.event_time.unix_timestamp_nanos();
+                let index = features
+                    .range((id, time, 0)..=(id, time, u64::MAX))?
+                    .next_back()
+                    .transpose()?
+                    .map(|(k, _)| k.value().2 + 1)
+                    .unwrap_or(0);
+                features.insert((id, time, index), event.value)?;
+                let count = counts.get(id)?.map(|c| c.value()).unwrap_or(0) + 1;
+                counts.insert(id, count)?;
+            }
+        }
+        let written = self.written.fetch_add(events.len(), Ordering::Relaxed) % PRUNE_EVERY + events.len();
+        if written >= PRUNE_EVERY {
+            self.prune(&tx)?;
+        }
+        tx.commit()?;
+        for (series, id) in new_series {
+            self.add_series(series, id);
+        }
+        Ok(())
+    }
+
+    /// Deletes the values older than the ttl before the newest value of each series
+    fn prune(&self, tx: &WriteTransaction) -> Result<(), StateError> {
+        let Some(ttl) = self.ttl else {
+            return Ok(());
+        };
+        let mut features = tx.open_table(FEATURES)?;
+        let mut counts = tx.open_table(COUNTS)?;
+        for id in self.series.iter().map(|e| *e.value()) {
+            let Some(newest) = features
+                .range((id, i128::MIN, 0)..=(id, i128::MAX, u64::MAX))?
+                .next_back()
+                .transpose()?
+                .map(|(k, _)| k.value().1)
+            else {
+                continue;
+            };
+            let mut deleted = 0;
+            features.retain_in((id, i128::MIN, 0)..(id, newest - ttl, 0), |_, _| {
+                deleted += 1;
+                false
+            })?;
+            if deleted > 0 {
+                let count = counts.get(id)?.map(|c| c.value()).unwrap_or(0);
+                counts.insert(id, count.saturating_sub(deleted))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Values of the series between the times in nanoseconds, both included, only the last ones when limited
+    fn values(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        start: i128,
+        end: i128,
+        last: Option<usize>,
+    ) -> Result<Vec<(i128, f64)>, StateError> {
+        let Some(id) = self.series.get(&(instrument.clone(), feature_id.clone())).map(|id| *id) else {
+            return Ok(Vec::new());
+        };
+        let tx = self.db.begin_read()?;
+        let features = tx.open_table(FEATURES)?;
+        let range = features.range((id, start, 0)..=(id, end, u64::MAX))?;
+        let entries: Box<dyn Iterator<Item = _>> = match last {
+            Some(n) => Box::new(range.rev().take(n)),
+            None => Box::new(range),
+        };
+        let mut values = entries
+            .map(|e| e.map(|(k, v)| (k.value().1, v.value())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if last.is_some() {
+            values.reverse();
+        }
+        Ok(values)
+    }
+
+    /// The kept count and one value from each end of every series
+    fn series_stats(&self) -> Result<Vec<(Instrument, FeatureId, SeriesStats)>, StateError> {
+        let tx = self.db.begin_read()?;
+        let features = tx.open_table(FEATURES)?;
+        let counts = tx.open_table(COUNTS)?;
+        let mut stats = Vec::with_capacity(self.series.len());
+        for entry in self.series.iter() {
+            let ((instrument, feature_id), id) = (entry.key(), *entry.value());
+            let mut range = features.range((id, i128::MIN, 0)..=(id, i128::MAX, u64::MAX))?;
+            let oldest = entry_time(range.next())?;
+            // A series of one value has nothing left at the back
+            let newest = entry_time(range.next_back())?.or(oldest);
+            let series = SeriesStats {
+                entries: counts.get(id)?.map(|c| c.value() as usize).unwrap_or(0),
+                oldest,
+                newest,
+            };
+            stats.push((instrument.clone(), feature_id.clone(), series));
+        }
+        Ok(stats)
+    }
+
+    fn read(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        start: i128,
+        end: i128,
+        last: Option<usize>,
+    ) -> Vec<f64> {
+        match self.values(instrument, feature_id, start, end, last) {
+            Ok(values) => values.into_iter().map(|(_, v)| v).collect(),
+            Err(e) => {
+                error!("Failed to read {} of {} from the feature store: {}", feature_id, instrument, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl FeatureStore for RedbFeatureStore {
+    fn add_feature(&self, event: FeatureEvent) {
+        if let Err(e) = self.insert(std::slice::from_ref(&event)) {
+            error!("Failed to write {} to the feature store: {}", event.id, e);
+        }
+    }
+
+    fn add_features(&self, events: Vec<FeatureEvent>) {
+        if let Err(e) = self.insert(&events) {
+            error!("Failed to write {} features to the feature store: {}", events.len(), e);
+        }
+    }
+
+    fn latest_features(&self, timestamp: &OffsetDateTime) -> Vec<FeatureEvent> {
+        let end = timestamp.unix_timestamp_nanos();
+        self.series
+            .iter()
+            .filter_map(|entry| {
+                let (instrument, id) = entry.key();
+                self.latest(instrument, id, end)
+            })
+            .collect()
+    }
+
+    fn latest_features_of(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<FeatureEvent> {
+        let Some(ids) = self.ids.get(instrument).map(|ids| ids.clone()) else {
+            return Vec::new();
+        };
+        let end = timestamp.unix_timestamp_nanos();
+        ids.iter().filter_map(|id| self.latest(instrument, id, end)).collect()
+    }
+
+    fn stats(&self) -> Vec<(Instrument, FeatureId, SeriesStats)> {
+        match self.series_stats() {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("Failed to read the stats of the feature store: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn in_memory(&self) -> bool {
+        false
+    }
+
+    fn last_entry(&self, instrument: &Instrument, feature_id: &FeatureId, timestamp: &OffsetDateTime) -> Vec<f64> {
+        self.read(instrument, feature_id, i128::MIN, timestamp.unix_timestamp_nanos(), Some(1))
+    }
+
+    fn list_entries_window(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        timestamp: &OffsetDateTime,
+        window: &Duration,
+    ) -> Vec<f64> {
+        let start = (*timestamp - *window).unix_timestamp_nanos();
+        self.read(instrument, feature_id, start, timestamp.unix_timestamp_nanos(), None)
+    }
+
+    fn list_entries_periods(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        timestamp: &OffsetDateTime,
+        periods: &usize,
+    ) -> Vec<f64> {
+        self.read(
+            instrument,
+            feature_id,
+            i128::MIN,
+            timestamp.unix_timestamp_nanos(),
+            Some(*periods),
+        )
+    }
+
+    fn list_entries_since(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        since: &OffsetDateTime,
+        timestamp: &OffsetDateTime,
+    ) -> Vec<(OffsetDateTime, f64)> {
+        let (start, end) = (since.unix_timestamp_nanos() + 1, timestamp.unix_timestamp_nanos());
+        if start > end {
+            return Vec::new();
+        }
+        match self.values(instrument, feature_id, start, end, None) {
+            Ok(values) => values
+                .into_iter()
+                .filter_map(|(t, v)| Some((OffsetDateTime::from_unix_timestamp_nanos(t).ok()?, v)))
+                .collect(),
+            Err(e) => {
+                error!("Failed to read {} of {} from the feature store: {}", feature_id, instrument, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_redb_feature_store() {
+        let path = std::env::temp_dir().join(format!("aurelion_features_{}.redb", std::process::id()));
+        let config = RedbFeatureStoreConfig {
+            path: path.to_string_lossy().into_owned(),
+            ttl: Some(60),
+            compact_on_start: true,
+        };
+        let instrument = test_utils::test_perp_instrument();
+        let id = FeatureId::from("vwap");
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+
+        {
+            let store = RedbFeatureStore::open(&config).unwrap();
+            let events = [(0, 1.), (30, 2.), (30, 3.)]
+                .into_iter()
+                .map(|(seconds, value)| FeatureEvent::new(id.clone(), instrument.clone(), at(seconds), value))
+                .collect();
+            store.add_features(events);
+            store.add_feature(FeatureEvent::new(id.clone(), instrument.clone(), at(90), 4.));
+            // Values past the ttl are only deleted every so many writes
+            assert_eq!(store.stats()[0].2.entries, 4);
+        }
+
+        // The series survives the restart, the value older than the ttl is deleted on open
+        let store = RedbFeatureStore::open(&config).unwrap();
+        assert_eq!(store.list_entries_periods(&instrument, &id, &at(90), &10), [2., 3., 4.]);
+        assert_eq!(store.last_entry(&instrument, &id, &at(60)), [3.]);
+        assert_eq!(
+            store.list_entries_window(&instrument, &id, &at(90), &Duration::from_secs(60)),
+            [2., 3., 4.]
+        );
+        assert!(store.last_entry(&instrument, &FeatureId::from("spread"), &at(90)).is_empty());
+        assert_eq!(store.list_entries_since(&instrument, &id, &at(30), &at(90)), [(at(90), 4.)]);
+        let latest = store.latest_features(&at(45));
+        assert!(latest.len() == 1 && latest[0].value == 3. && latest[0].event_time == at(30));
+        assert_eq!(store.latest_features_of(&instrument, &at(45))[0].value, 3.);
+        assert!(store
+            .latest_features_of(&test_utils::test_multi_perp_instrument()[1], &at(45))
+            .is_empty());
+        let stats = store.stats()[0].2;
+        assert!(stats.entries == 3 && stats.oldest == Some(at(30)) && stats.newest == Some(at(90)));
+        assert!(!store.in_memory());
+
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    collections::{BTreeMap, HashMap},
-    time::Duration,
-};
+use std::{collections::HashMap, time::Duration};
 
 use dashmap::DashMap;
 use time::OffsetDateTime;
@@ -13,57 +10,177 @@ use crate::{
     utils::CompositeIndex,
 };
 
-#[derive(Default)]
-pub struct FeatureState {
-    features: DashMap<(Instrument, FeatureId), BTreeMap<CompositeIndex, f64>>,
-}
+use super::{Retention, SeriesStats, TimeSeries};
+
+/// Storage of the feature series, the state reads every feature request through it
+pub trait FeatureStore: Send + Sync {
+    /// Stores that bound themselves otherwise ignore the retention of the state
+    fn set_retention(&mut self, _retention: Retention) {}
 
-impl FeatureState {
-    pub fn add_feature(&self, event: FeatureEvent) {
-        let key = (event.instrument, event.id);
-        let mut composit_key = CompositeIndex::new(&event.event_time);
+    fn add_feature(&self, event: FeatureEvent);
 
-        let mut entry = self.features.entry(key).or_default();
-        while entry.get(&composit_key).is_some() {
-            composit_key.increment();
+    /// Adds the values of a pipeline run, stores that write in transactions write them in one
+    fn add_features(&self, events: Vec<FeatureEvent>) {
+        for event in events {
+            self.add_feature(event);
         }
-        entry.insert(composit_key, event.value);
     }
 
-    pub fn read_features(
+    /// Latest value of every feature series at the timestamp
+    fn latest_features(&self, timestamp: &OffsetDateTime) -> Vec<FeatureEvent>;
+
+    /// Latest value of the feature series of one instrument at the timestamp, without touching the others
+    fn latest_features_of(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<FeatureEvent>;
+
+    /// Number and time span of the stored values per instrument and feature
+    fn stats(&self) -> Vec<(Instrument, FeatureId, SeriesStats)>;
+
+    /// Whether the values are kept in memory, otherwise they don't count to the memory of the state
+    fn in_memory(&self) -> bool {
+        true
+    }
+
+    fn last_entry(&self, instrument: &Instrument, feature_id: &FeatureId, timestamp: &OffsetDateTime) -> Vec<f64>;
+
+    fn list_entries_window(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        timestamp: &OffsetDateTime,
+        window: &Duration,
+    ) -> Vec<f64>;
+
+    fn list_entries_periods(
         &self,
         instrument: &Instrument,
+        feature_id: &FeatureId,
         timestamp: &OffsetDateTime,
+        periods: &usize,
+    ) -> Vec<f64>;
+
+    /// Values with their times after the first timestamp up to and including the second
+    fn list_entries_since(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        since: &OffsetDateTime,
+        timestamp: &OffsetDateTime,
+    ) -> Vec<(OffsetDateTime, f64)>;
+
+    /// Reads the requests at the timestamp. With the time of the previous read of the instrument the update
+    /// requests only read what came after it, otherwise they read everything their features start from.
+    fn read_features(
+        &self,
+        instrument: &Instrument,
+        timestamp: &OffsetDateTime,
+        previous: Option<&OffsetDateTime>,
         request: &[FeatureDataRequest],
     ) -> FeatureDataResponse {
-        FeatureDataResponse::new(
-            request
-                .iter()
-                .map(|r| {
-                    let data = match &r {
-                        FeatureDataRequest::Latest { feature_id } => self.last_entry(instrument, feature_id, timestamp),
-                        FeatureDataRequest::Window { feature_id, window } => {
-                            self.list_entries_window(instrument, feature_id, timestamp, window)
-                        }
-                        FeatureDataRequest::Period {
-                            feature_id,
-                            periods,
-                        } => self.list_entries_periods(instrument, feature_id, timestamp, periods),
-                    };
-                    (r.feature_id().clone(), data)
-                })
-                .collect(),
-        )
+        let mut data = HashMap::new();
+        let mut times = HashMap::new();
+        for r in request {
+            let values = match &r {
+                FeatureDataRequest::Latest { feature_id } => self.last_entry(instrument, feature_id, timestamp),
+                FeatureDataRequest::Window { feature_id, window } => {
+                    self.list_entries_window(instrument, feature_id, timestamp, window)
+                }
+                FeatureDataRequest::Period {
+                    feature_id,
+                    periods,
+                } => self.list_entries_periods(instrument, feature_id, timestamp, periods),
+                FeatureDataRequest::WindowUpdates { feature_id, window } => {
+                    // One nanosecond before the window as the start of the window is included
+                    let start = *timestamp - *window - time::Duration::NANOSECOND;
+                    let since = previous.map_or(start, |p| (*p).max(start));
+                    let (t, values) = self
+                        .list_entries_since(instrument, feature_id, &since, timestamp)
+                        .into_iter()
+                        .unzip();
+                    times.insert(feature_id.clone(), t);
+                    values
+                }
+                FeatureDataRequest::PeriodUpdates {
+                    feature_id,
+                    periods,
+                } => match previous {
+                    Some(previous) => {
+                        let updates = self.list_entries_since(instrument, feature_id, previous, timestamp);
+                        let skip = updates.len().saturating_sub(*periods);
+                        updates.into_iter().skip(skip).map(|(_, v)| v).collect()
+                    }
+                    None => self.list_entries_periods(instrument, feature_id, timestamp, periods),
+                },
+            };
+            data.insert(r.feature_id().clone(), values);
+        }
+        FeatureDataResponse::new(instrument.clone(), *timestamp, data).with_updates(previous.is_some(), times)
     }
+}
 
-    fn last_entry(&self, instrument: &Instrument, feature_id: &FeatureId, timestamp: &OffsetDateTime) -> Vec<f64> {
-        let index = CompositeIndex::new_max(timestamp);
+/// Feature series in memory
+#[derive(Default)]
+pub struct FeatureState {
+    features: DashMap<(Instrument, FeatureId), TimeSeries<f64>>,
+    /// Features with a series per instrument
+    ids: DashMap<Instrument, Vec<FeatureId>>,
+    retention: Retention,
+}
 
-        if let Some(tree) = self.features.get(&(instrument.to_owned(), feature_id.to_owned())) {
-            tree.value().range(..=index).rev().take(1).map(|(_, v)| *v).collect()
-        } else {
-            Vec::new()
-        }
+impl FeatureStore for FeatureState {
+    fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    fn add_feature(&self, event: FeatureEvent) {
+        let mut series = self
+            .features
+            .entry((event.instrument.clone(), event.id.clone()))
+            .or_insert_with(|| {
+                self.ids.entry(event.instrument).or_default().push(event.id);
+                TimeSeries::default()
+            });
+        series.insert(CompositeIndex::new(&event.event_time), event.value);
+        series.prune(&self.retention);
+    }
+
+    fn latest_features(&self, timestamp: &OffsetDateTime) -> Vec<FeatureEvent> {
+        self.features
+            .iter()
+            .filter_map(|entry| {
+                let (instrument, id) = entry.key();
+                entry
+                    .value()
+                    .latest(timestamp)
+                    .map(|(k, v)| FeatureEvent::new(id.clone(), instrument.clone(), *k.timestamp(), *v))
+            })
+            .collect()
+    }
+
+    fn latest_features_of(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<FeatureEvent> {
+        let Some(ids) = self.ids.get(instrument).map(|ids| ids.clone()) else {
+            return Vec::new();
+        };
+        ids.into_iter()
+            .filter_map(|id| {
+                let series = self.features.get(&(instrument.clone(), id.clone()))?;
+                let (k, v) = series.latest(timestamp)?;
+                Some(FeatureEvent::new(id, instrument.clone(), *k.timestamp(), *v))
+            })
+            .collect()
+    }
+
+    fn stats(&self) -> Vec<(Instrument, FeatureId, SeriesStats)> {
+        self.features
+            .iter()
+            .map(|entry| (entry.key().0.clone(), entry.key().1.clone(), entry.value().stats()))
+            .collect()
+    }
+
+    fn last_entry(&self, instrument: &Instrument, feature_id: &FeatureId, timestamp: &OffsetDateTime) -> Vec<f64> {
+        self.features
+            .get(&(instrument.to_owned(), feature_id.to_owned()))
+            .and_then(|series| series.latest(timestamp).map(|(_, v)| vec![*v]))
+            .unwrap_or_default()
     }
 
     fn list_entries_window(
@@ -73,14 +190,10 @@ impl FeatureState {
         timestamp: &OffsetDateTime,
         window: &Duration,
     ) -> Vec<f64> {
-        let index = CompositeIndex::new_max(timestamp);
-        let end_index = CompositeIndex::new(&(*timestamp - *window));
-
-        if let Some(tree) = self.features.get(&(instrument.to_owned(), feature_id.to_owned())) {
-            tree.value().range(end_index..=index).map(|(_, v)| *v).collect()
-        } else {
-            Vec::new()
-        }
+        self.features
+            .get(&(instrument.to_owned(), feature_id.to_owned()))
+            .map(|series| series.window(timestamp, window).map(|(_, v)| *v).collect())
+            .unwrap_or_default()
     }
 
     fn list_entries_periods(
@@ -90,12 +203,9 @@ impl FeatureState {
         timestamp: &OffsetDateTime,
         periods: &usize,
     ) -> Vec<f64> {
-        let index = CompositeIndex::new_max(timestamp);
-
-        if let Some(tree) = self.features.get(&(instrument.to_owned(), feature_id.to_owned())) {
-            let mut res = tree
-                .value()
-                .range(..=index)
+        if let Some(series) = self.features.get(&(instrument.to_owned(), feature_id.to_owned())) {
+            let mut res = series
+                .until(timestamp)
                 .rev()
                 .take(*periods)
                 .map(|(_, v)| *v)
@@ -106,6 +216,19 @@ impl FeatureState {
             Vec::new()
         }
     }
+
+    fn list_entries_since(
+        &self,
+        instrument: &Instrument,
+        feature_id: &FeatureId,
+        since: &OffsetDateTime,
+        timestamp: &OffsetDateTime,
+    ) -> Vec<(OffsetDateTime, f64)> {
+        self.features
+            .get(&(instrument.to_owned(), feature_id.to_owned()))
+            .map(|series| series.after(since, timestamp).map(|(k, v)| (*k.timestamp(), *v)).collect())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug)]
@@ -121,6 +244,16 @@ pub enum FeatureDataRequest {
         feature_id: FeatureId,
         periods: usize,
     },
+    /// The values of the window that came after the previous read, with their times
+    WindowUpdates {
+        feature_id: FeatureId,
+        window: Duration,
+    },
+    /// At most the last periods of the values that came after the previous read
+    PeriodUpdates {
+        feature_id: FeatureId,
+        periods: usize,
+    },
 }
 
 impl From<LatestInputConfig> for FeatureDataRequest {
@@ -150,23 +283,70 @@ impl From<PeriodInputConfig> for FeatureDataRequest {
 }
 
 impl FeatureDataRequest {
+    /// Reads only what came after the previous read when resumed
+    pub fn is_update(&self) -> bool {
+        matches!(
+            self,
+            FeatureDataRequest::WindowUpdates { .. } | FeatureDataRequest::PeriodUpdates { .. }
+        )
+    }
+
     pub fn feature_id(&self) -> &FeatureId {
         match self {
             FeatureDataRequest::Latest { feature_id } => feature_id,
             FeatureDataRequest::Window { feature_id, .. } => feature_id,
             FeatureDataRequest::Period { feature_id, .. } => feature_id,
+            FeatureDataRequest::WindowUpdates { feature_id, .. } => feature_id,
+            FeatureDataRequest::PeriodUpdates { feature_id, .. } => feature_id,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct FeatureDataResponse {
+    instrument: Instrument,
+    timestamp: OffsetDateTime,
+    resumed: bool,
     data: HashMap<FeatureId, Vec<f64>>,
+    times: HashMap<FeatureId, Vec<OffsetDateTime>>,
 }
 
 impl FeatureDataResponse {
-    pub fn new(data: HashMap<FeatureId, Vec<f64>>) -> Self {
-        FeatureDataResponse { data }
+    pub fn new(instrument: Instrument, timestamp: OffsetDateTime, data: HashMap<FeatureId, Vec<f64>>) -> Self {
+        FeatureDataResponse {
+            instrument,
+            timestamp,
+            resumed: false,
+            data,
+            times: HashMap::new(),
+        }
+    }
+
+    /// Whether the update requests read from the previous read on, and the times of the window updates
+    pub fn with_updates(mut self, resumed: bool, times: HashMap<FeatureId, Vec<OffsetDateTime>>) -> Self {
+        self.resumed = resumed;
+        self.times = times;
+        self
+    }
+
+    pub fn instrument(&self) -> &Instrument {
+        &self.instrument
+    }
+
+    /// Time the data was read for
+    pub fn timestamp(&self) -> &OffsetDateTime {
+        &self.timestamp
+    }
+
+    /// False when the update requests read everything their features start from
+    pub fn resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// Values of a window update with their times
+    pub fn timed(&self, feature_id: &FeatureId) -> Vec<(OffsetDateTime, f64)> {
+        let times = self.times.get(feature_id).map(Vec::as_slice).unwrap_or_default();
+        times.iter().copied().zip(self.get(feature_id)).collect()
     }
 
     // Convenience method to get the last value for a feature ID
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+use crate::{
+    models::{Instrument, Side, Trade},
+    utils::CompositeIndex,
+};
+
+use super::{Retention, TimeSeries};
+
+/// Running totals of the traded volume by aggressor side
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TradeFlow {
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+}
+
+impl TradeFlow {
+    /// Volume bought by takers minus volume sold by them
+    pub fn delta(&self) -> Decimal {
+        self.buy_volume - self.sell_volume
+    }
+
+    /// Delta relative to the total volume between -1 and 1, none without volume
+    pub fn imbalance(&self) -> Option<Decimal> {
+        let total = self.buy_volume + self.sell_volume;
+        (!total.is_zero()).then(|| self.delta() / total)
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        let quantity = trade.quantity.value().abs();
+        match trade.aggressor() {
+            Side::Buy => self.buy_volume += quantity,
+            Side::Sell => self.sell_volume += quantity,
+        }
+    }
+
+    fn since(&self, earlier: &TradeFlow) -> TradeFlow {
+        TradeFlow {
+            buy_volume: self.buy_volume - earlier.buy_volume,
+            sell_volume: self.sell_volume - earlier.sell_volume,
+        }
+    }
+}
+
+/// Flow of a single trade and the cumulative flow up to and including it
+#[derive(Clone, Copy, Default)]
+struct FlowEntry {
+    trade: TradeFlow,
+    total: TradeFlow,
+}
+
+/// Cumulative flow per instrument after every trade, so the flow over any window is the difference of two
+/// lookups instead of a pass over the trades
+#[derive(Default)]
+pub struct FlowState {
+    totals: DashMap<Instrument, TimeSeries<FlowEntry>>,
+    retention: Retention,
+}
+
+impl FlowState {
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    /// Add the trade with its sequence number on the bus, which orders it among the trades at the same time
+    pub fn add_trade(&self, seq: u64, trade: &Trade) {
+        let mut totals = self.totals.entry(trade.instrument.clone()).or_default();
+        // Counted from the time it was known, so the flow as of a time only has the trades received by then
+        let index = CompositeIndex::with_index(&trade.event_time.max(trade.received_time), seq);
+        let mut entry = FlowEntry::default();
+        entry.trade.add(trade);
+        let index = totals.insert(index, entry);
+        let previous = totals.range(..&index).next_back().map(|(_, e)| e.total).unwrap_or_default();
+        // A trade from another feed may come in behind later ones and moves their totals as well
+        for (i, later) in totals.range_mut(&index..) {
+            if *i == index {
+                later.total = previous;
+            }
+            later.total.add(trade);
+        }
+        totals.prune(&self.retention);
+    }
+
+    /// Flow of the kept trades up to and including the timestamp
+    pub fn cumulative(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> TradeFlow {
+        let Some(totals) = self.totals.get(instrument) else {
+            return TradeFlow::default();
+        };
+        let end = totals.latest(timestamp).map(|(_, e)| e.total).unwrap_or_else(|| base(&totals));
+        end.since(&base(&totals))
+    }
+
+    /// Flow of the trades in the window ending at the timestamp, both ends included
+    pub fn window(&self, instrument: &Instrument, timestamp: &OffsetDateTime, window: &Duration) -> TradeFlow {
+        let Some(totals) = self.totals.get(instrument) else {
+            return TradeFlow::default();
+        };
+        let end = totals.latest(timestamp).map(|(_, e)| e.total).unwrap_or_else(|| base(&totals));
+        let start = totals
+            .before(&(*timestamp - *window))
+            .next_back()
+            .map(|(_, e)| e.total)
+            .unwrap_or_else(|| base(&totals));
+        end.since(&start)
+    }
+}
+
+/// Cumulative flow before the first kept trade, the totals still count the pruned trades
+fn base(totals: &TimeSeries<FlowEntry>) -> TradeFlow {
+    totals
+        .first()
+        .map(|(_, first)| first.total.since(&first.trade))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::RetentionConfig, ingestors::IngestorID, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_flow_after_pruning() {
+        let mut flow = FlowState::default();
+        flow.set_retention(Retention::from_config(&RetentionConfig {
+            max_age: None,
+            max_count: Some(2),
+        }));
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+        for (i, quantity) in [5., 1., -2.].into_iter().enumerate() {
+            let time = at(i as i64);
+            flow.add_trade(
+                i as u64,
+                &Trade::new(
+                    time,
+                    time,
+                    instrument.clone(),
+                    i as u64,
+                    100.0.into(),
+                    quantity.into(),
+                    IngestorID::Test,
+                ),
+            );
+        }
+
+        // The first trade fell out of the retention and no longer counts
+        assert_eq!(flow.cumulative(&instrument, &at(2)).delta(), Decimal::from(-1));
+        assert_eq!(flow.cumulative(&instrument, &at(0)), TradeFlow::default());
+        let window = flow.window(&instrument, &at(2), &Duration::from_secs(60));
+        assert_eq!((window.buy_volume, window.sell_volume), (Decimal::from(1), Decimal::from(2)));
+        assert_eq!(window.imbalance(), Some(Decimal::from(-1) / Decimal::from(3)));
+    }
+}
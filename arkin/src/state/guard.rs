@@ -0,0 +1,152 @@
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use time::OffsetDateTime;
+use tracing::warn;
+
+use super::StateError;
+use crate::{clock::Clock, config::LookaheadMode, models::Event};
+
+thread_local! {
+    static EXEMPT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run reads that may look ahead, e.g. a simulated exchange applying latency to an order
+pub fn without_lookahead_guard<R>(f: impl FnOnce() -> R) -> R {
+    let previous = EXEMPT.with(|e| e.replace(true));
+    let result = f();
+    EXEMPT.with(|e| e.set(previous));
+    result
+}
+
+/// Checks reads from the state against the clock so nothing from the future leaks into a replay
+pub struct LookaheadGuard {
+    clock: Arc<dyn Clock>,
+    mode: LookaheadMode,
+    enabled: AtomicBool,
+    violations: AtomicU64,
+}
+
+impl LookaheadGuard {
+    pub fn new(clock: Arc<dyn Clock>, mode: LookaheadMode) -> Self {
+        LookaheadGuard {
+            clock,
+            mode,
+            enabled: AtomicBool::new(false),
+            violations: AtomicU64::new(0),
+        }
+    }
+
+    /// Only enforce while replaying, reporting afterwards may look at the whole period
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Release);
+    }
+
+    fn is_active(&self) -> bool {
+        self.mode != LookaheadMode::Off && self.enabled.load(Ordering::Acquire) && !EXEMPT.with(|e| e.get())
+    }
+
+    pub fn violations(&self) -> u64 {
+        self.violations.load(Ordering::Relaxed)
+    }
+
+    /// Query timestamps may not be ahead of the clock
+    pub fn check_query(&self, timestamp: &OffsetDateTime) {
+        if !self.is_active() {
+            return;
+        }
+        let now = self.clock.now();
+        if *timestamp > now {
+            self.violation(StateError::LookaheadQuery {
+                query: *timestamp,
+                now,
+            });
+        }
+    }
+
+    /// Returns false for events that were not known yet at the current time
+    pub fn is_visible(&self, event: &Event) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let now = self.clock.now();
+        let known_time = event.known_time();
+        if *known_time > now {
+            self.violation(StateError::LookaheadEvent {
+                event_type: event.event_type(),
+                event_time: *event.event_time(),
+                received_time: *known_time,
+                now,
+            });
+            return false;
+        }
+        true
+    }
+
+    fn violation(&self, error: StateError) {
+        self.violations.fetch_add(1, Ordering::Relaxed);
+        match self.mode {
+            LookaheadMode::Strict => panic!("{}", error),
+            _ => warn!("{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::SimulatedClock,
+        ingestors::IngestorID,
+        models::{Quantity, Trade},
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    #[test]
+    fn test_lookahead_guard() {
+        let now = datetime!(2024-01-01 00:00:10).assume_utc();
+        let clock = Arc::new(SimulatedClock::new(now));
+        let guard = LookaheadGuard::new(clock, LookaheadMode::Warn);
+
+        let late = Event::Trade(Trade::new(
+            now + time::Duration::seconds(1),
+            now,
+            test_utils::test_perp_instrument(),
+            0,
+            100.0.into(),
+            Quantity::from(1.),
+            IngestorID::Test,
+        ));
+
+        // Nothing is checked before the guard is enabled
+        assert!(guard.is_visible(&late));
+
+        guard.enable();
+        assert!(!guard.is_visible(&late));
+        guard.check_query(&(now + time::Duration::seconds(1)));
+        guard.check_query(&now);
+        assert_eq!(guard.violations(), 2);
+
+        assert!(without_lookahead_guard(|| guard.is_visible(&late)));
+        assert_eq!(guard.violations(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Lookahead bias")]
+    fn test_lookahead_guard_strict() {
+        let now = datetime!(2024-01-01 00:00:10).assume_utc();
+        let guard = LookaheadGuard::new(Arc::new(SimulatedClock::new(now)), LookaheadMode::Strict);
+        guard.enable();
+        guard.check_query(&(now + time::Duration::seconds(1)));
+    }
+}
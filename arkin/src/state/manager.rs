@@ -1,30 +1,252 @@
 use std::{
     collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
+use dashmap::DashMap;
+use parking_lot::RwLock;
+
+use rust_decimal::Decimal;
 use time::OffsetDateTime;
 
 use crate::{
-    features::FeatureEvent,
-    models::{Event, EventType, EventTypeOf, Instrument},
+    features::{FeatureEvent, FeatureId},
+    metrics::METRICS,
+    models::{AccountUpdate, Event, EventType, EventTypeOf, Instrument, Price, Tick, Trade},
+    utils::CompositeIndex,
+};
+
+use super::{
+    EventState, FeatureDataRequest, FeatureDataResponse, FeatureState, FeatureStore, FlowState, LookaheadGuard,
+    Retention, SeriesStats, TradeFlow,
 };
 
-use super::{EventState, FeatureDataRequest, FeatureDataResponse, FeatureState};
+const EVENT_BYTES: usize = size_of::<(CompositeIndex, Event)>();
+const FEATURE_BYTES: usize = size_of::<(CompositeIndex, f64)>();
+
+/// Number of stored entries and their time span per series
+pub struct StateStats {
+    pub events: Vec<(Instrument, EventType, SeriesStats)>,
+    pub features: Vec<(Instrument, FeatureId, SeriesStats)>,
+    /// Whether the features are in memory, features on disk are left out of the bytes
+    pub features_in_memory: bool,
+}
+
+impl StateStats {
+    pub fn entries(&self) -> usize {
+        self.events.iter().map(|(_, _, s)| s.entries).sum::<usize>()
+            + self.features.iter().map(|(_, _, s)| s.entries).sum::<usize>()
+    }
+
+    /// Rough size of the entries in memory, without the tree nodes and what the events point to on the heap
+    pub fn approx_bytes(&self) -> usize {
+        self.bytes_by_instrument().values().sum()
+    }
+
+    pub fn bytes_by_instrument(&self) -> HashMap<Instrument, usize> {
+        let mut bytes = HashMap::<Instrument, usize>::new();
+        for (instrument, _, stats) in &self.events {
+            *bytes.entry(instrument.clone()).or_default() += stats.entries * EVENT_BYTES;
+        }
+        for (instrument, _, stats) in self.features.iter().filter(|_| self.features_in_memory) {
+            *bytes.entry(instrument.clone()).or_default() += stats.entries * FEATURE_BYTES;
+        }
+        bytes
+    }
+
+    /// Sets the state gauges, the age of a series is the time since its newest entry so a dead feed stands out
+    pub fn export_metrics(&self, now: OffsetDateTime) {
+        let events = self.events.iter().map(|(i, t, s)| (i, "event", t.to_string(), s));
+        let features = self.features.iter().map(|(i, f, s)| (i, "feature", f.clone(), s));
+        for (instrument, kind, series, stats) in events.chain(features) {
+            let instrument = instrument.to_string();
+            let labels = [instrument.as_str(), kind, series.as_str()];
+            METRICS
+                .state_series_entries
+                .with_label_values(&labels)
+                .set(stats.entries as i64);
+            if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+                METRICS
+                    .state_series_span
+                    .with_label_values(&labels)
+                    .set((newest - oldest).as_seconds_f64());
+                METRICS
+                    .state_series_age
+                    .with_label_values(&labels)
+                    .set((now - newest).as_seconds_f64());
+            }
+        }
+        for (instrument, bytes) in self.bytes_by_instrument() {
+            METRICS
+                .state_bytes
+                .with_label_values(&[&instrument.to_string()])
+                .set(bytes as i64);
+        }
+    }
+}
 
+/// Writers of an instrument share the lock, a snapshot of it takes the lock alone so none of its series changes
+/// while it is read. Writes to other instruments go on.
 #[derive(Default)]
+struct InstrumentWrites {
+    lock: RwLock<()>,
+    generation: AtomicU64,
+}
+
+/// Latest quote, recent trades and current features of an instrument, read while no write to it was in between
+pub struct InstrumentSnapshot {
+    pub instrument: Instrument,
+    pub as_of: OffsetDateTime,
+    /// Writes to the instrument before the snapshot was taken
+    pub generation: u64,
+    pub tick: Option<Tick>,
+    /// Trades in the snapshot window up to the as of time, oldest first
+    pub trades: Vec<Trade>,
+    pub features: Vec<FeatureEvent>,
+}
+
+/// Every read is as of its timestamp and only returns what was known by then, events later than it or received
+/// after it are left out. Live and in a replay the same read returns the same data.
 pub struct StateManager {
-    feature_state: FeatureState,
+    feature_state: Box<dyn FeatureStore>,
     event_state: EventState,
+    flow_state: FlowState,
+    guard: Option<Arc<LookaheadGuard>>,
+    writes: DashMap<Instrument, Arc<InstrumentWrites>>,
+    snapshot_window: Duration,
+}
+
+impl Default for StateManager {
+    fn default() -> Self {
+        StateManager {
+            feature_state: Box::new(FeatureState::default()),
+            event_state: EventState::default(),
+            flow_state: FlowState::default(),
+            guard: None,
+            writes: DashMap::new(),
+            snapshot_window: Duration::from_secs(600),
+        }
+    }
 }
 
 impl StateManager {
+    /// State that checks every read against the guard's clock
+    pub fn with_lookahead_guard(guard: Arc<LookaheadGuard>) -> Self {
+        StateManager {
+            event_state: EventState::with_guard(guard.clone()),
+            guard: Some(guard),
+            ..Default::default()
+        }
+    }
+
+    /// How far back the trades of a snapshot reach
+    pub fn with_snapshot_window(mut self, window: Duration) -> Self {
+        self.snapshot_window = window;
+        self
+    }
+
+    /// Bound the market data and features kept per series
+    pub fn with_retention(mut self, retention: Retention) -> Self {
+        self.event_state.set_retention(retention);
+        self.feature_state.set_retention(retention);
+        self.flow_state.set_retention(retention);
+        self
+    }
+
+    /// Keep the features in another store than the memory
+    pub fn with_feature_store(mut self, store: Box<dyn FeatureStore>) -> Self {
+        self.feature_state = store;
+        self
+    }
+
+    pub fn lookahead_guard(&self) -> Option<&Arc<LookaheadGuard>> {
+        self.guard.as_ref()
+    }
+
+    fn check_query(&self, timestamp: &OffsetDateTime) {
+        if let Some(guard) = &self.guard {
+            guard.check_query(timestamp);
+        }
+    }
+
+    fn writes_of(&self, instrument: &Instrument) -> Arc<InstrumentWrites> {
+        if let Some(writes) = self.writes.get(instrument) {
+            return writes.clone();
+        }
+        self.writes.entry(instrument.clone()).or_default().clone()
+    }
+
     pub fn add_event(&self, event: Event) {
-        self.event_state.add_event(event);
+        self.add_sequenced_event(0, event);
+    }
+
+    /// Add an event with its sequence number on the bus, which orders it among the events at the same time
+    pub fn add_sequenced_event(&self, seq: u64, event: Event) {
+        // Account updates belong to no instrument and are not part of a snapshot
+        let writes = event.instrument().map(|i| self.writes_of(i));
+        let _write = writes.as_ref().map(|w| w.lock.read());
+        if let Event::Trade(trade) = &event {
+            self.flow_state.add_trade(seq, trade);
+        }
+        self.event_state.add_sequenced_event(seq, event);
+        if let Some(writes) = &writes {
+            writes.generation.fetch_add(1, Ordering::Release);
+        }
     }
 
     pub fn add_feature(&self, event: FeatureEvent) {
+        let writes = self.writes_of(&event.instrument);
+        let _write = writes.lock.read();
         self.feature_state.add_feature(event);
+        writes.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Adds the features in one write to the store per instrument, only one instrument lock is held at a time
+    pub fn add_features(&self, events: Vec<FeatureEvent>) {
+        let mut by_instrument = HashMap::<Instrument, Vec<FeatureEvent>>::new();
+        for event in events {
+            by_instrument.entry(event.instrument.clone()).or_default().push(event);
+        }
+        for (instrument, events) in by_instrument {
+            let writes = self.writes_of(&instrument);
+            let _write = writes.lock.read();
+            let count = events.len() as u64;
+            self.feature_state.add_features(events);
+            writes.generation.fetch_add(count, Ordering::Release);
+        }
+    }
+
+    /// Quote, trades and features of the instrument as of the time, read in one go so a quote is never paired
+    /// with trades that came in while the snapshot was taken
+    pub fn snapshot_for(&self, instrument: &Instrument, as_of: &OffsetDateTime) -> InstrumentSnapshot {
+        self.check_query(as_of);
+        let writes = self.writes_of(instrument);
+        let _snapshot = writes.lock.write();
+        InstrumentSnapshot {
+            instrument: instrument.clone(),
+            as_of: *as_of,
+            generation: writes.generation.load(Ordering::Acquire),
+            tick: self.event_state.last_entry(instrument, as_of),
+            trades: self.event_state.list_entries_window(instrument, as_of, &self.snapshot_window),
+            features: self.feature_state.latest_features_of(instrument, as_of),
+        }
+    }
+
+    pub fn latest_features(&self, timestamp: &OffsetDateTime) -> Vec<FeatureEvent> {
+        self.check_query(timestamp);
+        self.feature_state.latest_features(timestamp)
+    }
+
+    pub fn stats(&self) -> StateStats {
+        StateStats {
+            events: self.event_state.stats(),
+            features: self.feature_state.stats(),
+            features_in_memory: self.feature_state.in_memory(),
+        }
     }
 
     pub fn read_features(
@@ -33,9 +255,28 @@ impl StateManager {
         timestamp: &OffsetDateTime,
         request: &[FeatureDataRequest],
     ) -> FeatureDataResponse {
-        self.feature_state.read_features(instrument, timestamp, request)
+        self.read_features_since(instrument, timestamp, None, request)
+    }
+
+    /// Reads the update requests from the previous read of the instrument on
+    pub fn read_features_since(
+        &self,
+        instrument: &Instrument,
+        timestamp: &OffsetDateTime,
+        previous: Option<&OffsetDateTime>,
+        request: &[FeatureDataRequest],
+    ) -> FeatureDataResponse {
+        self.check_query(timestamp);
+        self.feature_state.read_features(instrument, timestamp, previous, request)
     }
 
+    /// Account updates of every venue up to the timestamp, oldest first
+    pub fn account_updates(&self, timestamp: &OffsetDateTime) -> Vec<AccountUpdate> {
+        self.check_query(timestamp);
+        self.event_state.list_account_entries(timestamp)
+    }
+
+    /// Instruments with a series of the event type, regardless of when their events were known
     pub fn list_instruments(&self, event_type: &EventType) -> HashSet<Instrument> {
         self.event_state.list_instruments(event_type)
     }
@@ -44,6 +285,7 @@ impl StateManager {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         let event_type = T::event_type();
         let instruments = self.list_instruments(&event_type);
         instruments
@@ -55,10 +297,27 @@ impl StateManager {
             .collect()
     }
 
+    pub fn events_before<T>(&self, timestamp: &OffsetDateTime) -> HashMap<Instrument, Vec<T>>
+    where
+        T: TryFrom<Event, Error = ()> + EventTypeOf,
+    {
+        self.check_query(timestamp);
+        let event_type = T::event_type();
+        let instruments = self.list_instruments(&event_type);
+        instruments
+            .into_iter()
+            .map(|i| {
+                let event = self.event_state.list_entries_before(&i, timestamp);
+                (i, event)
+            })
+            .collect()
+    }
+
     pub fn events_by_instrument<T>(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Vec<T>
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         self.event_state.list_entries_since_start(instrument, timestamp)
     }
 
@@ -66,6 +325,7 @@ impl StateManager {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         let event_type = T::event_type();
         let instruments = self.list_instruments(&event_type);
         instruments
@@ -81,6 +341,7 @@ impl StateManager {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         self.event_state.last_entry(instrument, timestamp)
     }
 
@@ -88,6 +349,7 @@ impl StateManager {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         let event_type = T::event_type();
         let instruments = self.list_instruments(&event_type);
         instruments
@@ -108,6 +370,215 @@ impl StateManager {
     where
         T: TryFrom<Event, Error = ()> + EventTypeOf,
     {
+        self.check_query(timestamp);
         self.event_state.list_entries_window(instrument, timestamp, window)
     }
 }
+
+/// Reads of the market data as of a time, the range lookups are logarithmic in the length of the series
+impl StateManager {
+    /// The last n trades up to and including the timestamp, oldest first
+    pub fn last_n_trades(&self, instrument: &Instrument, timestamp: &OffsetDateTime, n: usize) -> Vec<Trade> {
+        self.check_query(timestamp);
+        self.event_state.last_n_entries(instrument, timestamp, n)
+    }
+
+    pub fn trades_in_window(
+        &self,
+        instrument: &Instrument,
+        timestamp: &OffsetDateTime,
+        window: &Duration,
+    ) -> Vec<Trade> {
+        self.events_window_by_instrument(instrument, timestamp, window)
+    }
+
+    /// The latest quote as of the timestamp
+    pub fn quote_at(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Option<Tick> {
+        self.latest_event_by_instrument(instrument, timestamp)
+    }
+
+    pub fn mid_price_at(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Option<Price> {
+        self.quote_at(instrument, timestamp).map(|t| t.mid_price())
+    }
+
+    /// Volume weighted price of the trades in the window, none without volume
+    pub fn vwap_over(&self, instrument: &Instrument, timestamp: &OffsetDateTime, window: &Duration) -> Option<Price> {
+        let (notional, volume) = self.trades_in_window(instrument, timestamp, window).iter().fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(notional, volume), t| {
+                let quantity = t.quantity.value().abs();
+                (notional + t.price.value() * quantity, volume + quantity)
+            },
+        );
+        (!volume.is_zero()).then(|| (notional / volume).into())
+    }
+
+    /// Cumulative volume delta of the kept trades up to and including the timestamp
+    pub fn cvd(&self, instrument: &Instrument, timestamp: &OffsetDateTime) -> Decimal {
+        self.check_query(timestamp);
+        self.flow_state.cumulative(instrument, timestamp).delta()
+    }
+
+    /// Buy and sell volume of the takers in the window, kept up to date on every trade
+    pub fn trade_flow(&self, instrument: &Instrument, timestamp: &OffsetDateTime, window: &Duration) -> TradeFlow {
+        self.check_query(timestamp);
+        self.flow_state.window(instrument, timestamp, window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingestors::IngestorID, test_utils};
+    use time::macros::datetime;
+
+    #[test]
+    fn test_market_reads() {
+        let state = StateManager::default();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+        for (i, (price, quantity)) in [(100., 1.), (101., 1.), (102., -2.), (104., 1.)].into_iter().enumerate() {
+            state.add_event(Event::Trade(Trade::new(
+                at(i as i64),
+                at(i as i64),
+                instrument.clone(),
+                i as u64,
+                price.into(),
+                quantity.into(),
+                IngestorID::Test,
+            )));
+        }
+        state.add_event(Event::Tick(Tick::new(
+            at(2),
+            instrument.clone(),
+            1,
+            99.0.into(),
+            1.0.into(),
+            101.0.into(),
+            1.0.into(),
+        )));
+
+        let last = state.last_n_trades(&instrument, &at(2), 2);
+        assert_eq!(last.iter().map(|t| t.trade_id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(state.last_n_trades(&instrument, &at(10), 10).len(), 4);
+
+        // The window reaches back to and includes its start
+        let window = Duration::from_secs(2);
+        assert_eq!(state.trades_in_window(&instrument, &at(3), &window).len(), 3);
+        // (101 + 102 * 2 + 104) / 4
+        assert_eq!(state.vwap_over(&instrument, &at(3), &window), Some(Price::from(102.25)));
+        assert!(state.vwap_over(&instrument, &at(60), &window).is_none());
+
+        assert!(state.quote_at(&instrument, &at(1)).is_none());
+        assert_eq!(state.mid_price_at(&instrument, &at(5)), Some(Price::from(100.)));
+
+        assert_eq!(state.cvd(&instrument, &at(3)), Decimal::from(1));
+        assert_eq!(state.cvd(&instrument, &at(1)), Decimal::from(2));
+        let flow = state.trade_flow(&instrument, &at(3), &window);
+        assert_eq!((flow.buy_volume, flow.sell_volume), (Decimal::from(2), Decimal::from(2)));
+        assert_eq!(flow.imbalance(), Some(Decimal::ZERO));
+        assert!(state.trade_flow(&instrument, &at(60), &window).imbalance().is_none());
+
+        // A late trade is only read once it was received
+        state.add_event(Event::Trade(Trade::new(
+            at(4),
+            at(0),
+            instrument.clone(),
+            4,
+            100.0.into(),
+            (-3.0).into(),
+            IngestorID::Test,
+        )));
+        assert_eq!(state.last_n_trades(&instrument, &at(3), 10).len(), 4);
+        assert_eq!(state.last_n_trades(&instrument, &at(4), 10)[1].trade_id, 4);
+        assert_eq!(state.cvd(&instrument, &at(3)), Decimal::from(1));
+        assert_eq!(state.cvd(&instrument, &at(4)), Decimal::from(-2));
+        assert_eq!(state.trade_flow(&instrument, &at(4), &window).delta(), Decimal::from(-4));
+    }
+
+    #[test]
+    fn test_state_metrics() {
+        let state = StateManager::default();
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        for seconds in [0, 30] {
+            let time = start + time::Duration::seconds(seconds);
+            state.add_event(Event::Trade(Trade::new(
+                time,
+                time,
+                instrument.clone(),
+                0,
+                100.0.into(),
+                1.0.into(),
+                IngestorID::Test,
+            )));
+        }
+
+        let stats = state.stats();
+        let (_, _, trades) = &stats.events[0];
+        assert!(trades.entries == 2 && trades.newest == Some(start + time::Duration::seconds(30)));
+        assert_eq!(stats.bytes_by_instrument()[&instrument], stats.approx_bytes());
+        let on_disk = StateStats {
+            events: Vec::new(),
+            features: vec![(
+                instrument.clone(),
+                "vwap".into(),
+                SeriesStats {
+                    entries: 10,
+                    ..Default::default()
+                },
+            )],
+            features_in_memory: false,
+        };
+        assert_eq!(on_disk.approx_bytes(), 0);
+
+        stats.export_metrics(start + time::Duration::seconds(90));
+        let labels = [instrument.to_string(), "event".into(), EventType::Trade.to_string()];
+        let labels = labels.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+        assert_eq!(METRICS.state_series_age.with_label_values(&labels).get(), 60.);
+        assert_eq!(METRICS.state_series_span.with_label_values(&labels).get(), 30.);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let state = StateManager::default().with_snapshot_window(Duration::from_secs(10));
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+        for seconds in [0, 20, 25] {
+            state.add_event(Event::Trade(Trade::new(
+                at(seconds),
+                at(seconds),
+                instrument.clone(),
+                seconds as u64,
+                100.0.into(),
+                1.0.into(),
+                IngestorID::Test,
+            )));
+        }
+        state.add_event(Event::Tick(Tick::new(
+            at(22),
+            instrument.clone(),
+            1,
+            99.0.into(),
+            1.0.into(),
+            101.0.into(),
+            1.0.into(),
+        )));
+        state.add_feature(FeatureEvent::new("spread".into(), instrument.clone(), at(21), 2.));
+        state.add_feature(FeatureEvent::new(
+            "spread".into(),
+            test_utils::test_multi_perp_instrument()[1].clone(),
+            at(21),
+            3.,
+        ));
+
+        let snapshot = state.snapshot_for(&instrument, &at(24));
+        // The feature of the other instrument is not a write to this one
+        assert_eq!(snapshot.generation, 5);
+        assert_eq!(snapshot.trades.iter().map(|t| t.trade_id).collect::<Vec<_>>(), [20]);
+        assert!(snapshot.tick.is_some_and(|t| t.event_time == at(22)));
+        assert!(snapshot.features.len() == 1 && snapshot.features[0].value == 2.);
+    }
+}
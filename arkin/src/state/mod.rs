@@ -1,9 +1,28 @@
+mod bars;
+mod errors;
 mod events;
+mod feature_store;
 mod features;
+mod flow;
+mod guard;
 mod manager;
+mod recorder;
+mod retention;
+mod series;
+mod warm_up;
 
 use events::EventState;
 use features::FeatureState;
+use flow::FlowState;
 
-pub use features::{FeatureDataRequest, FeatureDataResponse};
-pub use manager::StateManager;
+pub use bars::{BarAggregator, BarService};
+pub use errors::StateError;
+pub use feature_store::RedbFeatureStore;
+pub use features::{FeatureDataRequest, FeatureDataResponse, FeatureStore};
+pub use flow::TradeFlow;
+pub use guard::{without_lookahead_guard, LookaheadGuard};
+pub use manager::{InstrumentSnapshot, StateManager, StateStats};
+pub use recorder::StateRecorder;
+pub use retention::Retention;
+pub use series::{SeriesStats, TimeSeries};
+pub use warm_up::warm_up;
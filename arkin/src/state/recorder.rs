@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use rust_decimal::prelude::*;
+use tokio::select;
+use tracing::info;
+
+use crate::{
+    bus::{EventBus, Subscription},
+    constants::{
+        BOOK_IMBALANCE_ID, BOOK_LEVELS, BOOK_MICROPRICE_ID, BOOK_WEIGHTED_MID_ID, FUNDING_RATE_ID, INDEX_PRICE_ID,
+        MARK_PRICE_ID, SPOT_PRICE_ID, TRADE_PRICE_ID, TRADE_QUANTITY_ID,
+    },
+    features::FeatureEvent,
+    models::{
+        AccountUpdate, Allocation, Book, BookUpdate, Candle, DataGap, Event, Fill, FundingPayment, FundingRate,
+        Instrument, InstrumentType, Liquidation, Order, OrderUpdate, Signal, Tick, Trade,
+    },
+    shutdown::ShutdownSignal,
+};
+
+use super::StateManager;
+
+/// Records what is published on the bus into the state so it can be queried later
+pub struct StateRecorder {
+    state: Arc<StateManager>,
+    ticks: Subscription<Tick>,
+    trades: Subscription<Trade>,
+    books: Subscription<Book>,
+    book_updates: Subscription<BookUpdate>,
+    funding_rates: Subscription<FundingRate>,
+    liquidations: Subscription<Liquidation>,
+    candles: Subscription<Candle>,
+    data_gaps: Subscription<DataGap>,
+    signals: Subscription<Signal>,
+    allocations: Subscription<Allocation>,
+    orders: Subscription<Order>,
+    fills: Subscription<Fill>,
+    order_updates: Subscription<OrderUpdate>,
+    account_updates: Subscription<AccountUpdate>,
+    funding_payments: Subscription<FundingPayment>,
+}
+
+impl StateRecorder {
+    pub fn new(state: Arc<StateManager>, bus: &EventBus) -> Self {
+        StateRecorder {
+            state,
+            ticks: bus.subscribe(),
+            trades: bus.subscribe(),
+            books: bus.subscribe(),
+            book_updates: bus.subscribe(),
+            funding_rates: bus.subscribe(),
+            liquidations: bus.subscribe(),
+            candles: bus.subscribe(),
+            data_gaps: bus.subscribe(),
+            signals: bus.subscribe(),
+            allocations: bus.subscribe(),
+            orders: bus.subscribe(),
+            fills: bus.subscribe(),
+            order_updates: bus.subscribe(),
+            account_updates: bus.subscribe(),
+            funding_payments: bus.subscribe(),
+        }
+    }
+
+    pub(super) fn state(&self) -> &Arc<StateManager> {
+        &self.state
+    }
+
+    pub fn record(&self, event: Event) {
+        self.record_sequenced(0, event);
+    }
+
+    /// Record with the sequence number the bus gave the event
+    pub fn record_sequenced(&self, seq: u64, event: Event) {
+        // Trades are the base input of the feature pipeline
+        if let Event::Trade(trade) = &event {
+            self.state.add_feature(FeatureEvent::new(
+                TRADE_PRICE_ID.to_owned(),
+                trade.instrument.clone(),
+                trade.event_time,
+                trade.price.value().to_f64().unwrap_or(f64::NAN),
+            ));
+            self.state.add_feature(FeatureEvent::new(
+                TRADE_QUANTITY_ID.to_owned(),
+                trade.instrument.clone(),
+                trade.event_time,
+                trade.quantity.value().to_f64().unwrap_or(f64::NAN),
+            ));
+            // The perpetual of the pair reads the spot price for its basis
+            if trade.instrument.instrument_type() == &InstrumentType::Spot {
+                let perpetual = Instrument::perpetual(
+                    trade.instrument.venue().clone(),
+                    trade.instrument.base().clone(),
+                    trade.instrument.quote().clone(),
+                );
+                self.state.add_feature(FeatureEvent::new(
+                    SPOT_PRICE_ID.to_owned(),
+                    perpetual,
+                    trade.event_time,
+                    trade.price.value().to_f64().unwrap_or(f64::NAN),
+                ));
+            }
+        }
+        if let Event::FundingRate(funding) = &event {
+            let features = [
+                (&*MARK_PRICE_ID, funding.mark_price.value()),
+                (&*INDEX_PRICE_ID, funding.index_price.value()),
+                (&*FUNDING_RATE_ID, funding.funding_rate),
+            ];
+            for (id, value) in features {
+                self.state.add_feature(FeatureEvent::new(
+                    id.to_owned(),
+                    funding.instrument.clone(),
+                    funding.event_time,
+                    value.to_f64().unwrap_or(f64::NAN),
+                ));
+            }
+        }
+        // So are the book snapshots, a side without levels leaves the features at their last value
+        if let Event::Book(book) = &event {
+            let features = [
+                (&*BOOK_IMBALANCE_ID, book.imbalance(BOOK_LEVELS)),
+                (&*BOOK_MICROPRICE_ID, book.microprice().map(|p| p.value())),
+                (&*BOOK_WEIGHTED_MID_ID, book.weighted_mid(BOOK_LEVELS).map(|p| p.value())),
+            ];
+            for (id, value) in features {
+                if let Some(value) = value.and_then(|v| v.to_f64()) {
+                    self.state.add_feature(FeatureEvent::new(
+                        id.to_owned(),
+                        book.instrument.clone(),
+                        book.event_time,
+                        value,
+                    ));
+                }
+            }
+        }
+        self.state.add_sequenced_event(seq, event);
+    }
+
+    /// Record everything that is waiting, returns the number of recorded events
+    pub fn drain(&mut self) -> usize {
+        let mut events = Vec::new();
+        events.extend(std::iter::from_fn(|| self.ticks.try_recv_sequenced()).map(|s| (s.seq, Event::Tick(s.message))));
+        events
+            .extend(std::iter::from_fn(|| self.trades.try_recv_sequenced()).map(|s| (s.seq, Event::Trade(s.message))));
+        events.extend(std::iter::from_fn(|| self.books.try_recv_sequenced()).map(|s| (s.seq, Event::Book(s.message))));
+        events.extend(
+            std::iter::from_fn(|| self.book_updates.try_recv_sequenced())
+                .map(|s| (s.seq, Event::BookUpdate(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.funding_rates.try_recv_sequenced())
+                .map(|s| (s.seq, Event::FundingRate(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.liquidations.try_recv_sequenced())
+                .map(|s| (s.seq, Event::Liquidation(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.candles.try_recv_sequenced()).map(|s| (s.seq, Event::Candle(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.data_gaps.try_recv_sequenced()).map(|s| (s.seq, Event::DataGap(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.signals.try_recv_sequenced()).map(|s| (s.seq, Event::Signal(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.allocations.try_recv_sequenced()).map(|s| (s.seq, Event::Allocation(s.message))),
+        );
+        events
+            .extend(std::iter::from_fn(|| self.orders.try_recv_sequenced()).map(|s| (s.seq, Event::Order(s.message))));
+        events.extend(std::iter::from_fn(|| self.fills.try_recv_sequenced()).map(|s| (s.seq, Event::Fill(s.message))));
+        events.extend(
+            std::iter::from_fn(|| self.order_updates.try_recv_sequenced())
+                .map(|s| (s.seq, Event::OrderUpdate(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.account_updates.try_recv_sequenced())
+                .map(|s| (s.seq, Event::AccountUpdate(s.message))),
+        );
+        events.extend(
+            std::iter::from_fn(|| self.funding_payments.try_recv_sequenced())
+                .map(|s| (s.seq, Event::FundingPayment(s.message))),
+        );
+
+        // Every type has its own channel, the sequence numbers restore the order they were published in
+        events.sort_by_key(|(seq, _)| *seq);
+        let recorded = events.len();
+        for (seq, event) in events {
+            self.record_sequenced(seq, event);
+        }
+        recorded
+    }
+
+    /// Record until shutdown, then whatever is still queued on the bus is recorded before returning
+    pub async fn run(mut self, mut shutdown: ShutdownSignal) {
+        info!("Starting state recorder...");
+        loop {
+            let (seq, event) = select! {
+                _ = shutdown.wait() => {
+                    let recorded = self.drain();
+                    info!("State recorder flushed {} events", recorded);
+                    break;
+                }
+                Some(e) = self.ticks.recv_sequenced() => (e.seq, Event::Tick(e.message)),
+                Some(e) = self.trades.recv_sequenced() => (e.seq, Event::Trade(e.message)),
+                Some(e) = self.books.recv_sequenced() => (e.seq, Event::Book(e.message)),
+                Some(e) = self.book_updates.recv_sequenced() => (e.seq, Event::BookUpdate(e.message)),
+                Some(e) = self.funding_rates.recv_sequenced() => (e.seq, Event::FundingRate(e.message)),
+                Some(e) = self.liquidations.recv_sequenced() => (e.seq, Event::Liquidation(e.message)),
+                Some(e) = self.candles.recv_sequenced() => (e.seq, Event::Candle(e.message)),
+                Some(e) = self.data_gaps.recv_sequenced() => (e.seq, Event::DataGap(e.message)),
+                Some(e) = self.signals.recv_sequenced() => (e.seq, Event::Signal(e.message)),
+                Some(e) = self.allocations.recv_sequenced() => (e.seq, Event::Allocation(e.message)),
+                Some(e) = self.orders.recv_sequenced() => (e.seq, Event::Order(e.message)),
+                Some(e) = self.fills.recv_sequenced() => (e.seq, Event::Fill(e.message)),
+                Some(e) = self.order_updates.recv_sequenced() => (e.seq, Event::OrderUpdate(e.message)),
+                Some(e) = self.account_updates.recv_sequenced() => (e.seq, Event::AccountUpdate(e.message)),
+                Some(e) = self.funding_payments.recv_sequenced() => (e.seq, Event::FundingPayment(e.message)),
+                else => break,
+            };
+            self.record_sequenced(seq, event);
+        }
+        info!("State recorder stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        ingestors::IngestorID,
+        models::{BookUpdateSide, Liquidation, Venue},
+        state::FeatureDataRequest,
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    #[test]
+    fn test_record_liquidations() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let state = Arc::new(StateManager::default());
+        let mut recorder = StateRecorder::new(state.clone(), &bus);
+
+        let instrument = test_utils::test_perp_instrument();
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        for (i, quantity) in [-0.5, 2.0].into_iter().enumerate() {
+            bus.publish(Liquidation {
+                event_time: time + time::Duration::seconds(i as i64),
+                instrument: instrument.clone(),
+                price: 100.0.into(),
+                avg_price: 100.0.into(),
+                quantity: quantity.into(),
+                quantity_filled: quantity.into(),
+                source: IngestorID::Test,
+            });
+        }
+        assert_eq!(recorder.drain(), 2);
+
+        let window = std::time::Duration::from_secs(60);
+        let end = time + time::Duration::seconds(10);
+        let liquidations = state.events_window_by_instrument::<Liquidation>(&instrument, &end, &window);
+        assert_eq!(liquidations.len(), 2);
+        let latest = state.latest_event_by_instrument::<Liquidation>(&instrument, &end).unwrap();
+        assert_eq!(latest.notional(), Decimal::from(200));
+    }
+
+    #[test]
+    fn test_record_account_updates() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let state = Arc::new(StateManager::default());
+        let mut recorder = StateRecorder::new(state.clone(), &bus);
+
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        bus.publish(AccountUpdate {
+            event_time: time,
+            venue: Venue::Binance,
+            reason: "FUNDING_FEE".into(),
+            balances: vec![],
+            positions: vec![],
+        });
+        assert_eq!(recorder.drain(), 1);
+
+        // Account updates belong to no instrument and are kept by themselves
+        assert!(state.account_updates(&(time - time::Duration::seconds(1))).is_empty());
+        let updates = state.account_updates(&time);
+        assert!(updates.len() == 1 && updates[0].reason == "FUNDING_FEE");
+    }
+
+    #[test]
+    fn test_record_book_features() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let state = Arc::new(StateManager::default());
+        let recorder = StateRecorder::new(state.clone(), &bus);
+
+        let instrument = test_utils::test_perp_instrument();
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        let levels = |levels: &[(f64, f64)]| {
+            levels
+                .iter()
+                .map(|(price, quantity)| BookUpdateSide::new((*price).into(), (*quantity).into()))
+                .collect()
+        };
+        // Out of order levels and an empty one are fine
+        recorder.record(Event::Book(Book::new(
+            time,
+            time,
+            instrument.clone(),
+            levels(&[(98., 1.), (99., 3.), (99.5, 0.)]),
+            levels(&[(101., 1.)]),
+            IngestorID::Test,
+        )));
+
+        let features = state.read_features(
+            &instrument,
+            &time,
+            &[
+                BOOK_IMBALANCE_ID.clone(),
+                BOOK_MICROPRICE_ID.clone(),
+                BOOK_WEIGHTED_MID_ID.clone(),
+            ]
+            .map(|feature_id| FeatureDataRequest::Latest { feature_id }),
+        );
+        assert_eq!(features.last(&BOOK_IMBALANCE_ID), Some(0.6));
+        // (99 * 1 + 101 * 3) / 4
+        assert_eq!(features.last(&BOOK_MICROPRICE_ID), Some(100.5));
+        // (98.75 + 101) / 2
+        assert_eq!(features.last(&BOOK_WEIGHTED_MID_ID), Some(99.875));
+    }
+
+    #[test]
+    fn test_record_funding_features() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let state = Arc::new(StateManager::default());
+        let recorder = StateRecorder::new(state.clone(), &bus);
+
+        let perpetual = test_utils::test_perp_instrument();
+        let spot = Instrument::spot(Venue::Binance, "BTC".into(), "USDT".into());
+        let time = datetime!(2024-01-01 00:00:00).assume_utc();
+        recorder.record(Event::FundingRate(FundingRate {
+            event_time: time,
+            instrument: perpetual.clone(),
+            mark_price: 100.5.into(),
+            index_price: 100.25.into(),
+            funding_rate: Decimal::new(1, 4),
+            next_funding_time: time + time::Duration::hours(8),
+            source: IngestorID::Test,
+        }));
+        recorder.record(Event::Trade(Trade::new(
+            time,
+            time,
+            spot,
+            1,
+            100.0.into(),
+            1.0.into(),
+            IngestorID::Test,
+        )));
+
+        let features = state.read_features(
+            &perpetual,
+            &time,
+            &[
+                MARK_PRICE_ID.clone(),
+                INDEX_PRICE_ID.clone(),
+                FUNDING_RATE_ID.clone(),
+                SPOT_PRICE_ID.clone(),
+            ]
+            .map(|feature_id| FeatureDataRequest::Latest { feature_id }),
+        );
+        assert_eq!(features.last(&MARK_PRICE_ID), Some(100.5));
+        assert_eq!(features.last(&INDEX_PRICE_ID), Some(100.25));
+        assert_eq!(features.last(&FUNDING_RATE_ID), Some(0.0001));
+        // The spot trade lands on the perpetual of the pair
+        assert_eq!(features.last(&SPOT_PRICE_ID), Some(100.));
+    }
+}
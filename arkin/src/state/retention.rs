@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use time::Duration;
+
+use crate::{config::RetentionConfig, utils::CompositeIndex};
+
+/// Bounds a series of the state by the age of its entries relative to the newest one and by their count.
+/// The age is measured in event time, so a backtest prunes exactly like the live engine.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retention {
+    max_age: Option<Duration>,
+    max_count: Option<usize>,
+}
+
+impl Retention {
+    pub fn from_config(config: &RetentionConfig) -> Self {
+        Retention {
+            max_age: config.max_age.map(|s| Duration::seconds(s as i64)),
+            max_count: config.max_count,
+        }
+    }
+
+    /// Drops the entries that fell out of the retention, returns how many
+    pub fn prune<V>(&self, series: &mut BTreeMap<CompositeIndex, V>) -> usize {
+        let before = series.len();
+        if let (Some(max_age), Some((newest, _))) = (self.max_age, series.last_key_value()) {
+            let cutoff = CompositeIndex::new(&(*newest.timestamp() - max_age));
+            if series.first_key_value().is_some_and(|(oldest, _)| *oldest < cutoff) {
+                *series = series.split_off(&cutoff);
+            }
+        }
+        if let Some(max_count) = self.max_count {
+            while series.len() > max_count {
+                series.pop_first();
+            }
+        }
+        before - series.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_retention() {
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let mut series = (0..10)
+            .map(|i| (CompositeIndex::new(&(start + Duration::seconds(i))), i))
+            .collect::<BTreeMap<_, _>>();
+
+        assert_eq!(Retention::default().prune(&mut series), 0);
+
+        let by_age = Retention::from_config(&RetentionConfig {
+            max_age: Some(5),
+            max_count: None,
+        });
+        // Entries exactly at the cutoff stay
+        assert_eq!(by_age.prune(&mut series), 4);
+        assert_eq!(series.first_key_value().map(|(_, v)| *v), Some(4));
+
+        let by_count = Retention::from_config(&RetentionConfig {
+            max_age: Some(5),
+            max_count: Some(2),
+        });
+        assert_eq!(by_count.prune(&mut series), 4);
+        assert!(series.values().eq([8, 9].iter()));
+    }
+}
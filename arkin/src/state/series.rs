@@ -0,0 +1,153 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    ops::{Bound, RangeBounds},
+    time::Duration,
+};
+
+use time::OffsetDateTime;
+
+use crate::utils::CompositeIndex;
+
+use super::Retention;
+
+/// Depth and time span of a series
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeriesStats {
+    pub entries: usize,
+    pub oldest: Option<OffsetDateTime>,
+    pub newest: Option<OffsetDateTime>,
+}
+
+/// Values of one series ordered by their time and the index among the values at the same time.
+/// The state keeps one per instrument and event type or feature behind the lock of its map shard.
+#[derive(Clone)]
+pub struct TimeSeries<V> {
+    entries: BTreeMap<CompositeIndex, V>,
+}
+
+impl<V> Default for TimeSeries<V> {
+    fn default() -> Self {
+        TimeSeries {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V> TimeSeries<V> {
+    /// Inserts the value behind the values already at the index, returns the index it got
+    pub fn insert(&mut self, mut index: CompositeIndex, value: V) -> CompositeIndex {
+        while self.entries.contains_key(&index) {
+            index.increment();
+        }
+        self.entries.insert(index.clone(), value);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn first(&self) -> Option<(&CompositeIndex, &V)> {
+        self.entries.first_key_value()
+    }
+
+    /// The latest value up to and including the timestamp
+    pub fn latest(&self, timestamp: &OffsetDateTime) -> Option<(&CompositeIndex, &V)> {
+        self.until(timestamp).next_back()
+    }
+
+    /// Values up to and including the timestamp, oldest first
+    pub fn until(&self, timestamp: &OffsetDateTime) -> btree_map::Range<'_, CompositeIndex, V> {
+        self.entries.range(..=CompositeIndex::new_max(timestamp))
+    }
+
+    /// Values strictly before the timestamp, oldest first
+    pub fn before(&self, timestamp: &OffsetDateTime) -> btree_map::Range<'_, CompositeIndex, V> {
+        self.entries.range(..CompositeIndex::new(timestamp))
+    }
+
+    /// Values in the window ending at the timestamp, both ends included
+    pub fn window(&self, timestamp: &OffsetDateTime, window: &Duration) -> btree_map::Range<'_, CompositeIndex, V> {
+        self.entries
+            .range(CompositeIndex::new(&(*timestamp - *window))..=CompositeIndex::new_max(timestamp))
+    }
+
+    /// Values after the first timestamp up to and including the second, oldest first
+    pub fn after(
+        &self,
+        since: &OffsetDateTime,
+        timestamp: &OffsetDateTime,
+    ) -> Box<dyn DoubleEndedIterator<Item = (&CompositeIndex, &V)> + '_> {
+        if since >= timestamp {
+            return Box::new(std::iter::empty());
+        }
+        Box::new(self.entries.range((
+            Bound::Excluded(CompositeIndex::new_max(since)),
+            Bound::Included(CompositeIndex::new_max(timestamp)),
+        )))
+    }
+
+    pub fn range(&self, range: impl RangeBounds<CompositeIndex>) -> btree_map::Range<'_, CompositeIndex, V> {
+        self.entries.range(range)
+    }
+
+    pub fn range_mut(&mut self, range: impl RangeBounds<CompositeIndex>) -> btree_map::RangeMut<'_, CompositeIndex, V> {
+        self.entries.range_mut(range)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, CompositeIndex, V> {
+        self.entries.iter()
+    }
+
+    pub fn stats(&self) -> SeriesStats {
+        SeriesStats {
+            entries: self.entries.len(),
+            oldest: self.entries.first_key_value().map(|(k, _)| *k.timestamp()),
+            newest: self.entries.last_key_value().map(|(k, _)| *k.timestamp()),
+        }
+    }
+
+    /// Drops the values that fell out of the retention, returns how many
+    pub fn prune(&mut self, retention: &Retention) -> usize {
+        retention.prune(&mut self.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetentionConfig;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_time_series() {
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + time::Duration::seconds(seconds);
+        let mut series = TimeSeries::default();
+        for (seconds, value) in [(0, 'a'), (1, 'b'), (1, 'c'), (3, 'd')] {
+            series.insert(CompositeIndex::new(&at(seconds)), value);
+        }
+
+        let values = |entries: btree_map::Range<'_, CompositeIndex, char>| entries.map(|(_, v)| *v).collect::<String>();
+        // Values at the same time keep the order they came in
+        assert_eq!(values(series.until(&at(1))), "abc");
+        assert_eq!(values(series.before(&at(1))), "a");
+        assert_eq!(values(series.window(&at(3), &Duration::from_secs(2))), "bcd");
+        assert_eq!(series.after(&at(0), &at(1)).map(|(_, v)| *v).collect::<String>(), "bc");
+        assert_eq!(series.after(&at(1), &at(1)).count(), 0);
+        assert_eq!(series.latest(&at(2)).map(|(_, v)| *v), Some('c'));
+        assert!(series.stats().oldest == Some(at(0)) && series.stats().newest == Some(at(3)));
+        assert!(series.latest(&(start - time::Duration::SECOND)).is_none());
+
+        let retention = Retention::from_config(&RetentionConfig {
+            max_age: None,
+            max_count: Some(2),
+        });
+        assert_eq!(series.prune(&retention), 2);
+        assert_eq!(series.iter().map(|(_, v)| *v).collect::<String>(), "cd");
+    }
+}
@@ -0,0 +1,171 @@
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+
+use crate::{
+    backtest::load_events,
+    db::DBManager,
+    models::{Event, EventType},
+    pipeline::Pipeline,
+};
+
+use super::StateRecorder;
+
+/// Records the stored market data between start and end into the state in event time order and runs the
+/// pipeline over it every `frequency`, so indicators over long windows and the features derived from them have
+/// their history from the first step. Returns the number of recorded events.
+pub async fn warm_up(
+    db: &DBManager,
+    recorder: &StateRecorder,
+    pipeline: &Pipeline,
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+    frequency: Duration,
+) -> usize {
+    let mut events = load_events(db, start, end).await;
+    events.extend(db.read_funding_rates(start, end).await.into_iter().map(Event::FundingRate));
+    events.extend(db.read_candles(start, end).await.into_iter().map(Event::Candle));
+    events.sort_by_key(|e| *e.event_time());
+
+    let recorded = replay(events, recorder, pipeline, start, end, frequency);
+    info!("Warmed up the state with {} events from {} to {}", recorded, start, end);
+    recorded
+}
+
+/// Records the sorted events and calculates the pipeline for the traded instruments at every step in between,
+/// like the trading loop would have
+fn replay(
+    events: Vec<Event>,
+    recorder: &StateRecorder,
+    pipeline: &Pipeline,
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+    frequency: Duration,
+) -> usize {
+    let recorded = events.len();
+    let mut events = events.into_iter().peekable();
+    let mut step = start + frequency;
+    while step <= end {
+        while let Some(event) = events.next_if(|e| *e.event_time() <= step) {
+            recorder.record(event);
+        }
+        for instrument in recorder.state().list_instruments(&EventType::Trade) {
+            pipeline.calculate(instrument, step);
+        }
+        step += frequency;
+    }
+    events.for_each(|event| recorder.record(event));
+    recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        bus::EventBus,
+        config::{
+            self, DiffFeatureConfig, DiffMethod, FeatureConfig, LatestInputConfig, PeriodInputConfig, PipelineConfig,
+            SMAFeatureConfig,
+        },
+        ingestors::IngestorID,
+        models::{Instrument, Trade},
+        state::StateManager,
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    fn trade(instrument: &Instrument, event_time: OffsetDateTime, price: f64) -> Event {
+        Event::Trade(Trade::new(
+            event_time,
+            event_time,
+            instrument.clone(),
+            1,
+            price.into(),
+            1.0.into(),
+            IngestorID::Test,
+        ))
+    }
+
+    #[test]
+    fn test_warm_up_pipeline() {
+        let bus = EventBus::from_config(&config::load().bus);
+        let state = Arc::new(StateManager::default());
+        let recorder = StateRecorder::new(state.clone(), &bus);
+        let config = PipelineConfig {
+            name: "test".into(),
+            frequency: 1,
+            bar_close: None,
+            threads: Some(1),
+            warm_up: Default::default(),
+            features: vec![
+                FeatureConfig::SMA(SMAFeatureConfig {
+                    id: "sma".into(),
+                    input: PeriodInputConfig {
+                        from: "base".into(),
+                        feature_id: "trade_price".into(),
+                        periods: 3,
+                    },
+                    output: "sma".into(),
+                    incremental: true,
+                }),
+                FeatureConfig::Diff(DiffFeatureConfig {
+                    id: "sma_change".into(),
+                    input: LatestInputConfig {
+                        from: "sma".into(),
+                        feature_id: "sma".into(),
+                    },
+                    periods: 1,
+                    method: DiffMethod::Absolute,
+                    output: "sma_change".into(),
+                }),
+            ],
+        };
+        let pipeline = Pipeline::from_config(state.clone(), &config).unwrap();
+
+        let instrument = test_utils::test_perp_instrument();
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let at = |seconds: i64| start + Duration::seconds(seconds);
+        let events = (0..10).map(|i| trade(&instrument, at(i), 100. + i as f64)).collect();
+        assert_eq!(replay(events, &recorder, &pipeline, start, at(10), Duration::seconds(1)), 10);
+        assert!(state.latest_features(&at(10)).iter().any(|f| f.id == "sma_change"));
+
+        // The first live step already has the derived features
+        recorder.record(trade(&instrument, at(11), 111.));
+        let mut ids = pipeline
+            .calculate(instrument, at(11))
+            .into_iter()
+            .map(|f| f.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["sma", "sma_change"]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_warm_up() {
+        let config = config::load();
+        let db = DBManager::from_config(&config.db).await;
+        let instrument = test_utils::test_perp_instrument();
+        let end = OffsetDateTime::now_utc();
+        let event_time = end - time::Duration::minutes(30);
+        let trade = Trade::new(
+            event_time,
+            event_time,
+            instrument.clone(),
+            1,
+            100.0.into(),
+            1.0.into(),
+            IngestorID::Test,
+        );
+        db.insert_trade(trade).await.unwrap();
+
+        let bus = EventBus::from_config(&config.bus);
+        let state = Arc::new(StateManager::default());
+        let recorder = StateRecorder::new(state.clone(), &bus);
+        let pipeline = Pipeline::from_config(state.clone(), &config.feature_pipeline).unwrap();
+        let start = end - time::Duration::hours(1);
+        assert!(warm_up(&db, &recorder, &pipeline, start, end, Duration::minutes(1)).await > 0);
+        assert!(state.latest_event_by_instrument::<Trade>(&instrument, &end).is_some());
+    }
+}
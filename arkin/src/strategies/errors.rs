@@ -1 +1,9 @@
+use thiserror::Error;
 
+use super::StrategyId;
+
+#[derive(Error, Debug)]
+pub enum StrategyError {
+    #[error("Unknown strategy: {0}")]
+    UnknownStrategy(StrategyId),
+}
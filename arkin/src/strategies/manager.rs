@@ -1,21 +1,64 @@
-use super::{factory::StrategyFactory, Strategy};
-use crate::{config::StrategyManagerConfig, features::FeatureEvent, models::Signal};
+use std::collections::HashSet;
+
+use parking_lot::RwLock;
 use rayon::prelude::*;
+use tracing::info;
+
+use super::{factory::StrategyFactory, Strategy, StrategyError, StrategyId};
+use crate::{config::StrategyManagerConfig, features::FeatureEvent, models::Signal};
 
 pub struct StrategyManager {
     strategies: Vec<Box<dyn Strategy>>,
+    stopped: RwLock<HashSet<StrategyId>>,
 }
 
 impl StrategyManager {
     pub fn from_config(config: &StrategyManagerConfig) -> Self {
         Self {
             strategies: StrategyFactory::from_config(&config.strategies),
+            stopped: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// All strategies and whether they are running
+    pub fn strategies(&self) -> Vec<(StrategyId, bool)> {
+        let stopped = self.stopped.read();
+        self.strategies
+            .iter()
+            .map(|s| (s.id().clone(), !stopped.contains(s.id())))
+            .collect()
+    }
+
+    pub fn start(&self, id: &StrategyId) -> Result<(), StrategyError> {
+        self.check_exists(id)?;
+        info!("Starting strategy {}", id);
+        self.stopped.write().remove(id);
+        Ok(())
+    }
+
+    /// Stopped strategies don't produce signals until they are started again
+    pub fn stop(&self, id: &StrategyId) -> Result<(), StrategyError> {
+        self.check_exists(id)?;
+        info!("Stopping strategy {}", id);
+        self.stopped.write().insert(id.clone());
+        Ok(())
+    }
+
+    fn check_exists(&self, id: &StrategyId) -> Result<(), StrategyError> {
+        if self.strategies.iter().any(|s| s.id() == id) {
+            Ok(())
+        } else {
+            Err(StrategyError::UnknownStrategy(id.clone()))
         }
     }
 
+    /// Strategies wait until the pipeline passes on all their sources, which it does once they warmed up
     pub fn calculate(&self, data: &[FeatureEvent]) -> Vec<Signal> {
+        let stopped = self.stopped.read();
         self.strategies
             .par_iter()
+            .filter(|s| !stopped.contains(s.id()))
+            .filter(|s| s.sources().iter().all(|id| data.iter().any(|d| &d.id == id)))
             .map(|s| s.calculate(data))
             .flat_map(|s| s)
             .collect::<Vec<_>>()
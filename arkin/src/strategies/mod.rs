@@ -6,6 +6,7 @@ mod errors;
 mod factory;
 mod manager;
 
+pub use errors::StrategyError;
 pub use manager::StrategyManager;
 
 use crate::{
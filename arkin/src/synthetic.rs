@@ -0,0 +1,282 @@
+use rand::{rngs::StdRng, Rng};
+use rust_decimal::prelude::*;
+use time::OffsetDateTime;
+
+use crate::{
+    config::{PriceProcessConfig, SyntheticConfig},
+    ingestors::IngestorID,
+    models::{Event, Instrument, Price, Quantity, Tick, Trade},
+    utils::seeded_rng,
+};
+
+/// Generates tick and trade streams for tests and for backtests without recorded data
+pub struct SyntheticMarket {
+    config: SyntheticConfig,
+    process: PriceProcess,
+    rng: StdRng,
+}
+
+impl SyntheticMarket {
+    pub fn from_config(config: &SyntheticConfig, seed: u64) -> Self {
+        SyntheticMarket {
+            config: config.to_owned(),
+            process: PriceProcess::from_config(&config.process),
+            rng: seeded_rng(seed, "synthetic"),
+        }
+    }
+
+    /// Generate events between start and end, sorted by event time
+    pub fn generate(&mut self, instrument: &Instrument, start: OffsetDateTime, end: OffsetDateTime) -> Vec<Event> {
+        let interval = time::Duration::milliseconds(self.config.interval as i64);
+        let dt = self.config.interval as f64 / 1000.;
+
+        let mut events = Vec::new();
+        let mut price = self.config.start_price;
+        let mut tick_id = 0;
+        let mut trade_id = 0;
+        let mut timestamp = start;
+
+        while timestamp < end {
+            let previous = price;
+            price = self.process.step(price, dt, &mut self.rng);
+
+            // Markets get busier and wider when they move, scaled to the expected move of the interval
+            let expected_move = self.process.volatility() * dt.sqrt();
+            let surprise = if expected_move > 0. {
+                ((price / previous).ln() / expected_move).clamp(-5., 5.)
+            } else {
+                0.
+            };
+            let activity = 1. + surprise.abs();
+
+            let spread = price * self.config.spread_bps / 10_000. * (0.5 + self.rng.gen::<f64>()) * activity.sqrt();
+            let bid = price - spread / 2.;
+            let ask = price + spread / 2.;
+
+            events.push(Event::Tick(Tick::new(
+                timestamp,
+                instrument.clone(),
+                tick_id,
+                round_price(bid),
+                self.quantity(self.config.avg_book_quantity),
+                round_price(ask),
+                self.quantity(self.config.avg_book_quantity),
+            )));
+            tick_id += 1;
+
+            let trades = poisson(self.config.trades_per_second * dt * activity, &mut self.rng);
+            let mut trade_times = (0..trades)
+                .map(|_| timestamp + interval * self.rng.gen::<f64>())
+                .collect::<Vec<_>>();
+            trade_times.sort();
+            for trade_time in trade_times {
+                // Buyers lift the offer more often when the price moves up
+                let buy_probability = (0.5 + surprise * 0.1).clamp(0.1, 0.9);
+                let trade_price = if self.rng.gen_bool(buy_probability) {
+                    ask
+                } else {
+                    bid
+                };
+                events.push(Event::Trade(Trade::new(
+                    trade_time,
+                    trade_time,
+                    instrument.clone(),
+                    trade_id,
+                    round_price(trade_price),
+                    self.quantity(self.config.avg_trade_quantity * activity),
+                    IngestorID::Synthetic,
+                )));
+                trade_id += 1;
+            }
+
+            timestamp += interval;
+        }
+
+        events.sort_by_key(|e| *e.event_time());
+        events
+    }
+
+    /// Exponentially distributed quantity, never zero
+    fn quantity(&mut self, mean: f64) -> Quantity {
+        let quantity = -mean * (1. - self.rng.gen::<f64>()).ln();
+        Quantity::from(Decimal::from_f64(quantity.max(0.001)).unwrap_or(Decimal::ONE).round_dp(3))
+    }
+}
+
+enum PriceProcess {
+    RandomWalk {
+        volatility: f64,
+    },
+    Trending {
+        drift: f64,
+        volatility: f64,
+    },
+    MeanReverting {
+        mean: f64,
+        speed: f64,
+        volatility: f64,
+    },
+    RegimeSwitching {
+        switch_probability: f64,
+        regimes: Vec<PriceProcess>,
+        current: usize,
+    },
+}
+
+impl PriceProcess {
+    fn from_config(config: &PriceProcessConfig) -> Self {
+        match config {
+            PriceProcessConfig::RandomWalk(c) => PriceProcess::RandomWalk {
+                volatility: c.volatility,
+            },
+            PriceProcessConfig::Trending(c) => PriceProcess::Trending {
+                drift: c.drift,
+                volatility: c.volatility,
+            },
+            PriceProcessConfig::MeanReverting(c) => PriceProcess::MeanReverting {
+                mean: c.mean,
+                speed: c.speed,
+                volatility: c.volatility,
+            },
+            PriceProcessConfig::RegimeSwitching(c) => PriceProcess::RegimeSwitching {
+                switch_probability: c.switch_probability,
+                regimes: c.regimes.iter().map(PriceProcess::from_config).collect(),
+                current: 0,
+            },
+        }
+    }
+
+    fn volatility(&self) -> f64 {
+        match self {
+            PriceProcess::RandomWalk { volatility }
+            | PriceProcess::Trending { volatility, .. }
+            | PriceProcess::MeanReverting { volatility, .. } => *volatility,
+            PriceProcess::RegimeSwitching {
+                regimes, current, ..
+            } => regimes[*current].volatility(),
+        }
+    }
+
+    /// Price after dt seconds
+    fn step(&mut self, price: f64, dt: f64, rng: &mut StdRng) -> f64 {
+        let shock = standard_normal(rng) * dt.sqrt();
+        match self {
+            PriceProcess::RandomWalk { volatility } => {
+                price * (*volatility * shock - 0.5 * volatility.powi(2) * dt).exp()
+            }
+            PriceProcess::Trending { drift, volatility } => price * (*drift * dt + *volatility * shock).exp(),
+            PriceProcess::MeanReverting {
+                mean,
+                speed,
+                volatility,
+            } => {
+                let log_price = price.ln() + *speed * dt * (mean.ln() - price.ln()) + *volatility * shock;
+                log_price.exp()
+            }
+            PriceProcess::RegimeSwitching {
+                switch_probability,
+                regimes,
+                current,
+            } => {
+                let switch = 1. - (1. - *switch_probability).powf(dt);
+                if regimes.len() > 1 && rng.gen_bool(switch.clamp(0., 1.)) {
+                    *current = (*current + rng.gen_range(1..regimes.len())) % regimes.len();
+                }
+                regimes[*current].step(price, dt, rng)
+            }
+        }
+    }
+}
+
+fn round_price(price: f64) -> Price {
+    Price::from(Decimal::from_f64(price).unwrap_or_default().round_dp(2))
+}
+
+/// Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1 = 1. - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
+}
+
+/// Knuth's algorithm, fine for the small rates per interval used here
+fn poisson(lambda: f64, rng: &mut StdRng) -> u64 {
+    let limit = (-lambda).exp();
+    let mut k = 0;
+    let mut p = rng.gen::<f64>();
+    while p > limit {
+        k += 1;
+        p *= rng.gen::<f64>();
+    }
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, MeanRevertingConfig, TrendingConfig},
+        test_utils,
+    };
+    use time::macros::datetime;
+
+    fn generate(config: &SyntheticConfig, minutes: i64) -> Vec<Event> {
+        let start = datetime!(2024-01-01 00:00:00).assume_utc();
+        let end = start + time::Duration::minutes(minutes);
+        SyntheticMarket::from_config(config, 42).generate(&test_utils::test_perp_instrument(), start, end)
+    }
+
+    fn last_trade_price(events: &[Event]) -> f64 {
+        events
+            .iter()
+            .rev()
+            .find_map(|e| match e {
+                Event::Trade(t) => t.price.value().to_f64(),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_synthetic_market() {
+        let config = config::load().backtest.synthetic;
+        let events = generate(&config, 10);
+
+        // One tick per interval and roughly the configured number of trades
+        let ticks = events.iter().filter(|e| matches!(e, Event::Tick(_))).count();
+        let trades = events.len() - ticks;
+        assert_eq!(ticks, 6000);
+        assert!(trades as f64 > config.trades_per_second * 600. * 0.8);
+
+        assert!(events.windows(2).all(|w| w[0].event_time() <= w[1].event_time()));
+        assert!(events.iter().all(|e| match e {
+            Event::Tick(t) => t.bid_price.value() <= t.ask_price.value(),
+            Event::Trade(t) => t.quantity.value() > Decimal::ZERO,
+            _ => false,
+        }));
+
+        // Same seed gives the same stream
+        let again = generate(&config, 10);
+        assert_eq!(last_trade_price(&events), last_trade_price(&again));
+    }
+
+    #[test]
+    fn test_synthetic_processes() {
+        let mut config = config::load().backtest.synthetic;
+
+        config.process = PriceProcessConfig::Trending(TrendingConfig {
+            drift: 0.0001,
+            volatility: 0.0001,
+        });
+        assert!(last_trade_price(&generate(&config, 30)) > config.start_price * 1.1);
+
+        config.start_price = 60000.;
+        config.process = PriceProcessConfig::MeanReverting(MeanRevertingConfig {
+            mean: 50000.,
+            speed: 0.05,
+            volatility: 0.0001,
+        });
+        let price = last_trade_price(&generate(&config, 30));
+        assert!((price - 50000.).abs() < 1000.);
+    }
+}
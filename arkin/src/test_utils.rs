@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
-use time::macros::datetime;
+use time::{macros::datetime, OffsetDateTime};
 
 use crate::{
-    models::{Allocation, Event, Fill, Instrument, Notional, Price, Quantity, Tick, Venue},
+    ingestors::IngestorID,
+    models::{Allocation, Event, Fill, Instrument, Notional, Price, Quantity, Tick, Trade, Venue},
     state::StateManager,
 };
 pub fn test_perp_instrument() -> Instrument {
@@ -27,51 +28,51 @@ pub struct TestStateBuilder {
 impl TestStateBuilder {
     pub fn add_fills(self, instrument: &Instrument) -> Self {
         // Create a couple of fills
-        self.state.add_event(Event::Fill(Fill {
-            event_time: datetime!(2024-01-01 00:00:00).assume_utc(),
-            instrument: instrument.clone(),
-            order_id: 0,
-            strategy_id: "test".into(),
-            price: Price::from(80.),
-            quantity: Quantity::from(10.),
-            commission: Notional::from(1.5),
-        }));
-        self.state.add_event(Event::Fill(Fill {
-            event_time: datetime!(2024-01-01 00:01:00).assume_utc(),
-            instrument: instrument.clone(),
-            order_id: 1,
-            strategy_id: "test".into(),
-            price: Price::from(120.),
-            quantity: Quantity::from(10.),
-            commission: Notional::from(1.0),
-        }));
-        self.state.add_event(Event::Fill(Fill {
-            event_time: datetime!(2024-01-01 00:02:00).assume_utc(),
-            instrument: instrument.clone(),
-            order_id: 2,
-            strategy_id: "test".into(),
-            price: Price::from(100.),
-            quantity: Quantity::from(-10.),
-            commission: Notional::from(1.5),
-        }));
-        self.state.add_event(Event::Fill(Fill {
-            event_time: datetime!(2024-01-01 00:03:00).assume_utc(),
-            instrument: instrument.clone(),
-            order_id: 3,
-            strategy_id: "test".into(),
-            price: Price::from(100.),
-            quantity: Quantity::from(-20.),
-            commission: Notional::from(2.),
-        }));
-        self.state.add_event(Event::Fill(Fill {
-            event_time: datetime!(2024-01-01 00:04:00).assume_utc(),
-            instrument: instrument.clone(),
-            order_id: 3,
-            strategy_id: "test".into(),
-            price: Price::from(50.),
-            quantity: Quantity::from(10.),
-            commission: Notional::from(2.),
-        }));
+        self.state.add_event(Event::Fill(Fill::new(
+            datetime!(2024-01-01 00:00:00).assume_utc(),
+            instrument.clone(),
+            0,
+            "test".into(),
+            Price::from(80.),
+            Quantity::from(10.),
+            Notional::from(1.5),
+        )));
+        self.state.add_event(Event::Fill(Fill::new(
+            datetime!(2024-01-01 00:01:00).assume_utc(),
+            instrument.clone(),
+            1,
+            "test".into(),
+            Price::from(120.),
+            Quantity::from(10.),
+            Notional::from(1.0),
+        )));
+        self.state.add_event(Event::Fill(Fill::new(
+            datetime!(2024-01-01 00:02:00).assume_utc(),
+            instrument.clone(),
+            2,
+            "test".into(),
+            Price::from(100.),
+            Quantity::from(-10.),
+            Notional::from(1.5),
+        )));
+        self.state.add_event(Event::Fill(Fill::new(
+            datetime!(2024-01-01 00:03:00).assume_utc(),
+            instrument.clone(),
+            3,
+            "test".into(),
+            Price::from(100.),
+            Quantity::from(-20.),
+            Notional::from(2.),
+        )));
+        self.state.add_event(Event::Fill(Fill::new(
+            datetime!(2024-01-01 00:04:00).assume_utc(),
+            instrument.clone(),
+            3,
+            "test".into(),
+            Price::from(50.),
+            Quantity::from(10.),
+            Notional::from(2.),
+        )));
 
         self
     }
@@ -134,3 +135,31 @@ pub fn allocations(instrument: &Instrument) -> Vec<Allocation> {
         Notional::from(1000.),
     )]
 }
+
+/// Oscillating price with growing volume, one trade and tick per second
+pub fn market_events(instrument: &Instrument, start: OffsetDateTime, seconds: i64) -> Vec<Event> {
+    let mut events = Vec::new();
+    for i in 0..seconds {
+        let event_time = start + time::Duration::seconds(i);
+        let price = 100. + (i as f64 / 10.).sin() * 5.;
+        events.push(Event::Trade(Trade::new(
+            event_time,
+            event_time,
+            instrument.clone(),
+            i as u64,
+            price.into(),
+            Quantity::from(1. + i as f64 / 10.),
+            IngestorID::Test,
+        )));
+        events.push(Event::Tick(Tick::new(
+            event_time,
+            instrument.clone(),
+            i as u64,
+            (price - 0.05).into(),
+            Quantity::from(1.),
+            (price + 0.05).into(),
+            Quantity::from(1.),
+        )));
+    }
+    events
+}
@@ -0,0 +1,243 @@
+use std::{io, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table},
+    Frame, Terminal,
+};
+use time::OffsetDateTime;
+
+use crate::{
+    models::{Instrument, Order, Position, Signal},
+    portfolio::PnlTracker,
+    server::Server,
+};
+
+const REFRESH: Duration = Duration::from_millis(500);
+const RECENT_SIGNALS: usize = 20;
+/// Feeds without data for longer than this are shown as stale
+const STALE_AFTER: time::Duration = time::Duration::seconds(30);
+
+/// Everything the dashboard shows, taken from the server once per refresh
+pub struct Snapshot {
+    pub now: OffsetDateTime,
+    pub positions: Vec<Position>,
+    pub pnl: Vec<(String, String)>,
+    pub open_orders: Vec<Order>,
+    pub feeds: Vec<(Instrument, OffsetDateTime)>,
+    pub signals: Vec<Signal>,
+}
+
+impl Snapshot {
+    pub fn take(server: &Server) -> Self {
+        let mut tracker = PnlTracker::default();
+        let fills = server.fills();
+        for fill in &fills {
+            tracker.update(fill);
+        }
+        let mut strategies = fills.iter().map(|f| f.strategy_id.clone()).collect::<Vec<_>>();
+        strategies.sort_by_cached_key(|s| s.to_string());
+        strategies.dedup();
+
+        Snapshot {
+            now: server.now(),
+            positions: server.positions(),
+            pnl: strategies
+                .into_iter()
+                .map(|s| (s.to_string(), tracker.realized(&s).to_string()))
+                .collect(),
+            open_orders: server.open_orders(),
+            feeds: server.feeds(),
+            signals: server.recent_signals(RECENT_SIGNALS),
+        }
+    }
+}
+
+/// Run the dashboard in the terminal until q or esc is pressed or the server shuts down
+pub async fn run(server: Arc<Server>) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let result = tokio::task::spawn_blocking(move || event_loop(&mut terminal, &server)).await?;
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, server: &Server) -> Result<()> {
+    while !server.is_shutting_down() {
+        let snapshot = Snapshot::take(server);
+        terminal.draw(|frame| draw(frame, &snapshot))?;
+
+        if event::poll(REFRESH)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn draw(frame: &mut Frame, snapshot: &Snapshot) {
+    let [top, middle, bottom] = Layout::vertical([
+        Constraint::Percentage(30),
+        Constraint::Percentage(35),
+        Constraint::Percentage(35),
+    ])
+    .areas(frame.area());
+    let [positions, pnl] = Layout::horizontal([Constraint::Percentage(75), Constraint::Percentage(25)]).areas(top);
+    let [orders, feeds] = Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(middle);
+
+    table(
+        frame,
+        positions,
+        "Positions",
+        ["Strategy", "Instrument", "Quantity", "Avg price", "Commission"],
+        snapshot.positions.iter().map(|p| {
+            vec![
+                p.strategy_id.to_string(),
+                p.instrument.to_string(),
+                p.quantity.to_string(),
+                p.avg_price.to_string(),
+                p.commission.to_string(),
+            ]
+        }),
+    );
+    table(
+        frame,
+        pnl,
+        "Realized PnL",
+        ["Strategy", "PnL"],
+        snapshot.pnl.iter().map(|(strategy, pnl)| vec![strategy.clone(), pnl.clone()]),
+    );
+    table(
+        frame,
+        orders,
+        "Open orders",
+        ["Id", "Strategy", "Instrument", "Type", "Filled", "Status"],
+        snapshot.open_orders.iter().map(|o| {
+            vec![
+                o.order_id.to_string(),
+                o.strategy_id.to_string(),
+                o.instrument.to_string(),
+                o.order_type.to_string(),
+                format!("{}/{}", o.quantity_filled, o.quantity),
+                o.status.to_string(),
+            ]
+        }),
+    );
+
+    let rows = snapshot.feeds.iter().map(|(instrument, last)| {
+        let age = snapshot.now - *last;
+        let status = if age > STALE_AFTER { "STALE" } else { "OK" };
+        let color = if age > STALE_AFTER {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        Row::new(vec![
+            Cell::from(instrument.to_string()),
+            Cell::from(format!("{}s", age.whole_seconds())),
+            Cell::from(status).style(Style::default().fg(color)),
+        ])
+    });
+    frame.render_widget(
+        Table::new(rows, [Constraint::Fill(3), Constraint::Fill(1), Constraint::Fill(1)])
+            .header(header(["Instrument", "Age", "Status"]))
+            .block(Block::default().borders(Borders::ALL).title("Feeds")),
+        feeds,
+    );
+
+    table(
+        frame,
+        bottom,
+        "Recent signals",
+        ["Time", "Strategy", "Instrument", "Signal"],
+        snapshot.signals.iter().map(|s| {
+            vec![
+                s.event_time.time().to_string(),
+                s.strategy_id.to_string(),
+                s.instrument.to_string(),
+                s.signal.to_string(),
+            ]
+        }),
+    );
+}
+
+fn header<const N: usize>(titles: [&'static str; N]) -> Row<'static> {
+    Row::new(titles).style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+fn table<const N: usize>(
+    frame: &mut Frame,
+    area: Rect,
+    title: &'static str,
+    titles: [&'static str; N],
+    rows: impl Iterator<Item = Vec<String>>,
+) {
+    let widths = [Constraint::Fill(1); N];
+    frame.render_widget(
+        Table::new(rows.map(Row::new), widths)
+            .header(header(titles))
+            .block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, models::Fill, state::StateRecorder, test_utils};
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn test_dashboard() {
+        let server = Server::builder().config(&config::load()).build();
+        let mut recorder = StateRecorder::new(server.state().clone(), server.bus());
+        let instrument = test_utils::test_perp_instrument();
+        let start = OffsetDateTime::now_utc() - time::Duration::minutes(5);
+        for event in test_utils::market_events(&instrument, start, 60) {
+            server.bus().publish_event(event);
+        }
+        server.bus().publish(Fill::new(
+            start,
+            instrument.clone(),
+            1,
+            "test".into(),
+            100.0.into(),
+            1.0.into(),
+            0.5.into(),
+        ));
+        recorder.drain();
+
+        let snapshot = Snapshot::take(&server);
+        assert_eq!(snapshot.positions.len(), 1);
+        assert_eq!(snapshot.pnl, vec![("test".to_string(), "-0.5".to_string())]);
+        assert_eq!(snapshot.feeds.len(), 1);
+
+        let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+        terminal.draw(|frame| draw(frame, &snapshot)).unwrap();
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect::<String>();
+        for title in ["Positions", "Realized PnL", "Open orders", "Feeds", "Recent signals", "STALE"] {
+            assert!(screen.contains(title), "{} missing from dashboard", title);
+        }
+    }
+}
@@ -18,6 +18,14 @@ impl CompositeIndex {
         }
     }
 
+    /// Entries at the same time are ordered by the index, e.g. the sequence number of the event
+    pub fn with_index(timestamp: &OffsetDateTime, index: u64) -> Self {
+        CompositeIndex {
+            timestamp: timestamp.to_owned(),
+            index,
+        }
+    }
+
     pub fn new_max(timestamp: &OffsetDateTime) -> Self {
         CompositeIndex {
             timestamp: timestamp.to_owned(),
@@ -25,6 +33,10 @@ impl CompositeIndex {
         }
     }
 
+    pub fn timestamp(&self) -> &OffsetDateTime {
+        &self.timestamp
+    }
+
     pub fn increment(&mut self) {
         self.index += 1;
     }
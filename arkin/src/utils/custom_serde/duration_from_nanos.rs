@@ -16,3 +16,26 @@ where
     let nanos = i64::deserialize(deserializer)?;
     Ok(Duration::nanoseconds(nanos))
 }
+
+/// The same representation for optional durations, `None` is `null`
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::Duration;
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(duration) => super::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(Duration::nanoseconds))
+    }
+}
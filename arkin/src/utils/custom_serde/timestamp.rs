@@ -1,4 +1,9 @@
-use serde::{de, Deserialize, Deserializer, Serializer};
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
 use time::OffsetDateTime;
 
 /// Serialize a `time::OffsetDateTime` to a nanosecond representation.
@@ -15,39 +20,82 @@ pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let input = TimestampInput::deserialize(deserializer)?;
-
-    let timestamp_str = match input {
-        TimestampInput::Int128(i) => i.to_string(),
-        TimestampInput::Int64(i) => i.to_string(),
-        TimestampInput::Str(s) => s,
-    };
-
-    let num_digits = timestamp_str.len();
-    let timestamp_nanos = if num_digits < 19 {
-        let zeros_to_add = 19 - num_digits;
-        let filler = "0".repeat(zeros_to_add);
-        timestamp_str + &filler
-    } else {
-        timestamp_str
-    };
-
-    let timestamp_nanos = timestamp_nanos.parse::<i128>().map_err(de::Error::custom)?;
-    OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos).map_err(de::Error::custom)
+    deserializer.deserialize_any(TimestampVisitor)
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum TimestampInput {
-    Str(String),
-    Int128(i128),
-    Int64(i64),
+/// Timestamps shorter than 19 digits are scaled up to nanoseconds, parsed without allocating
+/// since every market data message carries them
+struct TimestampVisitor;
+
+impl TimestampVisitor {
+    fn from_digits<E: de::Error>(value: i128, digits: usize) -> Result<OffsetDateTime, E> {
+        let nanos = match 19usize.checked_sub(digits) {
+            Some(zeros) => value.checked_mul(10i128.pow(zeros as u32)),
+            None => Some(value),
+        }
+        .ok_or_else(|| E::custom("timestamp out of range"))?;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(E::custom)
+    }
+}
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = OffsetDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a timestamp in seconds, milliseconds, microseconds or nanoseconds")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        self.visit_i128(value.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        self.visit_i128(value.into())
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Self::Value, E> {
+        let digits = value.unsigned_abs().checked_ilog10().map_or(1, |d| d as usize + 1) + usize::from(value < 0);
+        Self::from_digits(value, digits)
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Self::Value, E> {
+        self.visit_i128(i128::try_from(value).map_err(E::custom)?)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Self::from_digits(value.parse().map_err(E::custom)?, value.len())
+    }
+}
+
+/// The same representation for optional timestamps, `None` is `null`
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::OffsetDateTime;
+
+    pub fn serialize<S>(datetime: &Option<OffsetDateTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match datetime {
+            Some(datetime) => super::serialize(datetime, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Timestamp(#[serde(with = "super")] OffsetDateTime);
+
+        Ok(Option::<Timestamp>::deserialize(deserializer)?.map(|t| t.0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize};
     use serde_json;
     use time::OffsetDateTime;
     use tokio;
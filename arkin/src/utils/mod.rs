@@ -1,10 +1,12 @@
 mod composit_key;
 pub mod custom_serde;
 mod deduplicator;
+mod rng;
 mod tick_helper;
 mod time_helper;
 
 pub use composit_key::*;
 pub use deduplicator::*;
+pub use rng::*;
 pub use tick_helper::*;
 pub use time_helper::*;
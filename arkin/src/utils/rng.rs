@@ -0,0 +1,39 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+/// FNV-1a hash, stable across runs and platforms unlike the hasher of the standard library
+pub fn fnv1a(data: &str) -> u64 {
+    data.bytes()
+        .fold(0xcbf29ce484222325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Derive an independent seed per component from the run seed, so adding randomness in one place
+/// doesn't shift the random numbers drawn everywhere else
+pub fn derive_seed(seed: u64, component: &str) -> u64 {
+    // FNV-1a of the component name mixed into the seed with splitmix64
+    let mut z = seed ^ fnv1a(component);
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+pub fn seeded_rng(seed: u64, component: &str) -> StdRng {
+    StdRng::seed_from_u64(derive_seed(seed, component))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_derive_seed() {
+        assert_eq!(derive_seed(42, "simulation"), derive_seed(42, "simulation"));
+        assert_ne!(derive_seed(42, "simulation"), derive_seed(42, "synthetic"));
+        assert_ne!(derive_seed(42, "simulation"), derive_seed(43, "simulation"));
+
+        let a = seeded_rng(42, "simulation").gen::<u64>();
+        let b = seeded_rng(42, "simulation").gen::<u64>();
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,355 @@
+use std::{collections::HashSet, future::pending, sync::Arc};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tokio::{net::TcpListener, select};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    api::{format_time, FillResponse},
+    bus::{BusMessage, Subscription},
+    models::{Fill, Signal, Tick},
+    portfolio::PnlTracker,
+    server::Server,
+};
+
+/// Streams live events to ui clients, every connection picks the topics it wants.
+///
+/// Clients send `{"action": "subscribe", "topics": ["ticks", "pnl"]}` or `unsubscribe` and receive
+/// `{"topic": "ticks", "data": {..}}` messages. A slow client skips messages instead of holding up the bus.
+pub fn router(server: Arc<Server>) -> Router {
+    Router::new().route("/ws", get(upgrade)).with_state(server)
+}
+
+/// Serve the websocket until the task is dropped
+pub async fn serve(address: &str, server: Arc<Server>) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind websocket to {}: {}", address, e);
+            return;
+        }
+    };
+    info!("Serving websocket on ws://{}/ws", address);
+    if let Err(e) = axum::serve(listener, router(server)).await {
+        error!("Websocket failed: {}", e);
+    }
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum StreamTopic {
+    Ticks,
+    Signals,
+    Fills,
+    Pnl,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe { topics: Vec<StreamTopic> },
+    Unsubscribe { topics: Vec<StreamTopic> },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "topic", content = "data", rename_all = "snake_case")]
+pub enum PushMessage {
+    /// Confirms the topics the connection is subscribed to after every request
+    Subscribed(Vec<StreamTopic>),
+    Ticks(TickUpdate),
+    Signals(SignalUpdate),
+    Fills(FillResponse),
+    Pnl(PnlUpdate),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TickUpdate {
+    pub event_time: String,
+    pub instrument: String,
+    pub bid_price: String,
+    pub bid_quantity: String,
+    pub ask_price: String,
+    pub ask_quantity: String,
+}
+
+impl From<&Tick> for TickUpdate {
+    fn from(tick: &Tick) -> Self {
+        TickUpdate {
+            event_time: format_time(&tick.event_time),
+            instrument: tick.instrument.to_string(),
+            bid_price: tick.bid_price.to_string(),
+            bid_quantity: tick.bid_quantity.to_string(),
+            ask_price: tick.ask_price.to_string(),
+            ask_quantity: tick.ask_quantity.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignalUpdate {
+    pub event_time: String,
+    pub instrument: String,
+    pub strategy_id: String,
+    pub signal: String,
+}
+
+impl From<&Signal> for SignalUpdate {
+    fn from(signal: &Signal) -> Self {
+        SignalUpdate {
+            event_time: format_time(&signal.event_time),
+            instrument: signal.instrument.to_string(),
+            strategy_id: signal.strategy_id.to_string(),
+            signal: signal.signal.to_string(),
+        }
+    }
+}
+
+/// Realized pnl net of commission of a strategy after one of its fills
+#[derive(Serialize, Deserialize)]
+pub struct PnlUpdate {
+    pub event_time: String,
+    pub strategy_id: String,
+    pub realized_pnl: String,
+}
+
+async fn upgrade(State(server): State<Arc<Server>>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| Session::new(server, socket).run())
+}
+
+struct Session {
+    server: Arc<Server>,
+    socket: WebSocket,
+    topics: HashSet<StreamTopic>,
+    ticks: Option<Subscription<Tick>>,
+    signals: Option<Subscription<Signal>>,
+    fills: Option<Subscription<Fill>>,
+    pnl: Option<Pnl>,
+}
+
+/// Pnl of the session, seeded with the fills that happened before it subscribed
+struct Pnl {
+    tracker: PnlTracker,
+    last_order_id: u64,
+}
+
+impl Session {
+    fn new(server: Arc<Server>, socket: WebSocket) -> Self {
+        Session {
+            server,
+            socket,
+            topics: HashSet::new(),
+            ticks: None,
+            signals: None,
+            fills: None,
+            pnl: None,
+        }
+    }
+
+    async fn run(mut self) {
+        debug!("Websocket client connected");
+        loop {
+            let result = select! {
+                message = self.socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle(&text).await,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => Ok(()),
+                    Some(Err(e)) => Err(e),
+                },
+                Some(tick) = next(&mut self.ticks) => self.send(PushMessage::Ticks(TickUpdate::from(&tick))).await,
+                Some(signal) = next(&mut self.signals) => {
+                    self.send(PushMessage::Signals(SignalUpdate::from(&signal))).await
+                }
+                Some(fill) = next(&mut self.fills) => self.fill(&fill).await,
+            };
+            if let Err(e) = result {
+                warn!("Websocket client dropped: {}", e);
+                break;
+            }
+        }
+        debug!("Websocket client disconnected");
+    }
+
+    async fn handle(&mut self, text: &str) -> Result<(), axum::Error> {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(e) => return self.send(PushMessage::Error(format!("Invalid request: {}", e))).await,
+        };
+        match message {
+            ClientMessage::Subscribe { topics } => self.topics.extend(topics),
+            ClientMessage::Unsubscribe { topics } => self.topics.retain(|t| !topics.contains(t)),
+        }
+
+        // Only listen on the bus for what the client wants
+        let bus = self.server.bus();
+        let wants = |topic| self.topics.contains(&topic);
+        self.ticks = toggle(self.ticks.take(), wants(StreamTopic::Ticks), || bus.subscribe());
+        self.signals = toggle(self.signals.take(), wants(StreamTopic::Signals), || bus.subscribe());
+        let wants_fills = wants(StreamTopic::Fills) || wants(StreamTopic::Pnl);
+        self.fills = toggle(self.fills.take(), wants_fills, || bus.subscribe());
+        if !wants(StreamTopic::Pnl) {
+            self.pnl = None;
+        } else if self.pnl.is_none() {
+            // Fills that are already recorded are skipped when they also come over the bus
+            let mut pnl = Pnl {
+                tracker: PnlTracker::default(),
+                last_order_id: 0,
+            };
+            for fill in self.server.fills() {
+                pnl.tracker.update(&fill);
+                pnl.last_order_id = pnl.last_order_id.max(fill.order_id);
+            }
+            self.pnl = Some(pnl);
+        }
+
+        let mut topics = self.topics.iter().copied().collect::<Vec<_>>();
+        topics.sort_by_key(|t| t.to_string());
+        self.send(PushMessage::Subscribed(topics)).await
+    }
+
+    async fn fill(&mut self, fill: &Fill) -> Result<(), axum::Error> {
+        if self.topics.contains(&StreamTopic::Fills) {
+            self.send(PushMessage::Fills(FillResponse::from(fill))).await?;
+        }
+        let Some(pnl) = self.pnl.as_mut() else {
+            return Ok(());
+        };
+        if fill.order_id <= pnl.last_order_id {
+            return Ok(());
+        }
+        pnl.tracker.update(fill);
+        pnl.last_order_id = fill.order_id;
+        let update = PnlUpdate {
+            event_time: format_time(&fill.event_time),
+            strategy_id: fill.strategy_id.to_string(),
+            realized_pnl: pnl.tracker.realized(&fill.strategy_id).to_string(),
+        };
+        self.send(PushMessage::Pnl(update)).await
+    }
+
+    async fn send(&mut self, message: PushMessage) -> Result<(), axum::Error> {
+        let text = serde_json::to_string(&message).expect("Push messages always serialize");
+        self.socket.send(Message::Text(text)).await
+    }
+}
+
+fn toggle<T: BusMessage>(
+    current: Option<Subscription<T>>,
+    wanted: bool,
+    subscribe: impl FnOnce() -> Subscription<T>,
+) -> Option<Subscription<T>> {
+    match (current, wanted) {
+        (Some(subscription), true) => Some(subscription),
+        (None, true) => Some(subscribe()),
+        (_, false) => None,
+    }
+}
+
+/// Next message of an optional subscription, never resolves without one
+async fn next<T: BusMessage>(subscription: &mut Option<Subscription<T>>) -> Option<T> {
+    match subscription {
+        Some(subscription) => subscription.recv().await,
+        None => pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, state::StateRecorder, test_utils};
+    use async_tungstenite::{
+        tokio::{connect_async, ConnectStream},
+        tungstenite, WebSocketStream,
+    };
+    use futures_util::{SinkExt, StreamExt};
+
+    async fn request(client: &mut WebSocketStream<ConnectStream>, request: &str) {
+        client.send(tungstenite::Message::Text(request.into())).await.unwrap();
+    }
+
+    async fn next(client: &mut WebSocketStream<ConnectStream>) -> PushMessage {
+        match client.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_server() {
+        let server = Arc::new(Server::builder().config(&config::load()).build());
+        let mut recorder = StateRecorder::new(server.state().clone(), server.bus());
+        let instrument = test_utils::test_perp_instrument();
+        let event_time = time::OffsetDateTime::now_utc() - time::Duration::seconds(10);
+        let fill = |order_id, price: f64, quantity: f64| {
+            Fill::new(
+                event_time,
+                instrument.clone(),
+                order_id,
+                "test".into(),
+                price.into(),
+                quantity.into(),
+                1.0.into(),
+            )
+        };
+
+        // Happened before the client connected so it only counts towards the pnl
+        server.bus().publish(fill(1, 100., 1.));
+        recorder.drain();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = router(server.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let (mut client, _) = connect_async(format!("ws://{}/ws", address)).await.unwrap();
+
+        request(&mut client, r#"{"action": "subscribe", "topics": ["pnl", "fills"]}"#).await;
+        match next(&mut client).await {
+            PushMessage::Subscribed(topics) => assert_eq!(topics, vec![StreamTopic::Fills, StreamTopic::Pnl]),
+            _ => panic!("Expected subscription confirmation"),
+        }
+
+        server.bus().publish(fill(2, 110., -1.));
+        match next(&mut client).await {
+            PushMessage::Fills(fill) => assert_eq!(fill.order_id, 2),
+            _ => panic!("Expected fill"),
+        }
+        match next(&mut client).await {
+            // 10 profit minus the commission of both fills
+            PushMessage::Pnl(pnl) => assert_eq!(pnl.realized_pnl, "8"),
+            _ => panic!("Expected pnl update"),
+        }
+
+        request(&mut client, r#"{"action": "unsubscribe", "topics": ["fills", "pnl"]}"#).await;
+        request(&mut client, r#"{"action": "subscribe", "topics": ["ticks"]}"#).await;
+        request(&mut client, "not json").await;
+        assert!(matches!(next(&mut client).await, PushMessage::Subscribed(topics) if topics.is_empty()));
+        assert!(
+            matches!(next(&mut client).await, PushMessage::Subscribed(topics) if topics == vec![StreamTopic::Ticks])
+        );
+        assert!(matches!(next(&mut client).await, PushMessage::Error(_)));
+
+        // Nothing is sent for topics the client left
+        server.bus().publish(fill(3, 100., 1.));
+        server.bus().publish(Tick::new(
+            event_time,
+            instrument.clone(),
+            1,
+            100.0.into(),
+            1.0.into(),
+            101.0.into(),
+            1.0.into(),
+        ));
+        assert!(matches!(next(&mut client).await, PushMessage::Ticks(_)));
+    }
+}